@@ -0,0 +1,109 @@
+//! Fansub anime release names.
+//!
+//! Fansub groups name episodes after the series, not the season:
+//! `[SubsPlease] One Piece - 1071 (1080p) [ABCD1234].mkv` is the 1071st
+//! episode, `05v2` a fixed re-release of episode 5, and the bracketed hex
+//! the file's CRC32. hunch finds each of these; this module keeps them as
+//! the file's [`AnimeRelease`] rather than dropping them. Movies often give
+//! the English title after the romaji one, `[Judas] Kimi no Na wa. (Your
+//! Name) [BD 1080p].mkv`, which becomes the title's English variant.
+//!
+//! Which entry the name means is settled on AniList (see
+//! `providers::anilist`), whose romaji and synonym titles match fansub
+//! names far better than a TMDb search does.
+
+use std::sync::LazyLock;
+
+use hunch::HunchResult;
+use regex::Regex;
+
+use crate::models::AnimeRelease;
+use crate::profile;
+
+/// The title after a leading `[Group]`, and the parentheses after it.
+static ALTERNATIVE_TITLE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*(?:\[[^\]]*\]\s*)?[^\[(]+?\s*\((?P<alt>[^()]+)\)").unwrap());
+
+/// `05v2` revision of an episode.
+static VERSION_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b\d{1,4}v(?P<version>\d)\b").unwrap());
+
+/// The fansub details of `filename`, when it looks like a fansub release
+/// (see [`profile::looks_like_anime`]); `result` is hunch's parse of it.
+/// An episode without a season is an absolute one.
+pub fn release(filename: &str, result: &HunchResult) -> Option<AnimeRelease> {
+    if !profile::looks_like_anime(filename) {
+        return None;
+    }
+    Some(AnimeRelease {
+        absolute_episode: result.episode().filter(|_| result.season().is_none()),
+        version: VERSION_RE
+            .captures(filename)
+            .and_then(|c| c["version"].parse().ok()),
+        crc32: profile::CRC_RE
+            .captures(filename)
+            .map(|c| c["crc"].to_uppercase()),
+        anilist_id: None,
+    })
+}
+
+/// The title a fansub name gives in parentheses after the first one:
+/// `Kimi no Na wa. (Your Name)` → `Your Name`. Parentheses holding release
+/// details (`(1080p)`, `(BD Dual Audio)`) or a year are no title.
+pub fn alternative_title(filename: &str) -> Option<String> {
+    let name = filename.replace('_', " ");
+    let alt = ALTERNATIVE_TITLE_RE.captures(&name)?["alt"]
+        .trim()
+        .to_string();
+    if alt.chars().any(|c| c.is_ascii_digit()) || !alt.chars().any(char::is_alphabetic) {
+        return None;
+    }
+    // hunch takes plain words whole as the title; release tags it claims.
+    let plain = hunch::hunch(&alt).title() == Some(alt.as_str());
+    plain.then_some(alt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fansub_release_details() {
+        let name = "[SubsPlease] One Piece - 1071 (1080p) [abcd1234].mkv";
+        let release = release(name, &hunch::hunch(name)).unwrap();
+        assert_eq!(release.absolute_episode, Some(1071));
+        assert_eq!(release.crc32.as_deref(), Some("ABCD1234"));
+
+        let name = "[Erai-raws] Kimetsu no Yaiba - 05v2 [1080p][0A1B2C3D].mkv";
+        let release = super::release(name, &hunch::hunch(name)).unwrap();
+        assert_eq!(
+            (release.absolute_episode, release.version),
+            (Some(5), Some(2))
+        );
+
+        let name = "Heat.1995.1080p.BluRay.x264-GRP.mkv";
+        assert!(super::release(name, &hunch::hunch(name)).is_none());
+    }
+
+    #[test]
+    fn test_alternative_title() {
+        assert_eq!(
+            alternative_title("[Judas] Kimi no Na wa. (Your Name) [BD 1080p][HEVC].mkv").as_deref(),
+            Some("Your Name")
+        );
+        assert_eq!(
+            alternative_title(
+                "[Coalgirls]_Toki_wo_Kakeru_Shoujo_(1920x1080_Blu-ray_FLAC)_[CE7E1C3F].mkv"
+            ),
+            None
+        );
+        assert_eq!(
+            alternative_title("[Group] Akira (BD Dual Audio) [ABCD1234].mkv"),
+            None
+        );
+        assert_eq!(
+            alternative_title("[Group] Akira (1988) [ABCD1234].mkv"),
+            None
+        );
+    }
+}
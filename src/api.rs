@@ -0,0 +1,381 @@
+//! High-level entry point for driving the pipeline from other programs.
+//!
+//! [`Organizer`] ties the scanner, parser, enricher, planner, and executor
+//! together the way the `plan` and `organize` commands do, without their
+//! terminal output: scan a source, plan it into the configured destination,
+//! review the [`OrganizePlan`], and apply it. Pins, corrections, rejected
+//! review items, and learned title tokens in the library database are
+//! honored, and applied runs are recorded there for `undo`.
+//!
+//! ```rust,no_run
+//! use plex_media_organizer::config::AppConfig;
+//! use plex_media_organizer::Organizer;
+//! use std::path::Path;
+//!
+//! let config = AppConfig {
+//!     destination: "/plex".to_string(),
+//!     ..Default::default()
+//! };
+//! let organizer = Organizer::new(config, "/var/lib/plex-organizer");
+//! let plan = organizer.plan(Path::new("/downloads"))?;
+//! for (source, media) in &plan.held {
+//!     println!("needs review: {} ({:.0}%)", source.display(), media.confidence);
+//! }
+//! let manifest = organizer.apply(&plan)?;
+//! println!("{} files organized", manifest.entries.len());
+//! # Ok::<(), plex_media_organizer::Error>(())
+//! ```
+//!
+//! [`Organizer::with_progress`] reports each stage as a [`ProgressEvent`],
+//! and [`Organizer::with_cancel`] lets another thread stop the work (see
+//! [`progress`](crate::progress)).
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+
+use anyhow::anyhow;
+use tracing::{info, warn};
+
+use crate::config::AppConfig;
+use crate::database::cache::{self, ResponseCache};
+use crate::database::Database;
+use crate::enricher::Enricher;
+use crate::error::{Error, Result};
+use crate::identify;
+pub use crate::identify::Item;
+use crate::models::{MediaFile, SavedPlan, UndoManifest};
+use crate::organizer::{
+    self, AlreadyHad, DestinationCheck, DestinationPolicy, ExecuteOptions, Nesting,
+};
+use crate::progress::{self, CancelToken, ProgressEvent};
+use crate::scanner::{self, ScanOptions};
+
+/// Planned actions for a source, and the files held back from them.
+#[derive(Debug, Clone)]
+pub struct OrganizePlan {
+    /// Actions for the files ready to organize; save it with
+    /// [`organizer::save_plan`] to apply later.
+    pub plan: SavedPlan,
+    /// Files below the review threshold, which were not planned.
+    pub held: Vec<Item>,
+    /// With `organize.upgrade`, movies the library already has at least
+    /// as good, which were not planned.
+    pub already_had: Vec<AlreadyHad>,
+}
+
+/// Runs scan → parse → enrich → plan → execute with one configuration.
+pub struct Organizer {
+    config: AppConfig,
+    enricher: Enricher,
+    data_dir: PathBuf,
+    events: Option<Sender<ProgressEvent>>,
+    cancel: Option<CancelToken>,
+}
+
+impl Organizer {
+    /// Create an organizer keeping its library database, undo manifests,
+    /// and TMDb cache under `data_dir` (the CLI uses `~/.plex-organizer`).
+    ///
+    /// The TMDb cache is installed for the rest of the process, as the CLI
+    /// does at start.
+    pub fn new(config: AppConfig, data_dir: impl Into<PathBuf>) -> Self {
+        let data_dir = data_dir.into();
+        if config.tmdb.cache_ttl_hours > 0 {
+            match ResponseCache::open(&data_dir.join("tmdb_cache.json"), &config.tmdb) {
+                Ok(opened) => cache::install(opened),
+                Err(e) => warn!("Not using the TMDb cache: {e:#}"),
+            }
+        }
+        Self {
+            enricher: Enricher::new(config.clone()),
+            config,
+            data_dir,
+            events: None,
+            cancel: None,
+        }
+    }
+
+    /// Send progress events of every scan, plan, and run to `events`.
+    pub fn with_progress(mut self, events: Sender<ProgressEvent>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Stop work once `token` is cancelled: [`identify`](Self::identify)
+    /// and [`plan`](Self::plan) fail with [`Error::Cancelled`] before the
+    /// next file, and [`apply`](Self::apply) starts no new file operation,
+    /// returning the manifest of those done.
+    pub fn with_cancel(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    pub fn config(&self) -> &AppConfig {
+        &self.config
+    }
+
+    /// Path of the library database.
+    pub fn database_path(&self) -> PathBuf {
+        self.data_dir.join("library.json")
+    }
+
+    /// Directory of the undo manifests.
+    pub fn undo_dir(&self) -> PathBuf {
+        self.data_dir.join("undo")
+    }
+
+    /// Find the media files under `path`.
+    pub fn scan(&self, path: &Path) -> Result<Vec<MediaFile>> {
+        self.scan_into(path, None)
+    }
+
+    /// Parse and enrich `files`, as `plan` does before planning (see
+    /// [`identify`](crate::identify)). Title tokens learned from TMDb
+    /// matches are saved.
+    pub fn identify(&self, files: &[MediaFile]) -> Result<Vec<Item>> {
+        let mut db = Database::open(&self.database_path())?;
+        let identified = identify::identify(
+            files.to_vec(),
+            &mut db,
+            &self.enricher,
+            &self.config,
+            &|| progress::cancelled(self.cancel.as_ref()),
+            &mut |event| self.emit(event),
+        );
+        if let Err(e) = cache::finish() {
+            warn!("Could not save the TMDb cache: {e:#}");
+        }
+        self.check_cancelled()?;
+        if identified.learned > 0 {
+            info!(
+                "learned {} title-safe tokens from TMDb matches",
+                identified.learned
+            );
+            db.save()?;
+        }
+        Ok(identified.items)
+    }
+
+    /// Scan `path` and plan it into the configured destination. Nothing
+    /// is moved until the plan is applied.
+    pub fn plan(&self, path: &Path) -> Result<OrganizePlan> {
+        let dest = self.destination()?;
+        let files = self.scan_into(path, Some(&dest))?;
+        let items = self.identify(&files)?;
+        let (held, ready): (Vec<_>, Vec<_>) = items.into_iter().partition(|(_, e)| e.needs_review);
+        let strategy = &self.config.organize.strategy;
+        let (actions, already_had) = if self.config.organize.upgrade {
+            let db = Database::open(&self.database_path())?;
+            let known = |path: &Path| {
+                db.find_by_destination(&path.to_string_lossy())
+                    .map(|r| r.quality.clone())
+            };
+            organizer::plan_upgrades(&ready, &dest, &self.config, strategy, &known)
+        } else {
+            let actions = organizer::plan_actions(&ready, &dest, &self.config, strategy);
+            (actions, Vec::new())
+        };
+        Ok(OrganizePlan {
+            plan: SavedPlan::new(dest, actions),
+            held,
+            already_had,
+        })
+    }
+
+    /// Carry out a plan and record the run in the library database, so
+    /// `undo` can reverse it. Returns the run's undo manifest.
+    pub fn apply(&self, plan: &OrganizePlan) -> Result<UndoManifest> {
+        self.apply_saved(&plan.plan)
+    }
+
+    /// Carry out a plan saved with [`organizer::save_plan`]. As with
+    /// `organize --apply-plan`, actions whose source changed since planning
+    /// or whose destination is now taken are skipped (see
+    /// [`organizer::validate_plan`]) and logged.
+    pub fn apply_saved(&self, plan: &SavedPlan) -> Result<UndoManifest> {
+        self.check_destination(&plan.dest_root)?;
+        let (actions, drifted) = organizer::validate_plan(&plan.actions);
+        for d in &drifted {
+            warn!("Skipping {}: {}", d.action.source.display(), d.reason);
+        }
+        let dest = Some(&plan.dest_root).filter(|_| self.config.organize.journals(actions.len()));
+        let options = ExecuteOptions {
+            journal: dest.cloned(),
+            cancel: self.cancel.clone(),
+            progress: self.events.clone(),
+            ..ExecuteOptions::from_config(&self.config)
+        };
+        let manifest =
+            organizer::execute_actions(&actions, &self.undo_dir(), &options).map_err(Error::Io)?;
+        if !manifest.entries.is_empty() {
+            let mut db = Database::open(&self.database_path())?;
+            db.record_run(&manifest);
+            db.save()?;
+        }
+        Ok(manifest)
+    }
+
+    fn emit(&self, event: ProgressEvent) {
+        progress::emit(self.events.as_ref(), event);
+    }
+
+    fn check_cancelled(&self) -> Result<()> {
+        if progress::cancelled(self.cancel.as_ref()) {
+            return Err(Error::Cancelled);
+        }
+        Ok(())
+    }
+
+    /// The configured destination, once it is known to be safe.
+    fn destination(&self) -> Result<PathBuf> {
+        if self.config.destination.is_empty() {
+            return Err(Error::Config(anyhow!("No destination is configured")));
+        }
+        let dest = PathBuf::from(&self.config.destination);
+        self.check_destination(&dest)?;
+        Ok(dest)
+    }
+
+    fn check_destination(&self, dest: &Path) -> Result<()> {
+        let policy = DestinationPolicy::from_config(&self.config, &self.data_dir);
+        match organizer::check_destination(dest, &policy) {
+            DestinationCheck::Protected(path) => Err(Error::Config(anyhow!(
+                "Refusing to organize into {}: {} is a protected location",
+                dest.display(),
+                path.display()
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Scan `path`, leaving out a destination `dest` inside it; a source
+    /// inside `dest` is refused.
+    fn scan_into(&self, path: &Path, dest: Option<&Path>) -> Result<Vec<MediaFile>> {
+        let mut options = ScanOptions::from_config(&self.config);
+        if let Some(dest) = dest {
+            match organizer::check_nesting(path, dest) {
+                Nesting::Separate => {}
+                Nesting::DestInsideSource => options.exclude_dirs.push(dest.to_path_buf()),
+                Nesting::SourceInsideDest => {
+                    return Err(Error::Config(anyhow!(
+                        "Source {} is inside the destination {}",
+                        path.display(),
+                        dest.display()
+                    )))
+                }
+            }
+        }
+        self.check_cancelled()?;
+        self.emit(ProgressEvent::Scanning {
+            root: path.to_path_buf(),
+        });
+        Ok(scanner::scan_directory(path, &options)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_plan_and_apply() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = tmp.path().join("downloads");
+        let dest = tmp.path().join("plex");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(
+            source.join("The.Matrix.1999.1080p.BluRay.x264-GROUP.mkv"),
+            b"x",
+        )
+        .unwrap();
+
+        let mut config = AppConfig {
+            destination: dest.to_string_lossy().into_owned(),
+            review_threshold: 0.0,
+            ..Default::default()
+        };
+        config.scan.min_video_size_mb = 0;
+        let organizer = Organizer::new(config, tmp.path().join("data"));
+
+        let plan = organizer.plan(&source).unwrap();
+        assert!(plan.held.is_empty());
+        assert_eq!(plan.plan.actions.len(), 1);
+        let expected = dest.join("Movies/The Matrix (1999)/The Matrix (1999).mkv");
+        assert_eq!(plan.plan.actions[0].destination, expected);
+
+        let manifest = organizer.apply(&plan).unwrap();
+        assert_eq!(manifest.entries.len(), 1);
+        assert!(expected.exists());
+        assert!(organizer.database_path().exists());
+
+        let inside = Organizer::new(
+            AppConfig {
+                destination: source.to_string_lossy().into_owned(),
+                ..Default::default()
+            },
+            tmp.path().join("data"),
+        );
+        assert!(matches!(
+            inside.plan(&source.join("sub")),
+            Err(Error::Config(_))
+        ));
+    }
+
+    #[test]
+    fn test_progress_events_and_cancellation() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = tmp.path().join("downloads");
+        fs::create_dir_all(&source).unwrap();
+        for name in ["Heat.1995.1080p.mkv", "Alien.1979.1080p.mkv"] {
+            fs::write(source.join(name), b"x").unwrap();
+        }
+        let mut config = AppConfig {
+            destination: tmp.path().join("plex").to_string_lossy().into_owned(),
+            review_threshold: 0.0,
+            ..Default::default()
+        };
+        config.scan.min_video_size_mb = 0;
+        let (events, received) = std::sync::mpsc::channel();
+        let token = CancelToken::new();
+        let organizer = Organizer::new(config, tmp.path().join("data"))
+            .with_progress(events)
+            .with_cancel(token.clone());
+
+        let plan = organizer.plan(&source).unwrap();
+        let seen: Vec<_> = received.try_iter().collect();
+        assert_eq!(
+            seen[0],
+            ProgressEvent::Scanning {
+                root: source.clone()
+            }
+        );
+        assert!(matches!(
+            seen[1],
+            ProgressEvent::Parsing {
+                done: 1,
+                total: 2,
+                ..
+            }
+        ));
+        assert!(matches!(
+            seen.last(),
+            Some(ProgressEvent::Matching { done: 2, .. })
+        ));
+
+        // Cancelled between planning and applying: nothing is started.
+        token.cancel();
+        let manifest = organizer.apply(&plan).unwrap();
+        assert!(manifest.entries.is_empty());
+        assert_eq!(manifest.stats.unwrap().unstarted, 2);
+        assert_eq!(
+            received.try_iter().collect::<Vec<_>>(),
+            [ProgressEvent::Done {
+                placed: 0,
+                unstarted: 2,
+                cancelled: true,
+            }]
+        );
+        assert!(matches!(organizer.plan(&source), Err(Error::Cancelled)));
+    }
+}
@@ -0,0 +1,170 @@
+//! Artwork companions of already-curated movie folders.
+//!
+//! A folder someone has curated for Plex, Kodi, or Jellyfin often holds
+//! local assets next to the movie: `poster.jpg`, `fanart.jpg`, `theme.mp3`,
+//! or `<movie>-poster.jpg`. These move with the movie and are renamed to
+//! the names Plex reads (`cover.jpg` → `poster.jpg`, `backdrop.jpg` →
+//! `fanart.jpg`). Folder-wide names only count when the video is the only
+//! one in its folder; in a shared downloads folder they belong to nobody.
+
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::scanner::{self, VIDEO_EXTENSIONS};
+
+/// Image extensions that can hold artwork.
+pub const IMAGE_EXTENSIONS: &[&str] = &[".jpg", ".jpeg", ".png", ".tbn", ".webp"];
+
+/// Asset names and the role each is saved as.
+const ROLES: &[(&str, &str)] = &[
+    ("poster", "poster"),
+    ("cover", "poster"),
+    ("folder", "poster"),
+    ("default", "poster"),
+    ("movie", "poster"),
+    ("fanart", "fanart"),
+    ("art", "fanart"),
+    ("background", "fanart"),
+    ("backdrop", "fanart"),
+    ("banner", "banner"),
+    ("clearlogo", "clearlogo"),
+    ("logo", "clearlogo"),
+    ("clearart", "clearart"),
+    ("disc", "discart"),
+    ("discart", "discart"),
+    ("landscape", "landscape"),
+    ("thumb", "landscape"),
+];
+
+/// An artwork file that belongs with a video.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArtworkCompanion {
+    pub path: PathBuf,
+    /// File name in the new folder, e.g. `poster.jpg` or
+    /// `Heat (1995)-fanart.jpg`.
+    pub name: String,
+}
+
+/// The role an asset base name (lowercase) is saved as, given its
+/// extension: images by [`ROLES`], `theme` only as audio.
+fn role(base: &str, ext: &str) -> Option<&'static str> {
+    if ext == ".mp3" {
+        return (base == "theme").then_some("theme");
+    }
+    if !IMAGE_EXTENSIONS.contains(&ext) {
+        return None;
+    }
+    ROLES
+        .iter()
+        .find(|(name, _)| *name == base)
+        .map(|(_, r)| *r)
+}
+
+/// Find artwork next to `video`, named for a video whose new stem is
+/// `new_stem`.
+///
+/// Names already in their saved form come first, so `poster.jpg` wins over
+/// `cover.jpg` when a folder has both.
+pub fn find_artwork(video: &Path, new_stem: &str) -> Vec<ArtworkCompanion> {
+    let (Some(stem), Some(parent)) = (video.file_stem().and_then(OsStr::to_str), video.parent())
+    else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(parent) else {
+        return Vec::new();
+    };
+    let files: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file())
+        .collect();
+    let dedicated = files
+        .iter()
+        .filter(|p| VIDEO_EXTENSIONS.contains(&scanner::dotted_extension(p).as_str()))
+        .count()
+        == 1;
+
+    let mut found: Vec<(bool, ArtworkCompanion)> = files
+        .into_iter()
+        .filter_map(|path| {
+            let ext = scanner::dotted_extension(&path);
+            let base = path.file_stem()?.to_str()?;
+            let (role, renamed) = match base.strip_prefix(stem).and_then(|b| b.strip_prefix('-')) {
+                Some(suffix) => {
+                    let role = role(&suffix.to_lowercase(), &ext)?;
+                    (role, format!("{new_stem}-{role}{ext}"))
+                }
+                None if dedicated => {
+                    let role = role(&base.to_lowercase(), &ext)?;
+                    (role, format!("{role}{ext}"))
+                }
+                None => return None,
+            };
+            let canonical = base.eq_ignore_ascii_case(role) || base.ends_with(&format!("-{role}"));
+            Some((
+                canonical,
+                ArtworkCompanion {
+                    path,
+                    name: renamed,
+                },
+            ))
+        })
+        .collect();
+    found.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.path.cmp(&b.1.path)));
+    found.into_iter().map(|(_, art)| art).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(found: &[ArtworkCompanion]) -> Vec<&str> {
+        found.iter().map(|a| a.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_find_artwork() {
+        let tmp = tempfile::tempdir().unwrap();
+        let curated = tmp.path().join("heat");
+        fs::create_dir_all(&curated).unwrap();
+        for name in [
+            "Heat.1995.mkv",
+            "cover.jpg",
+            "poster.jpg",
+            "Backdrop.JPG",
+            "theme.mp3",
+            "Heat.1995-banner.png",
+            "notes.txt",
+            "theme.jpg",
+        ] {
+            fs::write(curated.join(name), b"x").unwrap();
+        }
+        let found = find_artwork(&curated.join("Heat.1995.mkv"), "Heat (1995)");
+        assert_eq!(
+            names(&found),
+            [
+                "Heat (1995)-banner.png",
+                "poster.jpg",
+                "theme.mp3",
+                "fanart.jpg",
+                "poster.jpg"
+            ]
+        );
+        assert_eq!(found[1].path, curated.join("poster.jpg"));
+
+        // A shared folder: only artwork named after the video belongs to it.
+        let shared = tmp.path().join("downloads");
+        fs::create_dir_all(&shared).unwrap();
+        for name in [
+            "Heat.1995.mkv",
+            "Alien.1979.mkv",
+            "poster.jpg",
+            "Alien.1979-fanart.jpg",
+        ] {
+            fs::write(shared.join(name), b"x").unwrap();
+        }
+        let found = find_artwork(&shared.join("Alien.1979.mkv"), "Alien (1979)");
+        assert_eq!(names(&found), ["Alien (1979)-fanart.jpg"]);
+    }
+}
@@ -0,0 +1,195 @@
+//! Backfill provider details for movies already in the library.
+//!
+//! Libraries organized before details were collected (or imported from
+//! elsewhere) have only title, year, and paths. `plex-org enrich` walks the
+//! movie records in batches, resolves each to a TMDb id (by search when the
+//! id was never recorded), and stores genres, runtime, collection,
+//! certification, and alternative titles. The database is saved after every
+//! batch so an interrupted run keeps its progress.
+
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::config::TmdbSettings;
+use crate::database::{Database, FileRecord};
+use crate::models::MediaType;
+use crate::providers::tmdb::TmdbClient;
+use crate::providers::{self, MovieDetails};
+use crate::scanner;
+
+/// Knobs for one backfill run.
+#[derive(Debug, Clone)]
+pub struct BackfillOptions {
+    /// Re-fetch records that already have details.
+    pub refresh: bool,
+    /// Stop after this many records.
+    pub limit: Option<usize>,
+    /// Records per batch; the database is saved after each.
+    pub batch_size: usize,
+}
+
+impl Default for BackfillOptions {
+    fn default() -> Self {
+        Self {
+            refresh: false,
+            limit: None,
+            batch_size: 25,
+        }
+    }
+}
+
+/// Outcome of a backfill run, by library path.
+#[derive(Debug, Default)]
+pub struct BackfillReport {
+    pub enriched: Vec<String>,
+    /// No confident provider match.
+    pub not_found: Vec<String>,
+    pub failed: Vec<(String, String)>,
+}
+
+/// Movie records (video files only) that still need details.
+pub fn pending_records(db: &Database, refresh: bool) -> Vec<&FileRecord> {
+    db.files()
+        .iter()
+        .filter(|r| r.media_type == MediaType::Movie.to_string())
+        .filter(|r| scanner::is_media_path(Path::new(&r.destination)))
+        .filter(|r| refresh || r.details.is_none())
+        .collect()
+}
+
+/// Fill in details for pending records using `lookup`, which resolves a
+/// record to its TMDb id and details (`Ok(None)` when nothing matches).
+///
+/// `on_batch` is called after each saved batch with the running report.
+pub fn backfill_details<L, B>(
+    db: &mut Database,
+    options: &BackfillOptions,
+    mut lookup: L,
+    mut on_batch: B,
+) -> Result<BackfillReport>
+where
+    L: FnMut(&FileRecord) -> Result<Option<(u64, MovieDetails)>>,
+    B: FnMut(&BackfillReport),
+{
+    let mut pending: Vec<FileRecord> = pending_records(db, options.refresh)
+        .into_iter()
+        .cloned()
+        .collect();
+    if let Some(limit) = options.limit {
+        pending.truncate(limit);
+    }
+
+    let mut report = BackfillReport::default();
+    for batch in pending.chunks(options.batch_size.max(1)) {
+        for record in batch {
+            match lookup(record) {
+                Ok(Some((id, details))) => {
+                    db.set_details(&record.destination, id, details);
+                    report.enriched.push(record.destination.clone());
+                }
+                Ok(None) => report.not_found.push(record.destination.clone()),
+                Err(e) => report
+                    .failed
+                    .push((record.destination.clone(), format!("{e:#}"))),
+            }
+        }
+        db.save()?;
+        on_batch(&report);
+    }
+    Ok(report)
+}
+
+/// A `lookup` for [`backfill_details`] backed by TMDb.
+pub fn tmdb_lookup<'a>(
+    client: &'a TmdbClient,
+    settings: &'a TmdbSettings,
+) -> impl FnMut(&FileRecord) -> Result<Option<(u64, MovieDetails)>> + 'a {
+    move |record| {
+        let id = match record.tmdb_id {
+            Some(id) => id,
+            None => {
+                let candidates = client.search_movie(&record.title, record.year)?;
+                // The file was on disk when it was organized.
+                let released_by = record.organized_time().map(|t| t.date_naive());
+                let best = providers::best_candidate(
+                    &record.title,
+                    record.year,
+                    released_by,
+                    &candidates,
+                    settings.min_match_score,
+                );
+                match best.and_then(|(candidate, _)| candidate.id) {
+                    Some(id) => id,
+                    None => return Ok(None),
+                }
+            }
+        };
+        Ok(Some((id, client.movie_details(id, &settings.region)?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{UndoEntry, UndoManifest};
+
+    fn entry(title: &str, destination: &str) -> UndoEntry {
+        UndoEntry {
+            source: format!("/dl/{title}.mkv"),
+            destination: destination.to_string(),
+            strategy: "move".to_string(),
+            timestamp: "2026-03-01T10:00:00+00:00".to_string(),
+            title: title.to_string(),
+            media_type: "movie".to_string(),
+            year: Some(2000),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_backfill_in_batches() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("library.json");
+        let mut db = Database::open(&path).unwrap();
+        db.record_run(&UndoManifest {
+            entries: vec![
+                entry("Heat", "/plex/Heat (2000)/Heat (2000).mkv"),
+                entry("Heat", "/plex/Heat (2000)/Heat (2000).en.srt"),
+                entry("Unknown", "/plex/Unknown (2000)/Unknown (2000).mkv"),
+                entry("Broken", "/plex/Broken (2000)/Broken (2000).mkv"),
+            ],
+            ..Default::default()
+        });
+
+        let options = BackfillOptions {
+            batch_size: 2,
+            ..Default::default()
+        };
+        let mut batches = 0;
+        let report = backfill_details(
+            &mut db,
+            &options,
+            |record| match record.title.as_str() {
+                "Heat" => Ok(Some((949, MovieDetails::default()))),
+                "Broken" => anyhow::bail!("HTTP 500"),
+                _ => Ok(None),
+            },
+            |_| batches += 1,
+        )
+        .unwrap();
+
+        assert_eq!(batches, 2);
+        assert_eq!(report.enriched.len(), 1);
+        assert_eq!(report.not_found.len(), 1);
+        assert_eq!(report.failed.len(), 1);
+
+        let db = Database::open(&path).unwrap();
+        let heat = db
+            .find_by_destination("/plex/Heat (2000)/Heat (2000).mkv")
+            .unwrap();
+        assert_eq!(heat.tmdb_id, Some(949));
+        assert!(heat.details.is_some());
+        assert_eq!(pending_records(&db, false).len(), 2);
+    }
+}
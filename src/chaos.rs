@@ -0,0 +1,213 @@
+//! Failure injection for resilience testing (hidden `--chaos` flag).
+//!
+//! Chaos mode makes file operations and provider requests fail at random,
+//! at configured rates, so undo, the review queue, and interrupted-run
+//! handling can be exercised against realistic failures instead of mocks.
+//! It is off unless [`install`] is called; the CLI does so only for the
+//! hidden `--chaos` developer flag.
+
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Context, Result};
+use tracing::warn;
+
+/// A kind of injected failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// A file operation (move, copy, link, undo) fails.
+    Rename,
+    /// A provider request returns HTTP 500.
+    ServerError,
+    /// A provider request times out.
+    Timeout,
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Rename => "rename",
+            Self::ServerError => "http",
+            Self::Timeout => "timeout",
+        })
+    }
+}
+
+/// Failure rates (0.0–1.0) per fault, plus an optional RNG seed.
+///
+/// Parsed from `0.2` (every fault at 20%) or a comma-separated list such as
+/// `rename=0.1,http=0.3,timeout=0.05,seed=42`; faults left out never fire.
+/// A seed makes the sequence of failures reproducible.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChaosSpec {
+    pub rename: f64,
+    pub http: f64,
+    pub timeout: f64,
+    pub seed: Option<u64>,
+}
+
+impl ChaosSpec {
+    fn rate(&self, fault: Fault) -> f64 {
+        match fault {
+            Fault::Rename => self.rename,
+            Fault::ServerError => self.http,
+            Fault::Timeout => self.timeout,
+        }
+    }
+}
+
+impl FromStr for ChaosSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Ok(rate) = s.trim().parse::<f64>() {
+            let rate = check_rate(rate)?;
+            return Ok(Self {
+                rename: rate,
+                http: rate,
+                timeout: rate,
+                seed: None,
+            });
+        }
+        let mut spec = Self::default();
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .with_context(|| format!("Expected key=value, got {part:?}"))?;
+            let value = value.trim();
+            match key.trim() {
+                "seed" => spec.seed = Some(value.parse().context("Invalid seed")?),
+                key => {
+                    let rate = check_rate(
+                        value
+                            .parse()
+                            .with_context(|| format!("Invalid rate for {key}"))?,
+                    )?;
+                    match key {
+                        "rename" => spec.rename = rate,
+                        "http" => spec.http = rate,
+                        "timeout" => spec.timeout = rate,
+                        _ => bail!("Unknown fault {key:?} (expected rename, http, timeout)"),
+                    }
+                }
+            }
+        }
+        Ok(spec)
+    }
+}
+
+fn check_rate(rate: f64) -> Result<f64> {
+    if !(0.0..=1.0).contains(&rate) {
+        bail!("Rate {rate} is outside 0.0–1.0");
+    }
+    Ok(rate)
+}
+
+/// Installed chaos settings and RNG state.
+#[derive(Debug)]
+struct Chaos {
+    spec: ChaosSpec,
+    rng: Mutex<u64>,
+}
+
+impl Chaos {
+    fn new(spec: ChaosSpec) -> Self {
+        let seed = spec.seed.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_nanos() as u64)
+        });
+        // Scramble (splitmix64) so small seeds don't start xorshift near
+        // zero; its state must also be non-zero.
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        Self {
+            spec,
+            rng: Mutex::new((z ^ (z >> 31)) | 1),
+        }
+    }
+
+    /// Whether `fault` fires this time.
+    fn roll(&self, fault: Fault) -> bool {
+        let rate = self.spec.rate(fault);
+        if rate <= 0.0 {
+            return false;
+        }
+        let mut state = self.rng.lock().unwrap_or_else(|e| e.into_inner());
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        let sample = (*state >> 11) as f64 / (1u64 << 53) as f64;
+        sample < rate
+    }
+}
+
+static CHAOS: OnceLock<Chaos> = OnceLock::new();
+
+/// Turn on chaos mode for the rest of the process. Only the first call
+/// takes effect.
+pub fn install(spec: ChaosSpec) {
+    warn!("Chaos mode: injecting failures ({spec:?})");
+    let _ = CHAOS.set(Chaos::new(spec));
+}
+
+/// Fail with an injected error if `fault` fires; a no-op unless chaos mode
+/// is on.
+pub fn inject(fault: Fault) -> io::Result<()> {
+    let Some(chaos) = CHAOS.get() else {
+        return Ok(());
+    };
+    if !chaos.roll(fault) {
+        return Ok(());
+    }
+    warn!("Chaos: injecting {fault} failure");
+    let (kind, message) = match fault {
+        Fault::Rename => (io::ErrorKind::Other, "injected file operation failure"),
+        Fault::ServerError => (io::ErrorKind::Other, "injected server error"),
+        Fault::Timeout => (io::ErrorKind::TimedOut, "injected request timeout"),
+    };
+    Err(io::Error::new(kind, format!("chaos: {message}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_spec() {
+        let all: ChaosSpec = "0.25".parse().unwrap();
+        assert_eq!((all.rename, all.http, all.timeout), (0.25, 0.25, 0.25));
+
+        let spec: ChaosSpec = "rename=0.5, http=1, seed=7".parse().unwrap();
+        assert_eq!(spec.rename, 0.5);
+        assert_eq!(spec.http, 1.0);
+        assert_eq!(spec.timeout, 0.0);
+        assert_eq!(spec.seed, Some(7));
+
+        assert!("rename=1.5".parse::<ChaosSpec>().is_err());
+        assert!("disk=0.1".parse::<ChaosSpec>().is_err());
+    }
+
+    #[test]
+    fn test_roll_respects_rates_and_seed() {
+        let spec = ChaosSpec {
+            rename: 0.3,
+            http: 1.0,
+            seed: Some(42),
+            ..Default::default()
+        };
+        let a = Chaos::new(spec.clone());
+        let b = Chaos::new(spec);
+        let rolls: Vec<bool> = (0..1000).map(|_| a.roll(Fault::Rename)).collect();
+        let again: Vec<bool> = (0..1000).map(|_| b.roll(Fault::Rename)).collect();
+        assert_eq!(rolls, again);
+        let fired = rolls.iter().filter(|r| **r).count();
+        assert!((200..400).contains(&fired), "fired {fired} of 1000");
+        assert!(a.roll(Fault::ServerError));
+        assert!(!a.roll(Fault::Timeout));
+    }
+}
@@ -0,0 +1,427 @@
+//! The command line: global flags and one `Command` per subcommand.
+
+use std::path::PathBuf;
+
+use clap::builder::PossibleValuesParser;
+use clap::{Parser, Subcommand};
+
+use plex_media_organizer::chaos::ChaosSpec;
+use plex_media_organizer::collections::ExportFormat;
+use plex_media_organizer::hash::HashMode;
+use plex_media_organizer::organizer;
+
+use super::cache::CacheAction;
+use super::corrections::CorrectionsAction;
+use super::defaults::DefaultsReport;
+use super::library::LibraryAction;
+use super::offline_db::OfflineDbAction;
+use super::organize::PreviewStage;
+use super::output::{OutputFormat, ProgressMode};
+use super::queue::QueueAction;
+use super::schema::SchemaAction;
+use super::service::{ServiceAction, WatchArgs};
+use super::view::ViewAction;
+
+#[derive(Parser)]
+#[command(
+    name = "plex-org",
+    about = "Organize media files into Plex-compatible folder structures",
+    version
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+
+    /// Increase verbosity (-v, -vv, -vvv)
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    pub verbose: u8,
+
+    /// Config file path
+    #[arg(short, long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Developer mode: inject random failures, e.g. `0.2` or
+    /// `rename=0.1,http=0.3,timeout=0.05,seed=42` (bare flag: 10% each).
+    #[arg(long, global = true, hide = true, value_name = "SPEC")]
+    #[arg(num_args = 0..=1, require_equals = true, default_missing_value = "0.1")]
+    pub chaos: Option<ChaosSpec>,
+
+    /// Progress display: auto, bar, quiet, or json (events on stderr).
+    #[arg(long, global = true, default_value_t = ProgressMode::Auto, value_name = "MODE")]
+    pub progress: ProgressMode,
+
+    /// Results of scan, plan, and dry-run organize: pretty, json, or csv.
+    #[arg(long, global = true, default_value_t = OutputFormat::Pretty, value_name = "FORMAT")]
+    pub output: OutputFormat,
+
+    /// Hide progress (same as `--progress quiet`).
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// Go easy on a shared machine: paced TMDb requests, one file
+    /// operation at a time, low CPU and disk priority.
+    #[arg(long, global = true)]
+    pub low_impact: bool,
+
+    /// Record every TMDb response of this run to FILE, for bug reports.
+    #[arg(long, global = true, value_name = "FILE")]
+    pub tmdb_record: Option<PathBuf>,
+
+    /// Answer TMDb requests from a recording instead of the network.
+    #[arg(
+        long,
+        global = true,
+        value_name = "FILE",
+        conflicts_with = "tmdb_record"
+    )]
+    pub tmdb_replay: Option<PathBuf>,
+
+    /// What `[cli_defaults]` contributed (see `Cli::parse_with_defaults`).
+    #[arg(skip)]
+    pub defaults: DefaultsReport,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Scan a directory and show discovered media files.
+    Scan {
+        /// Directory to scan.
+        path: PathBuf,
+        /// Movie inbox: skip TV season-pack folders as a unit.
+        #[arg(long)]
+        movies_only: bool,
+        /// Each top-level folder is one movie: organize only its main video.
+        #[arg(long)]
+        one_movie_per_folder: bool,
+        /// Hash contents to find duplicates: off, partial, or full.
+        #[arg(long)]
+        hash_mode: Option<HashMode>,
+        /// Go on from where an interrupted scan of this path stopped.
+        #[arg(long)]
+        resume: bool,
+    },
+    /// Size up a source directory before organizing: counts, sizes, depth,
+    /// languages, and an estimated organize time.
+    Survey {
+        /// Directory to survey.
+        path: PathBuf,
+        /// Strategy to estimate for: move, copy, hardlink, reflink, or symlink.
+        #[arg(short, long, alias = "link-mode", default_value = "move", value_parser = strategies())]
+        strategy: String,
+        /// Emit JSON instead of a report.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Preview the organization plan (dry-run).
+    Plan {
+        /// Source directory.
+        path: PathBuf,
+        /// Destination root directory.
+        #[arg(short, long)]
+        dest: PathBuf,
+        /// File strategy: move, copy, hardlink, reflink, or symlink.
+        #[arg(short, long, alias = "link-mode", default_value = "move", value_parser = strategies())]
+        strategy: String,
+        /// Save the plan (with source fingerprints) for `organize --apply-plan`.
+        #[arg(long, value_name = "FILE")]
+        plan_out: Option<PathBuf>,
+        /// Movie inbox: skip TV season-pack folders as a unit.
+        #[arg(long)]
+        movies_only: bool,
+        /// Each top-level folder is one movie: organize only its main video.
+        #[arg(long)]
+        one_movie_per_folder: bool,
+        /// Allow a source inside the destination library (re-organizing it).
+        #[arg(long)]
+        allow_nested: bool,
+        /// Stop after parsing (no TMDb requests), matching, or the full plan.
+        #[arg(long, value_enum, default_value_t)]
+        stage: PreviewStage,
+    },
+    /// Execute the organization plan.
+    Organize {
+        /// Source directory.
+        #[arg(required_unless_present = "apply_plan")]
+        path: Option<PathBuf>,
+        /// Destination root directory.
+        #[arg(short, long, required_unless_present_any = ["apply_plan", "stage"])]
+        dest: Option<PathBuf>,
+        /// File strategy: move, copy, hardlink, reflink, or symlink.
+        #[arg(short, long, alias = "link-mode", default_value = "move", value_parser = strategies())]
+        strategy: String,
+        /// Apply a plan saved with `plan --plan-out`, re-validating every source.
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["path", "dest"])]
+        apply_plan: Option<PathBuf>,
+        /// Deprecated: save the plan with `plan --plan-out` instead.
+        #[arg(
+            long,
+            value_name = "FILE",
+            hide = true,
+            conflicts_with_all = ["execute", "apply_plan"]
+        )]
+        plan_out: Option<PathBuf>,
+        /// Organize into this month's staging bucket instead of the library.
+        #[arg(long, conflicts_with = "dest")]
+        stage: bool,
+        /// Verify every file by size and quick hash after it is placed.
+        #[arg(long)]
+        paranoid: bool,
+        /// Checksum copied files (copies and cross-filesystem moves) in full
+        /// before and after, keeping the source on a mismatch.
+        #[arg(long)]
+        verify: bool,
+        /// Write a Kodi-style movie.nfo (plot, genres, cast, ids) beside
+        /// each organized movie.
+        #[arg(long)]
+        write_nfo: bool,
+        /// Run up to N file operations at once (the upper bound with --adaptive).
+        #[arg(long, value_name = "N")]
+        max_parallel: Option<usize>,
+        /// Tune concurrency from observed latency and errors (for flaky mounts).
+        #[arg(long)]
+        adaptive: bool,
+        /// Copy at most this many MB per second to or from any one
+        /// filesystem, however many operations run at once.
+        #[arg(long, value_name = "MB")]
+        io_limit: Option<u64>,
+        /// `journaled` records each file on disk as it is placed, so an
+        /// interrupted run can be undone and resumed; `quick` keeps the
+        /// record in memory; `auto` journals large runs.
+        #[arg(long, value_name = "MODE", value_parser = ["auto", "quick", "journaled"])]
+        pipeline: Option<String>,
+        /// Movie inbox: skip TV season-pack folders as a unit.
+        #[arg(long)]
+        movies_only: bool,
+        /// Each top-level folder is one movie: organize only its main video.
+        #[arg(long)]
+        one_movie_per_folder: bool,
+        /// Allow a source inside the destination library (re-organizing it).
+        #[arg(long)]
+        allow_nested: bool,
+        /// Allow a destination outside the configured library roots.
+        #[arg(long)]
+        allow_outside_library: bool,
+        /// Remove source folders the run empties, moving leftover junk
+        /// (`organize.cleanup_junk`) aside where `undo` can restore it.
+        #[arg(long)]
+        cleanup_source: bool,
+        /// When a movie is already in the library, replace a lower-quality
+        /// copy (moved to `organize.recycle_dir`) or skip the new file.
+        #[arg(long)]
+        upgrade: bool,
+        /// Stop starting new work after this long, e.g. `2h`; the rest is
+        /// saved for `--apply-plan` and the exit status is 75.
+        #[arg(long, value_name = "DURATION")]
+        max_runtime: Option<String>,
+        /// Actually execute (without this flag, acts as dry-run).
+        #[arg(long)]
+        execute: bool,
+    },
+    /// Deprecated spelling of `organize --apply-plan`.
+    #[command(hide = true)]
+    Apply {
+        /// Plan saved with `plan --plan-out`.
+        plan: PathBuf,
+        /// Allow a destination outside the configured library roots.
+        #[arg(long)]
+        allow_outside_library: bool,
+        /// Actually execute (without this flag, acts as dry-run).
+        #[arg(long)]
+        execute: bool,
+    },
+    /// Move staged items that have aged out into the library root.
+    Promote {
+        /// Library root (defaults to `destination` from config).
+        #[arg(short, long)]
+        dest: Option<PathBuf>,
+        /// Minimum time in staging, e.g. `7d` (defaults to `promote_after_days`).
+        #[arg(long, value_name = "AGE")]
+        older_than: Option<String>,
+        /// Allow a library root outside the configured library roots.
+        #[arg(long)]
+        allow_outside_library: bool,
+        /// Actually execute (without this flag, acts as dry-run).
+        #[arg(long)]
+        execute: bool,
+    },
+    /// List media organized within a recent timeframe.
+    List {
+        /// How far back to look, e.g. `7d`, `24h`, `2w`.
+        #[arg(long, default_value = "7d")]
+        since: String,
+        /// Include TV and music, not just movies.
+        #[arg(long)]
+        all: bool,
+        /// Emit JSON instead of a table.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Summarize the library database: counts and sizes by type, year,
+    /// resolution, source, codec, and month organized.
+    Stats,
+    /// Maintain the library database's picture of a library root.
+    Library {
+        #[command(subcommand)]
+        action: LibraryAction,
+    },
+    /// Check the library for problems: movie folder years against TMDb.
+    Health {
+        /// Library root (defaults to `destination` from config).
+        path: Option<PathBuf>,
+        /// Plan renames to the TMDb year for mismatched folders.
+        #[arg(long)]
+        fix: bool,
+        /// Emit JSON instead of a report.
+        #[arg(long, conflicts_with = "fix")]
+        json: bool,
+        /// Actually rename (with --fix; without this flag, acts as dry-run).
+        #[arg(long, requires = "fix")]
+        execute: bool,
+    },
+    /// Pin a file to a fixed destination name, overriding parsing.
+    Pin {
+        /// File to pin.
+        #[arg(required_unless_present_any = ["list", "remove"])]
+        source: Option<PathBuf>,
+        /// Destination relative to the library root, e.g.
+        /// `Movies/My Fan Edit (2020)/My Fan Edit (2020).mkv`.
+        #[arg(required_unless_present_any = ["list", "remove"])]
+        destination: Option<String>,
+        /// List pinned files.
+        #[arg(long, conflicts_with_all = ["source", "remove"])]
+        list: bool,
+        /// Remove the pin of this file.
+        #[arg(long, value_name = "FILE", conflicts_with = "source")]
+        remove: Option<PathBuf>,
+    },
+    /// Place loose subtitle files next to matching movies/episodes in the library.
+    Subtitles {
+        /// Directory containing subtitle files.
+        path: PathBuf,
+        /// File strategy: move, copy, hardlink, reflink, or symlink.
+        #[arg(short, long, alias = "link-mode", default_value = "move", value_parser = strategies())]
+        strategy: String,
+        /// Actually execute (without this flag, acts as dry-run).
+        #[arg(long)]
+        execute: bool,
+    },
+    /// Backfill TMDb details (genres, runtime, collection, rating) for movies
+    /// already in the library.
+    Enrich {
+        /// Re-fetch movies that already have details.
+        #[arg(long)]
+        refresh: bool,
+        /// Stop after this many movies.
+        #[arg(long)]
+        limit: Option<usize>,
+        /// Movies per batch; progress is saved after each batch.
+        #[arg(long, default_value_t = 25)]
+        batch_size: usize,
+    },
+    /// Export one playlist per TMDb collection in the library (from `enrich`).
+    Collections {
+        /// Output directory (defaults to `export.collections_dir`).
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+        /// Listing format: m3u or json (defaults to `export.collections_format`).
+        #[arg(long)]
+        format: Option<ExportFormat>,
+        /// Skip collections with fewer movies in the library.
+        #[arg(long)]
+        min_size: Option<usize>,
+        /// Actually write the listings (without this flag, acts as dry-run).
+        #[arg(long)]
+        execute: bool,
+    },
+    /// Record what a file is, by TMDb id or title and year; later runs
+    /// use it instead of parsing and searching.
+    Match {
+        /// The file to match.
+        file: PathBuf,
+        /// TMDb movie id, e.g. 603.
+        #[arg(long, required_unless_present = "title")]
+        tmdb_id: Option<u64>,
+        #[arg(long)]
+        title: Option<String>,
+        #[arg(long)]
+        year: Option<i32>,
+    },
+    /// Tell the organizer what files are ahead of time, e.g. from a CSV.
+    Corrections {
+        #[command(subcommand)]
+        action: CorrectionsAction,
+    },
+    /// Work through files held back for low confidence.
+    Queue {
+        #[command(subcommand)]
+        action: QueueAction,
+    },
+    /// Build a Plex-named library of links to the originals, never moving them.
+    View {
+        #[command(subcommand)]
+        action: ViewAction,
+    },
+    /// Keep organizing new downloads on an interval until stopped.
+    Watch {
+        #[command(flatten)]
+        args: WatchArgs,
+        /// Actually execute (without this flag, runs one dry-run pass).
+        #[arg(long)]
+        execute: bool,
+    },
+    /// Run `watch` as a system service.
+    Service {
+        #[command(subcommand)]
+        action: ServiceAction,
+    },
+    /// Trace how filenames are parsed, step by step; without names, an
+    /// interactive prompt that can also change config values live.
+    DebugParse {
+        /// Filenames to trace.
+        names: Vec<String>,
+        /// One JSON trace per line (`schema print explain`).
+        #[arg(long, requires = "names")]
+        json: bool,
+    },
+    /// Check the environment — config, database, TMDb key, destination
+    /// permissions and free space, network mounts — and list fixes.
+    Doctor {
+        /// Destinations to check besides those in the config.
+        destinations: Vec<PathBuf>,
+    },
+    /// Reverse an organize operation, the most recent by default.
+    #[command(alias = "rollback")]
+    Undo {
+        /// Reverse the most recent operation (the default).
+        #[arg(long, conflicts_with = "operation_id")]
+        last: bool,
+        /// Reverse this operation, as listed by `history`.
+        #[arg(long, value_name = "ID")]
+        operation_id: Option<String>,
+    },
+    /// List past organize operations that can be undone.
+    History,
+    /// Inspect or clear the TMDb responses kept between runs.
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+    /// Match movies without a network from IMDb's dataset.
+    OfflineDb {
+        #[command(subcommand)]
+        action: OfflineDbAction,
+    },
+    /// JSON Schemas of plans, run reports, parse traces, and TMDb
+    /// recordings.
+    Schema {
+        #[command(subcommand)]
+        action: SchemaAction,
+    },
+    /// Show current configuration.
+    Config,
+}
+
+/// The `--strategy` values, checked by clap.
+fn strategies() -> PossibleValuesParser {
+    PossibleValuesParser::new(organizer::STRATEGIES)
+}
@@ -0,0 +1,81 @@
+//! The time budget of a `--max-runtime` run.
+//!
+//! Once the budget is spent, matching stops taking new files and execution
+//! stops starting new operations; what was left is saved as a plan for
+//! `organize --apply-plan`, and the process exits with [`PARTIAL_EXIT`].
+
+use std::path::Path;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+
+use plex_media_organizer::models::{OrganizeAction, SavedPlan, UndoManifest};
+use plex_media_organizer::organizer;
+
+use super::dirs_data;
+
+/// Exit status of a run stopped by its time budget (`EX_TEMPFAIL`: run it
+/// again to continue).
+pub(super) const PARTIAL_EXIT: u8 = 75;
+
+static DEADLINE: OnceLock<Instant> = OnceLock::new();
+static SPENT: AtomicBool = AtomicBool::new(false);
+
+/// Start the budget for the rest of the process.
+pub(super) fn start(limit: Duration) -> Result<()> {
+    let Some(deadline) = Instant::now().checked_add(limit) else {
+        bail!("--max-runtime is too long");
+    };
+    let _ = DEADLINE.set(deadline);
+    Ok(())
+}
+
+/// When the budget runs out, if there is one.
+pub(super) fn deadline() -> Option<Instant> {
+    DEADLINE.get().copied()
+}
+
+/// Whether the budget has run out, noting it for the exit status.
+pub(super) fn exceeded() -> bool {
+    let spent = deadline().is_some_and(|d| Instant::now() >= d);
+    if spent {
+        SPENT.store(true, Ordering::Relaxed);
+    }
+    spent
+}
+
+/// The process exit status: [`PARTIAL_EXIT`] when the budget cut work short.
+pub(super) fn exit_code() -> ExitCode {
+    if SPENT.load(Ordering::Relaxed) {
+        ExitCode::from(PARTIAL_EXIT)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Save the actions a run left unstarted, if it ran out of time, and say
+/// how to resume.
+pub(super) fn save_remaining(
+    actions: &[OrganizeAction],
+    manifest: &UndoManifest,
+    dest: &Path,
+) -> Result<()> {
+    let unstarted = manifest.stats.as_ref().map_or(0, |s| s.unstarted) as usize;
+    if unstarted == 0 {
+        return Ok(());
+    }
+    SPENT.store(true, Ordering::Relaxed);
+    let mut remaining = actions[actions.len().saturating_sub(unstarted)..].to_vec();
+    organizer::fingerprint_actions(&mut remaining);
+    let path = dirs_data().join("remaining-plan.json");
+    organizer::save_plan(&path, &SavedPlan::new(dest.to_path_buf(), remaining))?;
+    println!(
+        "\n⏱️  Time budget reached: {unstarted} actions not started. Resume with \
+         `plex-org organize --apply-plan {} --execute`.",
+        path.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,46 @@
+//! `cache`: the TMDb responses kept between runs.
+
+use anyhow::Result;
+use clap::Subcommand;
+
+use plex_media_organizer::config::AppConfig;
+use plex_media_organizer::database::cache::ResponseCache;
+
+use super::dirs_cache;
+
+#[derive(Subcommand)]
+pub enum CacheAction {
+    /// Show how many responses are kept, and how many have expired.
+    Stats,
+    /// Forget every kept response.
+    Clear,
+}
+
+pub(super) fn cmd_cache(action: CacheAction, config: &AppConfig) -> Result<()> {
+    let cache = ResponseCache::open(&dirs_cache(), &config.tmdb)?;
+    match action {
+        CacheAction::Stats => {
+            let stats = cache.stats();
+            println!("Cache:    {}", cache.path().display());
+            println!(
+                "Entries:  {} ({} searches that found nothing)",
+                stats.entries, stats.negative
+            );
+            println!("Expired:  {} (dropped on the next save)", stats.expired);
+            println!("Size:     {} KB", stats.bytes.div_ceil(1024));
+            if let Some(oldest) = stats.oldest {
+                println!("Oldest:   {}", oldest.format("%Y-%m-%d %H:%M UTC"));
+            }
+            println!(
+                "TTL:      {} h, {} h for searches that found nothing",
+                config.tmdb.cache_ttl_hours, config.tmdb.negative_cache_ttl_hours
+            );
+        }
+        CacheAction::Clear => {
+            let count = cache.clear();
+            cache.save()?;
+            println!("Cleared {count} cached TMDb responses.");
+        }
+    }
+    Ok(())
+}
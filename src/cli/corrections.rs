@@ -0,0 +1,170 @@
+//! User corrections: `match` for one file, bulk import from CSV, list,
+//! remove.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use clap::Subcommand;
+
+use plex_media_organizer::config::AppConfig;
+use plex_media_organizer::database::corrections::{self, Correction, CsvImport};
+use plex_media_organizer::database::Database;
+use plex_media_organizer::providers::tmdb::TmdbClient;
+
+use super::{dirs_database, progress};
+
+#[derive(Subcommand)]
+pub enum CorrectionsAction {
+    /// Import corrections from a CSV with a header naming a source column
+    /// (source/path/file/filename) and tmdb_id and/or title, plus year.
+    Import {
+        /// CSV file.
+        file: PathBuf,
+        /// Check the file and show what would be imported without saving.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// List corrections.
+    List {
+        /// Emit JSON instead of a listing.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Remove the correction of a file (path or bare file name).
+    Remove { source: String },
+}
+
+pub(super) fn cmd_corrections(action: CorrectionsAction, config: &AppConfig) -> Result<()> {
+    match action {
+        CorrectionsAction::Import { file, dry_run } => cmd_import(&file, dry_run, config),
+        CorrectionsAction::List { json } => cmd_list(json),
+        CorrectionsAction::Remove { source } => {
+            let mut db = Database::open(&dirs_database())?;
+            if !db.remove_correction(&source) {
+                bail!("No correction for {source}");
+            }
+            db.save()?;
+            println!("Removed the correction of {source}");
+            Ok(())
+        }
+    }
+}
+
+/// Record what one file is, so later runs skip its search.
+pub(super) fn cmd_match(
+    file: &Path,
+    tmdb_id: Option<u64>,
+    title: Option<String>,
+    year: Option<i32>,
+    config: &AppConfig,
+) -> Result<()> {
+    if !file.is_file() {
+        bail!("Not a file: {}", file.display());
+    }
+    let source = std::path::absolute(file)?;
+    let mut correction = Correction::new(&source.to_string_lossy(), tmdb_id, title, year)?;
+    // An id alone is named from TMDb when there's a key.
+    if let (Some(id), None) = (correction.tmdb_id, &correction.title) {
+        if !config.tmdb.api_key.is_empty() {
+            let client = TmdbClient::from_settings(&config.tmdb);
+            let details = client.movie_details(id, &config.tmdb.region)?;
+            correction.title = details.title;
+            correction.year = correction.year.or(details.year);
+        }
+    }
+
+    let mut db = Database::open(&dirs_database())?;
+    let replaced = db.add_correction(correction.clone());
+    db.save()?;
+    let note = if replaced {
+        " (replaced the earlier match)"
+    } else {
+        ""
+    };
+    println!("🎯 {} → {}{note}", file.display(), identity(&correction));
+    Ok(())
+}
+
+fn cmd_import(file: &Path, dry_run: bool, config: &AppConfig) -> Result<()> {
+    let text =
+        fs::read_to_string(file).with_context(|| format!("Failed to read {}", file.display()))?;
+    let CsvImport { mut rows, errors } = corrections::parse_csv(&text)?;
+
+    // Rows with only a TMDb id get their title from TMDb when there's a key.
+    let unnamed = rows.iter().filter(|(_, c)| c.title.is_none()).count();
+    if unnamed > 0 && !config.tmdb.api_key.is_empty() {
+        let client = TmdbClient::from_settings(&config.tmdb);
+        let mut progress = progress("Fetching titles", Some(unnamed as u64));
+        for (line, correction) in rows.iter_mut().filter(|(_, c)| c.title.is_none()) {
+            progress.advance(1);
+            let Some(id) = correction.tmdb_id else {
+                continue;
+            };
+            match client.movie_details(id, &config.tmdb.region) {
+                Ok(details) => {
+                    correction.title = details.title;
+                    correction.year = correction.year.or(details.year);
+                }
+                Err(e) => eprintln!("⚠️  line {line}: {e:#}"),
+            }
+        }
+        progress.finish("done");
+    }
+
+    for (_, correction) in &rows {
+        println!("  {} → {}", correction.source, identity(correction));
+    }
+    if !errors.is_empty() {
+        println!("\n⚠️  Skipped {} rows:\n", errors.len());
+        for error in &errors {
+            println!("  line {}: {}", error.line, error.message);
+        }
+    }
+    if dry_run {
+        println!("\nDry-run: {} corrections would be imported.", rows.len());
+        return Ok(());
+    }
+
+    let mut db = Database::open(&dirs_database())?;
+    let total = rows.len();
+    let replaced = rows
+        .into_iter()
+        .filter(|(_, correction)| db.add_correction(correction.clone()))
+        .count();
+    db.save()?;
+    println!("\n✅ Imported {total} corrections ({replaced} replaced earlier ones).");
+    Ok(())
+}
+
+fn cmd_list(json: bool) -> Result<()> {
+    let db = Database::open(&dirs_database())?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(db.corrections())?);
+        return Ok(());
+    }
+    if db.corrections().is_empty() {
+        println!("No corrections.");
+        return Ok(());
+    }
+    for correction in db.corrections() {
+        println!("  {} → {}", correction.source, identity(correction));
+    }
+    println!("\n{} corrections.", db.corrections().len());
+    Ok(())
+}
+
+/// `Title (Year) [tmdb 603]`, with whichever parts are known.
+fn identity(correction: &Correction) -> String {
+    let mut parts = Vec::new();
+    if let Some(title) = &correction.title {
+        parts.push(title.clone());
+    }
+    if let Some(year) = correction.year {
+        parts.push(format!("({year})"));
+    }
+    if let Some(id) = correction.tmdb_id {
+        parts.push(format!("[tmdb {id}]"));
+    }
+    parts.join(" ")
+}
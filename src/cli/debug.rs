@@ -0,0 +1,111 @@
+//! `debug-parse`: an interactive parser trace for contributors.
+
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use anyhow::Result;
+
+use plex_media_organizer::config::AppConfig;
+use plex_media_organizer::database::Database;
+use plex_media_organizer::parse_trace::{self, ParseTrace};
+
+use super::dirs_database;
+use super::output::{self, OutputFormat};
+
+const HELP: &str = "\
+Paste a filename to trace it. Commands:
+  :set KEY VALUE   change a config value, e.g. `:set profiles.detect_anime true`
+  :reset           drop every :set
+  :learned on|off  apply learned title tokens from the library database
+  :show            list the changes in effect
+  :quit            leave (Ctrl-D works too)";
+
+/// Trace `names`, or read filenames and commands from stdin when none are
+/// given. With `json` or `--output json`, each trace is one line of JSON
+/// (the `explain` schema).
+pub(super) fn cmd_debug_parse(names: &[String], json: bool, config: &AppConfig) -> Result<()> {
+    let json = json || output::format() == OutputFormat::Json;
+    let db = Database::open(&dirs_database())?;
+    if !names.is_empty() {
+        for name in names {
+            let trace = parse_trace::trace(name, config, Some(&db));
+            if json {
+                println!("{}", serde_json::to_string(&trace)?);
+            } else {
+                print_trace(&trace);
+            }
+        }
+        return Ok(());
+    }
+
+    let interactive = io::stdin().is_terminal();
+    if interactive {
+        println!("{HELP}\n");
+    }
+    let mut current = config.clone();
+    let mut changes: Vec<String> = Vec::new();
+    let mut learned = true;
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        if interactive {
+            print!("parse> ");
+            io::stdout().flush()?;
+        }
+        let Some(line) = lines.next().transpose()? else {
+            break;
+        };
+        let line = line.trim();
+        let mut words = line.splitn(3, ' ');
+        match (words.next().unwrap_or_default(), words.next(), words.next()) {
+            ("", _, _) => {}
+            (":quit" | ":q", _, _) => break,
+            (":help" | ":h", _, _) => println!("{HELP}"),
+            (":set", Some(key), Some(value)) => match current.with_value(key, value) {
+                Ok(changed) => {
+                    current = changed;
+                    changes.push(format!("{key} = {value}"));
+                    println!("✓ {key} = {value}");
+                }
+                Err(e) => println!("❌ {e:#}"),
+            },
+            (":reset", _, _) => {
+                current = config.clone();
+                changes.clear();
+                println!("✓ Back to the loaded config.");
+            }
+            (":learned", Some(setting @ ("on" | "off")), None) => {
+                learned = setting == "on";
+                println!("✓ Learned title tokens {setting}.");
+            }
+            (":show", _, _) => {
+                println!("learned tokens: {}", if learned { "on" } else { "off" });
+                for change in &changes {
+                    println!("{change}");
+                }
+            }
+            (command, _, _) if command.starts_with(':') => {
+                println!("❌ Unknown or incomplete command {line:?}; :help lists them.")
+            }
+            _ => print_trace(&parse_trace::trace(line, &current, learned.then_some(&db))),
+        }
+    }
+    Ok(())
+}
+
+fn print_trace(trace: &ParseTrace) {
+    let parsed = &trace.parsed;
+    println!("\n{}", parsed.raw_filename);
+    for step in &trace.steps {
+        println!("  {:<11} {}", step.stage, step.detail);
+    }
+    let episode = match (parsed.season, parsed.episode) {
+        (Some(s), Some(e)) => format!(" S{s:02}E{e:02}"),
+        (None, Some(e)) => format!(" E{e:02}"),
+        _ => String::new(),
+    };
+    let year = parsed.year.map(|y| format!(" ({y})")).unwrap_or_default();
+    println!(
+        "  → {} {:?}{year}{episode}, quality {:?}, confidence {:.0}",
+        parsed.media_type, parsed.title, parsed.quality, parsed.confidence
+    );
+    println!("  → {}\n", trace.destination.display());
+}
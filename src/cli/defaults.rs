@@ -0,0 +1,158 @@
+//! `[cli_defaults]`: flag defaults from the config file.
+//!
+//! Keys are long flag names (`max-parallel = 4`, `paranoid = true`); each
+//! seeds the default of that flag on every command that has it, before the
+//! command line is parsed. A value given on the command line still wins. A
+//! switch seeded with `true` stays on for every run, as switches have no
+//! `--no-` form; remove the key to turn it off.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Command, CommandFactory, FromArgMatches};
+
+use plex_media_organizer::config::AppConfig;
+
+use super::Cli;
+
+/// What `[cli_defaults]` did for this run, reported once logging is set up.
+#[derive(Debug, Clone, Default)]
+pub struct DefaultsReport {
+    /// Flags this run took from config, e.g. `--max-parallel 4`.
+    pub used: Vec<String>,
+    /// Keys that were ignored, with the reason.
+    pub ignored: Vec<String>,
+}
+
+/// A flag whose default was seeded from config.
+struct Seeded {
+    /// Subcommand path, e.g. ` queue apply`; empty for the top level.
+    command: String,
+    id: String,
+    flag: String,
+    value: String,
+}
+
+impl Cli {
+    /// Parse the command line, with `[cli_defaults]` from the config file
+    /// named by `-c` as flag defaults. Exits on a usage error, like
+    /// `Cli::parse`.
+    pub fn parse_with_defaults() -> Self {
+        let args: Vec<OsString> = std::env::args_os().collect();
+        let config = config_path(&args).map(|path| AppConfig::load_or_default(Some(&path)));
+        let mut report = DefaultsReport::default();
+        let mut defaults = BTreeMap::new();
+        for (key, value) in config.map(|c| c.cli_defaults).unwrap_or_default() {
+            let flag = key.replace('_', "-");
+            let value = match value {
+                toml::Value::String(s) => s,
+                toml::Value::Integer(n) => n.to_string(),
+                toml::Value::Float(n) => n.to_string(),
+                toml::Value::Boolean(b) => b.to_string(),
+                _ => {
+                    report.ignored.push(format!("{key}: not a single value"));
+                    continue;
+                }
+            };
+            if flag == "config" {
+                report
+                    .ignored
+                    .push(format!("{key}: the config file cannot name itself"));
+                continue;
+            }
+            defaults.insert(flag, value);
+        }
+
+        let mut seeded = Vec::new();
+        let mut known = BTreeSet::new();
+        let command = seed(Cli::command(), &defaults, "", &mut seeded, &mut known);
+        for flag in defaults.keys().filter(|f| !known.contains(*f)) {
+            report
+                .ignored
+                .push(format!("{flag}: no command has --{flag}"));
+        }
+        let matches = command.get_matches_from(args);
+        let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+        report.used = used(&matches, &seeded);
+        cli.defaults = report;
+        cli
+    }
+}
+
+/// The `-c`/`--config` value, found before clap runs.
+fn config_path(args: &[OsString]) -> Option<PathBuf> {
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        let arg = arg.to_string_lossy();
+        if arg == "--" {
+            break;
+        }
+        if arg == "-c" || arg == "--config" {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.strip_prefix("--config=") {
+            return Some(PathBuf::from(path));
+        }
+        if let Some(path) = arg.strip_prefix("-c").filter(|p| !p.is_empty()) {
+            if !arg.starts_with("--") {
+                return Some(PathBuf::from(path.trim_start_matches('=')));
+            }
+        }
+    }
+    None
+}
+
+/// Set the defaults of `command` and its subcommands, recording each.
+fn seed(
+    command: Command,
+    defaults: &BTreeMap<String, String>,
+    path: &str,
+    seeded: &mut Vec<Seeded>,
+    known: &mut BTreeSet<String>,
+) -> Command {
+    let command = command.mut_args(|arg| {
+        let Some((flag, value)) = arg.get_long().and_then(|long| defaults.get_key_value(long))
+        else {
+            return arg;
+        };
+        known.insert(flag.clone());
+        seeded.push(Seeded {
+            command: path.to_string(),
+            id: arg.get_id().to_string(),
+            flag: flag.clone(),
+            value: value.clone(),
+        });
+        // Defaults must be 'static without clap's `string` feature; these
+        // are few and live for the whole run.
+        arg.default_value(&*value.clone().leak())
+    });
+    command.mut_subcommands(|sub| {
+        let path = format!("{path} {}", sub.get_name());
+        seed(sub, defaults, &path, seeded, known)
+    })
+}
+
+/// The seeded flags this invocation left to their default.
+fn used(matches: &ArgMatches, seeded: &[Seeded]) -> Vec<String> {
+    let mut used = Vec::new();
+    let mut path = String::new();
+    let mut level = Some(matches);
+    while let Some(matches) = level {
+        for flag in seeded.iter().filter(|s| s.command == path) {
+            if matches.value_source(&flag.id) == Some(ValueSource::DefaultValue) {
+                match flag.value.as_str() {
+                    "false" => {}
+                    "true" => used.push(format!("--{}", flag.flag)),
+                    value => used.push(format!("--{} {value}", flag.flag)),
+                }
+            }
+        }
+        level = matches.subcommand().map(|(name, sub)| {
+            path = format!("{path} {name}");
+            sub
+        });
+    }
+    used
+}
@@ -0,0 +1,62 @@
+//! `doctor`: environment checks with a fix-it list.
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+
+use plex_media_organizer::config::AppConfig;
+use plex_media_organizer::doctor::{self, DoctorInputs, Severity};
+
+use super::dirs_database;
+
+/// Run every check, print the results, then the fixes most urgent first.
+/// Fails when a problem was found, so scripts can gate on it.
+///
+/// `config_error` is why the config at `config_path` did not load, if it
+/// did not; `config` is then the defaults.
+pub(super) fn cmd_doctor(
+    config: &AppConfig,
+    config_path: Option<PathBuf>,
+    config_error: Option<String>,
+    destinations: Vec<PathBuf>,
+) -> Result<()> {
+    let inputs = DoctorInputs {
+        config_path,
+        config_error,
+        database: dirs_database(),
+        destinations,
+    };
+    let findings = doctor::diagnose(config, &inputs);
+    println!("\n🩺 plex-org doctor\n");
+    for finding in &findings {
+        let icon = match finding.severity {
+            Severity::Problem => "❌",
+            Severity::Warning => "⚠️ ",
+            Severity::Ok => "✅",
+        };
+        println!("{icon} {:<11} {}", finding.check, finding.detail);
+    }
+
+    let fixes: Vec<_> = findings.iter().filter(|f| f.fix.is_some()).collect();
+    if fixes.is_empty() {
+        println!("\nAll checks passed.");
+        return Ok(());
+    }
+    println!("\n🔧 Fix-it list, most urgent first:\n");
+    for (n, finding) in fixes.iter().enumerate() {
+        println!(
+            "  {}. [{}] {}",
+            n + 1,
+            finding.check,
+            finding.fix.as_deref().unwrap_or_default()
+        );
+    }
+    let problems = fixes
+        .iter()
+        .filter(|f| f.severity == Severity::Problem)
+        .count();
+    if problems > 0 {
+        bail!("{problems} problems found");
+    }
+    Ok(())
+}
@@ -0,0 +1,92 @@
+//! `undo` and `history`: past organize operations.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use plex_media_organizer::database::Database;
+use plex_media_organizer::organizer::{self, UndoReport};
+
+use super::pipeline::recover_interrupted;
+use super::{dirs_database, dirs_undo};
+
+/// Reverse the most recent operation with `last` or without an `id`,
+/// else operation `id`.
+pub(super) fn cmd_undo(last: bool, id: Option<&str>) -> Result<()> {
+    let id = id.filter(|_| !last);
+    recover_interrupted()?;
+    // Open the database first so a broken database aborts before any file moves.
+    let mut db = Database::open(&dirs_database())?;
+    // Saved after every unit, so the database never lags the files by more
+    // than the unit being undone.
+    let mut record = |unit: &UndoReport| -> Result<()> {
+        // A file already back at its source was reversed by an undo whose
+        // database update failed; it is rolled back like one reversed now.
+        let (returned, gone): (Vec<_>, Vec<_>) = unit
+            .missing
+            .iter()
+            .cloned()
+            .partition(|e| Path::new(&e.source).exists());
+        db.rollback_entries(&unit.reversed);
+        db.rollback_entries(&returned);
+        db.forget_entries(&gone);
+        db.save().with_context(|| {
+            format!(
+                "{} files were reversed but the library database was not updated; \
+                 run `undo` again to update it",
+                unit.reversed.len()
+            )
+        })?;
+        Ok(())
+    };
+    let report = match id {
+        Some(id) => organizer::undo_operation(&dirs_undo(), id, &mut record)?,
+        None => organizer::undo_last(&dirs_undo(), &mut record)?,
+    };
+
+    println!(
+        "Undo complete: {} files reversed, {} already gone.",
+        report.reversed.len(),
+        report.missing.len()
+    );
+    if !report.is_complete() {
+        println!(
+            "\n⚠️  {} files could not be reversed:\n",
+            report.failed.len()
+        );
+        for (entry, err) in &report.failed {
+            println!("  {} — {}", entry.destination, err);
+        }
+        let again = id.map_or("undo".to_string(), |id| format!("undo --operation-id {id}"));
+        println!(
+            "\nThey remain in {}; run `{again}` again after fixing the cause.",
+            report.manifest_path.display()
+        );
+    }
+    Ok(())
+}
+
+/// List the operations `undo` can reverse, newest first.
+pub(super) fn cmd_history() -> Result<()> {
+    recover_interrupted()?;
+    let operations = organizer::list_operations(&dirs_undo())?;
+    if operations.is_empty() {
+        println!("No organize operations to undo.");
+        return Ok(());
+    }
+    println!("{:<18} {:>6}  {:<26} DESCRIPTION", "ID", "FILES", "CREATED");
+    for op in &operations {
+        println!(
+            "{:<18} {:>6}  {:<26} {}",
+            op.id,
+            op.files,
+            op.created_at.get(..19).unwrap_or(&op.created_at),
+            op.description
+        );
+    }
+    println!(
+        "\n{} operations. Reverse one with `plex-org undo --operation-id <ID>`.",
+        operations.len()
+    );
+    Ok(())
+}
@@ -0,0 +1,579 @@
+//! Queries and maintenance against the organized library.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use clap::Subcommand;
+use serde::Serialize;
+
+use plex_media_organizer::backfill::{self, BackfillOptions};
+use plex_media_organizer::collections::{self, ExportFormat};
+use plex_media_organizer::config::AppConfig;
+use plex_media_organizer::database::Database;
+use plex_media_organizer::hash::{self, HashMode};
+use plex_media_organizer::health;
+use plex_media_organizer::models::MediaType;
+use plex_media_organizer::parser::{self, ParseOptions};
+use plex_media_organizer::probe;
+use plex_media_organizer::providers::tmdb::TmdbClient;
+use plex_media_organizer::scanner::{self, ScanOptions};
+use plex_media_organizer::subtitles;
+use plex_media_organizer::utils;
+use tracing::warn;
+
+use super::output::{self, print_rows, OutputFormat};
+use super::{
+    dirs_database, execute_and_record, guard_destination, print_skipped_dirs, progress, say,
+    truncate,
+};
+
+#[derive(Subcommand)]
+pub enum LibraryAction {
+    /// Record the media files already in a library root, so later runs
+    /// know what it holds without parsing it again.
+    Index {
+        /// Library root (defaults to `destination` from config).
+        path: Option<PathBuf>,
+        /// Measure each new file's streams with ffprobe, as `scan.probe`.
+        #[arg(long)]
+        probe: bool,
+        /// Drop the records of deleted files even when they are more than
+        /// half of the library's.
+        #[arg(long)]
+        confirm_removals: bool,
+    },
+}
+
+pub(super) fn cmd_list(since: &str, all: bool, json: bool) -> Result<()> {
+    let cutoff = list_cutoff(since)?;
+
+    let db = Database::open(&dirs_database())?;
+    let records: Vec<_> = db
+        .files_since(cutoff)
+        .into_iter()
+        .filter(|r| all || r.media_type == MediaType::Movie.to_string())
+        .filter(|r| scanner::is_media_path(Path::new(&r.destination)))
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&records)?);
+        return Ok(());
+    }
+    if records.is_empty() {
+        println!("Nothing organized in the last {since}.");
+        return Ok(());
+    }
+
+    println!(
+        "{:<12} {:<40} {:<6} {:<24} Path",
+        "Organized", "Title", "Year", "Quality"
+    );
+    println!("{}", "-".repeat(110));
+    for r in &records {
+        let date = r
+            .organized_time()
+            .map(|t| t.format("%Y-%m-%d").to_string())
+            .unwrap_or_default();
+        let year = r.year.map(|y| y.to_string()).unwrap_or_default();
+        println!(
+            "{:<12} {:<40} {:<6} {:<24} {}",
+            date,
+            truncate(&r.title, 38),
+            year,
+            truncate(&r.quality, 22),
+            r.destination
+        );
+    }
+    println!("\n{} items organized in the last {since}.", records.len());
+    Ok(())
+}
+
+/// One group of `stats --output csv`.
+#[derive(Serialize)]
+struct StatsRow<'a> {
+    group: &'a str,
+    key: &'a str,
+    files: usize,
+    bytes: u64,
+}
+
+pub(super) fn cmd_stats() -> Result<()> {
+    let db = Database::open(&dirs_database())?;
+    let stats = db.stats();
+    let groups = [
+        ("type", &stats.by_type),
+        ("year", &stats.by_year),
+        ("resolution", &stats.by_resolution),
+        ("source", &stats.by_source),
+        ("codec", &stats.by_codec),
+        ("month", &stats.by_month),
+    ];
+    match output::format() {
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(&stats)?);
+            return Ok(());
+        }
+        OutputFormat::Csv => {
+            let rows: Vec<StatsRow> = groups
+                .iter()
+                .flat_map(|(group, buckets)| {
+                    buckets.iter().map(|b| StatsRow {
+                        group,
+                        key: &b.key,
+                        files: b.files,
+                        bytes: b.bytes,
+                    })
+                })
+                .collect();
+            return print_rows(&rows, &["group", "key", "files", "bytes"]);
+        }
+        OutputFormat::Pretty => {}
+    }
+
+    if stats.files == 0 {
+        println!("The library database is empty; nothing has been organized yet.");
+        return Ok(());
+    }
+    println!(
+        "📚 {} files, {}",
+        stats.files,
+        utils::format_size(stats.bytes)
+    );
+    if let Some(confidence) = stats.average_confidence {
+        println!("🎯 Average match confidence: {confidence:.0}%");
+    }
+    println!(
+        "❓ {} movies organized without a TMDb match; {} files awaiting review",
+        stats.unmatched, stats.awaiting_review
+    );
+    for (group, buckets) in groups {
+        println!("\nBy {group}:");
+        for b in buckets {
+            println!(
+                "  {:<12} {:>7} {:>5.1}%  {:>10}",
+                truncate(&b.key, 12),
+                b.files,
+                b.files as f64 * 100.0 / stats.files as f64,
+                utils::format_size(b.bytes)
+            );
+        }
+    }
+    Ok(())
+}
+
+pub(super) fn cmd_health(
+    path: Option<PathBuf>,
+    fix: bool,
+    json: bool,
+    execute: bool,
+    config: &AppConfig,
+) -> Result<()> {
+    if config.tmdb.api_key.is_empty() {
+        bail!("No TMDb API key: set `tmdb.api_key` in config or TMDB_API_KEY");
+    }
+    let root = library_root(path, config)?;
+    let folders = health::movie_folders(&root, &config.organize.movies_dir);
+    let db = Database::open(&dirs_database())?;
+    let client = TmdbClient::from_settings(&config.tmdb);
+    let mut lookup = health::tmdb_year_lookup(&client, &config.tmdb);
+    let mut progress = progress("Checking years", Some(folders.len() as u64));
+    let report = health::check_years(&folders, &db, |folder, record| {
+        progress.advance(1);
+        lookup(folder, record)
+    });
+    progress.finish(&format!("{} movie folders", report.checked));
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+    println!(
+        "\n🩺 Years: {} folders checked, {} mismatched, {} without a confident match, {} failed.",
+        report.checked,
+        report.mismatches.len(),
+        report.unresolved.len(),
+        report.failed.len()
+    );
+    for m in &report.mismatches {
+        let via = if m.stored_id { "stored id" } else { "search" };
+        println!(
+            "  {} — TMDb says {} (id {}, {via})",
+            m.folder.path.display(),
+            m.tmdb_year,
+            m.tmdb_id
+        );
+    }
+    for path in &report.unresolved {
+        println!("  no match: {}", path.display());
+    }
+    for (path, err) in &report.failed {
+        println!("  failed:   {} — {err}", path.display());
+    }
+    if !fix || report.mismatches.is_empty() {
+        if !report.mismatches.is_empty() {
+            println!("\nRe-run with --fix to preview renames.");
+        }
+        return Ok(());
+    }
+
+    let actions: Vec<_> = report
+        .mismatches
+        .iter()
+        .flat_map(health::year_fix_actions)
+        .collect();
+    println!("\n📋 Fixes ({} files):\n", actions.len());
+    for action in &actions {
+        println!(
+            "  {} → {}",
+            action.source.display(),
+            action.destination.display()
+        );
+    }
+    if !execute {
+        println!("\nDry-run complete. Use `health --fix --execute` to rename.");
+        return Ok(());
+    }
+
+    guard_destination(&root, false, false, config)?;
+    let manifest = execute_and_record(&actions, Some(&root), None, config)?;
+    for m in &report.mismatches {
+        health::remove_emptied_folder(&m.folder.path);
+    }
+    println!(
+        "\n✅ Renamed {} files. Undo with `plex-org undo`.",
+        manifest.entries.len()
+    );
+    Ok(())
+}
+
+/// `path`, or the configured destination, made absolute.
+fn library_root(path: Option<PathBuf>, config: &AppConfig) -> Result<PathBuf> {
+    let root = match path {
+        Some(p) => p,
+        None if !config.destination.is_empty() => PathBuf::from(&config.destination),
+        None => bail!("No library root: pass a path or set `destination` in config"),
+    };
+    Ok(std::path::absolute(&root)?)
+}
+
+pub(super) fn cmd_library(action: LibraryAction, config: &AppConfig) -> Result<()> {
+    match action {
+        LibraryAction::Index {
+            path,
+            probe,
+            confirm_removals,
+        } => cmd_index(path, probe, confirm_removals, config),
+    }
+}
+
+/// Bring the records under a library root up to date: follow renames,
+/// drop deleted files, and record the files the database does not know,
+/// parsed from their names. With `scan.hash_mode` on, new records are
+/// hashed and copies of files already recorded are listed.
+fn cmd_index(
+    path: Option<PathBuf>,
+    probe: bool,
+    confirm_removals: bool,
+    config: &AppConfig,
+) -> Result<()> {
+    let root = library_root(path, config)?;
+    // The library is laid out already: no season packs or one-movie folders,
+    // and extras folders belong to the movie beside them.
+    let options = ScanOptions {
+        skip_season_packs: false,
+        one_movie_per_folder: false,
+        claim_extras: true,
+        ..ScanOptions::from_config(config)
+    };
+    let report = scanner::scan_directory_report(&root, &options)?;
+    print_skipped_dirs(&report.skipped);
+
+    let mut db = Database::open(&dirs_database())?;
+    // A scan that found no media is more likely an unmounted drive than an
+    // emptied library; its records are only dropped when confirmed.
+    let changes = if report.files.is_empty() && !confirm_removals {
+        say!(
+            "No media files under {}; records left as they are.",
+            root.display()
+        );
+        Default::default()
+    } else {
+        db.reconcile(&root, confirm_removals)?
+    };
+    let mut indexing = progress("Indexing", Some(report.files.len() as u64));
+    let mut added = Vec::new();
+    let options = ParseOptions::from_config(config);
+    for file in &report.files {
+        indexing.advance(1);
+        if db
+            .find_by_destination(&file.source_path.to_string_lossy())
+            .is_some()
+        {
+            continue;
+        }
+        let mut parsed = parser::parse_media_file(file, &options);
+        if (probe || config.scan.probe) && parsed.media_type != MediaType::Music {
+            probe::apply(&mut parsed, &file.source_path);
+        }
+        db.index_file(&file.source_path, &parsed);
+        added.push(&file.source_path);
+    }
+    indexing.finish(&format!("{} new", added.len()));
+
+    let mode = config.scan.hash_mode;
+    let mut copies = Vec::new();
+    if mode != HashMode::Off && !added.is_empty() {
+        let mut hashing = progress(&format!("Hashing ({mode})"), Some(added.len() as u64));
+        for path in &added {
+            hashing.advance(1);
+            match hash::content_hash(path, mode, config.scan.hash_partial_mb) {
+                Ok(Some(content_hash)) => {
+                    if let Some(record) = db.find_by_content_hash(&content_hash) {
+                        copies.push((path, record.destination.clone()));
+                    }
+                    db.set_content_hash(&path.to_string_lossy(), content_hash);
+                }
+                Ok(None) => {}
+                Err(e) => warn!("Could not hash {}: {}", path.display(), e),
+            }
+        }
+        hashing.finish(&format!("{} hashed", added.len()));
+    }
+    db.save()?;
+
+    say!(
+        "\n📚 Indexed {}: {} new files recorded, {} already known.",
+        root.display(),
+        added.len(),
+        report.files.len() - added.len()
+    );
+    if !changes.is_empty() {
+        say!(
+            "🔀 {} records followed renamed files; 🗑️  {} records of deleted files were dropped.",
+            changes.renamed.len(),
+            changes.removed.len()
+        );
+    }
+    for (path, original) in &copies {
+        say!("♊ {} has the same contents as {original}", path.display());
+    }
+    Ok(())
+}
+
+/// Pin a file, remove a pin, or (with neither) list pins.
+pub(super) fn cmd_pin(pin: Option<(PathBuf, String)>, remove: Option<&Path>) -> Result<()> {
+    let mut db = Database::open(&dirs_database())?;
+    if let Some((source, destination)) = pin {
+        if !source.is_file() {
+            bail!("Not a file: {}", source.display());
+        }
+        db.pin(&source, &destination)?;
+        db.save()?;
+        println!("📌 {} → {destination}", source.display());
+        return Ok(());
+    }
+    if let Some(source) = remove {
+        if !db.unpin(source) {
+            bail!("{} is not pinned", source.display());
+        }
+        db.save()?;
+        println!("Unpinned {}", source.display());
+        return Ok(());
+    }
+
+    if db.pins().is_empty() {
+        println!("No pinned files.");
+        return Ok(());
+    }
+    for pin in db.pins() {
+        let missing = if Path::new(&pin.source).exists() {
+            ""
+        } else {
+            " (source missing)"
+        };
+        println!("📌 {}{missing}\n   → {}", pin.source, pin.destination);
+    }
+    println!("\n{} pinned files.", db.pins().len());
+    Ok(())
+}
+
+pub(super) fn cmd_subtitles(
+    path: &Path,
+    strategy: &str,
+    execute: bool,
+    config: &AppConfig,
+) -> Result<()> {
+    let loose = subtitles::find_loose_subtitles(path);
+    if loose.is_empty() {
+        println!("No loose subtitle files found in {}", path.display());
+        return Ok(());
+    }
+
+    let db = Database::open(&dirs_database())?;
+    let plan = subtitles::plan_loose_subtitles(
+        &loose,
+        db.files(),
+        strategy,
+        &ParseOptions::from_config(config),
+    );
+
+    println!("\n📋 Subtitle plan ({} placed):\n", plan.actions.len());
+    for action in &plan.actions {
+        println!(
+            "  {} → {}",
+            action.source.display(),
+            action.destination.display()
+        );
+    }
+    if !plan.unplaced.is_empty() {
+        println!("\n⚠️  Not placed ({}):\n", plan.unplaced.len());
+        for (sub, reason) in &plan.unplaced {
+            println!("  {} — {}", sub.display(), reason);
+        }
+    }
+
+    if !execute {
+        println!("\nDry-run. Add --execute to apply.");
+        return Ok(());
+    }
+
+    let manifest = execute_and_record(&plan.actions, None, None, config)?;
+    println!(
+        "\n✅ Placed {} subtitles. Undo manifest saved.",
+        manifest.entries.len()
+    );
+    Ok(())
+}
+
+pub(super) fn cmd_enrich(options: &BackfillOptions, config: &AppConfig) -> Result<()> {
+    if config.tmdb.api_key.is_empty() {
+        bail!("No TMDb API key: set `tmdb.api_key` in config or TMDB_API_KEY");
+    }
+    let mut db = Database::open(&dirs_database())?;
+    let pending = backfill::pending_records(&db, options.refresh).len();
+    let total = options.limit.map_or(pending, |l| l.min(pending));
+    if total == 0 {
+        println!("Every movie in the library already has details.");
+        return Ok(());
+    }
+
+    println!(
+        "Fetching TMDb details for {total} movies (≤{}/s)...",
+        config.tmdb.requests_per_second
+    );
+    let client = TmdbClient::from_settings(&config.tmdb);
+    let mut progress = progress("Enriching", Some(total as u64));
+    let mut reported = 0;
+    let report = backfill::backfill_details(
+        &mut db,
+        options,
+        backfill::tmdb_lookup(&client, &config.tmdb),
+        |r| {
+            let done = r.enriched.len() + r.not_found.len() + r.failed.len();
+            progress.advance((done - reported) as u64);
+            reported = done;
+        },
+    )?;
+    progress.finish(&format!("{reported} movies"));
+
+    println!(
+        "\n✅ Enriched {} movies; {} without a confident match, {} failed.",
+        report.enriched.len(),
+        report.not_found.len(),
+        report.failed.len()
+    );
+    for path in &report.not_found {
+        println!("  no match: {path}");
+    }
+    for (path, err) in &report.failed {
+        println!("  failed:   {path} — {err}");
+    }
+
+    if !config.export.collections_dir.is_empty() {
+        let dir = Path::new(&config.export.collections_dir);
+        let found = collections::collections(&db, config.export.min_collection_size);
+        let report = collections::export(dir, &found, config.export.collections_format)?;
+        println!(
+            "Collections in {}: {} updated, {} removed.",
+            dir.display(),
+            report.written.len(),
+            report.removed.len()
+        );
+    }
+    Ok(())
+}
+
+pub(super) fn cmd_collections(
+    out: Option<PathBuf>,
+    format: Option<ExportFormat>,
+    min_size: Option<usize>,
+    execute: bool,
+    config: &AppConfig,
+) -> Result<()> {
+    let out = out.or_else(|| {
+        let dir = &config.export.collections_dir;
+        (!dir.is_empty()).then(|| PathBuf::from(dir))
+    });
+    let format = format.unwrap_or(config.export.collections_format);
+    let min_size = min_size.unwrap_or(config.export.min_collection_size);
+
+    let db = Database::open(&dirs_database())?;
+    let found = collections::collections(&db, min_size);
+    if found.is_empty() {
+        println!("No collections with {min_size}+ movies. Run `plex-org enrich` first?");
+        return Ok(());
+    }
+    for collection in &found {
+        println!("{} ({} movies)", collection.name, collection.movies.len());
+        for movie in &collection.movies {
+            let year = movie.year.map(|y| format!(" ({y})")).unwrap_or_default();
+            println!("  {}{year}", movie.title);
+        }
+    }
+
+    let Some(out) = out else {
+        println!("\nPass --out (or set `export.collections_dir`) to write {format} listings.");
+        return Ok(());
+    };
+    if !execute {
+        println!(
+            "\nDry-run: would write {} {format} listings to {}. Add --execute to apply.",
+            found.len(),
+            out.display()
+        );
+        return Ok(());
+    }
+    let report = collections::export(&out, &found, format)?;
+    println!(
+        "\n✅ {} written, {} unchanged, {} removed in {}.",
+        report.written.len(),
+        report.unchanged,
+        report.removed.len(),
+        out.display()
+    );
+    Ok(())
+}
+
+/// The earliest organize time `list --since` covers.
+fn list_cutoff(since: &str) -> Result<DateTime<Utc>> {
+    let window = utils::parse_duration(since)
+        .with_context(|| format!("Invalid duration: {since:?} (try 7d, 24h)"))?;
+    chrono::Duration::from_std(window)
+        .ok()
+        .and_then(|window| Utc::now().checked_sub_signed(window))
+        .with_context(|| format!("{since:?} reaches back further than dates go"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_cutoff_out_of_range() {
+        let week = list_cutoff("1w").unwrap();
+        assert!(Utc::now() - week >= chrono::Duration::days(7));
+        assert!(list_cutoff("20000000w").is_err());
+        assert!(list_cutoff("1000000000000w").is_err());
+    }
+}
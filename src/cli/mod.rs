@@ -0,0 +1,322 @@
+//! CLI command dispatch — thin layer over library functions.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use anyhow::{bail, Context, Result};
+use chrono::Local;
+use tracing::{info, warn};
+
+use plex_media_organizer::backfill::BackfillOptions;
+use plex_media_organizer::chaos;
+use plex_media_organizer::config::{AppConfig, ConfigSources};
+use plex_media_organizer::database::cache::{self, ResponseCache};
+use plex_media_organizer::pacing;
+use plex_media_organizer::providers::cassette::{self, Cassette};
+use plex_media_organizer::staging;
+
+mod args;
+mod budget;
+mod cache;
+mod corrections;
+mod debug;
+mod defaults;
+mod doctor;
+mod history;
+mod library;
+mod offline_db;
+mod organize;
+mod output;
+mod overrides;
+mod pipeline;
+mod queue;
+mod scan;
+mod schema;
+mod service;
+mod view;
+
+use args::strategies;
+pub use args::{Cli, Command};
+use cache::cmd_cache;
+use corrections::{cmd_corrections, cmd_match};
+use debug::cmd_debug_parse;
+use doctor::cmd_doctor;
+use history::{cmd_history, cmd_undo};
+use library::{
+    cmd_collections, cmd_enrich, cmd_health, cmd_library, cmd_list, cmd_pin, cmd_stats,
+    cmd_subtitles,
+};
+use offline_db::cmd_offline_db;
+use organize::{
+    cmd_apply_plan, cmd_organize, cmd_plan, cmd_promote, cmd_survey, guard_destination,
+};
+use output::{progress, say, truncate, ProgressMode};
+use pipeline::{execute_and_record, mark_output, print_skipped_dirs};
+use queue::cmd_queue;
+use scan::cmd_scan;
+use schema::cmd_schema;
+use service::{cmd_service, cmd_watch};
+use view::cmd_view;
+
+// ── Command dispatch ───────────────────────────────────────────────────────
+
+pub fn run(cli: Cli) -> Result<ExitCode> {
+    for flag in &cli.defaults.used {
+        info!("{flag} (from [cli_defaults])");
+    }
+    for ignored in &cli.defaults.ignored {
+        warn!("Ignoring cli_defaults.{ignored}");
+    }
+    if let Some(spec) = cli.chaos.clone() {
+        chaos::install(spec);
+    }
+    let mode = if cli.quiet {
+        ProgressMode::Quiet
+    } else {
+        cli.progress
+    };
+    output::set_mode(mode, cli.output);
+    // `doctor` reports a config that does not load instead of failing on it.
+    let mut config_error = None;
+    let (mut config, sources) = match cli.config.as_deref().filter(|p| p.exists()) {
+        Some(path) => match AppConfig::load_traced(path) {
+            Ok(loaded) => loaded,
+            Err(e) if matches!(cli.command, Command::Doctor { .. }) => {
+                config_error = Some(format!("{e:#}"));
+                Default::default()
+            }
+            Err(e) => return Err(e),
+        },
+        None => Default::default(),
+    };
+    if config.tmdb.api_key.is_empty() {
+        if let Ok(key) = std::env::var("TMDB_API_KEY") {
+            config.tmdb.api_key = key;
+        }
+    }
+    if config.omdb.api_key.is_empty() {
+        if let Ok(key) = std::env::var("OMDB_API_KEY") {
+            config.omdb.api_key = key;
+        }
+    }
+    if config.providers.offline_db.is_empty() {
+        let path = dirs_data().join("imdb_movies.tsv");
+        config.providers.offline_db = path.to_string_lossy().into_owned();
+    }
+    if let Some(path) = &cli.tmdb_replay {
+        cassette::install(Cassette::replay(path)?);
+        // Replay needs no key, but TMDb matching only runs with one.
+        if config.tmdb.api_key.is_empty() {
+            config.tmdb.api_key = "replay".to_string();
+        }
+    } else if let Some(path) = &cli.tmdb_record {
+        if config.tmdb.api_key.is_empty() {
+            warn!("No TMDb API key is set; there will be nothing to record");
+        }
+        cassette::install(Cassette::record(path));
+    }
+    overrides::apply(&cli.command, &mut config)?;
+    // A recording must see TMDb's answers, and a replay gives its own.
+    let cassette = cli.tmdb_record.is_some() || cli.tmdb_replay.is_some();
+    if config.tmdb.cache_ttl_hours > 0 && !cassette {
+        match ResponseCache::open(&dirs_cache(), &config.tmdb) {
+            Ok(opened) => cache::install(opened),
+            Err(e) => warn!("Not using the TMDb cache: {e:#}"),
+        }
+    }
+    config.low_impact |= cli.low_impact;
+    pacing::apply_low_impact(&mut config);
+    if config.low_impact {
+        if let Err(e) = pacing::lower_priority() {
+            warn!("Could not lower process priority: {e}");
+        }
+    }
+
+    let result = match cli.command {
+        Command::Scan { path, resume, .. } => cmd_scan(&path, resume, &config),
+        Command::Survey {
+            path,
+            strategy,
+            json,
+        } => cmd_survey(&path, &strategy, json, &config),
+        Command::Plan {
+            path,
+            dest,
+            strategy,
+            plan_out,
+            allow_nested,
+            stage,
+            ..
+        } => cmd_plan(
+            &path,
+            &dest,
+            &strategy,
+            plan_out.as_deref(),
+            allow_nested,
+            stage,
+            &config,
+        ),
+        Command::Organize {
+            apply_plan: Some(plan_path),
+            allow_outside_library,
+            execute,
+            ..
+        } => cmd_apply_plan(&plan_path, allow_outside_library, execute, &config),
+        Command::Apply {
+            plan,
+            allow_outside_library,
+            execute,
+        } => {
+            warn!("`apply` is deprecated; use `organize --apply-plan`");
+            cmd_apply_plan(&plan, allow_outside_library, execute, &config)
+        }
+        Command::Organize {
+            path,
+            dest,
+            strategy,
+            plan_out,
+            stage,
+            allow_nested,
+            allow_outside_library,
+            execute,
+            ..
+        } => {
+            let path = path.context("a source directory is required")?;
+            let dest = match dest {
+                Some(d) => d,
+                None if stage => staging::bucket_dir(&staging_dir(&config)?, &Local::now()),
+                None => bail!("--dest is required unless --stage or --apply-plan is given"),
+            };
+            if plan_out.is_some() {
+                warn!("`organize --plan-out` is deprecated; use `plan --plan-out`");
+            }
+            let allow = (allow_nested, allow_outside_library);
+            let plan_out = plan_out.as_deref();
+            cmd_organize(&path, &dest, &strategy, allow, execute, plan_out, &config)
+        }
+        Command::Promote {
+            dest,
+            older_than,
+            allow_outside_library,
+            execute,
+        } => cmd_promote(
+            dest,
+            older_than.as_deref(),
+            allow_outside_library,
+            execute,
+            &config,
+        ),
+        Command::List { since, all, json } => cmd_list(&since, all, json),
+        Command::Stats => cmd_stats(),
+        Command::Library { action } => cmd_library(action, &config),
+        Command::Health {
+            path,
+            fix,
+            json,
+            execute,
+        } => cmd_health(path, fix, json, execute, &config),
+        Command::Pin {
+            source,
+            destination,
+            list: _,
+            remove,
+        } => cmd_pin(source.zip(destination), remove.as_deref()),
+        Command::Subtitles {
+            path,
+            strategy,
+            execute,
+        } => cmd_subtitles(&path, &strategy, execute, &config),
+        Command::Enrich {
+            refresh,
+            limit,
+            batch_size,
+        } => {
+            let options = BackfillOptions {
+                refresh,
+                limit,
+                batch_size,
+            };
+            cmd_enrich(&options, &config)
+        }
+        Command::Collections {
+            out,
+            format,
+            min_size,
+            execute,
+        } => cmd_collections(out, format, min_size, execute, &config),
+        Command::Match {
+            file,
+            tmdb_id,
+            title,
+            year,
+        } => cmd_match(&file, tmdb_id, title, year, &config),
+        Command::Corrections { action } => cmd_corrections(action, &config),
+        Command::Queue { action } => cmd_queue(action, &config),
+        Command::View { action } => cmd_view(action, &config),
+        Command::Watch { args, execute } => cmd_watch(&args, execute, &config),
+        Command::Service { action } => cmd_service(action, cli.config.as_deref(), &config),
+        Command::DebugParse { names, json } => cmd_debug_parse(&names, json, &config),
+        Command::Doctor { destinations } => {
+            cmd_doctor(&config, cli.config.clone(), config_error, destinations)
+        }
+        Command::Undo { last, operation_id } => cmd_undo(last, operation_id.as_deref()),
+        Command::History => cmd_history(),
+        Command::Cache { action } => cmd_cache(action, &config),
+        Command::OfflineDb { action } => cmd_offline_db(action, &config),
+        Command::Schema { action } => cmd_schema(action),
+        Command::Config => cmd_config(&config, &sources),
+    };
+    // A recording is kept even when the command failed; that may be the bug.
+    if let Some((path, count)) = cassette::finish()? {
+        eprintln!("📼 Recorded {count} TMDb responses to {}", path.display());
+    }
+    match cache::finish() {
+        Ok(Some((hits, misses))) if hits > 0 => {
+            info!("{hits} TMDb requests answered from the cache, {misses} sent")
+        }
+        Ok(_) => {}
+        Err(e) => warn!("Could not save the TMDb cache: {e:#}"),
+    }
+    result.map(|()| budget::exit_code())
+}
+
+// ── Command implementations ────────────────────────────────────────────────
+
+fn cmd_config(config: &AppConfig, sources: &ConfigSources) -> Result<()> {
+    let toml_str = toml::to_string_pretty(config)?;
+    println!("{}", sources.annotate(&toml_str));
+    Ok(())
+}
+
+// ── Helpers ─────────────────────────────────────────────────────────────────
+
+/// Configured staging root, required by `--stage` and `promote`.
+fn staging_dir(config: &AppConfig) -> Result<PathBuf> {
+    if config.organize.staging_dir.is_empty() {
+        bail!("No staging directory configured: set `organize.staging_dir` in config");
+    }
+    Ok(PathBuf::from(&config.organize.staging_dir))
+}
+
+/// Application data directory: ~/.plex-organizer/
+fn dirs_data() -> PathBuf {
+    let home = std::env::var("HOME")
+        .or_else(|_| std::env::var("USERPROFILE"))
+        .unwrap_or_else(|_| ".".to_string());
+    PathBuf::from(home).join(".plex-organizer")
+}
+
+/// Default undo directory: ~/.plex-organizer/undo/
+fn dirs_undo() -> PathBuf {
+    dirs_data().join("undo")
+}
+
+/// Default library database: ~/.plex-organizer/library.json
+fn dirs_database() -> PathBuf {
+    dirs_data().join("library.json")
+}
+
+/// TMDb responses kept between runs: ~/.plex-organizer/tmdb_cache.json
+fn dirs_cache() -> PathBuf {
+    dirs_data().join("tmdb_cache.json")
+}
@@ -0,0 +1,38 @@
+//! `offline-db`: the IMDb dataset used for matching without a network.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use clap::Subcommand;
+
+use plex_media_organizer::config::AppConfig;
+use plex_media_organizer::providers::imdb;
+
+use super::{progress, say};
+
+#[derive(Subcommand)]
+pub enum OfflineDbAction {
+    /// Load the movies of IMDb's title.basics.tsv.gz (from
+    /// datasets.imdbws.com), replacing any earlier import.
+    Import {
+        /// The downloaded `title.basics.tsv.gz` (or unpacked `.tsv`).
+        path: PathBuf,
+    },
+}
+
+pub(super) fn cmd_offline_db(action: OfflineDbAction, config: &AppConfig) -> Result<()> {
+    match action {
+        OfflineDbAction::Import { path } => {
+            let dest = Path::new(&config.providers.offline_db);
+            let mut progress = progress(&format!("Importing {}", path.display()), None);
+            let kept = imdb::import(&path, dest)?;
+            progress.finish(&format!("{kept} movies"));
+            say!(
+                "💾 Saved {kept} movies to {}; titles are matched offline when \
+                 no online provider has a match.",
+                dest.display()
+            );
+        }
+    }
+    Ok(())
+}
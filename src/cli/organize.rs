@@ -0,0 +1,524 @@
+//! Survey, plan, organize, and promote.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use serde::Serialize;
+
+use plex_media_organizer::config::AppConfig;
+use plex_media_organizer::database::Database;
+use plex_media_organizer::models::{EnrichedMedia, OrganizeAction, SavedPlan, UndoManifest};
+use plex_media_organizer::organizer::{self, DestinationCheck, DestinationPolicy};
+use plex_media_organizer::parser::ParseOptions;
+use plex_media_organizer::staging;
+use plex_media_organizer::survey;
+use plex_media_organizer::utils;
+
+use super::output::{self, print_rows};
+use super::pipeline::{
+    hold_for_review, notify_run, parse_enrich, plan_run, queue_for_review, scan_parse_enrich,
+    scan_source, update_plex,
+};
+use super::{
+    budget, dirs_data, dirs_database, execute_and_record, mark_output, progress, say, scan,
+    staging_dir, truncate,
+};
+
+/// Refuse a protected destination root. One outside every configured
+/// library root is refused too when files will be written there, unless
+/// `allow_outside` confirms it.
+pub(super) fn guard_destination(
+    dest: &Path,
+    writes: bool,
+    allow_outside: bool,
+    config: &AppConfig,
+) -> Result<()> {
+    let policy = DestinationPolicy::from_config(config, &dirs_data());
+    match organizer::check_destination(dest, &policy) {
+        DestinationCheck::Allowed => Ok(()),
+        DestinationCheck::Protected(path) => bail!(
+            "Refusing to organize into {}: {} is a protected location. \
+             Check the destination for typos.",
+            dest.display(),
+            path.display()
+        ),
+        DestinationCheck::OutsideLibraryRoots if !writes || allow_outside => Ok(()),
+        DestinationCheck::OutsideLibraryRoots => bail!(
+            "{} is outside every configured library root (`destination`, \
+             `staging_dir`, `view_dir`, `library_roots`). Pass \
+             --allow-outside-library if that is intended.",
+            dest.display()
+        ),
+    }
+}
+
+pub(super) fn cmd_survey(
+    path: &Path,
+    strategy: &str,
+    json: bool,
+    config: &AppConfig,
+) -> Result<()> {
+    let mut progress = progress(&format!("Surveying {}", path.display()), None);
+    let survey = survey::survey(path, &ParseOptions::from_config(config))?;
+    progress.finish(&format!("{} files", survey.total.files));
+    let db = Database::open(&dirs_database())?;
+    let throughput = db.throughput(strategy);
+    let estimate = throughput.map(|t| t.estimate(survey.media.files, survey.media.bytes));
+
+    if json {
+        let mut value = serde_json::to_value(&survey)?;
+        value["strategy"] = strategy.into();
+        value["estimated_secs"] = estimate.map(|d| d.as_secs()).into();
+        println!("{}", serde_json::to_string_pretty(&value)?);
+        return Ok(());
+    }
+
+    let size = utils::format_size;
+    println!(
+        "📊 {}: {} files, {} ({} media files, {})",
+        path.display(),
+        survey.total.files,
+        size(survey.total.bytes),
+        survey.media.files,
+        size(survey.media.bytes)
+    );
+
+    println!(
+        "
+By extension:"
+    );
+    let mut extensions: Vec<_> = survey.by_extension.iter().collect();
+    extensions.sort_by_key(|(_, tally)| std::cmp::Reverse(tally.bytes));
+    for (ext, tally) in extensions {
+        let ext = if ext.is_empty() { "(none)" } else { ext };
+        println!(
+            "  {ext:<10} {:>7} files  {:>10}",
+            tally.files,
+            size(tally.bytes)
+        );
+    }
+
+    println!(
+        "
+File sizes:"
+    );
+    for (label, count) in &survey.size_buckets {
+        println!("  {label:<12} {count:>7}");
+    }
+
+    println!(
+        "
+Folder depth:"
+    );
+    for (depth, count) in &survey.depths {
+        println!("  {depth:<12} {count:>7}");
+    }
+
+    if !survey.languages.is_empty() {
+        let languages: Vec<_> = survey
+            .languages
+            .iter()
+            .map(|(code, n)| format!("{code} {n}"))
+            .collect();
+        println!(
+            "
+Title languages: {}",
+            languages.join(", ")
+        );
+    }
+    if survey.unreadable > 0 {
+        println!(
+            "
+⚠️  {} entries could not be read.",
+            survey.unreadable
+        );
+    }
+
+    match (estimate, throughput) {
+        (Some(estimate), Some(t)) => println!(
+            "
+⏱️  Estimated {strategy} time: {} (from {} past run(s))",
+            utils::format_duration(estimate),
+            t.runs
+        ),
+        _ => println!(
+            "
+⏱️  No {strategy} history yet; the estimate appears after a first run."
+        ),
+    }
+    Ok(())
+}
+
+/// Where `plan --stage` stops the pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum PreviewStage {
+    /// Parse filenames only; no TMDb requests.
+    Parse,
+    /// Parse and match against TMDb; no destination paths.
+    Match,
+    /// The full plan.
+    #[default]
+    Plan,
+}
+
+pub(super) fn cmd_plan(
+    path: &Path,
+    dest: &Path,
+    strategy: &str,
+    plan_out: Option<&Path>,
+    allow_nested: bool,
+    stage: PreviewStage,
+    config: &AppConfig,
+) -> Result<()> {
+    if stage != PreviewStage::Plan && plan_out.is_some() {
+        bail!("--plan-out needs the full plan; drop --stage or use --stage plan");
+    }
+    match stage {
+        PreviewStage::Parse => {
+            let files = scan_source(path, dest, allow_nested, config)?;
+            if files.is_empty() {
+                say!("No media files found.");
+                return Ok(());
+            }
+            scan::print_parsed(&files, config)?;
+            say!("\nStopped after parsing; no TMDb requests were made.");
+            return Ok(());
+        }
+        PreviewStage::Match => {
+            let files = scan_source(path, dest, allow_nested, config)?;
+            let items = parse_enrich(files, false, config)?;
+            if items.is_empty() {
+                say!("No media files found.");
+                return Ok(());
+            }
+            print_matches(&items)?;
+            say!("\nStopped after matching; no destinations were planned.");
+            return Ok(());
+        }
+        PreviewStage::Plan => {}
+    }
+    let items = scan_parse_enrich(path, dest, allow_nested, false, config)?;
+
+    if items.is_empty() {
+        say!("No media files found.");
+        return Ok(());
+    }
+    let (items, _held) = hold_for_review(items);
+
+    let actions = plan_run(&items, dest, config, strategy)?;
+    print_actions(&actions, config)?;
+
+    if let Some(plan_path) = plan_out {
+        return export_plan(plan_path, dest, actions);
+    }
+    say!("\nDry-run complete. Use `organize --execute` to apply.");
+    Ok(())
+}
+
+/// Save a dry-run plan, fingerprinting its sources so `--apply-plan` can tell
+/// which changed before it is carried out.
+fn export_plan(plan_path: &Path, dest: &Path, mut actions: Vec<OrganizeAction>) -> Result<()> {
+    organizer::fingerprint_actions(&mut actions);
+    organizer::save_plan(plan_path, &SavedPlan::new(dest.to_path_buf(), actions))?;
+    say!("\nPlan saved to {}.", plan_path.display());
+    say!(
+        "Review it, then use `organize --apply-plan {} --execute` to carry it out.",
+        plan_path.display()
+    );
+    Ok(())
+}
+
+/// Columns of planned actions in `--output csv`.
+const ACTION_COLUMNS: &[&str] = &[
+    "source",
+    "destination",
+    "strategy",
+    "media_type",
+    "title",
+    "year",
+    "confidence",
+    "tmdb_id",
+    "second_feature",
+];
+
+/// A plan: listed, or as rows for `--output json|csv` (the `plan`
+/// schema's actions).
+fn print_actions(actions: &[OrganizeAction], config: &AppConfig) -> Result<()> {
+    if output::structured() {
+        return print_rows(actions, ACTION_COLUMNS);
+    }
+    println!("\n📋 Plan ({} actions):\n", actions.len());
+    for action in actions {
+        print_action(action);
+    }
+    print_space_needs(actions, config);
+    Ok(())
+}
+
+/// What the plan needs on each destination filesystem, and whether it fits.
+fn print_space_needs(actions: &[OrganizeAction], config: &AppConfig) {
+    let reserve = config.organize.space_reserve_mb.saturating_mul(1 << 20);
+    for need in organizer::space_needs(actions) {
+        let free = need
+            .available
+            .map(|free| format!(" ({} free)", utils::format_size(free)))
+            .unwrap_or_default();
+        println!(
+            "\n💾 Needs {} on {}{free}",
+            utils::format_size(need.needed),
+            need.path.display()
+        );
+        if !need.fits(reserve) {
+            println!(
+                "   ⚠️  Not enough space: --execute will refuse this run \
+                 (organize.space_reserve_mb keeps {} free)",
+                utils::format_size(reserve)
+            );
+        }
+    }
+}
+
+/// One plan line, plus the second movie of a double feature and the
+/// copies an upgrade replaces. Files that take up space at the
+/// destination show how much.
+fn print_action(action: &OrganizeAction) {
+    let needed = organizer::bytes_needed(action);
+    let size = if needed > 0 {
+        format!(" (+{})", utils::format_size(needed))
+    } else {
+        String::new()
+    };
+    println!(
+        "  {} → {}{size}",
+        action.source.display(),
+        action.destination.display()
+    );
+    if let Some(second) = &action.second_feature {
+        println!("      🎞️  double feature: also {second}");
+    }
+    for copy in &action.replaces {
+        println!(
+            "      ♻️  replaces {} ({})",
+            copy.path.display(),
+            copy.quality
+        );
+    }
+}
+
+/// One match of `plan --stage match`, for `--output json|csv`.
+#[derive(Serialize)]
+struct MatchRow<'a> {
+    source: &'a Path,
+    title: &'a str,
+    year: Option<i32>,
+    confidence: f64,
+    tmdb_id: Option<u64>,
+    matched_by: &'a str,
+    needs_review: bool,
+}
+
+/// The match table for `plan --stage match`.
+fn print_matches(items: &[(PathBuf, EnrichedMedia)]) -> Result<()> {
+    if output::structured() {
+        let rows: Vec<_> = items
+            .iter()
+            .map(|(source, enriched)| MatchRow {
+                source,
+                title: enriched.best_title(),
+                year: enriched.best_year(),
+                confidence: enriched.confidence,
+                tmdb_id: enriched.tmdb_id(),
+                matched_by: enriched.enrichment_source.as_deref().unwrap_or("parser"),
+                needs_review: enriched.needs_review,
+            })
+            .collect();
+        let columns = [
+            "source",
+            "title",
+            "year",
+            "confidence",
+            "tmdb_id",
+            "matched_by",
+            "needs_review",
+        ];
+        return print_rows(&rows, &columns);
+    }
+    println!(
+        "{:<50} {:<40} {:<6} {:<5} {:<10} {:<12}",
+        "Filename", "Match", "Year", "Conf", "TMDb", "Source"
+    );
+    println!("{}", "-".repeat(128));
+    for (source, enriched) in items {
+        let name = source
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let review = if enriched.needs_review { " ⏸" } else { "" };
+        println!(
+            "{:<50} {:<40} {:<6} {:<5} {:<10} {:<12}{review}",
+            truncate(&name, 48),
+            truncate(enriched.best_title(), 38),
+            enriched
+                .best_year()
+                .map(|y| y.to_string())
+                .unwrap_or_default(),
+            format!("{:.0}%", enriched.confidence),
+            enriched
+                .tmdb_id()
+                .map(|id| id.to_string())
+                .unwrap_or_default(),
+            enriched.enrichment_source.as_deref().unwrap_or("parser"),
+        );
+    }
+    let held = items.iter().filter(|(_, e)| e.needs_review).count();
+    println!(
+        "\n{} files matched; {held} below the review threshold (⏸).",
+        items.len()
+    );
+    Ok(())
+}
+
+pub(super) fn cmd_organize(
+    path: &Path,
+    dest: &Path,
+    strategy: &str,
+    (allow_nested, allow_outside): (bool, bool),
+    execute: bool,
+    plan_out: Option<&Path>,
+    config: &AppConfig,
+) -> Result<()> {
+    if execute {
+        guard_destination(dest, true, allow_outside, config)?;
+    }
+    let items = scan_parse_enrich(path, dest, allow_nested, execute, config)?;
+
+    if items.is_empty() {
+        say!("No media files found.");
+        return Ok(());
+    }
+    let (items, held) = hold_for_review(items);
+
+    let actions = plan_run(&items, dest, config, strategy)?;
+
+    if !execute {
+        print_actions(&actions, config)?;
+        if let Some(plan_path) = plan_out {
+            return export_plan(plan_path, dest, actions);
+        }
+        say!("\nDry-run. Add --execute to apply.");
+        return Ok(());
+    }
+
+    queue_for_review(&held)?;
+    let result = execute_and_record(&actions, Some(dest), Some(path), config);
+    notify_run(path, dest, actions.len(), &held, result.as_ref(), config);
+    let manifest = result?;
+    mark_output(dest);
+    budget::save_remaining(&actions, &manifest, dest)?;
+    say!(
+        "\n✅ Organized {} files. Undo manifest saved.",
+        manifest.entries.len()
+    );
+    update_plex(&manifest, config);
+    Ok(())
+}
+
+pub(super) fn cmd_apply_plan(
+    plan_path: &Path,
+    allow_outside: bool,
+    execute: bool,
+    config: &AppConfig,
+) -> Result<()> {
+    let plan = organizer::load_plan(plan_path)?;
+    guard_destination(&plan.dest_root, execute, allow_outside, config)?;
+    let (valid, drifted) = organizer::validate_plan(&plan.actions);
+
+    say!(
+        "\n📋 Saved plan from {} ({} actions, {} still valid):\n",
+        plan.created_at,
+        plan.actions.len(),
+        valid.len()
+    );
+    if output::structured() {
+        print_rows(&valid, ACTION_COLUMNS)?;
+    } else {
+        valid.iter().for_each(print_action);
+    }
+
+    if !drifted.is_empty() {
+        say!("\n⚠️  Drift since planning ({} skipped):\n", drifted.len());
+        for d in &drifted {
+            say!("  {} — {}", d.action.source.display(), d.reason);
+        }
+    }
+
+    if !execute {
+        say!("\nDry-run. Add --execute to apply.");
+        return Ok(());
+    }
+
+    let manifest = execute_and_record(&valid, Some(&plan.dest_root), None, config)?;
+    mark_output(&plan.dest_root);
+    budget::save_remaining(&valid, &manifest, &plan.dest_root)?;
+    say!(
+        "\n✅ Organized {} files ({} skipped due to drift). Undo manifest saved.",
+        manifest.entries.len(),
+        drifted.len()
+    );
+    update_plex(&manifest, config);
+    Ok(())
+}
+
+pub(super) fn cmd_promote(
+    dest: Option<PathBuf>,
+    older_than: Option<&str>,
+    allow_outside_library: bool,
+    execute: bool,
+    config: &AppConfig,
+) -> Result<()> {
+    let staging_dir = staging_dir(config)?;
+    let library_root = match dest {
+        Some(d) => d,
+        None if !config.destination.is_empty() => PathBuf::from(&config.destination),
+        None => bail!("No library root: pass --dest or set `destination` in config"),
+    };
+    guard_destination(&library_root, execute, allow_outside_library, config)?;
+    let min_age = match older_than {
+        Some(s) => utils::parse_duration(s)
+            .with_context(|| format!("Invalid duration: {s:?} (try 7d, 36h)"))?,
+        None => Duration::from_secs(config.organize.promote_after_days * 86_400),
+    };
+
+    let actions = staging::plan_promotion(
+        &staging_dir,
+        &library_root,
+        min_age,
+        SystemTime::now(),
+        config,
+    )?;
+    if actions.is_empty() {
+        say!("Nothing in staging is ready for promotion.");
+        return Ok(());
+    }
+
+    if !execute {
+        say!("\n📋 Promotion plan ({} files):\n", actions.len());
+        for action in &actions {
+            print_action(action);
+        }
+        say!("\nDry-run. Add --execute to apply.");
+        return Ok(());
+    }
+
+    let manifest = execute_and_record(&actions, Some(&library_root), None, config)?;
+    mark_output(&library_root);
+    staging::prune_empty_dirs(&staging_dir);
+    say!(
+        "\n✅ Promoted {} files into {}. Undo manifest saved.",
+        manifest.entries.len(),
+        library_root.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,380 @@
+//! Progress reporting and result formats.
+//!
+//! Commands ask [`progress`] for a reporter and drive it; how progress is
+//! shown is decided once per process by the global `--progress` (or
+//! `--quiet`) flag. Reporters write to stderr so stdout stays clean for
+//! tables and `--json` output.
+//!
+//! The global `--output json|csv` turns the results of `scan`, `plan`, and
+//! a dry-run `organize` into rows for scripts ([`print_rows`]); the lines
+//! around them go through [`say!`], which moves them to stderr then.
+
+use std::fmt;
+use std::io::{self, IsTerminal, Write};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use anyhow::bail;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use plex_media_organizer::utils;
+
+/// How progress is shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProgressMode {
+    /// Bars and spinners on a terminal, nothing otherwise.
+    #[default]
+    Auto,
+    /// Bars and spinners, even when stderr is not a terminal.
+    Bar,
+    /// Nothing.
+    Quiet,
+    /// One JSON event per line (`start`, `progress`, `finish`).
+    Json,
+}
+
+impl FromStr for ProgressMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "auto" => Ok(Self::Auto),
+            "bar" => Ok(Self::Bar),
+            "quiet" => Ok(Self::Quiet),
+            "json" => Ok(Self::Json),
+            _ => bail!("Unknown progress mode {s:?} (expected auto, bar, quiet, json)"),
+        }
+    }
+}
+
+impl fmt::Display for ProgressMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Auto => "auto",
+            Self::Bar => "bar",
+            Self::Quiet => "quiet",
+            Self::Json => "json",
+        })
+    }
+}
+
+/// How results are printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// Tables and messages for people.
+    #[default]
+    Pretty,
+    /// One JSON array of results.
+    Json,
+    /// A header line and one CSV line per result.
+    Csv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "pretty" => Ok(Self::Pretty),
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            _ => bail!("Unknown output format {s:?} (expected pretty, json, csv)"),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Pretty => "pretty",
+            Self::Json => "json",
+            Self::Csv => "csv",
+        })
+    }
+}
+
+static MODE: OnceLock<ProgressMode> = OnceLock::new();
+static FORMAT: OnceLock<OutputFormat> = OnceLock::new();
+
+/// Choose how progress is shown and results are printed for the rest of
+/// the process.
+pub(super) fn set_mode(mode: ProgressMode, format: OutputFormat) {
+    let _ = MODE.set(mode);
+    let _ = FORMAT.set(format);
+}
+
+/// How results are printed.
+pub(super) fn format() -> OutputFormat {
+    FORMAT.get().copied().unwrap_or_default()
+}
+
+/// Whether results are printed for scripts rather than people.
+pub(super) fn structured() -> bool {
+    format() != OutputFormat::Pretty
+}
+
+/// `println!` for lines around the results: on stderr when stdout carries
+/// `--output json` or `csv`.
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if $crate::cli::output::structured() {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+pub(super) use say;
+
+/// Print `rows` in the `--output` format: a JSON array, or CSV with the
+/// `columns` of each row's JSON form. Nothing in the pretty format, which
+/// each command prints its own way.
+pub(super) fn print_rows<T: Serialize>(rows: &[T], columns: &[&str]) -> anyhow::Result<()> {
+    match format() {
+        OutputFormat::Pretty => {}
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(rows)?),
+        OutputFormat::Csv => {
+            println!("{}", utils::csv_row(columns));
+            for row in rows {
+                let row = serde_json::to_value(row)?;
+                let fields: Vec<String> = columns
+                    .iter()
+                    .map(|column| match &row[*column] {
+                        Value::Null => String::new(),
+                        Value::String(s) => s.clone(),
+                        other => other.to_string(),
+                    })
+                    .collect();
+                println!("{}", utils::csv_row(&fields));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Progress of one long operation.
+pub(super) trait ProgressReporter {
+    /// `n` more units are done.
+    fn advance(&mut self, n: u64);
+    /// The operation is over; `summary` describes the outcome.
+    fn finish(&mut self, summary: &str);
+}
+
+/// A reporter for `task`: a bar when `total` is known, a spinner otherwise.
+pub(super) fn progress(task: &str, total: Option<u64>) -> Box<dyn ProgressReporter> {
+    let mode = MODE.get().copied().unwrap_or_default();
+    let draw = match mode {
+        ProgressMode::Quiet => false,
+        ProgressMode::Json => return Box::new(JsonEvents::start(task, total)),
+        ProgressMode::Auto => io::stderr().is_terminal(),
+        ProgressMode::Bar => true,
+    };
+    match (draw, total) {
+        (false, _) => Box::new(Quiet),
+        (true, Some(total)) => Box::new(Bar::new(task, total)),
+        (true, None) => Box::new(Spinner::start(task)),
+    }
+}
+
+/// Shows nothing.
+struct Quiet;
+
+impl ProgressReporter for Quiet {
+    fn advance(&mut self, _: u64) {}
+    fn finish(&mut self, _: &str) {}
+}
+
+const BAR_WIDTH: usize = 30;
+
+/// `Matching [#########-----------] 12/40`, redrawn in place.
+struct Bar {
+    task: String,
+    done: u64,
+    total: u64,
+    finished: bool,
+}
+
+impl Bar {
+    fn new(task: &str, total: u64) -> Self {
+        let bar = Self {
+            task: task.to_string(),
+            done: 0,
+            total,
+            finished: false,
+        };
+        bar.draw();
+        bar
+    }
+
+    fn draw(&self) {
+        let filled = match self.total {
+            0 => BAR_WIDTH,
+            total => (self.done.min(total) as usize * BAR_WIDTH) / total as usize,
+        };
+        eprint!(
+            "\r\x1b[2K{} [{}{}] {}/{}",
+            self.task,
+            "#".repeat(filled),
+            "-".repeat(BAR_WIDTH - filled),
+            self.done,
+            self.total
+        );
+        let _ = io::stderr().flush();
+    }
+}
+
+impl ProgressReporter for Bar {
+    fn advance(&mut self, n: u64) {
+        self.done += n;
+        self.draw();
+    }
+
+    fn finish(&mut self, summary: &str) {
+        self.finished = true;
+        eprintln!("\r\x1b[2K{}: {summary}", self.task);
+    }
+}
+
+impl Drop for Bar {
+    fn drop(&mut self) {
+        if !self.finished {
+            eprintln!();
+        }
+    }
+}
+
+const SPINNER_FRAMES: &[char] = &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// A spinner redrawn by a background thread until finished.
+struct Spinner {
+    task: String,
+    done: Arc<Mutex<u64>>,
+    stop: Arc<AtomicBool>,
+    ticker: Option<JoinHandle<()>>,
+}
+
+impl Spinner {
+    fn start(task: &str) -> Self {
+        let done = Arc::new(Mutex::new(0));
+        let stop = Arc::new(AtomicBool::new(false));
+        let ticker = {
+            let (task, done, stop) = (task.to_string(), done.clone(), stop.clone());
+            thread::spawn(move || {
+                let started = Instant::now();
+                for frame in SPINNER_FRAMES.iter().cycle() {
+                    if stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let count = *done.lock().unwrap_or_else(|e| e.into_inner());
+                    let count = if count > 0 {
+                        format!(" {count}")
+                    } else {
+                        String::new()
+                    };
+                    eprint!(
+                        "\r\x1b[2K{frame} {task}{count} ({}s)",
+                        started.elapsed().as_secs()
+                    );
+                    let _ = io::stderr().flush();
+                    thread::sleep(Duration::from_millis(100));
+                }
+            })
+        };
+        Self {
+            task: task.to_string(),
+            done,
+            stop,
+            ticker: Some(ticker),
+        }
+    }
+
+    fn stop(&mut self) -> bool {
+        self.stop.store(true, Ordering::Relaxed);
+        match self.ticker.take() {
+            Some(ticker) => {
+                let _ = ticker.join();
+                eprint!("\r\x1b[2K");
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl ProgressReporter for Spinner {
+    fn advance(&mut self, n: u64) {
+        *self.done.lock().unwrap_or_else(|e| e.into_inner()) += n;
+    }
+
+    fn finish(&mut self, summary: &str) {
+        if self.stop() {
+            eprintln!("{}: {summary}", self.task);
+        }
+    }
+}
+
+impl Drop for Spinner {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Newline-delimited JSON events on stderr, for wrappers and UIs.
+struct JsonEvents {
+    task: String,
+    done: u64,
+    finished: bool,
+}
+
+impl JsonEvents {
+    fn start(task: &str, total: Option<u64>) -> Self {
+        emit(json!({"event": "start", "task": task, "total": total}));
+        Self {
+            task: task.to_string(),
+            done: 0,
+            finished: false,
+        }
+    }
+}
+
+impl ProgressReporter for JsonEvents {
+    fn advance(&mut self, n: u64) {
+        self.done += n;
+        emit(json!({"event": "progress", "task": self.task, "done": self.done}));
+    }
+
+    fn finish(&mut self, summary: &str) {
+        self.finished = true;
+        emit(json!({
+            "event": "finish",
+            "task": self.task,
+            "done": self.done,
+            "summary": summary,
+        }));
+    }
+}
+
+impl Drop for JsonEvents {
+    fn drop(&mut self) {
+        if !self.finished {
+            emit(json!({"event": "abort", "task": self.task, "done": self.done}));
+        }
+    }
+}
+
+fn emit(event: serde_json::Value) {
+    eprintln!("{event}");
+}
+
+/// `s` cut to `max` bytes, ending in `...` when cut.
+pub(super) fn truncate(s: &str, max: usize) -> String {
+    if s.len() <= max {
+        s.to_string()
+    } else {
+        format!("{}...", &s[..max - 3])
+    }
+}
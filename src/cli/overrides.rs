@@ -0,0 +1,86 @@
+//! Command-line flags that override config values for one run.
+
+use anyhow::{Context, Result};
+
+use plex_media_organizer::config::{AppConfig, Pipeline};
+use plex_media_organizer::utils;
+
+use super::{budget, Command};
+
+/// Fold the flags of `command` into `config`, and start the run's time
+/// budget when `--max-runtime` is given.
+pub(super) fn apply(command: &Command, config: &mut AppConfig) -> Result<()> {
+    if let Command::Organize {
+        paranoid,
+        verify,
+        write_nfo,
+        cleanup_source,
+        upgrade,
+        max_parallel,
+        adaptive,
+        io_limit,
+        ref pipeline,
+        ref max_runtime,
+        ..
+    } = *command
+    {
+        if let Some(limit) = max_runtime {
+            let limit = utils::parse_duration(limit)
+                .with_context(|| format!("Invalid duration: {limit:?} (try 90m, 2h)"))?;
+            budget::start(limit)?;
+        }
+        config.organize.paranoid |= paranoid;
+        config.organize.verify_checksums |= verify;
+        config.organize.write_nfo |= write_nfo;
+        config.organize.cleanup_source |= cleanup_source;
+        config.organize.upgrade |= upgrade;
+        config.organize.adaptive_parallel |= adaptive;
+        if let Some(n) = max_parallel {
+            config.organize.max_parallel = n.max(1);
+        }
+        if let Some(mb) = io_limit {
+            config.organize.io_limit_mb = mb;
+        }
+        match pipeline.as_deref() {
+            Some("quick") => config.organize.pipeline = Pipeline::Quick,
+            Some("journaled") => config.organize.pipeline = Pipeline::Journaled,
+            Some(_) => config.organize.pipeline = Pipeline::Auto,
+            None => {}
+        }
+    }
+    if let Command::Scan {
+        hash_mode: Some(mode),
+        ..
+    } = *command
+    {
+        config.scan.hash_mode = mode;
+    }
+    if let Command::Scan {
+        movies_only: true, ..
+    }
+    | Command::Plan {
+        movies_only: true, ..
+    }
+    | Command::Organize {
+        movies_only: true, ..
+    } = *command
+    {
+        config.scan.movies_only = true;
+    }
+    if let Command::Scan {
+        one_movie_per_folder: true,
+        ..
+    }
+    | Command::Plan {
+        one_movie_per_folder: true,
+        ..
+    }
+    | Command::Organize {
+        one_movie_per_folder: true,
+        ..
+    } = *command
+    {
+        config.scan.one_movie_per_folder = true;
+    }
+    Ok(())
+}
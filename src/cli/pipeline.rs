@@ -0,0 +1,581 @@
+//! The shared scan → parse → enrich pipeline behind `plan`, `organize`,
+//! `watch`, and `view`, the review hold-back that follows it, and the
+//! notifications and Plex refresh after a run.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use tracing::{info, warn};
+
+use plex_media_organizer::config::AppConfig;
+use plex_media_organizer::database::Database;
+use plex_media_organizer::enricher::Enricher;
+use plex_media_organizer::hash::{self, HashMode};
+use plex_media_organizer::identify::{self, Item};
+use plex_media_organizer::models::{EnrichedMedia, MediaFile, OrganizeAction, UndoManifest};
+use plex_media_organizer::nfo::{self, NfoWrite};
+use plex_media_organizer::notify::{self, ReviewItem, RunReport};
+use plex_media_organizer::organizer::{self, ExecuteOptions, FailedRun, Nesting, SourceCleanup};
+use plex_media_organizer::pacing;
+use plex_media_organizer::progress::ProgressEvent;
+use plex_media_organizer::providers::plex::{self, PlexClient};
+use plex_media_organizer::providers::tmdb::TmdbClient;
+use plex_media_organizer::scanner::{self, ScanOptions, ScanReport, SkippedDir};
+
+use super::output::ProgressReporter;
+use super::{budget, dirs_data, dirs_database, dirs_undo, guard_destination, progress, say};
+
+/// Held across each open → change → save of the library database, so
+/// sources watched side by side do not overwrite each other's changes.
+static DATABASE: Mutex<()> = Mutex::new(());
+
+pub(super) fn lock_database() -> MutexGuard<'static, ()> {
+    DATABASE.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Run the full scan → parse → enrich pipeline, returning items for plan/organize.
+pub(super) fn scan_parse_enrich(
+    path: &Path,
+    dest: &Path,
+    allow_nested: bool,
+    learn: bool,
+    config: &AppConfig,
+) -> Result<Vec<Item>> {
+    let files = scan_source(path, dest, allow_nested, config)?;
+    parse_enrich(files, learn, config)
+}
+
+/// Scan a source directory for an organize run into `dest`.
+///
+/// A destination inside the source is excluded from the scan; a source
+/// inside the destination is refused unless `allow_nested` is set.
+pub(super) fn scan_source(
+    path: &Path,
+    dest: &Path,
+    allow_nested: bool,
+    config: &AppConfig,
+) -> Result<Vec<MediaFile>> {
+    guard_destination(dest, false, false, config)?;
+    let mut options = ScanOptions::from_config(config);
+    match organizer::check_nesting(path, dest) {
+        Nesting::Separate => {}
+        Nesting::DestInsideSource => options.exclude_dirs.push(dest.to_path_buf()),
+        Nesting::SourceInsideDest if allow_nested => {}
+        Nesting::SourceInsideDest => bail!(
+            "Source {} is inside the destination {}; this would re-organize \
+             the library into itself. Pass --allow-nested if that is intended.",
+            path.display(),
+            dest.display()
+        ),
+    }
+
+    let report = scan_report(path, &options)?;
+    print_skipped_dirs(&report.skipped);
+    Ok(report.files)
+}
+
+/// Scan a directory with a progress spinner.
+pub(super) fn scan_report(path: &Path, options: &ScanOptions) -> Result<ScanReport> {
+    let mut progress = progress(&format!("Scanning {}", path.display()), None);
+    let report = scanner::scan_directory_report(path, options)?;
+    progress.finish(&format!("{} media files", report.files.len()));
+    Ok(report)
+}
+
+/// Parse and enrich scanned files with [`identify`], drawing its progress.
+///
+/// Once a `--max-runtime` budget is spent the remaining files are left
+/// unmatched. Learned tokens are saved only with `learn`; dry runs write
+/// nothing.
+pub(super) fn parse_enrich(
+    files: Vec<MediaFile>,
+    learn: bool,
+    config: &AppConfig,
+) -> Result<Vec<Item>> {
+    let enricher = Enricher::new(config.clone());
+    let mut db = Database::open(&dirs_database())?;
+    let mut parsing = Some(progress("Parsing", Some(files.len() as u64)));
+    let mut matching: Option<Box<dyn ProgressReporter>> = None;
+    let mut matched = 0;
+    let identified = identify::identify(
+        files,
+        &mut db,
+        &enricher,
+        config,
+        &budget::exceeded,
+        &mut |event| match event {
+            ProgressEvent::Parsing { .. } => {
+                if let Some(parsing) = parsing.as_mut() {
+                    parsing.advance(1);
+                }
+            }
+            ProgressEvent::Matching { total, .. } => {
+                if let Some(mut parsing) = parsing.take() {
+                    parsing.finish(&format!("{total} files"));
+                }
+                matching
+                    .get_or_insert_with(|| progress("Matching", Some(total as u64)))
+                    .advance(1);
+                matched += 1;
+            }
+            ProgressEvent::Retrying { total } => {
+                if let Some(mut matching) = matching.take() {
+                    matching.finish(&format!("{matched} files"));
+                }
+                say!("🔁 Retrying {total} TMDb lookups that hit server errors or timeouts…");
+            }
+            _ => {}
+        },
+    );
+    let items = identified.items;
+    for mut bar in parsing.into_iter().chain(matching) {
+        bar.finish(&format!("{} files", items.len()));
+    }
+
+    let still = identified.retried - identified.recovered;
+    if still > 0 {
+        say!(
+            "⚠️  {still} files could not be checked against TMDb; they keep their \
+             parsed names (recovered {}).",
+            identified.recovered
+        );
+    } else if identified.retried > 0 {
+        say!("✓ All {} retried lookups succeeded.", identified.recovered);
+    }
+    if identified.deferred > 0 {
+        say!(
+            "⏱️  Time budget reached while matching; {} files were left for the next run.",
+            identified.deferred
+        );
+    }
+    if identified.home_videos > 0 {
+        say!(
+            "📹 Skipped {} home videos (phone/camera names). Set \
+             scan.home_videos = \"organize\" to file them under {}/YYYY/MM/.",
+            identified.home_videos,
+            config.organize.home_videos_dir
+        );
+    }
+
+    if learn && identified.learned > 0 {
+        info!(
+            "learned {} title-safe tokens from TMDb matches",
+            identified.learned
+        );
+        // Matching takes a while; re-read so concurrent changes survive.
+        let _lock = lock_database();
+        let mut db = Database::open(&dirs_database())?;
+        for (_, enriched) in &items {
+            db.learn_title_tokens(enriched);
+        }
+        db.save()?;
+    }
+    info!("{} files scanned and enriched", items.len());
+    Ok(items)
+}
+
+/// Tag a destination root so future scans of an enclosing source skip it.
+pub(super) fn mark_output(dest: &Path) {
+    if let Err(e) = organizer::mark_output_root(dest) {
+        warn!(
+            "Could not mark {} as organizer output: {}",
+            dest.display(),
+            e
+        );
+    }
+}
+
+/// Report directories the scanner skipped as a unit.
+pub(super) fn print_skipped_dirs(skipped: &[SkippedDir]) {
+    for dir in skipped {
+        say!("⏭️  Skipped {} — {}", dir.path.display(), dir.reason);
+    }
+}
+
+/// Plan `items` into `dest`. With `organize.upgrade`, each movie is
+/// weighed against the copies already in the library by the quality the
+/// database recorded for them, and those it already has as good are
+/// reported and left out.
+pub(super) fn plan_run(
+    items: &[(PathBuf, EnrichedMedia)],
+    dest: &Path,
+    config: &AppConfig,
+    strategy: &str,
+) -> Result<Vec<OrganizeAction>> {
+    if !config.organize.upgrade {
+        return Ok(organizer::plan_actions(items, dest, config, strategy));
+    }
+    let db = Database::open(&dirs_database())?;
+    let known = |path: &Path| {
+        db.find_by_destination(&path.to_string_lossy())
+            .map(|r| r.quality.clone())
+    };
+    let (actions, already_had) = organizer::plan_upgrades(items, dest, config, strategy, &known);
+    for had in &already_had {
+        say!("⏭️  {} — {}", had.source.display(), had.reason());
+    }
+    Ok(actions)
+}
+
+/// Execute `actions` and record the run in the library database.
+///
+/// A run into a known library root `dest` is journaled when
+/// `organize.pipeline` calls for it; runs interrupted before are recovered
+/// first. With `organize.cleanup_source`, folders under `source` the run
+/// empties are removed. The database lock is held throughout, so runs
+/// from sources watched side by side place files and record them one at a
+/// time.
+pub(super) fn execute_and_record(
+    actions: &[OrganizeAction],
+    dest: Option<&Path>,
+    source: Option<&Path>,
+    config: &AppConfig,
+) -> Result<UndoManifest> {
+    let _lock = lock_database();
+    recover_interrupted()?;
+    let journal = dest.filter(|_| config.organize.journals(actions.len()));
+    let options = ExecuteOptions {
+        deadline: budget::deadline(),
+        journal: journal.map(Path::to_path_buf),
+        cleanup: source.and_then(|root| SourceCleanup::from_config(root, config)),
+        ..ExecuteOptions::from_config(config)
+    };
+    let mut progress = progress(&format!("Organizing {} files", actions.len()), None);
+    let manifest = organizer::execute_actions(actions, &dirs_undo(), &options)?;
+    progress.finish(&format!("{} done", manifest.entries.len()));
+    if let Some(v) = &manifest.verification {
+        say!(
+            "\n🔍 Paranoid mode: {} verified, {} mismatched; hashing took {:.1}s of {:.1}s ({:.0}% overhead)",
+            v.verified,
+            v.mismatched,
+            v.hash_ms as f64 / 1000.0,
+            v.total_ms as f64 / 1000.0,
+            v.overhead_percent()
+        );
+    }
+    let checksummed = manifest
+        .entries
+        .iter()
+        .filter(|e| e.checksum.is_some())
+        .count();
+    if checksummed > 0 {
+        say!("\n🔐 Checksums matched for {checksummed} copied files.");
+    }
+    if let Some(pacing) = pacing::summary(config) {
+        say!("🐢 Pacing: {pacing}.");
+    }
+    if !manifest.replaced.is_empty() {
+        say!(
+            "♻️  Replaced {} lower-quality copies; they were moved aside under {} \
+             (undo restores them).",
+            manifest.replaced.len(),
+            options
+                .recycle_dir
+                .clone()
+                .unwrap_or_else(|| dirs_undo().join("recycle"))
+                .display()
+        );
+    }
+    if !manifest.cleaned.is_empty() {
+        say!(
+            "🧹 Removed emptied source folders; {} leftover files were moved \
+             aside under {} (undo restores them).",
+            manifest.cleaned.len(),
+            dirs_undo().join("cleanup").display()
+        );
+    }
+    if !manifest.entries.is_empty() {
+        let mut db = Database::open(&dirs_database())?;
+        db.record_run(&manifest);
+        store_hashes(&manifest, &mut db, config);
+        if config.organize.write_nfo {
+            write_nfos(&manifest, &mut db, config);
+        }
+        db.save()?;
+    }
+    Ok(manifest)
+}
+
+/// Hash each file the run placed into its record, with `scan.hash_mode`
+/// on, so later scans find copies of it and reconcile can follow it.
+/// Files checksummed as they were copied already have their full hash;
+/// with `partial` it is replaced, since scans compare hashes of one mode.
+fn store_hashes(manifest: &UndoManifest, db: &mut Database, config: &AppConfig) {
+    let mode = config.scan.hash_mode;
+    if mode == HashMode::Off {
+        return;
+    }
+    let unhashed = manifest
+        .entries
+        .iter()
+        .filter(|e| mode != HashMode::Full || e.checksum.is_none());
+    for entry in unhashed {
+        match hash::content_hash(
+            Path::new(&entry.destination),
+            mode,
+            config.scan.hash_partial_mb,
+        ) {
+            Ok(Some(content_hash)) => {
+                db.set_content_hash(&entry.destination, content_hash);
+            }
+            Ok(None) => {}
+            Err(e) => warn!("Could not hash {}: {}", entry.destination, e),
+        }
+    }
+}
+
+/// Write `movie.nfo` beside each movie the run placed, from the details
+/// `enrich` stored or, with a TMDb key, fetched now and stored.
+fn write_nfos(manifest: &UndoManifest, db: &mut Database, config: &AppConfig) {
+    let client = (!config.tmdb.api_key.is_empty()).then(|| TmdbClient::from_settings(&config.tmdb));
+    let (mut written, mut kept) = (0, 0);
+    for entry in manifest.entries.iter().filter(|e| nfo::wants_nfo(e)) {
+        let mut details = db
+            .find_by_destination(&entry.destination)
+            .and_then(|r| r.details.clone());
+        if let (None, Some(client), Some(id)) = (&details, &client, entry.tmdb_id) {
+            match client.movie_details(id, &config.tmdb.region) {
+                Ok(fetched) => {
+                    db.set_details(&entry.destination, id, fetched.clone());
+                    details = Some(fetched);
+                }
+                Err(e) => warn!("{e:#}; writing the .nfo without TMDb details"),
+            }
+        }
+        match nfo::write_movie_nfo(entry, details.as_ref()) {
+            Ok(NfoWrite::Written(_)) => written += 1,
+            Ok(NfoWrite::Kept(path)) => {
+                info!("kept the movie.nfo that came with {}", path.display());
+                kept += 1;
+            }
+            Err(e) => warn!("Could not write movie.nfo for {}: {}", entry.destination, e),
+        }
+    }
+    if written + kept > 0 {
+        let kept = match kept {
+            0 => String::new(),
+            n => format!(" ({n} kept as shipped)"),
+        };
+        say!("📝 Wrote {written} movie.nfo files{kept}.");
+    }
+}
+
+/// Recover journaled runs that were interrupted: record what they placed,
+/// so `undo` can reverse it, and save the rest for `--apply-plan`.
+pub(super) fn recover_interrupted() -> Result<()> {
+    let runs = organizer::recover_journals(&dirs_undo())?;
+    if runs.is_empty() {
+        return Ok(());
+    }
+    let mut db = Database::open(&dirs_database())?;
+    for run in runs {
+        db.record_run(&run.manifest);
+        say!(
+            "\n♻️  Recovered an interrupted run from {}: {} files placed, recorded for undo.",
+            run.remaining.dest_root.display(),
+            run.manifest.entries.len()
+        );
+        if run.remaining.actions.is_empty() {
+            continue;
+        }
+        let stamp = run.manifest_path.file_stem().and_then(|s| s.to_str());
+        let name = stamp
+            .unwrap_or("undo")
+            .replacen("undo_", "interrupted-plan_", 1);
+        let path = dirs_data().join(format!("{name}.json"));
+        organizer::save_plan(&path, &run.remaining)?;
+        say!(
+            "   {} actions were not done. Resume with `plex-org organize --apply-plan {} --execute`.",
+            run.remaining.actions.len(),
+            path.display()
+        );
+    }
+    db.save()
+}
+
+/// Split off items below the review threshold, listing them.
+pub(super) fn hold_for_review(items: Vec<Item>) -> (Vec<Item>, Vec<Item>) {
+    let (held, ready): (Vec<_>, Vec<_>) = items.into_iter().partition(|(_, e)| e.needs_review);
+    let exempt: Vec<_> = ready.iter().filter(|(_, e)| e.review_exempt).collect();
+    if !exempt.is_empty() {
+        say!(
+            "\n✓ {} files are below the review threshold but exactly match TMDb \
+             by title and year, and were planned:\n",
+            exempt.len()
+        );
+        for (source, enriched) in exempt {
+            say!(
+                "  {} — {:?} ({:.0}%)",
+                source.display(),
+                enriched.best_title(),
+                enriched.confidence
+            );
+        }
+    }
+    if !held.is_empty() {
+        say!(
+            "\n⏸️  {} files are below the review threshold and were not planned:\n",
+            held.len()
+        );
+        for (source, enriched) in &held {
+            say!(
+                "  {} — {:?} ({:.0}%)",
+                source.display(),
+                enriched.best_title(),
+                enriched.confidence
+            );
+        }
+    }
+    (ready, held)
+}
+
+/// Add held items to the persistent review queue.
+pub(super) fn queue_for_review(held: &[Item]) -> Result<()> {
+    if held.is_empty() {
+        return Ok(());
+    }
+    let _lock = lock_database();
+    let mut db = Database::open(&dirs_database())?;
+    let added = held
+        .iter()
+        .filter(|(source, enriched)| db.enqueue_review(source, enriched))
+        .count();
+    db.save()?;
+    say!(
+        "\n{} files queued for review ({} new). See `plex-org queue list`.",
+        held.len(),
+        added
+    );
+    Ok(())
+}
+
+/// Tell `[[notify.hooks]]` what a run of `planned` actions from `source`
+/// did: `outcome` is its manifest or its error. A hook that fails only
+/// warns.
+pub(super) fn notify_run(
+    source: &Path,
+    dest: &Path,
+    planned: usize,
+    held: &[Item],
+    outcome: Result<&UndoManifest, &anyhow::Error>,
+    config: &AppConfig,
+) {
+    let hooks = &config.notify.hooks;
+    if hooks.is_empty() {
+        return;
+    }
+    let (organized, skipped, failed, error) = match outcome {
+        Ok(manifest) => {
+            let organized = manifest.entries.len();
+            (organized, planned.saturating_sub(organized), 0, None)
+        }
+        // What a run placed before it failed still counts as organized.
+        Err(e) => {
+            let organized = e
+                .downcast_ref::<FailedRun>()
+                .map_or(0, |run| run.manifest.entries.len());
+            let failed = planned.saturating_sub(organized);
+            (organized, 0, failed, Some(format!("{e:#}")))
+        }
+    };
+    let report = RunReport {
+        source: source.display().to_string(),
+        destination: dest.display().to_string(),
+        organized,
+        skipped,
+        failed,
+        error,
+        review: held
+            .iter()
+            .map(|(path, enriched)| ReviewItem {
+                path: path.display().to_string(),
+                title: enriched.best_title().to_string(),
+                confidence: enriched.confidence,
+            })
+            .collect(),
+    };
+    for e in notify::notify(hooks, &report) {
+        say!("⚠️  {e:#}");
+    }
+}
+
+/// Refresh Plex after a run and, with `plex.verify`, report movies Plex
+/// resolved differently from our match. Plex being unreachable only warns;
+/// the files are organized either way.
+pub(super) fn update_plex(manifest: &UndoManifest, config: &AppConfig) {
+    let settings = &config.plex;
+    if settings.url.is_empty() || manifest.entries.is_empty() {
+        return;
+    }
+    let client = PlexClient::new(&settings.url, &settings.token);
+    let refreshed = if settings.partial_scan {
+        let folders: BTreeSet<String> = manifest
+            .entries
+            .iter()
+            .filter_map(|e| Path::new(&e.destination).parent())
+            .map(|p| std::path::absolute(p).unwrap_or_else(|_| p.to_path_buf()))
+            .map(|p| p.to_string_lossy().into_owned())
+            .collect();
+        let folders: Vec<String> = folders.into_iter().collect();
+        client
+            .refresh_paths(&folders, &settings.path_map)
+            .map(|report| match report {
+                plex::ScanReport { full: true, .. } => {
+                    "🔄 Asked Plex to refresh its libraries (a folder is outside every section)."
+                        .to_string()
+                }
+                plex::ScanReport { folders, .. } => {
+                    format!("🔄 Asked Plex to scan {folders} folder(s).")
+                }
+            })
+    } else {
+        client
+            .refresh()
+            .map(|()| "🔄 Asked Plex to refresh its libraries.".to_string())
+    };
+    match refreshed {
+        Ok(message) => say!("{message}"),
+        Err(e) => {
+            say!("⚠️  {e:#}");
+            return;
+        }
+    }
+    if !settings.verify {
+        return;
+    }
+    say!(
+        "⏳ Giving Plex {}s to scan before checking its matches…",
+        settings.verify_wait_secs
+    );
+    std::thread::sleep(Duration::from_secs(settings.verify_wait_secs));
+    let check = match client.movies() {
+        Ok(movies) => plex::check_titles(&manifest.entries, &movies),
+        Err(e) => {
+            say!("⚠️  {e:#}");
+            return;
+        }
+    };
+    let year = |y: Option<i32>| y.map_or(String::new(), |y| format!(" ({y})"));
+    say!(
+        "\n🔍 Plex agrees on {} movies, disagrees on {}, has not picked up {}.",
+        check.agreed,
+        check.disagreements.len(),
+        check.missing.len()
+    );
+    for d in &check.disagreements {
+        say!(
+            "  ⚠️  {} — matched {}{}, Plex shows {}{}",
+            d.destination,
+            d.title,
+            year(d.year),
+            d.plex_title,
+            year(d.plex_year)
+        );
+    }
+    for destination in &check.missing {
+        say!("  ⏳ {destination} — not in Plex yet");
+    }
+}
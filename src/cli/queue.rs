@@ -0,0 +1,256 @@
+//! The pending-review queue: list, show, accept, reject.
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use clap::Subcommand;
+
+use plex_media_organizer::config::AppConfig;
+use plex_media_organizer::database::queue::{ReviewItem, ReviewStatus};
+use plex_media_organizer::database::Database;
+use plex_media_organizer::enricher::Enricher;
+
+use super::pipeline::plan_run;
+use super::{dirs_database, execute_and_record, mark_output, strategies, truncate};
+
+#[derive(Subcommand)]
+pub enum QueueAction {
+    /// List files waiting for review.
+    List {
+        /// Include rejected files.
+        #[arg(long)]
+        all: bool,
+        /// Emit JSON instead of a table.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show the parse output and candidates for one queued file.
+    Show {
+        /// Queue id (from `queue list`).
+        id: u64,
+    },
+    /// Organize a queued file with a confirmed identity.
+    Accept {
+        /// Queue id (from `queue list`).
+        id: u64,
+        /// Use this candidate (1-based, from `queue show`).
+        #[arg(long, conflicts_with_all = ["title", "year"])]
+        candidate: Option<usize>,
+        /// Use this title instead of the parsed one.
+        #[arg(long)]
+        title: Option<String>,
+        /// Use this year instead of the parsed one.
+        #[arg(long)]
+        year: Option<i32>,
+        /// Library root (defaults to `destination` from config).
+        #[arg(short, long)]
+        dest: Option<PathBuf>,
+        /// File strategy: move, copy, hardlink, reflink, or symlink.
+        #[arg(short, long, alias = "link-mode", default_value = "move", value_parser = strategies())]
+        strategy: String,
+        /// Actually execute (without this flag, acts as dry-run).
+        #[arg(long)]
+        execute: bool,
+    },
+    /// Reject a queued file; later scans will skip it.
+    Reject {
+        /// Queue id (from `queue list`).
+        id: u64,
+    },
+}
+
+pub(super) fn cmd_queue(action: QueueAction, config: &AppConfig) -> Result<()> {
+    match action {
+        QueueAction::List { all, json } => cmd_queue_list(all, json),
+        QueueAction::Show { id } => cmd_queue_show(id),
+        QueueAction::Accept {
+            id,
+            candidate,
+            title,
+            year,
+            dest,
+            strategy,
+            execute,
+        } => {
+            let choice = Choice {
+                candidate,
+                title,
+                year,
+            };
+            cmd_queue_accept(id, choice, dest, &strategy, execute, config)
+        }
+        QueueAction::Reject { id } => {
+            let mut db = Database::open(&dirs_database())?;
+            db.reject_review(id)?;
+            db.save()?;
+            println!("Rejected #{id}; it will be skipped by future scans.");
+            Ok(())
+        }
+    }
+}
+
+fn cmd_queue_list(all: bool, json: bool) -> Result<()> {
+    let db = Database::open(&dirs_database())?;
+    let items: Vec<&ReviewItem> = db
+        .review_items()
+        .iter()
+        .filter(|i| all || i.status == ReviewStatus::Pending)
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&items)?);
+        return Ok(());
+    }
+    if items.is_empty() {
+        println!("The review queue is empty.");
+        return Ok(());
+    }
+
+    println!(
+        "{:<5} {:<12} {:<5} {:<32} {:<6} Source",
+        "Id", "Queued", "Conf", "Parsed title", "Cands"
+    );
+    println!("{}", "-".repeat(100));
+    for item in &items {
+        let queued = item.queued_at.get(..10).unwrap_or(&item.queued_at);
+        let mut title = truncate(&item.parsed.title, 30);
+        if item.status == ReviewStatus::Rejected {
+            title = format!("(rejected) {title}");
+        }
+        println!(
+            "{:<5} {:<12} {:<5.0} {:<32} {:<6} {}",
+            item.id,
+            queued,
+            item.confidence,
+            title,
+            item.candidates.len(),
+            item.source
+        );
+    }
+    println!(
+        "\n{} items. Use `queue show <id>` for details.",
+        items.len()
+    );
+    Ok(())
+}
+
+fn cmd_queue_show(id: u64) -> Result<()> {
+    let db = Database::open(&dirs_database())?;
+    let item = find(&db, id)?;
+    let parsed = &item.parsed;
+
+    println!("#{} — {}", item.id, item.source);
+    println!("  Status:      {:?}", item.status);
+    println!("  Queued:      {}", item.queued_at);
+    println!("  Last seen:   {}", item.updated_at);
+    println!("  Confidence:  {:.0}%", item.confidence);
+    println!("  Parsed:      {:?} ({})", parsed.title, parsed.media_type);
+    if let Some(year) = parsed.year {
+        println!("  Year:        {year}");
+    }
+    if let (Some(s), Some(e)) = (parsed.season, parsed.episode) {
+        println!("  Episode:     S{s:02}E{e:02}");
+    }
+    if !parsed.quality.is_empty() {
+        println!("  Quality:     {}", parsed.quality);
+    }
+
+    if item.candidates.is_empty() {
+        println!("\nNo provider candidates. Accept with --title/--year to name it yourself.");
+    } else {
+        println!("\nCandidates:");
+        for (n, c) in item.candidates.iter().enumerate() {
+            let year = c.year.map(|y| format!(" ({y})")).unwrap_or_default();
+            let id = match (c.id, &c.imdb_id) {
+                (Some(id), _) => format!(" [tmdb {id}]"),
+                (None, Some(imdb)) => format!(" [imdb {imdb}]"),
+                (None, None) => String::new(),
+            };
+            println!("  {}. {}{}{}", n + 1, c.title, year, id);
+        }
+        println!("\nAccept one with `queue accept {id} --candidate N`.");
+    }
+    Ok(())
+}
+
+/// The identity a reviewer picked.
+struct Choice {
+    candidate: Option<usize>,
+    title: Option<String>,
+    year: Option<i32>,
+}
+
+fn cmd_queue_accept(
+    id: u64,
+    choice: Choice,
+    dest: Option<PathBuf>,
+    strategy: &str,
+    execute: bool,
+    config: &AppConfig,
+) -> Result<()> {
+    let db = Database::open(&dirs_database())?;
+    let item = find(&db, id)?.clone();
+    let source = PathBuf::from(&item.source);
+    if !source.exists() {
+        bail!(
+            "{} no longer exists; reject #{id} or re-scan its new location",
+            source.display()
+        );
+    }
+    let library_root = match dest {
+        Some(d) => d,
+        None if !config.destination.is_empty() => PathBuf::from(&config.destination),
+        None => bail!("No library root: pass --dest or set `destination` in config"),
+    };
+
+    let candidate = match choice.candidate {
+        Some(n) => Some(
+            item.candidates
+                .get(n.wrapping_sub(1))
+                .cloned()
+                .with_context(|| format!("#{id} has no candidate {n}"))?,
+        ),
+        None => None,
+    };
+    let mut parsed = item.parsed;
+    if let Some(title) = choice.title {
+        parsed.title = title;
+    }
+    if choice.year.is_some() {
+        parsed.year = choice.year;
+    }
+    let enriched = Enricher::new(config.clone()).confirm(parsed, candidate);
+
+    let items = [(source, enriched)];
+    let actions = plan_run(&items, &library_root, config, strategy)?;
+    println!("\n📋 Plan for #{id} ({} actions):\n", actions.len());
+    for action in &actions {
+        println!(
+            "  {} → {}",
+            action.source.display(),
+            action.destination.display()
+        );
+    }
+    if !execute {
+        println!("\nDry-run. Add --execute to apply.");
+        return Ok(());
+    }
+
+    execute_and_record(&actions, Some(&library_root), None, config)?;
+    mark_output(&library_root);
+    // Recording the run already dropped the item; learn from a TMDb pick.
+    let mut db = Database::open(&dirs_database())?;
+    db.remove_review(id);
+    db.learn_title_tokens(&items[0].1);
+    db.save()?;
+    println!(
+        "\n✅ Accepted #{id} and organized it into {}.",
+        library_root.display()
+    );
+    Ok(())
+}
+
+fn find(db: &Database, id: u64) -> Result<&ReviewItem> {
+    db.review_item(id)
+        .with_context(|| format!("No queued item with id {id} (see `queue list`)"))
+}
@@ -0,0 +1,291 @@
+//! `plex-org scan`: what a source holds and how each file parses.
+//!
+//! A scan stores a checkpoint in the library database every half minute,
+//! and `scan --resume` goes on from it; see [`ScanCheckpoint`].
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use chrono::Utc;
+use serde::Serialize;
+use tracing::warn;
+
+use plex_media_organizer::config::AppConfig;
+use plex_media_organizer::database::Database;
+use plex_media_organizer::hash::{self, HashMode};
+use plex_media_organizer::models::{MediaFile, MediaType, ParsedMedia};
+use plex_media_organizer::parser::{self, ParseOptions};
+use plex_media_organizer::probe;
+use plex_media_organizer::scanner::{self, ScanCheckpoint, ScanOptions};
+use plex_media_organizer::utils;
+
+use super::output::{self, print_rows};
+use super::{dirs_database, print_skipped_dirs, progress, say, truncate};
+
+/// How often a scan's progress is written to the database.
+const CHECKPOINT_EVERY: Duration = Duration::from_secs(30);
+
+/// Columns of `--output csv`.
+const COLUMNS: &[&str] = &[
+    "source",
+    "media_type",
+    "title",
+    "year",
+    "season",
+    "episode",
+    "quality",
+    "confidence",
+    "size_bytes",
+];
+
+/// One parsed file, for `--output json|csv`.
+#[derive(Serialize)]
+struct ParseRow<'a> {
+    source: &'a Path,
+    media_type: MediaType,
+    title: &'a str,
+    year: Option<i32>,
+    season: Option<i32>,
+    episode: Option<i32>,
+    quality: &'a str,
+    confidence: f64,
+    size_bytes: u64,
+}
+
+pub(super) fn cmd_scan(path: &Path, resume: bool, config: &AppConfig) -> Result<()> {
+    let opts = ScanOptions {
+        min_video_size: 0, // Show small videos too; samples are still left out
+        ..ScanOptions::from_config(config)
+    };
+    // Checkpoints are kept by absolute path, whatever the working directory.
+    let root = std::path::absolute(path)?;
+    let from = if resume { resume_point(&root)? } else { None };
+
+    let mut progress = progress(&format!("Scanning {}", path.display()), None);
+    let report = scanner::scan_directory_resumable(
+        &root,
+        &opts,
+        from.clone(),
+        CHECKPOINT_EVERY,
+        &mut |checkpoint| store(checkpoint.clone()),
+    )?;
+    progress.finish(&format!("{} media files", report.files.len()));
+    print_skipped_dirs(&report.skipped);
+
+    if report.files.is_empty() {
+        clear(&root)?;
+        say!("No media files found in {}", path.display());
+        return Ok(());
+    }
+    let mut checkpoint = ScanCheckpoint {
+        complete: true,
+        files: report.files,
+        skipped: report.skipped,
+        parsed: from.map(|c| c.parsed).unwrap_or_default(),
+        ..ScanCheckpoint::new(&root)
+    };
+    let done = std::mem::take(&mut checkpoint.parsed);
+    store(checkpoint.clone())?;
+    let parsed = parse_files(&checkpoint.files, config, done, &mut |parsed| {
+        store(ScanCheckpoint {
+            parsed: parsed.to_vec(),
+            ..checkpoint.clone()
+        })
+    })?;
+    clear(&root)?;
+    print_results(&checkpoint.files, &parsed)?;
+    print_content_matches(&checkpoint.files, config)
+}
+
+/// The checkpoint `scan --resume` goes on from, if there is one.
+fn resume_point(root: &Path) -> Result<Option<ScanCheckpoint>> {
+    let db = Database::open(&dirs_database())?;
+    let Some(checkpoint) = db.scan_checkpoint(root) else {
+        say!(
+            "No unfinished scan of {}; scanning from the start.",
+            root.display()
+        );
+        return Ok(None);
+    };
+    let walk = if checkpoint.complete {
+        "walk finished"
+    } else {
+        "walk unfinished"
+    };
+    say!(
+        "▶️  Resuming the scan of {} from {} ({walk}, {} files found, {} parsed).",
+        root.display(),
+        checkpoint.updated_at,
+        checkpoint.files.len(),
+        checkpoint.parsed.len()
+    );
+    Ok(Some(checkpoint.clone()))
+}
+
+fn store(mut checkpoint: ScanCheckpoint) -> Result<()> {
+    checkpoint.updated_at = Utc::now().to_rfc3339();
+    let mut db = Database::open(&dirs_database())?;
+    db.set_scan_checkpoint(checkpoint);
+    db.save()
+}
+
+fn clear(root: &Path) -> Result<()> {
+    let mut db = Database::open(&dirs_database())?;
+    if db.clear_scan_checkpoint(root) {
+        db.save()?;
+    }
+    Ok(())
+}
+
+/// Parse `files` after the first `done.len()`, which were parsed before,
+/// handing the results so far to `save` every [`CHECKPOINT_EVERY`].
+fn parse_files(
+    files: &[MediaFile],
+    config: &AppConfig,
+    mut done: Vec<ParsedMedia>,
+    save: &mut dyn FnMut(&[ParsedMedia]) -> Result<()>,
+) -> Result<Vec<ParsedMedia>> {
+    let db = Database::open(&dirs_database())?;
+    let options = ParseOptions::from_config(config);
+    done.truncate(files.len());
+    let mut last_save = Instant::now();
+    for file in &files[done.len()..] {
+        if last_save.elapsed() >= CHECKPOINT_EVERY {
+            save(&done)?;
+            last_save = Instant::now();
+        }
+        let mut parsed = parser::parse_media_file(file, &options);
+        if config.scan.probe && parsed.media_type != MediaType::Music {
+            probe::apply(&mut parsed, &file.source_path);
+        }
+        db.restore_title(&mut parsed, &options);
+        done.push(parsed);
+    }
+    Ok(done)
+}
+
+/// The parse table shared by `scan` and `plan --stage parse`.
+pub(super) fn print_parsed(files: &[MediaFile], config: &AppConfig) -> Result<()> {
+    let parsed = parse_files(files, config, Vec::new(), &mut |_| Ok(()))?;
+    print_results(files, &parsed)
+}
+
+fn print_results(files: &[MediaFile], parsed: &[ParsedMedia]) -> Result<()> {
+    if !output::structured() {
+        print_table(files, parsed);
+        return Ok(());
+    }
+    let rows: Vec<_> = files
+        .iter()
+        .zip(parsed)
+        .map(|(file, parsed)| ParseRow {
+            source: &file.source_path,
+            media_type: parsed.media_type,
+            title: &parsed.title,
+            year: parsed.year,
+            season: parsed.season,
+            episode: parsed.episode,
+            quality: &parsed.quality,
+            confidence: parsed.confidence,
+            size_bytes: file.size_bytes,
+        })
+        .collect();
+    print_rows(&rows, COLUMNS)
+}
+
+fn print_table(files: &[MediaFile], parsed: &[ParsedMedia]) {
+    // Table header
+    println!(
+        "{:<50} {:<8} {:<40} {:<6} {:<8} {:<10}",
+        "Filename", "Type", "Title", "Year", "S/E", "Size"
+    );
+    println!("{}", "-".repeat(122));
+
+    let mut before_year = 0;
+    for (file, parsed) in files.iter().zip(parsed) {
+        before_year += usize::from(!parsed.title_strategy.is_hunch());
+
+        let type_str = match parsed.media_type {
+            MediaType::Movie => "movie",
+            MediaType::Tv => "tv",
+            MediaType::Music => "music",
+            MediaType::Unknown => "?",
+        };
+
+        let se = match (parsed.season, parsed.episode) {
+            (Some(s), Some(e)) => format!("S{s:02}E{e:02}"),
+            _ => String::new(),
+        };
+
+        let year_str = parsed.year.map(|y| y.to_string()).unwrap_or_default();
+
+        let name = truncate(&file.full_name(), 48);
+        let title = truncate(&parsed.title, 38);
+
+        println!(
+            "{:<50} {:<8} {:<40} {:<6} {:<8} {:<10}",
+            name,
+            type_str,
+            title,
+            year_str,
+            se,
+            utils::format_size(file.size_bytes),
+        );
+    }
+
+    println!("\n{} media files found.", files.len());
+    if before_year > 0 {
+        println!(
+            "{before_year} titles were taken from the words before the year, \
+             which scored better than hunch's."
+        );
+    }
+}
+
+/// Hash scanned files with `scan.hash_mode` on and list the ones whose
+/// contents are already in the library or appear twice in the scan.
+fn print_content_matches(files: &[MediaFile], config: &AppConfig) -> Result<()> {
+    let mode = config.scan.hash_mode;
+    if mode == HashMode::Off {
+        return Ok(());
+    }
+    let db = Database::open(&dirs_database())?;
+    let mut progress = progress(&format!("Hashing ({mode})"), Some(files.len() as u64));
+    let mut seen: HashMap<String, Vec<&Path>> = HashMap::new();
+    for file in files {
+        progress.advance(1);
+        match hash::content_hash(&file.source_path, mode, config.scan.hash_partial_mb) {
+            Ok(Some(content_hash)) => seen
+                .entry(content_hash)
+                .or_default()
+                .push(&file.source_path),
+            Ok(None) => {}
+            Err(e) => warn!("Could not hash {}: {}", file.source_path.display(), e),
+        }
+    }
+    progress.finish(&format!("{} distinct", seen.len()));
+
+    let mut groups: Vec<_> = seen.into_iter().collect();
+    groups.sort_by(|a, b| a.1.cmp(&b.1));
+    let mut reported = 0;
+    for (content_hash, paths) in groups {
+        let in_library = db.find_by_content_hash(&content_hash);
+        if paths.len() < 2 && in_library.is_none() {
+            continue;
+        }
+        reported += 1;
+        say!("\n♊ Same contents ({content_hash}):");
+        for path in paths {
+            say!("   {}", path.display());
+        }
+        if let Some(record) = in_library {
+            say!("   already in the library as {}", record.destination);
+        }
+    }
+    if reported == 0 {
+        say!("\nNo duplicate contents found.");
+    }
+    Ok(())
+}
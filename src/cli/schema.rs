@@ -0,0 +1,33 @@
+//! `schema`: the JSON Schemas of the files plex-org writes.
+
+use anyhow::Result;
+use clap::Subcommand;
+
+use plex_media_organizer::schema::Artifact;
+
+#[derive(Subcommand)]
+pub enum SchemaAction {
+    /// Print the schema of one artifact: plan, run-report, explain, or repro.
+    Print {
+        #[arg(value_parser = parse_artifact)]
+        artifact: Artifact,
+    },
+    /// List the artifacts and their current versions.
+    List,
+}
+
+fn parse_artifact(s: &str) -> Result<Artifact> {
+    s.parse()
+}
+
+pub(super) fn cmd_schema(action: SchemaAction) -> Result<()> {
+    match action {
+        SchemaAction::Print { artifact } => print!("{}", artifact.schema()),
+        SchemaAction::List => {
+            for artifact in Artifact::ALL {
+                println!("{:<12} v{}", artifact.name(), artifact.version());
+            }
+        }
+    }
+    Ok(())
+}
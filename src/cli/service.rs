@@ -0,0 +1,552 @@
+//! Watch mode and service installation.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Mutex, TryLockError};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{bail, Context, Result};
+use chrono::Local;
+use clap::{Args, Subcommand};
+
+use plex_media_organizer::config::AppConfig;
+use plex_media_organizer::database::Database;
+use plex_media_organizer::models::UndoManifest;
+use plex_media_organizer::schedule::Schedule;
+use plex_media_organizer::service::{self, Shutdown, SystemdUnit};
+use plex_media_organizer::utils;
+
+use super::pipeline::{
+    hold_for_review, lock_database, notify_run, parse_enrich, plan_run, queue_for_review,
+    scan_source,
+};
+use super::{dirs_database, execute_and_record, guard_destination, mark_output, strategies};
+
+/// What `watch` organizes, and how often.
+#[derive(Args, Clone)]
+pub struct WatchArgs {
+    /// Source directories (defaults to `source_dirs` from config).
+    pub paths: Vec<PathBuf>,
+    /// Library root (defaults to `destination` from config).
+    #[arg(short, long)]
+    pub dest: Option<PathBuf>,
+    /// File strategy: move, copy, hardlink, reflink, or symlink.
+    #[arg(short, long, alias = "link-mode", default_value = "move", value_parser = strategies())]
+    pub strategy: String,
+    /// Time between passes, e.g. `5m`, `1h`.
+    #[arg(long, default_value = "5m")]
+    pub interval: String,
+    /// Leave files modified more recently than this (still downloading).
+    #[arg(long, default_value = "2m")]
+    pub settle: String,
+    /// Movie inbox: skip TV season-pack folders as a unit.
+    #[arg(long)]
+    pub movies_only: bool,
+    /// Allow a library root outside the configured library roots.
+    #[arg(long)]
+    pub allow_outside_library: bool,
+    /// In each full run (every pass without `watch.schedule`), follow
+    /// renames and drop deletions made in the library by other tools, so
+    /// the database does not go stale.
+    #[arg(long)]
+    pub track_library: bool,
+}
+
+#[derive(Subcommand)]
+pub enum ServiceAction {
+    /// Install a service that runs `watch --execute` at boot.
+    ///
+    /// Writes a systemd unit on Linux. On Windows it registers a Task
+    /// Scheduler task that starts at boot, not a Windows service.
+    Install {
+        #[command(flatten)]
+        watch: WatchArgs,
+        /// Account the service runs as (defaults to the invoking user).
+        #[arg(long, conflicts_with = "per_user")]
+        user: Option<String>,
+        /// Install a per-user systemd unit (~/.config/systemd/user).
+        #[arg(long)]
+        per_user: bool,
+        /// Service name.
+        #[arg(long, default_value = "plex-org")]
+        name: String,
+        /// Actually install (without this flag, shows what would be written).
+        #[arg(long)]
+        execute: bool,
+    },
+}
+
+/// A resolved watch configuration.
+struct Watch {
+    sources: Vec<Source>,
+    /// Sources come from `[[watch.sources]]` rather than the command line.
+    from_config: bool,
+    strategy: String,
+    interval: Duration,
+    settle: Duration,
+    track_library: bool,
+    /// When full runs over every source happen (`watch.schedule`).
+    schedule: Option<Schedule>,
+}
+
+/// A watched source with its library root and the config its files are
+/// processed under.
+struct Source {
+    path: PathBuf,
+    dest: PathBuf,
+    config: AppConfig,
+    /// Held by whichever pass is working on the source, so a scheduled
+    /// full run and an interval pass never overlap.
+    busy: Mutex<()>,
+}
+
+impl Watch {
+    fn resolve(args: &WatchArgs, config: &AppConfig) -> Result<Self> {
+        let from_config = args.paths.is_empty() && !config.watch.sources.is_empty();
+        let dest = |own: Option<&str>| -> Result<PathBuf> {
+            match (own, &args.dest) {
+                (Some(d), _) => Ok(PathBuf::from(d)),
+                (None, Some(d)) => Ok(d.clone()),
+                (None, None) if !config.destination.is_empty() => {
+                    Ok(PathBuf::from(&config.destination))
+                }
+                (None, None) => {
+                    bail!("No library root: pass --dest or set `destination` in config")
+                }
+            }
+        };
+        let mut sources = Vec::new();
+        if from_config {
+            for source in &config.watch.sources {
+                let mut source_config = config.clone();
+                source_config.scan.movies_only |= source.movies_only;
+                if source.profile.is_some() {
+                    source_config.profiles.force = source.profile;
+                }
+                sources.push(Source {
+                    path: PathBuf::from(&source.path),
+                    dest: dest(source.destination.as_deref())?,
+                    config: source_config,
+                    busy: Mutex::new(()),
+                });
+            }
+        } else {
+            let paths: Vec<PathBuf> = if args.paths.is_empty() {
+                config.source_dirs.iter().map(PathBuf::from).collect()
+            } else {
+                args.paths.clone()
+            };
+            for path in paths {
+                sources.push(Source {
+                    path,
+                    dest: dest(None)?,
+                    config: config.clone(),
+                    busy: Mutex::new(()),
+                });
+            }
+        }
+        if sources.is_empty() {
+            bail!("No sources: pass directories or set `source_dirs` in config");
+        }
+        let duration = |s: &str| {
+            utils::parse_duration(s)
+                .with_context(|| format!("Invalid duration: {s:?} (try 5m, 1h)"))
+        };
+        let schedule = match config.watch.schedule.trim() {
+            "" => None,
+            text => {
+                let schedule: Schedule = text.parse()?;
+                if schedule.next_local(Local::now()).is_none() {
+                    bail!("watch.schedule {text:?} never fires");
+                }
+                Some(schedule)
+            }
+        };
+        Ok(Self {
+            sources,
+            from_config,
+            strategy: args.strategy.clone(),
+            interval: duration(&args.interval)?,
+            settle: duration(&args.settle)?,
+            track_library: args.track_library,
+            schedule,
+        })
+    }
+}
+
+pub(super) fn cmd_watch(args: &WatchArgs, execute: bool, config: &AppConfig) -> Result<()> {
+    let mut config = config.clone();
+    config.scan.movies_only |= args.movies_only;
+    let watch = Watch::resolve(args, &config)?;
+    for source in &watch.sources {
+        guard_destination(
+            &source.dest,
+            execute,
+            args.allow_outside_library,
+            &source.config,
+        )?;
+    }
+
+    if !execute {
+        for source in &watch.sources {
+            watch_pass(&watch, source, false, true)?;
+        }
+        if let Some((schedule, at)) = watch
+            .schedule
+            .as_ref()
+            .and_then(|s| Some((s, s.next_local(Local::now())?)))
+        {
+            println!(
+                "\nFull runs are scheduled at `{schedule}`; the next would start {}.",
+                at.format("%Y-%m-%d %H:%M")
+            );
+        }
+        println!("\nDry-run of one pass. Add --execute to keep watching and apply.");
+        return Ok(());
+    }
+
+    let shutdown = Shutdown::install();
+    println!(
+        "👀 Watching {} every {}; stop with Ctrl-C or SIGTERM (the current pass finishes first).",
+        watch
+            .sources
+            .iter()
+            .map(|s| s.path.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", "),
+        args.interval
+    );
+    if let Some(schedule) = &watch.schedule {
+        println!("🗓️  Full runs at `{schedule}` (local time).");
+    }
+    // Each source gets its own loop, so a long pass over one (a big
+    // season pack being matched) does not delay the others. Scheduled full
+    // runs get one more.
+    let (passes, runs) = thread::scope(|scope| {
+        let loops: Vec<_> = watch
+            .sources
+            .iter()
+            .map(|source| {
+                let (watch, shutdown) = (&watch, &shutdown);
+                scope.spawn(move || {
+                    service::run_loop(watch.interval, shutdown, || interval_pass(watch, source))
+                })
+            })
+            .collect();
+        let runs = watch.schedule.as_ref().map(|schedule| {
+            let (watch, shutdown) = (&watch, &shutdown);
+            scope.spawn(move || service::run_schedule(schedule, shutdown, || full_run(watch)))
+        });
+        let passes: usize = loops.into_iter().map(|l| l.join().unwrap_or(0)).sum();
+        let runs = runs.map_or(0, |r| r.join().unwrap_or(0));
+        (passes, runs)
+    });
+    if runs > 0 {
+        println!("Stopped after {passes} passes and {runs} full runs.");
+    } else {
+        println!("Stopped after {passes} passes.");
+    }
+    Ok(())
+}
+
+/// An interval pass, unless the scheduled full run is on the source; it
+/// covers the new files itself. Without a schedule there are no full runs,
+/// so every interval pass does their library check.
+fn interval_pass(watch: &Watch, source: &Source) -> Result<()> {
+    let _busy = match source.busy.try_lock() {
+        Ok(guard) => guard,
+        Err(TryLockError::Poisoned(e)) => e.into_inner(),
+        Err(TryLockError::WouldBlock) => return Ok(()),
+    };
+    watch_pass(watch, source, true, watch.schedule.is_none())
+}
+
+/// A scheduled full run: a pass over every source, each first bringing its
+/// library's records in line with the files under `--track-library`. Each
+/// source is taken in turn once its interval pass in flight, if any,
+/// finishes. A source that fails is reported and the run goes on to the
+/// next.
+fn full_run(watch: &Watch) -> Result<()> {
+    println!(
+        "[{}] 🗓️  Starting a full run.",
+        Local::now().format("%Y-%m-%d %H:%M:%S")
+    );
+    let mut failed = 0;
+    for source in &watch.sources {
+        let _busy = source.busy.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = watch_pass(watch, source, true, true) {
+            eprintln!("⚠️  Full run of {} failed: {e:#}", source.path.display());
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        bail!("{failed} of {} sources failed", watch.sources.len());
+    }
+    Ok(())
+}
+
+/// One organize pass over one source. Quiet when there is nothing new.
+/// A `full_run` pass first brings the library's records in line with its
+/// files under `--track-library`.
+///
+/// Scanning and matching run alongside other sources' passes; changes to
+/// the library and the database are made one source at a time.
+fn watch_pass(watch: &Watch, source: &Source, execute: bool, full_run: bool) -> Result<()> {
+    let config = &source.config;
+    if full_run && watch.track_library {
+        let _lock = lock_database();
+        let mut db = Database::open(&dirs_database())?;
+        track_library(&mut db, &source.dest, execute)?;
+    }
+    let db = Database::open(&dirs_database())?;
+    let now = SystemTime::now();
+    // Queued files wait for `queue accept`; unsettled ones for a later pass.
+    let files = scan_source(&source.path, &source.dest, false, config)?
+        .into_iter()
+        .filter(|f| !db.is_queued(&f.source_path))
+        .filter(|f| service::is_settled(&f.source_path, watch.settle, now))
+        .collect();
+    let items = parse_enrich(files, execute, config)?;
+    if items.is_empty() {
+        return Ok(());
+    }
+
+    let (items, held) = hold_for_review(items);
+    let actions = plan_run(&items, &source.dest, config, &watch.strategy)?;
+    if !execute {
+        println!(
+            "\n📋 Plan for {} ({} actions):\n",
+            source.path.display(),
+            actions.len()
+        );
+        for action in &actions {
+            println!(
+                "  {} → {}",
+                action.source.display(),
+                action.destination.display()
+            );
+        }
+        return Ok(());
+    }
+
+    queue_for_review(&held)?;
+    if actions.is_empty() {
+        let nothing = UndoManifest::default();
+        notify_run(&source.path, &source.dest, 0, &held, Ok(&nothing), config);
+        return Ok(());
+    }
+    let result = execute_and_record(&actions, Some(&source.dest), Some(&source.path), config);
+    notify_run(
+        &source.path,
+        &source.dest,
+        actions.len(),
+        &held,
+        result.as_ref(),
+        config,
+    );
+    let manifest = result?;
+    mark_output(&source.dest);
+    println!(
+        "[{}] ✅ Organized {} files from {}.",
+        Local::now().format("%Y-%m-%d %H:%M:%S"),
+        manifest.entries.len(),
+        source.path.display()
+    );
+    Ok(())
+}
+
+/// Bring the database in line with renames and deletions in the library.
+/// Saved only when executing; a dry run just reports. A library that looks
+/// unmounted, or would lose most of its records, fails the pass instead.
+fn track_library(db: &mut Database, dest: &Path, execute: bool) -> Result<()> {
+    let changes = db.reconcile(dest, false).with_context(|| {
+        format!(
+            "Library tracking stopped; if the files really are gone, run \
+             `plex-org library index {} --confirm-removals`",
+            dest.display()
+        )
+    })?;
+    if execute && (!changes.is_empty() || changes.refreshed > 0) {
+        db.save()?;
+    }
+    if changes.is_empty() {
+        return Ok(());
+    }
+    let now = Local::now().format("%Y-%m-%d %H:%M:%S");
+    for (old, new) in &changes.renamed {
+        println!("[{now}] 🔀 Renamed in library: {old} → {new}");
+    }
+    for record in &changes.removed {
+        println!("[{now}] 🗑️  Removed from library: {}", record.destination);
+    }
+    Ok(())
+}
+
+pub(super) fn cmd_service(
+    action: ServiceAction,
+    config_path: Option<&Path>,
+    config: &AppConfig,
+) -> Result<()> {
+    match action {
+        ServiceAction::Install {
+            watch,
+            user,
+            per_user,
+            name,
+            execute,
+        } => {
+            let target = Target {
+                name,
+                user,
+                per_user,
+            };
+            cmd_service_install(&watch, &target, execute, config_path, config)
+        }
+    }
+}
+
+/// Where and as whom the service is installed.
+struct Target {
+    name: String,
+    user: Option<String>,
+    per_user: bool,
+}
+
+fn cmd_service_install(
+    args: &WatchArgs,
+    target: &Target,
+    execute: bool,
+    config_path: Option<&Path>,
+    config: &AppConfig,
+) -> Result<()> {
+    // Resolve now so a bad source or duration fails here, not at boot.
+    let watch = Watch::resolve(args, config)?;
+    let exe = std::env::current_exe().context("Cannot locate the plex-org binary")?;
+    let config_path = config_path.map(std::path::absolute).transpose()?;
+    let watch_args = service_args(args, &watch)?;
+
+    if std::env::var_os("TMDB_API_KEY").is_some() {
+        println!("Note: the service does not inherit TMDB_API_KEY; set `tmdb.api_key` in the config file.\n");
+    }
+
+    if cfg!(windows) {
+        let task = service::windows_task_args(
+            &target.name,
+            &exe,
+            config_path.as_deref(),
+            target.user.as_deref(),
+            &watch_args,
+        );
+        println!("Startup task:\n\n  schtasks {}\n", task.join(" "));
+        if !execute {
+            println!("Dry-run. Add --execute to register it (from an elevated prompt).");
+            return Ok(());
+        }
+        let status = Command::new("schtasks")
+            .args(&task)
+            .status()
+            .context("Failed to run schtasks")?;
+        if !status.success() {
+            bail!("schtasks failed ({status}); run from an elevated prompt");
+        }
+        println!(
+            "✅ Registered task {:?}; it starts at the next boot.",
+            target.name
+        );
+        println!(
+            "It is a scheduled task, not a Windows service: ending it stops the \
+             current pass midway."
+        );
+        return Ok(());
+    }
+
+    let user = match (&target.user, target.per_user) {
+        (_, true) => None,
+        (Some(user), false) => Some(user.clone()),
+        (None, false) => Some(
+            ["SUDO_USER", "USER"]
+                .iter()
+                .find_map(|v| std::env::var(v).ok())
+                .context("Cannot tell which account to run as; pass --user")?,
+        ),
+    };
+    let unit = SystemdUnit {
+        exe,
+        config: config_path,
+        user,
+        watch_args,
+    };
+    let unit_path = unit_dir(target.per_user)?.join(format!("{}.service", target.name));
+    println!("{}:\n\n{}", unit_path.display(), unit.render());
+    if !execute {
+        println!("Dry-run. Add --execute to write the unit.");
+        return Ok(());
+    }
+
+    if let Some(dir) = unit_path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&unit_path, unit.render())
+        .with_context(|| format!("Failed to write {}", unit_path.display()))?;
+    let scope = if target.per_user { " --user" } else { "" };
+    println!("✅ Wrote {}. Start it with:\n", unit_path.display());
+    println!("  systemctl{scope} daemon-reload");
+    println!("  systemctl{scope} enable --now {}", target.name);
+    Ok(())
+}
+
+/// `watch` arguments for the service, with absolute paths.
+fn service_args(args: &WatchArgs, watch: &Watch) -> Result<Vec<String>> {
+    let mut out = Vec::new();
+    // Sources from `[[watch.sources]]` are read from the config at boot.
+    if !watch.from_config {
+        for source in &watch.sources {
+            out.push(
+                std::path::absolute(&source.path)?
+                    .to_string_lossy()
+                    .into_owned(),
+            );
+        }
+    }
+    let dest = match &args.dest {
+        Some(dest) => Some(dest.as_path()),
+        None if !watch.from_config => Some(watch.sources[0].dest.as_path()),
+        None => None,
+    };
+    if let Some(dest) = dest {
+        out.push("--dest".to_string());
+        out.push(std::path::absolute(dest)?.to_string_lossy().into_owned());
+    }
+    for (flag, value) in [
+        ("--strategy", &args.strategy),
+        ("--interval", &args.interval),
+        ("--settle", &args.settle),
+    ] {
+        out.push(flag.to_string());
+        out.push(value.clone());
+    }
+    if args.movies_only {
+        out.push("--movies-only".to_string());
+    }
+    if args.allow_outside_library {
+        out.push("--allow-outside-library".to_string());
+    }
+    if args.track_library {
+        out.push("--track-library".to_string());
+    }
+    out.push("--execute".to_string());
+    Ok(out)
+}
+
+/// systemd unit directory: system-wide, or the user's own.
+fn unit_dir(per_user: bool) -> Result<PathBuf> {
+    if !per_user {
+        return Ok(PathBuf::from("/etc/systemd/system"));
+    }
+    let config_home = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME").context("HOME is not set")?).join(".config"),
+    };
+    Ok(config_home.join("systemd").join("user"))
+}
@@ -0,0 +1,210 @@
+//! The link-only view library: build, verify, sync.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use clap::Subcommand;
+
+use plex_media_organizer::config::AppConfig;
+use plex_media_organizer::view::{self, LinkKind, SyncPlan, ViewManifest};
+
+use super::mark_output;
+use super::pipeline::{hold_for_review, parse_enrich, scan_source};
+
+#[derive(Subcommand)]
+pub enum ViewAction {
+    /// Link new files from source directories into the view.
+    Build {
+        /// Source directories (defaults to `source_dirs` from config).
+        paths: Vec<PathBuf>,
+        /// View root (defaults to `organize.view_dir` from config).
+        #[arg(long)]
+        view: Option<PathBuf>,
+        /// Link kind for a new view: hardlink or symlink.
+        #[arg(long)]
+        link: Option<String>,
+        /// Movie inbox: skip TV season-pack folders as a unit.
+        #[arg(long)]
+        movies_only: bool,
+        /// Actually execute (without this flag, acts as dry-run).
+        #[arg(long)]
+        execute: bool,
+    },
+    /// Report links that drifted from their originals or the naming config.
+    Verify {
+        /// View root (defaults to `organize.view_dir` from config).
+        #[arg(long)]
+        view: Option<PathBuf>,
+    },
+    /// Repair drift and re-lay the view out under the current config.
+    Sync {
+        /// View root (defaults to `organize.view_dir` from config).
+        #[arg(long)]
+        view: Option<PathBuf>,
+        /// Actually execute (without this flag, acts as dry-run).
+        #[arg(long)]
+        execute: bool,
+    },
+}
+
+pub(super) fn cmd_view(action: ViewAction, config: &AppConfig) -> Result<()> {
+    match action {
+        ViewAction::Build {
+            paths,
+            view,
+            link,
+            movies_only,
+            execute,
+        } => {
+            let mut config = config.clone();
+            config.scan.movies_only |= movies_only;
+            let root = view_root(view, &config)?;
+            cmd_view_build(&paths, &root, link.as_deref(), execute, &config)
+        }
+        ViewAction::Verify { view } => cmd_view_verify(&view_root(view, config)?, config),
+        ViewAction::Sync { view, execute } => {
+            let root = view_root(view, config)?;
+            let mut manifest = load(&root)?;
+            let plan = view::plan_sync(&root, &manifest, config);
+            apply(&root, &mut manifest, plan, execute)
+        }
+    }
+}
+
+fn cmd_view_build(
+    paths: &[PathBuf],
+    root: &Path,
+    link: Option<&str>,
+    execute: bool,
+    config: &AppConfig,
+) -> Result<()> {
+    let sources: Vec<PathBuf> = if paths.is_empty() {
+        config.source_dirs.iter().map(PathBuf::from).collect()
+    } else {
+        paths.to_vec()
+    };
+    if sources.is_empty() {
+        bail!("No sources: pass directories or set `source_dirs` in config");
+    }
+
+    let mut manifest = ViewManifest::load(root)?.unwrap_or_default();
+    let is_new = manifest.items.is_empty() && manifest.links.is_empty();
+    if let Some(kind) = link.map(str::parse::<LinkKind>).transpose()? {
+        if is_new {
+            manifest.link_kind = kind;
+        } else if kind != manifest.link_kind {
+            bail!(
+                "{} is a {} view; build a new view to use {kind}s",
+                root.display(),
+                manifest.link_kind
+            );
+        }
+    }
+
+    // Files already in the view are not parsed or looked up again.
+    let mut items = Vec::new();
+    for source in &sources {
+        let files = scan_source(source, root, false, config)?
+            .into_iter()
+            .filter(|f| {
+                let path = std::path::absolute(&f.source_path).unwrap_or_default();
+                !manifest.items.iter().any(|i| i.source == path)
+            })
+            .collect();
+        items.extend(parse_enrich(files, execute, config)?);
+    }
+    let (ready, _held) = hold_for_review(items);
+    let added = manifest.add_items(ready);
+    println!("\n{added} new files for the view at {}.", root.display());
+
+    let plan = view::plan_sync(root, &manifest, config);
+    apply(root, &mut manifest, plan, execute)
+}
+
+fn cmd_view_verify(root: &Path, config: &AppConfig) -> Result<()> {
+    let manifest = load(root)?;
+    let drift = view::verify(root, &manifest, config);
+    if drift.is_empty() {
+        println!(
+            "✅ View at {} is in sync ({} links).",
+            root.display(),
+            manifest.links.len()
+        );
+        return Ok(());
+    }
+
+    println!("\n⚠️  {} links drifted:\n", drift.len());
+    for d in &drift {
+        println!(
+            "  {} → {} — {}",
+            d.link.link.display(),
+            d.link.source.display(),
+            d.reason
+        );
+    }
+    println!("\nRun `view sync --execute` to repair.");
+    Ok(())
+}
+
+/// Print a sync plan, and apply it with `execute`.
+fn apply(root: &Path, manifest: &mut ViewManifest, plan: SyncPlan, execute: bool) -> Result<()> {
+    if plan.is_empty() {
+        println!("Nothing to change; {} links in place.", plan.keep.len());
+        if execute {
+            manifest.save(root)?;
+        }
+        return Ok(());
+    }
+
+    println!(
+        "\n📋 View plan ({} {}s to create, {} to remove):\n",
+        plan.create.len(),
+        manifest.link_kind,
+        plan.remove.len()
+    );
+    for link in &plan.remove {
+        println!("  - {}", link.link.display());
+    }
+    for link in &plan.create {
+        println!("  + {} → {}", link.link.display(), link.source.display());
+    }
+    if !execute {
+        println!("\nDry-run. Add --execute to apply.");
+        return Ok(());
+    }
+
+    let report = view::apply_sync(root, manifest, plan);
+    manifest.save(root)?;
+    mark_output(root);
+    println!(
+        "\n✅ View updated: {} created, {} removed. Originals untouched.",
+        report.created, report.removed
+    );
+    if !report.failed.is_empty() {
+        println!("\n⚠️  {} links failed:\n", report.failed.len());
+        for (link, err) in &report.failed {
+            println!("  {} — {}", link.link.display(), err);
+        }
+    }
+    Ok(())
+}
+
+fn load(root: &Path) -> Result<ViewManifest> {
+    match ViewManifest::load(root)? {
+        Some(manifest) => Ok(manifest),
+        None => bail!(
+            "No view at {}; create one with `view build`",
+            root.display()
+        ),
+    }
+}
+
+fn view_root(view: Option<PathBuf>, config: &AppConfig) -> Result<PathBuf> {
+    match view {
+        Some(v) => Ok(v),
+        None if !config.organize.view_dir.is_empty() => {
+            Ok(PathBuf::from(&config.organize.view_dir))
+        }
+        None => bail!("No view root: pass --view or set `organize.view_dir` in config"),
+    }
+}
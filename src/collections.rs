@@ -0,0 +1,269 @@
+//! Collection listings exported from the library database.
+//!
+//! `plex-org enrich` records each movie's TMDb collection ("The Dark Knight
+//! Collection"). This module groups the library by collection and writes one
+//! playlist per collection — M3U with library paths, or a JSON manifest with
+//! TMDb ids — for scripts or tools that build Plex collections. Exports are
+//! idempotent: unchanged files are left alone, and listings for collections
+//! that no longer qualify are removed. An index in the export directory
+//! records which files the exporter owns, so nothing else there is touched.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::database::Database;
+use crate::models::MediaType;
+use crate::scanner;
+use crate::utils::sanitize_name;
+
+/// Index of exported files, kept in the export directory.
+pub const INDEX_FILE: &str = ".plex-org-collections.json";
+
+/// Listing file format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// Extended M3U playlist of library paths.
+    #[default]
+    M3u,
+    /// JSON manifest with titles, years, TMDb ids, and paths.
+    Json,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::M3u => "m3u",
+            Self::Json => "json",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "m3u" => Ok(Self::M3u),
+            "json" => Ok(Self::Json),
+            _ => bail!("Unknown format {s:?} (expected m3u or json)"),
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+/// A movie in a collection.
+#[derive(Debug, Clone, Serialize)]
+pub struct CollectionMovie {
+    pub title: String,
+    pub year: Option<i32>,
+    pub tmdb_id: Option<u64>,
+    /// Runtime in minutes, when enriched.
+    pub runtime: Option<u32>,
+    /// Path of the file inside the library.
+    pub path: String,
+}
+
+/// A TMDb collection and the library's movies in it, oldest first.
+#[derive(Debug, Clone, Serialize)]
+pub struct Collection {
+    pub name: String,
+    pub movies: Vec<CollectionMovie>,
+}
+
+/// Group the library's enriched movies by collection, keeping collections
+/// with at least `min_size` movies.
+pub fn collections(db: &Database, min_size: usize) -> Vec<Collection> {
+    let mut groups: BTreeMap<String, Vec<CollectionMovie>> = BTreeMap::new();
+    for record in db.files() {
+        if record.media_type != MediaType::Movie.to_string()
+            || !scanner::is_media_path(Path::new(&record.destination))
+        {
+            continue;
+        }
+        let Some(details) = &record.details else {
+            continue;
+        };
+        let Some(name) = &details.collection else {
+            continue;
+        };
+        groups
+            .entry(name.clone())
+            .or_default()
+            .push(CollectionMovie {
+                title: record.title.clone(),
+                year: record.year,
+                tmdb_id: record.tmdb_id,
+                runtime: details.runtime,
+                path: record.destination.clone(),
+            });
+    }
+
+    groups
+        .into_iter()
+        .filter(|(_, movies)| movies.len() >= min_size.max(1))
+        .map(|(name, mut movies)| {
+            movies.sort_by(|a, b| (a.year, &a.title).cmp(&(b.year, &b.title)));
+            Collection { name, movies }
+        })
+        .collect()
+}
+
+/// Render a collection as an extended M3U playlist.
+pub fn render_m3u(collection: &Collection) -> String {
+    let mut out = format!("#EXTM3U\n#PLAYLIST:{}\n", collection.name);
+    for movie in &collection.movies {
+        let seconds = movie.runtime.map_or(-1, |m| i64::from(m) * 60);
+        let year = movie.year.map(|y| format!(" ({y})")).unwrap_or_default();
+        out.push_str(&format!("#EXTINF:{seconds},{}{year}\n", movie.title));
+        out.push_str(&movie.path);
+        out.push('\n');
+    }
+    out
+}
+
+/// Render a collection in `format`.
+pub fn render(collection: &Collection, format: ExportFormat) -> Result<String> {
+    match format {
+        ExportFormat::M3u => Ok(render_m3u(collection)),
+        ExportFormat::Json => Ok(serde_json::to_string_pretty(collection)? + "\n"),
+    }
+}
+
+/// Files the exporter wrote on its last run.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ExportIndex {
+    files: Vec<String>,
+}
+
+/// What an export changed.
+#[derive(Debug, Default)]
+pub struct ExportReport {
+    pub written: Vec<String>,
+    pub unchanged: usize,
+    pub removed: Vec<String>,
+}
+
+/// Write one listing per collection into `dir`, removing listings from
+/// earlier exports that are no longer produced.
+pub fn export(
+    dir: &Path,
+    collections: &[Collection],
+    format: ExportFormat,
+) -> Result<ExportReport> {
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let index_path = dir.join(INDEX_FILE);
+    let previous: ExportIndex = fs::read_to_string(&index_path)
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default();
+
+    let mut report = ExportReport::default();
+    let mut index = ExportIndex::default();
+    for collection in collections {
+        let name = format!("{}.{}", sanitize_name(&collection.name), format.extension());
+        if index.files.contains(&name) {
+            continue; // Two names that sanitize alike; keep the first.
+        }
+        let content = render(collection, format)?;
+        let path = dir.join(&name);
+        if fs::read_to_string(&path).ok().as_deref() == Some(content.as_str()) {
+            report.unchanged += 1;
+        } else {
+            fs::write(&path, content)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            report.written.push(name.clone());
+        }
+        index.files.push(name);
+    }
+
+    for stale in previous.files {
+        if !index.files.contains(&stale) && fs::remove_file(dir.join(&stale)).is_ok() {
+            report.removed.push(stale);
+        }
+    }
+    fs::write(&index_path, serde_json::to_string_pretty(&index)?)
+        .with_context(|| format!("Failed to write {}", index_path.display()))?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{UndoEntry, UndoManifest};
+    use crate::providers::MovieDetails;
+
+    fn entry(title: &str, year: i32) -> UndoEntry {
+        UndoEntry {
+            source: format!("/dl/{title}.mkv"),
+            destination: format!("/plex/{title} ({year})/{title} ({year}).mkv"),
+            strategy: "move".to_string(),
+            timestamp: "2026-03-01T10:00:00+00:00".to_string(),
+            title: title.to_string(),
+            media_type: "movie".to_string(),
+            year: Some(year),
+            ..Default::default()
+        }
+    }
+
+    fn details(collection: &str) -> MovieDetails {
+        MovieDetails {
+            collection: Some(collection.to_string()),
+            runtime: Some(152),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_export_collections_and_prune_stale() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut db = Database::open(&tmp.path().join("library.json")).unwrap();
+        db.record_run(&UndoManifest {
+            entries: vec![
+                entry("The Dark Knight", 2008),
+                entry("Batman Begins", 2005),
+                entry("Alien", 1979),
+            ],
+            ..Default::default()
+        });
+        for (title, year, id) in [("The Dark Knight", 2008, 155), ("Batman Begins", 2005, 272)] {
+            let dest = format!("/plex/{title} ({year})/{title} ({year}).mkv");
+            db.set_details(&dest, id, details("The Dark Knight Collection"));
+        }
+        db.set_details(
+            "/plex/Alien (1979)/Alien (1979).mkv",
+            348,
+            details("Alien Collection"),
+        );
+
+        let found = collections(&db, 2);
+        assert_eq!(found.len(), 1);
+        let m3u = render_m3u(&found[0]);
+        assert!(m3u.starts_with("#EXTM3U\n#PLAYLIST:The Dark Knight Collection\n"));
+        let begins = m3u.find("Batman Begins (2005)").unwrap();
+        assert!(begins < m3u.find("The Dark Knight (2008)").unwrap());
+        assert!(m3u.contains("#EXTINF:9120,"));
+
+        let out = tmp.path().join("collections");
+        let report = export(&out, &collections(&db, 1), ExportFormat::M3u).unwrap();
+        assert_eq!(report.written.len(), 2);
+        fs::write(out.join("notes.txt"), b"mine").unwrap();
+
+        // A second export changes nothing; a smaller set prunes only ours.
+        let report = export(&out, &found, ExportFormat::M3u).unwrap();
+        assert_eq!(report.unchanged, 1);
+        assert_eq!(report.removed, ["Alien Collection.m3u"]);
+        assert!(out.join("notes.txt").exists());
+    }
+}
@@ -0,0 +1,288 @@
+//! Config includes and profile inheritance, with where each value came from.
+//!
+//! A config file may pull in shared fragments with a top-level
+//! `include = ["shared/tmdb.toml"]`, resolved relative to the including
+//! file. Included files are merged first, in order, and the including file
+//! overrides them; fragments may include others. A profile may name another
+//! with `inherit = "default"` and take whatever it leaves unset from it.
+//! [`ConfigSources`] records the file or profile each value came from, for
+//! `plex-org config`.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use toml::{Table, Value};
+
+use super::{ProfileRules, ProfileSettings};
+
+/// Where configured values came from, keyed by dotted path
+/// (`tmdb.api_key`, `profiles.anime.review_threshold`). Values not listed
+/// are defaults.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigSources {
+    sources: BTreeMap<String, String>,
+}
+
+impl ConfigSources {
+    /// Where the value at a dotted path came from.
+    pub fn source_of(&self, key: &str) -> Option<&str> {
+        self.sources.get(key).map(String::as_str)
+    }
+
+    /// Mark each `key = value` line of a serialized config with where the
+    /// value came from, as a TOML comment.
+    pub fn annotate(&self, toml: &str) -> String {
+        let mut table = String::new();
+        let mut out = String::new();
+        for line in toml.lines() {
+            let trimmed = line.trim();
+            if let Some(header) = trimmed.strip_prefix('[').and_then(|h| h.strip_suffix(']')) {
+                table = header.trim_matches(['[', ']']).to_string();
+            }
+            out.push_str(line);
+            let key = trimmed
+                .split_once(" = ")
+                .map(|(key, _)| key.trim_matches('"'))
+                .filter(|key| !trimmed.starts_with('#') && !key.is_empty());
+            if let Some(key) = key {
+                let path = match table.as_str() {
+                    "" => key.to_string(),
+                    table => format!("{table}.{key}"),
+                };
+                if let Some(source) = self.source_of(&path) {
+                    out.push_str(&format!("  # {source}"));
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Read a config file with its includes merged in.
+pub(super) fn load_table(path: &Path) -> Result<(Table, ConfigSources)> {
+    let mut sources = ConfigSources::default();
+    let table = load_layer(path, &mut Vec::new(), &mut sources.sources)?;
+    Ok((table, sources))
+}
+
+fn load_layer(
+    path: &Path,
+    stack: &mut Vec<PathBuf>,
+    sources: &mut BTreeMap<String, String>,
+) -> Result<Table> {
+    let canonical = path
+        .canonicalize()
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+    if let Some(start) = stack.iter().position(|p| *p == canonical) {
+        let cycle: Vec<_> = stack[start..]
+            .iter()
+            .chain([&canonical])
+            .map(|p| p.display().to_string())
+            .collect();
+        bail!("Config include cycle: {}", cycle.join(" → "));
+    }
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+    let mut own: Table = toml::from_str(&content)
+        .with_context(|| format!("Failed to parse TOML config: {}", path.display()))?;
+
+    let includes = match own.remove("include") {
+        None => Vec::new(),
+        Some(Value::String(one)) => vec![one],
+        Some(Value::Array(many)) => many
+            .into_iter()
+            .map(|v| match v {
+                Value::String(s) => Ok(s),
+                other => bail!("`include` entries must be paths, found {other}"),
+            })
+            .collect::<Result<_>>()?,
+        Some(other) => bail!("`include` must be a path or a list of paths, found {other}"),
+    };
+    stack.push(canonical);
+    let base = path.parent().unwrap_or(Path::new(""));
+    let mut merged = Table::new();
+    for include in includes {
+        let layer = load_layer(&base.join(include), stack, sources)?;
+        merge(&mut merged, layer);
+    }
+    stack.pop();
+
+    record_leaves(&own, "", &path.display().to_string(), sources);
+    merge(&mut merged, own);
+    Ok(merged)
+}
+
+/// Merge `top` over `base`, table by table.
+fn merge(base: &mut Table, top: Table) {
+    for (key, value) in top {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(top)) => merge(base, top),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn record_leaves(table: &Table, prefix: &str, source: &str, out: &mut BTreeMap<String, String>) {
+    for (key, value) in table {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{prefix}.{key}")
+        };
+        match value {
+            Value::Table(inner) => record_leaves(inner, &path, source, out),
+            _ => {
+                out.insert(path, source.to_string());
+            }
+        }
+    }
+}
+
+impl ProfileSettings {
+    fn profile(&self, name: &str) -> Option<&ProfileRules> {
+        match name {
+            "default" => Some(&self.default),
+            "anime" => Some(&self.anime),
+            _ => None,
+        }
+    }
+
+    fn profile_mut(&mut self, name: &str) -> &mut ProfileRules {
+        match name {
+            "anime" => &mut self.anime,
+            _ => &mut self.default,
+        }
+    }
+
+    /// The profile followed by the profiles it inherits from, nearest first.
+    fn lineage(&self, name: &str) -> Result<Vec<String>> {
+        let mut chain = vec![name.to_string()];
+        let mut current = name;
+        while let Some(parent) = self.profile(current).and_then(|p| p.inherit.as_deref()) {
+            if self.profile(parent).is_none() {
+                bail!("profiles.{current} inherits from unknown profile {parent:?}");
+            }
+            if chain.iter().any(|c| c == parent) {
+                chain.push(parent.to_string());
+                bail!("Profile inheritance cycle: {}", chain.join(" → "));
+            }
+            chain.push(parent.to_string());
+            current = parent;
+        }
+        Ok(chain)
+    }
+
+    /// Fill each profile's unset values from the profiles it inherits from,
+    /// recording which profile supplied them.
+    pub(super) fn resolve_inheritance(&mut self, sources: &mut ConfigSources) -> Result<()> {
+        let original = self.clone();
+        for name in ["default", "anime"] {
+            let lineage = original.lineage(name)?;
+            let rules = self.profile_mut(name);
+            for ancestor in &lineage[1..] {
+                let parent = original.profile(ancestor).unwrap();
+                let mut inherited = Vec::new();
+                if rules.review_threshold.is_none() && parent.review_threshold.is_some() {
+                    rules.review_threshold = parent.review_threshold;
+                    inherited.push("review_threshold");
+                }
+                if rules.min_match_score.is_none() && parent.min_match_score.is_some() {
+                    rules.min_match_score = parent.min_match_score;
+                    inherited.push("min_match_score");
+                }
+                if rules.strip_terms.is_empty() && !parent.strip_terms.is_empty() {
+                    rules.strip_terms = parent.strip_terms.clone();
+                    inherited.push("strip_terms");
+                }
+                if rules.skip_terms.is_empty() && !parent.skip_terms.is_empty() {
+                    rules.skip_terms = parent.skip_terms.clone();
+                    inherited.push("skip_terms");
+                }
+                if rules.preserve_artwork.is_none() && parent.preserve_artwork.is_some() {
+                    rules.preserve_artwork = parent.preserve_artwork;
+                    inherited.push("preserve_artwork");
+                }
+                for field in inherited {
+                    let from = format!("profiles.{ancestor}.{field}");
+                    let source = match sources.source_of(&from) {
+                        Some(file) => format!("inherited from {from} ({file})"),
+                        None => format!("inherited from {from}"),
+                    };
+                    sources
+                        .sources
+                        .insert(format!("profiles.{name}.{field}"), source);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+
+    #[test]
+    fn test_includes_and_inheritance() {
+        let tmp = tempfile::tempdir().unwrap();
+        let shared = tmp.path().join("shared");
+        fs::create_dir_all(&shared).unwrap();
+        fs::write(
+            shared.join("tmdb.toml"),
+            "[tmdb]\nregion = \"GB\"\nmin_match_score = 60.0\n",
+        )
+        .unwrap();
+        let main = tmp.path().join("config.toml");
+        fs::write(
+            &main,
+            r#"
+            include = ["shared/tmdb.toml"]
+            [tmdb]
+            min_match_score = 80.0
+            [profiles.default]
+            review_threshold = 40.0
+            skip_terms = ["Trailer"]
+            [profiles.anime]
+            inherit = "default"
+            skip_terms = ["NCOP"]
+            "#,
+        )
+        .unwrap();
+
+        let (config, sources) = AppConfig::load_traced(&main).unwrap();
+        assert_eq!(config.tmdb.region, "GB");
+        assert_eq!(config.tmdb.min_match_score, 80.0);
+        assert_eq!(config.profiles.anime.review_threshold, Some(40.0));
+        assert_eq!(config.profiles.anime.skip_terms, ["NCOP"]);
+        let shared_file = shared.join("tmdb.toml").display().to_string();
+        assert_eq!(sources.source_of("tmdb.region"), Some(shared_file.as_str()));
+        assert_eq!(
+            sources.source_of("tmdb.min_match_score"),
+            Some(main.display().to_string().as_str())
+        );
+        assert!(sources
+            .source_of("profiles.anime.review_threshold")
+            .unwrap()
+            .starts_with("inherited from profiles.default.review_threshold"));
+        let annotated = sources.annotate("[tmdb]\nregion = \"GB\"\n");
+        assert!(annotated.contains("region = \"GB\"  # "));
+
+        fs::write(shared.join("tmdb.toml"), "include = \"../config.toml\"\n").unwrap();
+        let error = AppConfig::load_traced(&main).unwrap_err();
+        assert!(error.to_string().contains("include cycle"), "{error}");
+
+        let mut profiles = ProfileSettings::default();
+        profiles.default.inherit = Some("anime".to_string());
+        profiles.anime.inherit = Some("default".to_string());
+        let error = profiles
+            .resolve_inheritance(&mut ConfigSources::default())
+            .unwrap_err();
+        assert!(error.to_string().contains("cycle"), "{error}");
+    }
+}
@@ -0,0 +1,419 @@
+//! Application configuration loaded from TOML.
+
+pub mod layers;
+mod organize;
+mod providers;
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::collections::ExportFormat;
+use crate::error::Error;
+use crate::hash::HashMode;
+use crate::profile::Profile;
+use crate::scoring::ScoringWeights;
+pub use layers::ConfigSources;
+pub use organize::{
+    DoubleFeatures, Extras, IdTags, OrganizeSettings, Pipeline, Provenance, DEFAULT_CLEANUP_JUNK,
+};
+pub use providers::{AniListSettings, OmdbSettings, ProviderKind, ProviderSettings, TmdbSettings};
+
+/// Top-level configuration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    /// Directories to scan for media files.
+    pub source_dirs: Vec<String>,
+    /// Root destination directory for organized files.
+    pub destination: String,
+    /// Confidence threshold above which files are auto-organized.
+    pub auto_organize_threshold: f64,
+    /// Below this threshold, files are flagged for manual review.
+    pub review_threshold: f64,
+    pub scan: ScanSettings,
+    pub parsing: ParsingSettings,
+    pub organize: OrganizeSettings,
+    pub tmdb: TmdbSettings,
+    pub anilist: AniListSettings,
+    pub omdb: OmdbSettings,
+    pub providers: ProviderSettings,
+    pub export: ExportSettings,
+    pub profiles: ProfileSettings,
+    pub watch: WatchSettings,
+    pub plex: PlexSettings,
+    pub notify: NotifySettings,
+    /// Go easy on a shared machine: paced TMDb requests, one file operation
+    /// at a time, and low CPU and disk priority (see `pacing`).
+    pub low_impact: bool,
+    /// Default values for command-line flags, keyed by long flag name
+    /// (`max-parallel = 4`); flags given on the command line win.
+    pub cli_defaults: BTreeMap<String, toml::Value>,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            source_dirs: Vec::new(),
+            destination: String::new(),
+            auto_organize_threshold: 90.0,
+            review_threshold: 50.0,
+            scan: ScanSettings::default(),
+            parsing: ParsingSettings::default(),
+            organize: OrganizeSettings::default(),
+            tmdb: TmdbSettings::default(),
+            anilist: AniListSettings::default(),
+            omdb: OmdbSettings::default(),
+            providers: ProviderSettings::default(),
+            export: ExportSettings::default(),
+            profiles: ProfileSettings::default(),
+            watch: WatchSettings::default(),
+            plex: PlexSettings::default(),
+            notify: NotifySettings::default(),
+            low_impact: false,
+            cli_defaults: BTreeMap::new(),
+        }
+    }
+}
+
+/// Settings for the directory scanner.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScanSettings {
+    /// Videos smaller than this many MB are skipped (menus/promos). 0 disables.
+    pub min_video_size_mb: u64,
+    /// Videos smaller than this fraction of the largest video in the same
+    /// folder are treated as samples when named like one (`smpl`). 0
+    /// disables.
+    pub sample_size_ratio: f64,
+    /// Sources are movie inboxes: skip TV season-pack folders as a unit.
+    pub movies_only: bool,
+    /// Each top-level source folder holds exactly one movie (plus extras).
+    pub one_movie_per_folder: bool,
+    /// What to do with phone/camera recordings (see `home_video`).
+    pub home_videos: HomeVideos,
+    /// File and folder names never scanned, ignoring case; by default
+    /// Plex's own artifacts (`Plex Versions`, `.plexmatch`) and OS litter.
+    pub exclude_names: Vec<String>,
+    /// Deepest folder level scanned below a source; 0 is unlimited.
+    pub max_depth: usize,
+    /// Folders with more entries than this are skipped and reported, not
+    /// scanned; 0 is unlimited.
+    pub max_files_per_dir: usize,
+    /// Read each video's streams with ffprobe: duration, resolution,
+    /// codec, and tracks are kept, and the measured resolution replaces
+    /// the one in the name.
+    pub probe: bool,
+    /// Hash file contents to find duplicates and follow moved files: off,
+    /// partial (the first and last `hash_partial_mb`), or full.
+    pub hash_mode: HashMode,
+    /// MB read from each end of a file in partial hash mode.
+    pub hash_partial_mb: u64,
+}
+
+/// Handling of phone and camera recordings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum HomeVideos {
+    /// Leave them where they are.
+    #[default]
+    Skip,
+    /// File them under `<home_videos_dir>/YYYY/MM/` by recording date.
+    Organize,
+}
+
+impl Default for ScanSettings {
+    fn default() -> Self {
+        Self {
+            min_video_size_mb: 50,
+            sample_size_ratio: 0.10,
+            movies_only: false,
+            one_movie_per_folder: false,
+            home_videos: HomeVideos::default(),
+            exclude_names: crate::scanner::default_exclude_names(),
+            max_depth: 0,
+            max_files_per_dir: crate::scanner::DEFAULT_MAX_FILES_PER_DIR,
+            probe: false,
+            hash_mode: HashMode::default(),
+            hash_partial_mb: 4,
+        }
+    }
+}
+
+/// Settings for filename parsing.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParsingSettings {
+    /// Weights of parse and match confidence (see `scoring`).
+    pub scoring: ScoringWeights,
+    /// How the before-year title is cut from a name (see `title_strategy`).
+    pub title_extraction: TitleExtraction,
+}
+
+/// How the before-year title is cut from a name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TitleExtraction {
+    /// Split into bracket groups and words; the title ends at the first
+    /// group, year, or technical word, including split ones (`Blu.ray`).
+    #[default]
+    Positional,
+    /// Judge each word alone, as earlier releases did.
+    Tokens,
+}
+
+/// Plex Media Server to refresh after organizing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlexSettings {
+    /// Server URL, e.g. `http://localhost:32400`. Empty disables Plex.
+    pub url: String,
+    /// `X-Plex-Token` for the server.
+    pub token: String,
+    /// After the refresh, compare Plex's title and year for each new movie
+    /// with the TMDb match and report disagreements.
+    pub verify: bool,
+    /// Time Plex is given to scan before the comparison, in seconds.
+    pub verify_wait_secs: u64,
+    /// Scan only the folders a run touched rather than whole sections.
+    pub partial_scan: bool,
+    /// Local path prefixes and the prefix Plex sees each under, for a
+    /// server that mounts the library elsewhere (`"/mnt/media" = "/data"`).
+    pub path_map: BTreeMap<String, String>,
+}
+
+impl Default for PlexSettings {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            token: String::new(),
+            verify: false,
+            verify_wait_secs: 60,
+            partial_scan: true,
+            path_map: BTreeMap::new(),
+        }
+    }
+}
+
+/// Sources watched by `plex-org watch` when none are given on the command
+/// line, each with its own profile and library root. Each source runs its
+/// own passes, so a busy one does not hold up the others.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchSettings {
+    pub sources: Vec<WatchSource>,
+    /// Cron expression (`"0 3 * * *"`, local time) for full runs over
+    /// every source, alongside the interval passes; empty for none.
+    pub schedule: String,
+}
+
+/// One `[[watch.sources]]` entry.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchSource {
+    /// Directory to watch.
+    pub path: String,
+    /// Profile for every file from this source; unset, it is detected
+    /// per file as usual.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<Profile>,
+    /// Library root for this source; unset, `--dest` or `destination`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub destination: Option<String>,
+    /// Movie inbox: skip TV season-pack folders as a unit.
+    pub movies_only: bool,
+}
+
+/// Endpoints told about each `organize --execute` and `watch` run that
+/// did something (see `notify`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotifySettings {
+    pub hooks: Vec<NotifyHook>,
+}
+
+/// One `[[notify.hooks]]` entry.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotifyHook {
+    /// URL the run summary is POSTed to.
+    pub url: String,
+    pub format: HookFormat,
+}
+
+/// Body of a notification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum HookFormat {
+    /// The run summary as JSON, for scripts and automation tools.
+    #[default]
+    Json,
+    /// A Discord webhook message.
+    Discord,
+    /// A Slack incoming-webhook message.
+    Slack,
+}
+
+/// Settings for exported collection listings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportSettings {
+    /// Directory for per-collection listings. Set to refresh them after
+    /// each `plex-org enrich`; empty disables the refresh.
+    pub collections_dir: String,
+    /// Listing format: `m3u` or `json`.
+    pub collections_format: ExportFormat,
+    /// Collections with fewer movies in the library are not exported.
+    pub min_collection_size: usize,
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        Self {
+            collections_dir: String::new(),
+            collections_format: ExportFormat::M3u,
+            min_collection_size: 2,
+        }
+    }
+}
+
+/// Per-profile overrides: files detected as anime use `anime`, all others
+/// use `default`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfileSettings {
+    /// Pick the anime profile for files that look like fansub releases.
+    /// Off: every file uses the default profile.
+    pub detect_anime: bool,
+    /// Use this profile for every file, whatever detection says.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub force: Option<Profile>,
+    pub default: ProfileRules,
+    pub anime: ProfileRules,
+}
+
+/// Rules for one profile. Unset values come from the profile named by
+/// `inherit`, if any; after that, unset thresholds fall back to the
+/// top-level `review_threshold` and `tmdb.min_match_score`, unset switches
+/// to their `[organize]` counterparts.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfileRules {
+    /// Profile (`default` or `anime`) whose values fill the unset ones.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inherit: Option<String>,
+    pub review_threshold: Option<f64>,
+    pub min_match_score: Option<f64>,
+    /// Release terms removed from parsed titles (e.g., "Dual Audio").
+    pub strip_terms: Vec<String>,
+    /// Files whose names contain one of these terms are skipped (e.g., "NCOP").
+    pub skip_terms: Vec<String>,
+    /// Overrides `organize.preserve_artwork`.
+    pub preserve_artwork: Option<bool>,
+}
+
+impl AppConfig {
+    /// Load config from a TOML file, falling back to defaults for missing fields.
+    pub fn load(path: &Path) -> crate::Result<Self> {
+        Self::load_traced(path).map(|(config, _)| config)
+    }
+
+    /// Load config with its includes and profile inheritance resolved, and
+    /// where each value came from.
+    pub fn load_traced(path: &Path) -> crate::Result<(Self, ConfigSources)> {
+        Self::read_traced(path).map_err(Error::Config)
+    }
+
+    fn read_traced(path: &Path) -> Result<(Self, ConfigSources)> {
+        let (table, mut sources) = layers::load_table(path)?;
+        let mut config: Self = toml::Value::Table(table)
+            .try_into()
+            .with_context(|| "Failed to parse TOML config")?;
+        config.profiles.resolve_inheritance(&mut sources)?;
+        Ok((config, sources))
+    }
+
+    /// A copy with the value at a dotted path (`profiles.detect_anime`)
+    /// replaced. `value` is read as TOML (`true`, `3`, `["NCOP"]`), or taken
+    /// as a plain string when it is not valid TOML.
+    pub fn with_value(&self, key: &str, value: &str) -> crate::Result<Self> {
+        self.replaced(key, value).map_err(Error::Config)
+    }
+
+    fn replaced(&self, key: &str, value: &str) -> Result<Self> {
+        let parsed = toml::from_str::<toml::Table>(&format!("v = {value}"))
+            .ok()
+            .and_then(|mut t| t.remove("v"))
+            .unwrap_or_else(|| toml::Value::String(value.to_string()));
+        let mut root = toml::Value::try_from(self)?;
+        let (parents, leaf) = match key.rsplit_once('.') {
+            Some((parents, leaf)) => (parents.split('.').collect(), leaf),
+            None => (Vec::new(), key),
+        };
+        let mut table = root.as_table_mut().context("Config is not a table")?;
+        for part in parents {
+            table = match table.get_mut(part).and_then(|v| v.as_table_mut()) {
+                Some(inner) => inner,
+                None => bail!("Unknown config section in {key:?}"),
+            };
+        }
+        table.insert(leaf.to_string(), parsed);
+        let config: Self = root
+            .try_into()
+            .with_context(|| format!("Invalid value for {key}: {value}"))?;
+        // Keys serde does not know are dropped silently; catch typos here.
+        let mut check = toml::Value::try_from(&config)?;
+        for part in key.split('.') {
+            check = match check.get(part) {
+                Some(inner) => inner.clone(),
+                None => bail!("Unknown config key {key:?}"),
+            };
+        }
+        Ok(config)
+    }
+
+    /// Load from a file if it exists, otherwise return defaults.
+    pub fn load_or_default(path: Option<&Path>) -> Self {
+        match path {
+            Some(p) if p.exists() => Self::load(p).unwrap_or_default(),
+            _ => Self::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_config_falls_back_to_defaults() {
+        let config: AppConfig = toml::from_str(
+            r#"
+            [organize]
+            provenance = "sidecar"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.organize.provenance, Provenance::Sidecar);
+        assert_eq!(config.organize.movies_dir, "Movies");
+        assert_eq!(config.review_threshold, 50.0);
+    }
+
+    #[test]
+    fn test_with_value() {
+        let config = AppConfig::default();
+        let changed = config.with_value("profiles.detect_anime", "true").unwrap();
+        assert!(changed.profiles.detect_anime);
+        let changed = changed.with_value("profiles.force", "anime").unwrap();
+        assert_eq!(changed.profiles.force, Some(Profile::Anime));
+        let changed = changed
+            .with_value("profiles.default.strip_terms", r#"["Dual Audio"]"#)
+            .unwrap();
+        assert_eq!(changed.profiles.default.strip_terms, ["Dual Audio"]);
+        assert!(config.with_value("profiles.detect_anim", "true").is_err());
+        assert!(config.with_value("nope.detect_anime", "true").is_err());
+        assert!(config.with_value("review_threshold", "high").is_err());
+    }
+}
@@ -0,0 +1,247 @@
+//! The `[organize]` section: how planned files are placed in the library.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Settings for file organization.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OrganizeSettings {
+    /// File operation strategy: "move", "copy", "hardlink", "reflink", or
+    /// "symlink".
+    pub strategy: String,
+    /// Subdirectory name for movies.
+    pub movies_dir: String,
+    /// Subdirectory name for TV shows.
+    pub tv_dir: String,
+    /// Subdirectory name for music.
+    pub music_dir: String,
+    /// Subdirectory name for home videos (`scan.home_videos = "organize"`).
+    pub home_videos_dir: String,
+    /// Movie and show names in the library: `{title}` (primary),
+    /// `{original}`, `{romanized}`, and `{english}` (see `titles`), and the
+    /// ids `{tmdb_id}`, `{imdb_id}`, and `{tvdb_id}`, e.g.
+    /// `"{english} [{original}]"`.
+    pub title_template: String,
+    /// File movies of a TMDb collection under `collection_template` rather
+    /// than `movies_dir`; costs one TMDb details request per movie.
+    pub collection_folders: bool,
+    /// Folder of a collection's movies, relative to the library root;
+    /// `{collection}` is the collection name ("Iron Man Collection").
+    pub collection_template: String,
+    /// Longest generated file or folder name, in bytes (0 = no limit).
+    /// Titles are shortened to fit; years, tags, and extensions are kept.
+    pub max_name_length: usize,
+    /// Staging root for two-stage organization (`organize --stage`).
+    /// Items land in `<staging_dir>/YYYY-MM/` until promoted.
+    pub staging_dir: String,
+    /// Minimum days an item stays in staging before `promote` moves it.
+    pub promote_after_days: u64,
+    /// Where to record each file's original path besides the database.
+    pub provenance: Provenance,
+    /// Remove source folders a move leaves holding nothing but
+    /// `cleanup_junk` (see `organizer::SourceCleanup`); `--cleanup-source`.
+    pub cleanup_source: bool,
+    /// File name patterns (`*.nfo`) of leftovers that do not keep a source
+    /// folder from being removed; they are moved aside, not deleted.
+    pub cleanup_junk: Vec<String>,
+    /// A movie whose folder already holds a copy replaces it when of
+    /// better quality, and is skipped otherwise (see `organizer::upgrade`);
+    /// `--upgrade`. Off, the new file is added beside the old one.
+    pub upgrade: bool,
+    /// Where replaced copies are moved; empty is `recycle/` in the undo
+    /// directory.
+    pub recycle_dir: String,
+    /// Megabytes each destination filesystem must keep free after a run;
+    /// runs needing more are refused before any file is touched.
+    pub space_reserve_mb: u64,
+    /// Verify every operation by size and quick hash (slower; for flaky mounts).
+    pub paranoid: bool,
+    /// Hash every byte of copied files before and after the copy, keeping
+    /// the source when they differ, and store the hash in the database;
+    /// `--verify`.
+    pub verify_checksums: bool,
+    /// File operations run at once (1 = one at a time); the upper bound
+    /// when `adaptive_parallel` is on.
+    pub max_parallel: usize,
+    /// Lower bound for adaptive concurrency.
+    pub min_parallel: usize,
+    /// Tune concurrency between `min_parallel` and `max_parallel` from
+    /// observed operation latency and errors, retrying failed operations.
+    pub adaptive_parallel: bool,
+    /// MB per second copied to or from any one filesystem, shared by the
+    /// operations in flight (0 = no limit); `--io-limit`.
+    pub io_limit_mb: u64,
+    /// Whether organize runs are journaled (see `organizer::journal`).
+    pub pipeline: Pipeline,
+    /// Actions from which an `auto` pipeline run is journaled.
+    pub journal_threshold: usize,
+    /// Move posters, fanart, and theme music of curated movie folders along
+    /// with the movie (see `profiles.*.preserve_artwork`).
+    pub preserve_artwork: bool,
+    /// What to do with trailers, featurettes, and other extras of movies
+    /// (see `extras`).
+    pub extras: Extras,
+    /// Write a Kodi-style `movie.nfo` beside each organized movie (see
+    /// `nfo`), with TMDb details when a key is set.
+    pub write_nfo: bool,
+    /// How a file holding two movies is named (see `double_feature`).
+    pub double_features: DoubleFeatures,
+    /// Database id tag appended to movie and show names, which Plex
+    /// matches exactly: `Heat (1995) {tmdb-949}`.
+    pub id_tags: IdTags,
+    /// Release details put after movie and episode file names (not
+    /// folders), e.g. `"[{resolution} {hdr}]"` → `Dune (2021) [2160p
+    /// DV].mkv`; see `titles::render_info`. Empty adds nothing.
+    pub file_info_template: String,
+    /// Name movies with the edition their file names, as a Plex edition
+    /// tag: `Aliens (1986) {edition-Director's Cut}` (see `edition`).
+    pub edition_tags: bool,
+    /// Route movies and TV whose title language is detected with confidence
+    /// into a per-language top folder, keyed by ISO 639-1 code
+    /// (e.g., `ko = "Korean"` → `<dest>/Korean/Movies/...`).
+    pub language_dirs: BTreeMap<String, String>,
+    /// Root of the link-only view library built by `plex-org view`.
+    pub view_dir: String,
+    /// More library locations. Organizing anywhere outside these,
+    /// `destination`, `staging_dir`, and `view_dir` needs confirmation.
+    pub library_roots: Vec<String>,
+    /// Extra paths (e.g., mount points) that are never organized into.
+    pub protected_paths: Vec<String>,
+}
+
+/// Default `organize.cleanup_junk`: release notes, checksums, links,
+/// screenshots, samples, and OS litter.
+pub const DEFAULT_CLEANUP_JUNK: &[&str] = &[
+    "*.nfo",
+    "*.txt",
+    "*.sfv",
+    "*.md5",
+    "*.url",
+    "*.jpg",
+    "*.png",
+    "*sample*",
+    ".DS_Store",
+    "Thumbs.db",
+    "desktop.ini",
+];
+
+/// Where to keep the original-path breadcrumb for organized files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Provenance {
+    /// Library database only.
+    #[default]
+    Database,
+    /// `<file>.origin.json` sidecar next to the organized file.
+    Sidecar,
+    /// Extended attribute on the organized file (Linux/macOS).
+    Xattr,
+}
+
+/// How an organize run keeps its undo record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Pipeline {
+    /// Quick below `journal_threshold` actions, journaled from there.
+    #[default]
+    Auto,
+    /// In memory, written as the undo manifest when the run ends.
+    Quick,
+    /// Also journaled to disk file by file, so an interrupted run can be
+    /// undone and resumed.
+    Journaled,
+}
+
+/// Handling of movie extras (`organize.extras`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Extras {
+    /// Leave them where they are.
+    #[default]
+    Skip,
+    /// Move them with their movie into Plex's extras folders
+    /// (`Heat (1995)/Trailers/`).
+    Organize,
+}
+
+/// Naming of double-feature files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DoubleFeatures {
+    /// Named after the first movie; the second is recorded in the library
+    /// database and plan.
+    #[default]
+    Primary,
+    /// Also named after the first movie, with a Plex edition tag naming the
+    /// second (`{edition-With Death Proof}`), so Plex shows it.
+    Edition,
+}
+
+/// Which database id tag names carry (`organize.id_tags`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum IdTags {
+    #[default]
+    Off,
+    /// `{tmdb-949}`, when the TMDb id is known.
+    Tmdb,
+    /// `{imdb-tt0113277}` for movies whose IMDb id is known, `{tmdb-…}`
+    /// otherwise.
+    Imdb,
+}
+
+impl Default for OrganizeSettings {
+    fn default() -> Self {
+        Self {
+            strategy: "move".to_string(),
+            movies_dir: "Movies".to_string(),
+            tv_dir: "TV Shows".to_string(),
+            music_dir: "Music".to_string(),
+            home_videos_dir: "Home Videos".to_string(),
+            title_template: "{title}".to_string(),
+            collection_folders: false,
+            collection_template: "Collections/{collection}".to_string(),
+            max_name_length: 255,
+            staging_dir: String::new(),
+            promote_after_days: 7,
+            provenance: Provenance::default(),
+            cleanup_source: false,
+            cleanup_junk: DEFAULT_CLEANUP_JUNK.iter().map(|p| p.to_string()).collect(),
+            upgrade: false,
+            recycle_dir: String::new(),
+            space_reserve_mb: 1024,
+            paranoid: false,
+            verify_checksums: false,
+            max_parallel: 1,
+            min_parallel: 1,
+            adaptive_parallel: false,
+            io_limit_mb: 0,
+            pipeline: Pipeline::default(),
+            journal_threshold: 500,
+            preserve_artwork: true,
+            extras: Extras::default(),
+            write_nfo: false,
+            double_features: DoubleFeatures::default(),
+            id_tags: IdTags::default(),
+            file_info_template: String::new(),
+            edition_tags: false,
+            language_dirs: BTreeMap::new(),
+            view_dir: String::new(),
+            library_roots: Vec::new(),
+            protected_paths: Vec::new(),
+        }
+    }
+}
+
+impl OrganizeSettings {
+    /// Whether a run of `actions` file operations is journaled.
+    pub fn journals(&self, actions: usize) -> bool {
+        match self.pipeline {
+            Pipeline::Auto => actions >= self.journal_threshold,
+            Pipeline::Quick => false,
+            Pipeline::Journaled => true,
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! The metadata provider sections: `[tmdb]`, `[anilist]`, `[omdb]` and
+//! the `[providers]` fallback order.
+
+use serde::{Deserialize, Serialize};
+
+/// Settings for TMDb lookups during enrichment.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TmdbSettings {
+    /// TMDb v3 API key or v4 read access token. Empty disables lookups;
+    /// the CLI also reads `TMDB_API_KEY`.
+    pub api_key: String,
+    /// Minimum match score (0–100) to accept a TMDb result.
+    pub min_match_score: f64,
+    /// Request rate cap (TMDb allows roughly 50/s). 0 disables the cap.
+    pub requests_per_second: u32,
+    /// Pause after every request on top of the rate cap, in milliseconds.
+    pub politeness_delay_ms: u64,
+    /// Random extra pause of up to this many milliseconds per request.
+    pub jitter_ms: u64,
+    /// ISO 3166-1 country whose certification (rating) is recorded.
+    pub region: String,
+    /// Files whose parsed title and year exactly match a TMDb result are
+    /// never held for review, whatever the review threshold.
+    pub exact_match_override: bool,
+    /// Rounds of end-of-run retries for lookups that hit a server error or
+    /// timeout. 0 disables them.
+    pub retry_attempts: u32,
+    /// Wait before the first retry round, in seconds; doubled each round,
+    /// up to five minutes.
+    pub retry_backoff_secs: u64,
+    /// Result pages (20 titles each) searched for a parsed title when the
+    /// first page has no acceptable match. 1 searches the first page only.
+    pub max_search_pages: u32,
+    /// Distinct title searches made at once before matching, sharing the
+    /// rate cap. 1 searches one file at a time as it is matched.
+    pub concurrent_lookups: usize,
+    /// Hours a TMDb response is kept between runs (`tmdb_cache.json`).
+    /// 0 disables the cache.
+    pub cache_ttl_hours: u64,
+    /// Hours a search that found nothing is kept. 0 keeps none.
+    pub negative_cache_ttl_hours: u64,
+}
+
+impl Default for TmdbSettings {
+    fn default() -> Self {
+        Self {
+            api_key: String::new(),
+            min_match_score: 75.0,
+            requests_per_second: 20,
+            politeness_delay_ms: 0,
+            jitter_ms: 0,
+            region: "US".to_string(),
+            exact_match_override: true,
+            retry_attempts: 2,
+            retry_backoff_secs: 5,
+            max_search_pages: 3,
+            concurrent_lookups: 4,
+            cache_ttl_hours: 168,
+            negative_cache_ttl_hours: 24,
+        }
+    }
+}
+
+/// Settings for AniList lookups of anime (see `providers::anilist`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AniListSettings {
+    /// Identify fansub releases and files of the anime profile on AniList
+    /// before the TMDb search. AniList needs no key.
+    pub enabled: bool,
+    /// Request rate cap (AniList allows 90 a minute, 30 when degraded).
+    /// 0 disables the cap.
+    pub requests_per_minute: u32,
+}
+
+impl Default for AniListSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            requests_per_minute: 30,
+        }
+    }
+}
+
+/// Settings for OMDb movie searches (see `providers::omdb`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OmdbSettings {
+    /// OMDb API key (free at omdbapi.com). Empty disables OMDb; the CLI
+    /// also reads `OMDB_API_KEY`.
+    pub api_key: String,
+    /// Request rate cap. 0 disables the cap.
+    pub requests_per_second: u32,
+}
+
+impl Default for OmdbSettings {
+    fn default() -> Self {
+        Self {
+            api_key: String::new(),
+            requests_per_second: 5,
+        }
+    }
+}
+
+/// A movie search provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    Tmdb,
+    Omdb,
+    /// The offline IMDb dataset (`offline-db import`).
+    Imdb,
+}
+
+/// Which providers confirm movie titles, and in what order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProviderSettings {
+    /// Providers searched for a movie, first to last, until one matches.
+    /// Providers without an API key (or offline database) are left out.
+    pub order: Vec<ProviderKind>,
+    /// Offline database written by `offline-db import`. Empty means
+    /// `~/.plex-organizer/imdb_movies.tsv` for the CLI.
+    pub offline_db: String,
+}
+
+impl Default for ProviderSettings {
+    fn default() -> Self {
+        Self {
+            order: vec![ProviderKind::Tmdb, ProviderKind::Omdb, ProviderKind::Imdb],
+            offline_db: String::new(),
+        }
+    }
+}
@@ -0,0 +1,309 @@
+//! TMDb responses kept between runs (`tmdb_cache.json`).
+//!
+//! Rescanning a download folder asks TMDb the same questions as the run
+//! before. Successful responses are kept beside the library database for
+//! `tmdb.cache_ttl_hours` and answer those requests without the network.
+//! A search that found nothing is kept too, for the shorter
+//! `tmdb.negative_cache_ttl_hours`, so a title TMDb does not know costs one
+//! request a day rather than one per run; failed requests are never kept.
+//! Requests are keyed as in cassettes (see `providers::cassette`), without
+//! the API key; OMDb searches are kept the same way.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::config::TmdbSettings;
+
+/// Current cache file format version.
+pub const VERSION: u32 = 1;
+
+/// One kept response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub body: String,
+    pub fetched_at: DateTime<Utc>,
+    /// A search that found nothing, kept for the negative TTL.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub empty: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    #[serde(default)]
+    entries: BTreeMap<String, CachedResponse>,
+}
+
+/// Counts for `cache stats`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CacheStats {
+    pub entries: usize,
+    /// Entries kept for a search that found nothing.
+    pub negative: usize,
+    /// Entries past their TTL, dropped on the next save.
+    pub expired: usize,
+    pub bytes: u64,
+    pub oldest: Option<DateTime<Utc>>,
+}
+
+/// The response cache of one data directory.
+#[derive(Debug)]
+pub struct ResponseCache {
+    path: PathBuf,
+    ttl: Duration,
+    negative_ttl: Duration,
+    entries: Mutex<BTreeMap<String, CachedResponse>>,
+    changed: AtomicBool,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl ResponseCache {
+    /// Load the cache at `path`; a missing file is an empty cache.
+    pub fn open(path: &Path, settings: &TmdbSettings) -> Result<Self> {
+        let file = match fs::read_to_string(path) {
+            Ok(content) => {
+                let file: CacheFile = serde_json::from_str(&content)
+                    .with_context(|| format!("Invalid TMDb cache: {}", path.display()))?;
+                if file.version > VERSION {
+                    bail!(
+                        "TMDb cache {} is version {}, newer than this plex-org ({VERSION})",
+                        path.display(),
+                        file.version
+                    );
+                }
+                file
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => CacheFile::default(),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            ttl: Duration::hours(settings.cache_ttl_hours as i64),
+            negative_ttl: Duration::hours(settings.negative_cache_ttl_hours as i64),
+            entries: Mutex::new(file.entries),
+            changed: AtomicBool::new(false),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Answer `request` from the cache while its entry is fresh, otherwise
+    /// by calling `live` and keeping a success. `empty` tells a search that
+    /// found nothing from one that did.
+    pub fn fetch(
+        &self,
+        request: &str,
+        empty: impl FnOnce(&str) -> bool,
+        live: impl FnOnce() -> Result<String>,
+    ) -> Result<String> {
+        let now = Utc::now();
+        if let Some(cached) = lock(&self.entries).get(request) {
+            if self.is_fresh(cached, now) {
+                debug!("Answering {request} from the TMDb cache");
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(cached.body.clone());
+            }
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let body = live()?;
+        let entry = CachedResponse {
+            empty: empty(&body),
+            body: body.clone(),
+            fetched_at: now,
+        };
+        if self.ttl_of(&entry) > Duration::zero() {
+            lock(&self.entries).insert(request.to_string(), entry);
+            self.changed.store(true, Ordering::Relaxed);
+        }
+        Ok(body)
+    }
+
+    fn ttl_of(&self, entry: &CachedResponse) -> Duration {
+        if entry.empty {
+            self.negative_ttl
+        } else {
+            self.ttl
+        }
+    }
+
+    fn is_fresh(&self, entry: &CachedResponse, now: DateTime<Utc>) -> bool {
+        now - entry.fetched_at < self.ttl_of(entry)
+    }
+
+    /// Requests answered from the cache and from TMDb so far.
+    pub fn hit_counts(&self) -> (usize, usize) {
+        (
+            self.hits.load(Ordering::Relaxed),
+            self.misses.load(Ordering::Relaxed),
+        )
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let now = Utc::now();
+        let entries = lock(&self.entries);
+        CacheStats {
+            entries: entries.len(),
+            negative: entries.values().filter(|e| e.empty).count(),
+            expired: entries.values().filter(|e| !self.is_fresh(e, now)).count(),
+            bytes: fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0),
+            oldest: entries.values().map(|e| e.fetched_at).min(),
+        }
+    }
+
+    /// Drop every entry. Returns how many there were.
+    pub fn clear(&self) -> usize {
+        let mut entries = lock(&self.entries);
+        let count = entries.len();
+        entries.clear();
+        self.changed.store(true, Ordering::Relaxed);
+        count
+    }
+
+    /// Write the cache back, without expired entries, if this run changed
+    /// it.
+    pub fn save(&self) -> Result<()> {
+        if !self.changed.swap(false, Ordering::Relaxed) {
+            return Ok(());
+        }
+        let now = Utc::now();
+        let entries: BTreeMap<_, _> = lock(&self.entries)
+            .iter()
+            .filter(|(_, e)| self.is_fresh(e, now))
+            .map(|(k, e)| (k.clone(), e.clone()))
+            .collect();
+        let file = CacheFile {
+            version: VERSION,
+            entries,
+        };
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string(&file)?)
+            .with_context(|| format!("Failed to write {}", tmp.display()))?;
+        fs::rename(&tmp, &self.path)
+            .with_context(|| format!("Failed to replace {}", self.path.display()))?;
+        Ok(())
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+static CACHE: OnceLock<ResponseCache> = OnceLock::new();
+
+/// Keep TMDb responses in `cache` for the rest of the process. Only the
+/// first call takes effect.
+pub fn install(cache: ResponseCache) {
+    let _ = CACHE.set(cache);
+}
+
+/// Answer `request` through the installed cache, or by calling `live` when
+/// there is none.
+pub(crate) fn fetch(
+    request: &str,
+    empty: impl FnOnce(&str) -> bool,
+    live: impl FnOnce() -> Result<String>,
+) -> Result<String> {
+    match CACHE.get() {
+        Some(cache) => cache.fetch(request, empty, live),
+        None => live(),
+    }
+}
+
+/// Save the installed cache, if any. Returns how many requests it answered
+/// and how many went to TMDb.
+pub fn finish() -> Result<Option<(usize, usize)>> {
+    match CACHE.get() {
+        Some(cache) => {
+            cache.save()?;
+            Ok(Some(cache.hit_counts()))
+        }
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(ttl: u64, negative: u64) -> TmdbSettings {
+        TmdbSettings {
+            cache_ttl_hours: ttl,
+            negative_cache_ttl_hours: negative,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_responses_survive_a_reopen() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("tmdb_cache.json");
+        let cache = ResponseCache::open(&path, &settings(24, 0)).unwrap();
+        let heat = cache.fetch("search/movie?query=Heat", |_| false, || Ok("heat".into()));
+        assert_eq!(heat.unwrap(), "heat");
+        // Not kept: negative caching is off, and errors never are.
+        let nothing = cache.fetch("search/movie?query=Zzz", |_| true, || Ok("[]".into()));
+        assert_eq!(nothing.unwrap(), "[]");
+        let failed = cache.fetch("movie/1", |_| false, || bail!("HTTP 503"));
+        assert!(failed.is_err());
+        cache.save().unwrap();
+
+        let cache = ResponseCache::open(&path, &settings(24, 0)).unwrap();
+        let live = || -> Result<String> { panic!("cached requests must not hit TMDb") };
+        let heat = cache.fetch("search/movie?query=Heat", |_| false, live);
+        assert_eq!(heat.unwrap(), "heat");
+        assert_eq!(cache.stats().entries, 1);
+        assert_eq!(cache.hit_counts(), (1, 0));
+
+        assert_eq!(cache.clear(), 1);
+        cache.save().unwrap();
+        let cache = ResponseCache::open(&path, &settings(24, 0)).unwrap();
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                bytes: fs::metadata(&path).unwrap().len(),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_entries_expire_by_kind() {
+        let tmp = tempfile::tempdir().unwrap();
+        let cache = ResponseCache::open(&tmp.path().join("c.json"), &settings(48, 1)).unwrap();
+        let old = Utc::now() - Duration::hours(2);
+        let mut entries = lock(&cache.entries);
+        for (request, empty) in [("found", false), ("missing", true)] {
+            let body = request.to_string();
+            let entry = CachedResponse {
+                body,
+                fetched_at: old,
+                empty,
+            };
+            entries.insert(request.to_string(), entry);
+        }
+        drop(entries);
+
+        let stats = cache.stats();
+        assert_eq!((stats.entries, stats.negative, stats.expired), (2, 1, 1));
+        assert_eq!(stats.oldest, Some(old));
+        let live = || Ok("refetched".to_string());
+        assert_eq!(cache.fetch("found", |_| false, live).unwrap(), "found");
+        assert_eq!(cache.fetch("missing", |_| true, live).unwrap(), "refetched");
+    }
+}
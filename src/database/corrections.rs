@@ -0,0 +1,312 @@
+//! User corrections.
+//!
+//! A correction names what a file really is — a TMDb id, or a title and
+//! year — before it is ever scanned, so matches curated elsewhere (often in
+//! a spreadsheet) can be seeded in bulk with `plex-org corrections import`.
+//! At plan time a corrected file skips the search and is confirmed as given,
+//! like a review-queue acceptance. Corrections are keyed by absolute path, or
+//! by bare file name to match the file wherever it turns up.
+
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use super::queue::queue_key;
+use super::Database;
+use crate::enricher::Enricher;
+use crate::models::{EnrichedMedia, ParsedMedia};
+use crate::parser;
+use crate::providers::MovieCandidate;
+
+/// Header names accepted for each CSV column.
+const SOURCE_COLUMNS: &[&str] = &["source", "path", "file", "filename"];
+const TMDB_COLUMNS: &[&str] = &["tmdb_id", "tmdb", "id"];
+const TITLE_COLUMNS: &[&str] = &["title"];
+const YEAR_COLUMNS: &[&str] = &["year"];
+
+/// What a file really is.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Correction {
+    /// Absolute path, or a bare file name.
+    pub source: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tmdb_id: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub year: Option<i32>,
+    /// RFC 3339 timestamp.
+    pub added_at: String,
+}
+
+impl Correction {
+    /// A correction of `source` (an absolute path or a bare file name).
+    pub fn new(
+        source: &str,
+        tmdb_id: Option<u64>,
+        title: Option<String>,
+        year: Option<i32>,
+    ) -> Result<Self> {
+        let path = Path::new(source);
+        let source = if path.components().count() > 1 || path.is_absolute() {
+            if !path.is_absolute() {
+                bail!("Paths must be absolute (or give just the file name): {source:?}");
+            }
+            queue_key(path)
+        } else {
+            source.to_string()
+        };
+        if source.is_empty() {
+            bail!("Missing source file");
+        }
+        if tmdb_id.is_none() && title.is_none() {
+            bail!("{source:?} needs a TMDb id or a title");
+        }
+        Ok(Self {
+            source,
+            tmdb_id,
+            title,
+            year,
+            added_at: Utc::now().to_rfc3339(),
+        })
+    }
+
+    /// Whether this correction names a file by bare file name.
+    pub fn by_name(&self) -> bool {
+        !Path::new(&self.source).is_absolute()
+    }
+
+    /// Metadata for the corrected file: the given identity, confirmed.
+    ///
+    /// A TMDb id makes the file that movie; otherwise the title and year
+    /// replace the parsed ones.
+    pub fn enriched(&self, enricher: &Enricher, mut parsed: ParsedMedia) -> EnrichedMedia {
+        if let Some(title) = &self.title {
+            parser::set_title(&mut parsed, title.clone(), enricher.parse_options());
+        }
+        if self.year.is_some() {
+            parsed.year = self.year;
+        }
+        let candidate = self.tmdb_id.map(|id| MovieCandidate {
+            id: Some(id),
+            title: parsed.title.clone(),
+            original_title: None,
+            year: parsed.year,
+            release_date: None,
+            imdb_id: None,
+        });
+        let mut enriched = enricher.confirm(parsed, candidate);
+        enriched.enrichment_source = Some("correction".to_string());
+        enriched
+    }
+}
+
+/// A CSV row that could not be imported.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RowError {
+    /// 1-based line number.
+    pub line: usize,
+    pub message: String,
+}
+
+/// The rows of a corrections CSV.
+#[derive(Debug, Clone, Default)]
+pub struct CsvImport {
+    /// Good rows with their 1-based line numbers.
+    pub rows: Vec<(usize, Correction)>,
+    pub errors: Vec<RowError>,
+}
+
+/// Parse a corrections CSV.
+///
+/// The header names the columns: the source (`source`, `path`, `file`, or
+/// `filename`) and `tmdb_id`, `title`, or both, with an optional `year`.
+/// Fields may be quoted (`"Crouching Tiger, Hidden Dragon"`). Rows are
+/// returned with their line numbers; bad rows are reported, not fatal.
+pub fn parse_csv(text: &str) -> Result<CsvImport> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(n, line)| (n + 1, line.trim_start_matches('\u{feff}')))
+        .filter(|(_, line)| !line.trim().is_empty());
+    let Some((_, header)) = lines.next() else {
+        bail!("The CSV is empty");
+    };
+    let header: Vec<String> = split_row(header)
+        .iter()
+        .map(|h| h.trim().to_lowercase())
+        .collect();
+    let column = |names: &[&str]| header.iter().position(|h| names.contains(&h.as_str()));
+    let Some(source_col) = column(SOURCE_COLUMNS) else {
+        bail!("The CSV header needs a source column (source, path, file, or filename)");
+    };
+    let (tmdb_col, title_col, year_col) = (
+        column(TMDB_COLUMNS),
+        column(TITLE_COLUMNS),
+        column(YEAR_COLUMNS),
+    );
+    if tmdb_col.is_none() && title_col.is_none() {
+        bail!("The CSV header needs a tmdb_id or title column");
+    }
+
+    let mut import = CsvImport::default();
+    for (line, text) in lines {
+        let fields = split_row(text);
+        let field = |col: Option<usize>| {
+            col.and_then(|c| fields.get(c))
+                .map(|f| f.trim())
+                .filter(|f| !f.is_empty())
+        };
+        let parsed = (|| {
+            let tmdb_id = match field(tmdb_col) {
+                Some(id) => match id.parse() {
+                    Ok(id) => Some(id),
+                    Err(_) => bail!("Bad TMDb id {id:?}"),
+                },
+                None => None,
+            };
+            let year = match field(year_col) {
+                Some(year) => match year.parse() {
+                    Ok(year) => Some(year),
+                    Err(_) => bail!("Bad year {year:?}"),
+                },
+                None => None,
+            };
+            let source = field(Some(source_col)).unwrap_or_default();
+            Correction::new(source, tmdb_id, field(title_col).map(String::from), year)
+        })();
+        match parsed {
+            Ok(correction) => import.rows.push((line, correction)),
+            Err(e) => import.errors.push(RowError {
+                line,
+                message: e.to_string(),
+            }),
+        }
+    }
+    Ok(import)
+}
+
+/// Split one CSV line into fields, honoring double quotes (`""` inside a
+/// quoted field is a literal quote).
+fn split_row(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+impl Database {
+    /// Add a correction, replacing any earlier one for the same source.
+    /// Returns `true` when one was replaced.
+    pub fn add_correction(&mut self, correction: Correction) -> bool {
+        let before = self.data.corrections.len();
+        self.data
+            .corrections
+            .retain(|c| c.source != correction.source);
+        let replaced = self.data.corrections.len() != before;
+        self.data.corrections.push(correction);
+        replaced
+    }
+
+    /// Remove the correction keyed by `source` (a path or a bare file
+    /// name). Returns whether there was one.
+    pub fn remove_correction(&mut self, source: &str) -> bool {
+        let path = Path::new(source);
+        let key = if path.components().count() > 1 {
+            queue_key(path)
+        } else {
+            source.to_string()
+        };
+        let before = self.data.corrections.len();
+        self.data.corrections.retain(|c| c.source != key);
+        self.data.corrections.len() != before
+    }
+
+    /// All corrections, oldest first.
+    pub fn corrections(&self) -> &[Correction] {
+        &self.data.corrections
+    }
+
+    /// The correction for `source`: by its path, else by its file name.
+    pub fn correction_for(&self, source: &Path) -> Option<&Correction> {
+        if self.data.corrections.is_empty() {
+            return None;
+        }
+        let key = queue_key(source);
+        let name = source.file_name()?.to_string_lossy();
+        let corrections = &self.data.corrections;
+        corrections
+            .iter()
+            .find(|c| c.source == key)
+            .or_else(|| corrections.iter().find(|c| c.by_name() && c.source == name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+
+    #[test]
+    fn test_import_and_apply_corrections() {
+        let tmp = tempfile::tempdir().unwrap();
+        let exact = tmp.path().join("dl/ct.hd.mkv");
+        let csv = format!(
+            "\u{feff}Filename,TMDB_ID,Title,Year\n\
+             \"{}\",146,\"Crouching Tiger, Hidden Dragon\",2000\n\
+             fan.edit.mkv,,My Fan Edit,2020\n\
+             \n\
+             relative/x.mkv,1,,\n\
+             nothing.mkv,,,\n\
+             bad.mkv,abc,,\n",
+            exact.display()
+        );
+        let CsvImport { rows, errors } = parse_csv(&csv).unwrap();
+        assert_eq!(rows.len(), 2);
+        let lines: Vec<_> = errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, [5, 6, 7]);
+        assert!(parse_csv("file,notes\nx.mkv,hi\n").is_err());
+
+        let mut db = Database::open(&tmp.path().join("library.json")).unwrap();
+        for (_, correction) in rows.clone() {
+            assert!(!db.add_correction(correction));
+        }
+        assert!(db.add_correction(rows[1].1.clone()));
+        assert_eq!(db.corrections().len(), 2);
+
+        let enricher = Enricher::new(AppConfig::default());
+        let parse = |name| parser::parse_video(name, enricher.parse_options());
+        let tiger = db.correction_for(&exact).unwrap();
+        let enriched = tiger.enriched(&enricher, parse("ct.hd.mkv"));
+        assert_eq!(enriched.best_title(), "Crouching Tiger, Hidden Dragon");
+        assert_eq!(enriched.tmdb_id(), Some(146));
+        assert_eq!(enriched.confidence, 100.0);
+
+        let elsewhere = tmp.path().join("other/fan.edit.mkv");
+        let fan = db.correction_for(&elsewhere).unwrap();
+        let enriched = fan.enriched(&enricher, parse("fan.edit.mkv"));
+        assert_eq!(enriched.best_year(), Some(2020));
+        assert!(db
+            .correction_for(&tmp.path().join("dl/other.mkv"))
+            .is_none());
+
+        assert!(db.remove_correction("fan.edit.mkv"));
+        assert!(db.remove_correction(&exact.to_string_lossy()));
+        assert!(db.corrections().is_empty());
+    }
+}
@@ -0,0 +1,93 @@
+//! Records for a library organized before the database, or by hand.
+//!
+//! The database only knows what the organizer placed. `plex-org library
+//! index` walks a library root and records the media files it has no
+//! record of, parsed from their Plex names (and folder id tags), so
+//! duplicate checks, upgrades, `stats`, and `health` see the whole library
+//! without parsing it again on every run. An indexed file's record points
+//! at itself (it was never organized from anywhere), carries strategy
+//! `index`, and is dated by the file's modification time.
+
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+
+use super::{reconcile, Database, FileRecord};
+use crate::models::ParsedMedia;
+
+/// Strategy of records made by indexing rather than by a run.
+pub const INDEXED: &str = "index";
+
+impl Database {
+    /// Record the library file at `path`, described by `parsed`, unless a
+    /// record already has it. Returns whether a record was added.
+    pub fn index_file(&mut self, path: &Path, parsed: &ParsedMedia) -> bool {
+        let destination = path.to_string_lossy().into_owned();
+        if self.find_by_destination(&destination).is_some() {
+            return false;
+        }
+        let last_seen = reconcile::snapshot(path);
+        let modified = last_seen
+            .as_ref()
+            .and_then(|seen| seen.modified)
+            .and_then(|secs| DateTime::<Utc>::from_timestamp(secs, 0))
+            .unwrap_or_else(Utc::now);
+        self.data.files.push(FileRecord {
+            source: destination.clone(),
+            destination,
+            title: parsed.title.clone(),
+            titles: parsed.titles.clone(),
+            year: parsed.year,
+            media_type: parsed.media_type.to_string(),
+            quality: parsed.quality.clone(),
+            strategy: INDEXED.to_string(),
+            organized_at: modified.to_rfc3339(),
+            tmdb_id: parsed.ids.tmdb,
+            second_feature: None,
+            details: None,
+            last_seen,
+            content_hash: None,
+            confidence: None,
+        });
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::models::MediaType;
+
+    #[test]
+    fn test_index_records_untracked_files_once() {
+        let tmp = tempfile::tempdir().unwrap();
+        let heat = tmp
+            .path()
+            .join("Movies/Heat (1995) {tmdb-949}/Heat (1995).mkv");
+        fs::create_dir_all(heat.parent().unwrap()).unwrap();
+        fs::write(&heat, b"heat").unwrap();
+        let mut parsed = ParsedMedia {
+            title: "Heat".to_string(),
+            year: Some(1995),
+            media_type: MediaType::Movie,
+            quality: "1080p BluRay H.264".to_string(),
+            ..Default::default()
+        };
+        parsed.ids.tmdb = Some(949);
+
+        let mut db = Database::open(&tmp.path().join("library.json")).unwrap();
+        assert!(db.index_file(&heat, &parsed));
+        assert!(!db.index_file(&heat, &parsed));
+
+        let record = db.find_by_destination(&heat.to_string_lossy()).unwrap();
+        assert_eq!(record.source, record.destination);
+        assert_eq!(record.strategy, INDEXED);
+        assert_eq!(record.tmdb_id, Some(949));
+        assert_eq!(record.quality, "1080p BluRay H.264");
+        assert!(record.last_seen.is_some());
+        assert!(record.organized_time().is_some());
+        assert_eq!(db.stats().files, 1);
+    }
+}
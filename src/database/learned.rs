@@ -0,0 +1,179 @@
+//! Title tokens learned from provider-confirmed titles.
+//!
+//! hunch strips words it recognizes as release tags, which is wrong when the
+//! word is the title: `HDR.2020` loses its whole title, `The.Limited.2008`
+//! becomes "The", `DC.League.of.Super-Pets.2022` reads `DC` as "Director's
+//! Cut". When TMDb confirms a title containing such a word, the word is
+//! recorded as title-safe *in its context* — next to the same neighbouring
+//! word — so later parses keep it without un-stripping it everywhere.
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::normalize_title;
+
+/// Where a learned token sits relative to the rest of the title.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", tag = "kind", content = "word")]
+pub enum TokenContext {
+    /// The token is the entire title.
+    Whole,
+    /// The token directly precedes this (normalized) word.
+    Before(String),
+    /// The token directly follows this (normalized) word.
+    After(String),
+}
+
+/// A token confirmed to be part of a title.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LearnedToken {
+    /// Normalized token (lowercase, punctuation folded).
+    pub token: String,
+    pub context: TokenContext,
+    /// The confirmed title it was learned from.
+    pub title: String,
+    #[serde(default)]
+    pub tmdb_id: Option<u64>,
+    /// RFC 3339 timestamp.
+    pub learned_at: String,
+}
+
+/// Is every word of `token` among `words`?
+fn covered(token: &str, words: &[&str]) -> bool {
+    let token = normalize_title(token);
+    !token.is_empty() && token.split(' ').all(|w| words.contains(&w))
+}
+
+fn context_at(region: &[&str], index: usize) -> TokenContext {
+    if region.len() == 1 {
+        TokenContext::Whole
+    } else if let Some(next) = region.get(index + 1) {
+        TokenContext::Before(normalize_title(next))
+    } else {
+        TokenContext::After(normalize_title(region[index - 1]))
+    }
+}
+
+/// Tokens of the title region that the parser dropped but `confirmed`
+/// contains, with the context they appeared in.
+pub fn title_safe_tokens(
+    region: &[&str],
+    parsed_title: &str,
+    confirmed: &str,
+) -> Vec<(String, TokenContext)> {
+    let parsed = normalize_title(parsed_title);
+    let parsed: Vec<&str> = parsed.split(' ').collect();
+    let confirmed = normalize_title(confirmed);
+    let confirmed: Vec<&str> = confirmed.split(' ').collect();
+
+    region
+        .iter()
+        .enumerate()
+        .filter(|(_, token)| !covered(token, &parsed) && covered(token, &confirmed))
+        .map(|(i, token)| (normalize_title(token), context_at(region, i)))
+        .collect()
+}
+
+fn is_safe(region: &[&str], index: usize, learned: &[LearnedToken]) -> bool {
+    let token = normalize_title(region[index]);
+    learned
+        .iter()
+        .any(|l| l.token == token && l.context == context_at(region, index))
+}
+
+/// Rebuild a title by re-attaching learned tokens the parser stripped.
+///
+/// Only runs of safe tokens directly adjacent to the parsed title (or a
+/// region made entirely of safe tokens) are restored. `None` when nothing
+/// changes.
+pub fn restore_title(
+    region: &[&str],
+    parsed_title: &str,
+    learned: &[LearnedToken],
+) -> Option<String> {
+    let parsed = normalize_title(parsed_title);
+    let parsed_words: Vec<&str> = parsed.split(' ').collect();
+    let in_title: Vec<bool> = region.iter().map(|t| covered(t, &parsed_words)).collect();
+
+    let Some(first) = in_title.iter().position(|c| *c) else {
+        let all_safe = (0..region.len()).all(|i| is_safe(region, i, learned));
+        return (all_safe && !region.is_empty()).then(|| region.join(" "));
+    };
+    let last = in_title.iter().rposition(|c| *c).unwrap_or(first);
+
+    let mut start = first;
+    while start > 0 && is_safe(region, start - 1, learned) {
+        start -= 1;
+    }
+    let mut end = last + 1;
+    while end < region.len() && !in_title[end] && is_safe(region, end, learned) {
+        end += 1;
+    }
+    if start == first && end == last + 1 {
+        return None;
+    }
+
+    let mut parts: Vec<&str> = region[start..first].to_vec();
+    parts.push(parsed_title);
+    parts.extend(&region[last + 1..end]);
+    Some(parts.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn learn(region: &[&str], parsed: &str, confirmed: &str) -> Vec<LearnedToken> {
+        title_safe_tokens(region, parsed, confirmed)
+            .into_iter()
+            .map(|(token, context)| LearnedToken {
+                token,
+                context,
+                title: confirmed.to_string(),
+                tmdb_id: None,
+                learned_at: String::new(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_learns_stripped_tokens_with_context() {
+        let dc = title_safe_tokens(
+            &["DC", "League", "of", "Super-Pets"],
+            "League of Super-Pets",
+            "DC League of Super-Pets",
+        );
+        assert_eq!(
+            dc,
+            vec![("dc".to_string(), TokenContext::Before("league".into()))]
+        );
+        let hdr = title_safe_tokens(&["HDR"], "", "HDR");
+        assert_eq!(hdr, vec![("hdr".to_string(), TokenContext::Whole)]);
+        // Nothing was stripped: nothing to learn.
+        assert!(title_safe_tokens(&["The", "Matrix"], "The Matrix", "The Matrix").is_empty());
+    }
+
+    #[test]
+    fn test_restores_learned_tokens_only_in_context() {
+        let learned = learn(&["The", "Limited"], "The", "The Limited");
+        assert_eq!(
+            restore_title(&["The", "Limited"], "The", &learned).as_deref(),
+            Some("The Limited")
+        );
+        // "Limited" after a different word is still a release tag.
+        assert_eq!(restore_title(&["Heat", "Limited"], "Heat", &learned), None);
+
+        let learned = learn(&["HDR"], "", "HDR");
+        assert_eq!(
+            restore_title(&["HDR"], "", &learned).as_deref(),
+            Some("HDR")
+        );
+        assert_eq!(restore_title(&["Heat", "HDR"], "Heat", &learned), None);
+
+        let region = ["DC", "League", "of", "Super-Pets"];
+        let learned = learn(&region, "League of Super-Pets", "DC League of Super-Pets");
+        assert_eq!(
+            restore_title(&region, "League of Super-Pets", &learned).as_deref(),
+            Some("DC League of Super-Pets")
+        );
+    }
+}
@@ -0,0 +1,582 @@
+//! Local library database.
+//!
+//! A single JSON document (default `~/.plex-organizer/library.json`) that
+//! records every file the organizer has placed, so later commands can answer
+//! "what did we organize, from where, and when" without re-parsing the
+//! library. JSON rather than SQLite for the same reasons as undo manifests
+//! (see D004): human-readable, diffable, and no extra dependencies.
+
+pub mod cache;
+pub mod corrections;
+pub mod index;
+pub mod learned;
+pub mod pins;
+pub mod queue;
+pub mod reconcile;
+pub mod runs;
+pub mod scans;
+pub mod stats;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::models::{
+    EnrichedMedia, MediaType, ParsedMedia, SourceFingerprint, TitleVariants, UndoEntry,
+    UndoManifest,
+};
+use crate::parser::{self, ParseOptions};
+use crate::providers::MovieDetails;
+use crate::scanner::ScanCheckpoint;
+use crate::title_strategy;
+use corrections::Correction;
+use learned::LearnedToken;
+use pins::Pin;
+use queue::ReviewItem;
+use runs::RunRecord;
+
+/// Current on-disk schema version.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// One organized file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FileRecord {
+    /// Original path the file was organized from.
+    pub source: String,
+    /// Path of the file inside the library.
+    pub destination: String,
+    /// Primary title.
+    pub title: String,
+    /// Original, romanized, and English titles, where known.
+    #[serde(default, skip_serializing_if = "TitleVariants::is_empty")]
+    pub titles: TitleVariants,
+    #[serde(default)]
+    pub year: Option<i32>,
+    pub media_type: String,
+    #[serde(default)]
+    pub quality: String,
+    pub strategy: String,
+    /// RFC 3339 timestamp of the organize run.
+    pub organized_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tmdb_id: Option<u64>,
+    /// "Title (Year)" of the second movie in a double-feature file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub second_feature: Option<String>,
+    /// Provider details backfilled by `plex-org enrich`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<MovieDetails>,
+    /// Size and modification time of the library file when last seen, so
+    /// a rename by another tool can be followed (see [`reconcile`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_seen: Option<SourceFingerprint>,
+    /// [`hash::content_hash`](crate::hash::content_hash) of the library
+    /// file in `scan.hash_mode` as it was placed, or with that off the full
+    /// checksum `--verify` took.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    /// Match confidence (0-100) the file was organized with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+}
+
+impl FileRecord {
+    /// When the file was organized, if the timestamp parses.
+    pub fn organized_time(&self) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(&self.organized_at)
+            .ok()
+            .map(|t| t.with_timezone(&Utc))
+    }
+}
+
+/// Serialized database contents.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DatabaseData {
+    pub version: u32,
+    pub files: Vec<FileRecord>,
+    /// Title words hunch strips but a provider confirmed (see [`learned`]).
+    pub learned_tokens: Vec<LearnedToken>,
+    /// Low-confidence files waiting for review (see [`queue`]).
+    pub review_queue: Vec<ReviewItem>,
+    /// Files pinned to fixed destinations (see [`pins`]).
+    pub pins: Vec<Pin>,
+    /// Identities users gave files ahead of time (see [`corrections`]).
+    pub corrections: Vec<Correction>,
+    /// Recent executed runs, for throughput estimates (see [`runs`]).
+    pub runs: Vec<RunRecord>,
+    /// Where interrupted scans stopped (see [`scans`]).
+    pub scan_checkpoints: Vec<ScanCheckpoint>,
+}
+
+impl Default for DatabaseData {
+    fn default() -> Self {
+        Self {
+            version: SCHEMA_VERSION,
+            files: Vec::new(),
+            learned_tokens: Vec::new(),
+            review_queue: Vec::new(),
+            pins: Vec::new(),
+            corrections: Vec::new(),
+            runs: Vec::new(),
+            scan_checkpoints: Vec::new(),
+        }
+    }
+}
+
+/// Handle to the on-disk library database.
+///
+/// Changes are held in memory until [`Database::save`] is called.
+#[derive(Debug)]
+pub struct Database {
+    path: PathBuf,
+    data: DatabaseData,
+}
+
+impl Database {
+    /// Open the database at `path`, starting empty if it does not exist yet.
+    pub fn open(path: &Path) -> crate::Result<Self> {
+        Self::read(path).map_err(|source| Error::Database {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    fn read(path: &Path) -> Result<Self> {
+        let data = if path.exists() {
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read database: {}", path.display()))?;
+            let data: DatabaseData = serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse database: {}", path.display()))?;
+            if data.version > SCHEMA_VERSION {
+                bail!(
+                    "Database schema {} is newer than supported version {}",
+                    data.version,
+                    SCHEMA_VERSION
+                );
+            }
+            data
+        } else {
+            DatabaseData::default()
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            data,
+        })
+    }
+
+    /// Write the database back to disk atomically (temp file + rename).
+    pub fn save(&self) -> crate::Result<()> {
+        self.write().map_err(|source| Error::Database {
+            path: self.path.clone(),
+            source,
+        })
+    }
+
+    fn write(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create dir: {}", parent.display()))?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        let json = serde_json::to_string_pretty(&self.data)?;
+        fs::write(&tmp, json).with_context(|| format!("Failed to write {}", tmp.display()))?;
+        fs::rename(&tmp, &self.path)
+            .with_context(|| format!("Failed to replace {}", self.path.display()))?;
+        Ok(())
+    }
+
+    /// Schema version of the file as read.
+    pub fn schema_version(&self) -> u32 {
+        self.data.version
+    }
+
+    /// Path of the backing file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// All organized file records, oldest first.
+    pub fn files(&self) -> &[FileRecord] {
+        &self.data.files
+    }
+
+    /// Record every entry of a completed organize run.
+    ///
+    /// A file moved again from a known library path (e.g., promoted out of
+    /// staging) updates its existing record, keeping the original source and
+    /// any metadata the second move does not know about. Organized files
+    /// leave the review queue, and the run's throughput is kept.
+    pub fn record_run(&mut self, manifest: &UndoManifest) {
+        self.record_stats(manifest);
+        // Copies an upgrade replaced leave their paths to the new files;
+        // `rollback_entries` brings them back on undo.
+        for entry in &manifest.replaced {
+            if let Some(existing) = self
+                .data
+                .files
+                .iter_mut()
+                .rev()
+                .find(|r| r.destination == entry.source)
+            {
+                existing.destination = entry.destination.clone();
+                existing.last_seen = reconcile::snapshot(Path::new(&entry.destination));
+            }
+        }
+        for entry in &manifest.entries {
+            let key = queue::queue_key(Path::new(&entry.source));
+            self.data.review_queue.retain(|i| i.source != key);
+            if let Some(existing) = self
+                .data
+                .files
+                .iter_mut()
+                .rev()
+                .find(|r| r.destination == entry.source)
+            {
+                existing.destination = entry.destination.clone();
+                existing.last_seen = reconcile::snapshot(Path::new(&entry.destination));
+                existing.organized_at = entry.timestamp.clone();
+                existing.year = existing.year.or(entry.year);
+                existing.tmdb_id = existing.tmdb_id.or(entry.tmdb_id);
+                existing.second_feature = existing
+                    .second_feature
+                    .take()
+                    .or(entry.second_feature.clone());
+                if existing.titles.is_empty() {
+                    existing.titles = entry.titles.clone();
+                }
+                if existing.quality.is_empty() {
+                    existing.quality = entry.quality.clone();
+                }
+                if entry.checksum.is_some() {
+                    existing.content_hash = entry.checksum.clone();
+                }
+                existing.confidence = existing.confidence.or(entry.confidence);
+                continue;
+            }
+            self.data.files.push(FileRecord {
+                source: entry.source.clone(),
+                destination: entry.destination.clone(),
+                title: entry.title.clone(),
+                titles: entry.titles.clone(),
+                year: entry.year,
+                media_type: entry.media_type.clone(),
+                quality: entry.quality.clone(),
+                strategy: entry.strategy.clone(),
+                organized_at: entry.timestamp.clone(),
+                tmdb_id: entry.tmdb_id,
+                second_feature: entry.second_feature.clone(),
+                details: None,
+                last_seen: reconcile::snapshot(Path::new(&entry.destination)),
+                content_hash: entry.checksum.clone(),
+                confidence: entry.confidence,
+            });
+        }
+    }
+
+    /// Roll back the records for reversed undo entries.
+    ///
+    /// A record created by the reversed run is removed. A record that the run
+    /// only moved along (e.g., a promotion out of staging) is pointed back at
+    /// its previous location. Returns how many records changed.
+    pub fn rollback_entries(&mut self, entries: &[UndoEntry]) -> usize {
+        let mut changed = 0;
+        for entry in entries {
+            let Some(index) = self
+                .data
+                .files
+                .iter()
+                .rposition(|r| r.destination == entry.destination)
+            else {
+                continue;
+            };
+            if self.data.files[index].source == entry.source {
+                self.data.files.remove(index);
+            } else {
+                self.data.files[index].destination = entry.source.clone();
+            }
+            changed += 1;
+        }
+        changed
+    }
+
+    /// Drop the records at the destinations of undo entries whose files are
+    /// gone, so an undo leaves no record of a file that is nowhere. Returns
+    /// how many records were dropped.
+    pub fn forget_entries(&mut self, entries: &[UndoEntry]) -> usize {
+        let before = self.data.files.len();
+        self.data
+            .files
+            .retain(|r| !entries.iter().any(|e| e.destination == r.destination));
+        before - self.data.files.len()
+    }
+
+    /// Records organized at or after `cutoff`, oldest first.
+    pub fn files_since(&self, cutoff: DateTime<Utc>) -> Vec<&FileRecord> {
+        self.data
+            .files
+            .iter()
+            .filter(|r| r.organized_time().is_some_and(|t| t >= cutoff))
+            .collect()
+    }
+
+    /// Attach provider details to the record at `destination`.
+    ///
+    /// Returns `false` when no such record exists.
+    pub fn set_details(&mut self, destination: &str, tmdb_id: u64, details: MovieDetails) -> bool {
+        match self
+            .data
+            .files
+            .iter_mut()
+            .rev()
+            .find(|r| r.destination == destination)
+        {
+            Some(record) => {
+                record.tmdb_id = Some(tmdb_id);
+                record.details = Some(details);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Title-safe tokens learned so far.
+    pub fn learned_tokens(&self) -> &[LearnedToken] {
+        &self.data.learned_tokens
+    }
+
+    /// Learn title-safe tokens from a TMDb-confirmed movie.
+    ///
+    /// Returns how many new tokens were recorded.
+    pub fn learn_title_tokens(&mut self, enriched: &EnrichedMedia) -> usize {
+        let (Some("tmdb"), Some(movie)) = (enriched.enrichment_source.as_deref(), &enriched.movie)
+        else {
+            return 0;
+        };
+        let parsed = &enriched.parsed;
+        let Some(region) = title_strategy::title_region(&parsed.raw_filename, parsed.year) else {
+            return 0;
+        };
+
+        let mut added = 0;
+        for (token, context) in learned::title_safe_tokens(&region, &parsed.title, &movie.title) {
+            let known = self
+                .data
+                .learned_tokens
+                .iter()
+                .any(|l| l.token == token && l.context == context);
+            if !known {
+                self.data.learned_tokens.push(LearnedToken {
+                    token,
+                    context,
+                    title: movie.title.clone(),
+                    tmdb_id: movie.tmdb_id,
+                    learned_at: Utc::now().to_rfc3339(),
+                });
+                added += 1;
+            }
+        }
+        added
+    }
+
+    /// Re-attach learned title words the parser stripped from `parsed`.
+    ///
+    /// Returns whether the title changed.
+    pub fn restore_title(&self, parsed: &mut ParsedMedia, options: &ParseOptions) -> bool {
+        if self.data.learned_tokens.is_empty()
+            || !matches!(parsed.media_type, MediaType::Movie | MediaType::Tv)
+        {
+            return false;
+        }
+        let Some(region) = title_strategy::title_region(&parsed.raw_filename, parsed.year) else {
+            return false;
+        };
+        match learned::restore_title(&region, &parsed.title, &self.data.learned_tokens) {
+            Some(title) => {
+                parser::set_title(parsed, title, options);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Find the record for a library file by its current path.
+    pub fn find_by_destination(&self, destination: &str) -> Option<&FileRecord> {
+        self.data
+            .files
+            .iter()
+            .rev()
+            .find(|r| r.destination == destination)
+    }
+
+    /// Find a library file by its content hash.
+    pub fn find_by_content_hash(&self, hash: &str) -> Option<&FileRecord> {
+        self.data
+            .files
+            .iter()
+            .rev()
+            .find(|r| r.content_hash.as_deref() == Some(hash))
+    }
+
+    /// Store the content hash of the record at `destination`.
+    ///
+    /// Returns `false` when no such record exists.
+    pub fn set_content_hash(&mut self, destination: &str, hash: String) -> bool {
+        match self
+            .data
+            .files
+            .iter_mut()
+            .rev()
+            .find(|r| r.destination == destination)
+        {
+            Some(record) => {
+                record.content_hash = Some(hash);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest_with(source: &str, destination: &str) -> UndoManifest {
+        UndoManifest {
+            entries: vec![UndoEntry {
+                source: source.to_string(),
+                destination: destination.to_string(),
+                strategy: "move".to_string(),
+                timestamp: "2026-03-01T10:00:00+00:00".to_string(),
+                title: "Heat".to_string(),
+                media_type: "movie".to_string(),
+                year: Some(1995),
+                quality: "1080p".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_open_missing_database_is_empty() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db = Database::open(&tmp.path().join("library.json")).unwrap();
+        assert!(db.files().is_empty());
+    }
+
+    #[test]
+    fn test_record_save_and_reopen() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("nested/library.json");
+
+        let mut db = Database::open(&path).unwrap();
+        db.record_run(&manifest_with("/dl/Heat.1995.mkv", "/plex/Heat (1995).mkv"));
+        db.save().unwrap();
+
+        let db = Database::open(&path).unwrap();
+        assert_eq!(db.files().len(), 1);
+        let record = db.find_by_destination("/plex/Heat (1995).mkv").unwrap();
+        assert_eq!(record.source, "/dl/Heat.1995.mkv");
+        assert!(!path.with_extension("json.tmp").exists());
+    }
+
+    #[test]
+    fn test_chained_move_updates_existing_record() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut db = Database::open(&tmp.path().join("library.json")).unwrap();
+        db.record_run(&manifest_with(
+            "/dl/Heat.1995.mkv",
+            "/staging/Heat (1995).mkv",
+        ));
+
+        let mut promotion = manifest_with("/staging/Heat (1995).mkv", "/plex/Heat (1995).mkv");
+        promotion.entries[0].year = None;
+        promotion.entries[0].timestamp = "2026-03-09T10:00:00+00:00".to_string();
+        db.record_run(&promotion);
+
+        assert_eq!(db.files().len(), 1);
+        let record = &db.files()[0];
+        assert_eq!(record.source, "/dl/Heat.1995.mkv");
+        assert_eq!(record.destination, "/plex/Heat (1995).mkv");
+        assert_eq!(record.year, Some(1995));
+    }
+
+    #[test]
+    fn test_rollback_removes_new_and_restores_chained_records() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut db = Database::open(&tmp.path().join("library.json")).unwrap();
+        let staged = manifest_with("/dl/Heat.1995.mkv", "/staging/Heat (1995).mkv");
+        let promoted = manifest_with("/staging/Heat (1995).mkv", "/plex/Heat (1995).mkv");
+        db.record_run(&staged);
+        db.record_run(&promoted);
+
+        assert_eq!(db.rollback_entries(&promoted.entries), 1);
+        assert_eq!(db.files()[0].destination, "/staging/Heat (1995).mkv");
+
+        assert_eq!(db.rollback_entries(&staged.entries), 1);
+        assert!(db.files().is_empty());
+
+        db.record_run(&staged);
+        assert_eq!(db.forget_entries(&promoted.entries), 0);
+        assert_eq!(db.forget_entries(&staged.entries), 1);
+        assert!(db.files().is_empty());
+    }
+
+    #[test]
+    fn test_files_since_filters_by_timestamp() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut db = Database::open(&tmp.path().join("library.json")).unwrap();
+        db.record_run(&manifest_with("/dl/a.mkv", "/plex/a.mkv"));
+        let mut newer = manifest_with("/dl/b.mkv", "/plex/b.mkv");
+        newer.entries[0].timestamp = "2026-03-10T00:00:00+00:00".to_string();
+        db.record_run(&newer);
+
+        let cutoff = DateTime::parse_from_rfc3339("2026-03-05T00:00:00+00:00")
+            .unwrap()
+            .with_timezone(&Utc);
+        let recent = db.files_since(cutoff);
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].destination, "/plex/b.mkv");
+    }
+
+    #[test]
+    fn test_learned_tokens_restore_future_parses() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut db = Database::open(&tmp.path().join("library.json")).unwrap();
+
+        let options = ParseOptions::default();
+        let parsed = parser::parse_video("HDR.2020.1080p.WEB-DL.mkv", &options);
+        assert_eq!(parsed.title, "");
+        let mut enriched = EnrichedMedia::from_parsed(parsed);
+        enriched.enrichment_source = Some("tmdb".to_string());
+        enriched.movie = Some(crate::models::Movie {
+            title: "HDR".to_string(),
+            year: Some(2020),
+            tmdb_id: Some(1),
+            original_title: None,
+            confidence: 95.0,
+        });
+        assert_eq!(db.learn_title_tokens(&enriched), 1);
+        assert_eq!(db.learn_title_tokens(&enriched), 0);
+
+        let mut again = parser::parse_video("HDR.2020.2160p.BluRay.x265.mkv", &options);
+        assert!(db.restore_title(&mut again, &options));
+        assert_eq!(again.title, "HDR");
+        let mut other = parser::parse_video("Heat.1995.HDR.2160p.mkv", &options);
+        assert!(!db.restore_title(&mut other, &options));
+    }
+
+    #[test]
+    fn test_rejects_newer_schema() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("library.json");
+        fs::write(&path, r#"{"version": 99, "files": []}"#).unwrap();
+        assert!(Database::open(&path).is_err());
+    }
+}
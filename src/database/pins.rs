@@ -0,0 +1,158 @@
+//! Pinned destinations.
+//!
+//! Some files parse badly no matter what — concert films, fan edits, odd
+//! one-offs. A pin records the exact library-relative path such a file must
+//! be organized to; at plan time it overrides parsing, enrichment, and path
+//! building, and is never held for review.
+
+use std::path::{Component, Path, PathBuf};
+use std::sync::LazyLock;
+
+use anyhow::{bail, Result};
+use chrono::Utc;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::queue::queue_key;
+use super::Database;
+use crate::models::{EnrichedMedia, ParsedMedia};
+use crate::parser::{self, ParseOptions};
+use crate::utils::is_safe_component;
+
+/// `Title (2020)`.
+static PLEX_NAME_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(.+?)\s*\((\d{4})\)$").unwrap());
+
+/// A file pinned to a fixed destination.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pin {
+    /// Absolute path of the pinned file.
+    pub source: String,
+    /// Destination relative to the library root.
+    pub destination: String,
+    /// RFC 3339 timestamp.
+    pub pinned_at: String,
+}
+
+impl Pin {
+    /// Metadata for the pinned file: title and year come from the pinned
+    /// name (`Title (Year)`), and the destination is fixed.
+    pub fn enriched(&self, parsed: &ParsedMedia, options: &ParseOptions) -> EnrichedMedia {
+        let stem = Path::new(&self.destination)
+            .file_stem()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let (title, year) = match PLEX_NAME_RE.captures(&stem) {
+            Some(caps) => (caps[1].to_string(), caps[2].parse().ok()),
+            None => (stem, None),
+        };
+        let mut parsed = parsed.clone();
+        parsed.year = year;
+        parser::set_title(&mut parsed, title, options);
+        let mut enriched = EnrichedMedia::from_parsed(parsed);
+        enriched.confidence = 100.0;
+        enriched.enrichment_source = Some("pin".to_string());
+        enriched.pinned = Some(PathBuf::from(&self.destination));
+        enriched
+    }
+}
+
+/// Check a pinned destination: relative, no traversal, ending in a file
+/// with the source's extension.
+fn validate_destination(source: &Path, destination: &str) -> Result<()> {
+    let path = Path::new(destination);
+    let safe = path.components().all(|c| match c {
+        Component::Normal(part) => is_safe_component(&part.to_string_lossy()),
+        _ => false,
+    });
+    if !safe || destination.is_empty() {
+        bail!("Pinned destination must be a relative path inside the library: {destination:?}");
+    }
+    let ext = |p: &Path| p.extension().map(|e| e.to_string_lossy().to_lowercase());
+    if ext(path) != ext(source) {
+        bail!(
+            "Pinned destination {destination:?} must keep the extension of {}",
+            source.display()
+        );
+    }
+    Ok(())
+}
+
+impl Database {
+    /// Pin `source` to `destination` (relative to the library root),
+    /// replacing any earlier pin of the same file.
+    pub fn pin(&mut self, source: &Path, destination: &str) -> Result<()> {
+        validate_destination(source, destination)?;
+        let key = queue_key(source);
+        if let Some(other) = self
+            .data
+            .pins
+            .iter()
+            .find(|p| p.destination == destination && p.source != key)
+        {
+            bail!("{destination:?} is already pinned by {}", other.source);
+        }
+        self.data.pins.retain(|p| p.source != key);
+        self.data.pins.push(Pin {
+            source: key,
+            destination: destination.to_string(),
+            pinned_at: Utc::now().to_rfc3339(),
+        });
+        Ok(())
+    }
+
+    /// Remove the pin of `source`. Returns whether there was one.
+    pub fn unpin(&mut self, source: &Path) -> bool {
+        let key = queue_key(source);
+        let before = self.data.pins.len();
+        self.data.pins.retain(|p| p.source != key);
+        self.data.pins.len() != before
+    }
+
+    /// All pins, oldest first.
+    pub fn pins(&self) -> &[Pin] {
+        &self.data.pins
+    }
+
+    /// The pin of `source`, if any.
+    pub fn pin_for(&self, source: &Path) -> Option<&Pin> {
+        if self.data.pins.is_empty() {
+            return None;
+        }
+        let key = queue_key(source);
+        self.data.pins.iter().find(|p| p.source == key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pin_lifecycle() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut db = Database::open(&tmp.path().join("library.json")).unwrap();
+        let source = tmp.path().join("dl/fan.edit.final.v3.mkv");
+        let destination = "My Fan Edit (2020)/My Fan Edit (2020).mkv";
+
+        assert!(db.pin(&source, "../escape.mkv").is_err());
+        assert!(db.pin(&source, "/abs/x.mkv").is_err());
+        assert!(db.pin(&source, "My Fan Edit (2020).mp4").is_err());
+        db.pin(&source, destination).unwrap();
+        assert!(db.pin(&tmp.path().join("other.mkv"), destination).is_err());
+
+        let pin = db.pin_for(&source).unwrap();
+        let parsed = ParsedMedia {
+            raw_filename: "fan.edit.final.v3.mkv".to_string(),
+            ..Default::default()
+        };
+        let enriched = pin.enriched(&parsed, &ParseOptions::default());
+        assert_eq!(enriched.best_title(), "My Fan Edit");
+        assert_eq!(enriched.best_year(), Some(2020));
+        assert!(!enriched.needs_review);
+        assert_eq!(enriched.pinned.as_deref(), Some(Path::new(destination)));
+
+        assert!(db.unpin(&source));
+        assert!(db.pin_for(&source).is_none());
+    }
+}
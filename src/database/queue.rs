@@ -0,0 +1,207 @@
+//! Pending-review queue.
+//!
+//! Files scoring below `review_threshold` are not organized. They are queued
+//! here with their parse output and any near-miss provider candidates, so
+//! they can be worked through later with `plex-org queue` — from any machine
+//! that has the database.
+
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use super::Database;
+use crate::models::{EnrichedMedia, ParsedMedia};
+use crate::providers::MovieCandidate;
+
+/// Where a queued file stands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReviewStatus {
+    /// Waiting for a decision.
+    Pending,
+    /// Not media worth organizing; future scans leave it alone.
+    Rejected,
+}
+
+/// A file waiting for a person to confirm what it is.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewItem {
+    pub id: u64,
+    /// Path of the file when it was queued.
+    pub source: String,
+    pub parsed: ParsedMedia,
+    #[serde(default)]
+    pub candidates: Vec<MovieCandidate>,
+    pub confidence: f64,
+    pub status: ReviewStatus,
+    /// RFC 3339 timestamp of the first time the file was queued.
+    pub queued_at: String,
+    /// RFC 3339 timestamp of the last scan that saw it.
+    pub updated_at: String,
+}
+
+/// Queue entries are keyed by absolute path so they still make sense when
+/// the queue is worked from another directory.
+pub(super) fn queue_key(path: &Path) -> String {
+    std::path::absolute(path)
+        .unwrap_or_else(|_| path.to_path_buf())
+        .to_string_lossy()
+        .into_owned()
+}
+
+impl Database {
+    /// Queue a low-confidence file, or refresh it if already pending.
+    ///
+    /// Returns `true` when the file was newly queued.
+    pub fn enqueue_review(&mut self, source: &Path, enriched: &EnrichedMedia) -> bool {
+        let source = queue_key(source);
+        let now = Utc::now().to_rfc3339();
+
+        if let Some(item) = self
+            .data
+            .review_queue
+            .iter_mut()
+            .find(|i| i.source == source)
+        {
+            if item.status == ReviewStatus::Pending {
+                item.parsed = enriched.parsed.clone();
+                item.candidates = enriched.candidates.clone();
+                item.confidence = enriched.confidence;
+                item.updated_at = now;
+            }
+            return false;
+        }
+
+        let id = self
+            .data
+            .review_queue
+            .iter()
+            .map(|i| i.id)
+            .max()
+            .unwrap_or(0)
+            + 1;
+        self.data.review_queue.push(ReviewItem {
+            id,
+            source,
+            parsed: enriched.parsed.clone(),
+            candidates: enriched.candidates.clone(),
+            confidence: enriched.confidence,
+            status: ReviewStatus::Pending,
+            queued_at: now.clone(),
+            updated_at: now,
+        });
+        true
+    }
+
+    /// All queued items, oldest first.
+    pub fn review_items(&self) -> &[ReviewItem] {
+        &self.data.review_queue
+    }
+
+    /// Look up a queued item by id.
+    pub fn review_item(&self, id: u64) -> Option<&ReviewItem> {
+        self.data.review_queue.iter().find(|i| i.id == id)
+    }
+
+    /// Was this source rejected during review?
+    pub fn is_rejected(&self, source: &Path) -> bool {
+        let source = queue_key(source);
+        self.data
+            .review_queue
+            .iter()
+            .any(|i| i.status == ReviewStatus::Rejected && i.source == source)
+    }
+
+    /// Is this source in the queue, pending or rejected?
+    pub fn is_queued(&self, source: &Path) -> bool {
+        let source = queue_key(source);
+        self.data.review_queue.iter().any(|i| i.source == source)
+    }
+
+    /// Mark an item rejected so later scans skip the file.
+    pub fn reject_review(&mut self, id: u64) -> Result<()> {
+        match self.data.review_queue.iter_mut().find(|i| i.id == id) {
+            Some(item) => {
+                item.status = ReviewStatus::Rejected;
+                item.updated_at = Utc::now().to_rfc3339();
+                Ok(())
+            }
+            None => bail!("No queued item with id {id}"),
+        }
+    }
+
+    /// Drop an item from the queue (e.g., once it has been organized).
+    pub fn remove_review(&mut self, id: u64) -> Option<ReviewItem> {
+        let index = self.data.review_queue.iter().position(|i| i.id == id)?;
+        Some(self.data.review_queue.remove(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{MediaType, UndoEntry, UndoManifest};
+
+    fn low_confidence(title: &str) -> EnrichedMedia {
+        EnrichedMedia::from_parsed(ParsedMedia {
+            title: title.to_string(),
+            media_type: MediaType::Movie,
+            confidence: 30.0,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_enqueue_refreshes_and_survives_reopen() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("library.json");
+        let source = Path::new("/dl/mystery.mkv");
+
+        let mut db = Database::open(&path).unwrap();
+        assert!(db.enqueue_review(source, &low_confidence("mystery")));
+        assert!(!db.enqueue_review(source, &low_confidence("Mystery Film")));
+        assert!(db.enqueue_review(Path::new("/dl/other.mkv"), &low_confidence("x")));
+        db.save().unwrap();
+
+        let db = Database::open(&path).unwrap();
+        assert_eq!(db.review_items().len(), 2);
+        let item = db.review_item(1).unwrap();
+        assert_eq!(item.parsed.title, "Mystery Film");
+        assert_eq!(item.status, ReviewStatus::Pending);
+        assert_eq!(
+            db.review_item(2).unwrap().source,
+            queue_key(Path::new("/dl/other.mkv"))
+        );
+    }
+
+    #[test]
+    fn test_reject_and_organize_leave_queue() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut db = Database::open(&tmp.path().join("library.json")).unwrap();
+        db.enqueue_review(Path::new("/dl/junk.mkv"), &low_confidence("junk"));
+        db.enqueue_review(Path::new("/dl/heat.mkv"), &low_confidence("heat"));
+
+        db.reject_review(1).unwrap();
+        assert!(db.is_rejected(Path::new("/dl/junk.mkv")));
+        assert!(!db.enqueue_review(Path::new("/dl/junk.mkv"), &low_confidence("junk")));
+        assert!(db.reject_review(99).is_err());
+
+        db.record_run(&UndoManifest {
+            entries: vec![UndoEntry {
+                source: "/dl/heat.mkv".to_string(),
+                destination: "/plex/Heat (1995).mkv".to_string(),
+                strategy: "move".to_string(),
+                timestamp: Utc::now().to_rfc3339(),
+                title: "Heat".to_string(),
+                media_type: "movie".to_string(),
+                year: Some(1995),
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+        assert!(db.review_item(2).is_none());
+        assert_eq!(db.review_items().len(), 1);
+    }
+}
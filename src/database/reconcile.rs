@@ -0,0 +1,357 @@
+//! Keeping records in step with changes made to the library by hand.
+//!
+//! People and other tools rename and delete files in the library. Left
+//! alone, their records point at paths that are gone, so duplicate checks
+//! and "already organized" decisions go wrong. [`Database::reconcile`]
+//! compares the records under a library root with the files actually
+//! there: a file that vanished and reappeared elsewhere in the library with
+//! the same size and modification time (a rename or move keeps both) has
+//! its record follow it; one that is simply gone loses its record. A record
+//! with a content hash follows the file with that hash instead, so a copy
+//! across drives (a new modification time) is followed too, and a
+//! stranger of the same size and time is not.
+//! `watch --track-library` runs this before every pass.
+//!
+//! An unmounted drive looks exactly like a library whose files were all
+//! deleted. A root that is missing or empty while records point into it is
+//! refused outright, and a pass that would drop more than half of a root's
+//! records needs to be confirmed.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, Metadata};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use walkdir::WalkDir;
+
+use super::{Database, FileRecord};
+use crate::hash;
+use crate::models::SourceFingerprint;
+use crate::scanner::OUTPUT_MARKER;
+
+/// What changed in the library since the last look.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Reconciliation {
+    /// `(old destination, new destination)` of files renamed or moved.
+    pub renamed: Vec<(String, String)>,
+    /// Records of files deleted from the library.
+    pub removed: Vec<FileRecord>,
+    /// Files still in place whose size or modification time changed.
+    pub refreshed: usize,
+}
+
+/// Content hashes of untracked files computed during one reconcile, by
+/// path, kind of hash (`xxh64`, `xxh64-4m`), and size and modification
+/// time, so each file is read at most once however many records of its
+/// size are looked for.
+type Hashes = HashMap<(PathBuf, String, (u64, Option<i64>)), Option<String>>;
+
+/// Share of a root's records one reconcile may remove unconfirmed.
+const UNCONFIRMED_REMOVAL_SHARE: f64 = 0.5;
+
+impl Reconciliation {
+    /// No renames or removals; refreshed snapshots do not count.
+    pub fn is_empty(&self) -> bool {
+        self.renamed.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Size and modification time of a library file; the file itself, not a
+/// symlink's target.
+pub(super) fn snapshot(path: &Path) -> Option<SourceFingerprint> {
+    path.symlink_metadata().ok().map(|m| from_metadata(&m))
+}
+
+fn from_metadata(metadata: &Metadata) -> SourceFingerprint {
+    SourceFingerprint {
+        size: metadata.len(),
+        modified: hash::modified_secs(metadata),
+        quick_hash: None,
+    }
+}
+
+impl Database {
+    /// Follow renames and drop deleted files among the records under
+    /// `root`, refreshing the snapshot of every file still in place.
+    ///
+    /// The library is only walked when a record's file is missing. A
+    /// missing file is matched to an untracked one only when exactly one
+    /// has its last seen size and modification time or, for a record with
+    /// a content hash, when exactly one of its last seen size has that hash.
+    ///
+    /// Fails, changing nothing, when `root` is missing or empty (an
+    /// unmounted drive), or when more than half of its records would be
+    /// removed and `confirm_removals` is not set.
+    pub fn reconcile(&mut self, root: &Path, confirm_removals: bool) -> Result<Reconciliation> {
+        let mut under_root = 0;
+        let mut missing = Vec::new();
+        let mut seen = Vec::new();
+        for (index, record) in self.data.files.iter().enumerate() {
+            if !Path::new(&record.destination).starts_with(root) {
+                continue;
+            }
+            under_root += 1;
+            match snapshot(Path::new(&record.destination)) {
+                Some(now) if record.last_seen.as_ref() != Some(&now) => seen.push((index, now)),
+                Some(_) => {}
+                None => missing.push(index),
+            }
+        }
+        let mut changes = Reconciliation {
+            refreshed: seen.len(),
+            ..Default::default()
+        };
+        if !missing.is_empty() && is_missing_or_empty(root) {
+            bail!(
+                "{} is missing or empty, but {under_root} records point into it; \
+                 is the drive mounted?",
+                root.display()
+            );
+        }
+        let apply_seen = |files: &mut Vec<FileRecord>| {
+            for (index, now) in seen {
+                files[index].last_seen = Some(now);
+            }
+        };
+        if missing.is_empty() {
+            apply_seen(&mut self.data.files);
+            return Ok(changes);
+        }
+
+        let tracked: HashSet<&str> = self
+            .data
+            .files
+            .iter()
+            .map(|r| r.destination.as_str())
+            .collect();
+        let mut untracked: HashMap<(u64, Option<i64>), Vec<PathBuf>> = HashMap::new();
+        for entry in WalkDir::new(root).into_iter().filter_map(Result::ok) {
+            let path = entry.path();
+            if entry.file_type().is_dir()
+                || entry.file_name() == OUTPUT_MARKER
+                || tracked.contains(path.to_string_lossy().as_ref())
+            {
+                continue;
+            }
+            if let Ok(metadata) = entry.metadata() {
+                let seen = from_metadata(&metadata);
+                let key = (seen.size, seen.modified);
+                untracked.entry(key).or_default().push(path.to_path_buf());
+            }
+        }
+
+        let mut found = Vec::new();
+        let mut gone = Vec::new();
+        let mut hashes = Hashes::new();
+        for index in missing {
+            let record = &self.data.files[index];
+            let hit = record.last_seen.as_ref().and_then(|seen| {
+                let content_hash = record.content_hash.as_deref();
+                take_match(&mut untracked, &mut hashes, seen, content_hash)
+            });
+            match hit {
+                Some(path) => found.push((index, path)),
+                None => gone.push(index),
+            }
+        }
+        let share = gone.len() as f64 / under_root as f64;
+        if share > UNCONFIRMED_REMOVAL_SHARE && !confirm_removals {
+            bail!(
+                "{} of the {under_root} files recorded under {} are gone; \
+                 confirm their removal if that is intended",
+                gone.len(),
+                root.display()
+            );
+        }
+
+        apply_seen(&mut self.data.files);
+        for (index, path) in found {
+            let record = &mut self.data.files[index];
+            let new = path.to_string_lossy().into_owned();
+            let old = std::mem::replace(&mut record.destination, new.clone());
+            changes.renamed.push((old, new));
+        }
+        for index in gone.into_iter().rev() {
+            changes.removed.push(self.data.files.remove(index));
+        }
+        changes.removed.reverse();
+        Ok(changes)
+    }
+}
+
+/// Whether `root` is gone or holds nothing, as a mount point with nothing
+/// mounted on it does.
+fn is_missing_or_empty(root: &Path) -> bool {
+    fs::read_dir(root).map_or(true, |mut entries| entries.next().is_none())
+}
+
+/// The one untracked file `seen` describes: alone among those of its size
+/// in having `content_hash` or, without a hash, alone in having its size
+/// and modification time.
+fn take_match(
+    untracked: &mut HashMap<(u64, Option<i64>), Vec<PathBuf>>,
+    hashes: &mut Hashes,
+    seen: &SourceFingerprint,
+    content_hash: Option<&str>,
+) -> Option<PathBuf> {
+    let Some(content_hash) = content_hash else {
+        return untracked
+            .get_mut(&(seen.size, seen.modified))
+            .filter(|paths| paths.len() == 1)
+            .and_then(Vec::pop);
+    };
+    let kind = content_hash.split_once(':').map_or("", |(kind, _)| kind);
+    let mut hits = Vec::new();
+    for (key, paths) in untracked.iter().filter(|((size, _), _)| *size == seen.size) {
+        for (i, path) in paths.iter().enumerate() {
+            let hash = hashes
+                .entry((path.clone(), kind.to_string(), *key))
+                .or_insert_with(|| hash::hash_like(path, content_hash));
+            if hash.as_deref() == Some(content_hash) {
+                hits.push((*key, i));
+            }
+        }
+    }
+    match hits[..] {
+        [(key, i)] => untracked.get_mut(&key).map(|paths| paths.remove(i)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::models::{UndoEntry, UndoManifest};
+
+    #[test]
+    fn test_reconcile_follows_renames_and_drops_deletions() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().join("plex");
+        let heat = root.join("Movies/Heat (1995)/Heat (1995).mkv");
+        let alien = root.join("Movies/Alien (1979)/Alien (1979).mkv");
+        let entries = [(&heat, "heat video"), (&alien, "alien video, longer")]
+            .into_iter()
+            .map(|(path, content)| {
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, content).unwrap();
+                UndoEntry {
+                    source: "/dl/movie.mkv".to_string(),
+                    destination: path.to_string_lossy().into_owned(),
+                    strategy: "move".to_string(),
+                    timestamp: "2026-03-01T10:00:00+00:00".to_string(),
+                    media_type: "movie".to_string(),
+                    ..Default::default()
+                }
+            })
+            .collect();
+        let mut db = Database::open(&tmp.path().join("library.json")).unwrap();
+        db.record_run(&UndoManifest {
+            entries,
+            ..Default::default()
+        });
+        assert!(db.reconcile(&root, false).unwrap().is_empty());
+
+        let renamed = root.join("Movies/Heat (1995)/Heat (1995) - Director's Cut.mkv");
+        fs::rename(&heat, &renamed).unwrap();
+        fs::remove_file(&alien).unwrap();
+        let changes = db.reconcile(&root, false).unwrap();
+        assert_eq!(
+            changes.renamed,
+            [(
+                heat.to_string_lossy().into_owned(),
+                renamed.to_string_lossy().into_owned()
+            )]
+        );
+        assert_eq!(changes.removed.len(), 1);
+        assert_eq!(db.files().len(), 1);
+        assert!(db.find_by_destination(&renamed.to_string_lossy()).is_some());
+
+        // An unmounted drive, then a mass deletion, change nothing.
+        let unmounted = tmp.path().join("unmounted");
+        fs::rename(&root, &unmounted).unwrap();
+        assert!(db.reconcile(&root, true).is_err());
+        fs::create_dir(&root).unwrap();
+        assert!(db.reconcile(&root, true).is_err());
+        fs::remove_dir(&root).unwrap();
+        fs::rename(&unmounted, &root).unwrap();
+        fs::remove_file(&renamed).unwrap();
+        fs::write(root.join("Movies/notes.txt"), "kept").unwrap();
+        assert!(db.reconcile(&root, false).is_err());
+        assert_eq!(db.files().len(), 1);
+        assert_eq!(db.reconcile(&root, true).unwrap().removed.len(), 1);
+    }
+
+    #[test]
+    fn test_reconcile_follows_a_copy_by_content_hash() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().join("plex");
+        let heat = root.join("Movies/Heat (1995)/Heat (1995).mkv");
+        fs::create_dir_all(heat.parent().unwrap()).unwrap();
+        fs::write(&heat, "heat video").unwrap();
+        let mut db = Database::open(&tmp.path().join("library.json")).unwrap();
+        db.record_run(&UndoManifest {
+            entries: vec![UndoEntry {
+                source: "/dl/heat.mkv".to_string(),
+                destination: heat.to_string_lossy().into_owned(),
+                strategy: "move".to_string(),
+                title: "Heat".to_string(),
+                media_type: "movie".to_string(),
+                year: Some(1995),
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+        let full = hash::content_hash(&heat, hash::HashMode::Full, 4).unwrap();
+        db.set_content_hash(&heat.to_string_lossy(), full.unwrap());
+
+        // Copied elsewhere with a new modification time, beside a decoy of
+        // the size and modification time last seen.
+        let copy = root.join("Movies/Heat (1995) {tmdb-949}/Heat (1995).mkv");
+        fs::create_dir_all(copy.parent().unwrap()).unwrap();
+        fs::write(&copy, "heat video").unwrap();
+        fs::write(root.join("decoy.mkv"), "heat VIDEO").unwrap();
+        let old = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1);
+        fs::File::options()
+            .write(true)
+            .open(&copy)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        fs::remove_file(&heat).unwrap();
+
+        let changes = db.reconcile(&root, false).unwrap();
+        assert_eq!(changes.renamed.len(), 1);
+        assert_eq!(changes.renamed[0].1, copy.to_string_lossy());
+        assert!(changes.removed.is_empty());
+    }
+
+    #[test]
+    fn test_take_match_hashes_each_file_once() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (heat, alien) = (tmp.path().join("heat.mkv"), tmp.path().join("alien.mkv"));
+        fs::write(&heat, "heat video").unwrap();
+        fs::write(&alien, "alien vid!").unwrap();
+        let mut untracked: HashMap<_, Vec<PathBuf>> = HashMap::new();
+        for path in [&heat, &alien] {
+            let seen = snapshot(path).unwrap();
+            untracked
+                .entry((seen.size, seen.modified))
+                .or_default()
+                .push(path.clone());
+        }
+        let seen = snapshot(&heat).unwrap();
+        let full = |path: &Path| hash::content_hash(path, hash::HashMode::Full, 1).unwrap();
+
+        let mut hashes = Hashes::new();
+        let found = take_match(&mut untracked, &mut hashes, &seen, full(&alien).as_deref());
+        assert_eq!(found.as_deref(), Some(alien.as_path()));
+        assert_eq!(hashes.len(), 2);
+        fs::write(&heat, "overwrite!").unwrap();
+        // Answered from the hash taken above, not the rewritten file.
+        let found = take_match(&mut untracked, &mut hashes, &seen, full(&heat).as_deref());
+        assert_eq!(found, None);
+        assert_eq!(hashes.len(), 2);
+    }
+}
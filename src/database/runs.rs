@@ -0,0 +1,133 @@
+//! Throughput history of past organize runs.
+//!
+//! Each executed run records how many files and bytes it placed and how
+//! long that took, per strategy, so `plex-org survey` can estimate how long
+//! organizing a new directory will take on this machine.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::Database;
+use crate::models::UndoManifest;
+
+/// How many runs of history are kept.
+const MAX_RUNS: usize = 50;
+
+/// One executed run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunRecord {
+    /// RFC 3339 timestamp of the run.
+    pub finished_at: String,
+    pub strategy: String,
+    pub files: u64,
+    pub bytes: u64,
+    pub duration_ms: u64,
+}
+
+/// Observed speed of one strategy over the recorded runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Throughput {
+    pub files_per_sec: f64,
+    pub bytes_per_sec: f64,
+    /// Runs the figures are based on.
+    pub runs: usize,
+}
+
+impl Throughput {
+    /// Time to place `files` files totalling `bytes`.
+    ///
+    /// Renames cost per file, copies per byte; the slower estimate wins so
+    /// either kind of history gives a sensible answer.
+    pub fn estimate(&self, files: u64, bytes: u64) -> Duration {
+        let by_files = files as f64 / self.files_per_sec;
+        let by_bytes = bytes as f64 / self.bytes_per_sec;
+        Duration::from_secs_f64(by_files.max(by_bytes))
+    }
+}
+
+impl Database {
+    /// Record the stats of an executed run, keeping the latest runs only.
+    pub(super) fn record_stats(&mut self, manifest: &UndoManifest) {
+        let (Some(stats), Some(first)) = (&manifest.stats, manifest.entries.first()) else {
+            return;
+        };
+        self.data.runs.push(RunRecord {
+            finished_at: manifest.created_at.clone(),
+            strategy: first.strategy.clone(),
+            files: stats.files,
+            bytes: stats.bytes,
+            duration_ms: stats.duration_ms,
+        });
+        let excess = self.data.runs.len().saturating_sub(MAX_RUNS);
+        self.data.runs.drain(..excess);
+    }
+
+    /// Aggregate throughput of past `strategy` runs, if any took time.
+    pub fn throughput(&self, strategy: &str) -> Option<Throughput> {
+        let runs: Vec<_> = self
+            .data
+            .runs
+            .iter()
+            .filter(|r| r.strategy == strategy)
+            .collect();
+        let files: u64 = runs.iter().map(|r| r.files).sum();
+        let bytes: u64 = runs.iter().map(|r| r.bytes).sum();
+        // Sub-millisecond runs still took some time.
+        let secs = runs.iter().map(|r| r.duration_ms.max(1)).sum::<u64>() as f64 / 1000.0;
+        if runs.is_empty() || files == 0 {
+            return None;
+        }
+        Some(Throughput {
+            files_per_sec: files as f64 / secs,
+            bytes_per_sec: (bytes.max(1)) as f64 / secs,
+            runs: runs.len(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{RunStats, UndoEntry};
+
+    fn run(strategy: &str, files: u64, bytes: u64, duration_ms: u64) -> UndoManifest {
+        UndoManifest {
+            entries: vec![UndoEntry {
+                source: "/dl/a.mkv".to_string(),
+                destination: format!("/plex/{strategy}/{files}.mkv"),
+                strategy: strategy.to_string(),
+                timestamp: "2026-03-01T10:00:00+00:00".to_string(),
+                title: "A".to_string(),
+                media_type: "movie".to_string(),
+                ..Default::default()
+            }],
+            stats: Some(RunStats {
+                files,
+                bytes,
+                duration_ms,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_throughput_from_history() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut db = Database::open(&tmp.path().join("library.json")).unwrap();
+        assert_eq!(db.throughput("copy"), None);
+
+        db.record_run(&run("copy", 10, 1_000_000_000, 10_000));
+        db.record_run(&run("copy", 10, 1_000_000_000, 10_000));
+        db.record_run(&run("move", 100, 1_000_000_000, 1_000));
+
+        let copy = db.throughput("copy").unwrap();
+        assert_eq!(copy.runs, 2);
+        assert_eq!(copy.bytes_per_sec, 100_000_000.0);
+        // 5 GB at 100 MB/s dominates 10 files at 1 file/s.
+        assert_eq!(copy.estimate(10, 5_000_000_000), Duration::from_secs(50));
+        let moved = db.throughput("move").unwrap();
+        assert_eq!(moved.estimate(500, 0), Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,30 @@
+//! Checkpoints of scans that did not finish.
+//!
+//! `plex-org scan` stores a [`ScanCheckpoint`] per source as it goes, and
+//! `scan --resume` picks up from it. A scan that finishes drops its
+//! checkpoint.
+
+use std::path::Path;
+
+use super::Database;
+use crate::scanner::ScanCheckpoint;
+
+impl Database {
+    /// The checkpoint of an unfinished scan of `root`.
+    pub fn scan_checkpoint(&self, root: &Path) -> Option<&ScanCheckpoint> {
+        self.data.scan_checkpoints.iter().find(|c| c.root == root)
+    }
+
+    /// Keep `checkpoint`, replacing any earlier one of its root.
+    pub fn set_scan_checkpoint(&mut self, checkpoint: ScanCheckpoint) {
+        self.clear_scan_checkpoint(&checkpoint.root);
+        self.data.scan_checkpoints.push(checkpoint);
+    }
+
+    /// Drop the checkpoint of `root`, returning whether there was one.
+    pub fn clear_scan_checkpoint(&mut self, root: &Path) -> bool {
+        let before = self.data.scan_checkpoints.len();
+        self.data.scan_checkpoints.retain(|c| c.root != root);
+        self.data.scan_checkpoints.len() < before
+    }
+}
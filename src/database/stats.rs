@@ -0,0 +1,190 @@
+//! Library composition for `plex-org stats`.
+//!
+//! Everything comes from the records of organized files, so it is quick
+//! and works with the library offline: quality dimensions are read back
+//! from each record's canonical quality string, sizes from the size last
+//! seen (the file itself when never seen). Companion files (subtitles,
+//! artwork) are left out.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+
+use super::{Database, FileRecord};
+use crate::quality::Quality;
+use crate::scanner;
+
+/// Files and bytes of one group.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Bucket {
+    /// The group: a year, `1080p`, `BluRay`, `2024-05`; `unknown` when the
+    /// record does not say.
+    pub key: String,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// What the library database holds.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LibraryStats {
+    pub files: usize,
+    pub bytes: u64,
+    pub by_type: Vec<Bucket>,
+    pub by_year: Vec<Bucket>,
+    pub by_resolution: Vec<Bucket>,
+    pub by_source: Vec<Bucket>,
+    pub by_codec: Vec<Bucket>,
+    /// Files organized per month, oldest first.
+    pub by_month: Vec<Bucket>,
+    /// Movies organized without a provider match (no TMDb id).
+    pub unmatched: usize,
+    /// Files held below the review threshold, waiting in the queue.
+    pub awaiting_review: usize,
+    /// Mean match confidence of the files organized with one recorded.
+    pub average_confidence: Option<f64>,
+}
+
+/// Group `records` by `key`, in key order, with `None` last as `unknown`.
+fn tally<K: Ord + ToString>(
+    records: &[(&FileRecord, u64)],
+    key: impl Fn(&FileRecord) -> Option<K>,
+) -> Vec<Bucket> {
+    let mut known: BTreeMap<K, (usize, u64)> = BTreeMap::new();
+    let mut unknown = (0, 0);
+    for (record, size) in records {
+        let slot = match key(record) {
+            Some(k) => known.entry(k).or_default(),
+            None => &mut unknown,
+        };
+        slot.0 += 1;
+        slot.1 += size;
+    }
+    let mut buckets: Vec<Bucket> = known
+        .into_iter()
+        .map(|(key, (files, bytes))| Bucket {
+            key: key.to_string(),
+            files,
+            bytes,
+        })
+        .collect();
+    if unknown.0 > 0 {
+        buckets.push(Bucket {
+            key: "unknown".to_string(),
+            files: unknown.0,
+            bytes: unknown.1,
+        });
+    }
+    buckets
+}
+
+impl Database {
+    /// Counts and sizes of the organized library, grouped several ways.
+    pub fn stats(&self) -> LibraryStats {
+        let records: Vec<(&FileRecord, u64)> = self
+            .data
+            .files
+            .iter()
+            .filter(|r| scanner::is_media_path(Path::new(&r.destination)))
+            .map(|r| {
+                let size = match &r.last_seen {
+                    Some(seen) => seen.size,
+                    None => fs::metadata(&r.destination).map_or(0, |m| m.len()),
+                };
+                (r, size)
+            })
+            .collect();
+        let quality = |r: &FileRecord| Quality::parse(&r.quality);
+        let confidences: Vec<f64> = records.iter().filter_map(|(r, _)| r.confidence).collect();
+        LibraryStats {
+            files: records.len(),
+            bytes: records.iter().map(|(_, size)| size).sum(),
+            by_type: tally(&records, |r| Some(r.media_type.clone())),
+            by_year: tally(&records, |r| r.year),
+            by_resolution: tally(&records, |r| quality(r).resolution),
+            by_source: tally(&records, |r| quality(r).source),
+            by_codec: tally(&records, |r| quality(r).codec),
+            by_month: tally(&records, |r| {
+                r.organized_time().map(|t| t.format("%Y-%m").to_string())
+            }),
+            unmatched: records
+                .iter()
+                .filter(|(r, _)| r.media_type == "movie" && r.tmdb_id.is_none())
+                .count(),
+            awaiting_review: self.data.review_queue.len(),
+            average_confidence: (!confidences.is_empty())
+                .then(|| confidences.iter().sum::<f64>() / confidences.len() as f64),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{UndoEntry, UndoManifest};
+
+    fn entry(
+        destination: &str,
+        quality: &str,
+        year: Option<i32>,
+        tmdb_id: Option<u64>,
+    ) -> UndoEntry {
+        UndoEntry {
+            source: format!("/downloads/{destination}"),
+            destination: destination.to_string(),
+            strategy: "move".to_string(),
+            timestamp: "2024-05-03T10:00:00+00:00".to_string(),
+            media_type: "movie".to_string(),
+            year,
+            quality: quality.to_string(),
+            tmdb_id,
+            confidence: tmdb_id.map(|_| 90.0),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_stats_group_the_library() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut db = Database::open(&tmp.path().join("library.json")).unwrap();
+        db.record_run(&UndoManifest {
+            entries: vec![
+                entry(
+                    "/lib/Heat (1995).mkv",
+                    "1080p BluRay H.264",
+                    Some(1995),
+                    Some(949),
+                ),
+                entry(
+                    "/lib/Alien (1979).mkv",
+                    "2160p BluRay HDR H.265",
+                    Some(1979),
+                    Some(348),
+                ),
+                entry("/lib/Unknown.mkv", "", None, None),
+                entry("/lib/Heat (1995).en.srt", "", Some(1995), Some(949)),
+            ],
+            ..Default::default()
+        });
+
+        let stats = db.stats();
+        assert_eq!(stats.files, 3);
+        let keys = |buckets: &[Bucket]| -> Vec<String> {
+            buckets
+                .iter()
+                .map(|b| format!("{}:{}", b.key, b.files))
+                .collect()
+        };
+        assert_eq!(keys(&stats.by_year), ["1979:1", "1995:1", "unknown:1"]);
+        assert_eq!(
+            keys(&stats.by_resolution),
+            ["1080p:1", "2160p:1", "unknown:1"]
+        );
+        assert_eq!(keys(&stats.by_source), ["BluRay:2", "unknown:1"]);
+        assert_eq!(keys(&stats.by_codec), ["H.264:1", "H.265:1", "unknown:1"]);
+        assert_eq!(keys(&stats.by_month), ["2024-05:3"]);
+        assert_eq!(stats.unmatched, 1);
+        assert_eq!(stats.average_confidence, Some(90.0));
+    }
+}
@@ -0,0 +1,429 @@
+//! Environment self-diagnostics behind `plex-org doctor`.
+//!
+//! Most failed runs trace back to the environment rather than the code: a
+//! config typo, a TMDb key that was revoked, a library the service user
+//! cannot write to, a full disk, or a network mount that drops out under
+//! load. [`diagnose`] checks each of these and returns findings, the
+//! problems first, each with what to do about it.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::config::AppConfig;
+use crate::database::{Database, SCHEMA_VERSION};
+use crate::organizer::free_space;
+use crate::providers::tmdb::TmdbClient;
+
+/// Free space below which a destination is flagged.
+const LOW_SPACE_BYTES: u64 = 10 << 30;
+
+/// Filesystem types served over the network.
+const NETWORK_FS: &[&str] = &[
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smb3",
+    "smbfs",
+    "afpfs",
+    "webdav",
+    "9p",
+    "ceph",
+    "glusterfs",
+    "afs",
+    "sshfs",
+];
+
+/// How much a finding matters; problems sort first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Runs will fail or go wrong until this is fixed.
+    Problem,
+    /// Runs work, but worse than they could.
+    Warning,
+    /// All good.
+    Ok,
+}
+
+/// The outcome of one check.
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub severity: Severity,
+    /// What was checked, e.g. `tmdb` or `destination /media/plex`.
+    pub check: String,
+    pub detail: String,
+    /// What to do about it.
+    pub fix: Option<String>,
+}
+
+impl Finding {
+    fn ok(check: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Ok,
+            check: check.into(),
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn flag(
+        severity: Severity,
+        check: impl Into<String>,
+        detail: impl Into<String>,
+        fix: impl Into<String>,
+    ) -> Self {
+        Self {
+            severity,
+            check: check.into(),
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// What the environment is checked against.
+#[derive(Debug, Clone, Default)]
+pub struct DoctorInputs {
+    /// The config file named on the command line, if any.
+    pub config_path: Option<PathBuf>,
+    /// Why that file failed to load; defaults were checked instead.
+    pub config_error: Option<String>,
+    pub database: PathBuf,
+    /// Destinations to check besides those in the config.
+    pub destinations: Vec<PathBuf>,
+}
+
+/// Check the environment, returning findings ordered most urgent first.
+pub fn diagnose(config: &AppConfig, inputs: &DoctorInputs) -> Vec<Finding> {
+    let mut findings = vec![
+        check_config(inputs),
+        check_database(&inputs.database),
+        check_curl(),
+        check_tmdb(config),
+    ];
+    if config.scan.probe {
+        findings.push(check_ffprobe());
+    }
+    for dest in destinations(config, inputs) {
+        findings.extend(check_destination(&dest));
+    }
+    findings.sort_by_key(|f| f.severity);
+    findings
+}
+
+fn check_config(inputs: &DoctorInputs) -> Finding {
+    match (&inputs.config_path, &inputs.config_error) {
+        (Some(path), Some(error)) => Finding::flag(
+            Severity::Problem,
+            "config",
+            format!("{} does not load: {error}", path.display()),
+            "Fix the file; `plex-org config` shows the values in effect once it loads.",
+        ),
+        (Some(path), None) if !path.exists() => Finding::flag(
+            Severity::Warning,
+            "config",
+            format!("{} does not exist; running on defaults", path.display()),
+            "Copy config/default_config.toml there, or drop -c.",
+        ),
+        (Some(path), None) => Finding::ok("config", format!("{} loads", path.display())),
+        (None, _) => Finding::ok("config", "no config file; running on defaults"),
+    }
+}
+
+fn check_database(path: &Path) -> Finding {
+    let db = match Database::open(path) {
+        Ok(db) => db,
+        Err(e) => {
+            return Finding::flag(
+                Severity::Problem,
+                "database",
+                format!("{e:#}"),
+                format!(
+                    "Restore {} from a backup, or move it aside to start a new one \
+                     (organized files are kept; their history is lost).",
+                    path.display()
+                ),
+            );
+        }
+    };
+    let dir = path.parent().unwrap_or(Path::new("."));
+    if let Err(e) = fs::create_dir_all(dir).and_then(|()| probe_write(dir)) {
+        return Finding::flag(
+            Severity::Problem,
+            "database",
+            format!("{} is not writable: {e}", dir.display()),
+            format!(
+                "Give the user running plex-org write access to {}.",
+                dir.display()
+            ),
+        );
+    }
+    Finding::ok(
+        "database",
+        format!(
+            "{}: schema {} (supported: {SCHEMA_VERSION}), {} files",
+            path.display(),
+            db.schema_version(),
+            db.files().len()
+        ),
+    )
+}
+
+fn check_curl() -> Finding {
+    match Command::new("curl").arg("--version").output() {
+        Ok(out) if out.status.success() => {
+            let version = String::from_utf8_lossy(&out.stdout);
+            Finding::ok(
+                "curl",
+                version.lines().next().unwrap_or_default().to_string(),
+            )
+        }
+        _ => Finding::flag(
+            Severity::Problem,
+            "curl",
+            "curl is not on PATH; TMDb and Plex requests go through it",
+            "Install curl (it ships with macOS and Windows 10+).",
+        ),
+    }
+}
+
+fn check_ffprobe() -> Finding {
+    match Command::new("ffprobe").arg("-version").output() {
+        Ok(out) if out.status.success() => {
+            let version = String::from_utf8_lossy(&out.stdout);
+            Finding::ok(
+                "ffprobe",
+                version.lines().next().unwrap_or_default().to_string(),
+            )
+        }
+        _ => Finding::flag(
+            Severity::Warning,
+            "ffprobe",
+            "scan.probe is on but ffprobe is not on PATH; qualities come from names",
+            "Install FFmpeg, or set scan.probe = false.",
+        ),
+    }
+}
+
+fn check_tmdb(config: &AppConfig) -> Finding {
+    if config.tmdb.api_key.is_empty() {
+        return Finding::flag(
+            Severity::Warning,
+            "tmdb",
+            "no API key; files are matched by name alone",
+            "Set tmdb.api_key in the config, or TMDB_API_KEY.",
+        );
+    }
+    match TmdbClient::new(&config.tmdb.api_key, 0).check_key() {
+        Ok(()) => Finding::ok("tmdb", "API key accepted"),
+        Err(e) if format!("{e:#}").contains("HTTP 401") => Finding::flag(
+            Severity::Problem,
+            "tmdb",
+            "the API key was rejected",
+            "Copy the key (v3) or read access token (v4) again from themoviedb.org settings.",
+        ),
+        Err(e) => Finding::flag(
+            Severity::Warning,
+            "tmdb",
+            format!("could not reach TMDb: {e:#}"),
+            "Check the network or proxy; runs will match by name alone meanwhile.",
+        ),
+    }
+}
+
+/// The configured destinations, then the extra ones, without repeats.
+fn destinations(config: &AppConfig, inputs: &DoctorInputs) -> Vec<PathBuf> {
+    let organize = &config.organize;
+    let configured = [
+        &config.destination,
+        &organize.staging_dir,
+        &organize.view_dir,
+    ]
+    .into_iter()
+    .chain(&organize.library_roots)
+    .filter(|d| !d.is_empty())
+    .map(PathBuf::from);
+    let mut all: Vec<PathBuf> = Vec::new();
+    for dest in configured.chain(inputs.destinations.iter().cloned()) {
+        if !all.contains(&dest) {
+            all.push(dest);
+        }
+    }
+    all
+}
+
+fn check_destination(dest: &Path) -> Vec<Finding> {
+    let check = format!("destination {}", dest.display());
+    if !dest.is_dir() {
+        return vec![Finding::flag(
+            Severity::Warning,
+            check,
+            "does not exist (or is not mounted)",
+            format!(
+                "Create {} or mount it; a first run creates it, which may hide a missing mount.",
+                dest.display()
+            ),
+        )];
+    }
+    if let Err(e) = probe_write(dest) {
+        return vec![Finding::flag(
+            Severity::Problem,
+            check,
+            format!("cannot create and rename files: {e}"),
+            format!(
+                "Give the user running plex-org write access to {}.",
+                dest.display()
+            ),
+        )];
+    }
+    let mut findings = vec![Finding::ok(&check, "files can be created and renamed")];
+    match free_space(dest) {
+        Some(free) if free < LOW_SPACE_BYTES => findings.push(Finding::flag(
+            Severity::Warning,
+            &check,
+            format!("only {:.1} GiB free", gib(free)),
+            "Free up space; copies and cross-filesystem moves need room for whole files.",
+        )),
+        Some(free) => findings.push(Finding::ok(&check, format!("{:.0} GiB free", gib(free)))),
+        None => {}
+    }
+    if let Some(fs_type) = filesystem_type(dest).filter(|t| is_network_fs(t)) {
+        findings.push(Finding::flag(
+            Severity::Warning,
+            &check,
+            format!("on a network filesystem ({fs_type})"),
+            "For flaky mounts use --adaptive and --paranoid (or adaptive_parallel and \
+             paranoid under [organize]); moves from local disk copy, then delete.",
+        ));
+    }
+    findings
+}
+
+/// Create, rename, and remove a scratch file in `dir`.
+fn probe_write(dir: &Path) -> std::io::Result<()> {
+    let probe = dir.join(format!(".plex-org-doctor-{}", std::process::id()));
+    let renamed = probe.with_extension("renamed");
+    fs::write(&probe, b"")?;
+    let result = fs::rename(&probe, &renamed);
+    let _ = fs::remove_file(&probe);
+    let _ = fs::remove_file(&renamed);
+    result
+}
+
+fn gib(bytes: u64) -> f64 {
+    bytes as f64 / (1u64 << 30) as f64
+}
+
+fn is_network_fs(fs_type: &str) -> bool {
+    let fs_type = fs_type.strip_prefix("fuse.").unwrap_or(fs_type);
+    NETWORK_FS.contains(&fs_type)
+}
+
+/// Type of the filesystem holding `path`, e.g. `ext4` or `cifs`.
+#[cfg(target_os = "linux")]
+fn filesystem_type(path: &Path) -> Option<String> {
+    let path = fs::canonicalize(path).ok()?;
+    let mountinfo = fs::read_to_string("/proc/self/mountinfo").ok()?;
+    mount_type(&mountinfo, &path)
+}
+
+#[cfg(target_os = "macos")]
+fn filesystem_type(path: &Path) -> Option<String> {
+    use std::os::unix::ffi::OsStrExt;
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    let name = unsafe { std::ffi::CStr::from_ptr(stat.f_fstypename.as_ptr()) };
+    Some(name.to_string_lossy().into_owned())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn filesystem_type(_path: &Path) -> Option<String> {
+    None
+}
+
+/// The type of the innermost mount holding `path`, from the text of
+/// `/proc/self/mountinfo`.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn mount_type(mountinfo: &str, path: &Path) -> Option<String> {
+    mountinfo
+        .lines()
+        .filter_map(|line| {
+            let (mount, rest) = line.split_once(" - ")?;
+            let point = unescape(mount.split(' ').nth(4)?);
+            let fs_type = rest.split(' ').next()?;
+            path.starts_with(&point)
+                .then(|| (point.as_os_str().len(), fs_type.to_string()))
+        })
+        .max_by_key(|(len, _)| *len)
+        .map(|(_, fs_type)| fs_type)
+}
+
+/// Undo the octal escapes (`\040` for a space) of mountinfo paths.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn unescape(field: &str) -> PathBuf {
+    let mut out = String::new();
+    let mut rest = field;
+    while let Some(at) = rest.find('\\') {
+        out.push_str(&rest[..at]);
+        let code = rest.get(at + 1..at + 4);
+        match code.and_then(|c| u8::from_str_radix(c, 8).ok()) {
+            Some(byte) => {
+                out.push(byte as char);
+                rest = &rest[at + 4..];
+            }
+            None => {
+                out.push('\\');
+                rest = &rest[at + 1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    PathBuf::from(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mount_type_takes_the_innermost_mount() {
+        let mountinfo = "\
+22 1 8:1 / / rw,relatime shared:1 - ext4 /dev/sda1 rw
+40 22 0:50 / /mnt/nas rw,relatime shared:20 - cifs //nas/media rw,vers=3.0
+41 22 0:51 / /mnt/My\\040Movies rw shared:21 - fuse.sshfs host:/m rw";
+        let fs = |p: &str| mount_type(mountinfo, Path::new(p));
+        assert_eq!(fs("/mnt/nas/Movies").as_deref(), Some("cifs"));
+        assert_eq!(fs("/mnt/nasty").as_deref(), Some("ext4"));
+        assert_eq!(fs("/mnt/My Movies/x").as_deref(), Some("fuse.sshfs"));
+        assert!(is_network_fs("fuse.sshfs") && !is_network_fs("ext4"));
+    }
+
+    #[test]
+    fn test_diagnose_flags_unwritable_and_missing_destinations() {
+        let tmp = tempfile::tempdir().unwrap();
+        let inputs = DoctorInputs {
+            config_path: Some(tmp.path().join("missing.toml")),
+            database: tmp.path().join("library.json"),
+            destinations: vec![tmp.path().to_path_buf(), tmp.path().join("gone")],
+            ..Default::default()
+        };
+        let findings = diagnose(&AppConfig::default(), &inputs);
+        let severity = |check: &str| {
+            findings
+                .iter()
+                .filter(|f| f.check == check)
+                .map(|f| f.severity)
+                .min()
+        };
+        assert_eq!(severity("config"), Some(Severity::Warning));
+        assert_eq!(severity("database"), Some(Severity::Ok));
+        assert_eq!(severity("tmdb"), Some(Severity::Warning));
+        let gone = format!("destination {}", tmp.path().join("gone").display());
+        assert_eq!(severity(&gone), Some(Severity::Warning));
+        assert!(findings.windows(2).all(|w| w[0].severity <= w[1].severity));
+        assert_eq!(fs::read_dir(tmp.path()).unwrap().count(), 0);
+    }
+}
@@ -0,0 +1,164 @@
+//! Double features: one file holding two movies.
+//!
+//! `Grindhouse.Planet.Terror.Death.Proof.2007.mkv` names two films, and as a
+//! single title it matches neither. When the full title finds nothing on
+//! TMDb, its words are split into two halves and each is searched; a split
+//! where both halves match becomes a double feature. The file is named
+//! after the first movie, as Plex expects, and the second is recorded with
+//! it (see `organize.double_features`).
+//!
+//! A `&`, `+`, or ` / ` between titles marks the split outright, as does
+//! `and` in a title tagged "Double Feature"; other titles are split word by
+//! word, and need at least [`MIN_WORDS`] words unless tagged.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use anyhow::Result;
+use chrono::NaiveDate;
+use regex::Regex;
+
+use crate::providers::tmdb::TmdbClient;
+use crate::providers::{self, MovieCandidate};
+
+/// Unmarked titles shorter than this are never split.
+pub const MIN_WORDS: usize = 4;
+
+/// Titles longer than this are not split word by word; the searches would
+/// outnumber the odds of a hit.
+const MAX_WORDS: usize = 8;
+
+static MARKER_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\bdouble[\s._-]*feature\b").unwrap());
+
+static SEPARATOR_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\s+(?:&|\+|/)\s+").unwrap());
+
+static AND_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)\s+and\s+").unwrap());
+
+/// Whether a name carries a "Double Feature" tag.
+pub fn is_marked(name: &str) -> bool {
+    MARKER_RE.is_match(name)
+}
+
+/// Ways to read `title` as two movie titles, to be searched in order.
+///
+/// An explicit separator gives the only split. Otherwise every split into
+/// halves of two or more words is offered, and where the first half is
+/// longer, also without its first word, which is often an umbrella title
+/// (`Grindhouse`).
+pub fn splits(title: &str) -> Vec<(String, String)> {
+    let marked = is_marked(title);
+    let title = MARKER_RE.replace_all(title, " ");
+    let mut separators = vec![&*SEPARATOR_RE];
+    if marked {
+        separators.push(&*AND_RE);
+    }
+    for separator in separators {
+        let parts: Vec<&str> = separator.split(title.trim()).collect();
+        if let [first, second] = parts[..] {
+            let (first, second) = (first.trim(), second.trim());
+            if !first.is_empty() && !second.is_empty() {
+                return vec![(first.to_string(), second.to_string())];
+            }
+        }
+    }
+    let words: Vec<&str> = title.split_whitespace().collect();
+    let min_words = if marked { 2 } else { MIN_WORDS };
+    if !(min_words..=MAX_WORDS).contains(&words.len()) {
+        return Vec::new();
+    }
+    let mut splits = Vec::new();
+    for at in min_words / 2..=words.len() - min_words / 2 {
+        let (first, second) = (words[..at].join(" "), words[at..].join(" "));
+        if at >= 3 {
+            splits.push((words[1..at].join(" "), second.clone()));
+        }
+        splits.push((first, second));
+    }
+    splits
+}
+
+/// Two movies matched for one file, in file order, with the lower of their
+/// two scores.
+#[derive(Debug, Clone)]
+pub struct Match {
+    pub first: MovieCandidate,
+    pub second: MovieCandidate,
+    pub score: f64,
+}
+
+/// Search TMDb for `title` as two movies, trying [`splits`] in order until
+/// both halves match distinct films at `min_score` or better.
+///
+/// Halves are searched without a year, and each result is scored both
+/// with and without the file's year: a double-feature disc carries one
+/// year, often that of the later film.
+pub fn lookup(
+    client: &TmdbClient,
+    title: &str,
+    year: Option<i32>,
+    released_by: Option<NaiveDate>,
+    min_score: f64,
+) -> Result<Option<Match>> {
+    let mut searched: HashMap<String, Option<(MovieCandidate, f64)>> = HashMap::new();
+    let mut best = |half: &str| -> Result<Option<(MovieCandidate, f64)>> {
+        if let Some(found) = searched.get(half) {
+            return Ok(found.clone());
+        }
+        let candidates = client.search_movie(half, None)?;
+        let found = [year, None]
+            .into_iter()
+            .filter_map(|y| providers::best_candidate(half, y, released_by, &candidates, min_score))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        searched.insert(half.to_string(), found.clone());
+        Ok(found)
+    };
+    for (first, second) in splits(title) {
+        let Some((first, first_score)) = best(&first)? else {
+            continue;
+        };
+        let Some((second, second_score)) = best(&second)? else {
+            continue;
+        };
+        if first.id != second.id {
+            return Ok(Some(Match {
+                first,
+                second,
+                score: first_score.min(second_score),
+            }));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_splits() {
+        let splits = splits("Grindhouse Planet Terror Death Proof");
+        assert!(splits.contains(&("Planet Terror".into(), "Death Proof".into())));
+        assert!(splits.contains(&("Grindhouse Planet".into(), "Terror Death Proof".into())));
+        assert_eq!(splits.len(), 3);
+
+        assert_eq!(
+            super::splits("Kill Bill Vol 1 & Kill Bill Vol 2"),
+            [("Kill Bill Vol 1".into(), "Kill Bill Vol 2".into())]
+        );
+        assert_eq!(
+            super::splits("Double Feature Alien / Aliens"),
+            [("Alien".into(), "Aliens".into())]
+        );
+        assert_eq!(
+            super::splits("Alien and Aliens Double Feature"),
+            [("Alien".into(), "Aliens".into())]
+        );
+        assert_eq!(
+            super::splits("Double Feature Alien Aliens"),
+            [("Alien".into(), "Aliens".into())]
+        );
+        assert!(super::splits("The Dark Knight").is_empty());
+        assert!(is_marked("Alien.Aliens.Double.Feature.1986.mkv"));
+    }
+}
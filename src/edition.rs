@@ -0,0 +1,181 @@
+//! Edition detection: cuts, anniversary and remaster re-releases.
+//!
+//! Re-releases carry a second year and words that are not part of the
+//! title: `Alien.1979.40th.Anniversary.2019.mkv`,
+//! `Top.Gun.1986.Remastered.2020.2160p.mkv`. Left in the name, hunch takes
+//! the re-release year as the year and folds the original into the title
+//! (`Alien 1979 40th`, 2019). The tag is cut out before parsing and kept as
+//! the file's [`Edition`], with the re-release year beside it. Named cuts
+//! (`Director's Cut`, `Extended`, `IMAX`, `Criterion`, ...) are read the
+//! same way, under a single spelling, and Radarr's `{edition-…}` tag is
+//! taken as written. With `organize.edition_tags` the edition names the
+//! movie's folder and file, so several cuts of a film sit side by side.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::models::Edition;
+
+/// `40th Anniversary [Edition] [2019]`, `Remastered [Edition] [2014]`,
+/// `Director's Cut`, and the other named cuts, optionally in brackets,
+/// with the separator before it.
+static EDITION_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?ix)
+        [.\s_-]*[(\[]?
+        (?:
+            (?P<ordinal>\d{1,3}(?:st|nd|rd|th))[.\s_-]+anniversary
+          | (?P<remaster>(?:digitally[.\s_-]+)?remaster(?:ed)?)
+          | (?P<cut>
+                directors?'?s?[.\s_-]+cut | final[.\s_-]+cut
+              | (?:extended|theatrical)(?:[.\s_-]+(?:cut|version))?
+              | unrated | uncut | imax | criterion(?:[.\s_-]+collection)?
+              | ultimate(?:[.\s_-]+cut)?
+              | special[.\s_-]+edition | collector'?s[.\s_-]+edition
+            )
+        )
+        (?:[.\s_-]+edition)?
+        (?:[.\s_-]+(?P<year>(?:19|20)\d{2}))?
+        \b[)\]]?",
+    )
+    .unwrap()
+});
+
+/// Radarr's `{edition-Director's Cut}`.
+static EDITION_TAG_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\s*[\{\[]edition[-=]([^\}\]]*)[\}\]]").unwrap());
+
+/// A release year standing alone in a name.
+static YEAR_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?:^|[^0-9])(?:19|20)\d{2}(?:[^0-9]|$)").unwrap());
+
+/// Cut an edition tag out of a file or folder name, returning what is left
+/// and the edition.
+///
+/// Anniversary and remaster words only count after the original release
+/// year, so titles that contain them (`ReMastered: Who Shot the Sheriff`)
+/// are left alone.
+pub fn extract(name: &str) -> (String, Option<Edition>) {
+    if let Some(caps) = EDITION_TAG_RE.captures(name) {
+        let edition = Edition {
+            name: caps[1].trim().to_string(),
+            year: None,
+        };
+        let rest = EDITION_TAG_RE.replace(name, "").into_owned();
+        return (rest, Some(edition).filter(|e| !e.name.is_empty()));
+    }
+    let Some(caps) = EDITION_RE
+        .captures_iter(name)
+        .find(|caps| YEAR_RE.is_match(&name[..caps.get(0).unwrap().start()]))
+    else {
+        return (name.to_string(), None);
+    };
+    let label = match (caps.name("ordinal"), caps.name("cut")) {
+        (Some(ordinal), _) => format!("{} Anniversary", ordinal.as_str().to_lowercase()),
+        (_, Some(cut)) => cut_label(cut.as_str()).to_string(),
+        _ => "Remastered".to_string(),
+    };
+    let edition = Edition {
+        name: label,
+        year: caps.name("year").and_then(|y| y.as_str().parse().ok()),
+    };
+    let span = caps.get(0).unwrap().range();
+    let rest = format!("{}{}", &name[..span.start], &name[span.end..]);
+    (rest, Some(edition))
+}
+
+/// The one spelling of a named cut.
+fn cut_label(cut: &str) -> &'static str {
+    let cut = cut.to_lowercase();
+    const LABELS: [(&str, &str); 11] = [
+        ("director", "Director's Cut"),
+        ("final", "Final Cut"),
+        ("extended", "Extended"),
+        ("theatrical", "Theatrical"),
+        ("unrated", "Unrated"),
+        ("uncut", "Uncut"),
+        ("imax", "IMAX"),
+        ("criterion", "Criterion"),
+        ("ultimate", "Ultimate"),
+        ("special", "Special Edition"),
+        ("collector", "Collector's Edition"),
+    ];
+    LABELS
+        .iter()
+        .find(|(prefix, _)| cut.starts_with(prefix))
+        .map_or("Special Edition", |(_, label)| label)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_editions() {
+        let cases = [
+            (
+                "Alien.1979.40th.Anniversary.2019.mkv",
+                "Alien.1979.mkv",
+                Some(("40th Anniversary", Some(2019))),
+            ),
+            (
+                "Top.Gun.1986.Remastered.2020.2160p.mkv",
+                "Top.Gun.1986.2160p.mkv",
+                Some(("Remastered", Some(2020))),
+            ),
+            (
+                "Jaws (1975) 45th Anniversary Edition.mkv",
+                "Jaws (1975).mkv",
+                Some(("45th Anniversary", None)),
+            ),
+            (
+                "E.T.1982.(20TH.ANNIVERSARY).720p.mkv",
+                "E.T.1982.720p.mkv",
+                Some(("20th Anniversary", None)),
+            ),
+            (
+                "Heat (1995) {edition-Director's Cut}.mkv",
+                "Heat (1995).mkv",
+                Some(("Director's Cut", None)),
+            ),
+            (
+                "Blade.Runner.1982.Final.Cut.2007.1080p.mkv",
+                "Blade.Runner.1982.1080p.mkv",
+                Some(("Final Cut", Some(2007))),
+            ),
+            (
+                "Aliens.1986.Directors.Cut.1080p.BluRay.mkv",
+                "Aliens.1986.1080p.BluRay.mkv",
+                Some(("Director's Cut", None)),
+            ),
+            (
+                "The.Lord.of.the.Rings.2001.EXTENDED.EDITION.2160p.mkv",
+                "The.Lord.of.the.Rings.2001.2160p.mkv",
+                Some(("Extended", None)),
+            ),
+            (
+                "Seven Samurai (1954) [Criterion Collection].mkv",
+                "Seven Samurai (1954).mkv",
+                Some(("Criterion", None)),
+            ),
+            (
+                "Dune.2021.IMAX.2160p.mkv",
+                "Dune.2021.2160p.mkv",
+                Some(("IMAX", None)),
+            ),
+            ("ReMastered.Who.Shot.the.Sheriff.2018.mkv", "", None),
+            ("Unrated.2012.1080p.mkv", "", None),
+        ];
+        for (name, rest, expected) in cases {
+            let (left, edition) = extract(name);
+            let edition = edition.as_ref().map(|e| (e.name.as_str(), e.year));
+            assert_eq!(edition, expected, "{name}");
+            if expected.is_some() {
+                assert_eq!(left, rest, "{name}");
+            } else {
+                assert_eq!(left, name);
+            }
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! AniList identification of anime files (see `providers::anilist`).
+
+use tracing::{debug, warn};
+
+use super::Enricher;
+use crate::models::{AnimeRelease, MediaType, ParsedMedia, TitleVariants};
+use crate::parser;
+use crate::profile::{Profile, Rules};
+use crate::providers::anilist;
+
+impl Enricher {
+    /// Identify a fansub release, or any file of the anime profile, on
+    /// AniList and name it after the entry: its English title (else the
+    /// romaji one), its other titles as variants, and its year when the
+    /// name has none. The movie search that follows looks for that.
+    /// Returns the match score.
+    pub(super) fn identify_anime(&self, parsed: &mut ParsedMedia, rules: &Rules) -> Option<f64> {
+        let client = self.anilist.as_ref()?;
+        if parsed.title.is_empty() || (parsed.anime.is_none() && rules.profile != Profile::Anime) {
+            return None;
+        }
+        let results = match client.search(&parsed.title) {
+            Ok(results) => results,
+            Err(e) => {
+                warn!("{e:#}; continuing without AniList");
+                return None;
+            }
+        };
+        let mut names = vec![parsed.title.as_str()];
+        names.extend(parsed.titles.english.as_deref());
+        let (found, score) = anilist::best_candidate(
+            &names,
+            parsed.year,
+            parsed.media_type,
+            &results,
+            rules.min_match_score,
+        )?;
+        let title = found.title()?.to_string();
+        debug!(
+            "AniList identified {:?} as {title:?} ({:?}), score {score:.0}",
+            parsed.raw_filename, found.year
+        );
+
+        let confidence = parsed.confidence;
+        parser::set_title(parsed, title, &self.parse);
+        parsed.confidence = parsed.confidence.max(confidence);
+        parsed.titles = TitleVariants {
+            original: found.native.clone(),
+            romanized: found.romaji.clone(),
+            english: found.english.clone(),
+        };
+        if parsed.year.is_none() && found.year.is_some() {
+            parsed.year = found.year;
+            parsed.year_from_match = true;
+        }
+        if parsed.media_type == MediaType::Unknown {
+            parsed.media_type = found.media_type().unwrap_or_default();
+        }
+        parsed
+            .anime
+            .get_or_insert_with(AnimeRelease::default)
+            .anilist_id = Some(found.id);
+        Some(score)
+    }
+}
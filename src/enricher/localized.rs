@@ -0,0 +1,141 @@
+//! Searching TMDb in a title's own language (see `language`).
+
+use tracing::warn;
+
+use super::movie::{is_exact_match, release_cutoff};
+use super::Enricher;
+use crate::http;
+use crate::language;
+use crate::models::{EnrichedMedia, ParsedMedia};
+use crate::providers::{self, MovieCandidate, MovieDetails};
+
+impl Enricher {
+    /// Search TMDb in the title's own language, when it was detected surely
+    /// enough, for a title the plain search missed (`让子弹飞`, a traditional
+    /// spelling of a simplified original title, `Брат`). Results carry their
+    /// localized titles, which are scored against the file's; when none is
+    /// close enough, the alternative titles of the leading results are
+    /// scored too. A match is named by its English TMDb title. Returns the
+    /// match, its score, and whether the file named it exactly.
+    pub(super) fn lookup_localized(
+        &self,
+        parsed: &ParsedMedia,
+        min_score: f64,
+        enriched: &mut EnrichedMedia,
+    ) -> Option<(MovieCandidate, f64, bool)> {
+        let client = self.tmdb.as_ref().filter(|_| !enriched.lookup_failed)?;
+        let language = parsed
+            .title_language
+            .as_ref()
+            .filter(|l| l.confidence >= language::TRUSTED_CONFIDENCE)?;
+        let tag = search_language(&language.code)?;
+        let results = match client.search_movie_localized(&parsed.title, parsed.year, tag) {
+            Ok(results) => results,
+            Err(e) => {
+                warn!("{e:#}; continuing with parsed metadata");
+                enriched.lookup_failed = http::is_transient(&e);
+                return None;
+            }
+        };
+        let released_by = Some(release_cutoff(parsed));
+        let region = &self.config.tmdb.region;
+        let best = providers::best_candidate(
+            &parsed.title,
+            parsed.year,
+            released_by,
+            &results.candidates,
+            min_score,
+        );
+        if let Some((candidate, score)) = best {
+            let exact = is_exact_match(parsed, &candidate);
+            let details = candidate
+                .id
+                .and_then(|id| client.movie_details(id, region).ok());
+            return Some((in_english(candidate, details), score, exact));
+        }
+        for candidate in results
+            .candidates
+            .into_iter()
+            .take(ALTERNATIVE_TITLE_CHECKS)
+        {
+            let Some(id) = candidate.id else {
+                continue;
+            };
+            let details = match client.movie_details(id, region) {
+                Ok(details) => details,
+                Err(e) => {
+                    warn!("{e:#}; skipping its alternative titles");
+                    continue;
+                }
+            };
+            let score = details
+                .alternative_titles
+                .iter()
+                .map(|title| {
+                    let alternative = MovieCandidate {
+                        title: title.clone(),
+                        original_title: None,
+                        ..candidate.clone()
+                    };
+                    providers::score_candidate(
+                        &parsed.title,
+                        parsed.year,
+                        released_by,
+                        &alternative,
+                    )
+                })
+                .fold(0.0, f64::max);
+            if score >= min_score {
+                return Some((in_english(candidate, Some(details)), score, false));
+            }
+        }
+        None
+    }
+}
+
+/// Localized results whose alternative titles are checked.
+const ALTERNATIVE_TITLE_CHECKS: usize = 3;
+
+/// The TMDb `language` to search a title detected as `code` in: Chinese,
+/// Japanese, and Korean with the region their titles are written for,
+/// others by code alone. English is what the plain search returns.
+fn search_language(code: &str) -> Option<&str> {
+    match code {
+        "en" => None,
+        "zh" => Some("zh-CN"),
+        "ja" => Some("ja-JP"),
+        "ko" => Some("ko-KR"),
+        code => Some(code),
+    }
+}
+
+/// A localized search result named by its English TMDb title, from its
+/// `details` when they could be fetched.
+fn in_english(candidate: MovieCandidate, details: Option<MovieDetails>) -> MovieCandidate {
+    let Some(details) = details else {
+        return candidate;
+    };
+    MovieCandidate {
+        original_title: details.original_title.or(candidate.original_title),
+        title: details.title.unwrap_or(candidate.title),
+        ..candidate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_language() {
+        let tag = |title: &str| {
+            language::detect_title_language(title)
+                .filter(|l| l.confidence >= language::TRUSTED_CONFIDENCE)
+                .and_then(|l| search_language(&l.code).map(str::to_string))
+        };
+        assert_eq!(tag("기생충").as_deref(), Some("ko-KR"));
+        assert_eq!(tag("Брат").as_deref(), Some("ru"));
+        assert_eq!(tag("The Lord of the Rings"), None);
+        assert_eq!(tag("Heat"), None);
+    }
+}
@@ -0,0 +1,449 @@
+//! Enrichment orchestrator — validates parsed metadata against databases.
+//!
+//! Movies are confirmed against TMDb when an API key is configured, and
+//! against OMDb and the offline IMDb dataset when TMDb has no match (see
+//! `providers.order`); all other media (and movies no provider confirms)
+//! are promoted from parsed data unchanged. MusicBrainz and web search are
+//! future phases. Anime is identified on AniList first when enabled (see
+//! `anime`). How a movie is looked up is in `movie`, and the search in a
+//! title's own language in `localized`.
+
+mod anime;
+mod localized;
+mod movie;
+
+use movie::apply_candidate;
+
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use tracing::debug;
+
+use crate::config::{AppConfig, ProviderKind};
+use crate::models::{EnrichedMedia, MediaType, MusicTrack, ParsedMedia, TvEpisode};
+use crate::parser::{self, ParseOptions};
+use crate::profile::{self, Profile};
+use crate::providers::anilist::AniListClient;
+use crate::providers::imdb::OfflineDatabase;
+use crate::providers::omdb::OmdbClient;
+use crate::providers::tmdb::TmdbClient;
+use crate::providers::{MetadataProvider, MovieCandidate};
+
+/// Enrichment pipeline.
+pub struct Enricher {
+    config: AppConfig,
+    /// `[parsing]` settings, for titles changed here and match confidence.
+    parse: ParseOptions,
+    tmdb: Option<TmdbClient>,
+    anilist: Option<AniListClient>,
+    /// Movie search providers with a key, in `providers.order`.
+    movie_providers: Vec<Box<dyn MetadataProvider>>,
+}
+
+impl Enricher {
+    pub fn new(config: AppConfig) -> Self {
+        let tmdb =
+            (!config.tmdb.api_key.is_empty()).then(|| TmdbClient::from_settings(&config.tmdb));
+        let anilist = config
+            .anilist
+            .enabled
+            .then(|| AniListClient::new(config.anilist.requests_per_minute));
+        let mut movie_providers: Vec<Box<dyn MetadataProvider>> = Vec::new();
+        for kind in &config.providers.order {
+            match kind {
+                ProviderKind::Tmdb => {
+                    if let Some(client) = &tmdb {
+                        movie_providers.push(Box::new(client.clone()));
+                    }
+                }
+                ProviderKind::Omdb if !config.omdb.api_key.is_empty() => {
+                    movie_providers.push(Box::new(OmdbClient::from_settings(&config.omdb)));
+                }
+                ProviderKind::Omdb => {}
+                ProviderKind::Imdb => {
+                    let path = Path::new(&config.providers.offline_db);
+                    if !config.providers.offline_db.is_empty() && path.exists() {
+                        movie_providers.push(Box::new(OfflineDatabase::new(path)));
+                    }
+                }
+            }
+        }
+        Self {
+            parse: ParseOptions::from_config(&config),
+            config,
+            tmdb,
+            anilist,
+            movie_providers,
+        }
+    }
+
+    /// How files are parsed for this enricher's config.
+    pub fn parse_options(&self) -> &ParseOptions {
+        &self.parse
+    }
+
+    /// Enrich parsed metadata.
+    ///
+    /// Movies are looked up with the configured providers (TMDb, then
+    /// OMDb, by default) when any has a key; everything
+    /// else is wrapped into enriched models as parsed. Home videos are
+    /// never looked up.
+    /// The file's profile (see [`profile`]) decides the review and match
+    /// thresholds and which release terms are stripped from the title.
+    pub fn enrich(&self, mut parsed: ParsedMedia) -> EnrichedMedia {
+        if parsed.home_video {
+            return home_video(parsed);
+        }
+        let rules = profile::rules_for(&self.config, &parsed);
+        if let Some(title) = rules.strip_title(&parsed.title) {
+            debug!(
+                "{} profile: title {:?} → {title:?}",
+                rules.profile, parsed.title
+            );
+            parser::set_title(&mut parsed, title, &self.parse);
+        }
+        let anime_score = self.identify_anime(&mut parsed, &rules);
+        let mut enriched = EnrichedMedia::from_parsed(parsed.clone());
+
+        match parsed.media_type {
+            MediaType::Movie => {
+                self.enrich_movie(&parsed, rules.min_match_score, &mut enriched);
+                self.lookup_collection(&mut enriched);
+            }
+            MediaType::Tv => self.enrich_tv(&parsed, &mut enriched),
+            MediaType::Music => self.enrich_music(&parsed, &mut enriched),
+            MediaType::Unknown => {
+                debug!(
+                    "unknown type for {:?}, skipping enrichment",
+                    parsed.raw_filename
+                );
+            }
+        }
+
+        if let Some(score) = anime_score {
+            enriched.confidence = enriched.confidence.max(score);
+            if enriched.enrichment_source.as_deref() == Some("parser") {
+                enriched.enrichment_source = Some("anilist".to_string());
+            }
+        }
+
+        // Mark for review if confidence is too low
+        if enriched.confidence < rules.review_threshold
+            && enriched.exact_match
+            && self.config.tmdb.exact_match_override
+        {
+            enriched.review_exempt = true;
+            debug!(
+                "keeping {:?} below the review threshold as an exact TMDb match",
+                parsed.raw_filename
+            );
+        } else if enriched.confidence < rules.review_threshold {
+            enriched.needs_review = true;
+            debug!(
+                "marking {:?} for review (conf={:.0} < {} threshold={:.0})",
+                parsed.raw_filename, enriched.confidence, rules.profile, rules.review_threshold
+            );
+        }
+
+        enriched
+    }
+
+    /// Make the first TMDb search of each distinct movie title in `parsed`
+    /// ahead of [`enrich`](Self::enrich), `tmdb.concurrent_lookups` at a
+    /// time, so matching finds the pages already fetched. Files carrying an
+    /// id, home videos, and anime going to AniList are left out; failures
+    /// are left for matching to report.
+    pub fn prefetch(&self, parsed: &[ParsedMedia]) {
+        let workers = self.config.tmdb.concurrent_lookups;
+        // Searching TMDb ahead only pays when it is searched first.
+        let tmdb_first = self.movie_providers.first().map(|p| p.name()) == Some("tmdb");
+        let Some(client) = self.tmdb.as_ref().filter(|_| workers > 1 && tmdb_first) else {
+            return;
+        };
+        let mut queries: Vec<(String, Option<i32>)> = parsed
+            .iter()
+            .filter(|p| p.media_type == MediaType::Movie && !p.home_video)
+            .filter(|p| p.ids.tmdb.is_none() && p.ids.imdb.is_none())
+            .filter_map(|p| {
+                let rules = profile::rules_for(&self.config, p);
+                if self.anilist.is_some() && (p.anime.is_some() || rules.profile == Profile::Anime)
+                {
+                    return None;
+                }
+                let title = rules
+                    .strip_title(&p.title)
+                    .unwrap_or_else(|| p.title.clone());
+                (!title.is_empty()).then_some((title, p.year))
+            })
+            .collect();
+        queries.sort();
+        queries.dedup();
+        if queries.is_empty() {
+            return;
+        }
+        debug!(
+            "searching TMDb for {} distinct titles, {} at a time",
+            queries.len(),
+            workers.min(queries.len())
+        );
+        let next = AtomicUsize::new(0);
+        thread::scope(|scope| {
+            for _ in 0..workers.min(queries.len()) {
+                scope.spawn(|| {
+                    while let Some((title, year)) =
+                        queries.get(next.fetch_add(1, Ordering::Relaxed))
+                    {
+                        if let Err(e) = client.search_movie_page(title, *year, 1) {
+                            debug!("early TMDb search for {title:?} failed: {e:#}");
+                        }
+                    }
+                });
+            }
+        });
+    }
+
+    /// Enrich with an identity confirmed by a person (e.g., from the review
+    /// queue): no lookups, full confidence.
+    ///
+    /// With a `candidate` the file becomes that movie; otherwise the parsed
+    /// metadata (possibly edited) is taken as correct.
+    pub fn confirm(&self, parsed: ParsedMedia, candidate: Option<MovieCandidate>) -> EnrichedMedia {
+        let mut enriched = EnrichedMedia::from_parsed(parsed.clone());
+        match (candidate, parsed.media_type) {
+            (Some(candidate), _) => {
+                enriched.media_type = MediaType::Movie;
+                apply_candidate(&parsed, candidate, 100.0, &mut enriched);
+                self.lookup_collection(&mut enriched);
+            }
+            (None, MediaType::Tv) => self.enrich_tv(&parsed, &mut enriched),
+            (None, MediaType::Music) => self.enrich_music(&parsed, &mut enriched),
+            (None, _) => {
+                enriched.media_type = MediaType::Movie;
+                self.promote_movie(&parsed, &mut enriched);
+                enriched.enrichment_source = Some("review".to_string());
+            }
+        }
+        enriched.confidence = 100.0;
+        enriched
+    }
+
+    /// Second pass over files whose TMDb lookup failed transiently: wait
+    /// `tmdb.retry_backoff_secs`, doubling after each round up to
+    /// [`MAX_RETRY_BACKOFF`], and enrich them again, for up to
+    /// `tmdb.retry_attempts` rounds. Returns how many
+    /// recovered; the rest keep `lookup_failed`.
+    pub fn retry_failed_lookups<'a>(
+        &self,
+        items: impl IntoIterator<Item = &'a mut EnrichedMedia>,
+    ) -> usize {
+        let mut pending: Vec<_> = items.into_iter().filter(|e| e.lookup_failed).collect();
+        let total = pending.len();
+        let mut delay =
+            Duration::from_secs(self.config.tmdb.retry_backoff_secs).min(MAX_RETRY_BACKOFF);
+        for round in 1..=self.config.tmdb.retry_attempts {
+            if pending.is_empty() {
+                break;
+            }
+            debug!(
+                "retry round {round}: {} lookups after {delay:?}",
+                pending.len()
+            );
+            thread::sleep(delay);
+            delay = next_backoff(delay);
+            pending.retain_mut(|item| {
+                **item = self.enrich(item.parsed.clone());
+                item.lookup_failed
+            });
+        }
+        total - pending.len()
+    }
+
+    fn enrich_tv(&self, parsed: &ParsedMedia, enriched: &mut EnrichedMedia) {
+        enriched.tv_episode = Some(TvEpisode {
+            show_title: parsed.title.clone(),
+            season: parsed.season.unwrap_or(1),
+            episode: parsed.episode.unwrap_or(0),
+            episode_end: parsed.episode_end,
+            episode_title: parsed.episode_title.clone(),
+            year: parsed.year,
+            tmdb_id: parsed.ids.tmdb,
+            confidence: parsed.confidence,
+        });
+        enriched.enrichment_source = Some("parser".to_string());
+    }
+
+    fn enrich_music(&self, parsed: &ParsedMedia, enriched: &mut EnrichedMedia) {
+        enriched.music_track = Some(MusicTrack {
+            artist: parsed.artist.clone().unwrap_or_default(),
+            album: parsed.album.clone(),
+            track_title: parsed.track_title.clone(),
+            track_number: parsed.track_number,
+            year: parsed.year,
+            confidence: parsed.confidence,
+        });
+        enriched.enrichment_source = Some("parser".to_string());
+    }
+}
+
+/// A phone or camera recording: filed by date, nothing to confirm.
+fn home_video(parsed: ParsedMedia) -> EnrichedMedia {
+    let mut enriched = EnrichedMedia::from_parsed(parsed);
+    enriched.media_type = MediaType::Unknown;
+    enriched.confidence = 100.0;
+    enriched.enrichment_source = Some("home video".to_string());
+    enriched
+}
+
+/// Longest wait between retry rounds, however many there are.
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(300);
+
+/// The wait before the retry round after one that waited `delay`.
+fn next_backoff(delay: Duration) -> Duration {
+    delay.saturating_mul(2).min(MAX_RETRY_BACKOFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_enricher() -> Enricher {
+        Enricher::new(AppConfig::default())
+    }
+
+    #[test]
+    fn test_enrich_movie_pass_through() {
+        let parsed = ParsedMedia {
+            title: "The Matrix".to_string(),
+            year: Some(1999),
+            media_type: MediaType::Movie,
+            confidence: 80.0,
+            ..Default::default()
+        };
+        let enriched = default_enricher().enrich(parsed);
+        assert!(enriched.movie.is_some());
+        assert_eq!(enriched.movie.unwrap().title, "The Matrix");
+        assert_eq!(enriched.media_type, MediaType::Movie);
+    }
+
+    #[test]
+    fn test_enrich_movie_from_id_tag() {
+        let parsed =
+            crate::parser::parse_video("Heat (1995) {tmdb-949}.mkv", &ParseOptions::default());
+        let enriched = default_enricher().enrich(parsed);
+        assert_eq!(enriched.tmdb_id(), Some(949));
+        assert_eq!(enriched.confidence, 100.0);
+        assert_eq!(enriched.enrichment_source.as_deref(), Some("id tag"));
+    }
+
+    #[test]
+    fn test_enrich_tv_pass_through() {
+        let parsed = ParsedMedia {
+            title: "Breaking Bad".to_string(),
+            season: Some(1),
+            episode: Some(1),
+            media_type: MediaType::Tv,
+            confidence: 80.0,
+            ..Default::default()
+        };
+        let enriched = default_enricher().enrich(parsed);
+        assert!(enriched.tv_episode.is_some());
+        let ep = enriched.tv_episode.unwrap();
+        assert_eq!(ep.show_title, "Breaking Bad");
+        assert_eq!(ep.season, 1);
+        assert_eq!(ep.episode, 1);
+    }
+
+    #[test]
+    fn test_confirm_with_candidate() {
+        let parsed = ParsedMedia {
+            title: "Something".to_string(),
+            media_type: MediaType::Unknown,
+            confidence: 30.0,
+            ..Default::default()
+        };
+        let candidate = MovieCandidate {
+            id: Some(42),
+            title: "Something Else".to_string(),
+            original_title: None,
+            year: Some(2001),
+            release_date: None,
+            imdb_id: None,
+        };
+        let enriched = default_enricher().confirm(parsed, Some(candidate));
+        let movie = enriched.movie.unwrap();
+        assert_eq!(movie.title, "Something Else");
+        assert_eq!(movie.tmdb_id, Some(42));
+        assert_eq!(enriched.media_type, MediaType::Movie);
+        assert!(!enriched.needs_review);
+    }
+
+    #[test]
+    fn test_matched_year_fills_a_yearless_name() {
+        let parsed =
+            crate::parser::parse_video("Les.Miserables.1080p.BluRay.mkv", &ParseOptions::default());
+        assert_eq!(parsed.year, None);
+        let candidate = MovieCandidate {
+            id: Some(82695),
+            title: "Les Misérables".to_string(),
+            original_title: None,
+            year: Some(2012),
+            release_date: None,
+            imdb_id: None,
+        };
+        let enriched = default_enricher().confirm(parsed, Some(candidate));
+        assert_eq!(enriched.parsed.year, Some(2012));
+        assert!(enriched.parsed.year_from_match);
+
+        let dated =
+            crate::parser::parse_video("Les.Miserables.1998.1080p.mkv", &ParseOptions::default());
+        let candidate = MovieCandidate {
+            id: Some(4415),
+            title: "Les Misérables".to_string(),
+            original_title: None,
+            year: Some(1998),
+            release_date: None,
+            imdb_id: None,
+        };
+        let enriched = default_enricher().confirm(dated, Some(candidate));
+        assert!(!enriched.parsed.year_from_match);
+    }
+
+    #[test]
+    fn test_retry_failed_lookups() {
+        let mut config = AppConfig::default();
+        config.tmdb.retry_backoff_secs = 0;
+        let enricher = Enricher::new(config);
+        let parsed = crate::parser::parse_video("Heat.1995.1080p.mkv", &ParseOptions::default());
+        let mut failed = enricher.enrich(parsed.clone());
+        failed.lookup_failed = true;
+        let mut fine = enricher.enrich(parsed);
+        // Without a key the second attempt cannot fail again.
+        assert_eq!(enricher.retry_failed_lookups([&mut failed, &mut fine]), 1);
+        assert!(!failed.lookup_failed);
+        assert_eq!(failed.movie.unwrap().title, "Heat");
+    }
+
+    #[test]
+    fn test_retry_backoff_is_capped() {
+        assert_eq!(
+            next_backoff(Duration::from_secs(5)),
+            Duration::from_secs(10)
+        );
+        assert_eq!(next_backoff(MAX_RETRY_BACKOFF), MAX_RETRY_BACKOFF);
+        assert_eq!(next_backoff(Duration::MAX), MAX_RETRY_BACKOFF);
+    }
+
+    #[test]
+    fn test_low_confidence_flagged_for_review() {
+        let parsed = ParsedMedia {
+            title: "Something".to_string(),
+            media_type: MediaType::Movie,
+            confidence: 30.0,
+            ..Default::default()
+        };
+        let enriched = default_enricher().enrich(parsed);
+        assert!(enriched.needs_review);
+    }
+}
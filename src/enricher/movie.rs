@@ -0,0 +1,437 @@
+//! Movie identification: an id tag, then each provider in
+//! `providers.order` with fallback queries, then a search in the title's
+//! own language (see `localized`) and as a double feature, before the
+//! parsed title is kept as is.
+
+use chrono::{Local, NaiveDate};
+use tracing::{debug, warn};
+
+use super::Enricher;
+use crate::double_feature;
+use crate::http;
+use crate::models::{EnrichedMedia, Movie, ParsedMedia};
+use crate::providers::{self, MetadataProvider, MovieCandidate};
+use crate::title_strategy;
+use crate::utils::normalize_title;
+
+impl Enricher {
+    pub(super) fn enrich_movie(
+        &self,
+        parsed: &ParsedMedia,
+        min_score: f64,
+        enriched: &mut EnrichedMedia,
+    ) {
+        if let Some(candidate) = self.tagged_movie(parsed) {
+            debug!(
+                "{:?} carries TMDb id {}; no search needed",
+                parsed.raw_filename,
+                candidate.id.unwrap_or_default()
+            );
+            apply_candidate(parsed, candidate, 100.0, enriched);
+            enriched.enrichment_source = Some("id tag".to_string());
+            return;
+        }
+        if let Some((candidate, score, source)) = self.lookup_movie(parsed, min_score, enriched) {
+            debug!(
+                "{source} confirmed {:?} as {:?} ({:?}), score {:.0}",
+                parsed.raw_filename, candidate.title, candidate.year, score
+            );
+            let confidence = self
+                .parse
+                .scorer
+                .match_confidence(enriched.confidence, score);
+            enriched.exact_match = is_exact_match(parsed, &candidate);
+            apply_candidate(parsed, candidate, confidence, enriched);
+            enriched.enrichment_source = Some(source.to_string());
+            return;
+        }
+        if let Some((candidate, score, exact)) = self.lookup_localized(parsed, min_score, enriched)
+        {
+            debug!(
+                "TMDb matched {:?} in its own language as {:?} ({:?}), score {:.0}",
+                parsed.raw_filename, candidate.title, candidate.year, score
+            );
+            let confidence = self
+                .parse
+                .scorer
+                .match_confidence(enriched.confidence, score);
+            enriched.exact_match = exact;
+            apply_candidate(parsed, candidate, confidence, enriched);
+            return;
+        }
+        if let Some(found) = self.lookup_double_feature(parsed, min_score, enriched) {
+            debug!(
+                "TMDb matched {:?} as a double feature: {:?} and {:?}, score {:.0}",
+                parsed.raw_filename, found.first.title, found.second.title, found.score
+            );
+            let confidence = self
+                .parse
+                .scorer
+                .match_confidence(enriched.confidence, found.score);
+            apply_candidate(parsed, found.first, confidence, enriched);
+            enriched.second_feature = Some(Movie {
+                title: found.second.title,
+                year: found.second.year,
+                tmdb_id: found.second.id,
+                original_title: found.second.original_title,
+                confidence,
+            });
+            enriched.candidates.clear();
+            return;
+        }
+        self.promote_movie(parsed, enriched);
+    }
+
+    /// With `organize.collection_folders`, note the TMDb collection of a
+    /// matched movie. A failed lookup files the movie as a lone one.
+    pub(super) fn lookup_collection(&self, enriched: &mut EnrichedMedia) {
+        let (Some(tmdb), true) = (&self.tmdb, self.config.organize.collection_folders) else {
+            return;
+        };
+        let Some(id) = enriched.movie.as_ref().and_then(|m| m.tmdb_id) else {
+            return;
+        };
+        match tmdb.movie_details(id, &self.config.tmdb.region) {
+            Ok(details) => enriched.collection = details.collection,
+            Err(e) => warn!("No collection for TMDb movie {id}: {e:#}"),
+        }
+    }
+
+    /// Search for the title as two movies (see [`double_feature`]) once the
+    /// whole title found nothing.
+    fn lookup_double_feature(
+        &self,
+        parsed: &ParsedMedia,
+        min_score: f64,
+        enriched: &mut EnrichedMedia,
+    ) -> Option<double_feature::Match> {
+        let client = self.tmdb.as_ref().filter(|_| !enriched.lookup_failed)?;
+        let released_by = Some(release_cutoff(parsed));
+        match double_feature::lookup(client, &parsed.title, parsed.year, released_by, min_score) {
+            Ok(found) => found,
+            Err(e) => {
+                warn!("{e:#}; continuing with parsed metadata");
+                enriched.lookup_failed = http::is_transient(&e);
+                None
+            }
+        }
+    }
+
+    /// The movie named by an id tag from another organizer or a metadata
+    /// file beside the video (see `nfo`): a TMDb id is taken as is, with
+    /// the parsed title; an IMDb id is resolved through TMDb when a client
+    /// is configured.
+    fn tagged_movie(&self, parsed: &ParsedMedia) -> Option<MovieCandidate> {
+        if let Some(id) = parsed.ids.tmdb {
+            return Some(MovieCandidate {
+                id: Some(id),
+                title: parsed.title.clone(),
+                original_title: None,
+                year: parsed.year,
+                release_date: None,
+                imdb_id: None,
+            });
+        }
+        let (client, imdb) = (self.tmdb.as_ref()?, parsed.ids.imdb.as_ref()?);
+        match client.find_imdb(imdb) {
+            Ok(found) => found,
+            Err(e) => {
+                warn!("{e:#}; searching by title instead");
+                None
+            }
+        }
+    }
+
+    pub(super) fn promote_movie(&self, parsed: &ParsedMedia, enriched: &mut EnrichedMedia) {
+        enriched.movie = Some(Movie {
+            title: parsed.title.clone(),
+            year: parsed.year,
+            tmdb_id: None,
+            original_title: None,
+            confidence: parsed.confidence,
+        });
+        enriched.enrichment_source = Some("parser".to_string());
+    }
+
+    /// Search each movie provider in turn until one matches (see
+    /// [`search_provider`](Self::search_provider)). Results that fall short
+    /// are collected into `candidates`, best first; `lookup_failed` is set
+    /// when nothing matched and a provider failed transiently. Returns the
+    /// match and the provider's name.
+    fn lookup_movie(
+        &self,
+        parsed: &ParsedMedia,
+        min_score: f64,
+        enriched: &mut EnrichedMedia,
+    ) -> Option<(MovieCandidate, f64, &'static str)> {
+        let mut failed = false;
+        for provider in &self.movie_providers {
+            match self.search_provider(provider.as_ref(), parsed, min_score, enriched) {
+                Ok(Some((candidate, score))) => {
+                    enriched.candidates.clear();
+                    return Some((candidate, score, provider.name()));
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    warn!("{e:#}; continuing with parsed metadata");
+                    failed |= http::is_transient(&e);
+                }
+            }
+        }
+        enriched.lookup_failed = failed;
+        None
+    }
+
+    /// Search one provider with the parsed title, then with the raw title
+    /// region when hunch may have stripped title words (e.g., `HDR.2020`),
+    /// then with shorter chunks of a long title.
+    ///
+    /// Chunk results are scored against the full parsed title, so they only
+    /// help when the full title matches; the threshold is unchanged. When
+    /// nothing on the first page of the parsed-title search reaches the
+    /// threshold, later pages are fetched too, up to `tmdb.max_search_pages`:
+    /// generic titles ("Home", "Mother") can bury the right film past the
+    /// first 20 results.
+    fn search_provider(
+        &self,
+        provider: &dyn MetadataProvider,
+        parsed: &ParsedMedia,
+        min_score: f64,
+        enriched: &mut EnrichedMedia,
+    ) -> crate::Result<Option<(MovieCandidate, f64)>> {
+        let near_misses = &mut enriched.candidates;
+        // (query, title the results are scored against)
+        let mut queries: Vec<(String, String)> = Vec::new();
+        if !parsed.title.is_empty() {
+            queries.push((parsed.title.clone(), parsed.title.clone()));
+        }
+        // A kana or Hangul title as the providers that know only its
+        // romanization spell it (`Gisaengchung`).
+        if let Some(romanized) = &parsed.titles.romanized {
+            queries.push((romanized.clone(), romanized.clone()));
+        }
+        if let Some(region) = title_strategy::title_region(&parsed.raw_filename, parsed.year) {
+            let region = region.join(" ");
+            queries.push((region.clone(), region));
+        }
+        for chunk in providers::partial_queries(&parsed.title) {
+            queries.push((chunk, parsed.title.clone()));
+        }
+        let mut seen = Vec::new();
+        queries.retain(|(query, _)| {
+            let new = !seen.contains(query);
+            seen.push(query.clone());
+            new
+        });
+        let released_by = Some(release_cutoff(parsed));
+
+        let max_pages = self.config.tmdb.max_search_pages.max(1);
+
+        for (index, (query, full_title)) in queries.into_iter().enumerate() {
+            // Only the parsed-title search is paged; fallbacks take page 1.
+            let last_page = if index == 0 { max_pages } else { 1 };
+            let mut page = 1;
+            loop {
+                let results = provider.search_movie_page(&query, parsed.year, page)?;
+                let best = providers::best_candidate(
+                    &full_title,
+                    parsed.year,
+                    released_by,
+                    &results.candidates,
+                    min_score,
+                );
+                if best.is_some() {
+                    return Ok(best);
+                }
+                collect_near_misses(
+                    &full_title,
+                    parsed.year,
+                    released_by,
+                    results.candidates,
+                    near_misses,
+                );
+                if page >= last_page.min(results.total_pages) {
+                    break;
+                }
+                page += 1;
+                debug!(
+                    "No {} match for {query:?} yet; fetching result page {page}",
+                    provider.name()
+                );
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Record `candidate` as the movie identity.
+///
+/// A name without a year takes the movie's: the year is settled by the
+/// match rather than guessed from title words, and everything reading the
+/// parsed metadata afterwards (review, learned tokens) sees it.
+pub(super) fn apply_candidate(
+    parsed: &ParsedMedia,
+    candidate: MovieCandidate,
+    confidence: f64,
+    enriched: &mut EnrichedMedia,
+) {
+    if parsed.year.is_none() && candidate.year.is_some() {
+        enriched.parsed.year = candidate.year;
+        enriched.parsed.year_from_match = true;
+    }
+    if enriched.parsed.ids.imdb.is_none() {
+        enriched.parsed.ids.imdb = candidate.imdb_id;
+    }
+    enriched.confidence = confidence;
+    enriched.movie = Some(Movie {
+        title: candidate.title,
+        year: candidate.year.or(parsed.year),
+        tmdb_id: candidate.id,
+        original_title: candidate.original_title,
+        confidence,
+    });
+    enriched.enrichment_source = Some("tmdb".to_string());
+}
+
+/// Whether the parsed title and year are exactly the candidate's, ignoring
+/// case and punctuation.
+pub(super) fn is_exact_match(parsed: &ParsedMedia, candidate: &MovieCandidate) -> bool {
+    let title = normalize_title(&parsed.title);
+    parsed.year.is_some()
+        && parsed.year == candidate.year
+        && !title.is_empty()
+        && std::iter::once(&candidate.title)
+            .chain(&candidate.original_title)
+            .any(|t| normalize_title(t) == title)
+}
+
+/// How many near-miss candidates to keep for review.
+const MAX_NEAR_MISSES: usize = 5;
+
+/// The latest release date the file's content can have: today, or the
+/// day the file was written if earlier.
+pub(super) fn release_cutoff(parsed: &ParsedMedia) -> NaiveDate {
+    let today = Local::now().date_naive();
+    parsed.file_date.map_or(today, |d| d.min(today))
+}
+
+fn collect_near_misses(
+    query: &str,
+    year: Option<i32>,
+    released_by: Option<NaiveDate>,
+    candidates: Vec<MovieCandidate>,
+    near_misses: &mut Vec<MovieCandidate>,
+) {
+    for candidate in candidates {
+        // OMDb and IMDb matches have no TMDb id; tell them apart by IMDb id.
+        if !near_misses
+            .iter()
+            .any(|c| (c.id, &c.imdb_id) == (candidate.id, &candidate.imdb_id))
+        {
+            near_misses.push(candidate);
+        }
+    }
+    near_misses.sort_by(|a, b| {
+        let score = |c| providers::score_candidate(query, year, released_by, c);
+        score(b).total_cmp(&score(a))
+    });
+    near_misses.truncate(MAX_NEAR_MISSES);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::parser::ParseOptions;
+
+    #[test]
+    fn test_exact_match() {
+        let parsed = crate::parser::parse_video("heat 1995.mkv", &ParseOptions::default());
+        let candidate = |title: &str, original: Option<&str>, year| MovieCandidate {
+            id: Some(949),
+            title: title.to_string(),
+            original_title: original.map(String::from),
+            year: Some(year),
+            release_date: None,
+            imdb_id: None,
+        };
+        assert!(is_exact_match(&parsed, &candidate("Heat", None, 1995)));
+        assert!(is_exact_match(
+            &parsed,
+            &candidate("Chaleur", Some("Heat"), 1995)
+        ));
+        assert!(!is_exact_match(&parsed, &candidate("Heat", None, 1986)));
+        assert!(!is_exact_match(
+            &parsed,
+            &candidate("Heat Wave", None, 1995)
+        ));
+        let yearless = crate::parser::parse_video("heat.mkv", &ParseOptions::default());
+        assert!(!is_exact_match(&yearless, &candidate("Heat", None, 1995)));
+    }
+
+    /// A provider answering every search with the same movies.
+    struct Fixed(&'static str, Vec<MovieCandidate>);
+
+    impl MetadataProvider for Fixed {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn search_movie_page(
+            &self,
+            _query: &str,
+            _year: Option<i32>,
+            _page: u32,
+        ) -> crate::Result<providers::SearchPage> {
+            Ok(providers::SearchPage {
+                candidates: self.1.clone(),
+                total_pages: 1,
+            })
+        }
+    }
+
+    #[test]
+    fn test_next_provider_after_no_match() {
+        let heat = MovieCandidate {
+            id: None,
+            title: "Heat".to_string(),
+            original_title: None,
+            year: Some(1995),
+            release_date: None,
+            imdb_id: Some("tt0113277".to_string()),
+        };
+        let enricher = Enricher {
+            movie_providers: vec![
+                Box::new(Fixed("tmdb", Vec::new())),
+                Box::new(Fixed("omdb", vec![heat])),
+            ],
+            ..Enricher::new(AppConfig::default())
+        };
+        let enriched = enricher.enrich(crate::parser::parse_video(
+            "Heat.1995.1080p.mkv",
+            &ParseOptions::default(),
+        ));
+        assert_eq!(enriched.enrichment_source.as_deref(), Some("omdb"));
+        assert_eq!(enriched.tmdb_id(), None);
+        assert_eq!(enriched.parsed.ids.imdb.as_deref(), Some("tt0113277"));
+        assert!(enriched.confidence > 90.0);
+    }
+
+    #[test]
+    fn test_near_misses_without_tmdb_ids_stay_apart() {
+        let omdb = |title: &str, imdb: &str| MovieCandidate {
+            id: None,
+            title: title.to_string(),
+            original_title: None,
+            year: Some(1995),
+            release_date: None,
+            imdb_id: Some(imdb.to_string()),
+        };
+        let mut near_misses = Vec::new();
+        let found = vec![omdb("Heat", "tt0113277"), omdb("Heat", "tt0113277")];
+        collect_near_misses("Heat", Some(1995), None, found, &mut near_misses);
+        let found = vec![omdb("Heat Wave", "tt0100000")];
+        collect_near_misses("Heat", Some(1995), None, found, &mut near_misses);
+        assert_eq!(near_misses.len(), 2);
+    }
+}
@@ -0,0 +1,157 @@
+//! Error type of the library API.
+//!
+//! Inside the crate, failures gather context with `anyhow` on their way up.
+//! The entry points other programs call — loading config, opening the
+//! library database, scanning, TMDb and OMDb requests, and reading and
+//! writing plans — return [`Error`] instead, whose variant names what
+//! failed, so a caller can tell a bad config from an unreachable TMDb
+//! without reading messages. The message names the failure; the context
+//! chain is its [`source`](std::error::Error::source), which `{:#}` prints
+//! after it, as `anyhow` does.
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+use crate::http;
+
+/// What a library call failed at.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// A plan or other saved artifact could not be read as one.
+    Parse(#[source] anyhow::Error),
+    /// A TMDb request failed or its response made no sense. `status` is
+    /// the HTTP status TMDb answered with, when the failure was a non-2xx
+    /// response.
+    Tmdb {
+        status: Option<u16>,
+        #[source]
+        source: anyhow::Error,
+    },
+    /// A request to another metadata provider (`"omdb"`, `"imdb"`) failed.
+    Provider {
+        provider: &'static str,
+        status: Option<u16>,
+        #[source]
+        source: anyhow::Error,
+    },
+    /// The library database at `path` could not be read or written.
+    Database {
+        path: PathBuf,
+        #[source]
+        source: anyhow::Error,
+    },
+    /// A file or directory could not be read or written.
+    Io(#[source] anyhow::Error),
+    /// The configuration could not be loaded or a value was invalid.
+    Config(#[source] anyhow::Error),
+    /// The caller cancelled the operation (see `progress::CancelToken`).
+    Cancelled,
+}
+
+/// Result of a library call.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl Error {
+    /// A failed TMDb call, with the status of the response behind it.
+    pub(crate) fn tmdb(source: anyhow::Error) -> Self {
+        Self::Tmdb {
+            status: status_of(&source),
+            source,
+        }
+    }
+
+    /// A failed call to `provider`, with the status of the response behind
+    /// it.
+    pub(crate) fn provider(provider: &'static str, source: anyhow::Error) -> Self {
+        Self::Provider {
+            provider,
+            status: status_of(&source),
+            source,
+        }
+    }
+
+    /// The underlying error, with its context chain, for downcasting;
+    /// `None` for [`Error::Cancelled`].
+    pub fn inner(&self) -> Option<&anyhow::Error> {
+        match self {
+            Self::Parse(source)
+            | Self::Tmdb { source, .. }
+            | Self::Provider { source, .. }
+            | Self::Database { source, .. }
+            | Self::Io(source)
+            | Self::Config(source) => Some(source),
+            Self::Cancelled => None,
+        }
+    }
+}
+
+fn status_of(source: &anyhow::Error) -> Option<u16> {
+    http::find(source.as_ref()).and_then(|e| e.status)
+}
+
+impl fmt::Display for Error {
+    /// `{}` names what failed; `{:#}` follows it with the context chain.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parse(_) => f.write_str("Parse error")?,
+            Self::Tmdb { .. } => f.write_str("TMDb error")?,
+            Self::Provider { provider, .. } => write!(f, "Provider error ({provider})")?,
+            Self::Database { path, .. } => write!(f, "Database error ({})", path.display())?,
+            Self::Io(_) => f.write_str("I/O error")?,
+            Self::Config(_) => f.write_str("Config error")?,
+            Self::Cancelled => f.write_str("Cancelled")?,
+        }
+        if f.alternate() {
+            let mut next = self.source();
+            while let Some(cause) = next {
+                write!(f, ": {cause}")?;
+                next = cause.source();
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_source_keeps_context_chain() {
+        let cause: anyhow::Result<()> = Err(anyhow::anyhow!("Invalid API key"));
+        let error = Error::tmdb(
+            cause
+                .context("TMDb search for \"Heat\" failed")
+                .unwrap_err(),
+        );
+        assert_eq!(error.to_string(), "TMDb error");
+        assert_eq!(
+            format!("{error:#}"),
+            "TMDb error: TMDb search for \"Heat\" failed: Invalid API key"
+        );
+        assert!(matches!(error, Error::Tmdb { status: None, .. }));
+        assert_eq!(
+            anyhow::Error::from(error).chain().count(),
+            3,
+            "the chain is reported once, through source()"
+        );
+
+        let missing = io::Error::new(io::ErrorKind::NotFound, "gone");
+        let error = Error::from(missing);
+        assert!(error
+            .inner()
+            .is_some_and(|e| e.downcast_ref::<io::Error>().is_some()));
+        assert!(Error::Cancelled.inner().is_none());
+    }
+}
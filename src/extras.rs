@@ -0,0 +1,334 @@
+//! Trailers, featurettes, and other extras of a movie.
+//!
+//! The scanner never organizes extras as movies of their own. With
+//! `organize.extras = "organize"` they move with the movie they sit beside,
+//! into the folders Plex reads extras from: `Heat (1995)/Trailers/`,
+//! `Behind The Scenes/`, `Featurettes/`, and so on. An extra is named as
+//! one (`Heat.1995.Trailer.mkv`, `making_of.mkv`) or kept in an extras
+//! folder (`Featurettes/`, `Extras/`). As with artwork, extras are only
+//! taken from folders holding a single movie; in a shared downloads folder
+//! they belong to nobody.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::scanner::{self, VIDEO_EXTENSIONS};
+
+/// The kinds of extras Plex recognizes, each with its own folder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExtraKind {
+    BehindTheScenes,
+    DeletedScene,
+    Featurette,
+    Interview,
+    Scene,
+    Short,
+    Trailer,
+    Other,
+}
+
+impl ExtraKind {
+    /// The folder Plex reads this kind from, inside the movie's folder.
+    pub fn folder(self) -> &'static str {
+        match self {
+            ExtraKind::BehindTheScenes => "Behind The Scenes",
+            ExtraKind::DeletedScene => "Deleted Scenes",
+            ExtraKind::Featurette => "Featurettes",
+            ExtraKind::Interview => "Interviews",
+            ExtraKind::Scene => "Scenes",
+            ExtraKind::Short => "Shorts",
+            ExtraKind::Trailer => "Trailers",
+            ExtraKind::Other => "Other",
+        }
+    }
+}
+
+impl fmt::Display for ExtraKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.folder())
+    }
+}
+
+/// Words that mark a file stem as an extra, in the order they are tried
+/// (`deleted scenes` before `scenes`).
+const WORDS: &[(&str, ExtraKind)] = &[
+    ("trailer", ExtraKind::Trailer),
+    ("extras", ExtraKind::Other),
+    ("featurette", ExtraKind::Featurette),
+    ("behind the scenes", ExtraKind::BehindTheScenes),
+    ("deleted scenes", ExtraKind::DeletedScene),
+    ("interviews", ExtraKind::Interview),
+    ("scenes", ExtraKind::Scene),
+    ("shorts", ExtraKind::Short),
+    ("tokuten", ExtraKind::Other),
+];
+
+/// Stem prefixes of promos and extras.
+const PREFIXES: &[(&str, ExtraKind)] = &[
+    ("pv", ExtraKind::Trailer),
+    ("interview", ExtraKind::Interview),
+    ("making.of", ExtraKind::BehindTheScenes),
+    ("making_of", ExtraKind::BehindTheScenes),
+    ("behind.the.scenes", ExtraKind::BehindTheScenes),
+];
+
+/// Folder names holding extras of any kind.
+const GENERIC_FOLDERS: &[&str] = &["extras", "bonus"];
+
+/// Whether a lowercased file stem contains `word` as a word of its own:
+/// after a separator, or as the start of a short stem.
+pub(crate) fn has_word(stem_lower: &str, word: &str) -> bool {
+    if !stem_lower.contains(word) {
+        return false;
+    }
+    if stem_lower.starts_with(word) && stem_lower.len() < word.len() + 5 {
+        return true;
+    }
+    [".", "-", "_", " ", "[", "]"]
+        .iter()
+        .any(|sep| stem_lower.contains(&format!("{sep}{word}")))
+}
+
+/// The kind of extra a file stem names, if it names one.
+pub fn classify(stem: &str) -> Option<ExtraKind> {
+    let stem = stem.to_lowercase();
+    for (prefix, kind) in PREFIXES {
+        if let Some(rest) = stem.strip_prefix(prefix) {
+            // `pv1` is a promo video, `pvris` is not.
+            if *prefix != "pv" || rest.is_empty() || rest.starts_with(|c: char| c.is_ascii_digit())
+            {
+                return Some(*kind);
+            }
+        }
+    }
+    WORDS
+        .iter()
+        .find(|(word, _)| has_word(&stem, word))
+        .map(|(_, kind)| *kind)
+}
+
+/// The kind of extras a folder name holds: one of Plex's extras folders,
+/// or `Other` for a generic `Extras/` or `Bonus/`. Plex's own `Other` is
+/// too common a name to count.
+pub fn classify_folder(name: &str) -> Option<ExtraKind> {
+    let name = name.to_lowercase();
+    if GENERIC_FOLDERS.contains(&name.as_str()) {
+        return Some(ExtraKind::Other);
+    }
+    [
+        ExtraKind::BehindTheScenes,
+        ExtraKind::DeletedScene,
+        ExtraKind::Featurette,
+        ExtraKind::Interview,
+        ExtraKind::Scene,
+        ExtraKind::Short,
+        ExtraKind::Trailer,
+    ]
+    .into_iter()
+    .find(|kind| {
+        let folder = kind.folder().to_lowercase();
+        name == folder || folder.strip_suffix('s') == Some(name.as_str())
+    })
+}
+
+/// An extra that belongs with a movie.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Extra {
+    pub path: PathBuf,
+    pub kind: ExtraKind,
+}
+
+impl Extra {
+    /// A video in an extras folder of `folder_kind`: a typed folder
+    /// (`Trailers/`) decides the kind; in `Extras/` the name does, or else
+    /// it is `Other`.
+    fn in_folder(path: PathBuf, folder_kind: ExtraKind) -> Self {
+        let kind = match folder_kind {
+            ExtraKind::Other => classify(stem(&path)).unwrap_or(ExtraKind::Other),
+            kind => kind,
+        };
+        Extra { path, kind }
+    }
+
+    /// Where it goes in the movie folder: `Trailers/<file name>`.
+    pub fn relative_destination(&self) -> PathBuf {
+        let name = self.path.file_name().unwrap_or_default();
+        Path::new(self.kind.folder()).join(name)
+    }
+}
+
+/// Videos in `dir`, sorted.
+fn videos_in(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut videos: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file())
+        .filter(|p| VIDEO_EXTENSIONS.contains(&scanner::dotted_extension(p).as_str()))
+        .collect();
+    videos.sort();
+    videos
+}
+
+fn stem(path: &Path) -> &str {
+    path.file_stem().and_then(|s| s.to_str()).unwrap_or("")
+}
+
+fn is_sample(path: &Path) -> bool {
+    has_word(&stem(path).to_lowercase(), "sample")
+}
+
+/// Whether `videos` hold exactly one feature, the movie extras beside it
+/// belong to. Samples and videos named as extras don't count.
+fn single_feature(videos: &[PathBuf]) -> bool {
+    videos
+        .iter()
+        .filter(|v| !is_sample(v) && classify(stem(v)).is_none())
+        .count()
+        == 1
+}
+
+/// Whether `path` is in an extras folder beside a movie
+/// (`Heat (1995)/Featurettes/Making Of.mkv`), so `find_extras` claims it.
+/// A top-level `Shorts/` of short films, with no video beside it, is not;
+/// nor is an `Extras/` shared by two movies.
+pub fn in_extras_folder(path: &Path) -> bool {
+    let Some(folder) = path.parent() else {
+        return false;
+    };
+    let named = folder
+        .file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| classify_folder(n).is_some());
+    named
+        && folder
+            .parent()
+            .is_some_and(|movie| single_feature(&videos_in(movie)))
+}
+
+/// Find the extras of `video`: videos beside it named as extras, and the
+/// videos of extras folders beside it. A file in a typed folder
+/// (`Trailers/`) is of that kind; one in `Extras/` is classified by its
+/// name, or else `Other`. Samples are never extras.
+pub fn find_extras(video: &Path) -> Vec<Extra> {
+    let Some(parent) = video.parent() else {
+        return Vec::new();
+    };
+    let videos = videos_in(parent);
+    if !single_feature(&videos) {
+        return Vec::new();
+    }
+    let mut extras: Vec<Extra> = videos
+        .into_iter()
+        .filter(|path| !is_sample(path))
+        .filter_map(|path| {
+            let kind = classify(stem(&path))?;
+            Some(Extra { path, kind })
+        })
+        .collect();
+
+    let Ok(entries) = fs::read_dir(parent) else {
+        return extras;
+    };
+    let mut folders: Vec<(PathBuf, ExtraKind)> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .filter_map(|p| {
+            let kind = classify_folder(p.file_name()?.to_str()?)?;
+            Some((p, kind))
+        })
+        .collect();
+    folders.sort();
+    for (folder, folder_kind) in folders {
+        for path in videos_in(&folder) {
+            if is_sample(&path) {
+                continue;
+            }
+            extras.push(Extra::in_folder(path, folder_kind));
+        }
+    }
+    extras
+}
+
+/// A leftover video of a one-movie-per-folder unit (see `scanner`), as an
+/// extra of the unit's movie: of the kind its folder or name says, else
+/// `Other`.
+pub fn unit_extra(path: PathBuf) -> Extra {
+    let folder_kind = path
+        .parent()
+        .and_then(|p| p.file_name())
+        .and_then(|n| n.to_str())
+        .and_then(classify_folder);
+    match folder_kind {
+        Some(kind) => Extra::in_folder(path, kind),
+        None => {
+            let kind = classify(stem(&path)).unwrap_or(ExtraKind::Other);
+            Extra { path, kind }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify("Heat.1995.Trailer"), Some(ExtraKind::Trailer));
+        assert_eq!(classify("pv2"), Some(ExtraKind::Trailer));
+        assert_eq!(classify("pvris"), None);
+        assert_eq!(classify("making_of"), Some(ExtraKind::BehindTheScenes));
+        assert_eq!(
+            classify("Heat - Deleted Scenes"),
+            Some(ExtraKind::DeletedScene)
+        );
+        assert_eq!(classify("Heat.1995.1080p.BluRay"), None);
+        assert_eq!(classify_folder("Featurettes"), Some(ExtraKind::Featurette));
+        assert_eq!(classify_folder("trailer"), Some(ExtraKind::Trailer));
+        assert_eq!(classify_folder("Extras"), Some(ExtraKind::Other));
+        assert_eq!(classify_folder("Other"), None);
+    }
+
+    #[test]
+    fn test_find_extras() {
+        let tmp = tempfile::tempdir().unwrap();
+        let heat = tmp.path().join("Heat.1995.1080p");
+        fs::create_dir_all(heat.join("Featurettes")).unwrap();
+        fs::create_dir_all(heat.join("Extras")).unwrap();
+        for name in [
+            "Heat.1995.1080p.mkv",
+            "Heat.1995.Trailer.mkv",
+            "heat-sample.mkv",
+            "Featurettes/Making Of.mkv",
+            "Extras/Deleted Scenes.mkv",
+            "Extras/Commentary.mkv",
+        ] {
+            fs::write(heat.join(name), b"x").unwrap();
+        }
+        let found: Vec<PathBuf> = find_extras(&heat.join("Heat.1995.1080p.mkv"))
+            .iter()
+            .map(Extra::relative_destination)
+            .collect();
+        assert_eq!(
+            found,
+            [
+                PathBuf::from("Trailers/Heat.1995.Trailer.mkv"),
+                PathBuf::from("Other/Commentary.mkv"),
+                PathBuf::from("Deleted Scenes/Deleted Scenes.mkv"),
+                PathBuf::from("Featurettes/Making Of.mkv"),
+            ]
+        );
+
+        assert!(in_extras_folder(&heat.join("Featurettes/Making Of.mkv")));
+        assert!(!in_extras_folder(&heat.join("Heat.1995.1080p.mkv")));
+
+        // A shared folder: its extras belong to nobody, so they are scanned.
+        fs::write(heat.join("Alien.1979.mkv"), b"x").unwrap();
+        assert!(find_extras(&heat.join("Heat.1995.1080p.mkv")).is_empty());
+        assert!(!in_extras_folder(&heat.join("Featurettes/Making Of.mkv")));
+    }
+}
@@ -0,0 +1,403 @@
+//! Content fingerprints for change and duplicate detection.
+//!
+//! [`quick_hash`] is 64-bit FNV-1a over the file size plus its first and
+//! last 64 KiB. It only needs to notice that a file was replaced or
+//! rewritten between two points in time, cheaply enough to run over a NAS
+//! mount.
+//!
+//! [`content_hash`] identifies a file by what it holds, for finding the
+//! same video under two names and following a file moved outside the
+//! organizer. It is XXH64 over the whole file, or in [`HashMode::Partial`]
+//! over the size and the first and last N MB, which reads a fixed amount
+//! per file however large it is. Neither is cryptographic. Hashes carry
+//! their mode (`xxh64:…`, `xxh64-4m:…`), so a partial hash never equals a
+//! full one by accident, and a stored hash can be recomputed the same way.
+
+use std::fmt;
+use std::fs::{File, Metadata};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::SourceFingerprint;
+
+/// Bytes sampled from each end of the file.
+const QUICK_HASH_CHUNK: u64 = 64 * 1024;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+fn fnv1a(mut state: u64, bytes: &[u8]) -> u64 {
+    for b in bytes {
+        state ^= u64::from(*b);
+        state = state.wrapping_mul(FNV_PRIME);
+    }
+    state
+}
+
+/// Hash the size, head, and tail of a file into a 16-character hex string.
+pub fn quick_hash(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut state = fnv1a(FNV_OFFSET, &size.to_le_bytes());
+
+    let mut buf = vec![0u8; QUICK_HASH_CHUNK as usize];
+    let head = read_up_to(&mut file, &mut buf)?;
+    state = fnv1a(state, &buf[..head]);
+
+    if size > QUICK_HASH_CHUNK * 2 {
+        file.seek(SeekFrom::Start(size - QUICK_HASH_CHUNK))?;
+        let tail = read_up_to(&mut file, &mut buf)?;
+        state = fnv1a(state, &buf[..tail]);
+    } else if size > QUICK_HASH_CHUNK {
+        let rest = read_up_to(&mut file, &mut buf)?;
+        state = fnv1a(state, &buf[..rest]);
+    }
+
+    Ok(format!("{state:016x}"))
+}
+
+fn read_up_to(file: &mut File, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match file.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+/// How much of each file [`content_hash`] reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum HashMode {
+    /// Files are not hashed.
+    #[default]
+    Off,
+    /// The size and the first and last N MB; fast over network drives.
+    Partial,
+    /// Every byte.
+    Full,
+}
+
+impl fmt::Display for HashMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Off => "off",
+            Self::Partial => "partial",
+            Self::Full => "full",
+        })
+    }
+}
+
+impl FromStr for HashMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Self::Off),
+            "partial" => Ok(Self::Partial),
+            "full" => Ok(Self::Full),
+            other => Err(format!(
+                "unknown hash mode {other:?} (expected off, partial, or full)"
+            )),
+        }
+    }
+}
+
+const PRIME_1: u64 = 0x9e37_79b1_85eb_ca87;
+const PRIME_2: u64 = 0xc2b2_ae3d_27d4_eb4f;
+const PRIME_3: u64 = 0x1656_67b1_9e37_79f9;
+const PRIME_4: u64 = 0x85eb_ca77_c2b2_ae63;
+const PRIME_5: u64 = 0x27d4_eb2f_1656_67c5;
+
+/// Streaming XXH64 with seed 0.
+#[derive(Debug, Clone)]
+pub struct Xxh64 {
+    lanes: [u64; 4],
+    buf: [u8; 32],
+    buffered: usize,
+    total: u64,
+}
+
+impl Default for Xxh64 {
+    fn default() -> Self {
+        Self {
+            lanes: [
+                PRIME_1.wrapping_add(PRIME_2),
+                PRIME_2,
+                0,
+                0u64.wrapping_sub(PRIME_1),
+            ],
+            buf: [0; 32],
+            buffered: 0,
+            total: 0,
+        }
+    }
+}
+
+fn round(acc: u64, input: u64) -> u64 {
+    acc.wrapping_add(input.wrapping_mul(PRIME_2))
+        .rotate_left(31)
+        .wrapping_mul(PRIME_1)
+}
+
+fn merge(acc: u64, lane: u64) -> u64 {
+    (acc ^ round(0, lane))
+        .wrapping_mul(PRIME_1)
+        .wrapping_add(PRIME_4)
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes[..8].try_into().unwrap())
+}
+
+impl Xxh64 {
+    pub fn update(&mut self, mut bytes: &[u8]) {
+        self.total += bytes.len() as u64;
+        if self.buffered > 0 {
+            let take = bytes.len().min(32 - self.buffered);
+            self.buf[self.buffered..self.buffered + take].copy_from_slice(&bytes[..take]);
+            self.buffered += take;
+            bytes = &bytes[take..];
+            if self.buffered < 32 {
+                return;
+            }
+            let stripe = self.buf;
+            self.stripe(&stripe);
+            self.buffered = 0;
+        }
+        let mut stripes = bytes.chunks_exact(32);
+        for stripe in &mut stripes {
+            self.stripe(stripe);
+        }
+        let rest = stripes.remainder();
+        self.buf[..rest.len()].copy_from_slice(rest);
+        self.buffered = rest.len();
+    }
+
+    fn stripe(&mut self, stripe: &[u8]) {
+        for (lane, word) in self.lanes.iter_mut().zip(stripe.chunks_exact(8)) {
+            *lane = round(*lane, read_u64(word));
+        }
+    }
+
+    pub fn finish(&self) -> u64 {
+        let [v1, v2, v3, v4] = self.lanes;
+        let mut h = if self.total >= 32 {
+            let h = v1
+                .rotate_left(1)
+                .wrapping_add(v2.rotate_left(7))
+                .wrapping_add(v3.rotate_left(12))
+                .wrapping_add(v4.rotate_left(18));
+            self.lanes.iter().fold(h, |h, &lane| merge(h, lane))
+        } else {
+            PRIME_5
+        };
+        h = h.wrapping_add(self.total);
+
+        let mut rest = &self.buf[..self.buffered];
+        while rest.len() >= 8 {
+            h ^= round(0, read_u64(rest));
+            h = h
+                .rotate_left(27)
+                .wrapping_mul(PRIME_1)
+                .wrapping_add(PRIME_4);
+            rest = &rest[8..];
+        }
+        if rest.len() >= 4 {
+            let word = u32::from_le_bytes(rest[..4].try_into().unwrap());
+            h ^= u64::from(word).wrapping_mul(PRIME_1);
+            h = h
+                .rotate_left(23)
+                .wrapping_mul(PRIME_2)
+                .wrapping_add(PRIME_3);
+            rest = &rest[4..];
+        }
+        for &byte in rest {
+            h ^= u64::from(byte).wrapping_mul(PRIME_5);
+            h = h.rotate_left(11).wrapping_mul(PRIME_1);
+        }
+
+        h ^= h >> 33;
+        h = h.wrapping_mul(PRIME_2);
+        h ^= h >> 29;
+        h = h.wrapping_mul(PRIME_3);
+        h ^ (h >> 32)
+    }
+}
+
+/// Hash a file's contents as `mode` says, `partial_mb` from each end in
+/// partial mode. `None` when hashing is off.
+///
+/// A file no larger than both ends together is read whole, and its hash is
+/// the full one, so small files compare across modes.
+pub fn content_hash(path: &Path, mode: HashMode, partial_mb: u64) -> io::Result<Option<String>> {
+    if mode == HashMode::Off {
+        return Ok(None);
+    }
+    let chunk = partial_mb.max(1) * 1024 * 1024;
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut hasher = Xxh64::default();
+    let mut buf = vec![0u8; 1024 * 1024];
+    match mode {
+        HashMode::Partial if size > chunk * 2 => {
+            hasher.update(&size.to_le_bytes());
+            hash_range(&mut file, &mut hasher, &mut buf, chunk)?;
+            file.seek(SeekFrom::Start(size - chunk))?;
+            hash_range(&mut file, &mut hasher, &mut buf, chunk)?;
+            return Ok(Some(format!(
+                "xxh64-{partial_mb}m:{:016x}",
+                hasher.finish()
+            )));
+        }
+        HashMode::Off | HashMode::Partial | HashMode::Full => {
+            hash_range(&mut file, &mut hasher, &mut buf, u64::MAX)?;
+        }
+    }
+    Ok(Some(format!("xxh64:{:016x}", hasher.finish())))
+}
+
+/// Whether `path` still has the contents `stored` was computed from,
+/// hashing it the way `stored` was.
+pub fn matches(path: &Path, stored: &str) -> bool {
+    hash_like(path, stored).is_some_and(|hash| hash == stored)
+}
+
+/// The hash of `path` computed the way `stored` was; `None` when `stored`
+/// is of an unknown kind or the file cannot be read.
+pub fn hash_like(path: &Path, stored: &str) -> Option<String> {
+    let (mode, partial_mb) = match stored.split_once(':').map(|(kind, _)| kind)? {
+        "xxh64" => (HashMode::Full, 1),
+        kind => (
+            HashMode::Partial,
+            kind.strip_prefix("xxh64-")
+                .and_then(|mb| mb.strip_suffix('m'))
+                .and_then(|mb| mb.parse().ok())?,
+        ),
+    };
+    content_hash(path, mode, partial_mb).ok().flatten()
+}
+
+fn hash_range(
+    file: &mut File,
+    hasher: &mut Xxh64,
+    buf: &mut [u8],
+    mut left: u64,
+) -> io::Result<()> {
+    while left > 0 {
+        let want = buf.len().min(usize::try_from(left).unwrap_or(usize::MAX));
+        match file.read(&mut buf[..want])? {
+            0 => break,
+            n => {
+                hasher.update(&buf[..n]);
+                left -= n as u64;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Modification time in whole seconds since the Unix epoch, if available.
+pub fn modified_secs(metadata: &Metadata) -> Option<i64> {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+}
+
+/// Capture a file's size, mtime, and (optionally) quick hash.
+pub fn fingerprint(path: &Path, with_hash: bool) -> io::Result<SourceFingerprint> {
+    let metadata = std::fs::metadata(path)?;
+    let quick_hash = if with_hash {
+        Some(quick_hash(path)?)
+    } else {
+        None
+    };
+    Ok(SourceFingerprint {
+        size: metadata.len(),
+        modified: modified_secs(&metadata),
+        quick_hash,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_quick_hash_is_stable_and_content_sensitive() {
+        let tmp = tempfile::tempdir().unwrap();
+        let a = tmp.path().join("a.bin");
+        let b = tmp.path().join("b.bin");
+        fs::write(&a, b"hello world").unwrap();
+        fs::write(&b, b"hello world").unwrap();
+
+        let ha = quick_hash(&a).unwrap();
+        assert_eq!(ha.len(), 16);
+        assert_eq!(ha, quick_hash(&b).unwrap());
+
+        fs::write(&b, b"hello World").unwrap();
+        assert_ne!(ha, quick_hash(&b).unwrap());
+    }
+
+    #[test]
+    fn test_quick_hash_samples_tail_of_large_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("big.bin");
+        let mut data = vec![0u8; (QUICK_HASH_CHUNK * 3) as usize];
+        fs::write(&path, &data).unwrap();
+        let before = quick_hash(&path).unwrap();
+
+        *data.last_mut().unwrap() = 1;
+        fs::write(&path, &data).unwrap();
+        assert_ne!(before, quick_hash(&path).unwrap());
+    }
+
+    #[test]
+    fn test_xxh64_known_values_and_content_hash_modes() {
+        let xxh64 = |bytes: &[u8]| {
+            let mut hasher = Xxh64::default();
+            // Uneven pieces exercise the stripe buffer.
+            for piece in bytes.chunks(7) {
+                hasher.update(piece);
+            }
+            hasher.finish()
+        };
+        assert_eq!(xxh64(b""), 0xef46_db37_51d8_e999);
+        assert_eq!(xxh64(b"abc"), 0x44bc_2cf5_ad77_0999);
+        assert_eq!(
+            xxh64(b"Nobody inspects the spammish repetition"),
+            0xfbce_a83c_8a37_8bf1
+        );
+
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("big.bin");
+        let mut data = vec![7u8; 3 * 1024 * 1024];
+        fs::write(&path, &data).unwrap();
+        let full = content_hash(&path, HashMode::Full, 1).unwrap().unwrap();
+        let partial = content_hash(&path, HashMode::Partial, 1).unwrap().unwrap();
+        assert!(full.starts_with("xxh64:"), "{full}");
+        assert!(partial.starts_with("xxh64-1m:"), "{partial}");
+        assert!(matches(&path, &full) && matches(&path, &partial));
+        assert_eq!(content_hash(&path, HashMode::Off, 1).unwrap(), None);
+        // Small files hash whole in either mode.
+        assert_eq!(
+            content_hash(&path, HashMode::Partial, 2).unwrap().unwrap(),
+            full
+        );
+
+        // A change in the middle escapes the partial hash only.
+        data[3 * 512 * 1024] = 8;
+        fs::write(&path, &data).unwrap();
+        assert!(!matches(&path, &full));
+        assert!(matches(&path, &partial));
+    }
+}
@@ -0,0 +1,260 @@
+//! Library health checks.
+//!
+//! Libraries assembled by earlier tools drift from what Plex expects. The
+//! year check cross-verifies every `Movies/Title (Year)` folder against
+//! TMDb — through the id stored in the library database when there is one,
+//! otherwise by a fresh search — and plans renames for folders whose year
+//! disagrees. Fixes are ordinary move actions, so they get a dry-run
+//! preview, database updates, and an undo manifest like any organize run.
+
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use anyhow::Result;
+use chrono::Local;
+use regex::Regex;
+use serde::Serialize;
+use walkdir::WalkDir;
+
+use crate::config::TmdbSettings;
+use crate::database::{Database, FileRecord};
+use crate::models::{MediaType, OrganizeAction};
+use crate::providers;
+use crate::providers::tmdb::TmdbClient;
+use crate::scanner;
+
+/// `Title (1999)`, optionally followed by Plex tags such as `{tmdb-603}`.
+static FOLDER_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(.+?) \((\d{4})\)(.*)$").unwrap());
+
+/// A TMDb id and its release year, when TMDb has one.
+pub type TmdbYear = (u64, Option<i32>);
+
+/// A movie folder named with a year.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MovieFolder {
+    pub path: PathBuf,
+    pub title: String,
+    pub year: i32,
+}
+
+/// A folder whose year disagrees with TMDb.
+#[derive(Debug, Clone, Serialize)]
+pub struct YearMismatch {
+    pub folder: MovieFolder,
+    pub tmdb_id: u64,
+    pub tmdb_year: i32,
+    /// Whether the id came from the library database.
+    pub stored_id: bool,
+}
+
+/// Outcome of a year check.
+#[derive(Debug, Default, Serialize)]
+pub struct YearReport {
+    pub checked: usize,
+    pub mismatches: Vec<YearMismatch>,
+    /// No confident TMDb match.
+    pub unresolved: Vec<PathBuf>,
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+/// Movie folders under `root`: named `Title (Year)` inside a `movies_dir`
+/// folder (directly under `root`, or under a language folder).
+pub fn movie_folders(root: &Path, movies_dir: &str) -> Vec<MovieFolder> {
+    let mut folders: Vec<MovieFolder> = WalkDir::new(root)
+        .min_depth(2)
+        .max_depth(3)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_dir())
+        .filter(|e| {
+            let parent = e.path().parent().and_then(|p| p.file_name());
+            parent.is_some_and(|name| name == movies_dir)
+        })
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().into_owned();
+            let caps = FOLDER_RE.captures(&name)?;
+            Some(MovieFolder {
+                path: e.path().to_path_buf(),
+                title: caps[1].to_string(),
+                year: caps[2].parse().ok()?,
+            })
+        })
+        .collect();
+    folders.sort_by(|a, b| a.path.cmp(&b.path));
+    folders
+}
+
+/// The database record of the folder's main video, preferring one with a
+/// TMDb id.
+pub fn folder_record<'a>(db: &'a Database, folder: &Path) -> Option<&'a FileRecord> {
+    let in_folder = |r: &&FileRecord| {
+        let dest = Path::new(&r.destination);
+        dest.parent() == Some(folder) && scanner::is_media_path(dest)
+    };
+    let mut records = db.files().iter().filter(in_folder);
+    let first = records.clone().next();
+    records.find(|r| r.tmdb_id.is_some()).or(first)
+}
+
+/// Check every folder's year with `lookup`, which resolves a folder (and
+/// its database record, if any) to a TMDb id and release year.
+pub fn check_years<L>(folders: &[MovieFolder], db: &Database, mut lookup: L) -> YearReport
+where
+    L: FnMut(&MovieFolder, Option<&FileRecord>) -> Result<Option<TmdbYear>>,
+{
+    let mut report = YearReport::default();
+    for folder in folders {
+        report.checked += 1;
+        let record = folder_record(db, &folder.path);
+        match lookup(folder, record) {
+            Ok(Some((id, Some(year)))) if year != folder.year => {
+                report.mismatches.push(YearMismatch {
+                    folder: folder.clone(),
+                    tmdb_id: id,
+                    tmdb_year: year,
+                    stored_id: record.and_then(|r| r.tmdb_id) == Some(id),
+                })
+            }
+            Ok(Some(_)) => {}
+            Ok(None) => report.unresolved.push(folder.path.clone()),
+            Err(e) => report.failed.push((folder.path.clone(), format!("{e:#}"))),
+        }
+    }
+    report
+}
+
+/// A `lookup` for [`check_years`] backed by TMDb: the stored id (and its
+/// stored details) first, then a search scored against the folder year,
+/// then against the title alone.
+pub fn tmdb_year_lookup<'a>(
+    client: &'a TmdbClient,
+    settings: &'a TmdbSettings,
+) -> impl FnMut(&MovieFolder, Option<&FileRecord>) -> Result<Option<TmdbYear>> + 'a {
+    move |folder, record| {
+        if let Some(id) = record.and_then(|r| r.tmdb_id) {
+            let stored = record.and_then(|r| r.details.as_ref()).and_then(|d| d.year);
+            let year = match stored {
+                Some(year) => Some(year),
+                None => client.movie_details(id, &settings.region)?.year,
+            };
+            return Ok(Some((id, year)));
+        }
+        let candidates = client.search_movie(&folder.title, None)?;
+        let today = Some(Local::now().date_naive());
+        let best = [Some(folder.year), None].into_iter().find_map(|year| {
+            providers::best_candidate(
+                &folder.title,
+                year,
+                today,
+                &candidates,
+                settings.min_match_score,
+            )
+        });
+        Ok(best.and_then(|(c, _)| Some((c.id?, c.year))))
+    }
+}
+
+/// Move actions renaming a folder to the TMDb year, along with every file
+/// inside it that carries the old `Title (Year)` name.
+pub fn year_fix_actions(mismatch: &YearMismatch) -> Vec<OrganizeAction> {
+    let folder = &mismatch.folder;
+    let Some(name) = folder.path.file_name().map(|n| n.to_string_lossy()) else {
+        return Vec::new();
+    };
+    let old = format!("{} ({})", folder.title, folder.year);
+    let new = format!("{} ({})", folder.title, mismatch.tmdb_year);
+    let new_folder = folder.path.with_file_name(name.replacen(&old, &new, 1));
+
+    WalkDir::new(&folder.path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| {
+            let relative = e.path().strip_prefix(&folder.path).ok()?;
+            let mut destination = new_folder.join(relative);
+            let file_name = e.file_name().to_string_lossy();
+            if file_name.starts_with(&old) {
+                destination.set_file_name(file_name.replacen(&old, &new, 1));
+            }
+            Some(OrganizeAction {
+                source: e.path().to_path_buf(),
+                destination,
+                strategy: "move".to_string(),
+                media_type: MediaType::Movie,
+                title: folder.title.clone(),
+                year: Some(mismatch.tmdb_year),
+                confidence: 100.0,
+                tmdb_id: Some(mismatch.tmdb_id),
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+/// Remove a renamed folder once its files have moved out; anything left
+/// behind keeps it.
+pub fn remove_emptied_folder(folder: &Path) {
+    let dirs = WalkDir::new(folder)
+        .contents_first(true)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_dir());
+    for dir in dirs {
+        // Fails (harmlessly) on non-empty directories.
+        let _ = std::fs::remove_dir(dir.path());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_year_check_and_fix_plan() {
+        let tmp = tempfile::tempdir().unwrap();
+        let movies = tmp.path().join("Movies");
+        let heat = movies.join("Heat (1996)");
+        fs::create_dir_all(heat.join("Featurettes")).unwrap();
+        fs::write(heat.join("Heat (1996).mkv"), b"x").unwrap();
+        fs::write(heat.join("Heat (1996).en.srt"), b"x").unwrap();
+        fs::write(heat.join("Featurettes/Making Of.mkv"), b"x").unwrap();
+        fs::create_dir_all(movies.join("Alien (1979)")).unwrap();
+        fs::create_dir_all(movies.join("Unknown (2001)")).unwrap();
+        fs::create_dir_all(tmp.path().join("Music/Artist/Album (1999)")).unwrap();
+
+        let folders = movie_folders(tmp.path(), "Movies");
+        let titles: Vec<_> = folders.iter().map(|f| f.title.as_str()).collect();
+        assert_eq!(titles, ["Alien", "Heat", "Unknown"]);
+
+        let db = Database::open(&tmp.path().join("library.json")).unwrap();
+        let report = check_years(&folders, &db, |folder, _| {
+            Ok(match folder.title.as_str() {
+                "Heat" => Some((949, Some(1995))),
+                "Alien" => Some((348, Some(1979))),
+                _ => None,
+            })
+        });
+        assert_eq!(report.checked, 3);
+        assert_eq!(report.unresolved, [movies.join("Unknown (2001)")]);
+        let [mismatch] = &report.mismatches[..] else {
+            panic!("expected one mismatch: {:?}", report.mismatches);
+        };
+        assert_eq!((mismatch.folder.year, mismatch.tmdb_year), (1996, 1995));
+
+        let mut moves: Vec<_> = year_fix_actions(mismatch)
+            .into_iter()
+            .map(|a| a.destination.strip_prefix(&movies).unwrap().to_path_buf())
+            .collect();
+        moves.sort();
+        assert_eq!(
+            moves,
+            [
+                PathBuf::from("Heat (1995)/Featurettes/Making Of.mkv"),
+                PathBuf::from("Heat (1995)/Heat (1995).en.srt"),
+                PathBuf::from("Heat (1995)/Heat (1995).mkv"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,94 @@
+//! Home-video detection.
+//!
+//! Phone and camera clips (`IMG_2034.MOV`, `VID_20230812_140322.mp4`,
+//! `GX010045.MP4`, `2023-08-12 14.03.22.mp4`) are nobody's movie, and a
+//! TMDb search only turns up nonsense for them. They are recognized by the
+//! naming schemes cameras use and either skipped or filed by recording date
+//! under `Home Videos/YYYY/MM/` (`scan.home_videos`).
+
+use std::sync::LazyLock;
+
+use chrono::NaiveDate;
+use regex::Regex;
+
+/// Camera and phone file names: a device prefix followed by a counter or
+/// a timestamp.
+static CAMERA_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?ix)^(?:
+            (?:img|vid|mvi|mov|dsc[fn]?|pxl|cimg|mah|dji|video)[_-]?\d{3,}
+          | (?:gopr|gp|gh|gx)\d{6}
+          | (?:screen\ recording|rpreplay_final)\s*\d
+        )",
+    )
+    .unwrap()
+});
+
+/// An EXIF-style timestamp: `2023-08-12 14.03.22`, `20230812_140322`.
+static TIMESTAMP_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?x)(?:^|[^0-9])
+            ((?:19|20)\d{2})-?(\d{2})-?(\d{2})
+            [\ _T-]?
+            ([0-2]\d)[.:\-]?([0-5]\d)(?:[.:\-]?([0-5]\d))?",
+    )
+    .unwrap()
+});
+
+/// Whether a file name (with or without extension) looks like a phone or
+/// camera recording.
+pub fn is_home_video(name: &str) -> bool {
+    CAMERA_RE.is_match(name) || TIMESTAMP_RE.find(name).is_some_and(|m| m.start() == 0)
+}
+
+/// The recording date in a camera file name, if it carries one.
+pub fn recorded_date(name: &str) -> Option<NaiveDate> {
+    TIMESTAMP_RE.captures_iter(name).find_map(|caps| {
+        let num = |i: usize| caps[i].parse::<u32>().ok();
+        let hour = num(4)?;
+        (hour < 24).then_some(())?;
+        NaiveDate::from_ymd_opt(caps[1].parse().ok()?, num(2)?, num(3)?)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_home_video_names() {
+        for name in [
+            "IMG_2034.MOV",
+            "VID_20230812_140322.mp4",
+            "VID-20230812-WA0003.mp4",
+            "PXL_20230812_140322123.mp4",
+            "MVI_0412.AVI",
+            "GX010045.MP4",
+            "DJI_0001.mp4",
+            "2023-08-12 14.03.22.mp4",
+            "20230812_140322.mp4",
+            "Screen Recording 2023-08-12 at 14.03.22.mov",
+        ] {
+            assert!(is_home_video(name), "{name}");
+        }
+        for name in [
+            "Heat.1995.1080p.mkv",
+            "2012.2009.1080p.BluRay.mkv",
+            "Movie.2023.08.12.720p.mkv",
+            "Imagine.1988.mkv",
+            "The.Office.S01E01.mkv",
+        ] {
+            assert!(!is_home_video(name), "{name}");
+        }
+
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d);
+        assert_eq!(recorded_date("VID_20230812_140322.mp4"), date(2023, 8, 12));
+        assert_eq!(
+            recorded_date("PXL_20211231_235959999.mp4"),
+            date(2021, 12, 31)
+        );
+        assert_eq!(recorded_date("2023-08-12 14.03.22.mp4"), date(2023, 8, 12));
+        assert_eq!(recorded_date("IMG_2034.MOV"), None);
+        assert_eq!(recorded_date("VID_20231345_140322.mp4"), None);
+    }
+}
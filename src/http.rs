@@ -0,0 +1,319 @@
+//! Minimal blocking HTTP client for metadata providers.
+//!
+//! Requests go through the system `curl` binary rather than an HTTP crate:
+//! providers make a handful of small requests per run, and curl ships
+//! with Linux, macOS, and Windows 10+ (see D007). The URL, headers, and
+//! any body reach curl as a config on its stdin, never on its command
+//! line, where API keys and tokens would be visible to every local user in
+//! `ps` and a large body would overflow the argument limit.
+
+use std::error::Error as StdError;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use thiserror::Error;
+
+use crate::chaos::{self, Fault};
+
+/// Seconds before a request is abandoned.
+const TIMEOUT_SECS: &str = "20";
+
+/// GET `url` and return the response body.
+///
+/// Non-2xx responses and requests that got no response fail with an
+/// [`HttpError`]; a response's includes the start of the body, which is
+/// where APIs put their error messages.
+pub fn get(url: &str, headers: &[(&str, &str)]) -> Result<String> {
+    request(url, headers, None)
+}
+
+/// POST a JSON `body` to `url` (GraphQL APIs such as AniList's) and return
+/// the response body; errors are as for [`get`].
+pub fn post_json(url: &str, body: &str, headers: &[(&str, &str)]) -> Result<String> {
+    request(url, headers, Some(body))
+}
+
+fn request(url: &str, headers: &[(&str, &str)], json: Option<&str>) -> Result<String> {
+    if let Err(e) = chaos::inject(Fault::Timeout) {
+        return Err(HttpError::no_response(e.to_string()).into());
+    }
+    if let Err(e) = chaos::inject(Fault::ServerError) {
+        return Err(HttpError::status(500, e.to_string()).into());
+    }
+    let mut config = String::new();
+    config_line(&mut config, "url", url);
+    for (name, value) in headers {
+        config_line(&mut config, "header", &format!("{name}: {value}"));
+    }
+    if let Some(body) = json {
+        config_line(&mut config, "header", "Content-Type: application/json");
+        // `data-raw` sends the body as is, even one starting with `@`.
+        config_line(&mut config, "data-raw", body);
+    }
+
+    let mut cmd = Command::new("curl");
+    cmd.args(["--silent", "--show-error", "--location"])
+        .args(["--max-time", TIMEOUT_SECS])
+        .args(["--write-out", "\n%{http_code}"])
+        .args(["--config", "-"]);
+
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to run curl; is it installed and on PATH?")?;
+    // curl reads the whole config before it sends anything, so writing it
+    // up front cannot deadlock against the response on stdout.
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(config.as_bytes())
+            .context("Failed to pass the request to curl")?;
+    }
+    let output = child
+        .wait_with_output()
+        .context("Failed to run curl; is it installed and on PATH?")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(HttpError::no_response(stderr.trim()).into());
+    }
+
+    let stdout = String::from_utf8(output.stdout).context("Response is not valid UTF-8")?;
+    let (body, status) = stdout.rsplit_once('\n').unwrap_or(("", stdout.as_str()));
+    let status: u16 = status.trim().parse().unwrap_or(0);
+    if !(200..300).contains(&status) {
+        let excerpt: String = body.chars().take(200).collect();
+        return Err(HttpError::status(status, excerpt).into());
+    }
+    Ok(body.to_string())
+}
+
+/// Append `name = "value"` to a curl config, escaped the way curl
+/// unquotes it.
+fn config_line(config: &mut String, name: &str, value: &str) {
+    config.push_str(name);
+    config.push_str(" = \"");
+    for c in value.chars() {
+        match c {
+            '\\' => config.push_str("\\\\"),
+            '"' => config.push_str("\\\""),
+            '\n' => config.push_str("\\n"),
+            '\r' => config.push_str("\\r"),
+            '\t' => config.push_str("\\t"),
+            c => config.push(c),
+        }
+    }
+    config.push_str("\"\n");
+}
+
+/// A request that got no response, or a non-2xx one.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{message}")]
+pub struct HttpError {
+    /// The response's status; `None` when none came (curl failed, the
+    /// request timed out, or the connection dropped).
+    pub status: Option<u16>,
+    message: String,
+}
+
+impl HttpError {
+    fn status(status: u16, excerpt: impl Into<String>) -> Self {
+        Self {
+            status: Some(status),
+            message: format!("HTTP {status}: {}", excerpt.into()),
+        }
+    }
+
+    fn no_response(detail: impl Into<String>) -> Self {
+        Self {
+            status: None,
+            message: format!("HTTP request failed: {}", detail.into()),
+        }
+    }
+
+    /// An error read back from a recording, with its recorded message.
+    pub(crate) fn replayed(status: Option<u16>, message: String) -> Self {
+        Self { status, message }
+    }
+
+    /// Whether the failure may go away on its own: rate limiting (429), a
+    /// server error (5xx), or no response at all.
+    pub fn is_transient(&self) -> bool {
+        self.status
+            .is_none_or(|status| status == 429 || (500..600).contains(&status))
+    }
+}
+
+/// The [`HttpError`] behind `error`, if a request failed somewhere in its
+/// chain of causes.
+pub fn find(error: &(dyn StdError + 'static)) -> Option<&HttpError> {
+    let mut next = Some(error);
+    while let Some(error) = next {
+        if let Some(http) = error.downcast_ref::<HttpError>() {
+            return Some(http);
+        }
+        next = error.source();
+    }
+    None
+}
+
+/// Whether `error` came from a request whose failure may go away on its
+/// own (see [`HttpError::is_transient`]).
+pub fn is_transient(error: &(dyn StdError + 'static)) -> bool {
+    find(error).is_some_and(HttpError::is_transient)
+}
+
+/// Spaces requests evenly to stay under a provider's rate limit, with an
+/// optional politeness pause and random jitter on top.
+#[derive(Debug)]
+pub struct RateLimiter {
+    interval: Duration,
+    pause: Duration,
+    jitter: Duration,
+    next: Mutex<Instant>,
+    /// xorshift state, seeded per run.
+    rng: Mutex<u64>,
+}
+
+impl RateLimiter {
+    /// Allow at most `per_second` requests per second; 0 disables limiting.
+    pub fn per_second(per_second: u32) -> Self {
+        Self::every(match per_second {
+            0 => Duration::ZERO,
+            n => Duration::from_secs(1) / n,
+        })
+    }
+
+    /// Allow at most `per_minute` requests per minute; 0 disables limiting.
+    pub fn per_minute(per_minute: u32) -> Self {
+        Self::every(match per_minute {
+            0 => Duration::ZERO,
+            n => Duration::from_secs(60) / n,
+        })
+    }
+
+    fn every(interval: Duration) -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64)
+            ^ u64::from(std::process::id());
+        Self {
+            interval,
+            pause: Duration::ZERO,
+            jitter: Duration::ZERO,
+            next: Mutex::new(Instant::now()),
+            rng: Mutex::new(seed | 1),
+        }
+    }
+
+    /// Also wait `pause` plus a random share of `jitter` between requests,
+    /// so they neither come back to back nor tick at a fixed rhythm.
+    pub fn with_pacing(mut self, pause: Duration, jitter: Duration) -> Self {
+        self.pause = pause;
+        self.jitter = jitter;
+        self
+    }
+
+    /// Block until the next request may be sent.
+    pub fn wait(&self) {
+        let mut next = self.next.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        if *next > now {
+            thread::sleep(*next - now);
+        }
+        *next = Instant::now().max(*next) + self.interval + self.pause + self.random_jitter();
+    }
+
+    fn random_jitter(&self) -> Duration {
+        if self.jitter.is_zero() {
+            return Duration::ZERO;
+        }
+        let mut state = self.rng.lock().unwrap_or_else(|e| e.into_inner());
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        self.jitter
+            .mul_f64((*state >> 11) as f64 / (1u64 << 53) as f64)
+    }
+}
+
+/// Percent-encode a query-string component (RFC 3986 unreserved set kept).
+pub fn encode_component(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(byte as char)
+            }
+            _ => out.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter_spaces_requests() {
+        let limiter = RateLimiter::per_second(100);
+        let started = Instant::now();
+        for _ in 0..4 {
+            limiter.wait();
+        }
+        assert!(started.elapsed() >= Duration::from_millis(30));
+
+        let paced = RateLimiter::per_second(0)
+            .with_pacing(Duration::from_millis(10), Duration::from_millis(5));
+        let started = Instant::now();
+        for _ in 0..4 {
+            paced.wait();
+        }
+        assert!(started.elapsed() >= Duration::from_millis(30));
+    }
+
+    #[test]
+    fn test_transient_errors() {
+        let error = |http: HttpError| anyhow::Error::new(http).context("TMDb search failed");
+        assert!(is_transient(
+            error(HttpError::status(503, "Service Unavailable")).as_ref()
+        ));
+        assert!(is_transient(
+            error(HttpError::status(429, "too many requests")).as_ref()
+        ));
+        assert!(is_transient(
+            error(HttpError::no_response("timed out")).as_ref()
+        ));
+        assert!(!is_transient(
+            error(HttpError::status(401, "Invalid API key")).as_ref()
+        ));
+        assert!(!is_transient(
+            anyhow::anyhow!("HTTP 503: looks like one").as_ref()
+        ));
+        let found = error(HttpError::status(404, "not found"));
+        assert_eq!(find(found.as_ref()).and_then(|e| e.status), Some(404));
+    }
+
+    #[test]
+    fn test_config_line_escapes() {
+        let mut config = String::new();
+        config_line(&mut config, "url", "https://x.test/?q=a&api_key=k");
+        config_line(&mut config, "header", "X-Note: say \"hi\"\\\n");
+        assert_eq!(
+            config,
+            "url = \"https://x.test/?q=a&api_key=k\"\nheader = \"X-Note: say \\\"hi\\\"\\\\\\n\"\n"
+        );
+    }
+
+    #[test]
+    fn test_encode_component() {
+        assert_eq!(encode_component("The Matrix"), "The%20Matrix");
+        assert_eq!(encode_component("Amélie & co"), "Am%C3%A9lie%20%26%20co");
+        assert_eq!(encode_component("a-b_c.d~"), "a-b_c.d~");
+    }
+}
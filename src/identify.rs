@@ -0,0 +1,226 @@
+//! Identification of scanned files: parse each name and match it with the
+//! metadata providers, as the `plan`, `organize`, `watch`, and `view`
+//! commands and [`Organizer::identify`] do.
+//!
+//! Files rejected in review are left out unless pinned or corrected;
+//! pinned files keep their pin and corrected files are confirmed as their
+//! correction says, without a lookup. Home videos (unless organized) and
+//! files a profile skips are left out, and titles are restored from
+//! learned tokens before matching. All files are parsed before any is
+//! matched, so their TMDb searches can be made ahead of matching (see
+//! [`Enricher::prefetch`]), and lookups that hit a TMDb server error or
+//! timeout are retried at the end.
+//!
+//! [`Organizer::identify`]: crate::Organizer::identify
+
+use std::path::PathBuf;
+
+use tracing::info;
+
+use crate::config::{AppConfig, HomeVideos};
+use crate::database::Database;
+use crate::enricher::Enricher;
+use crate::models::{EnrichedMedia, MediaFile, MediaType, ParsedMedia};
+use crate::parser;
+use crate::probe;
+use crate::profile;
+use crate::progress::ProgressEvent;
+
+/// A source file with its enriched metadata.
+pub type Item = (PathBuf, EnrichedMedia);
+
+/// The files [`identify`] identified, and counts of what it left out.
+#[derive(Debug, Default)]
+pub struct Identified {
+    pub items: Vec<Item>,
+    /// Title tokens learned from TMDb matches into the database.
+    pub learned: usize,
+    /// Home videos left out with `scan.home_videos = "skip"`.
+    pub home_videos: usize,
+    /// Files left unidentified once `stop` said to stop.
+    pub deferred: usize,
+    /// Lookups retried after failing transiently.
+    pub retried: usize,
+    /// Retried lookups that succeeded.
+    pub recovered: usize,
+}
+
+/// Parse and enrich `files`, learning title tokens from the matches into
+/// `db`; saving it is up to the caller.
+///
+/// `stop` is asked before each file and before the prefetch and retry
+/// rounds; once it says so, the remaining files are counted as deferred.
+/// Each file is reported to `on_event` as it is parsed and matched, and a
+/// retry round as it starts.
+pub fn identify(
+    files: Vec<MediaFile>,
+    db: &mut Database,
+    enricher: &Enricher,
+    config: &AppConfig,
+    stop: &dyn Fn() -> bool,
+    on_event: &mut dyn FnMut(ProgressEvent),
+) -> Identified {
+    let mut identified = Identified::default();
+
+    let files: Vec<_> = files
+        .into_iter()
+        .filter(|f| {
+            let chosen =
+                db.pin_for(&f.source_path).is_some() || db.correction_for(&f.source_path).is_some();
+            chosen || !db.is_rejected(&f.source_path)
+        })
+        .collect();
+    let mut steps = Vec::new();
+    for (i, f) in files.iter().enumerate() {
+        on_event(ProgressEvent::Parsing {
+            done: i + 1,
+            total: files.len(),
+            path: f.source_path.clone(),
+        });
+        if stop() {
+            identified.deferred += 1;
+            continue;
+        }
+        let mut parsed = parser::parse_media_file(f, enricher.parse_options());
+        if config.scan.probe && parsed.media_type != MediaType::Music {
+            probe::apply(&mut parsed, &f.source_path);
+        }
+        if let Some(pin) = db.pin_for(&f.source_path) {
+            steps.push((
+                f,
+                Step::Chosen(pin.enriched(&parsed, enricher.parse_options())),
+            ));
+            continue;
+        }
+        if let Some(correction) = db.correction_for(&f.source_path) {
+            steps.push((f, Step::Chosen(correction.enriched(enricher, parsed))));
+            continue;
+        }
+        if parsed.home_video && config.scan.home_videos == HomeVideos::Skip {
+            info!("skipping home video {:?}", parsed.raw_filename);
+            identified.home_videos += 1;
+            continue;
+        }
+        let rules = profile::rules_for(config, &parsed);
+        if rules.skips(&parsed.raw_filename) {
+            info!("{} profile skips {:?}", rules.profile, parsed.raw_filename);
+            continue;
+        }
+        db.restore_title(&mut parsed, enricher.parse_options());
+        steps.push((f, Step::Match(parsed)));
+    }
+
+    // Titles shared by many files are searched once, several at a time.
+    let pending: Vec<_> = steps
+        .iter()
+        .filter_map(|(_, step)| match step {
+            Step::Match(parsed) => Some(parsed.clone()),
+            Step::Chosen(_) => None,
+        })
+        .collect();
+    if !stop() {
+        enricher.prefetch(&pending);
+    }
+
+    let total = steps.len();
+    for (i, (f, step)) in steps.into_iter().enumerate() {
+        on_event(ProgressEvent::Matching {
+            done: i + 1,
+            total,
+            path: f.source_path.clone(),
+        });
+        let parsed = match step {
+            Step::Chosen(enriched) => {
+                identified.items.push((f.source_path.clone(), enriched));
+                continue;
+            }
+            Step::Match(parsed) => parsed,
+        };
+        if stop() {
+            identified.deferred += 1;
+            continue;
+        }
+        let enriched = enricher.enrich(parsed);
+        identified.learned += db.learn_title_tokens(&enriched);
+        identified.items.push((f.source_path.clone(), enriched));
+    }
+
+    let failed: Vec<usize> = (0..identified.items.len())
+        .filter(|&i| identified.items[i].1.lookup_failed)
+        .collect();
+    if !failed.is_empty() && !stop() {
+        on_event(ProgressEvent::Retrying {
+            total: failed.len(),
+        });
+        identified.retried = failed.len();
+        identified.recovered =
+            enricher.retry_failed_lookups(identified.items.iter_mut().map(|(_, e)| e));
+        for &i in &failed {
+            if !identified.items[i].1.lookup_failed {
+                identified.learned += db.learn_title_tokens(&identified.items[i].1);
+            }
+        }
+    }
+    identified
+}
+
+/// A parsed file on its way through [`identify`].
+enum Step {
+    /// Pinned or corrected: identified without a lookup.
+    Chosen(EnrichedMedia),
+    /// To be matched by the enricher.
+    Match(ParsedMedia),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    use crate::scanner::{self, ScanOptions};
+
+    #[test]
+    fn test_rejected_and_deferred_files_are_left_out() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = tmp.path().join("downloads");
+        std::fs::create_dir_all(&source).unwrap();
+        for name in ["Heat.1995.1080p.mkv", "Alien.1979.1080p.mkv"] {
+            std::fs::write(source.join(name), b"x").unwrap();
+        }
+        let options = ScanOptions {
+            min_video_size: 0,
+            ..Default::default()
+        };
+        let files = scanner::scan_directory(&source, &options).unwrap();
+        let config = AppConfig::default();
+        let enricher = Enricher::new(config.clone());
+        let mut db = Database::open(&tmp.path().join("library.json")).unwrap();
+        let alien = EnrichedMedia::from_parsed(ParsedMedia::default());
+        db.enqueue_review(&source.join("Alien.1979.1080p.mkv"), &alien);
+        db.reject_review(1).unwrap();
+
+        let mut events = Vec::new();
+        let found = identify(
+            files.clone(),
+            &mut db,
+            &enricher,
+            &config,
+            &|| false,
+            &mut |e| events.push(e),
+        );
+        let sources: Vec<&Path> = found.items.iter().map(|(p, _)| p.as_path()).collect();
+        assert_eq!(sources, [source.join("Heat.1995.1080p.mkv")]);
+        assert!(matches!(
+            events.last(),
+            Some(ProgressEvent::Matching {
+                done: 1,
+                total: 1,
+                ..
+            })
+        ));
+
+        let stopped = identify(files, &mut db, &enricher, &config, &|| true, &mut |_| {});
+        assert!(stopped.items.is_empty());
+        assert_eq!(stopped.deferred, 1);
+    }
+}
@@ -0,0 +1,254 @@
+//! Title language detection.
+//!
+//! Release names carry no reliable language tag for the title itself, so the
+//! language is inferred from the title text:
+//!
+//! 1. **Script** — Hangul, kana, Han, Cyrillic, Thai, Arabic, Hebrew, Greek,
+//!    and Devanagari each identify a language (or a small family) outright.
+//! 2. **Latin stopwords and diacritics** — `le`/`les`/`é` for French,
+//!    `der`/`und`/`ß` for German, and so on. Ties and single weak hits are
+//!    reported with low confidence so a title like "Die Hard" does not get
+//!    routed as German.
+//!
+//! A detection at least [`TRUSTED_CONFIDENCE`] sure picks the language a
+//! title the plain TMDb search misses is searched in again, and the
+//! `organize.language_dirs` folder it is filed under.
+
+use crate::models::{LanguageInfo, Script};
+
+/// Detections at least this sure are acted on: the title is searched on
+/// TMDb in its language and routed by it (`organize.language_dirs`).
+pub const TRUSTED_CONFIDENCE: f64 = 0.6;
+
+/// ISO 639-1 code and the spellings that map to it (lowercase).
+const LANGUAGES: &[(&str, &[&str])] = &[
+    ("en", &["en", "eng", "english"]),
+    (
+        "zh",
+        &["zh", "chi", "zho", "chs", "cht", "chinese", "mandarin"],
+    ),
+    ("ja", &["ja", "jpn", "japanese"]),
+    ("ko", &["ko", "kor", "korean"]),
+    ("fr", &["fr", "fre", "fra", "french"]),
+    ("de", &["de", "ger", "deu", "german"]),
+    ("es", &["es", "spa", "spanish"]),
+    ("it", &["it", "ita", "italian"]),
+    (
+        "pt",
+        &["pt", "por", "portuguese", "pt-br", "ptbr", "brazilian"],
+    ),
+    ("ru", &["ru", "rus", "russian"]),
+    ("nl", &["nl", "dut", "nld", "dutch"]),
+    ("sv", &["sv", "swe", "swedish"]),
+    ("no", &["no", "nor", "nb", "norwegian"]),
+    ("da", &["da", "dan", "danish"]),
+    ("fi", &["fi", "fin", "finnish"]),
+    ("pl", &["pl", "pol", "polish"]),
+    ("tr", &["tr", "tur", "turkish"]),
+    ("ar", &["ar", "ara", "arabic"]),
+    ("he", &["he", "heb", "hebrew"]),
+    ("hi", &["hin", "hindi"]),
+    ("cs", &["cs", "cze", "ces", "czech"]),
+    ("hu", &["hu", "hun", "hungarian"]),
+    ("el", &["el", "gre", "ell", "greek"]),
+    ("th", &["th", "tha", "thai"]),
+    ("vi", &["vi", "vie", "vietnamese"]),
+    ("id", &["id", "ind", "indonesian"]),
+    ("uk", &["uk", "ukr", "ukrainian"]),
+    ("ro", &["ro", "rum", "ron", "romanian"]),
+];
+
+/// Normalize a language spelling (`eng`, `French`, `chs`) to ISO 639-1.
+///
+/// `hi` is deliberately absent: in release and subtitle names it almost
+/// always means "hearing impaired", not Hindi.
+pub fn normalize_language(token: &str) -> Option<&'static str> {
+    let token = token.to_lowercase();
+    LANGUAGES
+        .iter()
+        .find(|(_, names)| names.contains(&token.as_str()))
+        .map(|(code, _)| *code)
+}
+
+/// Common short words per Latin-script language.
+const STOPWORDS: &[(&str, &[&str])] = &[
+    (
+        "en",
+        &["the", "of", "and", "a", "in", "to", "with", "for", "from"],
+    ),
+    (
+        "fr",
+        &[
+            "le", "la", "les", "des", "du", "et", "un", "une", "au", "aux",
+        ],
+    ),
+    (
+        "de",
+        &[
+            "der", "die", "das", "und", "ein", "eine", "im", "mit", "von",
+        ],
+    ),
+    (
+        "es",
+        &["el", "los", "las", "y", "del", "una", "por", "con", "en"],
+    ),
+    ("it", &["il", "lo", "gli", "della", "di", "e", "una", "per"]),
+    ("pt", &["o", "os", "da", "do", "dos", "em", "uma", "e"]),
+    ("nl", &["het", "een", "van", "en", "op", "niet"]),
+    ("sv", &["och", "en", "ett", "av", "på", "med"]),
+];
+
+/// Letters that point at a specific Latin-script language.
+const DIACRITICS: &[(char, &str)] = &[
+    ('ñ', "es"),
+    ('ß', "de"),
+    ('ü', "de"),
+    ('ç', "fr"),
+    ('è', "fr"),
+    ('ê', "fr"),
+    ('œ', "fr"),
+    ('ã', "pt"),
+    ('õ', "pt"),
+    ('ò', "it"),
+    ('ì', "it"),
+    ('å', "sv"),
+];
+
+pub(crate) fn script_of(c: char) -> Option<Script> {
+    Some(match c as u32 {
+        0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF => Script::Hangul,
+        0x3040..=0x30FF | 0x31F0..=0x31FF => Script::Kana,
+        0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF => Script::Han,
+        0x0400..=0x04FF => Script::Cyrillic,
+        0x0E00..=0x0E7F => Script::Thai,
+        0x0600..=0x06FF => Script::Arabic,
+        0x0590..=0x05FF => Script::Hebrew,
+        0x0370..=0x03FF => Script::Greek,
+        0x0900..=0x097F => Script::Devanagari,
+        _ if c.is_alphabetic() => Script::Latin,
+        _ => return None,
+    })
+}
+
+/// Detect the language of a title.
+///
+/// Returns `None` when the text gives no usable signal (e.g., a bare
+/// ASCII word with no stopwords).
+pub fn detect_title_language(title: &str) -> Option<LanguageInfo> {
+    let mut counts: Vec<(Script, usize)> = Vec::new();
+    for script in title.chars().filter_map(script_of) {
+        match counts.iter_mut().find(|(s, _)| *s == script) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((script, 1)),
+        }
+    }
+
+    // Any non-Latin script wins: Chinese releases often append an English
+    // title, and the native-script part is what identifies the film.
+    let native = counts
+        .iter()
+        .filter(|(s, _)| *s != Script::Latin)
+        .max_by_key(|(_, n)| *n)
+        .map(|(s, _)| *s);
+    let has = |script| counts.iter().any(|(s, _)| *s == script);
+
+    let (code, confidence) = match native {
+        // Japanese mixes kanji with kana; kana alone is decisive.
+        Some(Script::Han | Script::Kana) if has(Script::Kana) => ("ja", 0.95),
+        Some(Script::Han) => ("zh", 0.9),
+        Some(Script::Hangul) => ("ko", 0.95),
+        Some(Script::Cyrillic) if title.contains(['і', 'ї', 'є', 'ґ']) => ("uk", 0.9),
+        Some(Script::Cyrillic) => ("ru", 0.8),
+        Some(Script::Thai) => ("th", 0.95),
+        Some(Script::Arabic) => ("ar", 0.85),
+        Some(Script::Hebrew) => ("he", 0.95),
+        Some(Script::Greek) => ("el", 0.95),
+        Some(Script::Devanagari) => ("hi", 0.85),
+        _ => return detect_latin(title),
+    };
+    Some(LanguageInfo {
+        code: code.to_string(),
+        script: native.unwrap_or(Script::Latin),
+        confidence,
+    })
+}
+
+fn detect_latin(title: &str) -> Option<LanguageInfo> {
+    let lower = title.to_lowercase();
+    let words: Vec<&str> = lower
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    let mut scores: Vec<(&str, u32)> = Vec::new();
+    let mut bump = |code: &'static str| match scores.iter_mut().find(|(c, _)| *c == code) {
+        Some((_, n)) => *n += 1,
+        None => scores.push((code, 1)),
+    };
+    for (code, stopwords) in STOPWORDS {
+        for word in &words {
+            if stopwords.contains(word) {
+                bump(code);
+            }
+        }
+    }
+    for c in lower.chars() {
+        if let Some((_, code)) = DIACRITICS.iter().find(|(d, _)| *d == c) {
+            bump(code);
+        }
+    }
+
+    scores.sort_by_key(|s| std::cmp::Reverse(s.1));
+    let (code, best) = *scores.first()?;
+    let runner_up = scores.get(1).map_or(0, |s| s.1);
+    if best == runner_up {
+        return None;
+    }
+    // One hit is a hint, two or more a fair bet; never as sure as a script.
+    let margin = f64::from(best - runner_up);
+    Some(LanguageInfo {
+        code: code.to_string(),
+        script: Script::Latin,
+        confidence: (0.25 * margin + 0.1).min(0.8),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code(title: &str) -> Option<String> {
+        detect_title_language(title).map(|l| l.code)
+    }
+
+    #[test]
+    fn test_detects_non_latin_scripts() {
+        assert_eq!(code("기생충").as_deref(), Some("ko"));
+        assert_eq!(code("千と千尋の神隠し").as_deref(), Some("ja"));
+        assert_eq!(code("大话西游 A Chinese Odyssey").as_deref(), Some("zh"));
+        assert_eq!(code("Брат").as_deref(), Some("ru"));
+        assert_eq!(code("ฉลาดเกมส์โกง").as_deref(), Some("th"));
+    }
+
+    #[test]
+    fn test_detects_latin_languages_by_stopwords() {
+        let amelie = detect_title_language("Le Fabuleux Destin d'Amélie Poulain").unwrap();
+        assert_eq!(amelie.code, "fr");
+        assert_eq!(code("Das Leben der Anderen").as_deref(), Some("de"));
+        assert_eq!(code("The Lord of the Rings").as_deref(), Some("en"));
+    }
+
+    #[test]
+    fn test_weak_latin_signals_have_low_confidence() {
+        assert_eq!(code("Heat"), None);
+        let die_hard = detect_title_language("Die Hard").unwrap();
+        assert!(die_hard.confidence < 0.5);
+    }
+
+    #[test]
+    fn test_normalize_language() {
+        assert_eq!(normalize_language("English"), Some("en"));
+        assert_eq!(normalize_language("chs"), Some("zh"));
+        assert_eq!(normalize_language("hi"), None);
+    }
+}
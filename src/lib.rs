@@ -19,16 +19,62 @@
 //!
 //! let files = scanner::scan_directory(Path::new("/downloads"), &Default::default()).unwrap();
 //! for f in &files {
-//!     let parsed = parser::parse_media_file(f);
+//!     let parsed = parser::parse_media_file(f, &Default::default());
 //!     println!("{}: {} ({:?})", f.full_name(), parsed.title, parsed.media_type);
 //! }
 //! ```
+//!
+//! [`Organizer`] drives the whole pipeline — scan, plan, apply — the way
+//! the `plan` and `organize` commands do (see [`api`]).
 
+pub mod anime;
+pub mod api;
+pub mod artwork;
+pub mod backfill;
+pub mod chaos;
+pub mod collections;
 pub mod config;
+pub mod database;
+pub mod doctor;
+pub mod double_feature;
+pub mod edition;
 pub mod enricher;
+pub mod error;
+pub mod extras;
+pub mod hash;
+pub mod health;
+pub mod home_video;
+pub mod http;
+pub mod identify;
+pub mod language;
 pub mod models;
+pub mod nfo;
+pub mod notify;
 pub mod organizer;
+pub mod pacing;
+pub mod parse_trace;
 pub mod parser;
+pub mod part;
+pub mod probe;
+pub mod profile;
+pub mod progress;
+pub mod providers;
+pub mod quality;
+pub mod release_group;
 pub mod scanner;
+pub mod schedule;
+pub mod schema;
+pub mod scoring;
+pub mod service;
+pub mod staging;
 pub mod subtitles;
+pub mod survey;
+pub mod title_strategy;
+pub mod titles;
+pub mod transliterate;
 pub mod utils;
+pub mod view;
+
+pub use api::{OrganizePlan, Organizer};
+pub use error::{Error, Result};
+pub use progress::{CancelToken, ProgressEvent};
@@ -1,12 +1,13 @@
 //! CLI entry point for plex-media-organizer.
 
-use clap::Parser;
+use std::process::ExitCode;
+
 use tracing_subscriber::EnvFilter;
 
 mod cli;
 
-fn main() -> anyhow::Result<()> {
-    let args = cli::Cli::parse();
+fn main() -> anyhow::Result<ExitCode> {
+    let args = cli::Cli::parse_with_defaults();
 
     // Configure tracing based on verbosity
     let filter = match args.verbose {
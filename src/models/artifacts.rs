@@ -0,0 +1,205 @@
+//! What a run plans and records on disk: organize actions, saved plans,
+//! and the undo manifest of each run.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use super::MediaType;
+
+// ── Organize Action ────────────────────────────────────────────────────────
+
+/// A planned file operation.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OrganizeAction {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    pub strategy: String,
+    pub media_type: MediaType,
+    /// Primary title.
+    pub title: String,
+    #[serde(default, skip_serializing_if = "TitleVariants::is_empty")]
+    pub titles: TitleVariants,
+    #[serde(default)]
+    pub year: Option<i32>,
+    /// Canonical quality from [`crate::quality::Quality`] (e.g., "1080p BluRay H.264").
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub quality: String,
+    pub confidence: f64,
+    /// TMDb id when the provider confirmed the title.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tmdb_id: Option<u64>,
+    /// "Title (Year)" of the second movie in a double-feature file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub second_feature: Option<String>,
+    /// Destination of the video this file travels with (a subtitle or
+    /// artwork); undo restores them together.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub companion_of: Option<PathBuf>,
+    /// Source state captured when the plan was saved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<SourceFingerprint>,
+    /// Lower-quality copies of the movie this file takes the place of
+    /// (`organize.upgrade`); they are moved to the recycle directory first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub replaces: Vec<ReplacedCopy>,
+}
+
+/// A copy of a movie already in the library that an upgrade replaces.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplacedCopy {
+    pub path: PathBuf,
+    /// Its canonical quality, as compared.
+    pub quality: String,
+}
+
+/// Source file state recorded at plan time and re-checked before applying.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceFingerprint {
+    pub size: u64,
+    /// Modification time in seconds since the Unix epoch.
+    pub modified: Option<i64>,
+    /// Quick head/tail content hash (see `hash::quick_hash`).
+    pub quick_hash: Option<String>,
+}
+
+/// A reviewed plan saved to disk for later application.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedPlan {
+    pub version: u32,
+    pub created_at: String,
+    pub dest_root: PathBuf,
+    pub actions: Vec<OrganizeAction>,
+}
+
+impl SavedPlan {
+    /// Current saved-plan format version.
+    pub const VERSION: u32 = 1;
+
+    pub fn new(dest_root: PathBuf, actions: Vec<OrganizeAction>) -> Self {
+        Self {
+            version: Self::VERSION,
+            created_at: chrono::Utc::now().to_rfc3339(),
+            dest_root,
+            actions,
+        }
+    }
+}
+
+// ── Undo ───────────────────────────────────────────────────────────────────
+
+/// A single reversible file operation.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UndoEntry {
+    pub source: String,
+    pub destination: String,
+    pub strategy: String,
+    pub timestamp: String,
+    pub title: String,
+    #[serde(default, skip_serializing_if = "TitleVariants::is_empty")]
+    pub titles: TitleVariants,
+    pub media_type: String,
+    #[serde(default)]
+    pub year: Option<i32>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub quality: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tmdb_id: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub second_feature: Option<String>,
+    /// Destination of the video this file was organized with; the video
+    /// and its companions are undone as one unit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub companion_of: Option<String>,
+    /// Paranoid-mode result: did the destination match the source?
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verified: Option<bool>,
+    /// Full content hash of a copied file, matched before and after the
+    /// copy (`--verify`; see `hash::content_hash`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+    /// Match confidence (0-100) the file was planned with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+}
+
+/// Paranoid-mode totals for one run, including what verification cost.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VerificationSummary {
+    pub verified: u32,
+    /// Operations whose destination did not match the source.
+    pub mismatched: u32,
+    /// Time spent fingerprinting sources and destinations.
+    pub hash_ms: u64,
+    /// Wall time of the whole run.
+    pub total_ms: u64,
+}
+
+impl VerificationSummary {
+    /// Share of the run spent on verification, in percent.
+    pub fn overhead_percent(&self) -> f64 {
+        if self.total_ms == 0 {
+            0.0
+        } else {
+            self.hash_ms as f64 * 100.0 / self.total_ms as f64
+        }
+    }
+}
+
+/// Size and wall time of one run, for throughput estimates.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunStats {
+    pub files: u64,
+    pub bytes: u64,
+    pub duration_ms: u64,
+    /// Planned actions left unstarted when the run's time budget ran out;
+    /// they are the tail of the plan.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub unstarted: u64,
+}
+
+fn is_zero(n: &u64) -> bool {
+    *n == 0
+}
+
+/// Collection of undo entries for a single organize run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UndoManifest {
+    pub version: u32,
+    pub entries: Vec<UndoEntry>,
+    pub created_at: String,
+    pub description: String,
+    /// Present when the run was executed in paranoid mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verification: Option<VerificationSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<RunStats>,
+    /// Leftovers of source folders the run removed, moved aside under the
+    /// undo directory (`--cleanup-source`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cleaned: Vec<UndoEntry>,
+    /// Lower-quality copies upgrades replaced, moved to the recycle
+    /// directory (`--upgrade`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub replaced: Vec<UndoEntry>,
+}
+
+impl UndoManifest {
+    /// Current run-report format version.
+    pub const VERSION: u32 = 1;
+}
+
+impl Default for UndoManifest {
+    fn default() -> Self {
+        Self {
+            version: Self::VERSION,
+            entries: Vec::new(),
+            created_at: String::new(),
+            description: String::new(),
+            verification: None,
+            stats: None,
+            cleaned: Vec::new(),
+            replaced: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,426 @@
+//! Core data models for plex-media-organizer.
+
+mod artifacts;
+
+pub use artifacts::{
+    OrganizeAction, ReplacedCopy, RunStats, SavedPlan, SourceFingerprint, UndoEntry, UndoManifest,
+    VerificationSummary,
+};
+
+use std::fmt;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::probe::MediaMetadata;
+use crate::providers::MovieCandidate;
+use crate::quality::ReleaseAttributes;
+use crate::scanner::DirOverrides;
+use crate::title_strategy::TitleStrategy;
+
+// ── Media Type ─────────────────────────────────────────────────────────────
+
+/// Detected media type for a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaType {
+    Movie,
+    Tv,
+    Music,
+    #[default]
+    Unknown,
+}
+
+impl fmt::Display for MediaType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Movie => write!(f, "movie"),
+            Self::Tv => write!(f, "tv"),
+            Self::Music => write!(f, "music"),
+            Self::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+impl std::str::FromStr for MediaType {
+    type Err = std::convert::Infallible;
+
+    /// Parse the [`Display`](fmt::Display) form; anything else is `Unknown`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "movie" => Self::Movie,
+            "tv" => Self::Tv,
+            "music" => Self::Music,
+            _ => Self::Unknown,
+        })
+    }
+}
+
+// ── Discovered File ────────────────────────────────────────────────────────
+
+/// A media file discovered on disk by the scanner.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MediaFile {
+    pub source_path: PathBuf,
+    /// Filename stem (no extension).
+    pub filename: String,
+    /// Extension including dot, lowercase (e.g., ".mkv").
+    pub extension: String,
+    /// Initial type guess from extension.
+    pub detected_type: MediaType,
+    pub size_bytes: u64,
+    /// Immediate parent directory name.
+    pub parent_dir: String,
+    /// Last modification time — for a download, roughly when it arrived.
+    pub modified: Option<SystemTime>,
+    /// Name of the top-level folder this file is the main video of, in
+    /// one-movie-per-folder scans.
+    pub unit_name: Option<String>,
+    /// The unit's other videos, which travel with this one as its extras.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unit_extras: Vec<PathBuf>,
+    /// `.plexorg.toml` overrides of its folder (see `scanner`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overrides: Option<DirOverrides>,
+}
+
+impl MediaFile {
+    /// Full filename with extension.
+    pub fn full_name(&self) -> String {
+        format!("{}{}", self.filename, self.extension)
+    }
+}
+
+// ── Parsed Metadata ────────────────────────────────────────────────────────
+
+/// Metadata extracted from a filename via hunch or music regex.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParsedMedia {
+    pub title: String,
+    pub year: Option<i32>,
+    pub season: Option<i32>,
+    pub episode: Option<i32>,
+    pub episode_end: Option<i32>,
+    pub episode_title: Option<String>,
+    pub media_type: MediaType,
+    pub release_group: Option<String>,
+    pub quality: String,
+    pub source_tag: Option<String>,
+    /// Audio/release language tag from the filename (e.g., "French").
+    pub language: Option<String>,
+    /// Language of the title text itself, detected from script and words.
+    pub title_language: Option<LanguageInfo>,
+    /// Original and English parts of a title naming the film twice;
+    /// `title` stays the whole.
+    #[serde(skip_serializing_if = "TitleVariants::is_empty")]
+    pub titles: TitleVariants,
+    pub confidence: f64,
+    pub raw_filename: String,
+    /// Local date the file was last modified; nothing released after it
+    /// can be what the file contains.
+    pub file_date: Option<NaiveDate>,
+    /// Named like a phone or camera recording (see `home_video`).
+    pub home_video: bool,
+    /// Metadata file beside the video whose ids, title, or year were
+    /// taken (see `nfo`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nfo: Option<PathBuf>,
+    /// Ids embedded in the name by another organizer.
+    #[serde(skip_serializing_if = "ExternalIds::is_empty")]
+    pub ids: ExternalIds,
+    /// Anniversary, remaster, or tagged edition named in the filename.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub edition: Option<Edition>,
+    /// Part of a movie split over several files (`CD2` → 2; see `part`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub part: Option<u32>,
+    /// What a fansub anime name says besides the title (see `anime`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anime: Option<AnimeRelease>,
+    /// Stream metadata read with `scan.probe` (see `probe`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media: Option<MediaMetadata>,
+    /// 3D layout, HDR formats, and Atmos named in the filename.
+    #[serde(skip_serializing_if = "ReleaseAttributes::is_empty")]
+    pub attributes: ReleaseAttributes,
+    /// Which extraction the title came from (see `title_strategy`).
+    #[serde(skip_serializing_if = "TitleStrategy::is_hunch")]
+    pub title_strategy: TitleStrategy,
+    /// The name gave no year; `year` is that of the matched TMDb movie or
+    /// AniList entry.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub year_from_match: bool,
+    /// Naming template from a `.plexorg.toml`, used instead of
+    /// `organize.title_template`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title_template: Option<String>,
+    /// Other videos of its one-movie-per-folder unit (see
+    /// `MediaFile::unit_extras`).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unit_extras: Vec<PathBuf>,
+    // Music-specific (placeholder regex)
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub track_number: Option<u32>,
+    pub track_title: Option<String>,
+}
+
+/// Database ids other tools embed in names: Radarr's `{imdb-tt0133093}`
+/// and `{tmdb-603}`, Jellyfin and FileBot's `[tmdbid-603]`, Sonarr's
+/// `{tvdb-81189}`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExternalIds {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tmdb: Option<u64>,
+    /// IMDb id including its `tt` prefix.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub imdb: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tvdb: Option<u64>,
+}
+
+impl ExternalIds {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Fansub release details of an anime file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnimeRelease {
+    /// Episode counted from the start of the series rather than of a
+    /// season (`One Piece - 1071`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub absolute_episode: Option<i32>,
+    /// Revision of a re-released episode (`05v2` → 2).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<u32>,
+    /// CRC32 of the file as the name gives it, uppercase hex.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crc32: Option<String>,
+    /// AniList entry the file was identified as.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anilist_id: Option<u64>,
+}
+
+/// A re-release named in the filename (see `edition`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Edition {
+    /// `40th Anniversary`, `Remastered`, or a tagged name as written.
+    pub name: String,
+    /// Year of the re-release, when the name gives it besides the original.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub year: Option<i32>,
+}
+
+/// The names a title goes by besides its primary one (see `titles`).
+///
+/// `寄生虫.Parasite.2019` gives the original `寄生虫` and the English
+/// `Parasite`; a TMDb match adds what the filename lacks, and a Latin
+/// filename title unlike both (`Gisaengchung`) is kept as the romanized one.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TitleVariants {
+    /// Title in its original language and script.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original: Option<String>,
+    /// Original title written in Latin letters.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub romanized: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub english: Option<String>,
+}
+
+impl TitleVariants {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Writing system of a piece of text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Script {
+    Latin,
+    Han,
+    Kana,
+    Hangul,
+    Cyrillic,
+    Thai,
+    Arabic,
+    Hebrew,
+    Greek,
+    Devanagari,
+}
+
+/// Detected language of a title.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LanguageInfo {
+    /// ISO 639-1 code, e.g. "ko".
+    pub code: String,
+    pub script: Script,
+    /// 0.0–1.0; script-based detections are near 1, stopword guesses lower.
+    pub confidence: f64,
+}
+
+// ── Enriched Metadata ──────────────────────────────────────────────────────
+
+/// Enriched movie metadata (from DB lookup — future phase).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Movie {
+    pub title: String,
+    pub year: Option<i32>,
+    pub tmdb_id: Option<u64>,
+    pub original_title: Option<String>,
+    pub confidence: f64,
+}
+
+/// Enriched TV episode metadata.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TvEpisode {
+    pub show_title: String,
+    pub season: i32,
+    pub episode: i32,
+    pub episode_end: Option<i32>,
+    pub episode_title: Option<String>,
+    pub year: Option<i32>,
+    pub tmdb_id: Option<u64>,
+    pub confidence: f64,
+}
+
+/// Enriched music track metadata.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MusicTrack {
+    pub artist: String,
+    pub album: Option<String>,
+    pub track_title: Option<String>,
+    pub track_number: Option<u32>,
+    pub year: Option<i32>,
+    pub confidence: f64,
+}
+
+/// Result of the enrichment pipeline.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnrichedMedia {
+    pub parsed: ParsedMedia,
+    pub movie: Option<Movie>,
+    pub tv_episode: Option<TvEpisode>,
+    pub music_track: Option<MusicTrack>,
+    pub media_type: MediaType,
+    pub confidence: f64,
+    pub needs_review: bool,
+    pub enrichment_source: Option<String>,
+    /// Provider results that fell short of the match threshold, best first;
+    /// kept so a reviewer can pick one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub candidates: Vec<MovieCandidate>,
+    /// Fixed destination relative to the library root, from `plex-org pin`;
+    /// overrides path building.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pinned: Option<PathBuf>,
+    /// The parsed title and year equal those of the confirmed TMDb movie.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub exact_match: bool,
+    /// Below the review threshold, but kept as an exact match
+    /// (`tmdb.exact_match_override`).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub review_exempt: bool,
+    /// The TMDb lookup hit a server error or timeout, so the identity is
+    /// the parsed one (see `Enricher::retry_failed_lookups`).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub lookup_failed: bool,
+    /// The second movie of a double-feature file; `movie` is the first.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub second_feature: Option<Movie>,
+    /// TMDb collection of the movie, looked up with
+    /// `organize.collection_folders`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collection: Option<String>,
+}
+
+impl EnrichedMedia {
+    /// Build from parsed metadata with no enrichment (pass-through).
+    pub fn from_parsed(parsed: ParsedMedia) -> Self {
+        let media_type = parsed.media_type;
+        let confidence = parsed.confidence;
+        Self {
+            parsed,
+            movie: None,
+            tv_episode: None,
+            music_track: None,
+            media_type,
+            confidence,
+            needs_review: false,
+            enrichment_source: None,
+            candidates: Vec::new(),
+            pinned: None,
+            exact_match: false,
+            review_exempt: false,
+            lookup_failed: false,
+            second_feature: None,
+            collection: None,
+        }
+    }
+
+    /// Best available title for display.
+    pub fn best_title(&self) -> &str {
+        if let Some(m) = &self.movie {
+            return &m.title;
+        }
+        if let Some(tv) = &self.tv_episode {
+            return &tv.show_title;
+        }
+        if let Some(music) = &self.music_track {
+            return &music.artist;
+        }
+        &self.parsed.title
+    }
+
+    /// TMDb id of the confirmed movie or show, if any.
+    pub fn tmdb_id(&self) -> Option<u64> {
+        self.movie
+            .as_ref()
+            .and_then(|m| m.tmdb_id)
+            .or_else(|| self.tv_episode.as_ref().and_then(|tv| tv.tmdb_id))
+    }
+
+    /// Ids to name the item by: the TMDb match's, with the IMDb and TVDB
+    /// ids of the source name kept unless it named another TMDb id.
+    pub fn external_ids(&self) -> ExternalIds {
+        let parsed = &self.parsed.ids;
+        let tmdb = self.tmdb_id().or(parsed.tmdb);
+        if parsed.tmdb.is_some() && parsed.tmdb != tmdb {
+            return ExternalIds {
+                tmdb,
+                ..Default::default()
+            };
+        }
+        ExternalIds {
+            tmdb,
+            ..parsed.clone()
+        }
+    }
+
+    /// "Title (Year)" of the second movie of a double feature.
+    pub fn second_feature_label(&self) -> Option<String> {
+        self.second_feature.as_ref().map(|m| match m.year {
+            Some(year) => format!("{} ({year})", m.title),
+            None => m.title.clone(),
+        })
+    }
+
+    /// Best available year (enriched first, then parsed).
+    pub fn best_year(&self) -> Option<i32> {
+        self.movie
+            .as_ref()
+            .and_then(|m| m.year)
+            .or_else(|| self.tv_episode.as_ref().and_then(|tv| tv.year))
+            .or_else(|| self.music_track.as_ref().and_then(|t| t.year))
+            .or(self.parsed.year)
+    }
+}
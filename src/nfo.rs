@@ -0,0 +1,403 @@
+//! Metadata files shipped beside a video, read as parse hints.
+//!
+//! Scene releases carry an `.nfo` that nearly always names the IMDb page,
+//! Kodi and media managers leave `movie.nfo` or `<name>.nfo` XML with ids,
+//! title, and year, and some tools write a `<name>.json`. An id found there
+//! identifies the file outright (the enricher resolves it like an id tag in
+//! the name), and a title and year beat what the filename suggests.
+//!
+//! Looked for, first found wins: `<video stem>.nfo`, `<video stem>.json`,
+//! `movie.nfo`, and any lone `.nfo` in a folder holding just this video.
+//!
+//! The other way round, `organize --write-nfo` leaves a Kodi-style
+//! `movie.nfo` beside each organized movie ([`write_movie_nfo`]), for Kodi,
+//! Jellyfin, Emby, and Plex's XBMCnfo agent. Undo removes it again.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde_json::Value;
+use tracing::debug;
+
+use crate::models::{ExternalIds, UndoEntry};
+use crate::providers::MovieDetails;
+use crate::scanner::{dotted_extension, VIDEO_EXTENSIONS};
+use crate::subtitles::only_video_in;
+
+/// Marks the `movie.nfo` files written here, so one that came with the
+/// movie is never overwritten or removed.
+const GENERATOR: &str = "<!-- written by plex-org -->";
+
+/// Larger files are not metadata.
+const MAX_SIZE: u64 = 1024 * 1024;
+
+static IMDB_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\b(tt\d{7,8})\b").unwrap());
+
+/// `<tmdbid>603</tmdbid>`, `<uniqueid type="tmdb">603</uniqueid>`, or a
+/// themoviedb.org link.
+static TMDB_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"(?i)<tmdbid>\s*(\d+)\s*<|<uniqueid[^>]*type="tmdb"[^>]*>\s*(\d+)\s*<|themoviedb\.org/movie/(\d+)"#,
+    )
+    .unwrap()
+});
+
+static TVDB_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)<tvdbid>\s*(\d+)\s*<|<uniqueid[^>]*type="tvdb"[^>]*>\s*(\d+)\s*<"#).unwrap()
+});
+
+static TITLE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<title>\s*([^<]+?)\s*</title>").unwrap());
+
+static YEAR_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)<year>\s*(\d{4})\s*<|<premiered>\s*(\d{4})-").unwrap());
+
+/// What a metadata file says about a video.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Hints {
+    /// The file the hints came from.
+    pub path: PathBuf,
+    pub ids: ExternalIds,
+    pub title: Option<String>,
+    pub year: Option<i32>,
+}
+
+impl Hints {
+    fn is_empty(&self) -> bool {
+        self.ids.is_empty() && self.title.is_none()
+    }
+}
+
+/// Hints for `video` from the first metadata file beside it that has any.
+pub fn find_hints(video: &Path) -> Option<Hints> {
+    let dir = video.parent()?;
+    let stem = video.file_stem()?.to_string_lossy();
+    let mut candidates = vec![
+        dir.join(format!("{stem}.nfo")),
+        dir.join(format!("{stem}.json")),
+        dir.join("movie.nfo"),
+    ];
+    if only_video_in(dir) {
+        let mut lone: Vec<PathBuf> = fs::read_dir(dir)
+            .ok()?
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|e| e.eq_ignore_ascii_case("nfo")))
+            .collect();
+        if lone.len() == 1 {
+            candidates.append(&mut lone);
+        }
+    }
+    candidates.into_iter().find_map(|path| {
+        let hints = read_hints(&path)?;
+        debug!(
+            "hints for {} from {}: {:?}",
+            video.display(),
+            path.display(),
+            hints
+        );
+        Some(hints)
+    })
+}
+
+/// Read `path` as a metadata file; `None` if missing, too large, or saying
+/// nothing useful.
+fn read_hints(path: &Path) -> Option<Hints> {
+    if fs::metadata(path).ok()?.len() > MAX_SIZE {
+        return None;
+    }
+    // Scene .nfo files are CP437 art around ASCII; the ids survive.
+    let text = String::from_utf8_lossy(&fs::read(path).ok()?).into_owned();
+    let mut hints = match path.extension().and_then(|e| e.to_str()) {
+        Some("json") => parse_json(&serde_json::from_str(&text).ok()?),
+        _ => parse_nfo(&text),
+    };
+    hints.path = path.to_path_buf();
+    (!hints.is_empty()).then_some(hints)
+}
+
+/// Ids, title, and year from Kodi XML or a free-text scene `.nfo`.
+pub fn parse_nfo(text: &str) -> Hints {
+    let first = |re: &Regex| {
+        re.captures(text)
+            .and_then(|c| c.iter().skip(1).flatten().next().map(|m| m.as_str()))
+    };
+    Hints {
+        ids: ExternalIds {
+            tmdb: first(&TMDB_RE).and_then(|id| id.parse().ok()),
+            imdb: first(&IMDB_RE).map(String::from),
+            tvdb: first(&TVDB_RE).and_then(|id| id.parse().ok()),
+        },
+        title: first(&TITLE_RE).map(unescape_xml).filter(|t| !t.is_empty()),
+        year: first(&YEAR_RE).and_then(|y| y.parse().ok()),
+        ..Default::default()
+    }
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Ids, title, and year from a JSON document, at any depth: flat
+/// (`{"imdb_id": "tt0133093"}`) or nested like Trakt's
+/// (`{"movie": {"title": "…", "ids": {"tmdb": 603}}}`).
+pub fn parse_json(value: &Value) -> Hints {
+    let mut hints = Hints::default();
+    collect_json(value, &mut hints);
+    hints
+}
+
+fn collect_json(value: &Value, hints: &mut Hints) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let key = key.to_lowercase().replace(['_', '-'], "");
+                let number = value
+                    .as_u64()
+                    .or_else(|| value.as_str().and_then(|s| s.parse().ok()));
+                match key.as_str() {
+                    "imdb" | "imdbid" => {
+                        let id = value.as_str().filter(|s| IMDB_RE.is_match(s));
+                        hints.ids.imdb = hints.ids.imdb.take().or(id.map(String::from));
+                    }
+                    "tmdb" | "tmdbid" => hints.ids.tmdb = hints.ids.tmdb.or(number),
+                    "tvdb" | "tvdbid" => hints.ids.tvdb = hints.ids.tvdb.or(number),
+                    "title" if hints.title.is_none() => {
+                        hints.title = value.as_str().map(String::from);
+                    }
+                    "year" if hints.year.is_none() => {
+                        hints.year = number.and_then(|y| i32::try_from(y).ok());
+                    }
+                    _ => collect_json(value, hints),
+                }
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|v| collect_json(v, hints)),
+        _ => {}
+    }
+}
+
+/// Whether `entry` placed a movie's main video, which gets a `movie.nfo`.
+pub fn wants_nfo(entry: &UndoEntry) -> bool {
+    entry.media_type == "movie"
+        && entry.companion_of.is_none()
+        && VIDEO_EXTENSIONS.contains(&dotted_extension(Path::new(&entry.destination)).as_str())
+}
+
+/// Kodi `movie.nfo` XML for an organized movie, with provider `details`
+/// when known.
+pub fn movie_nfo(entry: &UndoEntry, details: Option<&MovieDetails>) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n");
+    xml.push_str(GENERATOR);
+    xml.push_str("\n<movie>\n");
+    let mut element = |name: &str, value: &str| {
+        let _ = writeln!(xml, "  <{name}>{}</{name}>", escape_xml(value));
+    };
+    element("title", &entry.title);
+    let original = details
+        .and_then(|d| d.original_title.as_deref())
+        .or(entry.titles.original.as_deref());
+    if let Some(original) = original {
+        element("originaltitle", original);
+    }
+    if let Some(year) = entry.year.or(details.and_then(|d| d.year)) {
+        element("year", &year.to_string());
+    }
+    if let Some(d) = details {
+        for (name, value) in [
+            ("plot", d.plot.clone()),
+            ("runtime", d.runtime.map(|r| r.to_string())),
+            ("mpaa", d.certification.clone()),
+        ] {
+            if let Some(value) = value {
+                element(name, &value);
+            }
+        }
+        for genre in &d.genres {
+            element("genre", genre);
+        }
+    }
+    if let Some(collection) = details.and_then(|d| d.collection.as_deref()) {
+        let _ = writeln!(
+            xml,
+            "  <set>\n    <name>{}</name>\n  </set>",
+            escape_xml(collection)
+        );
+    }
+    if let Some(id) = entry.tmdb_id {
+        let _ = writeln!(
+            xml,
+            "  <uniqueid type=\"tmdb\" default=\"true\">{id}</uniqueid>"
+        );
+    }
+    if let Some(imdb) = details.and_then(|d| d.imdb_id.as_deref()) {
+        let _ = writeln!(
+            xml,
+            "  <uniqueid type=\"imdb\">{}</uniqueid>",
+            escape_xml(imdb)
+        );
+    }
+    for (order, actor) in details.iter().flat_map(|d| &d.cast).enumerate() {
+        let _ = writeln!(
+            xml,
+            "  <actor>\n    <name>{}</name>\n    <role>{}</role>\n    <order>{order}</order>\n  </actor>",
+            escape_xml(&actor.name),
+            escape_xml(&actor.role)
+        );
+    }
+    xml.push_str("</movie>\n");
+    xml
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// What [`write_movie_nfo`] did.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NfoWrite {
+    Written(PathBuf),
+    /// A `movie.nfo` that came with the movie is there and was kept.
+    Kept(PathBuf),
+}
+
+/// Write `movie.nfo` beside the movie `entry` placed, replacing one written
+/// earlier by this tool.
+pub fn write_movie_nfo(entry: &UndoEntry, details: Option<&MovieDetails>) -> io::Result<NfoWrite> {
+    let dir = Path::new(&entry.destination)
+        .parent()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no parent folder"))?;
+    let path = dir.join("movie.nfo");
+    if path.exists() && !is_generated(&path) {
+        return Ok(NfoWrite::Kept(path));
+    }
+    fs::write(&path, movie_nfo(entry, details))?;
+    Ok(NfoWrite::Written(path))
+}
+
+/// Remove the `movie.nfo` written beside `video` once no video is left in
+/// its folder, as when the movie was undone.
+pub fn remove_generated(video: &Path) {
+    let Some(dir) = video.parent() else {
+        return;
+    };
+    let path = dir.join("movie.nfo");
+    let videos_left = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .any(|e| VIDEO_EXTENSIONS.contains(&dotted_extension(&e.path()).as_str()));
+    if !videos_left && is_generated(&path) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+fn is_generated(path: &Path) -> bool {
+    fs::read_to_string(path).is_ok_and(|text| text.contains(GENERATOR))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_nfo_kodi_and_scene() {
+        let kodi = r#"<?xml version="1.0"?>
+            <movie>
+              <title>Amélie &amp; Co</title>
+              <year>2001</year>
+              <uniqueid type="imdb" default="true">tt0211915</uniqueid>
+              <uniqueid type="tmdb">194</uniqueid>
+            </movie>"#;
+        let hints = parse_nfo(kodi);
+        assert_eq!(hints.title.as_deref(), Some("Amélie & Co"));
+        assert_eq!(hints.year, Some(2001));
+        assert_eq!(hints.ids.imdb.as_deref(), Some("tt0211915"));
+        assert_eq!(hints.ids.tmdb, Some(194));
+
+        let scene = "░▒▓ GROUP ▓▒░\n  iMDB......: https://www.imdb.com/title/tt0133093/\n";
+        let hints = parse_nfo(scene);
+        assert_eq!(hints.ids.imdb.as_deref(), Some("tt0133093"));
+        assert_eq!(hints.title, None);
+
+        let json =
+            serde_json::json!({"movie": {"title": "Heat", "year": 1995, "ids": {"tmdb": 949}}});
+        let hints = parse_json(&json);
+        assert_eq!(hints.title.as_deref(), Some("Heat"));
+        assert_eq!(hints.year, Some(1995));
+        assert_eq!(hints.ids.tmdb, Some(949));
+    }
+
+    #[test]
+    fn test_find_hints_beside_the_video() {
+        let tmp = tempfile::tempdir().unwrap();
+        let video = tmp.path().join("the.matrix.1999.1080p-grp.mkv");
+        fs::write(&video, b"video").unwrap();
+        assert_eq!(find_hints(&video), None);
+
+        fs::write(
+            tmp.path().join("grp-matrix.nfo"),
+            "imdb.com/title/tt0133093",
+        )
+        .unwrap();
+        let hints = find_hints(&video).unwrap();
+        assert_eq!(hints.ids.imdb.as_deref(), Some("tt0133093"));
+
+        fs::write(tmp.path().join("other.mkv"), b"video").unwrap();
+        assert_eq!(find_hints(&video), None);
+    }
+
+    #[test]
+    fn test_written_nfo_reads_back_and_is_undone() {
+        let tmp = tempfile::tempdir().unwrap();
+        let video = tmp.path().join("Heat (1995)/Heat (1995).mkv");
+        fs::create_dir_all(video.parent().unwrap()).unwrap();
+        fs::write(&video, b"video").unwrap();
+        let entry = UndoEntry {
+            destination: video.to_string_lossy().to_string(),
+            strategy: "move".to_string(),
+            title: "Heat".to_string(),
+            media_type: "movie".to_string(),
+            year: Some(1995),
+            tmdb_id: Some(949),
+            ..Default::default()
+        };
+        let details = MovieDetails {
+            plot: Some("Cops & robbers <in LA>.".to_string()),
+            imdb_id: Some("tt0113277".to_string()),
+            genres: vec!["Crime".to_string()],
+            ..Default::default()
+        };
+        assert!(wants_nfo(&entry));
+        let NfoWrite::Written(path) = write_movie_nfo(&entry, Some(&details)).unwrap() else {
+            panic!("movie.nfo not written");
+        };
+        let xml = fs::read_to_string(&path).unwrap();
+        assert!(xml.contains("<plot>Cops &amp; robbers &lt;in LA&gt;.</plot>"));
+        let hints = find_hints(&video).unwrap();
+        assert_eq!(hints.title.as_deref(), Some("Heat"));
+        assert_eq!(hints.year, Some(1995));
+        assert_eq!(hints.ids.tmdb, Some(949));
+        assert_eq!(hints.ids.imdb.as_deref(), Some("tt0113277"));
+
+        remove_generated(&video);
+        assert!(path.exists());
+        fs::remove_file(&video).unwrap();
+        remove_generated(&video);
+        assert!(!path.exists());
+
+        fs::write(&path, "<movie><title>Curated</title></movie>").unwrap();
+        assert_eq!(write_movie_nfo(&entry, None).unwrap(), NfoWrite::Kept(path));
+    }
+}
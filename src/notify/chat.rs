@@ -0,0 +1,63 @@
+//! Discord and Slack messages for a [`RunReport`].
+
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use super::RunReport;
+
+/// Files held for review that a message names; the rest are counted.
+const LISTED: usize = 10;
+
+/// Longest message Discord accepts, in characters.
+const DISCORD_LIMIT: usize = 2000;
+
+/// The message: what the run did, then the files held for review.
+pub(super) fn text(report: &RunReport) -> String {
+    let mut lines = Vec::new();
+    match &report.error {
+        Some(error) => lines.push(format!(
+            "❌ Organizing {} files from {} failed: {error}",
+            report.failed, report.source
+        )),
+        None => lines.push(format!(
+            "✅ Organized {} files from {} into {}",
+            report.organized, report.source, report.destination
+        )),
+    }
+    if report.error.is_none() && report.skipped > 0 {
+        lines.push(format!("{} skipped", report.skipped));
+    }
+    if !report.review.is_empty() {
+        lines.push(format!(
+            "⏸️ {} files need review (`plex-org queue list`):",
+            report.review.len()
+        ));
+        for item in report.review.iter().take(LISTED) {
+            let name = Path::new(&item.path)
+                .file_name()
+                .map_or(item.path.clone(), |n| n.to_string_lossy().into_owned());
+            lines.push(format!(
+                "• {name} — {} ({:.0}%)",
+                item.title, item.confidence
+            ));
+        }
+        if report.review.len() > LISTED {
+            lines.push(format!("…and {} more", report.review.len() - LISTED));
+        }
+    }
+    lines.join("\n")
+}
+
+pub(super) fn discord(report: &RunReport) -> Value {
+    let mut content = text(report);
+    if content.chars().count() > DISCORD_LIMIT {
+        content = content.chars().take(DISCORD_LIMIT - 1).collect();
+        content.push('…');
+    }
+    json!({ "username": "plex-org", "content": content })
+}
+
+pub(super) fn slack(report: &RunReport) -> Value {
+    json!({ "text": text(report) })
+}
@@ -0,0 +1,159 @@
+//! Notifications after organize and watch runs (`[[notify.hooks]]`).
+//!
+//! Each hook is POSTed a summary of the run: how many files were
+//! organized, skipped, or failed, and which ones were held for review. A
+//! `json` hook gets the [`RunReport`] itself, for scripts and automation
+//! tools; `discord` and `slack` hooks get a chat message made from it. A
+//! hook that cannot be reached is reported, never fatal: the files are
+//! organized either way.
+
+mod chat;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::config::{HookFormat, NotifyHook};
+use crate::http;
+
+/// What one run did, as sent to the hooks.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RunReport {
+    /// Folder the files came from.
+    pub source: String,
+    /// Library root they went to.
+    pub destination: String,
+    /// Files placed in the library.
+    pub organized: usize,
+    /// Planned files left alone: already at their destination, gone from
+    /// the source, or not reached before the run's time ran out.
+    pub skipped: usize,
+    /// Planned files not organized because the run failed.
+    pub failed: usize,
+    /// Why the run failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Files below the review threshold, queued for `queue accept`.
+    pub review: Vec<ReviewItem>,
+}
+
+/// A file held for review.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReviewItem {
+    pub path: String,
+    /// The best guess at what it is.
+    pub title: String,
+    pub confidence: f64,
+}
+
+impl RunReport {
+    /// Nothing to tell, as after a watch pass that found nothing new.
+    pub fn is_empty(&self) -> bool {
+        self.organized + self.skipped + self.failed == 0 && self.review.is_empty()
+    }
+}
+
+/// Files held for review that a `json` payload lists. A first run over a
+/// big backlog can hold thousands; `review_total` still counts them all.
+const MAX_REVIEW_ITEMS: usize = 100;
+
+/// The request body of a `format` hook.
+pub fn payload(report: &RunReport, format: HookFormat) -> Value {
+    match format {
+        HookFormat::Json => {
+            let mut value = json!(report);
+            let listed = report.review.len().min(MAX_REVIEW_ITEMS);
+            value["review"] = json!(report.review[..listed]);
+            value["review_total"] = json!(report.review.len());
+            value
+        }
+        HookFormat::Discord => chat::discord(report),
+        HookFormat::Slack => chat::slack(report),
+    }
+}
+
+/// POST `report` to `hook`.
+pub fn send(hook: &NotifyHook, report: &RunReport) -> Result<()> {
+    let body = payload(report, hook.format).to_string();
+    http::post_json(&hook.url, &body, &[])
+        .with_context(|| format!("Notification to {} failed", host(&hook.url)))?;
+    Ok(())
+}
+
+/// Send `report` to every hook, unless it is empty. Returns the errors of
+/// the hooks that failed.
+pub fn notify(hooks: &[NotifyHook], report: &RunReport) -> Vec<anyhow::Error> {
+    if report.is_empty() {
+        return Vec::new();
+    }
+    hooks
+        .iter()
+        .filter_map(|hook| send(hook, report).err())
+        .collect()
+}
+
+/// The host of a hook URL. Webhook URLs carry their secret in the path,
+/// so errors name only the host.
+fn host(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    rest.split(['/', '?']).next().unwrap_or(rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payloads() {
+        assert!(RunReport::default().is_empty());
+        let report = RunReport {
+            source: "/downloads".to_string(),
+            destination: "/media/plex".to_string(),
+            organized: 2,
+            skipped: 1,
+            review: (0..12)
+                .map(|n| ReviewItem {
+                    path: format!("/downloads/Unknown.{n}.mkv"),
+                    title: "Unknown".to_string(),
+                    confidence: 40.0,
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        let generic = payload(&report, HookFormat::Json);
+        assert_eq!(generic["organized"], 2);
+        assert_eq!(generic["review"][11]["path"], "/downloads/Unknown.11.mkv");
+        assert!(generic.get("error").is_none());
+        let backlog = RunReport {
+            review: report.review.iter().cycle().take(240).cloned().collect(),
+            ..report.clone()
+        };
+        let capped = payload(&backlog, HookFormat::Json);
+        assert_eq!(capped["review"].as_array().unwrap().len(), MAX_REVIEW_ITEMS);
+        assert_eq!(capped["review_total"], 240);
+
+        let discord = payload(&report, HookFormat::Discord);
+        let content = discord["content"].as_str().unwrap();
+        assert!(content.starts_with("✅ Organized 2 files from /downloads into /media/plex"));
+        assert!(content.contains("1 skipped"));
+        assert!(content.contains("Unknown.0.mkv — Unknown (40%)"));
+        assert!(content.contains("…and 2 more"));
+        let slack = payload(&report, HookFormat::Slack);
+        assert_eq!(slack["text"].as_str(), Some(content));
+
+        let failed = RunReport {
+            failed: 3,
+            error: Some("Not enough space on /media".to_string()),
+            ..report.clone()
+        };
+        assert!(chat::text(&failed).starts_with(
+            "❌ Organizing 3 files from /downloads failed: Not enough space on /media"
+        ));
+
+        assert_eq!(
+            host("https://discord.com/api/webhooks/1/secret"),
+            "discord.com"
+        );
+    }
+}
@@ -0,0 +1,204 @@
+//! Removal of source folders an organize run emptied (`--cleanup-source`).
+//!
+//! Moving a release out of `Heat.1995.1080p.BluRay/` leaves the folder
+//! behind, often holding nothing but the release's `.nfo`, a `.txt`, and
+//! screenshots. With `organize.cleanup_source`, a folder a file was moved
+//! out of is removed once nothing is left in it but files matching
+//! `organize.cleanup_junk`, and so are the folders above it that this
+//! leaves empty, up to but never including the source root. Junk is not
+//! deleted: it is moved aside under the undo directory and listed in the
+//! run's manifest (`cleaned`), so `undo` puts it back with the videos.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use tracing::{info, warn};
+use walkdir::WalkDir;
+
+use super::fsops;
+use crate::config::AppConfig;
+use crate::models::UndoEntry;
+use crate::utils;
+
+/// Source root and junk patterns of a cleanup.
+#[derive(Debug, Clone)]
+pub struct SourceCleanup {
+    /// Folders are removed below this, never it.
+    pub root: PathBuf,
+    /// File name patterns (`*.nfo`) of files not worth keeping a folder for.
+    pub junk: Vec<String>,
+}
+
+impl SourceCleanup {
+    /// Cleanup below `root` when `organize.cleanup_source` is on.
+    pub fn from_config(root: &Path, config: &AppConfig) -> Option<Self> {
+        config.organize.cleanup_source.then(|| Self {
+            root: root.to_path_buf(),
+            junk: config.organize.cleanup_junk.clone(),
+        })
+    }
+
+    fn is_junk(&self, path: &Path) -> bool {
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        self.junk.iter().any(|p| utils::matches_glob(p, name))
+    }
+
+    /// Whether `dir` is strictly below the root.
+    fn covers(&self, dir: &Path) -> bool {
+        dir != self.root && dir.starts_with(&self.root)
+    }
+
+    /// The junk files under `dir` when junk is all it holds; `None` when
+    /// anything else is left.
+    fn leftovers(&self, dir: &Path) -> Option<Vec<PathBuf>> {
+        let mut junk = Vec::new();
+        for entry in WalkDir::new(dir).min_depth(1).sort_by_file_name() {
+            let entry = entry.ok()?;
+            if entry.file_type().is_dir() {
+                continue;
+            }
+            if !self.is_junk(entry.path()) {
+                return None;
+            }
+            junk.push(entry.into_path());
+        }
+        Some(junk)
+    }
+}
+
+/// Remove the folders `entries` moved files out of, once only junk is left
+/// in them, moving the junk under `trash`. Returns the undo entries of the
+/// junk moved. A folder that cannot be cleaned is reported and left.
+pub(super) fn clean_sources(
+    entries: &[UndoEntry],
+    cleanup: &SourceCleanup,
+    trash: &Path,
+    timestamp: &str,
+) -> Vec<UndoEntry> {
+    let mut pending: BTreeSet<PathBuf> = entries
+        .iter()
+        .filter(|e| e.strategy == "move")
+        .filter_map(|e| Path::new(&e.source).parent().map(Path::to_path_buf))
+        .filter(|dir| cleanup.covers(dir))
+        .collect();
+    let mut cleaned = Vec::new();
+    // Deepest first, so a folder is looked at after its emptied subfolders.
+    while let Some(dir) = pending
+        .iter()
+        .max_by_key(|d| d.components().count())
+        .cloned()
+    {
+        pending.remove(&dir);
+        let Some(junk) = cleanup.leftovers(&dir) else {
+            continue;
+        };
+        let mut moved = Vec::new();
+        match discard(&dir, &junk, cleanup, trash, timestamp, &mut moved) {
+            Ok(()) => {
+                info!("Removed emptied source folder {}", dir.display());
+                if let Some(parent) = dir.parent().filter(|p| cleanup.covers(p)) {
+                    pending.insert(parent.to_path_buf());
+                }
+            }
+            Err(e) => warn!("Could not clean up {}: {e}", dir.display()),
+        }
+        cleaned.extend(moved);
+    }
+    cleaned
+}
+
+/// Move `junk` out of `dir` and remove it with its subfolders. Junk moved
+/// before a failure is still recorded in `moved`.
+fn discard(
+    dir: &Path,
+    junk: &[PathBuf],
+    cleanup: &SourceCleanup,
+    trash: &Path,
+    timestamp: &str,
+    moved: &mut Vec<UndoEntry>,
+) -> io::Result<()> {
+    for file in junk {
+        let relative = file.strip_prefix(&cleanup.root).unwrap_or(file);
+        let aside = trash.join(relative);
+        if let Some(parent) = aside.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Often across filesystems: the undo directory is rarely beside
+        // the downloads.
+        fsops::move_file(file, &aside)?;
+        moved.push(UndoEntry {
+            source: file.to_string_lossy().into_owned(),
+            destination: aside.to_string_lossy().into_owned(),
+            strategy: "move".to_string(),
+            timestamp: timestamp.to_string(),
+            ..Default::default()
+        });
+    }
+    for entry in WalkDir::new(dir).contents_first(true) {
+        let entry = entry.map_err(io::Error::other)?;
+        if entry.file_type().is_dir() {
+            fs::remove_dir(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moved_out(source: &Path) -> UndoEntry {
+        UndoEntry {
+            source: source.to_string_lossy().into_owned(),
+            destination: "/lib/Movies/Heat (1995)/Heat (1995).mkv".to_string(),
+            strategy: "move".to_string(),
+            title: "Heat".to_string(),
+            media_type: "movie".to_string(),
+            year: Some(1995),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_emptied_folders_are_removed_with_their_junk() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().join("downloads");
+        let heat = root.join("Heat.1995.1080p");
+        let alien = root.join("Alien.1979");
+        fs::create_dir_all(heat.join("Screens")).unwrap();
+        fs::create_dir_all(&alien).unwrap();
+        fs::write(heat.join("Heat.1995.1080p.nfo"), b"nfo").unwrap();
+        fs::write(heat.join("Screens/01.jpg"), b"jpg").unwrap();
+        fs::write(alien.join("Alien.1979.nfo"), b"nfo").unwrap();
+        fs::write(alien.join("Alien.1979.en.srt"), b"srt").unwrap();
+
+        let cleanup = SourceCleanup {
+            root: root.clone(),
+            junk: vec!["*.nfo".to_string(), "*.jpg".to_string()],
+        };
+        let entries = [
+            moved_out(&heat.join("Heat.1995.1080p.mkv")),
+            moved_out(&alien.join("Alien.1979.mkv")),
+            moved_out(&root.join("Up.2009.mkv")),
+        ];
+        let trash = tmp.path().join("undo/cleanup/1");
+        let cleaned = clean_sources(&entries, &cleanup, &trash, "now");
+
+        assert!(!heat.exists());
+        // A subtitle is not junk: Alien's folder stays, and so does the root.
+        assert!(alien.join("Alien.1979.nfo").exists());
+        assert!(root.is_dir());
+        let aside: Vec<&str> = cleaned.iter().map(|e| e.destination.as_str()).collect();
+        assert_eq!(aside.len(), 2);
+        assert_eq!(
+            fs::read(trash.join("Heat.1995.1080p/Screens/01.jpg")).unwrap(),
+            b"jpg"
+        );
+        assert_eq!(
+            cleaned[0].source,
+            heat.join("Heat.1995.1080p.nfo").to_string_lossy()
+        );
+    }
+}
@@ -0,0 +1,232 @@
+//! How many file operations run at once.
+//!
+//! A fixed run keeps `max_parallel` operations in flight. An adaptive run
+//! starts at `min_parallel` and retunes after every round of completions
+//! (as many completions as the current limit): it grows by one while the
+//! per-operation latency stays near the best seen so far, shrinks by one
+//! when latency climbs well above it, and halves on an error. On a local
+//! disk that settles at the maximum; on a flaky SMB mount it backs off
+//! before timeouts pile up.
+
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::Duration;
+
+use tracing::debug;
+
+/// Latency within this factor of the best seen leaves room to grow.
+const GROW_RATIO: f64 = 1.25;
+
+/// Latency beyond this factor of the best seen means the target is
+/// saturated.
+const BACKOFF_RATIO: f64 = 2.0;
+
+/// Weight of the newest sample in the latency average.
+const SMOOTHING: f64 = 0.3;
+
+/// Latency is measured per MiB, with anything smaller counted as one, so
+/// renames and large copies are comparable.
+const MIB: f64 = 1024.0 * 1024.0;
+
+/// The concurrency limit and the latency history that drives it.
+#[derive(Debug)]
+pub(super) struct AdaptiveLimit {
+    limit: usize,
+    min: usize,
+    max: usize,
+    adaptive: bool,
+    /// Smoothed latency, milliseconds per MiB.
+    latency: Option<f64>,
+    best: Option<f64>,
+    /// Completions since the last adjustment.
+    round: usize,
+    /// Highest limit reached.
+    peak: usize,
+}
+
+impl AdaptiveLimit {
+    /// A limit between `min` and `max` (both at least 1). A fixed limit
+    /// stays at `max`.
+    pub(super) fn new(min: usize, max: usize, adaptive: bool) -> Self {
+        let max = max.max(1);
+        let min = min.clamp(1, max);
+        let limit = if adaptive { min } else { max };
+        Self {
+            limit,
+            min,
+            max,
+            adaptive,
+            latency: None,
+            best: None,
+            round: 0,
+            peak: limit,
+        }
+    }
+
+    pub(super) fn limit(&self) -> usize {
+        self.limit
+    }
+
+    pub(super) fn peak(&self) -> usize {
+        self.peak
+    }
+
+    /// An operation on `bytes` took `elapsed`.
+    pub(super) fn record(&mut self, elapsed: Duration, bytes: u64) {
+        if !self.adaptive {
+            return;
+        }
+        let sample = elapsed.as_secs_f64() * 1000.0 / (bytes as f64 / MIB).max(1.0);
+        let latency = match self.latency {
+            Some(avg) => avg + SMOOTHING * (sample - avg),
+            None => sample,
+        };
+        self.latency = Some(latency);
+        self.round += 1;
+        if self.round < self.limit {
+            return;
+        }
+        self.round = 0;
+
+        let best = self.best.map_or(latency, |b| b.min(latency));
+        self.best = Some(best);
+        if latency > best * BACKOFF_RATIO {
+            self.set_limit(self.limit - 1);
+        } else if latency <= best * GROW_RATIO {
+            self.set_limit(self.limit + 1);
+        }
+    }
+
+    /// An operation failed.
+    pub(super) fn record_error(&mut self) {
+        if self.adaptive {
+            self.round = 0;
+            self.set_limit(self.limit / 2);
+        }
+    }
+
+    fn set_limit(&mut self, limit: usize) {
+        let limit = limit.clamp(self.min, self.max);
+        if limit != self.limit {
+            debug!(
+                "Concurrency {} → {} ({:.1} ms/MiB)",
+                self.limit,
+                limit,
+                self.latency.unwrap_or_default()
+            );
+        }
+        self.limit = limit;
+        self.peak = self.peak.max(limit);
+    }
+}
+
+/// Hands out permits to run an operation, never more than the limit.
+#[derive(Debug)]
+pub(super) struct Limiter {
+    state: Mutex<(AdaptiveLimit, usize)>,
+    freed: Condvar,
+}
+
+impl Limiter {
+    pub(super) fn new(limit: AdaptiveLimit) -> Self {
+        Self {
+            state: Mutex::new((limit, 0)),
+            freed: Condvar::new(),
+        }
+    }
+
+    /// Wait until another operation may start.
+    pub(super) fn acquire(&self) -> Permit<'_> {
+        let mut state = self.lock();
+        while state.1 >= state.0.limit() {
+            state = self.freed.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+        state.1 += 1;
+        Permit {
+            limiter: self,
+            released: false,
+        }
+    }
+
+    /// Highest limit reached so far.
+    pub(super) fn peak(&self) -> usize {
+        self.lock().0.peak()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, (AdaptiveLimit, usize)> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn release(&self, update: impl FnOnce(&mut AdaptiveLimit)) {
+        let mut state = self.lock();
+        state.1 -= 1;
+        update(&mut state.0);
+        self.freed.notify_all();
+    }
+}
+
+/// One running operation. Dropping it without reporting releases the slot
+/// and leaves the limit alone.
+pub(super) struct Permit<'a> {
+    limiter: &'a Limiter,
+    released: bool,
+}
+
+impl Permit<'_> {
+    /// The operation succeeded after `elapsed` on `bytes`.
+    pub(super) fn succeeded(mut self, elapsed: Duration, bytes: u64) {
+        self.released = true;
+        self.limiter.release(|l| l.record(elapsed, bytes));
+    }
+
+    /// The operation failed.
+    pub(super) fn failed(mut self) {
+        self.released = true;
+        self.limiter.release(AdaptiveLimit::record_error);
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        if !self.released {
+            self.limiter.release(|_| {});
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_follows_latency_and_errors() {
+        let fast = Duration::from_millis(10);
+        let mut limit = AdaptiveLimit::new(1, 4, true);
+        assert_eq!(limit.limit(), 1);
+        for _ in 0..20 {
+            limit.record(fast, 0);
+        }
+        assert_eq!(limit.limit(), 4);
+
+        // A saturated mount: latency climbs well past the best seen.
+        for _ in 0..20 {
+            limit.record(fast * 10, 0);
+        }
+        assert_eq!(limit.limit(), 1);
+        assert_eq!(limit.peak(), 4);
+
+        let mut limit = AdaptiveLimit::new(2, 8, true);
+        for _ in 0..40 {
+            limit.record(fast, 0);
+        }
+        assert_eq!(limit.limit(), 8);
+        limit.record_error();
+        assert_eq!(limit.limit(), 4);
+        limit.record_error();
+        limit.record_error();
+        assert_eq!(limit.limit(), 2);
+
+        let mut fixed = AdaptiveLimit::new(1, 3, false);
+        fixed.record_error();
+        assert_eq!(fixed.limit(), 3);
+    }
+}
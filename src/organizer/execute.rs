@@ -0,0 +1,581 @@
+//! Execution of planned file operations.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use tracing::{error, info, warn};
+
+use super::cleanup::{self, SourceCleanup};
+use super::concurrency::{AdaptiveLimit, Limiter};
+use super::fsops::RenameTally;
+use super::journal::Journal;
+use super::provenance;
+use super::space;
+use super::throttle::Throttle;
+use super::transfer::{transfer, Checks, Transfer};
+use super::upgrade;
+use super::verify::Verifier;
+use crate::config::{AppConfig, Provenance};
+use crate::models::{OrganizeAction, RunStats, SavedPlan, UndoEntry, UndoManifest};
+use crate::progress::{self, CancelToken, ProgressEvent};
+
+/// File strategies: what becomes of the source, and what is placed.
+///
+/// `move` renames the source; `copy` and `reflink` (a copy sharing the
+/// source's data blocks where the filesystem can) leave it; `hardlink` adds
+/// a second name for it on the same filesystem, so a seeding torrent keeps
+/// its file; `symlink` points at it.
+pub const STRATEGIES: &[&str] = &["move", "copy", "hardlink", "reflink", "symlink"];
+
+/// Context of the error of a run that failed midway: the manifest of what
+/// it placed before stopping. Downcast the error to it to tell a partly
+/// failed run from one that placed nothing.
+#[derive(Debug)]
+pub struct FailedRun {
+    pub manifest: UndoManifest,
+}
+
+impl fmt::Display for FailedRun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Run stopped after placing {} files",
+            self.manifest.entries.len()
+        )
+    }
+}
+
+/// Settings that shape how planned actions are carried out.
+#[derive(Debug, Clone, Default)]
+pub struct ExecuteOptions {
+    /// Breadcrumb recording each file's original path.
+    pub provenance: Provenance,
+    /// Fingerprint every source before the operation and verify the
+    /// destination afterwards.
+    pub paranoid: bool,
+    /// Hash every byte of each file whose data is copied (a copy, a
+    /// reflink, a move across filesystems) before and after, failing the
+    /// operation and keeping the source on a mismatch.
+    pub verify_checksums: bool,
+    /// Operations in flight at once; the upper bound in adaptive mode.
+    pub max_parallel: usize,
+    /// Lower bound in adaptive mode.
+    pub min_parallel: usize,
+    /// Tune concurrency between the bounds from observed latency and errors.
+    pub adaptive: bool,
+    /// Bytes per second copied to or from any one filesystem, across all
+    /// operations in flight (see [`throttle`]). `None` copies at full speed.
+    ///
+    /// [`throttle`]: super::throttle
+    pub io_limit: Option<u64>,
+    /// Start no new operation after this; those in flight still finish.
+    pub deadline: Option<Instant>,
+    /// Start no new operation once cancelled; those in flight still finish.
+    pub cancel: Option<CancelToken>,
+    /// Receives a [`ProgressEvent::Moving`] as each operation starts and
+    /// [`ProgressEvent::Done`] when the run is over.
+    pub progress: Option<Sender<ProgressEvent>>,
+    /// Journal each placed file as it happens, saving the plan under this
+    /// library root, so an interrupted run can be undone and resumed (see
+    /// [`journal`]). `None` keeps the run in memory.
+    ///
+    /// [`journal`]: super::journal
+    pub journal: Option<PathBuf>,
+    /// Remove the source folders the run empties (see [`cleanup`]).
+    ///
+    /// [`cleanup`]: super::cleanup
+    pub cleanup: Option<SourceCleanup>,
+    /// Refuse the run, before touching any file, unless each destination
+    /// filesystem keeps this many bytes free after it (see [`space`]).
+    /// `None` skips the check.
+    ///
+    /// [`space`]: super::space
+    pub space_reserve: Option<u64>,
+    /// Where the copies an upgrade replaces are kept, a folder per run.
+    /// `None` uses `recycle` in the undo directory.
+    pub recycle_dir: Option<PathBuf>,
+}
+
+/// Upper bound for adaptive concurrency when `max_parallel` is left at 1.
+const ADAPTIVE_MAX_PARALLEL: usize = 8;
+
+impl ExecuteOptions {
+    /// Build execution options from the `[organize]` config section.
+    pub fn from_config(config: &AppConfig) -> Self {
+        let organize = &config.organize;
+        let max_parallel = match organize.max_parallel {
+            0 | 1 if organize.adaptive_parallel => ADAPTIVE_MAX_PARALLEL,
+            n => n,
+        };
+        Self {
+            provenance: config.organize.provenance,
+            paranoid: config.organize.paranoid,
+            verify_checksums: config.organize.verify_checksums,
+            max_parallel,
+            min_parallel: config.organize.min_parallel,
+            adaptive: config.organize.adaptive_parallel,
+            io_limit: (organize.io_limit_mb > 0)
+                .then(|| organize.io_limit_mb.saturating_mul(1 << 20)),
+            deadline: None,
+            cancel: None,
+            progress: None,
+            journal: None,
+            cleanup: None,
+            space_reserve: Some(organize.space_reserve_mb.saturating_mul(1 << 20)),
+            recycle_dir: (!organize.recycle_dir.is_empty())
+                .then(|| PathBuf::from(&organize.recycle_dir)),
+        }
+    }
+}
+
+/// Failed operations an adaptive run retries, after backing off, before
+/// giving up on the run.
+const ADAPTIVE_RETRIES: usize = 2;
+
+/// Execute planned file operations and write an undo manifest.
+///
+/// Up to `max_parallel` operations run at once (see [`concurrency`] for
+/// adaptive mode); the manifest keeps plan order either way. Past the
+/// `deadline`, or once the run is cancelled, no new operation starts; how
+/// many were left is recorded in the run stats.
+///
+/// In paranoid mode a copy that fails verification is removed and its
+/// source left in place; a cross-filesystem move is verified before the
+/// source is deleted. A same-filesystem rename cannot be retried, so a
+/// mismatch there is logged and recorded on the undo entry. With
+/// [`ExecuteOptions::verify_checksums`] a copy whose checksum differs from
+/// its source's is removed and fails the run, the source kept.
+///
+/// A journaled run (see [`ExecuteOptions::journal`]) also records each
+/// entry on disk as its file is placed. With [`ExecuteOptions::cleanup`],
+/// the source folders the run emptied are removed once it is done.
+///
+/// Fails without touching a file when the run would not fit on a
+/// destination filesystem (see [`ExecuteOptions::space_reserve`]). A run
+/// that fails midway still writes the manifest of what it placed before
+/// returning the error, so those files can be undone; the error carries it
+/// as a [`FailedRun`].
+///
+/// The copies an upgrade replaces are moved to the recycle directory (see
+/// [`ExecuteOptions::recycle_dir`]) just before the new file is placed,
+/// and back if it is not; a journaled run records each before it moves.
+/// A copy that cannot be put back stays in the manifest.
+///
+/// [`concurrency`]: super::concurrency
+pub fn execute_actions(
+    actions: &[OrganizeAction],
+    undo_dir: &Path,
+    options: &ExecuteOptions,
+) -> Result<UndoManifest> {
+    if let Some(reserve) = options.space_reserve {
+        space::check_space(actions, reserve)?;
+    }
+    let now = Utc::now();
+    let timestamp = now.to_rfc3339();
+    let started = Instant::now();
+    let verifier = options.paranoid.then(Verifier::default);
+    let throttle = options.io_limit.map(Throttle::new);
+    let limiter = Limiter::new(AdaptiveLimit::new(
+        options.min_parallel,
+        options.max_parallel,
+        options.adaptive,
+    ));
+    let renames = RenameTally::default();
+    let mut manifest = UndoManifest {
+        created_at: timestamp.clone(),
+        description: format!("Organize run at {}", now.format("%Y-%m-%d %H:%M:%S UTC")),
+        ..Default::default()
+    };
+
+    // Runs finishing in the same second (several watched sources) must
+    // not overwrite each other's manifest.
+    let stamp = now.format("%Y%m%d_%H%M%S").to_string();
+    let journal = match &options.journal {
+        Some(root) if !actions.is_empty() => Some(Journal::start(
+            undo_dir,
+            &stamp,
+            &SavedPlan::new(root.clone(), actions.to_vec()),
+        )?),
+        _ => None,
+    };
+
+    let recycle = match &options.recycle_dir {
+        Some(dir) => dir.join(&stamp),
+        None => undo_dir.join("recycle").join(&stamp),
+    };
+
+    let next = AtomicUsize::new(0);
+    let placed = Mutex::new(Vec::new());
+    let replaced = Mutex::new(Vec::new());
+    let failure = Mutex::new(None);
+    let workers = options.max_parallel.clamp(1, actions.len().max(1));
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let out_of_time = options.deadline.is_some_and(|d| Instant::now() >= d);
+                let cancelled = progress::cancelled(options.cancel.as_ref());
+                if out_of_time || cancelled || lock(&failure).is_some() {
+                    break;
+                }
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(action) = actions.get(index) else {
+                    break;
+                };
+                progress::emit(
+                    options.progress.as_ref(),
+                    ProgressEvent::Moving {
+                        done: index + 1,
+                        total: actions.len(),
+                        source: action.source.clone(),
+                        destination: action.destination.clone(),
+                    },
+                );
+                // A missing source is skipped below; its copies stay.
+                let mut aside = Vec::new();
+                if action.source.exists() {
+                    let set = upgrade::set_aside(action, &recycle, &timestamp, &mut aside, |e| {
+                        journal.as_ref().map_or(Ok(()), |j| j.record_replaced(e))
+                    });
+                    if let Err(e) = set {
+                        let stranded = upgrade::restore(aside);
+                        lock(&replaced).extend(stranded.into_iter().map(|e| (index, e)));
+                        lock(&failure).get_or_insert(e);
+                        break;
+                    }
+                }
+                let verifier = verifier.as_ref();
+                let throttle = throttle.as_ref();
+                let outcome = execute_one(
+                    action, &timestamp, options, verifier, throttle, &limiter, &renames,
+                );
+                // Copies that cannot go back stay recorded, so undo can.
+                if !matches!(outcome, Ok(Some(_))) {
+                    aside = upgrade::restore(aside);
+                }
+                lock(&replaced).extend(aside.into_iter().map(|e| (index, e)));
+                match outcome {
+                    Ok(Some(entry)) => {
+                        let journaled = journal.as_ref().map(|j| j.record(&entry.0));
+                        lock(&placed).push((index, entry));
+                        if let Some(Err(e)) = journaled {
+                            lock(&failure).get_or_insert(e);
+                            break;
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        lock(&failure).get_or_insert(e);
+                        break;
+                    }
+                }
+            });
+        }
+    });
+    let failure = failure.into_inner().unwrap_or_else(|e| e.into_inner());
+    if options.adaptive {
+        info!("Adaptive concurrency peaked at {}", limiter.peak());
+    }
+    renames.report();
+    let unstarted = actions.len().saturating_sub(next.into_inner()) as u64;
+    let cancelled = progress::cancelled(options.cancel.as_ref());
+    // A failed run's error says why it stopped.
+    if unstarted > 0 && failure.is_none() {
+        if cancelled {
+            info!("Cancelled; {unstarted} actions not started");
+        } else {
+            info!("Time budget reached; {unstarted} actions not started");
+        }
+    }
+
+    let mut placed = placed.into_inner().unwrap_or_else(|e| e.into_inner());
+    placed.sort_by_key(|(index, _)| *index);
+    let mut bytes = 0;
+    for (_, (entry, size)) in placed {
+        manifest.entries.push(entry);
+        bytes += size;
+    }
+    let mut replaced = replaced.into_inner().unwrap_or_else(|e| e.into_inner());
+    replaced.sort_by_key(|(index, _)| *index);
+    manifest.replaced = replaced.into_iter().map(|(_, entry)| entry).collect();
+
+    if let Some(cleanup) = &options.cleanup {
+        let trash = undo_dir.join("cleanup").join(&stamp);
+        manifest.cleaned = cleanup::clean_sources(&manifest.entries, cleanup, &trash, &timestamp);
+    }
+
+    manifest.verification = verifier.map(|v| v.finish(started.elapsed()));
+    manifest.stats = Some(RunStats {
+        files: manifest.entries.len() as u64,
+        bytes,
+        duration_ms: started.elapsed().as_millis() as u64,
+        unstarted,
+    });
+
+    if !manifest.entries.is_empty() || !manifest.replaced.is_empty() {
+        if let Err(e) = write_manifest(undo_dir, &stamp, &manifest) {
+            // The journal, if any, is left for recovery.
+            return Err(match failure {
+                Some(failure) => {
+                    error!("Could not write the undo manifest of the failed run: {e:#}");
+                    failure.context(FailedRun { manifest })
+                }
+                None => e,
+            });
+        }
+    }
+    if let Some(journal) = journal {
+        journal.finish();
+    }
+    if let Some(e) = failure {
+        return Err(e.context(FailedRun { manifest }));
+    }
+
+    progress::emit(
+        options.progress.as_ref(),
+        ProgressEvent::Done {
+            placed: manifest.entries.len(),
+            unstarted: unstarted as usize,
+            cancelled,
+        },
+    );
+    Ok(manifest)
+}
+
+/// Write `manifest` as `undo_{stamp}.json`, numbered (`_002`, zero-padded
+/// so names sort in run order) when a run finishing in the same second
+/// already took that name.
+fn write_manifest(undo_dir: &Path, stamp: &str, manifest: &UndoManifest) -> Result<()> {
+    fs::create_dir_all(undo_dir)?;
+    let mut manifest_path = undo_dir.join(format!("undo_{stamp}.json"));
+    let mut n = 1;
+    while manifest_path.exists() {
+        n += 1;
+        manifest_path = undo_dir.join(format!("undo_{stamp}_{n:03}.json"));
+    }
+    let json = serde_json::to_string_pretty(manifest)?;
+    fs::write(&manifest_path, json)?;
+    info!("Undo manifest written: {}", manifest_path.display());
+    Ok(())
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Carry out one action. Returns its undo entry and size, or `None` when
+/// it was skipped or its copy discarded.
+fn execute_one(
+    action: &OrganizeAction,
+    timestamp: &str,
+    options: &ExecuteOptions,
+    verifier: Option<&Verifier>,
+    throttle: Option<&Throttle>,
+    limiter: &Limiter,
+    renames: &RenameTally,
+) -> Result<Option<(UndoEntry, u64)>> {
+    let mut permit = limiter.acquire();
+    if !action.source.exists() {
+        warn!("Source file missing, skipping: {}", action.source.display());
+        return Ok(None);
+    }
+    if action.destination.exists() {
+        warn!(
+            "Destination exists, skipping: {}",
+            action.destination.display()
+        );
+        return Ok(None);
+    }
+
+    // Create parent directories
+    if let Some(parent) = action.destination.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create dir: {}", parent.display()))?;
+    }
+
+    let checks = Checks::before(action, verifier, options.verify_checksums)?;
+    let size = fs::metadata(&action.source).map_or(0, |m| m.len());
+
+    let mut attempt = 0;
+    let verified = loop {
+        let started = Instant::now();
+        match transfer(action, verifier, &checks, throttle, renames) {
+            Ok(outcome) => {
+                permit.succeeded(started.elapsed(), size);
+                match outcome {
+                    Transfer::Placed(verified) => break verified,
+                    Transfer::Discarded => return Ok(None),
+                }
+            }
+            Err(e) if options.adaptive && attempt < ADAPTIVE_RETRIES => {
+                warn!("{e:#}; backing off and retrying");
+                permit.failed();
+                attempt += 1;
+                permit = limiter.acquire();
+            }
+            Err(e) => {
+                permit.failed();
+                return Err(e);
+            }
+        }
+    };
+
+    info!(
+        "Organized: {} → {}",
+        action.source.display(),
+        action.destination.display()
+    );
+
+    // A missing breadcrumb never fails the run; the database still has it.
+    if let Err(e) = provenance::write_breadcrumb(
+        options.provenance,
+        &action.source,
+        &action.destination,
+        timestamp,
+    ) {
+        warn!(
+            "Could not record provenance for {}: {}",
+            action.destination.display(),
+            e
+        );
+    }
+
+    let entry = UndoEntry {
+        source: action.source.to_string_lossy().to_string(),
+        destination: action.destination.to_string_lossy().to_string(),
+        strategy: action.strategy.clone(),
+        timestamp: timestamp.to_string(),
+        title: action.title.clone(),
+        titles: action.titles.clone(),
+        media_type: action.media_type.to_string(),
+        year: action.year,
+        quality: action.quality.clone(),
+        tmdb_id: action.tmdb_id,
+        second_feature: action.second_feature.clone(),
+        companion_of: action
+            .companion_of
+            .as_ref()
+            .map(|p| p.to_string_lossy().to_string()),
+        verified,
+        checksum: checks.checksum,
+        confidence: Some(action.confidence),
+    };
+    Ok(Some((entry, size)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::MediaType;
+
+    #[test]
+    fn test_parallel_run_keeps_plan_order() {
+        let tmp = tempfile::tempdir().unwrap();
+        let actions: Vec<_> = (0..12)
+            .map(|i| {
+                let source = tmp.path().join(format!("in/{i}.mkv"));
+                fs::create_dir_all(source.parent().unwrap()).unwrap();
+                fs::write(&source, vec![b'x'; i * 100]).unwrap();
+                OrganizeAction {
+                    source,
+                    destination: tmp.path().join(format!("lib/{i}/{i}.mkv")),
+                    strategy: "copy".to_string(),
+                    ..Default::default()
+                }
+            })
+            .collect();
+        let options = ExecuteOptions {
+            max_parallel: 4,
+            adaptive: true,
+            ..Default::default()
+        };
+
+        let manifest = execute_actions(&actions, &tmp.path().join("undo"), &options).unwrap();
+        let placed: Vec<_> = manifest.entries.iter().map(|e| &e.destination).collect();
+        let planned: Vec<_> = actions
+            .iter()
+            .map(|a| a.destination.to_string_lossy().to_string())
+            .collect();
+        assert_eq!(placed, planned.iter().collect::<Vec<_>>());
+        assert_eq!(manifest.stats.unwrap().bytes, (0..12).sum::<u64>() * 100);
+    }
+
+    #[test]
+    fn test_failed_run_writes_manifest_of_placed_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = |name: &str| {
+            let path = tmp.path().join(name);
+            fs::write(&path, name).unwrap();
+            path
+        };
+        // A file where the second destination needs a folder.
+        fs::write(tmp.path().join("blocked"), b"").unwrap();
+        let actions = vec![
+            OrganizeAction {
+                source: source("Heat.1995.mkv"),
+                destination: tmp.path().join("lib/Heat (1995).mkv"),
+                strategy: "move".to_string(),
+                ..Default::default()
+            },
+            OrganizeAction {
+                source: source("Alien.1979.mkv"),
+                destination: tmp.path().join("blocked/Alien (1979).mkv"),
+                strategy: "move".to_string(),
+                ..Default::default()
+            },
+        ];
+        let undo_dir = tmp.path().join("undo");
+
+        let error = execute_actions(&actions, &undo_dir, &Default::default()).unwrap_err();
+        let failed = error.downcast_ref::<FailedRun>().unwrap();
+        assert_eq!(failed.manifest.entries.len(), 1);
+        let report = crate::organizer::undo_last(&undo_dir, &mut |_| Ok(())).unwrap();
+        assert_eq!(report.reversed.len(), 1);
+        assert!(actions[0].source.exists());
+    }
+
+    #[test]
+    fn test_same_second_manifests_list_in_run_order() {
+        let tmp = tempfile::tempdir().unwrap();
+        for run in 1..=11 {
+            let manifest = UndoManifest {
+                description: format!("run {run}"),
+                ..Default::default()
+            };
+            write_manifest(tmp.path(), "20260301_120000", &manifest).unwrap();
+        }
+        let operations = crate::organizer::list_operations(tmp.path()).unwrap();
+        assert_eq!(operations.len(), 11);
+        assert_eq!(operations[0].description, "run 11");
+        assert_eq!(operations[10].description, "run 1");
+    }
+
+    #[test]
+    fn test_deadline_stops_new_operations() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = tmp.path().join("Heat.1995.mkv");
+        fs::write(&source, b"video content").unwrap();
+        let actions = vec![OrganizeAction {
+            source: source.clone(),
+            destination: tmp.path().join("lib/Heat (1995).mkv"),
+            strategy: "move".to_string(),
+            ..Default::default()
+        }];
+        let options = ExecuteOptions {
+            deadline: Some(Instant::now()),
+            ..Default::default()
+        };
+
+        let manifest = execute_actions(&actions, &tmp.path().join("undo"), &options).unwrap();
+        assert!(manifest.entries.is_empty());
+        assert_eq!(manifest.stats.unwrap().unstarted, 1);
+        assert!(source.exists());
+    }
+}
@@ -0,0 +1,349 @@
+//! Platform file operations.
+//!
+//! Moves within one filesystem go through an atomic no-replace rename
+//! where the OS has one — `renameat2(RENAME_NOREPLACE)` on Linux,
+//! `renamex_np(RENAME_EXCL)` on macOS — so a file that shows up at the
+//! destination after the planner looked can never be overwritten. Elsewhere,
+//! or when the filesystem rejects the flag (some network and FUSE mounts),
+//! the destination is checked and then renamed over with a plain rename.
+//!
+//! Reflinks clone a file's data blocks instead of copying them — `FICLONE`
+//! on Linux (btrfs, XFS), `clonefile` on macOS (APFS) — and fall back to a
+//! copy where the filesystem cannot.
+//!
+//! A move onto another filesystem, where no rename can reach, is a copy
+//! ([`copy_durably`]) checked before the source is deleted. Under a
+//! bandwidth limit, copies go chunk by chunk through the [`Throttle`].
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use tracing::{debug, info};
+
+use super::throttle::Throttle;
+
+/// Bytes copied between two waits on the throttle.
+const THROTTLED_CHUNK: usize = 1024 * 1024;
+
+/// The atomic rename this platform offers, for reporting.
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+const ATOMIC_RENAME: &str = "renameat2(RENAME_NOREPLACE)";
+#[cfg(target_os = "macos")]
+const ATOMIC_RENAME: &str = "renamex_np(RENAME_EXCL)";
+#[cfg(not(any(all(target_os = "linux", target_env = "gnu"), target_os = "macos")))]
+const ATOMIC_RENAME: &str = "no atomic rename on this platform";
+
+/// How a rename was carried out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum RenameMethod {
+    /// The kernel refused to replace an existing destination.
+    NoReplace,
+    /// The destination was checked, then renamed over.
+    Checked,
+}
+
+/// Renames per method, and reflinks cloned or copied, over a run; reported
+/// in verbose mode.
+#[derive(Debug, Default)]
+pub(super) struct RenameTally {
+    no_replace: AtomicUsize,
+    checked: AtomicUsize,
+    cloned: AtomicUsize,
+    copied: AtomicUsize,
+}
+
+impl RenameTally {
+    pub(super) fn record(&self, method: RenameMethod) {
+        let counter = match method {
+            RenameMethod::NoReplace => &self.no_replace,
+            RenameMethod::Checked => &self.checked,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a reflink, `cloned` or copied in full.
+    pub(super) fn record_reflink(&self, cloned: bool) {
+        let counter = if cloned { &self.cloned } else { &self.copied };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Log the split, if anything was renamed.
+    pub(super) fn report(&self) {
+        let no_replace = self.no_replace.load(Ordering::Relaxed);
+        let checked = self.checked.load(Ordering::Relaxed);
+        if no_replace + checked > 0 {
+            info!(
+                "Renames: {no_replace} via {ATOMIC_RENAME}, {checked} via checked rename fallback"
+            );
+        }
+        let cloned = self.cloned.load(Ordering::Relaxed);
+        let copied = self.copied.load(Ordering::Relaxed);
+        if cloned + copied > 0 {
+            info!("Reflinks: {cloned} cloned, {copied} copied (filesystem cannot clone)");
+        }
+    }
+}
+
+/// Rename `from` to `to`, failing with `AlreadyExists` rather than
+/// replacing a file at `to`.
+///
+/// Errors other than an unsupported flag (e.g. `EXDEV` across filesystems)
+/// are returned as they are.
+pub(super) fn rename_no_replace(from: &Path, to: &Path) -> io::Result<RenameMethod> {
+    match atomic_rename(from, to) {
+        Some(Ok(())) => return Ok(RenameMethod::NoReplace),
+        Some(Err(e)) if !unsupported(&e) => return Err(e),
+        Some(Err(e)) => debug!("{ATOMIC_RENAME} unsupported for {}: {e}", to.display()),
+        None => {}
+    }
+    if to.symlink_metadata().is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", to.display()),
+        ));
+    }
+    fs::rename(from, to)?;
+    Ok(RenameMethod::Checked)
+}
+
+#[cfg(any(all(target_os = "linux", target_env = "gnu"), target_os = "macos"))]
+fn c_path(path: &Path) -> Option<std::ffi::CString> {
+    use std::os::unix::ffi::OsStrExt;
+    std::ffi::CString::new(path.as_os_str().as_bytes()).ok()
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+fn atomic_rename(from: &Path, to: &Path) -> Option<io::Result<()>> {
+    let (from, to) = (c_path(from)?, c_path(to)?);
+    // SAFETY: both paths are valid NUL-terminated strings for the call.
+    let rc = unsafe {
+        libc::renameat2(
+            libc::AT_FDCWD,
+            from.as_ptr(),
+            libc::AT_FDCWD,
+            to.as_ptr(),
+            libc::RENAME_NOREPLACE,
+        )
+    };
+    Some(if rc == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    })
+}
+
+#[cfg(target_os = "macos")]
+fn atomic_rename(from: &Path, to: &Path) -> Option<io::Result<()>> {
+    let (from, to) = (c_path(from)?, c_path(to)?);
+    // SAFETY: both paths are valid NUL-terminated strings for the call.
+    let rc = unsafe { libc::renamex_np(from.as_ptr(), to.as_ptr(), libc::RENAME_EXCL) };
+    Some(if rc == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    })
+}
+
+/// Clone `from` to `to`, which must not exist. Returns `false`, having
+/// created nothing, when the filesystem cannot clone (or the two are on
+/// different filesystems); the caller copies instead.
+pub(super) fn reflink(from: &Path, to: &Path) -> io::Result<bool> {
+    match clone_file(from, to) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Err(e),
+        Err(e) if cannot_clone(&e) => {
+            debug!("Cannot clone {}: {e}", to.display());
+            Ok(false)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn clone_file(from: &Path, to: &Path) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let source = fs::File::open(from)?;
+    let dest = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(to)?;
+    // SAFETY: both descriptors are open for the duration of the call.
+    if unsafe { libc::ioctl(dest.as_raw_fd(), libc::FICLONE, source.as_raw_fd()) } == 0 {
+        return Ok(());
+    }
+    let err = io::Error::last_os_error();
+    drop(dest);
+    let _ = fs::remove_file(to);
+    Err(err)
+}
+
+#[cfg(target_os = "macos")]
+fn clone_file(from: &Path, to: &Path) -> io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let from = CString::new(from.as_os_str().as_bytes())?;
+    let to = CString::new(to.as_os_str().as_bytes())?;
+    // SAFETY: both are valid NUL-terminated paths.
+    if unsafe { libc::clonefile(from.as_ptr(), to.as_ptr(), 0) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos")))]
+fn clone_file(_from: &Path, _to: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "reflinks are not supported on this platform",
+    ))
+}
+
+/// Copy `from` to `to`, paced by `throttle` when there is one. Returns the
+/// bytes copied.
+pub(super) fn copy(from: &Path, to: &Path, throttle: Option<&Throttle>) -> io::Result<u64> {
+    let Some(throttle) = throttle else {
+        return fs::copy(from, to);
+    };
+    let devices = throttle.devices(from, to);
+    let mut reader = fs::File::open(from)?;
+    let permissions = reader.metadata()?.permissions();
+    let mut writer = fs::File::create(to)?;
+    let mut buf = vec![0u8; THROTTLED_CHUNK];
+    let mut copied = 0;
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        throttle.pace(&devices, n as u64);
+        writer.write_all(&buf[..n])?;
+        copied += n as u64;
+    }
+    writer.set_permissions(permissions)?;
+    Ok(copied)
+}
+
+/// Copy `from` to `to` (see [`copy`]) and flush the copy to disk, so
+/// deleting `from` afterwards cannot lose the file. A copy of the wrong
+/// length, or one cut short by an error (a full disk), is removed. Returns
+/// the bytes copied.
+pub(super) fn copy_durably(from: &Path, to: &Path, throttle: Option<&Throttle>) -> io::Result<u64> {
+    let expected = fs::metadata(from)?.len();
+    let copied = copy(from, to, throttle).and_then(|copied| {
+        fs::File::open(to)?.sync_all()?;
+        if copied != expected || fs::metadata(to)?.len() != expected {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("copied {copied} of {expected} bytes"),
+            ));
+        }
+        Ok(copied)
+    });
+    if copied.is_err() {
+        let _ = fs::remove_file(to);
+    }
+    copied
+}
+
+/// Move `from` to `to`, which must not exist: a rename, or across
+/// filesystems a durable copy and then removal of `from`. Other rename
+/// failures are returned as they are.
+pub(super) fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    match rename_no_replace(from, to) {
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            copy_durably(from, to, None)?;
+            fs::remove_file(from)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Whether a clone failed for want of filesystem support rather than for a
+/// reason a copy shares.
+#[cfg(unix)]
+fn cannot_clone(e: &io::Error) -> bool {
+    matches!(
+        e.raw_os_error(),
+        Some(code) if code == libc::EOPNOTSUPP
+            || code == libc::ENOTSUP
+            || code == libc::EXDEV
+            || code == libc::EINVAL
+            || code == libc::ENOTTY
+            || code == libc::ENOSYS
+    )
+}
+
+#[cfg(not(unix))]
+fn cannot_clone(e: &io::Error) -> bool {
+    e.kind() == io::ErrorKind::Unsupported
+}
+
+#[cfg(not(any(all(target_os = "linux", target_env = "gnu"), target_os = "macos")))]
+fn atomic_rename(_from: &Path, _to: &Path) -> Option<io::Result<()>> {
+    None
+}
+
+/// Whether an atomic rename failed because the kernel or filesystem lacks
+/// the no-replace flag, rather than for a reason a plain rename shares.
+#[cfg(unix)]
+fn unsupported(e: &io::Error) -> bool {
+    matches!(
+        e.raw_os_error(),
+        Some(code) if code == libc::EINVAL
+            || code == libc::ENOSYS
+            || code == libc::ENOTSUP
+            || code == libc::EOPNOTSUPP
+    )
+}
+
+#[cfg(not(unix))]
+fn unsupported(_: &io::Error) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rename_never_replaces() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (a, b, c) = (
+            tmp.path().join("a"),
+            tmp.path().join("b"),
+            tmp.path().join("c"),
+        );
+        fs::write(&a, b"a").unwrap();
+        fs::write(&b, b"b").unwrap();
+
+        let err = rename_no_replace(&a, &b).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read(&b).unwrap(), b"b");
+        assert!(a.exists());
+
+        let tally = RenameTally::default();
+        tally.record(rename_no_replace(&a, &c).unwrap());
+        assert_eq!(fs::read(&c).unwrap(), b"a");
+        assert!(!a.exists());
+        let renames = tally.no_replace.load(Ordering::Relaxed) + tally.checked.into_inner();
+        assert_eq!(renames, 1);
+
+        assert_eq!(copy_durably(&c, &a, None).unwrap(), 1);
+        assert_eq!(fs::read(&a).unwrap(), b"a");
+        assert!(copy_durably(&tmp.path().join("gone"), &b, None).is_err());
+        assert_eq!(fs::read(&b).unwrap(), b"b");
+
+        let paced = tmp.path().join("d");
+        let throttle = Throttle::new(1 << 20);
+        assert_eq!(copy_durably(&c, &paced, Some(&throttle)).unwrap(), 1);
+        assert_eq!(fs::read(&paced).unwrap(), b"a");
+    }
+}
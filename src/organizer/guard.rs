@@ -0,0 +1,281 @@
+//! Guards against organizing a library into itself or somewhere unsafe.
+//!
+//! Output placed inside the source tree (`/downloads` → `/downloads/plex`)
+//! would be re-scanned and re-organized on the next run, nesting folders a
+//! level deeper every time. Organized roots are therefore tagged with a
+//! marker file, and source/destination overlap is detected up front.
+//!
+//! A mistyped destination (`/`, `C:\Windows`, the organizer's own data
+//! directory) is refused outright, and one outside every configured library
+//! root is flagged so the CLI can ask for confirmation.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::config::AppConfig;
+use crate::scanner::OUTPUT_MARKER;
+
+/// How a source directory and a destination root relate on disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Nesting {
+    /// Disjoint trees — the normal case.
+    Separate,
+    /// The destination lives inside the source; it must not be scanned.
+    DestInsideSource,
+    /// The source lives inside (or is) the destination library.
+    SourceInsideDest,
+}
+
+/// Canonicalize the longest existing ancestor of `path` and re-append the
+/// rest, so a not-yet-created destination still compares correctly.
+fn resolve(path: &Path) -> PathBuf {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut existing = absolute.as_path();
+    let mut missing = Vec::new();
+    while !existing.exists() {
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name);
+                existing = parent;
+            }
+            _ => return absolute,
+        }
+    }
+    let mut resolved = fs::canonicalize(existing).unwrap_or_else(|_| existing.to_path_buf());
+    resolved.extend(missing.iter().rev());
+    resolved
+}
+
+/// Classify how `source` and `dest` overlap.
+pub fn check_nesting(source: &Path, dest: &Path) -> Nesting {
+    let source = resolve(source);
+    let dest = resolve(dest);
+    if dest.starts_with(&source) && dest != source {
+        Nesting::DestInsideSource
+    } else if source.starts_with(&dest) {
+        Nesting::SourceInsideDest
+    } else {
+        Nesting::Separate
+    }
+}
+
+/// Directories that are never a destination themselves (roots and
+/// containers of user data), though folders inside them may be.
+#[cfg(unix)]
+const PROTECTED_EXACT: &[&str] = &["/", "/home", "/Users", "/mnt", "/media", "/Volumes", "/var"];
+#[cfg(windows)]
+const PROTECTED_EXACT: &[&str] = &["C:\\", "C:\\Users"];
+#[cfg(not(any(unix, windows)))]
+const PROTECTED_EXACT: &[&str] = &[];
+
+/// System trees no destination may be in.
+#[cfg(unix)]
+const PROTECTED_TREES: &[&str] = &[
+    "/bin",
+    "/boot",
+    "/dev",
+    "/etc",
+    "/lib",
+    "/lib64",
+    "/proc",
+    "/run",
+    "/sbin",
+    "/sys",
+    "/usr",
+    "/System",
+    "/Library",
+    "/Applications",
+];
+#[cfg(windows)]
+const PROTECTED_TREES: &[&str] = &[
+    "C:\\Windows",
+    "C:\\Program Files",
+    "C:\\Program Files (x86)",
+    "C:\\ProgramData",
+];
+#[cfg(not(any(unix, windows)))]
+const PROTECTED_TREES: &[&str] = &[];
+
+/// Where a destination root may go.
+#[derive(Debug, Clone, Default)]
+pub struct DestinationPolicy {
+    /// Refused as the destination itself.
+    pub protected_exact: Vec<PathBuf>,
+    /// Refused, along with everything inside them.
+    pub protected_trees: Vec<PathBuf>,
+    /// Configured library locations; a destination outside all of them
+    /// needs confirmation. Empty: no confirmation needed.
+    pub library_roots: Vec<PathBuf>,
+}
+
+impl DestinationPolicy {
+    /// Build the policy from the built-in system paths, the user's home,
+    /// the organizer's data directory (`app_dir`), and the config's
+    /// `protected_paths` and library roots.
+    pub fn from_config(config: &AppConfig, app_dir: &Path) -> Self {
+        let mut protected_exact: Vec<PathBuf> = PROTECTED_EXACT.iter().map(PathBuf::from).collect();
+        if let Some(home) = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")) {
+            protected_exact.push(PathBuf::from(home));
+        }
+        let mut protected_trees: Vec<PathBuf> = PROTECTED_TREES.iter().map(PathBuf::from).collect();
+        protected_trees.push(app_dir.to_path_buf());
+        protected_trees.extend(config.organize.protected_paths.iter().map(PathBuf::from));
+
+        let organize = &config.organize;
+        let library_roots = [
+            &config.destination,
+            &organize.staging_dir,
+            &organize.view_dir,
+        ]
+        .into_iter()
+        .chain(&organize.library_roots)
+        .filter(|root| !root.is_empty())
+        .map(PathBuf::from)
+        .collect();
+        Self {
+            protected_exact,
+            protected_trees,
+            library_roots,
+        }
+    }
+}
+
+/// Whether a destination root is safe to organize into.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DestinationCheck {
+    /// Inside a configured library root (or none are configured).
+    Allowed,
+    /// Safe, but outside every configured library root.
+    OutsideLibraryRoots,
+    /// A protected path, or inside one; never allowed.
+    Protected(PathBuf),
+}
+
+/// Check `dest` against `policy`.
+pub fn check_destination(dest: &Path, policy: &DestinationPolicy) -> DestinationCheck {
+    let dest = comparable(&resolve(dest));
+    for path in &policy.protected_exact {
+        if dest == comparable(&resolve(path)) {
+            return DestinationCheck::Protected(path.clone());
+        }
+    }
+    for path in &policy.protected_trees {
+        if dest.starts_with(comparable(&resolve(path))) {
+            return DestinationCheck::Protected(path.clone());
+        }
+    }
+    let inside_root = policy.library_roots.is_empty()
+        || policy
+            .library_roots
+            .iter()
+            .any(|root| dest.starts_with(comparable(&resolve(root))));
+    if inside_root {
+        DestinationCheck::Allowed
+    } else {
+        DestinationCheck::OutsideLibraryRoots
+    }
+}
+
+/// Windows paths compare case-insensitively.
+fn comparable(path: &Path) -> PathBuf {
+    if cfg!(windows) {
+        PathBuf::from(path.to_string_lossy().to_lowercase())
+    } else {
+        path.to_path_buf()
+    }
+}
+
+/// Tag `dest_root` as organizer output so later scans skip it.
+pub fn mark_output_root(dest_root: &Path) -> io::Result<()> {
+    let marker = dest_root.join(OUTPUT_MARKER);
+    if marker.exists() {
+        return Ok(());
+    }
+    fs::create_dir_all(dest_root)?;
+    fs::write(
+        marker,
+        "This directory is managed by plex-org; it is skipped when scanning sources.\n",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_nesting() {
+        let tmp = tempfile::tempdir().unwrap();
+        let downloads = tmp.path().join("downloads");
+        fs::create_dir_all(&downloads).unwrap();
+
+        assert_eq!(
+            check_nesting(&downloads, &downloads.join("organized")),
+            Nesting::DestInsideSource
+        );
+        assert_eq!(
+            check_nesting(&downloads.join("Movies"), &downloads),
+            Nesting::SourceInsideDest
+        );
+        assert_eq!(
+            check_nesting(&downloads, &tmp.path().join("plex")),
+            Nesting::Separate
+        );
+    }
+
+    #[test]
+    fn test_check_destination() {
+        let tmp = tempfile::tempdir().unwrap();
+        let policy = DestinationPolicy {
+            protected_exact: vec![tmp.path().to_path_buf()],
+            protected_trees: vec![tmp.path().join(".plex-organizer")],
+            library_roots: vec![tmp.path().join("plex")],
+        };
+        let check = |p: PathBuf| check_destination(&p, &policy);
+
+        assert_eq!(
+            check(tmp.path().to_path_buf()),
+            DestinationCheck::Protected(tmp.path().to_path_buf())
+        );
+        assert!(matches!(
+            check(tmp.path().join(".plex-organizer").join("undo")),
+            DestinationCheck::Protected(_)
+        ));
+        assert_eq!(
+            check(tmp.path().join("plex").join("Movies")),
+            DestinationCheck::Allowed
+        );
+        assert_eq!(
+            check(tmp.path().join("elsewhere")),
+            DestinationCheck::OutsideLibraryRoots
+        );
+
+        let builtin = DestinationPolicy::from_config(
+            &AppConfig::default(),
+            &tmp.path().join(".plex-organizer"),
+        );
+        #[cfg(unix)]
+        assert!(matches!(
+            check_destination(Path::new("/"), &builtin),
+            DestinationCheck::Protected(_)
+        ));
+        #[cfg(windows)]
+        assert!(matches!(
+            check_destination(Path::new("c:\\windows\\media"), &builtin),
+            DestinationCheck::Protected(_)
+        ));
+        assert_eq!(
+            check_destination(&tmp.path().join("plex"), &builtin),
+            DestinationCheck::Allowed
+        );
+    }
+
+    #[test]
+    fn test_mark_output_root_is_idempotent() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().join("plex");
+        mark_output_root(&root).unwrap();
+        mark_output_root(&root).unwrap();
+        assert!(root.join(OUTPUT_MARKER).is_file());
+    }
+}
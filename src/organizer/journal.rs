@@ -0,0 +1,285 @@
+//! Journaled execution for large runs.
+//!
+//! A quick run keeps its undo entries in memory and writes the manifest
+//! when it ends, so a run killed halfway leaves the files it already moved
+//! with no undo record. That is a fair trade for a few hundred files, not
+//! for an import of tens of thousands. A journaled run first saves its plan
+//! beside the undo manifests, then appends each undo entry as one JSON line
+//! the moment its file is placed, and each copy an upgrade replaces just
+//! before it is set aside. A finished run replaces the journal with its
+//! manifest; a journal left behind is turned by [`recover_journals`] into
+//! a manifest for `undo` and a plan of the actions not yet done.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use super::plan::{fingerprint_actions, load_plan, save_plan};
+use crate::models::{SavedPlan, UndoEntry, UndoManifest};
+
+/// One journal line: a placed file's undo entry as is, or a set-aside copy's
+/// as `{"replaced": …}`.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Line<E> {
+    Replaced { replaced: E },
+    Placed(E),
+}
+
+/// An open journal for one run.
+pub(super) struct Journal {
+    entries_path: PathBuf,
+    plan_path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl Journal {
+    /// Save `plan` and open an empty journal for it in `undo_dir`.
+    pub(super) fn start(undo_dir: &Path, stamp: &str, plan: &SavedPlan) -> Result<Self> {
+        fs::create_dir_all(undo_dir)?;
+        let mut name = format!("journal_{stamp}");
+        let mut n = 1;
+        while undo_dir.join(format!("{name}.jsonl")).exists() {
+            n += 1;
+            name = format!("journal_{stamp}_{n:03}");
+        }
+        let entries_path = undo_dir.join(format!("{name}.jsonl"));
+        let plan_path = undo_dir.join(format!("{name}.plan.json"));
+        save_plan(&plan_path, plan)?;
+        let file = OpenOptions::new()
+            .create_new(true)
+            .append(true)
+            .open(&entries_path)
+            .with_context(|| format!("Failed to open journal {}", entries_path.display()))?;
+        // Held until the run ends or the process dies, so recovery never
+        // takes the journal of a run still going.
+        file.lock()
+            .with_context(|| format!("Failed to lock journal {}", entries_path.display()))?;
+        info!("Journaling this run to {}", entries_path.display());
+        Ok(Self {
+            entries_path,
+            plan_path,
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Append the undo entry of a placed file.
+    pub(super) fn record(&self, entry: &UndoEntry) -> Result<()> {
+        self.append(&Line::Placed(entry))
+    }
+
+    /// Append the undo entry of a copy an upgrade is about to set aside.
+    pub(super) fn record_replaced(&self, entry: &UndoEntry) -> Result<()> {
+        self.append(&Line::Replaced { replaced: entry })
+    }
+
+    /// Each line is written whole, so a killed process leaves at most the
+    /// line in flight incomplete.
+    fn append(&self, line: &Line<&UndoEntry>) -> Result<()> {
+        let mut line = serde_json::to_vec(line)?;
+        line.push(b'\n');
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        file.write_all(&line)
+            .with_context(|| format!("Failed to write journal {}", self.entries_path.display()))
+    }
+
+    /// Remove the journal once the run's manifest is written.
+    pub(super) fn finish(self) {
+        // Windows cannot remove a file still open.
+        drop(self.file);
+        for path in [&self.entries_path, &self.plan_path] {
+            if let Err(e) = fs::remove_file(path) {
+                warn!("Could not remove journal {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+/// An interrupted journaled run, recovered.
+#[derive(Debug)]
+pub struct RecoveredRun {
+    /// Undo manifest written for the files the run placed.
+    pub manifest_path: PathBuf,
+    pub manifest: UndoManifest,
+    /// The actions the run had not finished, fingerprinted for
+    /// `organize --apply-plan`.
+    pub remaining: SavedPlan,
+}
+
+/// Turn every journal left in `undo_dir` by an interrupted run into an undo
+/// manifest, returning each with the actions it left undone. The journals
+/// are removed; those of runs still going are left alone.
+///
+/// A file being placed when the run died may have been placed without its
+/// entry; it shows up as drift (source missing) when the rest is resumed.
+pub fn recover_journals(undo_dir: &Path) -> Result<Vec<RecoveredRun>> {
+    let Ok(dir) = fs::read_dir(undo_dir) else {
+        return Ok(Vec::new());
+    };
+    let mut journals: Vec<PathBuf> = dir
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("journal_") && n.ends_with(".jsonl"))
+        })
+        .collect();
+    journals.sort();
+
+    let mut recovered = Vec::new();
+    for entries_path in journals {
+        let mut file = File::open(&entries_path)
+            .with_context(|| format!("Failed to open journal {}", entries_path.display()))?;
+        if file.try_lock().is_err() {
+            continue;
+        }
+        let mut content = String::new();
+        file.read_to_string(&mut content)
+            .with_context(|| format!("Failed to read journal {}", entries_path.display()))?;
+        let plan_path = entries_path.with_extension("plan.json");
+        let plan = load_plan(&plan_path)?;
+        let mut entries = Vec::new();
+        let mut replaced = Vec::new();
+        for line in content.lines() {
+            match serde_json::from_str(line) {
+                Ok(Line::Placed(entry)) => entries.push(entry),
+                // Journaled before it moved: a copy not in the recycle
+                // directory never left, or was put back.
+                Ok(Line::Replaced { replaced: entry }) => {
+                    if Path::new(&entry.destination).exists() {
+                        replaced.push(entry);
+                    }
+                }
+                Err(_) => {}
+            }
+        }
+
+        let order: HashMap<PathBuf, usize> = plan
+            .actions
+            .iter()
+            .enumerate()
+            .map(|(i, a)| (a.destination.clone(), i))
+            .collect();
+        entries.sort_by_key(|e| order.get(Path::new(&e.destination)).copied());
+        let done: HashSet<&str> = entries.iter().map(|e| e.destination.as_str()).collect();
+        let mut actions: Vec<_> = plan
+            .actions
+            .iter()
+            .filter(|a| !done.contains(a.destination.to_string_lossy().as_ref()))
+            .cloned()
+            .collect();
+        fingerprint_actions(&mut actions);
+
+        let manifest = UndoManifest {
+            entries,
+            replaced,
+            created_at: plan.created_at.clone(),
+            description: format!("Interrupted organize run at {}, recovered", plan.created_at),
+            ..Default::default()
+        };
+        let stem = entries_path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default()
+            .replacen("journal_", "undo_", 1);
+        let manifest_path = undo_dir.join(format!("{stem}.json"));
+        if !manifest.entries.is_empty() || !manifest.replaced.is_empty() {
+            fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)?;
+            info!("Recovered undo manifest: {}", manifest_path.display());
+        }
+        drop(file);
+        fs::remove_file(&entries_path)?;
+        fs::remove_file(&plan_path)?;
+        recovered.push(RecoveredRun {
+            manifest_path,
+            manifest,
+            remaining: SavedPlan::new(plan.dest_root, actions),
+        });
+    }
+    Ok(recovered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::OrganizeAction;
+
+    #[test]
+    fn test_interrupted_journal_is_recovered() {
+        let tmp = tempfile::tempdir().unwrap();
+        let undo_dir = tmp.path().join("undo");
+        let actions: Vec<_> = ["Heat", "Alien"]
+            .iter()
+            .map(|t| {
+                let source = tmp.path().join(format!("{t}.mkv"));
+                fs::write(&source, t).unwrap();
+                OrganizeAction {
+                    source,
+                    destination: tmp.path().join(format!("lib/{t}.mkv")),
+                    strategy: "move".to_string(),
+                    ..Default::default()
+                }
+            })
+            .collect();
+        let plan = SavedPlan::new(tmp.path().join("lib"), actions.clone());
+
+        let journal = Journal::start(&undo_dir, "20260101_000000", &plan).unwrap();
+        journal
+            .record(&UndoEntry {
+                source: actions[0].source.to_string_lossy().to_string(),
+                destination: actions[0].destination.to_string_lossy().to_string(),
+                strategy: "move".to_string(),
+                title: "Heat".to_string(),
+                media_type: "movie".to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        // An old copy set aside, and one journaled just before a crash.
+        let aside = tmp.path().join("recycle/Heat.720p.mkv");
+        fs::create_dir_all(aside.parent().unwrap()).unwrap();
+        fs::write(&aside, b"720p").unwrap();
+        for destination in [&aside, &tmp.path().join("recycle/Alien.720p.mkv")] {
+            journal
+                .record_replaced(&UndoEntry {
+                    source: tmp
+                        .path()
+                        .join("lib/old.mkv")
+                        .to_string_lossy()
+                        .into_owned(),
+                    destination: destination.to_string_lossy().into_owned(),
+                    strategy: "move".to_string(),
+                    ..Default::default()
+                })
+                .unwrap();
+        }
+        let running = recover_journals(&undo_dir).unwrap();
+        assert!(running.is_empty());
+        drop(journal);
+        fs::OpenOptions::new()
+            .append(true)
+            .open(undo_dir.join("journal_20260101_000000.jsonl"))
+            .unwrap()
+            .write_all(b"{\"source\":\"/tmp/cut-off")
+            .unwrap();
+
+        let runs = recover_journals(&undo_dir).unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].manifest.entries.len(), 1);
+        assert_eq!(runs[0].manifest.replaced.len(), 1);
+        assert!(undo_dir.join("undo_20260101_000000.json").exists());
+        assert_eq!(runs[0].remaining.actions.len(), 1);
+        assert_eq!(
+            runs[0].remaining.actions[0].destination,
+            actions[1].destination
+        );
+        assert!(runs[0].remaining.actions[0].fingerprint.is_some());
+        assert!(recover_journals(&undo_dir).unwrap().is_empty());
+    }
+}
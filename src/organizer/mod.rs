@@ -0,0 +1,40 @@
+//! File organizer — builds Plex paths, executes moves, manages undo.
+//!
+//! Supports move, copy, hardlink, reflink, and symlink strategies. Dry-run
+//! by default. Runs that would not fit on the destination are refused up
+//! front, and source folders a run empties can be cleaned up after it.
+//! With upgrades, a better copy of a movie replaces the one in the library.
+
+mod cleanup;
+mod concurrency;
+mod execute;
+mod fsops;
+mod guard;
+mod journal;
+mod paths;
+mod plan;
+mod provenance;
+mod space;
+mod templates;
+mod throttle;
+mod transfer;
+mod undo;
+mod upgrade;
+mod validate;
+mod verify;
+
+pub use cleanup::SourceCleanup;
+pub use execute::{execute_actions, ExecuteOptions, FailedRun, STRATEGIES};
+pub use guard::{
+    check_destination, check_nesting, mark_output_root, DestinationCheck, DestinationPolicy,
+    Nesting,
+};
+pub use journal::{recover_journals, RecoveredRun};
+pub use paths::build_destination_path;
+pub use plan::{
+    fingerprint_actions, load_plan, plan_actions, plan_layout, plan_upgrades, save_plan,
+};
+pub use space::{bytes_needed, check_space, free_space, space_needs, SpaceNeed};
+pub use undo::{list_operations, undo_last, undo_operation, Operation, UndoReport};
+pub use upgrade::AlreadyHad;
+pub use validate::{check_drift, validate_plan, DriftReason, DriftedAction};
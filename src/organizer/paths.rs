@@ -0,0 +1,592 @@
+//! Plex-compatible destination path building.
+
+use std::path::{Path, PathBuf};
+
+use chrono::Datelike;
+
+use super::templates;
+use crate::config::{AppConfig, DoubleFeatures};
+use crate::home_video;
+use crate::language;
+use crate::models::{EnrichedMedia, Movie, MusicTrack, ParsedMedia, TvEpisode};
+use crate::part;
+use crate::titles;
+use crate::utils::{fit_title, sanitize_name};
+
+/// Bytes kept free in generated file names for `" (2)"` duplicate
+/// counters and companion suffixes such as `.en.forced.srt`.
+const COMPANION_RESERVE: usize = 20;
+
+/// Episode titles are dropped rather than cut shorter than this.
+const MIN_EPISODE_TITLE: usize = 8;
+
+/// Name length budgets from `organize.max_name_length`, in bytes.
+struct NameLimit {
+    folder: usize,
+    file: usize,
+}
+
+impl NameLimit {
+    fn new(config: &AppConfig) -> Self {
+        match config.organize.max_name_length {
+            0 => Self {
+                folder: usize::MAX,
+                file: usize::MAX,
+            },
+            max => Self {
+                folder: max,
+                file: max.saturating_sub(COMPANION_RESERVE),
+            },
+        }
+    }
+
+    /// `title` shortened so a folder name made of it plus `fixed` more
+    /// bytes (year, tags) fits.
+    fn folder(&self, title: &str, fixed: usize) -> String {
+        fit_title(title, self.folder.saturating_sub(fixed))
+    }
+
+    /// `title` shortened so a file name made of it plus `fixed` more bytes
+    /// (year, episode tag, extension) fits.
+    fn file(&self, title: &str, fixed: usize) -> String {
+        fit_title(title, self.file.saturating_sub(fixed))
+    }
+}
+
+/// Build a Plex-compatible destination path for an enriched media file.
+///
+/// A pinned destination wins over everything else; home videos keep their
+/// file name under `Home Videos/YYYY/MM/`. Titles are shortened to keep
+/// names within `organize.max_name_length`; years, episode tags, and
+/// extensions are never cut.
+pub fn build_destination_path(
+    enriched: &EnrichedMedia,
+    source_file: &Path,
+    dest_root: &Path,
+    config: &AppConfig,
+) -> PathBuf {
+    let ext = source_file
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| format!(".{e}"))
+        .unwrap_or_default();
+
+    if let Some(pinned) = &enriched.pinned {
+        return dest_root.join(pinned);
+    }
+    if enriched.parsed.home_video {
+        return build_home_video_path(&enriched.parsed, source_file, &ext, dest_root, config);
+    }
+    let template = enriched
+        .parsed
+        .title_template
+        .as_ref()
+        .unwrap_or(&config.organize.title_template);
+    // Release details go before the extension, so on the file only.
+    let info = templates::file_info(&config.organize.file_info_template, &enriched.parsed);
+    let ids = enriched.external_ids();
+    if let Some(movie) = &enriched.movie {
+        let root = language_root(enriched, dest_root, config);
+        let name = titles::render(template, &movie.title, &titles::resolve(enriched), &ids);
+        let second = enriched.second_feature.as_ref();
+        let ext = match enriched.parsed.part {
+            Some(part) => format!("{info}{}{ext}", part::suffix(part)),
+            None => format!("{info}{ext}"),
+        };
+        let folder = match &enriched.collection {
+            Some(collection) if config.organize.collection_folders => {
+                templates::collection_dir(collection, &config.organize.collection_template)
+            }
+            _ => PathBuf::from(&config.organize.movies_dir),
+        };
+        let tag = templates::id_tag(&ids, config.organize.id_tags, true);
+        let root = root.join(folder);
+        let edition = match second {
+            Some(second) if config.organize.double_features == DoubleFeatures::Edition => {
+                Some(format!("With {}", second.title))
+            }
+            _ => (enriched.parsed.edition.as_ref())
+                .filter(|_| config.organize.edition_tags)
+                .map(|e| e.name.clone()),
+        };
+        return build_movie_path(&name, movie, edition.as_deref(), &tag, &ext, &root, config);
+    }
+    if let Some(tv) = &enriched.tv_episode {
+        let root = language_root(enriched, dest_root, config);
+        let name = titles::render(template, &tv.show_title, &titles::resolve(enriched), &ids);
+        let tag = templates::id_tag(&ids, config.organize.id_tags, false);
+        let ext = format!("{info}{ext}");
+        return build_tv_path(&name, tv, &tag, &ext, &root, config);
+    }
+    if let Some(music) = &enriched.music_track {
+        return build_music_path(music, &ext, dest_root, config);
+    }
+
+    // Fallback
+    let title = NameLimit::new(config).file(&sanitize_name(enriched.best_title()), ext.len());
+    dest_root.join("Unsorted").join(format!("{title}{ext}"))
+}
+
+/// Destination root after per-language routing (`organize.language_dirs`).
+fn language_root(enriched: &EnrichedMedia, dest_root: &Path, config: &AppConfig) -> PathBuf {
+    let routed = enriched
+        .parsed
+        .title_language
+        .as_ref()
+        .filter(|lang| lang.confidence >= language::TRUSTED_CONFIDENCE)
+        .and_then(|lang| config.organize.language_dirs.get(&lang.code));
+    match routed {
+        Some(dir) => dest_root.join(sanitize_name(dir)),
+        None => dest_root.to_path_buf(),
+    }
+}
+
+/// `Title (Year)/Title (Year).ext` under `root` (`Movies/` or a
+/// collection's folder), the title being `name` from
+/// `organize.title_template`. `edition` goes in a Plex edition tag: the
+/// file's cut with `organize.edition_tags`, or, for a double feature
+/// (named after its first movie) with `organize.double_features =
+/// "edition"`, the second movie. `tag` is the id tag, `ext` carries the
+/// release details (`organize.file_info_template`) and the ` - partN`
+/// suffix of a multi-part movie.
+fn build_movie_path(
+    name: &str,
+    movie: &Movie,
+    edition: Option<&str>,
+    tag: &str,
+    ext: &str,
+    root: &Path,
+    config: &AppConfig,
+) -> PathBuf {
+    let year = movie.year.map(|y| format!(" ({y})")).unwrap_or_default();
+    let edition = edition
+        .map(|e| format!(" {{edition-{}}}", sanitize_name(e)))
+        .unwrap_or_default();
+    // Cut for the file name, so folder and file keep the same title.
+    let fixed = year.len() + edition.len() + tag.len() + ext.len();
+    let title = NameLimit::new(config).file(&sanitize_name(name), fixed);
+    let folder = format!("{title}{year}{edition}{tag}");
+    let filename = format!("{folder}{ext}");
+    root.join(&folder).join(filename)
+}
+
+/// `Home Videos/2023/08/VID_20230812_140322.mp4`: dated by the name, else
+/// by the file; `Undated/` when neither is known.
+fn build_home_video_path(
+    parsed: &ParsedMedia,
+    source_file: &Path,
+    ext: &str,
+    root: &Path,
+    config: &AppConfig,
+) -> PathBuf {
+    let folder = root.join(&config.organize.home_videos_dir);
+    let folder = match home_video::recorded_date(&parsed.raw_filename).or(parsed.file_date) {
+        Some(date) => folder
+            .join(format!("{:04}", date.year()))
+            .join(format!("{:02}", date.month())),
+        None => folder.join("Undated"),
+    };
+    let stem = source_file
+        .file_stem()
+        .map(|n| n.to_string_lossy())
+        .unwrap_or_else(|| parsed.raw_filename.as_str().into());
+    let stem = NameLimit::new(config).file(&sanitize_name(&stem), ext.len());
+    folder.join(format!("{stem}{ext}"))
+}
+
+/// `TV Shows/Show/Season 01/Show - S01E02 - Episode.ext`, the show being
+/// `name` from `organize.title_template`; `tag`, the id tag, goes on the
+/// show folder only.
+fn build_tv_path(
+    name: &str,
+    ep: &TvEpisode,
+    tag: &str,
+    ext: &str,
+    root: &Path,
+    config: &AppConfig,
+) -> PathBuf {
+    let limit = NameLimit::new(config);
+    let show = sanitize_name(name);
+    let season_dir = format!("Season {:02}", ep.season);
+
+    let mut ep_tag = format!("S{:02}E{:02}", ep.season, ep.episode);
+    if let Some(end) = ep.episode_end {
+        ep_tag.push_str(&format!("-E{end:02}"));
+    }
+
+    // The episode title gives way first, then the show name.
+    let base = format!(
+        "{} - {ep_tag}",
+        limit.file(&show, ep_tag.len() + 3 + ext.len())
+    );
+    let room = limit.file.saturating_sub(base.len() + 3 + ext.len());
+    let filename = match &ep.episode_title {
+        Some(t) if !t.is_empty() && room >= MIN_EPISODE_TITLE => {
+            format!("{base} - {}{ext}", fit_title(&sanitize_name(t), room))
+        }
+        _ => format!("{base}{ext}"),
+    };
+
+    root.join(&config.organize.tv_dir)
+        .join(format!("{}{tag}", limit.folder(&show, tag.len())))
+        .join(&season_dir)
+        .join(filename)
+}
+
+fn build_music_path(track: &MusicTrack, ext: &str, root: &Path, config: &AppConfig) -> PathBuf {
+    let limit = NameLimit::new(config);
+    let artist = sanitize_name(if track.artist.is_empty() {
+        "Unknown Artist"
+    } else {
+        &track.artist
+    });
+    let artist = limit.folder(&artist, 0);
+    let album_name = sanitize_name(track.album.as_deref().unwrap_or("Unknown Album"));
+    let year = track.year.map(|y| format!(" ({y})")).unwrap_or_default();
+    let album_dir = format!("{}{year}", limit.folder(&album_name, year.len()));
+
+    let number = track
+        .track_number
+        .map(|n| format!("{n:02} - "))
+        .unwrap_or_default();
+    let track_name = sanitize_name(track.track_title.as_deref().unwrap_or("Track"));
+    let track_name = limit.file(&track_name, number.len() + ext.len());
+    let filename = format!("{number}{track_name}{ext}");
+
+    root.join(&config.organize.music_dir)
+        .join(&artist)
+        .join(&album_dir)
+        .join(filename)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::IdTags;
+    use crate::models::{Edition, MediaType, ParsedMedia};
+    use crate::quality::ReleaseAttributes;
+
+    fn make_movie_enriched(title: &str, year: Option<i32>) -> EnrichedMedia {
+        let parsed = ParsedMedia {
+            title: title.to_string(),
+            year,
+            media_type: MediaType::Movie,
+            confidence: 80.0,
+            ..Default::default()
+        };
+        let mut e = EnrichedMedia::from_parsed(parsed);
+        e.movie = Some(Movie {
+            title: title.to_string(),
+            year,
+            tmdb_id: None,
+            original_title: None,
+            confidence: 80.0,
+        });
+        e
+    }
+
+    #[test]
+    fn test_movie_path() {
+        let config = AppConfig::default();
+        let enriched = make_movie_enriched("The Matrix", Some(1999));
+        let source = Path::new("/downloads/The.Matrix.1999.mkv");
+        let dest = build_destination_path(&enriched, source, Path::new("/plex"), &config);
+
+        assert_eq!(
+            dest,
+            PathBuf::from("/plex/Movies/The Matrix (1999)/The Matrix (1999).mkv")
+        );
+
+        let mut enriched = make_movie_enriched("The Matrix", Some(1999));
+        enriched.parsed.part = Some(2);
+        let source = Path::new("/downloads/The.Matrix.1999.CD2.avi");
+        let dest = build_destination_path(&enriched, source, Path::new("/plex"), &config);
+        assert_eq!(
+            dest,
+            PathBuf::from("/plex/Movies/The Matrix (1999)/The Matrix (1999) - part2.avi")
+        );
+    }
+
+    #[test]
+    fn test_collection_folder_path() {
+        let mut config = AppConfig::default();
+        let mut enriched = make_movie_enriched("Iron Man", Some(2008));
+        enriched.collection = Some("Iron Man Collection".to_string());
+        let source = Path::new("/downloads/Iron.Man.2008.mkv");
+        let dest = build_destination_path(&enriched, source, Path::new("/plex"), &config);
+        assert_eq!(
+            dest,
+            PathBuf::from("/plex/Movies/Iron Man (2008)/Iron Man (2008).mkv")
+        );
+
+        config.organize.collection_folders = true;
+        let dest = build_destination_path(&enriched, source, Path::new("/plex"), &config);
+        assert_eq!(
+            dest,
+            PathBuf::from(
+                "/plex/Collections/Iron Man Collection/Iron Man (2008)/Iron Man (2008).mkv"
+            )
+        );
+        config.organize.collection_template = "Movies/{collection}".to_string();
+        enriched.collection = Some("Mad Max: Collection".to_string());
+        let dest = build_destination_path(&enriched, source, Path::new("/plex"), &config);
+        assert!(dest.starts_with(format!(
+            "/plex/Movies/{}",
+            sanitize_name("Mad Max: Collection")
+        )));
+    }
+
+    #[test]
+    fn test_double_feature_path() {
+        let mut config = AppConfig::default();
+        let mut enriched = make_movie_enriched("Planet Terror", Some(2007));
+        enriched.second_feature = make_movie_enriched("Death Proof", Some(2007)).movie;
+        let source = Path::new("/downloads/Grindhouse.Planet.Terror.Death.Proof.2007.mkv");
+
+        let dest = build_destination_path(&enriched, source, Path::new("/plex"), &config);
+        assert_eq!(
+            dest,
+            PathBuf::from("/plex/Movies/Planet Terror (2007)/Planet Terror (2007).mkv")
+        );
+
+        config.organize.double_features = DoubleFeatures::Edition;
+        let dest = build_destination_path(&enriched, source, Path::new("/plex"), &config);
+        assert_eq!(
+            dest,
+            PathBuf::from(
+                "/plex/Movies/Planet Terror (2007) {edition-With Death Proof}/\
+                 Planet Terror (2007) {edition-With Death Proof}.mkv"
+            )
+        );
+    }
+
+    #[test]
+    fn test_edition_tags() {
+        let mut config = AppConfig::default();
+        let mut enriched = make_movie_enriched("Aliens", Some(1986));
+        enriched.parsed.edition = Some(Edition {
+            name: "Director's Cut".to_string(),
+            year: None,
+        });
+        let source = Path::new("/downloads/Aliens.1986.Directors.Cut.mkv");
+        let dest = build_destination_path(&enriched, source, Path::new("/plex"), &config);
+        assert_eq!(
+            dest,
+            PathBuf::from("/plex/Movies/Aliens (1986)/Aliens (1986).mkv")
+        );
+
+        config.organize.edition_tags = true;
+        let dest = build_destination_path(&enriched, source, Path::new("/plex"), &config);
+        assert_eq!(
+            dest,
+            PathBuf::from(
+                "/plex/Movies/Aliens (1986) {edition-Director's Cut}/\
+                 Aliens (1986) {edition-Director's Cut}.mkv"
+            )
+        );
+    }
+
+    #[test]
+    fn test_file_info_template() {
+        let mut config = AppConfig::default();
+        config.organize.file_info_template = "[{resolution} {hdr}]".to_string();
+        let mut enriched = make_movie_enriched("Dune", Some(2021));
+        enriched.parsed.quality = "2160p WEB-DL DV H.265".to_string();
+        enriched.parsed.attributes = ReleaseAttributes::parse("Dune.2021.2160p.DV.mkv");
+        let source = Path::new("/downloads/Dune.2021.2160p.DV.mkv");
+        let dest = build_destination_path(&enriched, source, Path::new("/plex"), &config);
+        assert_eq!(
+            dest,
+            PathBuf::from("/plex/Movies/Dune (2021)/Dune (2021) [2160p DV].mkv")
+        );
+    }
+
+    #[test]
+    fn test_movie_routed_by_title_language() {
+        let mut config = AppConfig::default();
+        config
+            .organize
+            .language_dirs
+            .insert("ko".to_string(), "Korean".to_string());
+        let mut enriched = make_movie_enriched("기생충", Some(2019));
+        enriched.parsed.title_language = crate::language::detect_title_language("기생충");
+        let source = Path::new("/downloads/기생충.2019.mkv");
+        let dest = build_destination_path(&enriched, source, Path::new("/plex"), &config);
+
+        assert_eq!(
+            dest,
+            PathBuf::from("/plex/Korean/Movies/기생충 (2019)/기생충 (2019).mkv")
+        );
+    }
+
+    #[test]
+    fn test_title_template_names_variants() {
+        let mut config = AppConfig::default();
+        config.organize.title_template = "{english} [{original}]".to_string();
+        let mut enriched = make_movie_enriched("寄生虫 Parasite", Some(2019));
+        enriched.parsed.titles = crate::titles::split_title("寄生虫 Parasite");
+        let source = Path::new("/downloads/寄生虫.Parasite.2019.mkv");
+        let dest = build_destination_path(&enriched, source, Path::new("/plex"), &config);
+
+        assert_eq!(
+            dest,
+            PathBuf::from("/plex/Movies/Parasite [寄生虫] (2019)/Parasite [寄生虫] (2019).mkv")
+        );
+    }
+
+    #[test]
+    fn test_id_tags_name_movies() {
+        let mut config = AppConfig::default();
+        config.organize.id_tags = IdTags::Imdb;
+        let mut enriched = make_movie_enriched("Heat", Some(1995));
+        enriched.parsed.ids.imdb = Some("tt0113277".to_string());
+        enriched.movie.as_mut().unwrap().tmdb_id = Some(949);
+        let source = Path::new("/downloads/Heat.1995.{imdb-tt0113277}.mkv");
+        let dest = build_destination_path(&enriched, source, Path::new("/plex"), &config);
+        assert_eq!(
+            dest,
+            PathBuf::from(
+                "/plex/Movies/Heat (1995) {imdb-tt0113277}/Heat (1995) {imdb-tt0113277}.mkv"
+            )
+        );
+
+        // The name's IMDb id belongs to another movie than the match.
+        enriched.parsed.ids.tmdb = Some(11);
+        let dest = build_destination_path(&enriched, source, Path::new("/plex"), &config);
+        assert_eq!(
+            dest,
+            PathBuf::from("/plex/Movies/Heat (1995) {tmdb-949}/Heat (1995) {tmdb-949}.mkv")
+        );
+    }
+
+    #[test]
+    fn test_tv_path() {
+        let config = AppConfig::default();
+        let parsed = ParsedMedia {
+            title: "Breaking Bad".to_string(),
+            media_type: MediaType::Tv,
+            confidence: 80.0,
+            ..Default::default()
+        };
+        let mut enriched = EnrichedMedia::from_parsed(parsed);
+        enriched.tv_episode = Some(TvEpisode {
+            show_title: "Breaking Bad".to_string(),
+            season: 1,
+            episode: 1,
+            episode_end: None,
+            episode_title: Some("Pilot".to_string()),
+            year: Some(2008),
+            tmdb_id: None,
+            confidence: 80.0,
+        });
+
+        let source = Path::new("/downloads/Breaking.Bad.S01E01.mkv");
+        let dest = build_destination_path(&enriched, source, Path::new("/plex"), &config);
+
+        assert_eq!(
+            dest,
+            PathBuf::from(
+                "/plex/TV Shows/Breaking Bad/Season 01/Breaking Bad - S01E01 - Pilot.mkv"
+            )
+        );
+    }
+
+    #[test]
+    fn test_music_path() {
+        let config = AppConfig::default();
+        let parsed = ParsedMedia {
+            title: "Song Title".to_string(),
+            media_type: MediaType::Music,
+            confidence: 60.0,
+            ..Default::default()
+        };
+        let mut enriched = EnrichedMedia::from_parsed(parsed);
+        enriched.music_track = Some(MusicTrack {
+            artist: "Artist".to_string(),
+            album: Some("Album".to_string()),
+            track_title: Some("Song Title".to_string()),
+            track_number: Some(1),
+            year: Some(2020),
+            confidence: 60.0,
+        });
+
+        let source = Path::new("/music/01 - Song Title.flac");
+        let dest = build_destination_path(&enriched, source, Path::new("/plex"), &config);
+
+        assert_eq!(
+            dest,
+            PathBuf::from("/plex/Music/Artist/Album (2020)/01 - Song Title.flac")
+        );
+    }
+
+    #[test]
+    fn test_home_video_path() {
+        let config = AppConfig::default();
+        let dest = |name: &str, file_date| {
+            let mut parsed = crate::parser::parse_video(name, &Default::default());
+            parsed.file_date = file_date;
+            let enriched = crate::enricher::Enricher::new(config.clone()).enrich(parsed);
+            let source = Path::new("/phone").join(name);
+            build_destination_path(&enriched, &source, Path::new("/plex"), &config)
+        };
+
+        assert_eq!(
+            dest("VID_20230812_140322.mp4", None),
+            PathBuf::from("/plex/Home Videos/2023/08/VID_20230812_140322.mp4")
+        );
+        let mtime = chrono::NaiveDate::from_ymd_opt(2021, 1, 5);
+        assert_eq!(
+            dest("IMG_2034.MOV", mtime),
+            PathBuf::from("/plex/Home Videos/2021/01/IMG_2034.MOV")
+        );
+        assert_eq!(
+            dest("IMG_2035.MOV", None),
+            PathBuf::from("/plex/Home Videos/Undated/IMG_2035.MOV")
+        );
+    }
+
+    #[test]
+    fn test_long_names_fit_max_length() {
+        let mut config = AppConfig::default();
+        config.organize.max_name_length = 143;
+        let title = "The Assassination of Jesse James by the Coward Robert Ford \
+                     and Other Very Long Subtitles That Keep Going Well Past Any \
+                     Reasonable Limit";
+        let enriched = make_movie_enriched(title, Some(2007));
+        let source = Path::new("/downloads/jesse.james.mkv");
+        let dest = build_destination_path(&enriched, source, Path::new("/plex"), &config);
+
+        let file = dest.file_name().unwrap().to_string_lossy().into_owned();
+        let folder = dest
+            .parent()
+            .unwrap()
+            .file_name()
+            .unwrap()
+            .to_string_lossy();
+        assert!(file.len() + COMPANION_RESERVE <= 143, "{file}");
+        assert!(file.ends_with(" (2007).mkv"), "{file}");
+        assert_eq!(format!("{folder}.mkv"), file);
+        assert!(file.starts_with("The Assassination of Jesse James"));
+
+        let mut enriched = EnrichedMedia::from_parsed(ParsedMedia::default());
+        enriched.tv_episode = Some(TvEpisode {
+            show_title: title.to_string(),
+            season: 1,
+            episode: 2,
+            episode_end: None,
+            episode_title: Some("An Episode Title Long Enough to Need Cutting".to_string()),
+            year: None,
+            tmdb_id: None,
+            confidence: 80.0,
+        });
+        let dest = build_destination_path(&enriched, source, Path::new("/plex"), &config);
+        let file = dest.file_name().unwrap().to_string_lossy().into_owned();
+        assert!(file.len() + COMPANION_RESERVE <= 143, "{file}");
+        assert!(file.ends_with(" - S01E02.mkv"), "{file}");
+
+        config.organize.max_name_length = 0;
+        let dest = build_destination_path(&enriched, source, Path::new("/plex"), &config);
+        let file = dest.file_name().unwrap().to_string_lossy().into_owned();
+        assert!(file.ends_with("Need Cutting.mkv"), "{file}");
+    }
+}
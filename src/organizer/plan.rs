@@ -0,0 +1,448 @@
+//! Plan generation and saved-plan persistence.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use tracing::warn;
+
+use super::paths::build_destination_path;
+use super::upgrade::{self, AlreadyHad, Verdict};
+use crate::artwork;
+use crate::config::{AppConfig, Extras};
+use crate::error::Error;
+use crate::extras;
+use crate::hash;
+use crate::models::{EnrichedMedia, MediaType, OrganizeAction, SavedPlan};
+use crate::profile;
+use crate::schema::{self, Artifact};
+use crate::subtitles;
+use crate::titles;
+
+/// Generate planned file operations without executing them.
+///
+/// Discovers subtitle, artwork, and extras companions and creates
+/// co-located actions.
+pub fn plan_actions(
+    items: &[(PathBuf, EnrichedMedia)],
+    dest_root: &Path,
+    config: &AppConfig,
+    strategy: &str,
+) -> Vec<OrganizeAction> {
+    plan(items, dest_root, config, strategy, Path::exists, None).0
+}
+
+/// Like [`plan_actions`], but comparing each movie with the copies already
+/// in its folder: a better file replaces them, and one no better is left
+/// out and returned as already had. `known` gives the quality the database
+/// recorded for a file in the library.
+pub fn plan_upgrades(
+    items: &[(PathBuf, EnrichedMedia)],
+    dest_root: &Path,
+    config: &AppConfig,
+    strategy: &str,
+    known: &dyn Fn(&Path) -> Option<String>,
+) -> (Vec<OrganizeAction>, Vec<AlreadyHad>) {
+    plan(
+        items,
+        dest_root,
+        config,
+        strategy,
+        Path::exists,
+        Some(known),
+    )
+}
+
+/// Like [`plan_actions`], but ignoring files already at the destinations:
+/// the layout `items` should have, for comparing against what is on disk.
+pub fn plan_layout(
+    items: &[(PathBuf, EnrichedMedia)],
+    dest_root: &Path,
+    config: &AppConfig,
+    strategy: &str,
+) -> Vec<OrganizeAction> {
+    plan(items, dest_root, config, strategy, |_| false, None).0
+}
+
+fn plan(
+    items: &[(PathBuf, EnrichedMedia)],
+    dest_root: &Path,
+    config: &AppConfig,
+    strategy: &str,
+    occupied: impl Fn(&Path) -> bool,
+    upgrades: Option<&dyn Fn(&Path) -> Option<String>>,
+) -> (Vec<OrganizeAction>, Vec<AlreadyHad>) {
+    let mut actions = Vec::new();
+    let mut already_had = Vec::new();
+    let mut used_dests: HashSet<PathBuf> = HashSet::new();
+    // Copies an earlier upgrade in this plan replaces.
+    let mut claimed: HashSet<PathBuf> = HashSet::new();
+
+    // Pinned files claim their exact names before anything else is placed.
+    let (pinned, parsed): (Vec<_>, Vec<_>) = items.iter().partition(|(_, e)| e.pinned.is_some());
+    for (source, enriched) in pinned.into_iter().chain(parsed) {
+        let mut dest = build_destination_path(enriched, source, dest_root, config);
+
+        // A single-file movie against the copies already in its folder
+        let upgradable = enriched.media_type == MediaType::Movie
+            && enriched.pinned.is_none()
+            && enriched.parsed.part.is_none()
+            && !used_dests.contains(&dest);
+        let mut replaces = Vec::new();
+        if let Some(known) = upgrades.filter(|_| upgradable) {
+            let copies = upgrade::existing_copies(&dest, &claimed);
+            match upgrade::judge(source, &enriched.parsed.quality, &copies, known) {
+                Verdict::Plan => {}
+                Verdict::Replace(copies) => {
+                    claimed.extend(copies.iter().map(|c| c.path.clone()));
+                    replaces = copies;
+                }
+                Verdict::Skip(had) => {
+                    already_had.push(had);
+                    continue;
+                }
+            }
+        }
+        let replaced = |dest: &Path| replaces.iter().any(|c| c.path == dest);
+
+        // Handle duplicates with counter suffix
+        let original_dest = dest.clone();
+        let mut counter = 1u32;
+        while enriched.pinned.is_none()
+            && (used_dests.contains(&dest) || (occupied(&dest) && !replaced(&dest)))
+        {
+            let stem = original_dest
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("file");
+            let ext = original_dest
+                .extension()
+                .and_then(|e| e.to_str())
+                .map(|e| format!(".{e}"))
+                .unwrap_or_default();
+            dest = original_dest
+                .parent()
+                .unwrap_or(Path::new("."))
+                .join(format!("{stem} ({counter}){ext}"));
+            counter += 1;
+        }
+        used_dests.insert(dest.clone());
+
+        actions.push(OrganizeAction {
+            source: source.clone(),
+            destination: dest.clone(),
+            replaces,
+            ..action_for(enriched, strategy)
+        });
+
+        // Discover and plan subtitle companions
+        let video_stem = dest.file_stem().and_then(|s| s.to_str()).unwrap_or("file");
+        let folder = dest.parent().unwrap_or(Path::new("."));
+        for companion in subtitles::find_companions(source) {
+            let sub_ext = companion
+                .path
+                .extension()
+                .and_then(|e| e.to_str())
+                .map(|e| format!(".{e}"))
+                .unwrap_or_default();
+            let sub_dest = folder.join(format!("{video_stem}{}{sub_ext}", companion.suffix));
+
+            if !used_dests.insert(sub_dest.clone()) {
+                // Plex takes one subtitle per language and flags.
+                warn!(
+                    "Subtitle {} would share the name {}; left in place",
+                    companion.path.display(),
+                    sub_dest.display()
+                );
+                continue;
+            }
+            actions.push(OrganizeAction {
+                source: companion.path,
+                destination: sub_dest,
+                companion_of: Some(dest.clone()),
+                ..action_for(enriched, strategy)
+            });
+        }
+
+        // Posters, fanart, and theme music of a curated movie folder
+        let keep_artwork = enriched.media_type == MediaType::Movie
+            && profile::rules_for(config, &enriched.parsed).preserve_artwork;
+        if keep_artwork {
+            for art in artwork::find_artwork(source, video_stem) {
+                let art_dest = folder.join(&art.name);
+                if used_dests.insert(art_dest.clone()) {
+                    actions.push(OrganizeAction {
+                        source: art.path,
+                        destination: art_dest,
+                        companion_of: Some(dest.clone()),
+                        ..action_for(enriched, strategy)
+                    });
+                }
+            }
+        }
+
+        // Trailers, featurettes, and the like, into Plex's extras folders, along
+        // with the other videos of a one-movie-per-folder unit
+        if enriched.media_type == MediaType::Movie && config.organize.extras == Extras::Organize {
+            let unit_extras = enriched.parsed.unit_extras.iter().cloned();
+            let found = extras::find_extras(source)
+                .into_iter()
+                .chain(unit_extras.map(extras::unit_extra));
+            for extra in found {
+                let extra_dest = folder.join(extra.relative_destination());
+                if used_dests.insert(extra_dest.clone()) {
+                    actions.push(OrganizeAction {
+                        source: extra.path,
+                        destination: extra_dest,
+                        companion_of: Some(dest.clone()),
+                        ..action_for(enriched, strategy)
+                    });
+                }
+            }
+        }
+    }
+
+    (actions, already_had)
+}
+
+/// An action carrying `enriched`'s metadata, for a file that travels with
+/// it; `source` and `destination` are filled in by the caller.
+fn action_for(enriched: &EnrichedMedia, strategy: &str) -> OrganizeAction {
+    OrganizeAction {
+        strategy: strategy.to_string(),
+        media_type: enriched.media_type,
+        title: enriched.best_title().to_string(),
+        titles: titles::resolve(enriched),
+        year: enriched.best_year(),
+        quality: enriched.parsed.quality.clone(),
+        confidence: enriched.confidence,
+        tmdb_id: enriched.tmdb_id(),
+        second_feature: enriched.second_feature_label(),
+        ..Default::default()
+    }
+}
+
+/// Record size, mtime, and a quick content hash for every action's source.
+///
+/// Sources that cannot be read are left without a fingerprint and will be
+/// reported as missing when the plan is applied.
+pub fn fingerprint_actions(actions: &mut [OrganizeAction]) {
+    for action in actions.iter_mut() {
+        match hash::fingerprint(&action.source, true) {
+            Ok(fp) => action.fingerprint = Some(fp),
+            Err(e) => warn!("Could not fingerprint {}: {}", action.source.display(), e),
+        }
+    }
+}
+
+/// Write a plan to disk as pretty-printed JSON.
+pub fn save_plan(path: &Path, plan: &SavedPlan) -> crate::Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create dir: {}", parent.display()))
+            .map_err(Error::Io)?;
+    }
+    let json = serde_json::to_string_pretty(plan)
+        .context("Failed to serialize plan")
+        .map_err(Error::Io)?;
+    fs::write(path, json)
+        .with_context(|| format!("Failed to write plan: {}", path.display()))
+        .map_err(Error::Io)?;
+    Ok(())
+}
+
+/// Read a plan previously written by [`save_plan`].
+pub fn load_plan(path: &Path) -> crate::Result<SavedPlan> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read plan: {}", path.display()))
+        .map_err(Error::Io)?;
+    schema::from_str(Artifact::Plan, &content)
+        .with_context(|| format!("Failed to parse plan: {}", path.display()))
+        .map_err(Error::Parse)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Movie;
+
+    #[test]
+    fn test_save_and_load_plan_roundtrip() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = tmp.path().join("movie.mkv");
+        fs::write(&source, b"video content").unwrap();
+
+        let mut actions = vec![OrganizeAction {
+            source: source.clone(),
+            destination: tmp.path().join("dest/Movie (2024)/Movie (2024).mkv"),
+            strategy: "move".to_string(),
+            media_type: MediaType::Movie,
+            title: "Movie".to_string(),
+            confidence: 80.0,
+            ..Default::default()
+        }];
+        fingerprint_actions(&mut actions);
+        assert_eq!(actions[0].fingerprint.as_ref().unwrap().size, 13);
+
+        let plan_path = tmp.path().join("plans/plan.json");
+        let plan = SavedPlan::new(tmp.path().join("dest"), actions);
+        save_plan(&plan_path, &plan).unwrap();
+
+        let loaded = load_plan(&plan_path).unwrap();
+        assert_eq!(loaded.actions.len(), 1);
+        assert_eq!(loaded.actions[0].fingerprint, plan.actions[0].fingerprint);
+    }
+
+    #[test]
+    fn test_pinned_file_claims_its_name_first() {
+        let mut heat = EnrichedMedia::from_parsed(crate::parser::parse_video(
+            "Heat.1995.mkv",
+            &Default::default(),
+        ));
+        heat.movie = Some(Movie {
+            title: "Heat".to_string(),
+            year: Some(1995),
+            tmdb_id: None,
+            original_title: None,
+            confidence: 90.0,
+        });
+        let mut pinned = EnrichedMedia::from_parsed(crate::parser::parse_video(
+            "fan.edit.mkv",
+            &Default::default(),
+        ));
+        pinned.pinned = Some(PathBuf::from("Movies/Heat (1995)/Heat (1995).mkv"));
+        let items = vec![
+            (PathBuf::from("/dl/Heat.1995.mkv"), heat),
+            (PathBuf::from("/dl/fan.edit.mkv"), pinned),
+        ];
+
+        let actions = plan_layout(&items, Path::new("/lib"), &AppConfig::default(), "move");
+        let dest_of = |src: &str| {
+            let action = actions.iter().find(|a| a.source == Path::new(src));
+            action.unwrap().destination.clone()
+        };
+        assert_eq!(
+            dest_of("/dl/fan.edit.mkv"),
+            Path::new("/lib/Movies/Heat (1995)/Heat (1995).mkv")
+        );
+        assert_eq!(
+            dest_of("/dl/Heat.1995.mkv"),
+            Path::new("/lib/Movies/Heat (1995)/Heat (1995) (1).mkv")
+        );
+    }
+
+    #[test]
+    fn test_curated_artwork_moves_with_movie() {
+        let tmp = tempfile::tempdir().unwrap();
+        let folder = tmp.path().join("Heat.1995.1080p");
+        fs::create_dir_all(&folder).unwrap();
+        for name in ["Heat.1995.1080p.mkv", "cover.jpg", "theme.mp3"] {
+            fs::write(folder.join(name), b"x").unwrap();
+        }
+        let mut heat = EnrichedMedia::from_parsed(crate::parser::parse_video(
+            "Heat.1995.1080p.mkv",
+            &Default::default(),
+        ));
+        heat.movie = Some(Movie {
+            title: "Heat".to_string(),
+            year: Some(1995),
+            tmdb_id: None,
+            original_title: None,
+            confidence: 90.0,
+        });
+        let items = vec![(folder.join("Heat.1995.1080p.mkv"), heat)];
+
+        let mut config = AppConfig::default();
+        let dests = |config: &AppConfig| {
+            let actions = plan_layout(&items, Path::new("/lib"), config, "move");
+            let mut dests: Vec<_> = actions.into_iter().map(|a| a.destination).collect();
+            dests.sort();
+            dests
+        };
+        let heat_dir = Path::new("/lib/Movies/Heat (1995)");
+        assert_eq!(
+            dests(&config),
+            [
+                heat_dir.join("Heat (1995).mkv"),
+                heat_dir.join("poster.jpg"),
+                heat_dir.join("theme.mp3"),
+            ]
+        );
+
+        config.organize.preserve_artwork = false;
+        assert!(!dests(&config).contains(&heat_dir.join("poster.jpg")));
+    }
+
+    #[test]
+    fn test_extras_move_into_plex_folders() {
+        let tmp = tempfile::tempdir().unwrap();
+        let folder = tmp.path().join("Heat.1995.1080p");
+        fs::create_dir_all(folder.join("Featurettes")).unwrap();
+        for name in [
+            "Heat.1995.1080p.mkv",
+            "Heat.1995.Trailer.mkv",
+            "Featurettes/Making Of.mkv",
+        ] {
+            fs::write(folder.join(name), b"x").unwrap();
+        }
+        let mut heat = EnrichedMedia::from_parsed(crate::parser::parse_video(
+            "Heat.1995.1080p.mkv",
+            &Default::default(),
+        ));
+        heat.movie = Some(Movie {
+            title: "Heat".to_string(),
+            year: Some(1995),
+            tmdb_id: None,
+            original_title: None,
+            confidence: 90.0,
+        });
+        let items = vec![(folder.join("Heat.1995.1080p.mkv"), heat)];
+
+        let mut config = AppConfig::default();
+        config.organize.extras = Extras::Organize;
+        let actions = plan_layout(&items, Path::new("/lib"), &config, "move");
+        let heat_dir = Path::new("/lib/Movies/Heat (1995)");
+        let extras: Vec<_> = actions
+            .iter()
+            .filter(|a| a.companion_of.is_some())
+            .map(|a| a.destination.clone())
+            .collect();
+        assert_eq!(
+            extras,
+            [
+                heat_dir.join("Trailers/Heat.1995.Trailer.mkv"),
+                heat_dir.join("Featurettes/Making Of.mkv"),
+            ]
+        );
+
+        // A menu makes the folder ambiguous to `find_extras`, but as one
+        // movie per folder every other video is an extra.
+        fs::write(folder.join("menu.mkv"), b"x").unwrap();
+        let mut items = items;
+        items[0].1.parsed.unit_extras = vec![
+            folder.join("Featurettes/Making Of.mkv"),
+            folder.join("Heat.1995.Trailer.mkv"),
+            folder.join("menu.mkv"),
+        ];
+        let extras: Vec<_> = plan_layout(&items, Path::new("/lib"), &config, "move")
+            .into_iter()
+            .filter(|a| a.companion_of.is_some())
+            .map(|a| a.destination)
+            .collect();
+        assert_eq!(
+            extras,
+            [
+                heat_dir.join("Featurettes/Making Of.mkv"),
+                heat_dir.join("Trailers/Heat.1995.Trailer.mkv"),
+                heat_dir.join("Other/menu.mkv"),
+            ]
+        );
+
+        config.organize.extras = Extras::Skip;
+        assert_eq!(
+            plan_layout(&items, Path::new("/lib"), &config, "move").len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,201 @@
+//! Provenance breadcrumbs — where did this library file come from?
+//!
+//! The library database always records the original path. Depending on
+//! `organize.provenance`, a copy can also travel with the file itself so it
+//! survives losing the database:
+//!
+//! - `database` — database only (default)
+//! - `sidecar`  — `<file>.origin.json` next to the organized file
+//! - `xattr`    — extended attribute on the file (Linux/macOS)
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tracing::debug;
+
+use crate::config::Provenance;
+
+/// Extended attribute name used for the original path.
+#[cfg(target_os = "linux")]
+const XATTR_NAME: &str = "user.plex-org.origin";
+#[cfg(target_os = "macos")]
+const XATTR_NAME: &str = "com.plex-org.origin";
+
+/// Contents of an `.origin.json` sidecar.
+#[derive(Debug, Serialize)]
+struct OriginSidecar<'a> {
+    source: String,
+    original_filename: &'a str,
+    organized_at: &'a str,
+    tool: &'static str,
+    version: &'static str,
+}
+
+/// Path of the sidecar for an organized file: `<file>.origin.json`.
+pub fn sidecar_path(destination: &Path) -> PathBuf {
+    let mut name = destination.file_name().unwrap_or_default().to_os_string();
+    name.push(".origin.json");
+    destination.with_file_name(name)
+}
+
+/// Leave a breadcrumb of `source` on the organized `destination`.
+pub fn write_breadcrumb(
+    mode: Provenance,
+    source: &Path,
+    destination: &Path,
+    organized_at: &str,
+) -> io::Result<()> {
+    // The breadcrumb must make sense from anywhere, not just the CWD of the run.
+    let source = &std::path::absolute(source)?;
+    match mode {
+        Provenance::Database => Ok(()),
+        Provenance::Sidecar => {
+            let sidecar = OriginSidecar {
+                source: source.to_string_lossy().to_string(),
+                original_filename: source.file_name().and_then(|n| n.to_str()).unwrap_or(""),
+                organized_at,
+                tool: "plex-org",
+                version: env!("CARGO_PKG_VERSION"),
+            };
+            let json = serde_json::to_string_pretty(&sidecar)?;
+            fs::write(sidecar_path(destination), json)
+        }
+        Provenance::Xattr => {
+            if destination.is_symlink() {
+                debug!("not tagging symlink: {}", destination.display());
+                return Ok(());
+            }
+            set_xattr(destination, source.to_string_lossy().as_bytes())
+        }
+    }
+}
+
+/// Remove a sidecar left by [`write_breadcrumb`], if any.
+pub fn remove_sidecar(destination: &Path) {
+    let sidecar = sidecar_path(destination);
+    if sidecar.exists() {
+        let _ = fs::remove_file(sidecar);
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn set_xattr(path: &Path, value: &[u8]) -> io::Result<()> {
+    use std::ffi::{c_char, c_int, c_void, CString};
+    use std::os::unix::ffi::OsStrExt;
+
+    extern "C" {
+        fn setxattr(
+            path: *const c_char,
+            name: *const c_char,
+            value: *const c_void,
+            size: usize,
+            flags: c_int,
+        ) -> c_int;
+    }
+
+    let c_path = CString::new(path.as_os_str().as_bytes())?;
+    let c_name = CString::new(XATTR_NAME)?;
+    // SAFETY: both strings are NUL-terminated and outlive the call; the
+    // value pointer/length pair describes a live slice.
+    let rc = unsafe {
+        setxattr(
+            c_path.as_ptr(),
+            c_name.as_ptr(),
+            value.as_ptr().cast(),
+            value.len(),
+            0,
+        )
+    };
+    if rc == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn set_xattr(path: &Path, value: &[u8]) -> io::Result<()> {
+    use std::ffi::{c_char, c_int, c_void, CString};
+    use std::os::unix::ffi::OsStrExt;
+
+    extern "C" {
+        fn setxattr(
+            path: *const c_char,
+            name: *const c_char,
+            value: *const c_void,
+            size: usize,
+            position: u32,
+            options: c_int,
+        ) -> c_int;
+    }
+
+    let c_path = CString::new(path.as_os_str().as_bytes())?;
+    let c_name = CString::new(XATTR_NAME)?;
+    // SAFETY: see the Linux variant.
+    let rc = unsafe {
+        setxattr(
+            c_path.as_ptr(),
+            c_name.as_ptr(),
+            value.as_ptr().cast(),
+            value.len(),
+            0,
+            0,
+        )
+    };
+    if rc == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn set_xattr(_path: &Path, _value: &[u8]) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "extended attributes are only supported on Linux and macOS",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sidecar_path_keeps_extension() {
+        assert_eq!(
+            sidecar_path(Path::new("/plex/Heat (1995)/Heat (1995).mkv")),
+            PathBuf::from("/plex/Heat (1995)/Heat (1995).mkv.origin.json")
+        );
+    }
+
+    #[test]
+    fn test_sidecar_breadcrumb_roundtrip() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dest = tmp.path().join("Heat (1995).mkv");
+        fs::write(&dest, b"video").unwrap();
+
+        let source = Path::new("/downloads/Heat.1995.1080p.mkv");
+        write_breadcrumb(Provenance::Sidecar, source, &dest, "2026-03-01T10:00:00Z").unwrap();
+
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(sidecar_path(&dest)).unwrap()).unwrap();
+        let expected = std::path::absolute(source).unwrap();
+        assert_eq!(json["source"], expected.to_string_lossy().as_ref());
+        assert_eq!(json["original_filename"], "Heat.1995.1080p.mkv");
+
+        remove_sidecar(&dest);
+        assert!(!sidecar_path(&dest).exists());
+    }
+
+    #[test]
+    fn test_database_mode_writes_nothing() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dest = tmp.path().join("Heat (1995).mkv");
+        fs::write(&dest, b"video").unwrap();
+        write_breadcrumb(Provenance::Database, Path::new("/dl/x.mkv"), &dest, "").unwrap();
+        assert_eq!(fs::read_dir(tmp.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,182 @@
+//! Free-space checks before a run.
+//!
+//! A copy, a reflink, and a move onto another filesystem each need room
+//! for the whole file at the destination (a reflink the filesystem cannot
+//! share is a copy); a rename within one filesystem, a hardlink, and a
+//! symlink need none. [`space_needs`] adds up what a plan needs on each
+//! destination filesystem, and a run that would not fit, leaving
+//! `organize.space_reserve_mb` free, is refused before any file is touched.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+
+use crate::models::OrganizeAction;
+use crate::utils::format_size;
+
+/// What a plan needs on one destination filesystem.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpaceNeed {
+    /// An existing folder on that filesystem, nearest the destinations.
+    pub path: PathBuf,
+    /// Bytes the plan's files take up there.
+    pub needed: u64,
+    /// Bytes free there, when the platform can tell.
+    pub available: Option<u64>,
+}
+
+impl SpaceNeed {
+    /// Whether the plan fits and leaves `reserve` bytes free; unknown free
+    /// space is taken to fit.
+    pub fn fits(&self, reserve: u64) -> bool {
+        self.available
+            .is_none_or(|free| self.needed.saturating_add(reserve) <= free)
+    }
+}
+
+/// Bytes available to this user on the filesystem holding `path`.
+#[cfg(unix)]
+pub fn free_space(path: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    // The field types differ between platforms.
+    #[allow(clippy::unnecessary_cast)]
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+pub fn free_space(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// The nearest existing folder above `path`.
+pub(super) fn existing_ancestor(path: &Path) -> Option<&Path> {
+    path.ancestors().skip(1).find(|p| p.is_dir())
+}
+
+/// Which filesystem `path` is on.
+#[cfg(unix)]
+pub(super) fn device(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(path).ok().map(|m| m.dev())
+}
+
+/// Without device ids, every folder counts as its own filesystem.
+#[cfg(not(unix))]
+pub(super) fn device(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// Bytes `action` takes up on the destination's filesystem: its whole size
+/// for a copy, a reflink, or a move across filesystems; nothing for a
+/// rename, a link, or a source that is gone.
+pub fn bytes_needed(action: &OrganizeAction) -> u64 {
+    let Ok(size) = fs::metadata(&action.source).map(|m| m.len()) else {
+        return 0;
+    };
+    match action.strategy.as_str() {
+        "copy" | "reflink" => size,
+        "hardlink" | "symlink" => 0,
+        _ => {
+            let dest = existing_ancestor(&action.destination).and_then(device);
+            let same = dest.is_some() && dest == device(&action.source);
+            if same {
+                0
+            } else {
+                size
+            }
+        }
+    }
+}
+
+/// What `actions` need on each destination filesystem, in the order the
+/// filesystems first come up. Filesystems that need nothing are left out.
+pub fn space_needs(actions: &[OrganizeAction]) -> Vec<SpaceNeed> {
+    let mut needs: Vec<(Option<u64>, SpaceNeed)> = Vec::new();
+    for action in actions {
+        let needed = bytes_needed(action);
+        let Some(anchor) = existing_ancestor(&action.destination).filter(|_| needed > 0) else {
+            continue;
+        };
+        let dev = device(anchor);
+        let known = needs
+            .iter_mut()
+            .find(|(d, need)| dev.is_some() && *d == dev || need.path == anchor);
+        match known {
+            Some((_, need)) => need.needed += needed,
+            None => needs.push((
+                dev,
+                SpaceNeed {
+                    path: anchor.to_path_buf(),
+                    needed,
+                    available: free_space(anchor),
+                },
+            )),
+        }
+    }
+    needs.into_iter().map(|(_, need)| need).collect()
+}
+
+/// Refuse a run of `actions` that would leave less than `reserve` bytes
+/// free on any destination filesystem.
+pub fn check_space(actions: &[OrganizeAction], reserve: u64) -> Result<()> {
+    for need in space_needs(actions) {
+        if !need.fits(reserve) {
+            bail!(
+                "Not enough space on {}: the run needs {} and {} is free \
+                 (keeping {} free; see organize.space_reserve_mb)",
+                need.path.display(),
+                format_size(need.needed),
+                format_size(need.available.unwrap_or(0)),
+                format_size(reserve)
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_space_needs_by_strategy() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = tmp.path().join("Heat.1995.mkv");
+        fs::write(&source, vec![0u8; 1000]).unwrap();
+        let action = |strategy: &str| OrganizeAction {
+            source: source.clone(),
+            destination: tmp.path().join("lib/Movies/Heat (1995)/Heat (1995).mkv"),
+            strategy: strategy.to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(bytes_needed(&action("copy")), 1000);
+        assert_eq!(bytes_needed(&action("hardlink")), 0);
+        // A rename within the temporary directory's filesystem.
+        assert_eq!(bytes_needed(&action("move")), 0);
+
+        let needs = space_needs(&[action("copy"), action("reflink"), action("move")]);
+        assert_eq!(needs.len(), 1);
+        assert_eq!(needs[0].path, tmp.path());
+        assert_eq!(needs[0].needed, 2000);
+        assert!(check_space(&[action("copy")], 0).is_ok());
+        assert!(check_space(&[action("copy")], u64::MAX).is_err());
+
+        let full = SpaceNeed {
+            available: Some(1500),
+            ..needs[0].clone()
+        };
+        assert!(!full.fits(0));
+        assert!(SpaceNeed {
+            available: None,
+            ..full
+        }
+        .fits(u64::MAX));
+    }
+}
@@ -0,0 +1,68 @@
+//! Placeholders filled into destination names: the release details of
+//! `organize.file_info_template`, `organize.id_tags`, and the
+//! `{collection}` of `organize.collection_template`. Titles themselves are
+//! rendered by [`titles::render`].
+
+use std::path::PathBuf;
+
+use crate::config::IdTags;
+use crate::models::{ExternalIds, ParsedMedia};
+use crate::titles;
+use crate::utils::sanitize_name;
+
+/// `organize.file_info_template` filled in for `parsed`, sanitized and with
+/// a leading space, or empty when nothing in it is known.
+pub(super) fn file_info(template: &str, parsed: &ParsedMedia) -> String {
+    let info = titles::render_info(template, parsed);
+    if info.is_empty() {
+        String::new()
+    } else {
+        format!(" {}", sanitize_name(&info))
+    }
+}
+
+/// ` {tmdb-949}` or ` {imdb-tt0113277}` for `organize.id_tags`, empty when
+/// off or the id is unknown. Shows are tagged by TMDb id only: an IMDb id
+/// in an episode's name may be the episode's.
+pub(super) fn id_tag(ids: &ExternalIds, tags: IdTags, movie: bool) -> String {
+    let imdb = ids.imdb.as_ref().filter(|_| movie && tags == IdTags::Imdb);
+    match (tags, imdb, ids.tmdb) {
+        (IdTags::Off, _, _) => String::new(),
+        (_, Some(imdb), _) => format!(" {{imdb-{imdb}}}"),
+        (_, None, Some(tmdb)) => format!(" {{tmdb-{tmdb}}}"),
+        (_, None, None) => String::new(),
+    }
+}
+
+/// `collection_template` filled in for `collection`, each folder of it
+/// sanitized: `Collections/Iron Man Collection`.
+pub(super) fn collection_dir(collection: &str, template: &str) -> PathBuf {
+    template
+        .replace('\\', "/")
+        .split('/')
+        .map(|part| part.replace("{collection}", collection))
+        .filter(|part| !part.trim().is_empty())
+        .map(|part| sanitize_name(&part))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_placeholders() {
+        let ids = ExternalIds {
+            tmdb: Some(949),
+            imdb: Some("tt0113277".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(id_tag(&ids, IdTags::Imdb, true), " {imdb-tt0113277}");
+        assert_eq!(id_tag(&ids, IdTags::Imdb, false), " {tmdb-949}");
+        assert_eq!(id_tag(&ids, IdTags::Off, true), "");
+        assert_eq!(
+            collection_dir("Alien: Collection", "Collections\\{collection}/"),
+            PathBuf::from("Collections").join(sanitize_name("Alien: Collection"))
+        );
+    }
+}
@@ -0,0 +1,91 @@
+//! Bandwidth limit on copied data, per filesystem (`organize.io_limit_mb`).
+//!
+//! Parallel copies onto a NAS can saturate its link and starve whatever
+//! else uses it — Plex streaming from the same share, a torrent client
+//! seeding. With a limit, the data copied to or from any one filesystem is
+//! paced to that many MB per second, shared by all operations in flight
+//! on it. Each filesystem keeps a clock of when its bandwidth is next free;
+//! a chunk books its time on the clocks of both its source's and its
+//! destination's filesystem and waits its turn. Renames and links move no
+//! data and are never paced.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::space;
+
+/// Paces copied bytes per filesystem; shared by the workers of a run.
+#[derive(Debug)]
+pub(super) struct Throttle {
+    /// Bytes per second on each filesystem.
+    rate: u64,
+    /// When each filesystem's bandwidth is next free, by device id
+    /// (`None` where the platform has none).
+    free_at: Mutex<HashMap<Option<u64>, Instant>>,
+}
+
+impl Throttle {
+    /// A limit of `rate` bytes per second per filesystem.
+    pub(super) fn new(rate: u64) -> Self {
+        Self {
+            rate: rate.max(1),
+            free_at: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The filesystems a copy from `from` to `to` reads and writes.
+    pub(super) fn devices(&self, from: &Path, to: &Path) -> Vec<Option<u64>> {
+        let mut devices = vec![space::device(from)];
+        let dest = space::existing_ancestor(to).and_then(space::device);
+        if !devices.contains(&dest) {
+            devices.push(dest);
+        }
+        devices
+    }
+
+    /// Wait until `bytes` more may be copied across `devices`.
+    pub(super) fn pace(&self, devices: &[Option<u64>], bytes: u64) {
+        let cost = Duration::from_secs_f64(bytes as f64 / self.rate as f64);
+        let now = Instant::now();
+        let start = {
+            let mut free_at = self.free_at.lock().unwrap_or_else(|e| e.into_inner());
+            let start = devices
+                .iter()
+                .filter_map(|d| free_at.get(d).copied())
+                .fold(now, Instant::max);
+            for device in devices {
+                free_at.insert(*device, start + cost);
+            }
+            start
+        };
+        // A chunk is sent as soon as its turn comes; it is the next chunk
+        // that waits for the time this one takes.
+        thread::sleep(start.saturating_duration_since(now));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks_on_one_filesystem_wait_their_turn() {
+        let throttle = Throttle::new(10 << 20);
+        let started = Instant::now();
+        throttle.pace(&[Some(1)], 1 << 20);
+        assert!(started.elapsed() < Duration::from_millis(50));
+        throttle.pace(&[Some(1)], 1 << 20);
+        assert!(started.elapsed() >= Duration::from_millis(95));
+
+        // Another filesystem has bandwidth of its own...
+        let other = Instant::now();
+        throttle.pace(&[Some(2)], 1 << 20);
+        assert!(other.elapsed() < Duration::from_millis(50));
+        // ...but a copy between the two waits for both.
+        throttle.pace(&[Some(1), Some(2)], 1 << 20);
+        assert!(other.elapsed() >= Duration::from_millis(95));
+    }
+}
@@ -0,0 +1,310 @@
+//! Placing one file: moving, copying, or linking it, and checking the
+//! result in paranoid mode (`--paranoid`) and against a full checksum of
+//! copied data (`--verify`). A copy that fails a check is removed and its
+//! source kept.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use tracing::{error, info, warn};
+
+use super::fsops::{self, RenameTally};
+use super::space;
+use super::throttle::Throttle;
+use super::verify::{self, Verifier};
+use crate::chaos::{self, Fault};
+use crate::models::{OrganizeAction, SourceFingerprint};
+
+/// What a file is checked against once it is placed: its fingerprint in
+/// paranoid mode, and with `--verify` the checksum of data to be copied.
+pub(super) struct Checks {
+    expected: Option<SourceFingerprint>,
+    pub(super) checksum: Option<String>,
+}
+
+impl Checks {
+    /// Take the checks of `action`'s source before it is touched.
+    pub(super) fn before(
+        action: &OrganizeAction,
+        verifier: Option<&Verifier>,
+        verify_checksums: bool,
+    ) -> Result<Self> {
+        // Symlinks point at the source itself; there is no copy to verify.
+        let expected = match verifier {
+            Some(v) if action.strategy != "symlink" => {
+                Some(v.fingerprint(&action.source).with_context(|| {
+                    format!("Failed to fingerprint {}", action.source.display())
+                })?)
+            }
+            _ => None,
+        };
+        // Only files whose data is copied are checksummed; a rename or a
+        // link places the very same bytes.
+        let checksum = if verify_checksums && space::bytes_needed(action) > 0 {
+            Some(
+                verify::checksum(&action.source)
+                    .with_context(|| format!("Failed to checksum {}", action.source.display()))?,
+            )
+        } else {
+            None
+        };
+        Ok(Self { expected, checksum })
+    }
+}
+
+/// What became of a transferred file.
+pub(super) enum Transfer {
+    /// In place, with its verification result in paranoid mode.
+    Placed(Option<bool>),
+    /// The copy failed verification and was removed; the source is kept.
+    Discarded,
+}
+
+/// Move, copy, or link one file, verifying it in paranoid mode and
+/// matching a copy against the source's checksum when `checks` has one.
+/// Copied data is paced by the `throttle`.
+pub(super) fn transfer(
+    action: &OrganizeAction,
+    verifier: Option<&Verifier>,
+    checks: &Checks,
+    throttle: Option<&Throttle>,
+    renames: &RenameTally,
+) -> Result<Transfer> {
+    let (expected, checksum) = (checks.expected.as_ref(), checks.checksum.as_deref());
+    let mut verified = None;
+    chaos::inject(Fault::Rename)
+        .with_context(|| format!("Failed to {} {}", action.strategy, action.source.display()))?;
+    match action.strategy.as_str() {
+        "copy" => {
+            fsops::copy(&action.source, &action.destination, throttle).with_context(|| {
+                format!(
+                    "Failed to copy {} → {}",
+                    action.source.display(),
+                    action.destination.display()
+                )
+            })?;
+        }
+        "hardlink" => {
+            fs::hard_link(&action.source, &action.destination).with_context(|| {
+                format!(
+                    "Failed to hardlink {} → {} (both must be on one filesystem)",
+                    action.source.display(),
+                    action.destination.display()
+                )
+            })?;
+        }
+        "reflink" => {
+            let cloned = fsops::reflink(&action.source, &action.destination)
+                .with_context(|| format!("Failed to reflink {}", action.source.display()))?;
+            if !cloned {
+                fsops::copy(&action.source, &action.destination, throttle).with_context(|| {
+                    format!(
+                        "Failed to copy {} → {}",
+                        action.source.display(),
+                        action.destination.display()
+                    )
+                })?;
+            }
+            renames.record_reflink(cloned);
+        }
+        "symlink" => {
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(fs::canonicalize(&action.source)?, &action.destination)
+                .with_context(|| format!("Failed to symlink {}", action.source.display()))?;
+
+            #[cfg(not(unix))]
+            anyhow::bail!("Symlink strategy is only supported on Unix");
+        }
+        _ => {
+            // Default: move. rename fails across filesystems; fall back
+            // to copy+delete, the copy synced and its length checked (and
+            // in paranoid mode its content) before the source is removed.
+            // A destination that appeared since the check is never replaced,
+            // and any other failure is the move's.
+            match fsops::rename_no_replace(&action.source, &action.destination) {
+                Ok(method) => renames.record(method),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    return Err(e).with_context(|| {
+                        format!("Refusing to replace {}", action.destination.display())
+                    });
+                }
+                Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+                    fsops::copy_durably(&action.source, &action.destination, throttle)
+                        .with_context(|| {
+                            format!(
+                                "Failed to move {} → {}",
+                                action.source.display(),
+                                action.destination.display()
+                            )
+                        })?;
+                    if let Some(checksum) = checksum {
+                        check_copy(action, checksum)?;
+                    }
+                    if let (Some(v), Some(exp)) = (verifier, expected) {
+                        if !v.matches(&action.destination, exp) {
+                            discard_bad_copy(&action.destination);
+                            return Ok(Transfer::Discarded);
+                        }
+                        verified = Some(true);
+                    }
+                    fs::remove_file(&action.source)
+                        .with_context(|| format!("Failed to remove {}", action.source.display()))?;
+                }
+                Err(e) => {
+                    return Err(e).with_context(|| {
+                        format!(
+                            "Failed to move {} → {}",
+                            action.source.display(),
+                            action.destination.display()
+                        )
+                    });
+                }
+            }
+        }
+    }
+
+    if let (Some(checksum), "copy" | "reflink") = (checksum, action.strategy.as_str()) {
+        check_copy(action, checksum)?;
+    }
+    if let (None, Some(v), Some(exp)) = (verified, verifier, expected) {
+        let ok = v.matches(&action.destination, exp);
+        if !ok && matches!(action.strategy.as_str(), "copy" | "reflink") {
+            discard_bad_copy(&action.destination);
+            return Ok(Transfer::Discarded);
+        }
+        if !ok {
+            error!(
+                "Renamed file does not match its source fingerprint: {}",
+                action.destination.display()
+            );
+        }
+        verified = Some(ok);
+    }
+    Ok(Transfer::Placed(verified))
+}
+
+/// Fail unless the copy at the destination has the source's `checksum`,
+/// removing a copy that does not.
+fn check_copy(action: &OrganizeAction, checksum: &str) -> Result<()> {
+    let copied = verify::checksum(&action.destination)
+        .with_context(|| format!("Failed to checksum {}", action.destination.display()))?;
+    if copied != checksum {
+        error!(
+            "Checksum mismatch: {} is {copied}, its source {checksum}",
+            action.destination.display()
+        );
+        discard_bad_copy(&action.destination);
+        anyhow::bail!(
+            "Copy of {} does not match it (checksum {copied}, expected {checksum}); \
+             the copy was removed and the source kept",
+            action.source.display()
+        );
+    }
+    info!("Checksum matched: {}", action.destination.display());
+    Ok(())
+}
+
+/// Remove a destination copy that failed verification; the source is kept.
+fn discard_bad_copy(destination: &Path) {
+    warn!(
+        "Discarding unverified copy, source left in place: {}",
+        destination.display()
+    );
+    if let Err(e) = fs::remove_file(destination) {
+        error!("Could not remove {}: {}", destination.display(), e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::MediaType;
+    use crate::organizer::{execute_actions, ExecuteOptions};
+
+    #[test]
+    fn test_paranoid_copy_is_verified() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = tmp.path().join("Heat.1995.mkv");
+        fs::write(&source, b"video content").unwrap();
+        let actions = vec![OrganizeAction {
+            source: source.clone(),
+            destination: tmp.path().join("lib/Heat (1995).mkv"),
+            strategy: "copy".to_string(),
+            media_type: MediaType::Movie,
+            title: "Heat".to_string(),
+            ..Default::default()
+        }];
+        let options = ExecuteOptions {
+            paranoid: true,
+            ..Default::default()
+        };
+
+        let manifest = execute_actions(&actions, &tmp.path().join("undo"), &options).unwrap();
+        assert_eq!(manifest.entries[0].verified, Some(true));
+        let summary = manifest.verification.unwrap();
+        assert_eq!((summary.verified, summary.mismatched), (1, 0));
+        assert!(source.exists());
+    }
+
+    #[test]
+    fn test_verified_copy_records_its_checksum() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = tmp.path().join("Heat.1995.mkv");
+        fs::write(&source, b"video content").unwrap();
+        let action = |strategy: &str, name: &str| OrganizeAction {
+            source: source.clone(),
+            destination: tmp.path().join(format!("lib/{name}.mkv")),
+            strategy: strategy.to_string(),
+            ..Default::default()
+        };
+        let actions = vec![action("copy", "copied"), action("hardlink", "linked")];
+        let options = ExecuteOptions {
+            verify_checksums: true,
+            ..Default::default()
+        };
+
+        let manifest = execute_actions(&actions, &tmp.path().join("undo"), &options).unwrap();
+        let expected = verify::checksum(&source).unwrap();
+        assert!(expected.starts_with("xxh64:"));
+        assert_eq!(manifest.entries[0].checksum.as_ref(), Some(&expected));
+        // A link copies nothing, so there is nothing to check.
+        assert_eq!(manifest.entries[1].checksum, None);
+    }
+
+    #[test]
+    fn test_hardlink_and_reflink_keep_the_source() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = tmp.path().join("Heat.1995.mkv");
+        fs::write(&source, b"video content").unwrap();
+        let actions: Vec<_> = ["hardlink", "reflink"]
+            .iter()
+            .map(|strategy| OrganizeAction {
+                source: source.clone(),
+                destination: tmp.path().join(format!("lib/{strategy}/Heat (1995).mkv")),
+                strategy: strategy.to_string(),
+                ..Default::default()
+            })
+            .collect();
+        let undo_dir = tmp.path().join("undo");
+
+        let manifest = execute_actions(&actions, &undo_dir, &Default::default()).unwrap();
+        assert_eq!(manifest.entries.len(), 2);
+        for action in &actions {
+            assert_eq!(fs::read(&action.destination).unwrap(), b"video content");
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            let inode = |p: &Path| fs::metadata(p).unwrap().ino();
+            assert_eq!(inode(&actions[0].destination), inode(&source));
+            assert_ne!(inode(&actions[1].destination), inode(&source));
+        }
+
+        let report = crate::organizer::undo_last(&undo_dir, &mut |_| Ok(())).unwrap();
+        assert_eq!(report.reversed.len(), 2);
+        assert!(source.exists());
+        assert!(actions.iter().all(|a| !a.destination.exists()));
+    }
+}
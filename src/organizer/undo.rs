@@ -0,0 +1,576 @@
+//! Undo of previously executed organize runs.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use tracing::{info, warn};
+
+use super::fsops;
+use super::provenance;
+use crate::chaos::{self, Fault};
+use crate::models::{UndoEntry, UndoManifest};
+use crate::nfo;
+use crate::schema::{self, Artifact};
+
+/// Outcome of reversing one organize run.
+#[derive(Debug, Default)]
+pub struct UndoReport {
+    /// Manifest that was undone.
+    pub manifest_path: PathBuf,
+    /// Entries whose file operation was reversed.
+    pub reversed: Vec<UndoEntry>,
+    /// Entries whose destination no longer exists (nothing to reverse).
+    pub missing: Vec<UndoEntry>,
+    /// Entries that could not be reversed, with the error.
+    pub failed: Vec<(UndoEntry, String)>,
+}
+
+impl UndoReport {
+    /// Whether every entry was either reversed or already gone.
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    fn absorb(&mut self, unit: UndoReport) {
+        self.reversed.extend(unit.reversed);
+        self.missing.extend(unit.missing);
+        self.failed.extend(unit.failed);
+    }
+}
+
+/// A past organize run that can be undone.
+#[derive(Debug, Clone)]
+pub struct Operation {
+    /// The manifest's timestamp, e.g. `20260228_103000`.
+    pub id: String,
+    pub manifest_path: PathBuf,
+    pub created_at: String,
+    pub description: String,
+    /// Files still recorded for undo.
+    pub files: usize,
+}
+
+/// The runs recorded in `undo_dir`, newest first. Unreadable manifests are
+/// skipped.
+pub fn list_operations(undo_dir: &Path) -> Result<Vec<Operation>> {
+    if !undo_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut operations = Vec::new();
+    for path in manifest_paths(undo_dir)? {
+        let manifest = match read_manifest(&path) {
+            Ok(m) => m,
+            Err(e) => {
+                warn!("Skipping unreadable manifest {}: {e:#}", path.display());
+                continue;
+            }
+        };
+        let id = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .and_then(|s| s.strip_prefix("undo_"))
+            .unwrap_or_default()
+            .to_string();
+        operations.push(Operation {
+            id,
+            manifest_path: path,
+            created_at: manifest.created_at,
+            description: manifest.description,
+            files: manifest.entries.len(),
+        });
+    }
+    Ok(operations)
+}
+
+/// Reverse the most recent organize operation (see [`undo_operation`]).
+pub fn undo_last(
+    undo_dir: &Path,
+    record: &mut dyn FnMut(&UndoReport) -> Result<()>,
+) -> Result<UndoReport> {
+    if !undo_dir.exists() {
+        anyhow::bail!("No undo directory found: {}", undo_dir.display());
+    }
+    match manifest_paths(undo_dir)?.first() {
+        Some(path) => undo_manifest(path, record),
+        None => anyhow::bail!("No undo manifests found"),
+    }
+}
+
+/// Reverse the organize operation `id`, as listed by [`list_operations`].
+///
+/// A video and the subtitles and artwork organized with it are one unit:
+/// either all of them go back to their original paths or none do, so a
+/// movie never ends up split between its old and new folder. Every unit is
+/// attempted even if some fail. When all succeed the manifest is removed;
+/// otherwise it is rewritten with only the failed entries so the undo can
+/// be retried after fixing the cause.
+///
+/// Files a later run has moved on are no longer at this run's destinations
+/// and are reported as already gone.
+///
+/// `record` is handed each unit's outcome as soon as it is undone, so the
+/// library database can follow unit by unit. If it fails, the undo stops
+/// there and the manifest keeps that unit and every one not yet undone; a
+/// retry finds the unit's files already gone and hands them over again.
+pub fn undo_operation(
+    undo_dir: &Path,
+    id: &str,
+    record: &mut dyn FnMut(&UndoReport) -> Result<()>,
+) -> Result<UndoReport> {
+    let manifest_path = undo_dir.join(format!("undo_{id}.json"));
+    if !manifest_path.is_file() {
+        anyhow::bail!("No operation {id:?}; `history` lists them");
+    }
+    undo_manifest(&manifest_path, record)
+}
+
+/// Manifests in `undo_dir`, newest first.
+fn manifest_paths(undo_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut manifests: Vec<PathBuf> = fs::read_dir(undo_dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .map(|n| n.starts_with("undo_") && n.ends_with(".json"))
+                .unwrap_or(false)
+        })
+        .collect();
+    manifests.sort();
+    manifests.reverse();
+    Ok(manifests)
+}
+
+fn read_manifest(path: &Path) -> Result<UndoManifest> {
+    let content = fs::read_to_string(path)?;
+    schema::from_str(Artifact::RunReport, &content)
+        .with_context(|| format!("Invalid run report: {}", path.display()))
+}
+
+fn undo_manifest(
+    manifest_path: &Path,
+    record: &mut dyn FnMut(&UndoReport) -> Result<()>,
+) -> Result<UndoReport> {
+    let mut manifest = read_manifest(manifest_path)?;
+
+    let mut report = UndoReport {
+        manifest_path: manifest_path.to_path_buf(),
+        ..Default::default()
+    };
+
+    let mut stopped = None;
+    {
+        let units = units(&manifest.entries)
+            .into_iter()
+            .rev()
+            // Copies an upgrade replaced return once the new files are gone.
+            .chain(manifest.replaced.iter().map(|e| vec![e]))
+            // Leftovers of cleaned-up source folders go back beside them.
+            .chain(manifest.cleaned.iter().map(|e| vec![e]));
+        for unit in units {
+            let mut undone = UndoReport {
+                manifest_path: manifest_path.to_path_buf(),
+                ..Default::default()
+            };
+            reverse_unit(&unit, &mut undone);
+            if let Err(e) = record(&undone) {
+                stopped = Some(e);
+                break;
+            }
+            report.absorb(undone);
+        }
+    }
+
+    // Keep only what still needs reversing or recording, in original order.
+    let settled: HashSet<String> = report
+        .reversed
+        .iter()
+        .chain(&report.missing)
+        .map(|e| e.destination.clone())
+        .collect();
+    let unsettled = |entry: &UndoEntry| !settled.contains(&entry.destination);
+    manifest.entries.retain(unsettled);
+    manifest.replaced.retain(unsettled);
+    manifest.cleaned.retain(unsettled);
+    if manifest.entries.is_empty() && manifest.replaced.is_empty() && manifest.cleaned.is_empty() {
+        fs::remove_file(manifest_path)?;
+    } else {
+        fs::write(manifest_path, serde_json::to_string_pretty(&manifest)?)?;
+    }
+    if let Some(e) = stopped {
+        return Err(e);
+    }
+    info!(
+        "Undo finished: {} reversed, {} missing, {} failed",
+        report.reversed.len(),
+        report.missing.len(),
+        report.failed.len()
+    );
+    Ok(report)
+}
+
+/// Entries grouped into units, in manifest order: each video with the
+/// companions organized alongside it. Entries of older manifests carry no
+/// `companion_of` and are units of their own.
+fn units(entries: &[UndoEntry]) -> Vec<Vec<&UndoEntry>> {
+    let mut index: HashMap<&str, usize> = HashMap::new();
+    let mut units: Vec<Vec<&UndoEntry>> = Vec::new();
+    for entry in entries {
+        let key = entry.companion_of.as_deref().unwrap_or(&entry.destination);
+        match index.get(key) {
+            Some(&i) => units[i].push(entry),
+            None => {
+                index.insert(key, units.len());
+                units.push(vec![entry]);
+            }
+        }
+    }
+    units
+}
+
+/// Reverse every file of a unit, or none: an occupied original path stops
+/// the whole unit up front, and a failure midway puts back the files
+/// already reversed.
+fn reverse_unit(unit: &[&UndoEntry], report: &mut UndoReport) {
+    let mut present = Vec::new();
+    for &entry in unit.iter().rev() {
+        if Path::new(&entry.destination).exists() {
+            present.push(entry);
+        } else {
+            warn!("Destination no longer exists: {}", entry.destination);
+            report.missing.push(entry.clone());
+        }
+    }
+
+    let occupied = present.iter().find(|e| {
+        !matches!(
+            e.strategy.as_str(),
+            "copy" | "symlink" | "hardlink" | "reflink"
+        ) && Path::new(&e.source).exists()
+    });
+    if let Some(&entry) = occupied {
+        warn!(
+            "Could not reverse {}: original path is occupied",
+            entry.destination
+        );
+        let error = format!("original path is occupied: {}", entry.source);
+        fail_unit(report, &present, entry, &error);
+        return;
+    }
+
+    let mut done: Vec<&UndoEntry> = Vec::new();
+    for &entry in &present {
+        let (source, dest) = (Path::new(&entry.source), Path::new(&entry.destination));
+        let Err(e) = reverse_entry(&entry.strategy, source, dest) else {
+            done.push(entry);
+            continue;
+        };
+        warn!("Could not reverse {}: {}", dest.display(), e);
+        let mut stuck = Vec::new();
+        for &undone in done.iter().rev() {
+            let (source, dest) = (Path::new(&undone.source), Path::new(&undone.destination));
+            if let Err(e) = redo_entry(&undone.strategy, source, dest) {
+                warn!("Could not put back {}: {}", dest.display(), e);
+                stuck.push(undone);
+            }
+        }
+        // Whatever could not be put back is reversed after all.
+        present.retain(|p| !stuck.iter().any(|s| s.destination == p.destination));
+        done = stuck;
+        fail_unit(report, &present, entry, &e.to_string());
+        break;
+    }
+
+    for entry in done {
+        let dest = Path::new(&entry.destination);
+        provenance::remove_sidecar(dest);
+        nfo::remove_generated(dest);
+        info!("Reversed: {} → {}", entry.destination, entry.source);
+        report.reversed.push(entry.clone());
+        cleanup_empty_parents(dest);
+    }
+}
+
+/// Record every file of a unit as failed because of `culprit`.
+fn fail_unit(report: &mut UndoReport, unit: &[&UndoEntry], culprit: &UndoEntry, error: &str) {
+    for &entry in unit {
+        let error = if entry.destination == culprit.destination {
+            error.to_string()
+        } else {
+            format!("kept with {}: {error}", culprit.destination)
+        };
+        report.failed.push((entry.clone(), error));
+    }
+}
+
+/// Organize a reversed file again, to keep its unit whole.
+fn redo_entry(strategy: &str, source: &Path, dest: &Path) -> std::io::Result<()> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    match strategy {
+        "copy" | "reflink" => fs::copy(source, dest).map(|_| ()),
+        "hardlink" => fs::hard_link(source, dest),
+        #[cfg(unix)]
+        "symlink" => std::os::unix::fs::symlink(fs::canonicalize(source)?, dest),
+        #[cfg(not(unix))]
+        "symlink" => Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "symlinks are only supported on Unix",
+        )),
+        _ => match fsops::rename_no_replace(source, dest) {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+                fs::copy(source, dest)?;
+                fs::remove_file(source)
+            }
+            Err(e) => Err(e),
+        },
+    }
+}
+
+fn reverse_entry(strategy: &str, source: &Path, dest: &Path) -> std::io::Result<()> {
+    chaos::inject(Fault::Rename)?;
+    match strategy {
+        "symlink" | "copy" | "hardlink" | "reflink" => fs::remove_file(dest),
+        _ => {
+            if source.exists() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    format!("original path is occupied: {}", source.display()),
+                ));
+            }
+            if let Some(parent) = source.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            match fsops::rename_no_replace(dest, source) {
+                Ok(_) => Ok(()),
+                Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+                    fs::copy(dest, source)?;
+                    fs::remove_file(dest)
+                }
+                Err(e) => Err(e),
+            }
+        }
+    }
+}
+
+fn cleanup_empty_parents(path: &Path) {
+    let mut current = path.parent();
+    for _ in 0..3 {
+        match current {
+            Some(p) if p.is_dir() => {
+                if fs::read_dir(p)
+                    .map(|mut d| d.next().is_none())
+                    .unwrap_or(false)
+                {
+                    let _ = fs::remove_dir(p);
+                    current = p.parent();
+                } else {
+                    break;
+                }
+            }
+            _ => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{MediaType, OrganizeAction};
+    use crate::organizer::execute_actions;
+
+    #[test]
+    fn test_execute_and_undo_roundtrip() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source_dir = tmp.path().join("source");
+        let dest_dir = tmp.path().join("dest");
+        let undo_dir = tmp.path().join("undo");
+        fs::create_dir_all(&source_dir).unwrap();
+
+        let source_file = source_dir.join("movie.mkv");
+        fs::write(&source_file, b"video content").unwrap();
+
+        let dest_file = dest_dir.join("Movies/Test (2024)/Test (2024).mkv");
+        let actions = vec![OrganizeAction {
+            source: source_file.clone(),
+            destination: dest_file.clone(),
+            strategy: "move".to_string(),
+            media_type: MediaType::Movie,
+            title: "Test".to_string(),
+            confidence: 80.0,
+            ..Default::default()
+        }];
+
+        // Execute
+        let manifest = execute_actions(&actions, &undo_dir, &Default::default()).unwrap();
+        assert_eq!(manifest.entries.len(), 1);
+        assert!(dest_file.exists());
+        assert!(!source_file.exists());
+
+        // Undo
+        let report = undo_last(&undo_dir, &mut |_| Ok(())).unwrap();
+        assert_eq!(report.reversed.len(), 1);
+        assert!(report.is_complete());
+        assert!(source_file.exists());
+        assert!(!dest_file.exists());
+    }
+
+    #[test]
+    fn test_partial_undo_keeps_failed_entries() {
+        let tmp = tempfile::tempdir().unwrap();
+        let undo_dir = tmp.path().join("undo");
+        let actions: Vec<OrganizeAction> = ["a", "b"]
+            .iter()
+            .map(|name| {
+                let source = tmp.path().join(format!("{name}.mkv"));
+                fs::write(&source, name).unwrap();
+                OrganizeAction {
+                    source,
+                    destination: tmp.path().join(format!("lib/{name}.mkv")),
+                    strategy: "move".to_string(),
+                    ..Default::default()
+                }
+            })
+            .collect();
+        execute_actions(&actions, &undo_dir, &Default::default()).unwrap();
+
+        // Something new now sits at b's original path.
+        fs::write(&actions[1].source, b"new download").unwrap();
+
+        let report = undo_last(&undo_dir, &mut |_| Ok(())).unwrap();
+        assert_eq!(report.reversed.len(), 1);
+        assert_eq!(report.failed.len(), 1);
+        assert!(actions[0].source.exists());
+
+        let remaining: UndoManifest =
+            serde_json::from_str(&fs::read_to_string(&report.manifest_path).unwrap()).unwrap();
+        assert_eq!(remaining.entries.len(), 1);
+        assert_eq!(
+            remaining.entries[0].destination,
+            actions[1].destination.to_string_lossy()
+        );
+    }
+
+    #[test]
+    fn test_unrecorded_unit_stays_for_retry() {
+        let tmp = tempfile::tempdir().unwrap();
+        let undo_dir = tmp.path().join("undo");
+        let actions: Vec<OrganizeAction> = ["a", "b"]
+            .iter()
+            .map(|name| {
+                let source = tmp.path().join(format!("{name}.mkv"));
+                fs::write(&source, name).unwrap();
+                OrganizeAction {
+                    source,
+                    destination: tmp.path().join(format!("lib/{name}.mkv")),
+                    strategy: "move".to_string(),
+                    ..Default::default()
+                }
+            })
+            .collect();
+        execute_actions(&actions, &undo_dir, &Default::default()).unwrap();
+
+        // The database cannot be saved after the first unit is reversed.
+        let failed = undo_last(&undo_dir, &mut |_| anyhow::bail!("disk full"));
+        assert!(failed.is_err());
+        let back: Vec<_> = actions.iter().filter(|a| a.source.exists()).collect();
+        assert_eq!(back.len(), 1);
+
+        let mut recorded = Vec::new();
+        let report = undo_last(&undo_dir, &mut |unit| {
+            recorded.push((unit.reversed.len(), unit.missing.len()));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(recorded, [(0, 1), (1, 0)]);
+        assert_eq!(
+            report.missing[0].destination,
+            back[0].destination.to_string_lossy()
+        );
+        assert!(list_operations(&undo_dir).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_undo_an_earlier_operation() {
+        let tmp = tempfile::tempdir().unwrap();
+        let undo_dir = tmp.path().join("undo");
+        let actions: Vec<OrganizeAction> = ["a", "b"]
+            .iter()
+            .map(|name| {
+                let source = tmp.path().join(format!("{name}.mkv"));
+                fs::write(&source, name).unwrap();
+                OrganizeAction {
+                    source,
+                    destination: tmp.path().join(format!("lib/{name}.mkv")),
+                    strategy: "move".to_string(),
+                    ..Default::default()
+                }
+            })
+            .collect();
+        for action in &actions {
+            execute_actions(std::slice::from_ref(action), &undo_dir, &Default::default()).unwrap();
+        }
+
+        let operations = list_operations(&undo_dir).unwrap();
+        assert_eq!(operations.len(), 2);
+        assert_eq!(operations[0].files, 1);
+        let report = undo_operation(&undo_dir, &operations[1].id, &mut |_| Ok(())).unwrap();
+        assert_eq!(report.reversed.len(), 1);
+        assert!(actions[0].source.exists());
+        assert!(actions[1].destination.exists());
+        assert_eq!(list_operations(&undo_dir).unwrap().len(), 1);
+        assert!(undo_operation(&undo_dir, "19700101_000000", &mut |_| Ok(())).is_err());
+    }
+
+    #[test]
+    fn test_undo_keeps_companions_with_their_video() {
+        let tmp = tempfile::tempdir().unwrap();
+        let undo_dir = tmp.path().join("undo");
+        let folder = tmp.path().join("lib/Movies/Heat (1995)");
+        let video = folder.join("Heat (1995).mkv");
+        let actions: Vec<OrganizeAction> = [
+            ("heat.mkv", video.clone(), None),
+            (
+                "heat.srt",
+                folder.join("Heat (1995).srt"),
+                Some(video.clone()),
+            ),
+            ("poster.jpg", folder.join("poster.jpg"), Some(video.clone())),
+        ]
+        .into_iter()
+        .map(|(name, destination, companion_of)| {
+            let source = tmp.path().join(name);
+            fs::write(&source, name).unwrap();
+            OrganizeAction {
+                source,
+                destination,
+                strategy: "move".to_string(),
+                companion_of,
+                ..Default::default()
+            }
+        })
+        .collect();
+        let manifest = execute_actions(&actions, &undo_dir, &Default::default()).unwrap();
+        assert_eq!(
+            manifest.entries[1].companion_of.as_deref(),
+            Some(&*video.to_string_lossy())
+        );
+
+        // The subtitle's old path is taken: the whole movie stays put.
+        fs::write(&actions[1].source, b"other").unwrap();
+        let report = undo_last(&undo_dir, &mut |_| Ok(())).unwrap();
+        assert!(report.reversed.is_empty());
+        assert_eq!(report.failed.len(), 3);
+        assert!(actions.iter().all(|a| a.destination.exists()));
+
+        fs::remove_file(&actions[1].source).unwrap();
+        let report = undo_last(&undo_dir, &mut |_| Ok(())).unwrap();
+        assert_eq!(report.reversed.len(), 3);
+        assert!(actions.iter().all(|a| a.source.exists()));
+        assert!(!folder.exists());
+    }
+}
@@ -0,0 +1,271 @@
+//! Quality upgrades of movies already in the library (`organize.upgrade`).
+//!
+//! Without it, a second copy of a movie is filed beside the first as
+//! `Heat (1995) (1).mkv`. With it, the videos already in the movie's
+//! folder are compared with the new file by [`Quality`] — resolution, then
+//! source, dynamic range, and codec. A better new file replaces them: each
+//! is moved to the recycle directory just before the new one is placed,
+//! listed in the run's manifest (`replaced`) so `undo` puts it back, and
+//! returned if the new file is not placed after all. A new file no better
+//! than what is there is skipped as already had. A copy whose quality is
+//! unknown (not in the database, nothing in its name) is never judged:
+//! both are kept, as without upgrades.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use tracing::warn;
+
+use super::fsops;
+use crate::models::{OrganizeAction, ReplacedCopy, UndoEntry};
+use crate::quality::Quality;
+use crate::scanner;
+
+/// A file not organized because the library already has the movie at
+/// least as good.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlreadyHad {
+    pub source: PathBuf,
+    pub quality: String,
+    /// The best copy in the library.
+    pub existing: PathBuf,
+    pub existing_quality: String,
+}
+
+impl AlreadyHad {
+    /// Why the file was skipped.
+    pub fn reason(&self) -> String {
+        if Quality::parse(&self.quality) == Quality::parse(&self.existing_quality) {
+            format!("already have the same quality ({})", self.existing_quality)
+        } else {
+            format!(
+                "already have better ({} over {})",
+                self.existing_quality,
+                label(&self.quality)
+            )
+        }
+    }
+}
+
+fn label(quality: &str) -> &str {
+    if quality.is_empty() {
+        "unknown quality"
+    } else {
+        quality
+    }
+}
+
+/// What to do with a new copy of a movie.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Verdict {
+    /// No copy to compare with, or one of unknown quality: plan as usual.
+    Plan,
+    /// Better than every copy there, which it replaces.
+    Replace(Vec<ReplacedCopy>),
+    /// No better than the best copy there.
+    Skip(AlreadyHad),
+}
+
+/// The Plex edition tag in a file name (`{edition-Director's Cut}`).
+fn edition_tag(path: &Path) -> Option<&str> {
+    let name = path.file_stem()?.to_str()?;
+    let start = name.find("{edition-")?;
+    name[start..].split_inclusive('}').next()
+}
+
+/// Videos in the folder of `dest` that are copies of the same movie and
+/// edition, leaving out those `claimed` by an earlier upgrade in the same
+/// plan.
+pub(super) fn existing_copies(dest: &Path, claimed: &HashSet<PathBuf>) -> Vec<PathBuf> {
+    let Some(Ok(entries)) = dest.parent().map(fs::read_dir) else {
+        return Vec::new();
+    };
+    let edition = edition_tag(dest);
+    let mut copies: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && scanner::is_media_path(p) && !claimed.contains(p))
+        .filter(|p| edition_tag(p) == edition)
+        .collect();
+    copies.sort();
+    copies
+}
+
+/// Compare a new file of `quality` with the `copies` already there, each
+/// rated by the quality the database recorded for it (`known`) or else
+/// its name.
+pub(super) fn judge(
+    source: &Path,
+    quality: &str,
+    copies: &[PathBuf],
+    known: &dyn Fn(&Path) -> Option<String>,
+) -> Verdict {
+    let new = Quality::parse(quality);
+    if copies.is_empty() || new.is_unknown() {
+        return Verdict::Plan;
+    }
+    let mut rated = Vec::new();
+    for copy in copies {
+        let recorded = known(copy).filter(|q| !q.is_empty());
+        let name = copy.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        let existing = Quality::parse(recorded.as_deref().unwrap_or(name));
+        if existing.is_unknown() {
+            warn!(
+                "Quality of {} is not known; keeping both copies \
+                 (`library index --probe` can measure it)",
+                copy.display()
+            );
+            return Verdict::Plan;
+        }
+        rated.push((copy, existing));
+    }
+    let (best, best_quality) = rated.iter().max_by_key(|(_, q)| *q).expect("not empty");
+    if new > *best_quality {
+        return Verdict::Replace(
+            rated
+                .iter()
+                .map(|(copy, q)| ReplacedCopy {
+                    path: copy.to_path_buf(),
+                    quality: q.to_string(),
+                })
+                .collect(),
+        );
+    }
+    Verdict::Skip(AlreadyHad {
+        source: source.to_path_buf(),
+        quality: new.to_string(),
+        existing: best.to_path_buf(),
+        existing_quality: best_quality.to_string(),
+    })
+}
+
+/// Move the copies `action` replaces into `recycle`, keeping each in a
+/// folder named like its movie folder, and push their undo entries onto
+/// `moved`; a copy already gone is passed over. Each entry goes to
+/// `journal` before its copy moves, so a crash never leaves a copy in the
+/// recycle directory without a record. On failure `moved` holds the
+/// copies moved so far, for the caller to [`restore`].
+pub(super) fn set_aside(
+    action: &OrganizeAction,
+    recycle: &Path,
+    timestamp: &str,
+    moved: &mut Vec<UndoEntry>,
+    mut journal: impl FnMut(&UndoEntry) -> Result<()>,
+) -> Result<()> {
+    for copy in action.replaces.iter().filter(|c| c.path.exists()) {
+        let folder = copy.path.parent().and_then(Path::file_name);
+        let name = copy.path.file_name().unwrap_or_default();
+        let aside = recycle.join(folder.unwrap_or_default()).join(name);
+        let entry = UndoEntry {
+            source: copy.path.to_string_lossy().into_owned(),
+            destination: aside.to_string_lossy().into_owned(),
+            strategy: "move".to_string(),
+            timestamp: timestamp.to_string(),
+            title: action.title.clone(),
+            titles: action.titles.clone(),
+            media_type: action.media_type.to_string(),
+            year: action.year,
+            quality: copy.quality.clone(),
+            tmdb_id: action.tmdb_id,
+            ..Default::default()
+        };
+        journal(&entry)?;
+        fs::create_dir_all(aside.parent().unwrap_or(recycle))
+            .and_then(|()| fsops::move_file(&copy.path, &aside))
+            .with_context(|| {
+                format!(
+                    "Failed to move {} to the recycle directory",
+                    copy.path.display()
+                )
+            })?;
+        moved.push(entry);
+    }
+    Ok(())
+}
+
+/// Put copies [`set_aside`] moved back where they were. Returns those that
+/// could not be, still in the recycle directory.
+pub(super) fn restore(moved: Vec<UndoEntry>) -> Vec<UndoEntry> {
+    moved
+        .into_iter()
+        .filter(|entry| {
+            let back = fsops::move_file(Path::new(&entry.destination), Path::new(&entry.source));
+            if let Err(e) = &back {
+                warn!(
+                    "Could not put {} back from {}: {e:#}",
+                    entry.source, entry.destination
+                );
+            }
+            back.is_err()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_judge_and_set_aside() {
+        let tmp = tempfile::tempdir().unwrap();
+        let folder = tmp.path().join("Movies/Heat (1995)");
+        fs::create_dir_all(folder.join("Featurettes")).unwrap();
+        let old = folder.join("Heat (1995).mkv");
+        fs::write(&old, b"720p").unwrap();
+        fs::write(folder.join("Heat (1995).en.srt"), b"srt").unwrap();
+        fs::write(
+            folder.join("Heat (1995) {edition-Director's Cut}.mkv"),
+            b"cut",
+        )
+        .unwrap();
+        let copies = existing_copies(&folder.join("Heat (1995).mkv"), &HashSet::new());
+        assert_eq!(copies, [old.clone()]);
+
+        let source = Path::new("/downloads/Heat.1995.1080p.BluRay.x264.mkv");
+        let recorded = |_: &Path| Some("720p WEB-DL H.264".to_string());
+        let unrecorded = |_: &Path| None;
+        let Verdict::Replace(replaced) = judge(source, "1080p BluRay H.264", &copies, &recorded)
+        else {
+            panic!("a 1080p Blu-ray replaces a 720p WEB-DL");
+        };
+        assert_eq!(replaced[0].quality, "720p WEB-DL H.264");
+
+        let Verdict::Skip(had) = judge(source, "720p HDTV", &copies, &recorded) else {
+            panic!("a 720p HDTV is no better than a 720p WEB-DL");
+        };
+        assert_eq!(
+            had.reason(),
+            "already have better (720p WEB-DL H.264 over 720p HDTV)"
+        );
+        // Nothing in the database or the name: not judged.
+        assert_eq!(
+            judge(source, "1080p BluRay", &copies, &unrecorded),
+            Verdict::Plan
+        );
+
+        let action = OrganizeAction {
+            destination: old.clone(),
+            title: "Heat".to_string(),
+            replaces: replaced,
+            ..Default::default()
+        };
+        let recycle = tmp.path().join("recycle/1");
+        let mut moved = Vec::new();
+        let mut journaled = Vec::new();
+        set_aside(&action, &recycle, "now", &mut moved, |entry| {
+            journaled.push(entry.destination.clone());
+            Ok(())
+        })
+        .unwrap();
+        let aside = recycle.join("Heat (1995)/Heat (1995).mkv");
+        assert!(!old.exists());
+        assert_eq!(fs::read(&aside).unwrap(), b"720p");
+        assert_eq!(moved[0].destination, aside.to_string_lossy());
+        assert_eq!(journaled, [moved[0].destination.clone()]);
+
+        assert!(restore(moved).is_empty());
+        assert_eq!(fs::read(&old).unwrap(), b"720p");
+    }
+}
@@ -0,0 +1,167 @@
+//! Apply-time re-validation of saved plans against the filesystem.
+//!
+//! A plan may sit for hours between review and apply. Before executing it,
+//! every source is re-stat'ed and compared with the fingerprint recorded at
+//! plan time; anything that changed is set aside instead of renamed blindly.
+
+use std::fmt;
+
+use crate::hash;
+use crate::models::OrganizeAction;
+
+/// Why a planned action no longer matches the filesystem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DriftReason {
+    /// The source file is gone.
+    SourceMissing,
+    /// The source size differs from the planned size.
+    SizeChanged { planned: u64, actual: u64 },
+    /// The source modification time differs from the planned one.
+    ModifiedChanged,
+    /// Size and mtime agree, but the quick content hash does not.
+    ContentChanged,
+    /// Something now occupies the planned destination.
+    DestinationOccupied,
+}
+
+impl fmt::Display for DriftReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SourceMissing => write!(f, "source missing"),
+            Self::SizeChanged { planned, actual } => {
+                write!(f, "size changed ({planned} → {actual} bytes)")
+            }
+            Self::ModifiedChanged => write!(f, "modification time changed"),
+            Self::ContentChanged => write!(f, "content hash changed"),
+            Self::DestinationOccupied => write!(f, "destination already exists"),
+        }
+    }
+}
+
+/// A planned action that was skipped because the filesystem drifted.
+#[derive(Debug, Clone)]
+pub struct DriftedAction {
+    pub action: OrganizeAction,
+    pub reason: DriftReason,
+}
+
+/// Compare an action's source against its recorded fingerprint.
+///
+/// Returns `None` when the action is still safe to apply. Actions without a
+/// fingerprint are only checked for source existence and destination clashes.
+pub fn check_drift(action: &OrganizeAction) -> Option<DriftReason> {
+    let metadata = match std::fs::metadata(&action.source) {
+        Ok(m) if m.is_file() => m,
+        _ => return Some(DriftReason::SourceMissing),
+    };
+    if action.destination.exists() {
+        return Some(DriftReason::DestinationOccupied);
+    }
+
+    let planned = action.fingerprint.as_ref()?;
+    if metadata.len() != planned.size {
+        return Some(DriftReason::SizeChanged {
+            planned: planned.size,
+            actual: metadata.len(),
+        });
+    }
+    if planned.modified.is_some() && hash::modified_secs(&metadata) != planned.modified {
+        return Some(DriftReason::ModifiedChanged);
+    }
+    if let Some(expected) = &planned.quick_hash {
+        match hash::quick_hash(&action.source) {
+            Ok(actual) if &actual == expected => {}
+            _ => return Some(DriftReason::ContentChanged),
+        }
+    }
+    None
+}
+
+/// Split a saved plan into actions that are still valid and those that drifted.
+pub fn validate_plan(actions: &[OrganizeAction]) -> (Vec<OrganizeAction>, Vec<DriftedAction>) {
+    let mut valid = Vec::new();
+    let mut drifted = Vec::new();
+    for action in actions {
+        match check_drift(action) {
+            None => valid.push(action.clone()),
+            Some(reason) => drifted.push(DriftedAction {
+                action: action.clone(),
+                reason,
+            }),
+        }
+    }
+    (valid, drifted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::MediaType;
+    use std::fs;
+
+    fn fingerprinted_action(tmp: &std::path::Path, content: &[u8]) -> OrganizeAction {
+        let source = tmp.join("movie.mkv");
+        fs::write(&source, content).unwrap();
+        OrganizeAction {
+            fingerprint: Some(hash::fingerprint(&source, true).unwrap()),
+            source,
+            destination: tmp.join("dest/Movie (2024).mkv"),
+            strategy: "move".to_string(),
+            media_type: MediaType::Movie,
+            title: "Movie".to_string(),
+            confidence: 80.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_unchanged_source_is_valid() {
+        let tmp = tempfile::tempdir().unwrap();
+        let action = fingerprinted_action(tmp.path(), b"video content");
+        assert_eq!(check_drift(&action), None);
+    }
+
+    #[test]
+    fn test_detects_missing_and_resized_sources() {
+        let tmp = tempfile::tempdir().unwrap();
+        let action = fingerprinted_action(tmp.path(), b"video content");
+
+        fs::write(&action.source, b"longer video content").unwrap();
+        assert!(matches!(
+            check_drift(&action),
+            Some(DriftReason::SizeChanged {
+                planned: 13,
+                actual: 20
+            })
+        ));
+
+        fs::remove_file(&action.source).unwrap();
+        assert_eq!(check_drift(&action), Some(DriftReason::SourceMissing));
+    }
+
+    #[test]
+    fn test_detects_same_size_content_change() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut action = fingerprinted_action(tmp.path(), b"video content");
+        fs::write(&action.source, b"VIDEO CONTENT").unwrap();
+        // Pin mtime so only the hash can catch the edit.
+        let meta = fs::metadata(&action.source).unwrap();
+        action.fingerprint.as_mut().unwrap().modified = hash::modified_secs(&meta);
+
+        assert_eq!(check_drift(&action), Some(DriftReason::ContentChanged));
+    }
+
+    #[test]
+    fn test_validate_plan_splits_drifted_actions() {
+        let tmp = tempfile::tempdir().unwrap();
+        let good = fingerprinted_action(tmp.path(), b"video content");
+        let mut occupied = good.clone();
+        occupied.destination = tmp.path().join("taken.mkv");
+        fs::write(&occupied.destination, b"x").unwrap();
+
+        let (valid, drifted) = validate_plan(&[good, occupied]);
+        assert_eq!(valid.len(), 1);
+        assert_eq!(drifted.len(), 1);
+        assert_eq!(drifted[0].reason, DriftReason::DestinationOccupied);
+    }
+}
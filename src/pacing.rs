@@ -0,0 +1,157 @@
+//! Pacing for shared machines.
+//!
+//! On a seedbox the organizer shares CPU, disks, and an IP address with
+//! other users and with the torrent client that pays for the box. Besides
+//! the TMDb rate cap, `tmdb.politeness_delay_ms` and `tmdb.jitter_ms` space
+//! requests out further, and `low_impact` folds it all into one switch:
+//! paced requests made one at a time, one file operation at a time with
+//! copies capped in bandwidth, and the process at low CPU and disk
+//! priority. [`summary`] states the pacing a run used.
+
+use std::io;
+use std::sync::OnceLock;
+
+use crate::config::AppConfig;
+
+/// TMDb request cap under `low_impact`.
+const LOW_IMPACT_REQUESTS_PER_SECOND: u32 = 4;
+/// Politeness delay under `low_impact`, in milliseconds.
+const LOW_IMPACT_DELAY_MS: u64 = 250;
+/// Jitter under `low_impact`, in milliseconds.
+const LOW_IMPACT_JITTER_MS: u64 = 500;
+/// Copy bandwidth per filesystem under `low_impact`, in MB per second.
+const LOW_IMPACT_IO_LIMIT_MB: u64 = 25;
+
+/// Niceness set by [`lower_priority`].
+#[cfg(unix)]
+const NICENESS: libc::c_int = 10;
+
+/// What [`lower_priority`] achieved, for [`summary`].
+static PRIORITY: OnceLock<&'static str> = OnceLock::new();
+
+/// Tighten `config` when `low_impact` is on; settings already stricter
+/// are kept.
+pub fn apply_low_impact(config: &mut AppConfig) {
+    if !config.low_impact {
+        return;
+    }
+    let tmdb = &mut config.tmdb;
+    tmdb.requests_per_second = match tmdb.requests_per_second {
+        0 => LOW_IMPACT_REQUESTS_PER_SECOND,
+        n => n.min(LOW_IMPACT_REQUESTS_PER_SECOND),
+    };
+    tmdb.politeness_delay_ms = tmdb.politeness_delay_ms.max(LOW_IMPACT_DELAY_MS);
+    tmdb.jitter_ms = tmdb.jitter_ms.max(LOW_IMPACT_JITTER_MS);
+    tmdb.concurrent_lookups = 1;
+    let organize = &mut config.organize;
+    organize.max_parallel = 1;
+    organize.min_parallel = 1;
+    organize.adaptive_parallel = false;
+    organize.io_limit_mb = match organize.io_limit_mb {
+        0 => LOW_IMPACT_IO_LIMIT_MB,
+        n => n.min(LOW_IMPACT_IO_LIMIT_MB),
+    };
+}
+
+/// Lower this process's CPU priority and, on Linux, its disk priority (the
+/// lowest best-effort level), so other work on the machine goes first.
+/// Threads started afterwards inherit both, so call it early.
+pub fn lower_priority() -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        // A process already nicer is left so; only root may raise priority.
+        // SAFETY: getpriority and setpriority take integers only.
+        let current = unsafe { libc::getpriority(libc::PRIO_PROCESS, 0) };
+        if current < NICENESS && unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, NICENESS) } == -1
+        {
+            return Err(io::Error::last_os_error());
+        }
+        #[cfg(target_os = "linux")]
+        {
+            const IOPRIO_WHO_PROCESS: libc::c_long = 1;
+            const IOPRIO_CLASS_BE: libc::c_long = 2;
+            const IOPRIO_CLASS_SHIFT: libc::c_long = 13;
+            const LOWEST_LEVEL: libc::c_long = 7;
+            let ioprio = (IOPRIO_CLASS_BE << IOPRIO_CLASS_SHIFT) | LOWEST_LEVEL;
+            // SAFETY: ioprio_set takes integers only.
+            if unsafe { libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, ioprio) } == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            let _ = PRIORITY.set("low CPU and disk priority");
+        }
+        #[cfg(not(target_os = "linux"))]
+        let _ = PRIORITY.set("low CPU priority");
+        Ok(())
+    }
+    #[cfg(not(unix))]
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "process priority is only lowered on Unix",
+    ))
+}
+
+/// The pacing of this run — request cap, delay, jitter, concurrency, copy
+/// bandwidth, and priority — when any was asked for beyond the plain rate
+/// cap.
+pub fn summary(config: &AppConfig) -> Option<String> {
+    let tmdb = &config.tmdb;
+    let organize = &config.organize;
+    if !config.low_impact
+        && tmdb.politeness_delay_ms == 0
+        && tmdb.jitter_ms == 0
+        && organize.io_limit_mb == 0
+    {
+        return None;
+    }
+    let mut parts = vec![match tmdb.requests_per_second {
+        0 => "TMDb uncapped".to_string(),
+        n => format!("TMDb at most {n}/s"),
+    }];
+    if tmdb.politeness_delay_ms > 0 {
+        parts.push(format!("{} ms pause", tmdb.politeness_delay_ms));
+    }
+    if tmdb.jitter_ms > 0 {
+        parts.push(format!("up to {} ms jitter", tmdb.jitter_ms));
+    }
+    parts.push(match (organize.adaptive_parallel, organize.max_parallel) {
+        (true, max) => format!("{}–{max} file operations at once", organize.min_parallel),
+        (false, 1) => "one file operation at a time".to_string(),
+        (false, n) => format!("{n} file operations at once"),
+    });
+    if organize.io_limit_mb > 0 {
+        parts.push(format!(
+            "copies at most {} MB/s per filesystem",
+            organize.io_limit_mb
+        ));
+    }
+    if let Some(priority) = PRIORITY.get() {
+        parts.push(priority.to_string());
+    }
+    Some(parts.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_low_impact_tightens_but_keeps_stricter_settings() {
+        let mut config = AppConfig::default();
+        assert_eq!(summary(&config), None);
+        config.low_impact = true;
+        config.tmdb.politeness_delay_ms = 1000;
+        config.organize.max_parallel = 8;
+        apply_low_impact(&mut config);
+        assert_eq!(config.tmdb.requests_per_second, 4);
+        assert_eq!(config.tmdb.politeness_delay_ms, 1000);
+        assert_eq!(config.tmdb.jitter_ms, 500);
+        assert_eq!(config.tmdb.concurrent_lookups, 1);
+        assert_eq!(config.organize.max_parallel, 1);
+        assert_eq!(config.organize.io_limit_mb, 25);
+        assert_eq!(
+            summary(&config).unwrap(),
+            "TMDb at most 4/s, 1000 ms pause, up to 500 ms jitter, one file operation at a time, \
+             copies at most 25 MB/s per filesystem"
+        );
+    }
+}
@@ -0,0 +1,192 @@
+//! A step-by-step account of how one filename is parsed, for
+//! `plex-org debug-parse`.
+//!
+//! Fixing a misparse starts with finding the step that went wrong: the
+//! tokens, an edition, part, or id tag cut out too eagerly, a property hunch
+//! misread, the title strategy picking the worse title, a profile strip
+//! term, or a learned token. [`trace`] records each of those in order and
+//! ends with the parsed result and the destination it leads to, so a
+//! contributor can see the whole path without adding logging and
+//! rebuilding.
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::config::AppConfig;
+use crate::database::Database;
+use crate::edition;
+use crate::enricher::Enricher;
+use crate::home_video;
+use crate::models::ParsedMedia;
+use crate::organizer;
+use crate::parser::{self, ParseOptions};
+use crate::part;
+use crate::profile;
+use crate::release_group;
+use crate::title_strategy::{self, TitleStrategy};
+
+/// Current `debug-parse --json` format version.
+pub const VERSION: u32 = 1;
+
+/// One step of a parse.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TraceStep {
+    pub stage: &'static str,
+    pub detail: String,
+}
+
+/// Every step of a parse, the result, and where it would be filed.
+#[derive(Debug, Clone, Serialize)]
+pub struct ParseTrace {
+    pub version: u32,
+    pub steps: Vec<TraceStep>,
+    pub parsed: ParsedMedia,
+    /// Destination relative to the library root, without TMDb.
+    pub destination: PathBuf,
+}
+
+impl ParseTrace {
+    fn step(&mut self, stage: &'static str, detail: impl Into<String>) {
+        self.steps.push(TraceStep {
+            stage,
+            detail: detail.into(),
+        });
+    }
+}
+
+/// Parse `name` as the pipeline does (parser, profile rules, learned title
+/// tokens from `db` when given), recording each step.
+pub fn trace(name: &str, config: &AppConfig, db: Option<&Database>) -> ParseTrace {
+    let options = ParseOptions::from_config(config);
+    let mut parsed = parser::parse_video(name, &options);
+    let mut trace = ParseTrace {
+        version: VERSION,
+        steps: Vec::new(),
+        parsed: ParsedMedia::default(),
+        destination: PathBuf::new(),
+    };
+
+    let tokens: Vec<String> = name
+        .split(['.', ' ', '_'])
+        .filter(|t| !t.is_empty())
+        .map(|t| {
+            if title_strategy::is_technical(t) {
+                format!("[{t}]")
+            } else {
+                t.to_string()
+            }
+        })
+        .collect();
+    trace.step("tokens", format!("{} ([technical])", tokens.join(" · ")));
+
+    let (plain, found) = edition::extract(name);
+    match &found {
+        Some(e) => trace.step("edition", format!("{} {:?} → {plain:?}", e.name, e.year)),
+        None => trace.step("edition", "none"),
+    }
+    let (plain, part) = part::extract(&plain);
+    match part {
+        Some(n) => trace.step("part", format!("{n} → {plain:?}")),
+        None => trace.step("part", "none"),
+    }
+    let (untagged, ids) = parser::extract_ids(&plain);
+    if ids.is_empty() {
+        trace.step("ids", "none");
+    } else {
+        trace.step("ids", format!("{ids:?} → {untagged:?}"));
+    }
+
+    let (ungrouped, group) = release_group::extract(&untagged);
+    match &group {
+        Some(group) => trace.step("group", format!("{group:?} → {ungrouped:?}")),
+        None => trace.step("group", "none at the end; left to hunch"),
+    }
+
+    let hunch = hunch::hunch(&ungrouped);
+    for (property, values) in hunch.properties() {
+        trace.step("hunch", format!("{property} = {}", values.join(", ")));
+    }
+
+    let hunch_title = hunch.title().unwrap_or_default();
+    trace.step(
+        "title",
+        format!(
+            "hunch {hunch_title:?} scores {}",
+            title_strategy::title_score(hunch_title, options.title_extraction)
+        ),
+    );
+    match title_strategy::before_year_title(&ungrouped, hunch.year(), options.title_extraction) {
+        Some(alternative) => trace.step(
+            "title",
+            format!(
+                "before-year {alternative:?} scores {}",
+                title_strategy::title_score(&alternative, options.title_extraction)
+            ),
+        ),
+        None => trace.step("title", "before-year: no words"),
+    }
+    let verdict = match parsed.title_strategy {
+        TitleStrategy::Hunch => "kept hunch's title (ties go to hunch)",
+        TitleStrategy::BeforeYear => "took the before-year title",
+    };
+    trace.step("title", format!("{verdict}: {:?}", parsed.title));
+
+    if let Some(db) = db {
+        let before = parsed.title.clone();
+        if db.restore_title(&mut parsed, &options) {
+            trace.step("learned", format!("{before:?} → {:?}", parsed.title));
+        } else {
+            trace.step("learned", "no learned tokens apply");
+        }
+    }
+
+    let rules = profile::rules_for(config, &parsed);
+    let mut profile = format!("{} profile", rules.profile);
+    if rules.skips(name) {
+        profile.push_str("; a skip term matches, so the file is left alone");
+    }
+    trace.step("profile", profile);
+    if let Some(title) = rules.strip_title(&parsed.title) {
+        trace.step("strip", format!("{:?} → {title:?}", parsed.title));
+    }
+    if home_video::is_home_video(name) {
+        trace.step("home video", "phone/camera name; never looked up");
+    }
+
+    // The enricher applies the profile; without a key it never searches.
+    let mut offline = config.clone();
+    offline.tmdb.api_key.clear();
+    let enriched = Enricher::new(offline).enrich(parsed);
+    trace.destination =
+        organizer::build_destination_path(&enriched, Path::new(name), Path::new(""), config);
+    trace.parsed = enriched.parsed;
+    trace
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_follows_the_pipeline() {
+        let mut config = AppConfig::default();
+        config.profiles.default.strip_terms = vec!["Part Two".to_string()];
+        let trace = trace(
+            "Dune.Part.Two.2024.1080p.AMZN.WEB-DL.DDP5.1.H.264-FLUX.mkv",
+            &config,
+            None,
+        );
+        let stages: Vec<_> = trace.steps.iter().map(|s| s.stage).collect();
+        assert_eq!(stages[..5], ["tokens", "edition", "part", "ids", "group"]);
+        assert!(stages.contains(&"hunch"));
+        assert!(trace.steps[0].detail.contains("[1080p]"));
+        let strip = trace.steps.iter().find(|s| s.stage == "strip").unwrap();
+        assert_eq!(strip.detail, r#""Dune Part Two" → "Dune""#);
+        assert_eq!(trace.parsed.title, "Dune");
+        assert_eq!(
+            trace.destination,
+            Path::new("Movies/Dune (2024)/Dune (2024).mkv")
+        );
+    }
+}
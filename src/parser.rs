@@ -4,12 +4,25 @@
 //! does all the heavy lifting. For music, we use simple linear regex
 //! patterns as a placeholder until a proper music parser is built.
 
+use chrono::{DateTime, Local};
 use regex::Regex;
 use std::sync::LazyLock;
 use tracing::debug;
 
-use crate::models::{MediaFile, MediaType, ParsedMedia};
-use crate::scanner::AUDIO_EXTENSIONS;
+use crate::anime;
+use crate::config::{AppConfig, TitleExtraction};
+use crate::edition;
+use crate::home_video;
+use crate::language;
+use crate::models::{Edition, ExternalIds, MediaFile, MediaType, ParsedMedia};
+use crate::nfo;
+use crate::part;
+use crate::quality::{Quality, ReleaseAttributes};
+use crate::release_group;
+use crate::scanner::{DirOverrides, AUDIO_EXTENSIONS};
+use crate::scoring::{ConfidenceScorer, ParseSignals};
+use crate::title_strategy::{self, TitleStrategy};
+use crate::titles;
 
 // ── Music placeholder regex ────────────────────────────────────────────────
 
@@ -22,23 +35,155 @@ static ALBUM_DIR_RE: LazyLock<Regex> = LazyLock::new(|| {
     Regex::new(r"^(?P<artist>.+?)\s*-\s*(?P<album>.+?)(?:\s*\((?P<year>\d{4})\))?$").unwrap()
 });
 
+/// Id tags other organizers put in names — `{tmdb-603}`, `[tmdbid-603]`,
+/// `{imdb-tt0133093}`, `[imdbid-tt0133093]`, `{tvdb-81189}` — and Radarr's
+/// `{edition-…}`, which is only dropped here (see `edition`).
+static ID_TAG_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\s*[\{\[](tmdb|tmdbid|imdb|imdbid|tvdb|tvdbid|edition)[-=]([^\}\]]*)[\}\]]")
+        .unwrap()
+});
+
+static IMDB_ID_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^tt\d{7,}$").unwrap());
+
 // ── Public API ──────────────────────────────────────────────────────────────
 
+/// The `[parsing]` settings a parse follows: how the before-year title is
+/// cut (see `title_strategy`) and how confidence is scored (see `scoring`).
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    pub title_extraction: TitleExtraction,
+    pub scorer: ConfidenceScorer,
+}
+
+impl ParseOptions {
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            title_extraction: config.parsing.title_extraction,
+            scorer: ConfidenceScorer::new(config.parsing.scoring.clone()),
+        }
+    }
+}
+
 /// Parse a media file into structured metadata.
 ///
 /// Routes to hunch for video content, or simple regex for music.
-pub fn parse_media_file(file: &MediaFile) -> ParsedMedia {
+pub fn parse_media_file(file: &MediaFile, options: &ParseOptions) -> ParsedMedia {
     let full_name = file.full_name();
 
-    if AUDIO_EXTENSIONS.contains(&file.extension.as_str()) {
-        return parse_music(file);
+    let mut parsed = if AUDIO_EXTENSIONS.contains(&file.extension.as_str()) {
+        parse_music(file)
+    } else if let Some(folder) = &file.unit_name {
+        parse_unit(folder, &full_name, options)
+    } else {
+        parse_video(&full_name, options)
+    };
+    parsed.unit_extras = file.unit_extras.clone();
+    parsed.file_date = file
+        .modified
+        .map(|t| DateTime::<Local>::from(t).date_naive());
+    // Radarr and Jellyfin often tag only the movie folder.
+    if parsed.ids.is_empty() {
+        parsed.ids = extract_ids(&file.parent_dir).1;
+    }
+    // Episode .nfo files name the episode, not the show.
+    if matches!(parsed.media_type, MediaType::Movie | MediaType::Unknown) {
+        if let Some(hints) = nfo::find_hints(&file.source_path) {
+            apply_hints(&mut parsed, hints, options);
+        }
+    }
+    if let Some(overrides) = &file.overrides {
+        apply_overrides(&mut parsed, overrides, options);
+    }
+    parsed
+}
+
+/// Take what a `.plexorg.toml` says over everything the name and metadata
+/// files gave. Overrides naming the file make it certain.
+fn apply_overrides(parsed: &mut ParsedMedia, overrides: &DirOverrides, options: &ParseOptions) {
+    let ids = &mut parsed.ids;
+    ids.tmdb = overrides.tmdb_id.or(ids.tmdb);
+    ids.imdb = overrides.imdb_id.clone().or(ids.imdb.take());
+    ids.tvdb = overrides.tvdb_id.or(ids.tvdb);
+    parsed.year = overrides.year.or(parsed.year);
+    if let Some(title) = &overrides.title {
+        set_title(parsed, title.clone(), options);
+    }
+    if let Some(name) = &overrides.edition {
+        parsed.edition = Some(Edition {
+            name: name.clone(),
+            year: None,
+        });
+    }
+    parsed.title_template = overrides.title_template.clone();
+    if overrides.identifies() {
+        parsed.confidence = 100.0;
+    }
+}
+
+/// Take what a metadata file beside the video says: its ids where the name
+/// has none, and its title and year over the parsed ones.
+fn apply_hints(parsed: &mut ParsedMedia, hints: nfo::Hints, options: &ParseOptions) {
+    let ids = &mut parsed.ids;
+    ids.tmdb = ids.tmdb.or(hints.ids.tmdb);
+    ids.imdb = ids.imdb.take().or(hints.ids.imdb);
+    ids.tvdb = ids.tvdb.or(hints.ids.tvdb);
+    parsed.year = hints.year.or(parsed.year);
+    let title = hints.title.unwrap_or_else(|| parsed.title.clone());
+    let confidence = parsed.confidence;
+    set_title(parsed, title, options);
+    parsed.confidence = parsed.confidence.max(confidence);
+    parsed.nfo = Some(hints.path);
+}
+
+/// Remove id tags from a name, returning what is left and the ids found.
+pub fn extract_ids(name: &str) -> (String, ExternalIds) {
+    let mut ids = ExternalIds::default();
+    for caps in ID_TAG_RE.captures_iter(name) {
+        let value = caps[2].trim();
+        match caps[1].to_lowercase().as_str() {
+            "tmdb" | "tmdbid" => ids.tmdb = ids.tmdb.or(value.parse().ok()),
+            "imdb" | "imdbid" if IMDB_ID_RE.is_match(value) => {
+                ids.imdb.get_or_insert_with(|| value.to_string());
+            }
+            "tvdb" | "tvdbid" => ids.tvdb = ids.tvdb.or(value.parse().ok()),
+            _ => {}
+        }
     }
+    (ID_TAG_RE.replace_all(name, "").into_owned(), ids)
+}
 
-    parse_video(&full_name)
+/// Parse the main video of a one-movie folder: title and year come from
+/// the folder name, with the filename filling in whatever the folder lacks.
+///
+/// Falls back to the filename alone when the folder name has no title.
+fn parse_unit(folder: &str, filename: &str, options: &ParseOptions) -> ParsedMedia {
+    let from_file = parse_video(filename, options);
+    let mut parsed = parse_video(folder, options);
+    if parsed.title.is_empty() {
+        return from_file;
+    }
+    parsed.media_type = MediaType::Movie;
+    parsed.year = parsed.year.or(from_file.year);
+    // The file itself is the better witness of its encoding.
+    if !from_file.quality.is_empty() {
+        parsed.quality = from_file.quality;
+    }
+    if !from_file.attributes.is_empty() {
+        parsed.attributes = from_file.attributes;
+    }
+    parsed.release_group = parsed.release_group.or(from_file.release_group);
+    parsed.source_tag = parsed.source_tag.or(from_file.source_tag);
+    parsed.language = parsed.language.or(from_file.language);
+    parsed.edition = parsed.edition.or(from_file.edition);
+    parsed.part = from_file.part;
+    parsed.confidence = parsed.confidence.max(from_file.confidence);
+    parsed
 }
 
 /// Parse a video filename using hunch.
-fn parse_video(filename: &str) -> ParsedMedia {
+///
+/// Also useful for subtitle filenames, which follow the same release naming.
+pub fn parse_video(filename: &str, options: &ParseOptions) -> ParsedMedia {
     if filename.is_empty() {
         return ParsedMedia {
             raw_filename: filename.to_string(),
@@ -46,7 +191,11 @@ fn parse_video(filename: &str) -> ParsedMedia {
         };
     }
 
-    let result = hunch::hunch(filename);
+    let (plain, edition) = edition::extract(filename);
+    let (plain, part) = part::extract(&plain);
+    let (untagged, ids) = extract_ids(&plain);
+    let (ungrouped, group) = release_group::extract(&untagged);
+    let result = hunch::hunch(&ungrouped);
 
     let media_type = match result.media_type() {
         Some(hunch::MediaType::Movie) => MediaType::Movie,
@@ -54,21 +203,46 @@ fn parse_video(filename: &str) -> ParsedMedia {
         None => MediaType::Unknown,
     };
 
-    let title = result.title().unwrap_or("").to_string();
     let year = result.year();
+    let (title, title_strategy) = title_strategy::choose(
+        &ungrouped,
+        result.title().unwrap_or("").to_string(),
+        year,
+        options.title_extraction,
+    );
     let season = result.season();
     let episode = result.episode();
     let episode_title = result.episode_title().map(String::from);
-    let release_group = result.release_group().map(String::from);
+    let release_group = group.or_else(|| result.release_group().map(String::from));
     let source_tag = result.source().map(String::from);
-    let quality = build_quality_string(&result);
+    let quality = Quality::from_hunch(&result).to_string();
+    let title_language = language::detect_title_language(&title);
+    let mut titles = titles::variants(&title);
+    let anime = anime::release(filename, &result);
+    if let Some(english) = anime
+        .as_ref()
+        .and_then(|_| anime::alternative_title(filename))
+    {
+        if titles.is_empty() && english != title {
+            titles.romanized = Some(title.clone());
+            titles.english = Some(english);
+        }
+    }
 
     // Compute confidence from how many fields hunch populated
-    let confidence = compute_confidence(&title, year, media_type, season, episode);
+    let confidence = compute_confidence(
+        &options.scorer,
+        &title,
+        year,
+        media_type,
+        season,
+        episode,
+        title_strategy,
+    );
 
     debug!(
-        "parsed {filename:?} → title={title:?} type={media_type} year={year:?} \
-         S{season:?}E{episode:?} conf={confidence:.0}"
+        "parsed {filename:?} → title={title:?} ({title_strategy}) type={media_type} \
+         year={year:?} S{season:?}E{episode:?} conf={confidence:.0}"
     );
 
     ParsedMedia {
@@ -85,8 +259,22 @@ fn parse_video(filename: &str) -> ParsedMedia {
         language: result
             .first(hunch::matcher::span::Property::Language)
             .map(String::from),
+        title_language,
+        titles,
         confidence,
         raw_filename: filename.to_string(),
+        file_date: None,
+        home_video: home_video::is_home_video(filename),
+        nfo: None,
+        ids,
+        edition,
+        part,
+        anime,
+        media: None,
+        attributes: ReleaseAttributes::parse(filename),
+        title_strategy,
+        year_from_match: false,
+        title_template: None,
         artist: None,
         album: None,
         track_number: None,
@@ -94,6 +282,22 @@ fn parse_video(filename: &str) -> ParsedMedia {
     }
 }
 
+/// Replace the title of a parsed video, refreshing what derives from it.
+pub fn set_title(parsed: &mut ParsedMedia, title: String, options: &ParseOptions) {
+    parsed.title_language = language::detect_title_language(&title);
+    parsed.titles = titles::variants(&title);
+    parsed.confidence = compute_confidence(
+        &options.scorer,
+        &title,
+        parsed.year,
+        parsed.media_type,
+        parsed.season,
+        parsed.episode,
+        parsed.title_strategy,
+    );
+    parsed.title = title;
+}
+
 /// Parse a music file using simple regex (placeholder).
 fn parse_music(file: &MediaFile) -> ParsedMedia {
     let mut parsed = ParsedMedia {
@@ -136,63 +340,41 @@ fn parse_music(file: &MediaFile) -> ParsedMedia {
     parsed
 }
 
-/// Build a human-readable quality string from hunch output.
-fn build_quality_string(result: &hunch::HunchResult) -> String {
-    let mut parts = Vec::new();
-    if let Some(v) = result.screen_size() {
-        parts.push(v.to_string());
-    }
-    if let Some(v) = result.source() {
-        parts.push(v.to_string());
-    }
-    if let Some(v) = result.video_codec() {
-        parts.push(v.to_string());
-    }
-    parts.join(" ")
-}
-
-/// Compute a 0–100 confidence score based on populated fields.
+/// Compute a 0–100 confidence score based on populated fields, with the
+/// configured scoring weights (see `scoring`).
 ///
-/// Mirrors the Python version's heuristic. Capped at 85 — DB enrichment
-/// is needed for higher confidence.
+/// Mirrors the Python version's heuristic. Capped at 85 by default — DB
+/// enrichment is needed for higher confidence.
 fn compute_confidence(
+    scorer: &ConfidenceScorer,
     title: &str,
     year: Option<i32>,
     media_type: MediaType,
     season: Option<i32>,
     episode: Option<i32>,
+    strategy: TitleStrategy,
 ) -> f64 {
-    let mut score: f64 = 0.0;
-
-    if !title.is_empty() {
-        score += 30.0;
-    }
-    if year.is_some() {
-        score += 20.0;
-    }
-    if media_type != MediaType::Unknown {
-        score += 15.0;
-    }
-    if media_type == MediaType::Tv && season.is_some() {
-        score += 15.0;
-    }
-    if media_type == MediaType::Tv && episode.is_some() {
-        score += 15.0;
-    }
-    if media_type == MediaType::Movie && year.is_some() {
-        score += 15.0;
-    }
-
-    score.min(85.0)
+    scorer.parse_confidence(&ParseSignals {
+        title,
+        year,
+        media_type,
+        season,
+        episode,
+        strategy,
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::scoring::ScoringWeights;
 
     #[test]
     fn test_parse_movie() {
-        let result = parse_video("The.Matrix.1999.1080p.BluRay.x264-GROUP.mkv");
+        let result = parse_video(
+            "The.Matrix.1999.1080p.BluRay.x264-GROUP.mkv",
+            &ParseOptions::default(),
+        );
         assert_eq!(result.title, "The Matrix");
         assert_eq!(result.year, Some(1999));
         assert_eq!(result.media_type, MediaType::Movie);
@@ -201,7 +383,10 @@ mod tests {
 
     #[test]
     fn test_parse_tv_episode() {
-        let result = parse_video("The.Walking.Dead.S05E03.720p.BluRay.x264-DEMAND.mkv");
+        let result = parse_video(
+            "The.Walking.Dead.S05E03.720p.BluRay.x264-DEMAND.mkv",
+            &ParseOptions::default(),
+        );
         assert_eq!(result.title, "The Walking Dead");
         assert_eq!(result.season, Some(5));
         assert_eq!(result.episode, Some(3));
@@ -211,7 +396,7 @@ mod tests {
 
     #[test]
     fn test_parse_empty_filename() {
-        let result = parse_video("");
+        let result = parse_video("", &ParseOptions::default());
         assert_eq!(result.confidence, 0.0);
     }
 
@@ -224,8 +409,12 @@ mod tests {
             detected_type: MediaType::Music,
             size_bytes: 30_000_000,
             parent_dir: "Artist - Album (2020)".to_string(),
+            modified: None,
+            unit_name: None,
+            unit_extras: Vec::new(),
+            overrides: None,
         };
-        let result = parse_media_file(&file);
+        let result = parse_media_file(&file, &ParseOptions::default());
         assert_eq!(result.media_type, MediaType::Music);
         assert_eq!(result.track_number, Some(1));
         assert_eq!(result.track_title.as_deref(), Some("Song Title"));
@@ -234,9 +423,167 @@ mod tests {
         assert_eq!(result.year, Some(2020));
     }
 
+    #[test]
+    fn test_parse_folder_unit_prefers_folder_name() {
+        let file = MediaFile {
+            source_path: "/movies/Heat (1995)/heat-bd.1080p.mkv".into(),
+            filename: "heat-bd.1080p".to_string(),
+            extension: ".mkv".to_string(),
+            detected_type: MediaType::Unknown,
+            size_bytes: 30_000_000,
+            parent_dir: "Heat (1995)".to_string(),
+            modified: None,
+            unit_name: Some("Heat (1995)".to_string()),
+            unit_extras: Vec::new(),
+            overrides: None,
+        };
+        let result = parse_media_file(&file, &ParseOptions::default());
+        assert_eq!(result.title, "Heat");
+        assert_eq!(result.year, Some(1995));
+        assert_eq!(result.media_type, MediaType::Movie);
+        assert!(result.quality.contains("1080p"));
+    }
+
+    #[test]
+    fn test_anniversary_keeps_original_year() {
+        let result = parse_video(
+            "Alien.1979.40th.Anniversary.2019.1080p.BluRay.x264-GRP.mkv",
+            &ParseOptions::default(),
+        );
+        assert_eq!(result.title, "Alien");
+        assert_eq!(result.year, Some(1979));
+        let edition = result.edition.unwrap();
+        assert_eq!(
+            (edition.name.as_str(), edition.year),
+            ("40th Anniversary", Some(2019))
+        );
+    }
+
+    #[test]
+    fn test_release_groups_are_cut_whole() {
+        for (name, title, group) in [
+            (
+                "Heat.1995.1080p.BluRay.DTS.x264-D-Z0N3.mkv",
+                "Heat",
+                "D-Z0N3",
+            ),
+            (
+                "Inception.2010.1080p.BluRay.x264.AAC-YTS.MX.mp4",
+                "Inception",
+                "YTS.MX",
+            ),
+            (
+                "Inception (2010) [1080p] [YTS.MX].mp4",
+                "Inception",
+                "YTS.MX",
+            ),
+            (
+                "Heat.1995.1080p.BluRay.x264-ted423@FRDS.mkv",
+                "Heat",
+                "ted423@FRDS",
+            ),
+            (
+                "Spider-Man.2002.1080p.BluRay.x264-SPARKS.mkv",
+                "Spider-Man",
+                "SPARKS",
+            ),
+        ] {
+            let result = parse_video(name, &ParseOptions::default());
+            assert_eq!(result.title, title, "{name}");
+            assert!(result.year.is_some(), "{name}");
+            assert_eq!(result.release_group.as_deref(), Some(group), "{name}");
+            assert!(result.quality.contains("1080p"), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_id_tags() {
+        let result = parse_video(
+            "The Matrix (1999) {imdb-tt0133093} {edition-Remastered}.mkv",
+            &ParseOptions::default(),
+        );
+        assert_eq!(result.title, "The Matrix");
+        assert_eq!(result.year, Some(1999));
+        assert_eq!(result.ids.imdb.as_deref(), Some("tt0133093"));
+        assert_eq!(result.edition.unwrap().name, "Remastered");
+        assert_eq!(
+            result.raw_filename,
+            "The Matrix (1999) {imdb-tt0133093} {edition-Remastered}.mkv"
+        );
+
+        let (rest, ids) = extract_ids("Heat (1995) [tmdbid-949] [imdbid-bogus]");
+        assert_eq!(rest, "Heat (1995)");
+        assert_eq!((ids.tmdb, ids.imdb), (Some(949), None));
+
+        let file = MediaFile {
+            source_path: "/movies/Alien (1979) {tmdb-348}/Alien.1979.1080p.mkv".into(),
+            filename: "Alien.1979.1080p".to_string(),
+            extension: ".mkv".to_string(),
+            detected_type: MediaType::Unknown,
+            size_bytes: 30_000_000,
+            parent_dir: "Alien (1979) {tmdb-348}".to_string(),
+            modified: None,
+            unit_name: None,
+            unit_extras: Vec::new(),
+            overrides: None,
+        };
+        assert_eq!(
+            parse_media_file(&file, &ParseOptions::default()).ids.tmdb,
+            Some(348)
+        );
+    }
+
+    #[test]
+    fn test_directory_overrides_win() {
+        let file = MediaFile {
+            source_path: "/inbox/bullets/rip.mkv".into(),
+            filename: "rip".to_string(),
+            extension: ".mkv".to_string(),
+            detected_type: MediaType::Unknown,
+            size_bytes: 30_000_000,
+            parent_dir: "bullets".to_string(),
+            modified: None,
+            unit_name: None,
+            unit_extras: Vec::new(),
+            overrides: Some(DirOverrides {
+                title: Some("Let the Bullets Fly".to_string()),
+                year: Some(2010),
+                tmdb_id: Some(51533),
+                title_template: Some("{english}".to_string()),
+                ..Default::default()
+            }),
+        };
+        let result = parse_media_file(&file, &ParseOptions::default());
+        assert_eq!(result.title, "Let the Bullets Fly");
+        assert_eq!(result.year, Some(2010));
+        assert_eq!(result.ids.tmdb, Some(51533));
+        assert_eq!(result.title_template.as_deref(), Some("{english}"));
+        assert_eq!(result.confidence, 100.0);
+    }
+
     #[test]
     fn test_confidence_caps_at_85() {
-        let conf = compute_confidence("Title", Some(2024), MediaType::Movie, None, None);
+        let conf = compute_confidence(
+            &ConfidenceScorer::default(),
+            "Title",
+            Some(2024),
+            MediaType::Movie,
+            None,
+            None,
+            TitleStrategy::Hunch,
+        );
         assert!(conf <= 85.0);
+
+        // Each parse scores with the weights it is given.
+        let capped = ParseOptions {
+            scorer: ConfidenceScorer::new(ScoringWeights {
+                parse_cap: 50.0,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let name = "The.Matrix.1999.1080p.BluRay.x264-GROUP.mkv";
+        assert_eq!(parse_video(name, &capped).confidence, 50.0);
+        assert!(parse_video(name, &ParseOptions::default()).confidence > 50.0);
     }
 }
@@ -0,0 +1,95 @@
+//! Multi-part movies: one film split over several files.
+//!
+//! Rips of two-disc releases come as `Movie (2001) CD1.avi` and `CD2.avi`,
+//! `Movie.Disc.1.mkv`, or `Movie.pt1.mkv`. Left in the name, the tag ends
+//! up in the title and each file is filed as a movie of its own. The tag
+//! is cut out before parsing and kept as the file's part number, and the
+//! files go into one folder as `Movie (2001) - part1.avi`, which Plex
+//! stacks into one item.
+//!
+//! `Part N` is also how sequels and split finales are named (`Deathly
+//! Hallows Part 1`), so the spelled-out word only counts after the release
+//! year; `cd`, `disc`, `disk`, and `pt` count anywhere after the title.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+/// `CD1`, `Disc 2`, `pt.1`, `(Part 2)`, with the separator before it.
+static PART_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?ix)
+        [.\s_-]+[(\[]?
+        (?P<tag>cd|dis[ck]|pt|part)[.\s_-]?(?P<number>\d{1,2})
+        [)\]]?
+        (?P<after>[.\s_-]|$)",
+    )
+    .unwrap()
+});
+
+/// A release year standing alone in a name.
+static YEAR_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?:^|[^0-9])(?:19|20)\d{2}(?:[^0-9]|$)").unwrap());
+
+/// Cut a part tag out of a file name, returning what is left and the part
+/// number (1 for `CD1`).
+pub fn extract(name: &str) -> (String, Option<u32>) {
+    for caps in PART_RE.captures_iter(name) {
+        let whole = caps.get(0).unwrap();
+        let before = &name[..whole.start()];
+        if before.trim_matches(['.', ' ', '_', '-']).is_empty() {
+            continue;
+        }
+        if caps["tag"].eq_ignore_ascii_case("part") && !YEAR_RE.is_match(before) {
+            continue;
+        }
+        let Some(number) = caps["number"].parse().ok().filter(|&n| n > 0) else {
+            continue;
+        };
+        // The separator after the tag stays; it divides what follows.
+        let rest = format!("{before}{}", &name[caps.name("after").unwrap().start()..]);
+        return (rest, Some(number));
+    }
+    (name.to_string(), None)
+}
+
+/// The suffix Plex stacks parts by: ` - part2`.
+pub fn suffix(part: u32) -> String {
+    format!(" - part{part}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_part_tags() {
+        let cases = [
+            ("Movie (2001) CD1.mkv", "Movie (2001).mkv", Some(1)),
+            ("The.Matrix.1999.cd2.avi", "The.Matrix.1999.avi", Some(2)),
+            (
+                "Lawrence.of.Arabia.1962.Disc.2.1080p.mkv",
+                "Lawrence.of.Arabia.1962.1080p.mkv",
+                Some(2),
+            ),
+            ("Heat.pt1.mkv", "Heat.mkv", Some(1)),
+            (
+                "Seven Samurai (1954) (Part 2).mkv",
+                "Seven Samurai (1954).mkv",
+                Some(2),
+            ),
+            (
+                "Harry.Potter.and.the.Deathly.Hallows.Part.1.2010.mkv",
+                "Harry.Potter.and.the.Deathly.Hallows.Part.1.2010.mkv",
+                None,
+            ),
+            ("Dune.Part.Two.2024.mkv", "Dune.Part.Two.2024.mkv", None),
+            ("CD1.mkv", "CD1.mkv", None),
+            ("Movie.2001.CD0.mkv", "Movie.2001.CD0.mkv", None),
+        ];
+        for (name, rest, part) in cases {
+            assert_eq!(extract(name), (rest.to_string(), part), "{name}");
+        }
+        assert_eq!(suffix(2), " - part2");
+    }
+}
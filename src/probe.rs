@@ -0,0 +1,272 @@
+//! Embedded stream metadata read with ffprobe.
+//!
+//! A release name says what the uploader claimed; the streams say what the
+//! file holds. Upscaled and mislabeled releases are common — a `1080p` in
+//! the name over a 1280×720 stream — and quality decisions (duplicates,
+//! upgrades, `min_quality`) built on the claim keep the worse copy. With
+//! `scan.probe` on, each video is run through `ffprobe` (as HTTP goes
+//! through curl, nothing is linked in), its duration, resolution, codec,
+//! and audio and subtitle tracks are kept, and the measured resolution
+//! replaces the one in the name.
+
+use std::io;
+use std::path::Path;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::models::ParsedMedia;
+use crate::quality::{Quality, Resolution};
+
+/// Set once ffprobe turned out to be missing, so the warning is given once.
+static UNAVAILABLE: AtomicBool = AtomicBool::new(false);
+
+/// What the streams of a media file hold.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MediaMetadata {
+    /// Length in seconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f64>,
+    /// Width and height of the first video stream.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dimensions: Option<(u32, u32)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolution: Option<Resolution>,
+    /// Video codec as ffprobe names it (`h264`, `hevc`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub codec: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub audio_tracks: Vec<Track>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subtitle_tracks: Vec<Track>,
+}
+
+/// One audio or subtitle stream.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Track {
+    pub codec: String,
+    /// ISO 639-2 tag, when the stream has one (`eng`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Audio channels.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channels: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct Output {
+    #[serde(default)]
+    streams: Vec<Stream>,
+    #[serde(default)]
+    format: Option<Format>,
+}
+
+#[derive(Deserialize)]
+struct Stream {
+    #[serde(default)]
+    codec_type: String,
+    #[serde(default)]
+    codec_name: String,
+    width: Option<u32>,
+    height: Option<u32>,
+    channels: Option<u32>,
+    #[serde(default)]
+    tags: Tags,
+    #[serde(default)]
+    disposition: Disposition,
+}
+
+#[derive(Deserialize, Default)]
+struct Tags {
+    language: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct Disposition {
+    /// Cover art is a video stream too.
+    #[serde(default)]
+    attached_pic: u8,
+}
+
+#[derive(Deserialize)]
+struct Format {
+    /// Seconds, as a decimal string.
+    duration: Option<String>,
+}
+
+/// Run ffprobe on `path`.
+pub fn probe(path: &Path) -> Result<MediaMetadata> {
+    let output = Command::new("ffprobe")
+        .args(["-v", "error", "-print_format", "json"])
+        .args(["-show_format", "-show_streams"])
+        .arg(path)
+        .output()
+        .context("Failed to run ffprobe; is it installed and on PATH?")?;
+    if !output.status.success() {
+        bail!(
+            "ffprobe failed on {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    parse_output(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Read ffprobe's `-print_format json -show_format -show_streams` output.
+pub fn parse_output(json: &str) -> Result<MediaMetadata> {
+    let output: Output = serde_json::from_str(json).context("Unexpected ffprobe output")?;
+    let mut metadata = MediaMetadata {
+        duration: output
+            .format
+            .and_then(|f| f.duration)
+            .and_then(|d| d.parse().ok()),
+        ..Default::default()
+    };
+    for stream in output.streams {
+        let language = stream.tags.language.filter(|l| l != "und");
+        match stream.codec_type.as_str() {
+            "video" if stream.disposition.attached_pic == 0 && metadata.codec.is_none() => {
+                metadata.codec = Some(stream.codec_name);
+                if let (Some(width), Some(height)) = (stream.width, stream.height) {
+                    metadata.dimensions = Some((width, height));
+                    metadata.resolution = Resolution::from_dimensions(width, height);
+                }
+            }
+            "audio" => metadata.audio_tracks.push(Track {
+                codec: stream.codec_name,
+                language,
+                channels: stream.channels,
+            }),
+            "subtitle" => metadata.subtitle_tracks.push(Track {
+                codec: stream.codec_name,
+                language,
+                channels: None,
+            }),
+            _ => {}
+        }
+    }
+    Ok(metadata)
+}
+
+/// Probe the video behind `parsed`, keep what was found, and let the
+/// measured resolution override the named one. A file ffprobe cannot read
+/// keeps its parsed quality; a missing ffprobe is warned about once.
+pub fn apply(parsed: &mut ParsedMedia, path: &Path) {
+    apply_with(parsed, path, probe);
+}
+
+/// [`apply`], reading the streams with `probe`.
+fn apply_with(
+    parsed: &mut ParsedMedia,
+    path: &Path,
+    probe: impl Fn(&Path) -> Result<MediaMetadata>,
+) {
+    if UNAVAILABLE.load(Ordering::Relaxed) {
+        return;
+    }
+    let metadata = match probe(path) {
+        Ok(metadata) => metadata,
+        Err(e) if is_missing(&e) => {
+            if !UNAVAILABLE.swap(true, Ordering::Relaxed) {
+                warn!("{e:#}; scan.probe is ignored");
+            }
+            return;
+        }
+        Err(e) => {
+            debug!("{e:#}");
+            return;
+        }
+    };
+    if let Some(measured) = metadata.resolution {
+        let mut quality = Quality::parse(&parsed.quality);
+        if quality.resolution != Some(measured) {
+            debug!(
+                "{:?} claims {:?} but streams {:?}",
+                parsed.raw_filename, quality.resolution, metadata.dimensions
+            );
+            quality.resolution = Some(measured);
+            parsed.quality = quality.to_string();
+        }
+    }
+    parsed.media = Some(metadata);
+}
+
+/// Whether `error` is ffprobe not being installed: running it found no
+/// such program.
+fn is_missing(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .filter_map(|e| e.downcast_ref::<io::Error>())
+        .any(|e| e.kind() == io::ErrorKind::NotFound)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ffprobe_output_and_override_quality() {
+        let json = r#"{
+            "streams": [
+                {"codec_type": "video", "codec_name": "h264", "width": 1280, "height": 534},
+                {"codec_type": "audio", "codec_name": "ac3", "channels": 6,
+                 "tags": {"language": "eng"}},
+                {"codec_type": "audio", "codec_name": "aac", "channels": 2,
+                 "tags": {"language": "und"}},
+                {"codec_type": "subtitle", "codec_name": "subrip", "tags": {"language": "fre"}},
+                {"codec_type": "video", "codec_name": "mjpeg", "width": 600, "height": 900,
+                 "disposition": {"attached_pic": 1}}
+            ],
+            "format": {"duration": "6123.456000"}
+        }"#;
+        let metadata = parse_output(json).unwrap();
+        assert_eq!(metadata.duration, Some(6123.456));
+        assert_eq!(metadata.dimensions, Some((1280, 534)));
+        assert_eq!(metadata.resolution, Some(Resolution::Hd720));
+        assert_eq!(metadata.codec.as_deref(), Some("h264"));
+        assert_eq!(metadata.audio_tracks.len(), 2);
+        assert_eq!(metadata.audio_tracks[0].channels, Some(6));
+        assert_eq!(metadata.audio_tracks[1].language, None);
+        assert_eq!(metadata.subtitle_tracks[0].language.as_deref(), Some("fre"));
+
+        let mut parsed = ParsedMedia {
+            quality: "1080p BluRay x264".to_string(),
+            ..Default::default()
+        };
+        apply_with(
+            &mut parsed,
+            Path::new("movie.mkv"),
+            |_| Ok(metadata.clone()),
+        );
+        assert_eq!(
+            Quality::parse(&parsed.quality),
+            Quality::parse("720p BluRay x264")
+        );
+        assert_eq!(parsed.media, Some(metadata));
+
+        // An unreadable file keeps what its name says.
+        let mut unread = ParsedMedia {
+            quality: "1080p BluRay x264".to_string(),
+            ..Default::default()
+        };
+        apply_with(&mut unread, Path::new("movie.mkv"), |_| {
+            bail!("ffprobe failed")
+        });
+        assert_eq!(unread.quality, "1080p BluRay x264");
+        assert!(unread.media.is_none());
+    }
+
+    #[test]
+    fn test_missing_ffprobe_is_told_by_error_kind() {
+        let spawn = io::Error::new(io::ErrorKind::NotFound, "No such file or directory");
+        let missing = anyhow::Error::new(spawn).context("Failed to run ffprobe");
+        assert!(is_missing(&missing));
+        let denied = io::Error::new(io::ErrorKind::PermissionDenied, "Permission denied");
+        assert!(!is_missing(
+            &anyhow::Error::new(denied).context("Failed to run ffprobe")
+        ));
+    }
+}
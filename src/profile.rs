@@ -0,0 +1,209 @@
+//! Processing profiles: anime vs everything else.
+//!
+//! Fansub anime releases are structurally noisier than scene movie
+//! releases (`[Group] Title - 05 (1080p) [A1B2C3D4].mkv`), so they may need
+//! a lower review threshold, a different match score, and their own list
+//! of release terms. With `profiles.detect_anime` on, each file gets the
+//! anime profile when its name looks like a fansub release; all other files
+//! get the default profile. Unset thresholds fall back to the top-level
+//! config.
+
+use std::fmt;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{AppConfig, ProfileRules};
+use crate::models::ParsedMedia;
+
+/// Leading `[Group]` tag.
+static LEADING_GROUP_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\[[^\]]+\]").unwrap());
+
+/// `[A1B2C3D4]` / `(A1B2C3D4)` CRC32 checksum, also read by `anime`.
+pub(crate) static CRC_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[\[(](?P<crc>[0-9A-Fa-f]{8})[\])]").unwrap());
+
+/// ` - 05 ` / ` - 1042v2 ` absolute episode number.
+static ABSOLUTE_EPISODE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\s-\s\d{2,4}(?:v\d)?(?:[\s\[(.]|$)").unwrap());
+
+/// Which profile a file is processed under.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Profile {
+    Default,
+    Anime,
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Default => "default",
+            Self::Anime => "anime",
+        })
+    }
+}
+
+/// Whether a filename looks like a fansub anime release: at least two of
+/// a leading `[Group]` tag, a CRC32 tag, and a ` - NN` episode number.
+pub fn looks_like_anime(filename: &str) -> bool {
+    let signals = [
+        LEADING_GROUP_RE.is_match(filename),
+        CRC_RE.is_match(filename),
+        ABSOLUTE_EPISODE_RE.is_match(filename),
+    ];
+    signals.iter().filter(|s| **s).count() >= 2
+}
+
+/// A file's profile with its thresholds resolved.
+#[derive(Debug, Clone)]
+pub struct Rules<'a> {
+    pub profile: Profile,
+    pub review_threshold: f64,
+    pub min_match_score: f64,
+    pub strip_terms: &'a [String],
+    pub skip_terms: &'a [String],
+    pub preserve_artwork: bool,
+}
+
+impl Rules<'_> {
+    /// The title with strip terms removed, or `None` if nothing changed
+    /// (or nothing would be left).
+    pub fn strip_title(&self, title: &str) -> Option<String> {
+        let mut words: Vec<&str> = title.split_whitespace().collect();
+        let before = words.len();
+        for term in self.strip_terms {
+            let term = words_of(term);
+            if term.is_empty() {
+                continue;
+            }
+            let mut i = 0;
+            while i + term.len() <= words.len() {
+                let matches = words[i..i + term.len()]
+                    .iter()
+                    .zip(&term)
+                    .all(|(w, t)| normalize(w) == *t);
+                if matches {
+                    words.drain(i..i + term.len());
+                } else {
+                    i += 1;
+                }
+            }
+        }
+        (words.len() != before && !words.is_empty()).then(|| words.join(" "))
+    }
+
+    /// Whether the filename contains a skip term as whole words.
+    pub fn skips(&self, filename: &str) -> bool {
+        let haystack = format!(" {} ", words_of(filename).join(" "));
+        self.skip_terms.iter().any(|term| {
+            let term = words_of(term);
+            !term.is_empty() && haystack.contains(&format!(" {} ", term.join(" ")))
+        })
+    }
+}
+
+/// Select the profile for a parsed file and resolve its rules.
+///
+/// `profiles.force` (set per source by `watch`) wins over detection.
+pub fn rules_for<'a>(config: &'a AppConfig, parsed: &ParsedMedia) -> Rules<'a> {
+    let profiles = &config.profiles;
+    let profile = profiles.force.unwrap_or(
+        if profiles.detect_anime && looks_like_anime(&parsed.raw_filename) {
+            Profile::Anime
+        } else {
+            Profile::Default
+        },
+    );
+    let rules: &ProfileRules = match profile {
+        Profile::Anime => &profiles.anime,
+        Profile::Default => &profiles.default,
+    };
+    Rules {
+        profile,
+        review_threshold: rules.review_threshold.unwrap_or(config.review_threshold),
+        min_match_score: rules.min_match_score.unwrap_or(config.tmdb.min_match_score),
+        strip_terms: &rules.strip_terms,
+        skip_terms: &rules.skip_terms,
+        preserve_artwork: rules
+            .preserve_artwork
+            .unwrap_or(config.organize.preserve_artwork),
+    }
+}
+
+/// Lowercased alphanumeric words.
+fn words_of(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+fn normalize(word: &str) -> String {
+    word.trim_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_looks_like_anime() {
+        assert!(looks_like_anime(
+            "[SubsPlease] Sousou no Frieren - 05 (1080p) [A1B2C3D4].mkv"
+        ));
+        assert!(looks_like_anime(
+            "[Erai-raws] One Piece - 1042v2 [1080p].mkv"
+        ));
+        assert!(looks_like_anime(
+            "[Judas] Kimi no Na wa (BD 1080p) [5E3C9A1F].mkv"
+        ));
+        assert!(!looks_like_anime(
+            "The.Matrix.1999.1080p.BluRay.x264-GROUP.mkv"
+        ));
+        assert!(!looks_like_anime("[HD] Heat (1995).mkv"));
+    }
+
+    #[test]
+    fn test_anime_rules_override_defaults() {
+        let mut config = AppConfig::default();
+        config.profiles.detect_anime = true;
+        config.profiles.anime = ProfileRules {
+            review_threshold: Some(30.0),
+            strip_terms: vec!["Dual Audio".to_string()],
+            skip_terms: vec!["NCOP".to_string()],
+            ..Default::default()
+        };
+        let parsed = |raw: &str| ParsedMedia {
+            raw_filename: raw.to_string(),
+            ..Default::default()
+        };
+
+        let rules = rules_for(
+            &config,
+            &parsed("[Judas] Akira (1988) [Dual Audio] [ABCDEF12].mkv"),
+        );
+        assert_eq!(rules.profile, Profile::Anime);
+        assert_eq!(rules.review_threshold, 30.0);
+        assert_eq!(rules.min_match_score, config.tmdb.min_match_score);
+        assert_eq!(
+            rules.strip_title("Akira Dual Audio").as_deref(),
+            Some("Akira")
+        );
+        assert_eq!(rules.strip_title("Akira"), None);
+        assert!(rules.skips("[Group] Show - NCOP 01 [ABCDEF12].mkv"));
+        assert!(!rules.skips("[Group] Show - 01 [ABCDEF12].mkv"));
+
+        let rules = rules_for(&config, &parsed("Akira.1988.1080p.BluRay.mkv"));
+        assert_eq!(rules.profile, Profile::Default);
+        assert_eq!(rules.review_threshold, config.review_threshold);
+        assert!(!rules.skips("Akira.NCOP.mkv"));
+
+        // An anime inbox forces the profile whatever the name looks like.
+        config.profiles.force = Some(Profile::Anime);
+        let rules = rules_for(&config, &parsed("Akira.1988.1080p.BluRay.mkv"));
+        assert_eq!(rules.profile, Profile::Anime);
+    }
+}
@@ -0,0 +1,118 @@
+//! Progress events and cancellation for library consumers.
+//!
+//! The CLI draws its own bars; a program driving [`Organizer`] (a GUI, a
+//! TUI, a web service) instead hands it the sending end of a channel and
+//! receives a [`ProgressEvent`] as each stage moves along. A
+//! [`CancelToken`] stops a run cooperatively: identification stops before
+//! the next file with [`Error::Cancelled`], and an executing run starts no
+//! new file operation while those in flight finish, so the undo manifest
+//! and journal describe exactly what was done (as with `--max-runtime`).
+//!
+//! [`Organizer`]: crate::Organizer
+//! [`Error::Cancelled`]: crate::Error::Cancelled
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+
+/// A step of a scan, plan, or run. Counts start at 1.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProgressEvent {
+    /// Walking `root` for media files.
+    Scanning { root: PathBuf },
+    /// Parsing the file name of file `done` of `total`.
+    Parsing {
+        done: usize,
+        total: usize,
+        path: PathBuf,
+    },
+    /// Looking up file `done` of `total` with the metadata providers.
+    Matching {
+        done: usize,
+        total: usize,
+        path: PathBuf,
+    },
+    /// Looking up again the `total` files whose lookups hit a server error
+    /// or timeout.
+    Retrying { total: usize },
+    /// Starting file operation `done` of `total`.
+    Moving {
+        done: usize,
+        total: usize,
+        source: PathBuf,
+        destination: PathBuf,
+    },
+    /// A run finished without error: `placed` files were organized and
+    /// `unstarted` actions were never begun, because the run was cancelled
+    /// or out of time.
+    Done {
+        placed: usize,
+        unstarted: usize,
+        cancelled: bool,
+    },
+}
+
+/// Send `event` if anyone is listening. A receiver that hung up is not an
+/// error; the work goes on without it.
+pub fn emit(events: Option<&Sender<ProgressEvent>>, event: ProgressEvent) {
+    if let Some(events) = events {
+        let _ = events.send(event);
+    }
+}
+
+/// Asks a running operation to stop at its next safe point. Clones share
+/// one flag, so a token kept by the caller cancels the copy it handed out.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the operation to stop. Work in flight finishes first.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Whether `token` is present and cancelled.
+pub fn cancelled(token: Option<&CancelToken>) -> bool {
+    token.is_some_and(CancelToken::is_cancelled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_clones_share_cancellation_and_hangups_are_ignored() {
+        let token = CancelToken::new();
+        let handed_out = token.clone();
+        assert!(!cancelled(Some(&handed_out)));
+        token.cancel();
+        assert!(cancelled(Some(&handed_out)));
+        assert!(!cancelled(None));
+
+        let (events, received) = mpsc::channel();
+        emit(
+            Some(&events),
+            ProgressEvent::Scanning { root: "/in".into() },
+        );
+        assert_eq!(
+            received.recv().unwrap(),
+            ProgressEvent::Scanning { root: "/in".into() }
+        );
+        drop(received);
+        emit(
+            Some(&events),
+            ProgressEvent::Scanning { root: "/in".into() },
+        );
+    }
+}
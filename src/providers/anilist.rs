@@ -0,0 +1,261 @@
+//! AniList client for anime.
+//!
+//! Fansub names carry romaji titles (`Kimi no Na wa`, `Shingeki no
+//! Kyojin`) and rarely a year, which a TMDb search scores poorly. AniList's
+//! GraphQL `Media` search knows each entry's romaji, English, and native
+//! titles and its synonyms, plus its format (movie, TV series, OVA) and
+//! start year, so a fansub name is identified there first and the English
+//! title and year it gives are what TMDb is searched for.
+
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::json;
+
+use super::cassette;
+use crate::http::{self, RateLimiter};
+use crate::models::MediaType;
+use crate::utils::title_similarity;
+
+const API_URL: &str = "https://graphql.anilist.co";
+
+/// Entries asked for per search.
+const SEARCH_QUERY: &str = "query ($search: String) { \
+    Page(perPage: 10) { media(search: $search, type: ANIME) { \
+    id idMal format episodes startDate { year } \
+    title { romaji english native } synonyms } } }";
+
+/// Blocking AniList client. Clones share one rate limiter.
+#[derive(Debug, Clone)]
+pub struct AniListClient {
+    limiter: Arc<RateLimiter>,
+}
+
+/// An anime entry returned by a search.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AnimeCandidate {
+    pub id: u64,
+    /// MyAnimeList id.
+    pub id_mal: Option<u64>,
+    pub romaji: Option<String>,
+    pub english: Option<String>,
+    pub native: Option<String>,
+    pub synonyms: Vec<String>,
+    /// `MOVIE`, `TV`, `TV_SHORT`, `OVA`, `ONA`, `SPECIAL`, or `MUSIC`.
+    pub format: Option<String>,
+    /// Year the first episode aired or the movie was released.
+    pub year: Option<i32>,
+    pub episodes: Option<u32>,
+}
+
+impl AnimeCandidate {
+    /// The title to name the entry by: English, else romaji.
+    pub fn title(&self) -> Option<&str> {
+        self.english.as_deref().or(self.romaji.as_deref())
+    }
+
+    /// Every title the entry goes by.
+    pub fn titles(&self) -> impl Iterator<Item = &String> {
+        [&self.english, &self.romaji, &self.native]
+            .into_iter()
+            .flatten()
+            .chain(&self.synonyms)
+    }
+
+    /// Movie or series, as far as the format tells.
+    pub fn media_type(&self) -> Option<MediaType> {
+        match self.format.as_deref()? {
+            "MOVIE" => Some(MediaType::Movie),
+            "TV" | "TV_SHORT" | "ONA" => Some(MediaType::Tv),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Response {
+    data: Option<Data>,
+}
+
+#[derive(Deserialize)]
+struct Data {
+    #[serde(rename = "Page")]
+    page: Page,
+}
+
+#[derive(Deserialize)]
+struct Page {
+    #[serde(default)]
+    media: Vec<Media>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Media {
+    id: u64,
+    #[serde(default)]
+    id_mal: Option<u64>,
+    #[serde(default)]
+    format: Option<String>,
+    #[serde(default)]
+    episodes: Option<u32>,
+    #[serde(default)]
+    start_date: Option<FuzzyDate>,
+    #[serde(default)]
+    title: Option<MediaTitle>,
+    #[serde(default)]
+    synonyms: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct FuzzyDate {
+    year: Option<i32>,
+}
+
+#[derive(Deserialize)]
+struct MediaTitle {
+    romaji: Option<String>,
+    english: Option<String>,
+    native: Option<String>,
+}
+
+impl From<Media> for AnimeCandidate {
+    fn from(m: Media) -> Self {
+        let title = m.title.unwrap_or(MediaTitle {
+            romaji: None,
+            english: None,
+            native: None,
+        });
+        AnimeCandidate {
+            id: m.id,
+            id_mal: m.id_mal,
+            romaji: title.romaji,
+            english: title.english,
+            native: title.native,
+            synonyms: m.synonyms,
+            format: m.format,
+            year: m.start_date.and_then(|d| d.year),
+            episodes: m.episodes,
+        }
+    }
+}
+
+impl AniListClient {
+    /// Create a client sending at most `requests_per_minute` requests
+    /// (0 = unlimited).
+    pub fn new(requests_per_minute: u32) -> Self {
+        Self {
+            limiter: Arc::new(RateLimiter::per_minute(requests_per_minute)),
+        }
+    }
+
+    /// Search anime entries by title, best known first.
+    pub fn search(&self, query: &str) -> Result<Vec<AnimeCandidate>> {
+        let body = json!({ "query": SEARCH_QUERY, "variables": { "search": query } });
+        let request = format!("anilist/search?query={}", http::encode_component(query));
+        let response = cassette::fetch(&request, || {
+            self.limiter.wait();
+            http::post_json(
+                API_URL,
+                &body.to_string(),
+                &[("Accept", "application/json")],
+            )
+        })
+        .with_context(|| format!("AniList search for {query:?} failed"))?;
+        parse_search(&response)
+    }
+}
+
+fn parse_search(body: &str) -> Result<Vec<AnimeCandidate>> {
+    let response: Response =
+        serde_json::from_str(body).context("Unexpected AniList search response")?;
+    Ok(response
+        .data
+        .map(|d| d.page.media.into_iter().map(Into::into).collect())
+        .unwrap_or_default())
+}
+
+/// Score (0–100) how well `candidate` matches a file named `names` (its
+/// title and any alternative one).
+///
+/// The closest of the entry's titles supplies up to 80 points. The year
+/// counts as for TMDb (see [`super::score_candidate`]), and a format
+/// agreeing with the parsed type adds 10 where a disagreeing one costs 20,
+/// so a yearless fansub movie named exactly still scores 90.
+pub fn score_candidate(
+    names: &[&str],
+    year: Option<i32>,
+    media_type: MediaType,
+    candidate: &AnimeCandidate,
+) -> f64 {
+    let similarity = names
+        .iter()
+        .flat_map(|name| candidate.titles().map(|t| title_similarity(name, t)))
+        .fold(0.0, f64::max);
+    let year_score = match (year, candidate.year) {
+        (Some(a), Some(b)) if a == b => 20.0,
+        (Some(a), Some(b)) if (a - b).abs() == 1 => 10.0,
+        (Some(_), Some(_)) => -30.0,
+        _ => 0.0,
+    };
+    let format_score = match candidate.media_type() {
+        Some(kind) if kind == media_type => 10.0,
+        Some(_) if media_type != MediaType::Unknown => -20.0,
+        _ => 0.0,
+    };
+    (similarity * 80.0 + year_score + format_score).clamp(0.0, 100.0)
+}
+
+/// The highest-scoring candidate at or above `min_score`.
+pub fn best_candidate(
+    names: &[&str],
+    year: Option<i32>,
+    media_type: MediaType,
+    candidates: &[AnimeCandidate],
+    min_score: f64,
+) -> Option<(AnimeCandidate, f64)> {
+    candidates
+        .iter()
+        .map(|c| (c, score_candidate(names, year, media_type, c)))
+        .filter(|(_, score)| *score >= min_score)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(c, score)| (c.clone(), score))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_response_scored_by_any_title() {
+        let body = r#"{"data":{"Page":{"media":[
+            {"id":21519,"idMal":32281,"format":"MOVIE","episodes":1,
+             "startDate":{"year":2016},
+             "title":{"romaji":"Kimi no Na wa.","english":"Your Name.","native":"君の名は。"},
+             "synonyms":["Kimi no Na wa"]},
+            {"id":99,"format":"TV","startDate":{"year":2022},
+             "title":{"romaji":"Kimi no Na wa Special"},"synonyms":[]}
+        ]}}}"#;
+        let candidates = parse_search(body).unwrap();
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[0].title(), Some("Your Name."));
+
+        let (best, score) = best_candidate(
+            &["Kimi no Na wa", "Your Name"],
+            None,
+            MediaType::Movie,
+            &candidates,
+            75.0,
+        )
+        .unwrap();
+        assert_eq!((best.id, best.year), (21519, Some(2016)));
+        assert_eq!(score, 90.0);
+
+        // The series has the wrong format and is not close enough.
+        assert!(score_candidate(&["Kimi no Na wa"], None, MediaType::Movie, &candidates[1]) < 75.0);
+        assert!(parse_search(r#"{"data":null,"errors":[]}"#)
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,222 @@
+//! Recording and playback of TMDb responses (`--tmdb-record`,
+//! `--tmdb-replay`).
+//!
+//! A recorded run writes every TMDb request it made, with the response body
+//! or error, to a cassette file. Replaying that cassette answers the same
+//! requests from the file, so a matching bug seen on a user's machine can be
+//! reproduced exactly, offline and without their API key. Requests are keyed
+//! by path and query without the key, so a cassette never contains it.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::http::{self, HttpError};
+use crate::schema::{self, Artifact};
+
+/// Current cassette format version.
+pub const VERSION: u32 = 1;
+
+/// One request and what came back.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    /// `path?query`, e.g. `search/movie?include_adult=false&query=Heat`.
+    pub request: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    /// The error message, for a request that failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The HTTP status of a failed response, or 0 (as curl reports it) for
+    /// a request that got none, so replay tells transient failures apart
+    /// as the recorded run did.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CassetteFile {
+    version: u32,
+    created_at: String,
+    interactions: Vec<Interaction>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Record,
+    Replay,
+}
+
+/// A cassette being recorded or replayed.
+#[derive(Debug)]
+pub struct Cassette {
+    mode: Mode,
+    path: PathBuf,
+    interactions: Mutex<Vec<Interaction>>,
+    /// Replay position per request, so repeated requests are answered in
+    /// the order they were recorded.
+    cursors: Mutex<HashMap<String, usize>>,
+}
+
+impl Cassette {
+    /// Start recording into `path`; nothing is written until [`save`].
+    ///
+    /// [`save`]: Cassette::save
+    pub fn record(path: &Path) -> Self {
+        Self::new(Mode::Record, path, Vec::new())
+    }
+
+    /// Load a recorded cassette for playback.
+    pub fn replay(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read cassette: {}", path.display()))?;
+        let file: CassetteFile = schema::from_str(Artifact::Repro, &content)
+            .with_context(|| format!("Invalid cassette: {}", path.display()))?;
+        Ok(Self::new(Mode::Replay, path, file.interactions))
+    }
+
+    fn new(mode: Mode, path: &Path, interactions: Vec<Interaction>) -> Self {
+        Self {
+            mode,
+            path: path.to_path_buf(),
+            interactions: Mutex::new(interactions),
+            cursors: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Answer `request`: from the cassette when replaying, otherwise by
+    /// calling `live` and recording what it returns.
+    pub fn fetch(&self, request: &str, live: impl FnOnce() -> Result<String>) -> Result<String> {
+        if self.mode == Mode::Replay {
+            return self.play(request);
+        }
+        let result = live();
+        let failure = result.as_ref().err();
+        let interaction = Interaction {
+            request: request.to_string(),
+            body: result.as_ref().ok().cloned(),
+            error: failure.map(|e| format!("{e:#}")),
+            status: failure
+                .and_then(|e| http::find(e.as_ref()))
+                .map(|e| e.status.unwrap_or(0)),
+        };
+        lock(&self.interactions).push(interaction);
+        result
+    }
+
+    fn play(&self, request: &str) -> Result<String> {
+        let interactions = lock(&self.interactions);
+        let matching: Vec<_> = interactions
+            .iter()
+            .filter(|i| i.request == request)
+            .collect();
+        let mut cursors = lock(&self.cursors);
+        let cursor = cursors.entry(request.to_string()).or_default();
+        // Past the recorded repeats, the last answer stands.
+        let Some(interaction) = matching.get(*cursor).or(matching.last()) else {
+            bail!("{request} is not in the cassette; this run has diverged from the recording");
+        };
+        *cursor += 1;
+        debug!("Replaying {request}");
+        match (&interaction.body, &interaction.error) {
+            (_, Some(error)) => match interaction.status {
+                Some(status) => {
+                    let status = Some(status).filter(|&s| s != 0);
+                    Err(HttpError::replayed(status, error.clone()).into())
+                }
+                None => Err(anyhow!("{error}")),
+            },
+            (Some(body), None) => Ok(body.clone()),
+            (None, None) => bail!("Cassette entry for {request} has no body"),
+        }
+    }
+
+    /// Write a recording to its file. Returns how many requests it holds.
+    pub fn save(&self) -> Result<usize> {
+        let interactions = lock(&self.interactions).clone();
+        let count = interactions.len();
+        let file = CassetteFile {
+            version: VERSION,
+            created_at: chrono::Utc::now().to_rfc3339(),
+            interactions,
+        };
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&file)?)
+            .with_context(|| format!("Failed to write cassette: {}", self.path.display()))?;
+        Ok(count)
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+static CASSETTE: OnceLock<Cassette> = OnceLock::new();
+
+/// Record or replay TMDb traffic for the rest of the process. Only the
+/// first call takes effect.
+pub fn install(cassette: Cassette) {
+    let _ = CASSETTE.set(cassette);
+}
+
+/// Answer `request` through the installed cassette, or by calling `live`
+/// when there is none.
+pub(crate) fn fetch(request: &str, live: impl FnOnce() -> Result<String>) -> Result<String> {
+    match CASSETTE.get() {
+        Some(cassette) => cassette.fetch(request, live),
+        None => live(),
+    }
+}
+
+/// Write the recording, if one is running. Returns its path and how many
+/// requests it holds.
+pub fn finish() -> Result<Option<(PathBuf, usize)>> {
+    match CASSETTE.get() {
+        Some(cassette) if cassette.mode == Mode::Record => {
+            Ok(Some((cassette.path.clone(), cassette.save()?)))
+        }
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_then_replay() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("run.json");
+        let recorder = Cassette::record(&path);
+        let answers = ["first", "second"];
+        for answer in answers {
+            let got = recorder.fetch("search/movie?query=Heat", || Ok(answer.to_string()));
+            assert_eq!(got.unwrap(), answer);
+        }
+        let failed = recorder.fetch("movie/1", || {
+            Err(HttpError::replayed(Some(503), "HTTP 503: try later".into()).into())
+        });
+        assert!(failed.is_err());
+        assert_eq!(recorder.save().unwrap(), 3);
+
+        let player = Cassette::replay(&path).unwrap();
+        let live = || -> Result<String> { panic!("replay must not hit the network") };
+        for answer in ["first", "second", "second"] {
+            assert_eq!(
+                player.fetch("search/movie?query=Heat", live).unwrap(),
+                answer
+            );
+        }
+        let error = player.fetch("movie/1", live).unwrap_err();
+        assert_eq!(error.to_string(), "HTTP 503: try later");
+        assert!(http::is_transient(error.as_ref()));
+        assert!(player.fetch("movie/2", live).is_err());
+    }
+}
@@ -0,0 +1,256 @@
+//! Offline movie matching from IMDb's `title.basics` dataset.
+//!
+//! IMDb publishes its catalogue as `title.basics.tsv.gz`
+//! (datasets.imdbws.com). `offline-db import` keeps the movies of it —
+//! IMDb id, year, primary and original title — in a trimmed TSV beside the
+//! library database, and this provider matches titles against that file
+//! with no network or key, as the last of `providers.order` by default.
+//! Only exact titles (ignoring case and punctuation) are candidates; the
+//! year is scored as for the online providers. The gzip file is read
+//! through the system `gzip`, as HTTP goes through `curl` (see D007).
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, bail, Context, Result};
+use tracing::info;
+
+use super::{MetadataProvider, MovieCandidate, SearchPage};
+use crate::error::Error;
+use crate::utils::normalize_title;
+
+/// Title types kept from the dataset.
+const MOVIE_TYPES: [&str; 2] = ["movie", "tvMovie"];
+
+/// A movie of the dataset.
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    imdb_id: String,
+    year: Option<i32>,
+    title: String,
+    original_title: Option<String>,
+}
+
+impl From<&Entry> for MovieCandidate {
+    fn from(e: &Entry) -> Self {
+        MovieCandidate {
+            id: None,
+            title: e.title.clone(),
+            original_title: e.original_title.clone(),
+            year: e.year,
+            release_date: None,
+            imdb_id: Some(e.imdb_id.clone()),
+        }
+    }
+}
+
+/// Movies by normalized title.
+#[derive(Debug, Default)]
+struct Index {
+    entries: Vec<Entry>,
+    by_title: HashMap<String, Vec<usize>>,
+}
+
+impl Index {
+    fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open offline database {}", path.display()))?;
+        let mut index = Index::default();
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            let mut fields = line.split('\t');
+            let (Some(imdb_id), Some(year), Some(title)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            index.push(Entry {
+                imdb_id: imdb_id.to_string(),
+                year: year.parse().ok(),
+                title: title.to_string(),
+                original_title: fields.next().filter(|o| !o.is_empty()).map(String::from),
+            });
+        }
+        Ok(index)
+    }
+
+    fn push(&mut self, entry: Entry) {
+        let at = self.entries.len();
+        let mut keys = vec![normalize_title(&entry.title)];
+        keys.extend(entry.original_title.as_deref().map(normalize_title));
+        keys.dedup();
+        for key in keys.into_iter().filter(|k| !k.is_empty()) {
+            self.by_title.entry(key).or_default().push(at);
+        }
+        self.entries.push(entry);
+    }
+
+    fn search(&self, query: &str) -> Vec<MovieCandidate> {
+        self.by_title
+            .get(&normalize_title(query))
+            .into_iter()
+            .flatten()
+            .map(|&i| MovieCandidate::from(&self.entries[i]))
+            .collect()
+    }
+}
+
+/// The imported dataset, loaded on its first search.
+#[derive(Debug)]
+pub struct OfflineDatabase {
+    path: PathBuf,
+    index: OnceLock<Result<Index, String>>,
+}
+
+impl OfflineDatabase {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            index: OnceLock::new(),
+        }
+    }
+
+    fn index(&self) -> Result<&Index> {
+        self.index
+            .get_or_init(|| {
+                let index = Index::load(&self.path).map_err(|e| format!("{e:#}"))?;
+                info!(
+                    "loaded {} movies from the offline database",
+                    index.entries.len()
+                );
+                Ok(index)
+            })
+            .as_ref()
+            .map_err(|e| anyhow!("{e}"))
+    }
+
+    /// Movies whose title or original title is `query`.
+    pub fn search(&self, query: &str) -> Result<Vec<MovieCandidate>> {
+        Ok(self.index()?.search(query))
+    }
+}
+
+impl MetadataProvider for OfflineDatabase {
+    fn name(&self) -> &'static str {
+        "imdb"
+    }
+
+    fn search_movie_page(
+        &self,
+        query: &str,
+        _year: Option<i32>,
+        _page: u32,
+    ) -> crate::Result<SearchPage> {
+        let candidates = self
+            .search(query)
+            .map_err(|e| Error::provider(self.name(), e))?;
+        Ok(SearchPage {
+            total_pages: u32::from(!candidates.is_empty()),
+            candidates,
+        })
+    }
+}
+
+/// Import the movies of an IMDb `title.basics` file (`.tsv`, or `.tsv.gz`
+/// read through `gzip`) into the offline database at `dest`, replacing it.
+/// Returns how many movies were kept.
+pub fn import(source: &Path, dest: &Path) -> Result<usize> {
+    let gzipped = source.extension().is_some_and(|e| e == "gz");
+    let mut child = None;
+    let reader: Box<dyn Read> = if gzipped {
+        let mut gzip = Command::new("gzip")
+            .arg("-dc")
+            .arg(source)
+            .stdout(Stdio::piped())
+            .spawn()
+            .context("Failed to run gzip; is it installed and on PATH?")?;
+        let stdout = gzip.stdout.take().context("gzip gave no output")?;
+        child = Some(gzip);
+        Box::new(stdout)
+    } else {
+        Box::new(
+            File::open(source).with_context(|| format!("Failed to open {}", source.display()))?,
+        )
+    };
+
+    if let Some(parent) = dest.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp = dest.with_extension("tsv.tmp");
+    let mut out = BufWriter::new(
+        File::create(&tmp).with_context(|| format!("Failed to write {}", tmp.display()))?,
+    );
+    let kept = copy_movies(BufReader::new(reader), &mut out)?;
+    out.flush()?;
+    drop(out);
+    if let Some(mut gzip) = child {
+        if !gzip.wait()?.success() {
+            bail!("gzip could not read {}", source.display());
+        }
+    }
+    fs::rename(&tmp, dest).with_context(|| format!("Failed to replace {}", dest.display()))?;
+    Ok(kept)
+}
+
+/// Copy the non-adult movies of a `title.basics` table as
+/// `id, year, title, original title` rows. Returns how many were copied.
+fn copy_movies(input: impl BufRead, out: &mut impl Write) -> Result<usize> {
+    let mut lines = input.lines();
+    let header = lines.next().transpose()?.unwrap_or_default();
+    if !header.starts_with("tconst\ttitleType\tprimaryTitle") {
+        bail!("Not an IMDb title.basics file (header {header:?})");
+    }
+    let mut kept = 0;
+    for line in lines {
+        let line = line?;
+        let fields: Vec<&str> = line.split('\t').collect();
+        let [id, kind, title, original, adult, year, ..] = fields[..] else {
+            continue;
+        };
+        if !MOVIE_TYPES.contains(&kind) || adult == "1" {
+            continue;
+        }
+        let year = if year == "\\N" { "" } else { year };
+        let original = if original == title { "" } else { original };
+        writeln!(out, "{id}\t{year}\t{title}\t{original}")?;
+        kept += 1;
+    }
+    Ok(kept)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASICS: &str = "tconst\ttitleType\tprimaryTitle\toriginalTitle\tisAdult\tstartYear\tendYear\truntimeMinutes\tgenres
+tt0113277\tmovie\tHeat\tHeat\t0\t1995\t\\N\t170\tAction,Crime,Drama
+tt0093164\tmovie\tHeat\tHeat\t0\t1986\t\\N\t101\tAction,Crime
+tt0903747\ttvSeries\tBreaking Bad\tBreaking Bad\t0\t2008\t2013\t49\tCrime
+tt6751668\tmovie\tParasite\tGisaengchung\t0\t2019\t\\N\t132\tDrama
+";
+
+    #[test]
+    fn test_import_and_search() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = tmp.path().join("title.basics.tsv");
+        fs::write(&source, BASICS).unwrap();
+        let dest = tmp.path().join("imdb_movies.tsv");
+        assert_eq!(import(&source, &dest).unwrap(), 3);
+
+        let db = OfflineDatabase::new(&dest);
+        let heat = db.search("heat").unwrap();
+        assert_eq!(heat.len(), 2);
+        assert_eq!(heat[0].imdb_id.as_deref(), Some("tt0113277"));
+        assert_eq!(heat[0].year, Some(1995));
+        let parasite = db.search("Gisaengchung").unwrap();
+        assert_eq!(parasite[0].title, "Parasite");
+        assert!(db.search("Breaking Bad").unwrap().is_empty());
+
+        fs::write(&source, "id\tname\n").unwrap();
+        assert!(import(&source, &dest).is_err());
+    }
+}
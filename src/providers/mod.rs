@@ -0,0 +1,265 @@
+//! Metadata providers — online databases that confirm parsed titles.
+//!
+//! Providers are synchronous (see D002) and optional: without credentials
+//! the enricher falls back to parser-only results. Movie searches go
+//! through [`MetadataProvider`], tried in `providers.order` until one
+//! matches, so a user without a TMDb key (or whose region TMDb serves
+//! badly) can match against OMDb, or offline against IMDb's dataset
+//! (`imdb`), instead. Details, collections, and IMDb id lookups stay with
+//! TMDb.
+
+pub mod anilist;
+pub mod cassette;
+pub mod imdb;
+pub mod omdb;
+pub mod plex;
+pub mod tmdb;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::utils::title_similarity;
+
+/// A movie search by title and year, one page at a time.
+pub trait MetadataProvider: Send + Sync {
+    /// Name recorded as the enrichment source of a match, e.g. `tmdb`.
+    fn name(&self) -> &'static str;
+
+    /// One page (1-based) of movies matching `query`.
+    fn search_movie_page(
+        &self,
+        query: &str,
+        year: Option<i32>,
+        page: u32,
+    ) -> crate::Result<SearchPage>;
+}
+
+/// One page of movie search results.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchPage {
+    pub candidates: Vec<MovieCandidate>,
+    /// Pages available for the query; 0 when there are no results.
+    pub total_pages: u32,
+}
+
+/// A movie returned by a provider search.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MovieCandidate {
+    /// TMDb id; `None` for a movie from a provider without TMDb ids
+    /// (OMDb, the IMDb dataset).
+    #[serde(default, deserialize_with = "tmdb_id")]
+    pub id: Option<u64>,
+    pub title: String,
+    #[serde(default)]
+    pub original_title: Option<String>,
+    #[serde(default)]
+    pub year: Option<i32>,
+    /// Primary release date as reported by the provider (`YYYY-MM-DD`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release_date: Option<String>,
+    /// IMDb id including its `tt` prefix, when the provider gives it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub imdb_id: Option<String>,
+}
+
+/// A candidate's TMDb id, reading the 0 that review queues saved before
+/// ids were optional as none.
+fn tmdb_id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    Ok(Option::<u64>::deserialize(deserializer)?.filter(|&id| id != 0))
+}
+
+impl MovieCandidate {
+    /// The parsed primary release date, if known.
+    pub fn released_on(&self) -> Option<NaiveDate> {
+        NaiveDate::parse_from_str(self.release_date.as_deref()?, "%Y-%m-%d").ok()
+    }
+}
+
+/// Library-level details for a confirmed movie.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MovieDetails {
+    /// TMDb title.
+    pub title: Option<String>,
+    pub genres: Vec<String>,
+    /// Runtime in minutes.
+    pub runtime: Option<u32>,
+    /// Year of the primary release.
+    pub year: Option<i32>,
+    /// Collection (franchise) name, e.g. "The Dark Knight Collection".
+    pub collection: Option<String>,
+    /// Release certification for the configured region (e.g., "PG-13").
+    pub certification: Option<String>,
+    pub alternative_titles: Vec<String>,
+    pub original_title: Option<String>,
+    /// Overview.
+    pub plot: Option<String>,
+    /// IMDb id including its `tt` prefix.
+    pub imdb_id: Option<String>,
+    /// Top-billed cast, in billing order.
+    pub cast: Vec<CastMember>,
+    /// RFC 3339 timestamp of the fetch.
+    pub fetched_at: String,
+}
+
+/// An actor and the part they play.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CastMember {
+    pub name: String,
+    pub role: String,
+}
+
+/// Points lost by a candidate released after the file could have existed.
+const UNRELEASED_PENALTY: f64 = 50.0;
+
+/// Score (0–100) how well `candidate` matches a parsed title and year.
+///
+/// Title similarity supplies up to 80 points; the year adds 20 when exact,
+/// 10 when off by one (festival vs. theatrical release), and costs 30 when
+/// further apart. A candidate released after `released_by` (today, or the
+/// file's modification date) — typically a remake still in production —
+/// loses 50 more, unless the filename's year agrees with it exactly.
+pub fn score_candidate(
+    title: &str,
+    year: Option<i32>,
+    released_by: Option<NaiveDate>,
+    candidate: &MovieCandidate,
+) -> f64 {
+    let similarity = candidate
+        .original_title
+        .iter()
+        .map(|t| title_similarity(title, t))
+        .fold(title_similarity(title, &candidate.title), f64::max);
+    let year_score = match (year, candidate.year) {
+        (Some(a), Some(b)) if a == b => 20.0,
+        (Some(a), Some(b)) if (a - b).abs() == 1 => 10.0,
+        (Some(_), Some(_)) => -30.0,
+        _ => 0.0,
+    };
+    let unreleased = match (released_by, candidate.released_on()) {
+        (Some(cutoff), Some(released)) => released > cutoff,
+        _ => false,
+    };
+    let window_score = if unreleased && (year.is_none() || year != candidate.year) {
+        -UNRELEASED_PENALTY
+    } else {
+        0.0
+    };
+    (similarity * 80.0 + year_score + window_score).clamp(0.0, 100.0)
+}
+
+/// The highest-scoring candidate at or above `min_score`.
+pub fn best_candidate(
+    title: &str,
+    year: Option<i32>,
+    released_by: Option<NaiveDate>,
+    candidates: &[MovieCandidate],
+    min_score: f64,
+) -> Option<(MovieCandidate, f64)> {
+    candidates
+        .iter()
+        .map(|c| (c, score_candidate(title, year, released_by, c)))
+        .filter(|(_, score)| *score >= min_score)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(c, score)| (c.clone(), score))
+}
+
+/// Titles longer than this many words also get a leading-words query.
+const LONG_TITLE_WORDS: usize = 5;
+
+/// Words kept by the leading-words query.
+const LEADING_WORDS: usize = 3;
+
+/// Shorter fallback queries for a long title, most specific first.
+///
+/// Searches often miss titles with long subtitles ("Movie: An Extremely
+/// Long Subtitle About Things"), so this yields the main title before a
+/// colon or dash, then the first few words of a long title. Results found
+/// this way must still be scored against the full title.
+pub fn partial_queries(title: &str) -> Vec<String> {
+    let mut queries = Vec::new();
+    let main = [":", " - ", " – ", " — "]
+        .iter()
+        .filter_map(|sep| title.find(sep))
+        .min()
+        .map(|i| title[..i].trim());
+    if let Some(main) = main.filter(|m| !m.is_empty()) {
+        queries.push(main.to_string());
+    }
+
+    let words: Vec<&str> = title
+        .split_whitespace()
+        .filter(|w| w.chars().any(char::is_alphanumeric))
+        .collect();
+    if words.len() > LONG_TITLE_WORDS {
+        let leading = words[..LEADING_WORDS].join(" ");
+        if !queries.contains(&leading) {
+            queries.push(leading);
+        }
+    }
+    queries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(title: &str, year: i32) -> MovieCandidate {
+        MovieCandidate {
+            id: Some(1),
+            title: title.to_string(),
+            original_title: None,
+            year: Some(year),
+            release_date: Some(format!("{year}-06-01")),
+            imdb_id: None,
+        }
+    }
+
+    #[test]
+    fn test_best_candidate_prefers_year_match() {
+        let candidates = [candidate("Dune", 1984), candidate("Dune", 2021)];
+        let (best, score) = best_candidate("Dune", Some(2021), None, &candidates, 75.0).unwrap();
+        assert_eq!(best.year, Some(2021));
+        assert_eq!(score, 100.0);
+        assert!(best_candidate("Heat", Some(1995), None, &candidates, 75.0).is_none());
+    }
+
+    #[test]
+    fn test_unreleased_candidates_penalized() {
+        let candidates = [candidate("Heat", 1995), candidate("Heat", 2027)];
+        let cutoff = NaiveDate::from_ymd_opt(2026, 10, 1);
+
+        // No year in the filename: the remake still in production loses.
+        let (best, _) = best_candidate("Heat", None, cutoff, &candidates, 75.0).unwrap();
+        assert_eq!(best.year, Some(1995));
+        assert_eq!(score_candidate("Heat", None, cutoff, &candidates[1]), 30.0);
+
+        // An explicit year agreeing with the candidate keeps it eligible.
+        let (best, score) = best_candidate("Heat", Some(2027), cutoff, &candidates, 75.0).unwrap();
+        assert_eq!((best.year, score), (Some(2027), 100.0));
+    }
+
+    #[test]
+    fn test_partial_queries_rerank_by_full_title() {
+        assert_eq!(
+            partial_queries("Borat - Cultural Learnings of America for Make Benefit"),
+            ["Borat", "Borat Cultural Learnings"]
+        );
+        assert_eq!(
+            partial_queries("Birdman or The Unexpected Virtue of Ignorance"),
+            ["Birdman or The"]
+        );
+        assert!(partial_queries("Heat").is_empty());
+
+        // A search for the main title returns the sequel first; scoring
+        // against the full title still picks the right film.
+        let full = "Mission Impossible - Dead Reckoning Part One";
+        let candidates = [
+            candidate("Mission: Impossible", 2023),
+            candidate("Mission: Impossible - Dead Reckoning Part One", 2023),
+        ];
+        let (best, _) = best_candidate(full, Some(2023), None, &candidates, 75.0).unwrap();
+        assert_eq!(best.title, candidates[1].title);
+    }
+}
@@ -0,0 +1,178 @@
+//! OMDb client for movie searches.
+//!
+//! OMDb (omdbapi.com) indexes IMDb titles and takes a free key, so it
+//! confirms titles for users without a TMDb key or whose region TMDb
+//! serves badly. A search returns IMDb ids and release years, ten titles a
+//! page; matches carry no TMDb id, so their details and collections stay
+//! unknown.
+
+use std::fmt;
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+
+use super::{cassette, MetadataProvider, MovieCandidate, SearchPage};
+use crate::config::OmdbSettings;
+use crate::database::cache;
+use crate::error::Error;
+use crate::http::{self, RateLimiter};
+
+const API_BASE: &str = "https://www.omdbapi.com/";
+
+/// Results per search page.
+const PAGE_SIZE: u32 = 10;
+
+/// Blocking OMDb client. Clones share one rate limiter.
+#[derive(Clone)]
+pub struct OmdbClient {
+    api_key: String,
+    limiter: Arc<RateLimiter>,
+}
+
+/// The API key stays out of debug logs.
+impl fmt::Debug for OmdbClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OmdbClient")
+            .field("limiter", &self.limiter)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Deserialize)]
+struct SearchResponse {
+    #[serde(rename = "Search", default)]
+    search: Vec<SearchResult>,
+    #[serde(rename = "totalResults", default)]
+    total_results: Option<String>,
+    #[serde(rename = "Response")]
+    response: String,
+    #[serde(rename = "Error", default)]
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SearchResult {
+    #[serde(rename = "Title")]
+    title: String,
+    /// `1995`, or `2019–2021` for a series.
+    #[serde(rename = "Year", default)]
+    year: String,
+    #[serde(rename = "imdbID")]
+    imdb_id: String,
+}
+
+impl From<SearchResult> for MovieCandidate {
+    fn from(r: SearchResult) -> Self {
+        MovieCandidate {
+            id: None,
+            year: r.year.get(..4).and_then(|y| y.parse().ok()),
+            title: r.title,
+            original_title: None,
+            release_date: None,
+            imdb_id: Some(r.imdb_id),
+        }
+    }
+}
+
+impl OmdbClient {
+    pub fn from_settings(settings: &OmdbSettings) -> Self {
+        Self {
+            api_key: settings.api_key.clone(),
+            limiter: Arc::new(RateLimiter::per_second(settings.requests_per_second)),
+        }
+    }
+
+    fn search(&self, query: &str, year: Option<i32>, page: u32) -> Result<SearchPage> {
+        let mut params = format!("s={}&type=movie", http::encode_component(query));
+        if let Some(y) = year {
+            params.push_str(&format!("&y={y}"));
+        }
+        if page > 1 {
+            params.push_str(&format!("&page={page}"));
+        }
+        // The key rides in the URL, which `http::get` hands curl on stdin.
+        let url = format!(
+            "{API_BASE}?{params}&apikey={}",
+            http::encode_component(&self.api_key)
+        );
+        // Keyed without the API key, as for TMDb.
+        let request = format!("omdb?{params}");
+        let body = cache::fetch(
+            &request,
+            |body| parse_search_page(body).is_ok_and(|p| p.candidates.is_empty()),
+            || {
+                cassette::fetch(&request, || {
+                    self.limiter.wait();
+                    http::get(&url, &[("Accept", "application/json")])
+                })
+            },
+        )
+        .with_context(|| format!("OMDb search for {query:?} failed"))?;
+        parse_search_page(&body)
+    }
+}
+
+impl MetadataProvider for OmdbClient {
+    fn name(&self) -> &'static str {
+        "omdb"
+    }
+
+    fn search_movie_page(
+        &self,
+        query: &str,
+        year: Option<i32>,
+        page: u32,
+    ) -> crate::Result<SearchPage> {
+        self.search(query, year, page)
+            .map_err(|e| Error::provider(self.name(), e))
+    }
+}
+
+/// Parse a search response. OMDb reports "not found" (and "too many
+/// results" for very short queries) as an error; those are empty pages.
+pub fn parse_search_page(body: &str) -> Result<SearchPage> {
+    let response: SearchResponse =
+        serde_json::from_str(body).context("Unexpected OMDb search response")?;
+    if response.response != "True" {
+        return match response.error.as_deref() {
+            Some("Movie not found!" | "Too many results.") | None => Ok(SearchPage {
+                candidates: Vec::new(),
+                total_pages: 0,
+            }),
+            Some(error) => bail!("OMDb: {error}"),
+        };
+    }
+    let total: u32 = response
+        .total_results
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(0);
+    Ok(SearchPage {
+        candidates: response.search.into_iter().map(Into::into).collect(),
+        total_pages: total.div_ceil(PAGE_SIZE),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_search_page() {
+        let body = r#"{"Search": [
+            {"Title": "Heat", "Year": "1995", "imdbID": "tt0113277", "Type": "movie"},
+            {"Title": "Heat", "Year": "1986", "imdbID": "tt0093164", "Type": "movie"}
+        ], "totalResults": "23", "Response": "True"}"#;
+        let page = parse_search_page(body).unwrap();
+        assert_eq!(page.total_pages, 3);
+        assert_eq!(page.candidates[0].year, Some(1995));
+        assert_eq!(page.candidates[0].imdb_id.as_deref(), Some("tt0113277"));
+        assert_eq!(page.candidates[0].id, None);
+
+        let missing = r#"{"Response": "False", "Error": "Movie not found!"}"#;
+        assert!(parse_search_page(missing).unwrap().candidates.is_empty());
+        let bad_key = r#"{"Response": "False", "Error": "Invalid API key!"}"#;
+        let error = parse_search_page(bad_key).unwrap_err();
+        assert_eq!(error.to_string(), "OMDb: Invalid API key!");
+    }
+}
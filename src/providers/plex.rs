@@ -0,0 +1,495 @@
+//! Plex Media Server client: library refresh and a check of what Plex made
+//! of the files just organized.
+//!
+//! Plex matches files itself, with its own agents. When it resolves a movie
+//! to a different film than TMDb did, the library shows the wrong poster and
+//! title, and nobody notices until they browse to it. After an organize run
+//! the folders it touched are rescanned and, with `plex.verify` on, Plex's
+//! title and year for each new movie are compared with ours.
+//!
+//! A partial scan names a folder inside a section (`refresh?path=`), so a
+//! run that adds one movie does not make Plex walk a 20 TB library. Each
+//! folder goes to the section whose location holds it, translated through
+//! `plex.path_map` when Plex mounts the library elsewhere; a folder outside
+//! every section falls back to a full refresh.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::http;
+use crate::models::UndoEntry;
+use crate::utils::normalize_title;
+
+/// Blocking client for one Plex Media Server.
+#[derive(Clone)]
+pub struct PlexClient {
+    url: String,
+    token: String,
+}
+
+/// The token stays out of debug logs.
+impl fmt::Debug for PlexClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PlexClient")
+            .field("url", &self.url)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A movie as Plex has it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlexMovie {
+    pub title: String,
+    pub year: Option<i32>,
+    pub tmdb_id: Option<u64>,
+    /// Media files of the item, as paths on the Plex server.
+    pub files: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Response<T> {
+    #[serde(rename = "MediaContainer")]
+    container: T,
+}
+
+#[derive(Deserialize)]
+struct Sections {
+    #[serde(default, rename = "Directory")]
+    directories: Vec<Section>,
+}
+
+#[derive(Deserialize)]
+struct Section {
+    key: String,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default, rename = "Location")]
+    locations: Vec<Location>,
+}
+
+#[derive(Deserialize)]
+struct Location {
+    path: String,
+}
+
+/// A library section and the folders it covers, as paths on the server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LibrarySection {
+    pub key: String,
+    pub kind: String,
+    pub locations: Vec<String>,
+}
+
+/// Scans to request after a run: folders per section, and the folders no
+/// section holds.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScanPlan {
+    /// `(section key, folder on the server)`.
+    pub partial: Vec<(String, String)>,
+    pub unmatched: Vec<String>,
+}
+
+/// What [`PlexClient::refresh_paths`] asked Plex to do.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScanReport {
+    /// Folders scanned on their own.
+    pub folders: usize,
+    /// Whether every section was refreshed, for folders outside them all.
+    pub full: bool,
+}
+
+#[derive(Deserialize)]
+struct Items {
+    #[serde(default, rename = "Metadata")]
+    metadata: Vec<Item>,
+}
+
+#[derive(Deserialize)]
+struct Item {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    year: Option<i32>,
+    #[serde(default, rename = "Guid")]
+    guids: Vec<Guid>,
+    #[serde(default, rename = "Media")]
+    media: Vec<Media>,
+}
+
+#[derive(Deserialize)]
+struct Guid {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct Media {
+    #[serde(default, rename = "Part")]
+    parts: Vec<Part>,
+}
+
+#[derive(Deserialize)]
+struct Part {
+    file: String,
+}
+
+impl PlexClient {
+    /// A client for the server at `url` (e.g. `http://localhost:32400`).
+    pub fn new(url: &str, token: &str) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            token: token.to_string(),
+        }
+    }
+
+    /// Ask Plex to rescan every library section. Scanning continues in the
+    /// background after this returns.
+    pub fn refresh(&self) -> Result<()> {
+        for key in self.section_keys(None)? {
+            self.get(&format!("library/sections/{key}/refresh"))
+                .with_context(|| format!("Plex refresh of section {key} failed"))?;
+        }
+        Ok(())
+    }
+
+    /// Ask Plex to scan just `folders` (local paths), translated through
+    /// `path_map`. Any folder outside every section makes it a full
+    /// refresh instead.
+    pub fn refresh_paths(
+        &self,
+        folders: &[String],
+        path_map: &BTreeMap<String, String>,
+    ) -> Result<ScanReport> {
+        let body = self
+            .get("library/sections")
+            .context("Plex section listing failed")?;
+        let sections = parse_sections(&body)?;
+        let mapped: Vec<String> = folders.iter().map(|f| map_path(f, path_map)).collect();
+        let plan = plan_scans(&sections, &mapped);
+        if !plan.unmatched.is_empty() {
+            self.refresh()?;
+            return Ok(ScanReport {
+                folders: 0,
+                full: true,
+            });
+        }
+        for (key, folder) in &plan.partial {
+            let path = http::encode_component(folder);
+            self.get(&format!("library/sections/{key}/refresh?path={path}"))
+                .with_context(|| format!("Plex scan of {folder} failed"))?;
+        }
+        Ok(ScanReport {
+            folders: plan.partial.len(),
+            full: false,
+        })
+    }
+
+    /// Every movie in the server's movie sections.
+    pub fn movies(&self) -> Result<Vec<PlexMovie>> {
+        let mut movies = Vec::new();
+        for key in self.section_keys(Some("movie"))? {
+            let body = self
+                .get(&format!("library/sections/{key}/all?includeGuids=1"))
+                .with_context(|| format!("Plex listing of section {key} failed"))?;
+            movies.extend(parse_movies(&body)?);
+        }
+        Ok(movies)
+    }
+
+    fn section_keys(&self, kind: Option<&str>) -> Result<Vec<String>> {
+        let body = self
+            .get("library/sections")
+            .context("Plex section listing failed")?;
+        parse_section_keys(&body, kind)
+    }
+
+    /// GET `path` with the token as a header, which [`http::get`] hands to
+    /// curl on stdin; a `?X-Plex-Token=` query would do the same, but would
+    /// also end up in Plex's access log.
+    fn get(&self, path: &str) -> Result<String> {
+        let url = format!("{}/{path}", self.url);
+        http::get(
+            &url,
+            &[
+                ("Accept", "application/json"),
+                ("X-Plex-Token", self.token.as_str()),
+            ],
+        )
+    }
+}
+
+/// Keys of the sections in a `library/sections` response, optionally only
+/// those of one type (`movie`, `show`, `artist`).
+pub fn parse_section_keys(body: &str, kind: Option<&str>) -> Result<Vec<String>> {
+    Ok(parse_sections(body)?
+        .into_iter()
+        .filter(|s| kind.is_none_or(|k| s.kind == k))
+        .map(|s| s.key)
+        .collect())
+}
+
+/// The sections in a `library/sections` response, with their locations.
+pub fn parse_sections(body: &str) -> Result<Vec<LibrarySection>> {
+    let response: Response<Sections> =
+        serde_json::from_str(body).context("Unexpected Plex section listing")?;
+    Ok(response
+        .container
+        .directories
+        .into_iter()
+        .map(|s| LibrarySection {
+            key: s.key,
+            kind: s.kind,
+            locations: s.locations.into_iter().map(|l| l.path).collect(),
+        })
+        .collect())
+}
+
+/// `path` as Plex sees it: the longest matching local prefix of `path_map`
+/// swapped for its server prefix. Unmapped paths are taken as they are.
+pub fn map_path(path: &str, path_map: &BTreeMap<String, String>) -> String {
+    let best = path_map
+        .iter()
+        .filter(|(local, _)| within(path, local))
+        .max_by_key(|(local, _)| local.len());
+    let Some((local, remote)) = best else {
+        return path.to_string();
+    };
+    let rest = path[local.trim_end_matches(['/', '\\']).len()..].trim_start_matches(['/', '\\']);
+    let remote = remote.trim_end_matches(['/', '\\']);
+    if rest.is_empty() {
+        return remote.to_string();
+    }
+    // The server's separator, which need not be ours.
+    let separator = if remote.contains('\\') && !remote.contains('/') {
+        "\\"
+    } else {
+        "/"
+    };
+    let rest = rest.replace(['/', '\\'], separator);
+    format!("{remote}{separator}{rest}")
+}
+
+/// Which section scans each server-side folder: the one with the longest
+/// location holding it. Duplicate folders are scanned once.
+pub fn plan_scans(sections: &[LibrarySection], folders: &[String]) -> ScanPlan {
+    let mut plan = ScanPlan::default();
+    let mut seen = BTreeSet::new();
+    for folder in folders {
+        if !seen.insert(folder.as_str()) {
+            continue;
+        }
+        let section = sections
+            .iter()
+            .flat_map(|s| s.locations.iter().map(move |l| (s, l)))
+            .filter(|(_, location)| within(folder, location))
+            .max_by_key(|(_, location)| location.len());
+        match section {
+            Some((s, _)) => plan.partial.push((s.key.clone(), folder.clone())),
+            None => plan.unmatched.push(folder.clone()),
+        }
+    }
+    plan
+}
+
+/// Whether `path` is `root` or inside it, component-wise, in either
+/// separator style.
+fn within(path: &str, root: &str) -> bool {
+    let path = path.replace('\\', "/");
+    let root = root.replace('\\', "/");
+    let root = root.trim_end_matches('/');
+    match path.strip_prefix(root) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/') || root.is_empty(),
+        None => false,
+    }
+}
+
+/// Movies in a `library/sections/{key}/all` response.
+pub fn parse_movies(body: &str) -> Result<Vec<PlexMovie>> {
+    let response: Response<Items> =
+        serde_json::from_str(body).context("Unexpected Plex library listing")?;
+    Ok(response
+        .container
+        .metadata
+        .into_iter()
+        .map(|item| PlexMovie {
+            tmdb_id: item
+                .guids
+                .iter()
+                .find_map(|g| g.id.strip_prefix("tmdb://")?.parse().ok()),
+            files: item
+                .media
+                .into_iter()
+                .flat_map(|m| m.parts)
+                .map(|p| p.file)
+                .collect(),
+            title: item.title,
+            year: item.year,
+        })
+        .collect())
+}
+
+/// A movie Plex resolved differently from our match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Disagreement {
+    pub destination: String,
+    pub title: String,
+    pub year: Option<i32>,
+    pub plex_title: String,
+    pub plex_year: Option<i32>,
+}
+
+/// How Plex's view of organized movies compares with ours.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlexCheck {
+    pub agreed: usize,
+    pub disagreements: Vec<Disagreement>,
+    /// Destinations Plex has not picked up (yet).
+    pub missing: Vec<String>,
+}
+
+/// Compare the movies of an organize run with what Plex made of them.
+///
+/// Files are matched on their folder and file name, since Plex may see the
+/// library under another mount point. The TMDb id decides when Plex
+/// reports one; otherwise the title (ignoring case and punctuation) and
+/// year must agree.
+pub fn check_titles(entries: &[UndoEntry], movies: &[PlexMovie]) -> PlexCheck {
+    let mut check = PlexCheck::default();
+    for entry in entries.iter().filter(|e| e.media_type == "movie") {
+        let key = tail(&entry.destination);
+        let Some(movie) = movies
+            .iter()
+            .find(|m| m.files.iter().any(|f| tail(f) == key))
+        else {
+            check.missing.push(entry.destination.clone());
+            continue;
+        };
+        let agrees = match (entry.tmdb_id, movie.tmdb_id) {
+            (Some(ours), Some(theirs)) => ours == theirs,
+            _ => {
+                normalize_title(&entry.title) == normalize_title(&movie.title)
+                    && (entry.year.is_none() || entry.year == movie.year)
+            }
+        };
+        if agrees {
+            check.agreed += 1;
+        } else {
+            check.disagreements.push(Disagreement {
+                destination: entry.destination.clone(),
+                title: entry.title.clone(),
+                year: entry.year,
+                plex_title: movie.title.clone(),
+                plex_year: movie.year,
+            });
+        }
+    }
+    check
+}
+
+/// The last two components of a path, in either separator style.
+fn tail(path: &str) -> String {
+    let normalized = path.replace('\\', "/");
+    let parts: Vec<&str> = Path::new(&normalized)
+        .iter()
+        .filter_map(|p| p.to_str())
+        .collect();
+    parts[parts.len().saturating_sub(2)..].join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debug_hides_token() {
+        let client = PlexClient::new("http://localhost:32400/", "s3cret");
+        let shown = format!("{client:?}");
+        assert!(shown.contains("http://localhost:32400"));
+        assert!(!shown.contains("s3cret"));
+    }
+
+    #[test]
+    fn test_check_titles_against_plex() {
+        let sections = r#"{"MediaContainer": {"Directory": [
+            {"key": "1", "type": "movie", "title": "Movies"},
+            {"key": "2", "type": "show", "title": "TV Shows"}
+        ]}}"#;
+        assert_eq!(parse_section_keys(sections, Some("movie")).unwrap(), ["1"]);
+        assert_eq!(parse_section_keys(sections, None).unwrap().len(), 2);
+
+        let listing = r#"{"MediaContainer": {"Metadata": [
+            {"title": "Heat", "year": 1995, "Guid": [{"id": "tmdb://949"}],
+             "Media": [{"Part": [{"file": "/data/Movies/Heat (1995)/Heat (1995).mkv"}]}]},
+            {"title": "Alien: Covenant", "year": 2017,
+             "Media": [{"Part": [{"file": "/data/Movies/Alien (1979)/Alien (1979).mkv"}]}]}
+        ]}}"#;
+        let movies = parse_movies(listing).unwrap();
+        assert_eq!(movies[0].tmdb_id, Some(949));
+
+        let entry = |dest: &str, title: &str, year, tmdb_id| UndoEntry {
+            destination: dest.to_string(),
+            strategy: "move".to_string(),
+            title: title.to_string(),
+            media_type: "movie".to_string(),
+            year: Some(year),
+            tmdb_id,
+            ..Default::default()
+        };
+        let entries = [
+            entry(
+                "/media/Movies/Heat (1995)/Heat (1995).mkv",
+                "Heat",
+                1995,
+                Some(949),
+            ),
+            entry(
+                "/media/Movies/Alien (1979)/Alien (1979).mkv",
+                "Alien",
+                1979,
+                None,
+            ),
+            entry("/media/Movies/Up (2009)/Up (2009).mkv", "Up", 2009, None),
+        ];
+        let check = check_titles(&entries, &movies);
+        assert_eq!(check.agreed, 1);
+        assert_eq!(check.disagreements.len(), 1);
+        assert_eq!(check.disagreements[0].plex_title, "Alien: Covenant");
+        assert_eq!(check.missing, ["/media/Movies/Up (2009)/Up (2009).mkv"]);
+    }
+
+    #[test]
+    fn test_partial_scans_map_folders_to_sections() {
+        let body = r#"{"MediaContainer": {"Directory": [
+            {"key": "1", "type": "movie", "Location": [{"path": "/data/Movies"}]},
+            {"key": "2", "type": "show", "Location": [{"path": "/data/TV Shows/"}]}
+        ]}}"#;
+        let sections = parse_sections(body).unwrap();
+        assert_eq!(sections[1].locations, ["/data/TV Shows/"]);
+
+        let path_map = BTreeMap::from([("/mnt/media/".to_string(), "/data".to_string())]);
+        let folders: Vec<String> = [
+            "/mnt/media/Movies/Heat (1995)",
+            "/mnt/media/TV Shows/Dark/Season 01",
+            "/mnt/media/Movies/Heat (1995)",
+            "/mnt/media/Movies Extra/Up (2009)",
+        ]
+        .iter()
+        .map(|f| map_path(f, &path_map))
+        .collect();
+        assert_eq!(folders[0], "/data/Movies/Heat (1995)");
+        assert_eq!(map_path("/elsewhere/x", &path_map), "/elsewhere/x");
+
+        let plan = plan_scans(&sections, &folders);
+        assert_eq!(
+            plan.partial,
+            [
+                ("1".to_string(), "/data/Movies/Heat (1995)".to_string()),
+                ("2".to_string(), "/data/TV Shows/Dark/Season 01".to_string()),
+            ]
+        );
+        assert_eq!(plan.unmatched, ["/data/Movies Extra/Up (2009)"]);
+    }
+}
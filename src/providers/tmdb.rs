@@ -0,0 +1,595 @@
+//! The Movie Database (TMDb) client.
+//!
+//! `search/movie` confirms a parsed title and year and yields the canonical
+//! spelling and TMDb id; `movie/{id}` supplies library details (genres,
+//! runtime, collection, certification, alternative titles) for `enrich`.
+//!
+//! Many files of a large scan search for the same title (a movie with its
+//! extras, several releases of one film). Search pages are kept for the
+//! run, and identical searches in flight at once wait for the first, so
+//! each is requested only once.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use serde::Deserialize;
+use tracing::debug;
+
+pub use super::SearchPage;
+use super::{cassette, CastMember, MetadataProvider, MovieCandidate, MovieDetails};
+use crate::config::TmdbSettings;
+use crate::database::cache;
+use crate::error::Error;
+use crate::http::{self, RateLimiter};
+
+const API_BASE: &str = "https://api.themoviedb.org/3";
+
+/// Blocking TMDb client. Clones share one rate limiter and the search
+/// pages fetched so far.
+#[derive(Debug, Clone)]
+pub struct TmdbClient {
+    api_key: String,
+    limiter: Arc<RateLimiter>,
+    searches: Arc<Mutex<HashMap<SearchKey, SearchSlot>>>,
+}
+
+/// Query, year, and page of a search.
+type SearchKey = (String, Option<i32>, u32);
+
+/// A search page, or the error fetching it; filled once by whichever
+/// caller asks first while the others wait.
+type SearchSlot = Arc<OnceLock<Result<SearchPage, SharedError>>>;
+
+/// A failed search, handed to every caller that waited on it with its
+/// chain of causes intact, so each can tell a transient failure.
+#[derive(Debug, Clone)]
+struct SharedError(Arc<anyhow::Error>);
+
+impl fmt::Display for SharedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&*self.0, f)
+    }
+}
+
+impl std::error::Error for SharedError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.0.source()
+    }
+}
+
+#[derive(Deserialize)]
+struct SearchResponse {
+    #[serde(default)]
+    results: Vec<SearchResult>,
+    #[serde(default)]
+    total_pages: u32,
+}
+
+#[derive(Deserialize)]
+struct SearchResult {
+    id: u64,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    original_title: Option<String>,
+    #[serde(default)]
+    release_date: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct FindResponse {
+    #[serde(default)]
+    movie_results: Vec<SearchResult>,
+}
+
+impl From<SearchResult> for MovieCandidate {
+    fn from(r: SearchResult) -> Self {
+        MovieCandidate {
+            id: Some(r.id),
+            year: r
+                .release_date
+                .as_deref()
+                .and_then(|d| d.get(..4))
+                .and_then(|y| y.parse().ok()),
+            release_date: r.release_date.filter(|d| !d.is_empty()),
+            original_title: r.original_title.filter(|o| *o != r.title),
+            title: r.title,
+            imdb_id: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Named {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct DetailsResponse {
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    genres: Vec<Named>,
+    #[serde(default)]
+    runtime: Option<u32>,
+    #[serde(default)]
+    release_date: Option<String>,
+    #[serde(default)]
+    belongs_to_collection: Option<Named>,
+    #[serde(default)]
+    release_dates: Option<ReleaseDates>,
+    #[serde(default)]
+    alternative_titles: Option<AlternativeTitles>,
+    #[serde(default)]
+    original_title: Option<String>,
+    #[serde(default)]
+    overview: Option<String>,
+    #[serde(default)]
+    imdb_id: Option<String>,
+    #[serde(default)]
+    credits: Option<Credits>,
+}
+
+#[derive(Deserialize)]
+struct Credits {
+    #[serde(default)]
+    cast: Vec<Credit>,
+}
+
+#[derive(Deserialize)]
+struct Credit {
+    name: String,
+    #[serde(default)]
+    character: String,
+}
+
+/// Cast members kept from a details response.
+const MAX_CAST: usize = 10;
+
+#[derive(Deserialize)]
+struct ReleaseDates {
+    #[serde(default)]
+    results: Vec<CountryReleases>,
+}
+
+#[derive(Deserialize)]
+struct CountryReleases {
+    iso_3166_1: String,
+    #[serde(default)]
+    release_dates: Vec<Release>,
+}
+
+#[derive(Deserialize)]
+struct Release {
+    #[serde(default)]
+    certification: String,
+    /// 3 = theatrical.
+    #[serde(default, rename = "type")]
+    kind: u8,
+}
+
+#[derive(Deserialize)]
+struct AlternativeTitles {
+    #[serde(default)]
+    titles: Vec<AlternativeTitle>,
+}
+
+#[derive(Deserialize)]
+struct AlternativeTitle {
+    title: String,
+}
+
+impl TmdbClient {
+    /// Create a client from a v3 API key or a v4 read access token, sending
+    /// at most `requests_per_second` requests (0 = unlimited).
+    pub fn new(api_key: impl Into<String>, requests_per_second: u32) -> Self {
+        Self {
+            api_key: api_key.into(),
+            limiter: Arc::new(RateLimiter::per_second(requests_per_second)),
+            searches: Arc::default(),
+        }
+    }
+
+    /// Create a client paced as `settings` say: the request rate cap, plus
+    /// any politeness delay and jitter.
+    pub fn from_settings(settings: &TmdbSettings) -> Self {
+        let limiter = RateLimiter::per_second(settings.requests_per_second).with_pacing(
+            Duration::from_millis(settings.politeness_delay_ms),
+            Duration::from_millis(settings.jitter_ms),
+        );
+        Self {
+            api_key: settings.api_key.clone(),
+            limiter: Arc::new(limiter),
+            searches: Arc::default(),
+        }
+    }
+
+    /// Search movies by title, narrowed to `year` when known.
+    pub fn search_movie(
+        &self,
+        query: &str,
+        year: Option<i32>,
+    ) -> crate::Result<Vec<MovieCandidate>> {
+        Ok(self.search_movie_page(query, year, 1)?.candidates)
+    }
+
+    /// One page (1-based) of a movie search. A page already fetched this
+    /// run is not requested again; failures are not kept.
+    pub fn search_movie_page(
+        &self,
+        query: &str,
+        year: Option<i32>,
+        page: u32,
+    ) -> crate::Result<SearchPage> {
+        let key = (query.to_string(), year, page);
+        let slot = lock(&self.searches).entry(key.clone()).or_default().clone();
+        if slot.get().is_some() {
+            debug!("TMDb search for {query:?} (page {page}) already made this run");
+        }
+        let result = slot.get_or_init(|| {
+            self.fetch_search_page(query, year, page)
+                .map_err(|e| SharedError(Arc::new(e)))
+        });
+        match result {
+            Ok(found) => Ok(found.clone()),
+            Err(error) => {
+                let mut searches = lock(&self.searches);
+                if searches.get(&key).is_some_and(|s| Arc::ptr_eq(s, &slot)) {
+                    searches.remove(&key);
+                }
+                Err(Error::tmdb(error.clone().into()))
+            }
+        }
+    }
+
+    fn fetch_search_page(&self, query: &str, year: Option<i32>, page: u32) -> Result<SearchPage> {
+        let mut params = format!(
+            "include_adult=false&query={}",
+            http::encode_component(query)
+        );
+        if let Some(y) = year {
+            params.push_str(&format!("&year={y}"));
+        }
+        // The first page is TMDb's default; leaving it implicit keeps
+        // existing cassettes replaying.
+        if page > 1 {
+            params.push_str(&format!("&page={page}"));
+        }
+        let body = self
+            .get("search/movie", &params)
+            .with_context(|| format!("TMDb search for {query:?} failed"))?;
+        parse_search_page(&body)
+    }
+
+    /// The first page of a movie search in `language` (`zh-CN`, `ja-JP`),
+    /// whose results carry their titles in that language.
+    pub fn search_movie_localized(
+        &self,
+        query: &str,
+        year: Option<i32>,
+        language: &str,
+    ) -> crate::Result<SearchPage> {
+        let mut params = format!(
+            "include_adult=false&language={language}&query={}",
+            http::encode_component(query)
+        );
+        if let Some(y) = year {
+            params.push_str(&format!("&year={y}"));
+        }
+        let body = self
+            .get("search/movie", &params)
+            .with_context(|| format!("TMDb {language} search for {query:?} failed"))
+            .map_err(Error::tmdb)?;
+        parse_search_page(&body).map_err(Error::tmdb)
+    }
+
+    /// The movie with IMDb id `imdb_id` (e.g. `tt0133093`), if TMDb has it.
+    pub fn find_imdb(&self, imdb_id: &str) -> crate::Result<Option<MovieCandidate>> {
+        let body = self
+            .get(
+                &format!("find/{}", http::encode_component(imdb_id)),
+                "external_source=imdb_id",
+            )
+            .with_context(|| format!("TMDb lookup of {imdb_id} failed"))
+            .map_err(Error::tmdb)?;
+        parse_find_response(&body).map_err(Error::tmdb)
+    }
+
+    /// Fetch library details for a movie; certification is taken from
+    /// `region` (ISO 3166-1, e.g. "US").
+    pub fn movie_details(&self, id: u64, region: &str) -> crate::Result<MovieDetails> {
+        let body = self
+            .get(
+                &format!("movie/{id}"),
+                "append_to_response=release_dates,alternative_titles,credits",
+            )
+            .with_context(|| format!("TMDb details for movie {id} failed"))
+            .map_err(Error::tmdb)?;
+        parse_details_response(&body, region).map_err(Error::tmdb)
+    }
+
+    /// Make one cheap request, to check the key is accepted.
+    pub fn check_key(&self) -> crate::Result<()> {
+        self.get("configuration", "")
+            .context("TMDb configuration request failed")
+            .map_err(Error::tmdb)?;
+        Ok(())
+    }
+
+    fn get(&self, path: &str, params: &str) -> Result<String> {
+        let mut url = format!("{API_BASE}/{path}?{params}");
+
+        // v4 read access tokens are JWTs and go in a header; v3 keys in the URL.
+        let bearer;
+        let mut headers = vec![("Accept", "application/json")];
+        if self.api_key.starts_with("eyJ") {
+            bearer = format!("Bearer {}", self.api_key);
+            headers.push(("Authorization", bearer.as_str()));
+        } else {
+            url.push_str(&format!(
+                "&api_key={}",
+                http::encode_component(&self.api_key)
+            ));
+        }
+
+        // Cassettes and the cache key requests without the API key, so
+        // they never hold it.
+        let request = format!("{path}?{params}");
+        let live = || {
+            cassette::fetch(&request, || {
+                self.limiter.wait();
+                http::get(&url, &headers)
+            })
+        };
+        // The key check must reach TMDb.
+        if path == "configuration" {
+            return live();
+        }
+        cache::fetch(&request, |body| found_nothing(path, body), live)
+    }
+}
+
+impl MetadataProvider for TmdbClient {
+    fn name(&self) -> &'static str {
+        "tmdb"
+    }
+
+    fn search_movie_page(
+        &self,
+        query: &str,
+        year: Option<i32>,
+        page: u32,
+    ) -> crate::Result<SearchPage> {
+        TmdbClient::search_movie_page(self, query, year, page)
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Whether a search or find response names no movie, so the cache keeps
+/// it only for the negative TTL.
+fn found_nothing(path: &str, body: &str) -> bool {
+    if path.starts_with("search/") {
+        parse_search_page(body).is_ok_and(|page| page.candidates.is_empty())
+    } else if path.starts_with("find/") {
+        parse_find_response(body).is_ok_and(|found| found.is_none())
+    } else {
+        false
+    }
+}
+
+/// Parse a `search/movie` response body into candidates.
+pub fn parse_search_response(body: &str) -> Result<Vec<MovieCandidate>> {
+    Ok(parse_search_page(body)?.candidates)
+}
+
+/// Parse a `search/movie` response body into candidates and the page count.
+pub fn parse_search_page(body: &str) -> Result<SearchPage> {
+    let response: SearchResponse =
+        serde_json::from_str(body).context("Unexpected TMDb search response")?;
+    Ok(SearchPage {
+        candidates: response
+            .results
+            .into_iter()
+            .map(MovieCandidate::from)
+            .collect(),
+        total_pages: response.total_pages,
+    })
+}
+
+/// Parse a `find/{imdb_id}` response into the movie it names, if any.
+pub fn parse_find_response(body: &str) -> Result<Option<MovieCandidate>> {
+    let response: FindResponse =
+        serde_json::from_str(body).context("Unexpected TMDb find response")?;
+    Ok(response
+        .movie_results
+        .into_iter()
+        .next()
+        .map(MovieCandidate::from))
+}
+
+/// Parse a `movie/{id}` response (with release dates, alternative titles,
+/// and credits appended).
+pub fn parse_details_response(body: &str, region: &str) -> Result<MovieDetails> {
+    let response: DetailsResponse =
+        serde_json::from_str(body).context("Unexpected TMDb details response")?;
+
+    let releases: Vec<&Release> = response
+        .release_dates
+        .iter()
+        .flat_map(|r| &r.results)
+        .filter(|c| c.iso_3166_1.eq_ignore_ascii_case(region))
+        .flat_map(|c| &c.release_dates)
+        .filter(|r| !r.certification.is_empty())
+        .collect();
+    let certification = releases
+        .iter()
+        .find(|r| r.kind == 3)
+        .or(releases.first())
+        .map(|r| r.certification.clone());
+
+    let mut alternative_titles: Vec<String> = Vec::new();
+    for t in response
+        .alternative_titles
+        .into_iter()
+        .flat_map(|a| a.titles)
+    {
+        if !alternative_titles.contains(&t.title) {
+            alternative_titles.push(t.title);
+        }
+    }
+
+    Ok(MovieDetails {
+        title: response.title.filter(|t| !t.is_empty()),
+        genres: response.genres.into_iter().map(|g| g.name).collect(),
+        runtime: response.runtime.filter(|r| *r > 0),
+        year: response
+            .release_date
+            .as_deref()
+            .and_then(|d| d.get(..4))
+            .and_then(|y| y.parse().ok()),
+        collection: response.belongs_to_collection.map(|c| c.name),
+        certification,
+        alternative_titles,
+        original_title: response.original_title.filter(|t| !t.is_empty()),
+        plot: response.overview.filter(|p| !p.is_empty()),
+        imdb_id: response.imdb_id.filter(|id| !id.is_empty()),
+        cast: response
+            .credits
+            .into_iter()
+            .flat_map(|c| c.cast)
+            .take(MAX_CAST)
+            .map(|c| CastMember {
+                name: c.name,
+                role: c.character,
+            })
+            .collect(),
+        fetched_at: Utc::now().to_rfc3339(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_search_response() {
+        let body = r#"{
+            "page": 1,
+            "results": [
+                {"id": 700391, "title": "65", "original_title": "65",
+                 "release_date": "2023-03-02", "popularity": 40.1},
+                {"id": 496243, "title": "Parasite", "original_title": "기생충",
+                 "release_date": "2019-05-30"},
+                {"id": 1, "title": "Untitled", "release_date": ""}
+            ],
+            "total_results": 3
+        }"#;
+        let results = parse_search_response(body).unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].title, "65");
+        assert_eq!(results[0].year, Some(2023));
+        assert_eq!(results[0].original_title, None);
+        assert_eq!(results[1].original_title.as_deref(), Some("기생충"));
+        assert_eq!(results[2].year, None);
+        assert_eq!(parse_search_page(body).unwrap().total_pages, 0);
+        let page = parse_search_page(r#"{"page": 2, "results": [], "total_pages": 7}"#).unwrap();
+        assert_eq!(page.total_pages, 7);
+
+        let found = r#"{"movie_results": [{"id": 603, "title": "The Matrix",
+            "release_date": "1999-03-30"}], "tv_results": []}"#;
+        let matrix = parse_find_response(found).unwrap().unwrap();
+        assert_eq!((matrix.id, matrix.year), (Some(603), Some(1999)));
+        assert_eq!(
+            parse_find_response(r#"{"movie_results": []}"#).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_search_pages_are_shared_by_clones() {
+        let client = TmdbClient::new("key", 0);
+        let page = parse_search_page(
+            r#"{"results": [{"id": 949, "title": "Heat", "release_date": "1995-12-15"}],
+                "total_pages": 1}"#,
+        )
+        .unwrap();
+        let key = ("Heat".to_string(), Some(1995), 1);
+        lock(&client.searches).insert(key, Arc::new(OnceLock::from(Ok(page))));
+
+        // Answered from the first client's search, without a request.
+        let found = client
+            .clone()
+            .search_movie_page("Heat", Some(1995), 1)
+            .unwrap();
+        assert_eq!(found.candidates[0].id, Some(949));
+
+        let key = ("Nope".to_string(), None, 1);
+        let cause = http::HttpError::replayed(Some(503), "HTTP 503: try later".into());
+        let failed = SharedError(Arc::new(anyhow::Error::new(cause).context("search failed")));
+        lock(&client.searches).insert(key.clone(), Arc::new(OnceLock::from(Err(failed))));
+        let error = client.search_movie_page("Nope", None, 1).unwrap_err();
+        assert!(matches!(
+            error,
+            Error::Tmdb {
+                status: Some(503),
+                ..
+            }
+        ));
+        assert!(http::is_transient(&error));
+        assert!(!lock(&client.searches).contains_key(&key));
+    }
+
+    #[test]
+    fn test_parse_details_response() {
+        let body = r#"{
+            "id": 155, "title": "The Dark Knight", "runtime": 152,
+            "release_date": "2008-07-16",
+            "genres": [{"id": 18, "name": "Drama"}, {"id": 28, "name": "Action"}],
+            "belongs_to_collection": {"id": 263, "name": "The Dark Knight Collection"},
+            "release_dates": {"results": [
+                {"iso_3166_1": "GB", "release_dates": [{"certification": "12A", "type": 3}]},
+                {"iso_3166_1": "US", "release_dates": [
+                    {"certification": "", "type": 1},
+                    {"certification": "PG-13", "type": 3}
+                ]}
+            ]},
+            "alternative_titles": {"titles": [
+                {"iso_3166_1": "US", "title": "Dark Knight"},
+                {"iso_3166_1": "CA", "title": "Dark Knight"},
+                {"iso_3166_1": "FR", "title": "Le Chevalier noir"}
+            ]},
+            "imdb_id": "tt0468569", "overview": "Batman raises the stakes.",
+            "credits": {"cast": [
+                {"name": "Christian Bale", "character": "Bruce Wayne"},
+                {"name": "Heath Ledger", "character": "Joker"}
+            ]}
+        }"#;
+        let details = parse_details_response(body, "US").unwrap();
+        assert_eq!(details.title.as_deref(), Some("The Dark Knight"));
+        assert_eq!(details.genres, ["Drama", "Action"]);
+        assert_eq!(details.runtime, Some(152));
+        assert_eq!(details.year, Some(2008));
+        assert_eq!(
+            details.collection.as_deref(),
+            Some("The Dark Knight Collection")
+        );
+        assert_eq!(details.certification.as_deref(), Some("PG-13"));
+        assert_eq!(
+            details.alternative_titles,
+            ["Dark Knight", "Le Chevalier noir"]
+        );
+        assert_eq!(details.imdb_id.as_deref(), Some("tt0468569"));
+        assert_eq!(details.plot.as_deref(), Some("Batman raises the stakes."));
+        assert_eq!(details.cast[1].role, "Joker");
+
+        let bare = parse_details_response(r#"{"id": 1, "belongs_to_collection": null}"#, "US");
+        assert_eq!(bare.unwrap().certification, None);
+    }
+}
@@ -0,0 +1,520 @@
+//! Release quality normalization and ranking.
+//!
+//! Release names spell the same quality many ways: `4K`/`2160p`/`UHD`,
+//! `WEBDL`/`WEB-DL`/`WEB`, `x265`/`HEVC`/`H.265`. Raw tokens (or hunch
+//! output) are mapped to canonical enums here, and every quality decision —
+//! duplicate resolution, upgrades, minimum-quality filters, naming — compares
+//! [`Quality`] values instead of strings.
+//!
+//! Ranking is lexicographic: resolution first, then source, then dynamic
+//! range, then codec. A 1080p WEB-DL beats a 720p Remux; between two 2160p
+//! files the Blu-ray wins.
+//!
+//! What a name says beyond that ranking — a 3D layout, each HDR format,
+//! Atmos sound — is kept apart as [`ReleaseAttributes`], for naming
+//! (`organize.file_info_template`) rather than comparison.
+
+use std::convert::Infallible;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// Vertical resolution, lowest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Resolution {
+    Sd480,
+    Sd576,
+    Hd720,
+    Hd1080,
+    Uhd2160,
+    Uhd4320,
+}
+
+/// Release source, worst first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Source {
+    Cam,
+    Telesync,
+    Screener,
+    Dvd,
+    Hdtv,
+    WebRip,
+    WebDl,
+    BluRay,
+    Remux,
+}
+
+/// Video codec, oldest first. Only a tiebreaker: codecs say little about
+/// picture quality on their own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum VideoCodec {
+    Xvid,
+    H264,
+    Vp9,
+    H265,
+    Av1,
+}
+
+/// Dynamic range, standard first.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum DynamicRange {
+    #[default]
+    Sdr,
+    Hdr,
+    DolbyVision,
+}
+
+/// Normalized quality of a release. Unknown dimensions are `None` and rank
+/// below every known value.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct Quality {
+    // Field order is the ranking order (derived `Ord`).
+    pub resolution: Option<Resolution>,
+    pub source: Option<Source>,
+    pub dynamic_range: DynamicRange,
+    pub codec: Option<VideoCodec>,
+}
+
+/// What a normalized token contributes.
+enum Token {
+    Resolution(Resolution),
+    Source(Source),
+    Codec(VideoCodec),
+    Range(DynamicRange),
+}
+
+/// Map one normalized token (lowercase, no `-`/`+`) to its meaning.
+fn classify(token: &str) -> Option<Token> {
+    use Token::*;
+    Some(match token {
+        "480p" | "480i" | "sd" => Resolution(self::Resolution::Sd480),
+        "576p" | "576i" => Resolution(self::Resolution::Sd576),
+        "720p" | "720i" => Resolution(self::Resolution::Hd720),
+        "1080p" | "1080i" | "fhd" => Resolution(self::Resolution::Hd1080),
+        "2160p" | "4k" | "uhd" | "ultrahd" => Resolution(self::Resolution::Uhd2160),
+        "4320p" | "8k" => Resolution(self::Resolution::Uhd4320),
+        "cam" | "camrip" | "hdcam" | "camera" | "hdcamera" => Source(self::Source::Cam),
+        "ts" | "hdts" | "telesync" | "tc" | "telecine" => Source(self::Source::Telesync),
+        "scr" | "screener" | "dvdscr" => Source(self::Source::Screener),
+        "dvd" | "dvdrip" | "dvd5" | "dvd9" => Source(self::Source::Dvd),
+        "hdtv" | "pdtv" | "tvrip" => Source(self::Source::Hdtv),
+        "webrip" => Source(self::Source::WebRip),
+        "web" | "webdl" => Source(self::Source::WebDl),
+        "bluray" | "bdrip" | "brrip" | "bd" => Source(self::Source::BluRay),
+        "remux" => Source(self::Source::Remux),
+        "xvid" | "divx" => Codec(VideoCodec::Xvid),
+        "x264" | "h264" | "avc" => Codec(VideoCodec::H264),
+        "vp9" => Codec(VideoCodec::Vp9),
+        "x265" | "h265" | "hevc" => Codec(VideoCodec::H265),
+        "av1" => Codec(VideoCodec::Av1),
+        "hdr" | "hdr10" | "hdr10plus" => Range(DynamicRange::Hdr),
+        "dv" | "dovi" | "dolbyvision" => Range(DynamicRange::DolbyVision),
+        _ => return None,
+    })
+}
+
+impl Quality {
+    /// Normalize free-form quality text: a release name, a stored quality
+    /// string, or hunch's display values (`Ultra HD Blu-ray`, `H.265`).
+    pub fn parse(text: &str) -> Self {
+        // `-` separates too, or a release group glued on (`x264-GROUP`)
+        // hides the codec; `WEB-DL` is rejoined as a pair below.
+        let tokens: Vec<String> = text
+            .split(|c: char| {
+                c.is_whitespace() || matches!(c, '.' | '_' | '-' | '[' | ']' | '(' | ')')
+            })
+            .map(|t| t.to_lowercase().replace('+', ""))
+            .filter(|t| !t.is_empty())
+            .collect();
+
+        let mut quality = Self::default();
+        let mut i = 0;
+        while i < tokens.len() {
+            // Separators split some spellings: `H.264`, `WEB-DL`, `Dolby Vision`.
+            if let Some(next) = tokens.get(i + 1) {
+                if let Some(token) = classify(&format!("{}{}", tokens[i], next)) {
+                    quality.apply(token);
+                    i += 2;
+                    continue;
+                }
+            }
+            if let Some(token) = classify(&tokens[i]) {
+                quality.apply(token);
+            }
+            i += 1;
+        }
+        quality
+    }
+
+    /// Build from a hunch parse.
+    pub fn from_hunch(result: &hunch::HunchResult) -> Self {
+        let other = result.other();
+        let mut text: Vec<&str> = [result.screen_size(), result.source(), result.video_codec()]
+            .into_iter()
+            .flatten()
+            .collect();
+        text.extend(&other);
+        let mut quality = Self::parse(&text.join(" "));
+
+        // hunch reports WEBRip as source "Web" plus other "Rip".
+        if result.source() == Some("Web") && other.contains(&"Rip") {
+            quality.source = Some(Source::WebRip);
+        }
+        quality
+    }
+
+    fn apply(&mut self, token: Token) {
+        match token {
+            Token::Resolution(r) => self.resolution = self.resolution.max(Some(r)),
+            // Remux always wins over the disc it came from.
+            Token::Source(s) => self.source = self.source.max(Some(s)),
+            Token::Codec(c) => self.codec = Some(c),
+            Token::Range(d) => self.dynamic_range = self.dynamic_range.max(d),
+        }
+    }
+
+    /// Nothing was recognized.
+    pub fn is_unknown(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Does this quality satisfy `minimum` in every dimension `minimum` sets?
+    ///
+    /// An unknown dimension never satisfies a set minimum.
+    pub fn meets(&self, minimum: &Quality) -> bool {
+        fn at_least<T: Ord>(actual: Option<T>, min: Option<T>) -> bool {
+            match (actual, min) {
+                (_, None) => true,
+                (Some(a), Some(m)) => a >= m,
+                (None, Some(_)) => false,
+            }
+        }
+        at_least(self.resolution, minimum.resolution)
+            && at_least(self.source, minimum.source)
+            && at_least(self.codec, minimum.codec)
+            && self.dynamic_range >= minimum.dynamic_range
+    }
+}
+
+impl Resolution {
+    /// The class of a stream `width`×`height`. Widescreen films are cropped
+    /// in height (1920×800 is 1080p), so the width decides as well.
+    pub fn from_dimensions(width: u32, height: u32) -> Option<Self> {
+        Some(match (width, height) {
+            (0, _) | (_, 0) => return None,
+            (w, h) if w >= 6400 || h >= 3600 => Self::Uhd4320,
+            (w, h) if w >= 3200 || h >= 1800 => Self::Uhd2160,
+            (w, h) if w >= 1600 || h >= 900 => Self::Hd1080,
+            (w, h) if w >= 1100 || h >= 650 => Self::Hd720,
+            (_, h) if h >= 560 => Self::Sd576,
+            _ => Self::Sd480,
+        })
+    }
+}
+
+impl FromStr for Quality {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::parse(s))
+    }
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Sd480 => "480p",
+            Self::Sd576 => "576p",
+            Self::Hd720 => "720p",
+            Self::Hd1080 => "1080p",
+            Self::Uhd2160 => "2160p",
+            Self::Uhd4320 => "4320p",
+        })
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Cam => "CAM",
+            Self::Telesync => "TS",
+            Self::Screener => "SCR",
+            Self::Dvd => "DVD",
+            Self::Hdtv => "HDTV",
+            Self::WebRip => "WEBRip",
+            Self::WebDl => "WEB-DL",
+            Self::BluRay => "BluRay",
+            Self::Remux => "Remux",
+        })
+    }
+}
+
+impl fmt::Display for VideoCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Xvid => "XviD",
+            Self::H264 => "H.264",
+            Self::Vp9 => "VP9",
+            Self::H265 => "H.265",
+            Self::Av1 => "AV1",
+        })
+    }
+}
+
+impl fmt::Display for DynamicRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Sdr => "SDR",
+            Self::Hdr => "HDR",
+            Self::DolbyVision => "DV",
+        })
+    }
+}
+
+/// Canonical spelling, e.g. `2160p BluRay HDR H.265`. SDR is implied and
+/// unknown dimensions are left out, so an unknown quality prints as "".
+impl fmt::Display for Quality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts: Vec<String> = Vec::new();
+        parts.extend(self.resolution.map(|r| r.to_string()));
+        parts.extend(self.source.map(|s| s.to_string()));
+        if self.dynamic_range != DynamicRange::Sdr {
+            parts.push(self.dynamic_range.to_string());
+        }
+        parts.extend(self.codec.map(|c| c.to_string()));
+        f.write_str(&parts.join(" "))
+    }
+}
+
+/// Stereoscopic 3D layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Stereo3d {
+    /// Half side-by-side (`HSBS`).
+    HalfSbs,
+    /// Half over-under (`HOU`, `HTAB`).
+    HalfOu,
+    FullSbs,
+    FullOu,
+    /// Frame-packed Blu-ray 3D (`MVC`).
+    Mvc,
+    /// `3D` with no layout named.
+    Unspecified,
+}
+
+/// An HDR format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HdrFormat {
+    DolbyVision,
+    Hdr10Plus,
+    Hdr10,
+    Hlg,
+}
+
+/// Picture and sound attributes a release name gives besides its
+/// [`Quality`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReleaseAttributes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stereo_3d: Option<Stereo3d>,
+    /// Each format named, Dolby Vision first.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub hdr: Vec<HdrFormat>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub atmos: bool,
+}
+
+impl ReleaseAttributes {
+    /// Read the attributes of a release name. Full side-by-side and
+    /// over-under layouts count only beside `3D`, as `ou` is also a word.
+    pub fn parse(name: &str) -> Self {
+        let tokens: Vec<String> = name
+            .split(|c: char| {
+                c.is_whitespace() || matches!(c, '.' | '_' | '-' | '[' | ']' | '(' | ')')
+            })
+            .map(str::to_lowercase)
+            .filter(|t| !t.is_empty())
+            .collect();
+        let has = |token: &str| tokens.iter().any(|t| t == token);
+        let pair = |a: &str, b: &str| tokens.windows(2).any(|w| w[0] == a && w[1] == b);
+        let three_d = has("3d");
+
+        let stereo_3d = if has("hsbs") || pair("half", "sbs") {
+            Some(Stereo3d::HalfSbs)
+        } else if has("hou") || has("htab") || pair("half", "ou") || pair("half", "tab") {
+            Some(Stereo3d::HalfOu)
+        } else if has("mvc") {
+            Some(Stereo3d::Mvc)
+        } else if three_d && (has("sbs") || has("fsbs")) {
+            Some(Stereo3d::FullSbs)
+        } else if three_d && (has("ou") || has("tab") || has("fou")) {
+            Some(Stereo3d::FullOu)
+        } else {
+            three_d.then_some(Stereo3d::Unspecified)
+        };
+
+        let mut hdr = Vec::new();
+        if has("dv") || has("dovi") || pair("dolby", "vision") {
+            hdr.push(HdrFormat::DolbyVision);
+        }
+        if has("hdr10+") || has("hdr10plus") {
+            hdr.push(HdrFormat::Hdr10Plus);
+        }
+        if has("hdr10") || has("hdr") {
+            hdr.push(HdrFormat::Hdr10);
+        }
+        if has("hlg") {
+            hdr.push(HdrFormat::Hlg);
+        }
+
+        Self {
+            stereo_3d,
+            hdr,
+            atmos: has("atmos"),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// `DV HDR10`, or "" for SDR.
+    pub fn hdr_label(&self) -> String {
+        let labels: Vec<String> = self.hdr.iter().map(|f| f.to_string()).collect();
+        labels.join(" ")
+    }
+}
+
+impl fmt::Display for Stereo3d {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::HalfSbs => "3D HSBS",
+            Self::HalfOu => "3D HOU",
+            Self::FullSbs => "3D SBS",
+            Self::FullOu => "3D OU",
+            Self::Mvc => "3D MVC",
+            Self::Unspecified => "3D",
+        })
+    }
+}
+
+impl fmt::Display for HdrFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::DolbyVision => "DV",
+            Self::Hdr10Plus => "HDR10+",
+            Self::Hdr10 => "HDR10",
+            Self::Hlg => "HLG",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spellings_normalize_to_the_same_quality() {
+        let a = Quality::parse("Movie.2020.4K.UHD.WEBDL.x265.HDR");
+        let b = Quality::parse("2160p WEB-DL HEVC HDR10");
+        assert_eq!(a, b);
+        assert_eq!(a.to_string(), "2160p WEB-DL HDR H.265");
+        assert_eq!(
+            Quality::parse("Ultra HD Blu-ray H.265").source,
+            Some(Source::BluRay)
+        );
+        assert_eq!(Quality::parse("BluRay.REMUX").source, Some(Source::Remux));
+        assert!(Quality::parse("Heat.1995").is_unknown());
+    }
+
+    #[test]
+    fn test_release_attributes() {
+        let a = ReleaseAttributes::parse("Avatar.2009.3D.HSBS.1080p.BluRay.x264.mkv");
+        assert_eq!(a.stereo_3d, Some(Stereo3d::HalfSbs));
+        assert!(a.hdr.is_empty() && !a.atmos);
+
+        let a = ReleaseAttributes::parse("Dune.2021.2160p.WEB-DL.DV.HDR10+.TrueHD.Atmos.mkv");
+        assert_eq!(a.hdr, vec![HdrFormat::DolbyVision, HdrFormat::Hdr10Plus]);
+        assert_eq!(a.hdr_label(), "DV HDR10+");
+        assert!(a.atmos);
+        assert_eq!(a.stereo_3d, None);
+
+        let a = ReleaseAttributes::parse("Gravity.2013.3D.Half-OU.1080p.mkv");
+        assert_eq!(a.stereo_3d, Some(Stereo3d::HalfOu));
+        // `ou` alone is a word, not a layout.
+        assert!(ReleaseAttributes::parse("Tout.ou.rien.2010.mkv").is_empty());
+        assert_eq!(
+            ReleaseAttributes::parse("Movie.2016.Dolby.Vision.mkv").hdr,
+            vec![HdrFormat::DolbyVision]
+        );
+    }
+
+    #[test]
+    fn test_display_round_trips() {
+        for text in ["2160p Remux DV H.265", "720p WEBRip H.264", "DVD XviD", ""] {
+            assert_eq!(Quality::parse(text).to_string(), text);
+        }
+    }
+
+    #[test]
+    fn test_ranking_order() {
+        let q = |s: &str| Quality::parse(s);
+        assert!(q("1080p WEB-DL") > q("720p Remux"));
+        assert!(q("2160p BluRay") > q("2160p WEB-DL"));
+        assert!(q("1080p BluRay HDR") > q("1080p BluRay"));
+        assert!(q("1080p HDTV") > q("unknown"));
+    }
+
+    #[test]
+    fn test_meets_minimum() {
+        let min = Quality::parse("1080p");
+        assert!(Quality::parse("2160p WEBRip").meets(&min));
+        assert!(!Quality::parse("720p BluRay").meets(&min));
+        assert!(!Quality::parse("BluRay").meets(&min));
+        assert!(Quality::parse("DVD").meets(&Quality::default()));
+    }
+
+    #[test]
+    fn test_from_hunch() {
+        let webrip = hunch::hunch("Movie.2020.720p.WEBRip.x264.mkv");
+        assert_eq!(
+            Quality::from_hunch(&webrip).to_string(),
+            "720p WEBRip H.264"
+        );
+        let remux = hunch::hunch("Movie.2020.2160p.UHD.BluRay.REMUX.HDR.HEVC.mkv");
+        assert_eq!(
+            Quality::from_hunch(&remux).to_string(),
+            "2160p Remux HDR H.265"
+        );
+    }
+
+    #[test]
+    fn test_hunch_and_raw_names_agree() {
+        // Both paths end in `classify`; a release name read raw must come
+        // out the same as hunch's reading of it.
+        for name in [
+            "The.Matrix.1999.1080p.BluRay.x264-GROUP.mkv",
+            "Movie.2020.720p.WEBRip.x264.mkv",
+            "Movie.2020.1080p.WEB-DL.DDP5.1.H.264-NTb.mkv",
+            "Movie.2020.2160p.UHD.BluRay.REMUX.HDR.HEVC.mkv",
+            "Movie.2021.2160p.WEB-DL.DV.HDR10.x265.mkv",
+            "Show.S01E02.HDTV.x264-LOL.mkv",
+            "Movie.2003.DVDRip.XviD.avi",
+            "Movie.2022.HDCAM.x264.mkv",
+            "Movie.2019.1080p.BDRip.AV1.mkv",
+        ] {
+            assert_eq!(
+                Quality::from_hunch(&hunch::hunch(name)),
+                Quality::parse(name),
+                "{name}"
+            );
+        }
+    }
+}
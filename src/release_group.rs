@@ -0,0 +1,127 @@
+//! Release groups, cut from the end of a name before parsing.
+//!
+//! A scene name ends with its group after a hyphen: `…x264-SPARKS.mkv`.
+//! Groups may hold hyphens, dots, or an `@` themselves — `D-Z0N3`,
+//! `YTS.MX`, `ted423@FRDS` — and read token by token they fall apart:
+//! hunch takes `AAC-YTS.MX` as group `YTS` and misses a trailing
+//! `[YTS.MX]` altogether. [`extract`] instead anchors at the end of the
+//! name, takes everything after the hyphen that follows the last
+//! technical token, and hands the rest to the parser.
+
+use std::path::Path;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::scanner::{dotted_extension, AUDIO_EXTENSIONS, SUBTITLE_EXTENSIONS, VIDEO_EXTENSIONS};
+use crate::title_strategy::is_technical;
+
+/// Longest plausible group name.
+const MAX_LEN: usize = 32;
+
+/// A trailing `[Group]`, with what comes before it.
+static BRACKET_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?P<rest>.*?)(?P<hyphen>-?)\s*\[(?P<group>[^\[\]\s]+)\]$").unwrap()
+});
+
+/// Find the release group at the end of `name` (a trailing media extension
+/// is allowed), returning the name without it, and the group.
+///
+/// The group follows a hyphen whose word before is technical or a number
+/// (`x264-`, `DDP5.1-`, `2010-`), and whose own token is not a technical
+/// term (`WEB-DL`, `DTS-HD`). A bracketed group is taken after a hyphen,
+/// or when it is dotted like a site (`[YTS.MX]`).
+pub fn extract(name: &str) -> (String, Option<String>) {
+    let ext = dotted_extension(Path::new(name));
+    let known = [VIDEO_EXTENSIONS, AUDIO_EXTENSIONS, SUBTITLE_EXTENSIONS]
+        .iter()
+        .any(|set| set.contains(&ext.as_str()));
+    let (stem, ext) = if known {
+        name.split_at(name.len() - ext.len())
+    } else {
+        (name, "")
+    };
+    let stem = stem.trim_end();
+
+    if let Some(caps) = BRACKET_RE.captures(stem) {
+        let group = &caps["group"];
+        let marked = !caps["hyphen"].is_empty() || group.contains('.');
+        if marked && is_group(group) && !caps["rest"].trim().is_empty() {
+            return (
+                format!("{}{ext}", caps["rest"].trim_end()),
+                Some(group.to_string()),
+            );
+        }
+    }
+
+    for (at, _) in stem.rmatch_indices('-') {
+        let before = &stem[..at];
+        let word = before
+            .rsplit(['.', ' ', '_', '-'])
+            .next()
+            .unwrap_or_default();
+        let token_start = before.rfind(['.', ' ', '_']).map_or(0, |i| i + 1);
+        let token = stem[token_start..]
+            .split(['.', ' ', '_'])
+            .next()
+            .unwrap_or_default();
+        let group = &stem[at + 1..];
+        let anchored =
+            is_technical(word) || (!word.is_empty() && word.bytes().all(|b| b.is_ascii_digit()));
+        if anchored && token_start > 0 && !is_technical(token) && is_group(group) {
+            return (format!("{before}{ext}"), Some(group.to_string()));
+        }
+    }
+    (name.to_string(), None)
+}
+
+/// Whether `text` can be a group name: short, one word with a letter, and
+/// no technical terms or bare numbers in it.
+fn is_group(text: &str) -> bool {
+    !text.is_empty()
+        && text.len() <= MAX_LEN
+        && !text.contains(char::is_whitespace)
+        && text.chars().any(|c| c.is_ascii_alphabetic())
+        && text.split(['.', '_']).all(|part| {
+            !part.is_empty() && !is_technical(part) && !part.bytes().all(|b| b.is_ascii_digit())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_groups() {
+        let cases = [
+            ("Heat.1995.1080p.BluRay.DTS.x264-D-Z0N3.mkv", "D-Z0N3"),
+            ("Inception.2010.1080p.BluRay.x264.AAC-YTS.MX.mp4", "YTS.MX"),
+            ("Inception.2010.1080p.BluRay.x264-[YTS.MX].mp4", "YTS.MX"),
+            ("Inception (2010) [1080p] [YTS.MX].mp4", "YTS.MX"),
+            ("Heat.1995.1080p.BluRay.x264-ted423@FRDS.mkv", "ted423@FRDS"),
+            ("Heat 1995 1080p BluRay x264-DON.mkv", "DON"),
+            ("Alien.1979.1080p.BluRay.DTS-HD.MA.5.1-FGT.mkv", "FGT"),
+            ("Spider-Man.2002.1080p.WEB-DL.x264-SPARKS", "SPARKS"),
+            ("Show.S01E02.720p.HDTV.x264-KILLERS.mkv", "KILLERS"),
+        ];
+        for (name, group) in cases {
+            let (rest, found) = extract(name);
+            assert_eq!(found.as_deref(), Some(group), "{name}");
+            assert!(!rest.contains(group), "{name} → {rest}");
+        }
+        assert_eq!(
+            extract("Heat.1995.1080p.BluRay.DTS.x264-D-Z0N3.mkv").0,
+            "Heat.1995.1080p.BluRay.DTS.x264.mkv"
+        );
+
+        for name in [
+            "X-Men.2000.mkv",
+            "Spider-Man.2002.1080p.WEB-DL.mkv",
+            "Movie.2010.1080p.DTS-HD.mkv",
+            "[SubsPlease] Show - 05 [1080p] [A1B2C3D4].mkv",
+            "Heat (1995).mkv",
+        ] {
+            assert_eq!(extract(name), (name.to_string(), None), "{name}");
+        }
+    }
+}
@@ -0,0 +1,137 @@
+//! Checkpoints for resuming an interrupted scan.
+//!
+//! Walking a library of 50,000 files over a network mount takes long
+//! enough to be interrupted by a closed laptop or a dropped connection.
+//! [`scan_directory_resumable`](super::scan_directory_resumable) hands out
+//! a [`ScanCheckpoint`] every so often: what the walk found and skipped so
+//! far, and the last path it visited. The caller keeps it (`plex-org scan`
+//! stores it in the library database), and a scan started from it goes on
+//! after that path. Callers that parse the files found keep their progress
+//! in the same checkpoint.
+
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use super::{SkipReason, SkippedDir};
+use crate::models::{MediaFile, ParsedMedia};
+
+/// How far a scan of `root` got.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScanCheckpoint {
+    /// Directory being scanned.
+    pub root: PathBuf,
+    /// RFC 3339 time the checkpoint was taken.
+    pub updated_at: String,
+    /// Last path the walk visited; everything before it in name order is
+    /// in `files` and `skipped`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_path: Option<PathBuf>,
+    /// The walk finished and `files` is the scan's result.
+    pub complete: bool,
+    pub files: Vec<MediaFile>,
+    pub skipped: Vec<SkippedDir>,
+    /// Parse results of the first `parsed.len()` files, for callers that
+    /// parse what the scan found.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub parsed: Vec<ParsedMedia>,
+}
+
+impl ScanCheckpoint {
+    /// A checkpoint of a scan of `root` taken now, with nothing walked.
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            updated_at: Utc::now().to_rfc3339(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Note a folder left out of the scan, unless a resumed walk has it
+/// already: a checkpoint can be taken between the skip and the next path.
+pub(super) fn record_skip(skipped: &RefCell<Vec<SkippedDir>>, path: &Path, reason: SkipReason) {
+    let mut skipped = skipped.borrow_mut();
+    if !skipped.iter().any(|s| s.path == path) {
+        skipped.push(SkippedDir {
+            path: path.to_path_buf(),
+            reason,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::time::Duration;
+
+    use anyhow::bail;
+
+    use super::*;
+    use crate::scanner::{scan_directory_report, scan_directory_resumable, ScanOptions};
+
+    #[test]
+    fn test_interrupted_scan_resumes_from_checkpoint() {
+        let tmp = tempfile::tempdir().unwrap();
+        for name in [
+            "Alien (1979)/Alien.1979.mkv",
+            "Heat (1995)/Heat.1995.mkv",
+            "Heat (1995)/Heat.1995.en.srt",
+            "Show.S01.COMPLETE/Show.S01E01.mkv",
+            "Show.S01.COMPLETE/Show.S01E02.mkv",
+            "Up (2009)/Up.2009.mkv",
+            "Zodiac.2007.mkv",
+        ] {
+            let path = tmp.path().join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "video").unwrap();
+        }
+        let options = ScanOptions {
+            min_video_size: 0,
+            skip_season_packs: true,
+            ..Default::default()
+        };
+        let full = scan_directory_report(tmp.path(), &options).unwrap();
+        assert_eq!(full.files.len(), 4);
+
+        // Interrupt the walk after a few checkpoints, then go on from the last.
+        let mut saved: Vec<ScanCheckpoint> = Vec::new();
+        let mut save = |checkpoint: &ScanCheckpoint| {
+            saved.push(checkpoint.clone());
+            if saved.len() == 6 {
+                bail!("interrupted");
+            }
+            Ok(())
+        };
+        let result =
+            scan_directory_resumable(tmp.path(), &options, None, Duration::ZERO, &mut save);
+        assert!(result.is_err());
+        let checkpoint = saved.pop().unwrap();
+        assert!(checkpoint.last_path.is_some() && !checkpoint.complete);
+        assert!(checkpoint.files.len() < 4);
+
+        let mut revisited = Vec::new();
+        let mut save = |checkpoint: &ScanCheckpoint| {
+            revisited.extend(checkpoint.last_path.clone());
+            Ok(())
+        };
+        let resumed = scan_directory_resumable(
+            tmp.path(),
+            &options,
+            Some(checkpoint.clone()),
+            Duration::ZERO,
+            &mut save,
+        )
+        .unwrap();
+        let paths = |files: &[MediaFile]| -> Vec<PathBuf> {
+            files.iter().map(|f| f.source_path.clone()).collect()
+        };
+        assert_eq!(paths(&resumed.files), paths(&full.files));
+        assert_eq!(resumed.skipped.len(), 1);
+        let last = checkpoint.last_path.unwrap();
+        assert!(revisited.iter().all(|p| *p >= last), "{revisited:?}");
+    }
+}
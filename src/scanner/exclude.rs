@@ -0,0 +1,106 @@
+//! Names the scanner never looks at: system junk, and Plex's own files.
+//!
+//! Re-organizing an existing Plex library means walking folders Plex has
+//! written into. Its optimized copies under `Plex Versions/` are real videos
+//! that must not be parsed and moved as if they were movies, and
+//! `.plexmatch`, `.DS_Store`, and `Thumbs.db` are litter. The Plex and OS
+//! names are the `scan.exclude_names` default and can be changed; NAS and
+//! trash folders are always skipped, as are samples, trailers, and extras.
+
+use crate::extras;
+
+/// Default `scan.exclude_names`: Plex artifacts and OS metadata files.
+pub const DEFAULT_EXCLUDE_NAMES: &[&str] = &[
+    "Plex Versions",
+    ".plexmatch",
+    ".plexignore",
+    ".DS_Store",
+    "Thumbs.db",
+    "desktop.ini",
+];
+
+/// Folders that are never media: archive debris, NAS metadata, trash.
+const SKIP_DIRS: &[&str] = &[
+    "__macosx",
+    ".ds_store",
+    "@eadir",
+    "#recycle",
+    ".recycle",
+    "lost+found",
+    "$recycle.bin",
+];
+
+/// Abbreviations of "sample" that mark a video as one only when it is also
+/// much smaller than its folder's main video.
+const SAMPLE_HINTS: &[&str] = &["sample", "smpl", "samp"];
+
+/// Stem prefixes of disc menus.
+const MENU_PREFIXES: &[&str] = &["bdmenu"];
+
+/// [`DEFAULT_EXCLUDE_NAMES`] as owned strings, for config defaults.
+pub fn default_exclude_names() -> Vec<String> {
+    DEFAULT_EXCLUDE_NAMES
+        .iter()
+        .map(|n| n.to_string())
+        .collect()
+}
+
+/// Whether a file or folder name is one of `excluded`, ignoring case.
+pub(super) fn is_excluded(name: &str, excluded: &[String]) -> bool {
+    excluded.iter().any(|x| x.eq_ignore_ascii_case(name))
+}
+
+pub(super) fn should_skip_dir(dirname: &str, excluded: &[String]) -> bool {
+    dirname.starts_with('.')
+        || SKIP_DIRS.contains(&dirname.to_lowercase().as_str())
+        || is_excluded(dirname, excluded)
+}
+
+/// Whether a lowercased file stem names a disc menu, promo, or extra
+/// (see `extras`).
+pub(super) fn is_extras_file(stem_lower: &str) -> bool {
+    MENU_PREFIXES.iter().any(|p| stem_lower.starts_with(p))
+        || extras::classify(stem_lower).is_some()
+}
+
+/// Whether a lowercased file stem names a sample.
+pub(super) fn is_sample_file(stem_lower: &str) -> bool {
+    extras::has_word(stem_lower, "sample")
+}
+
+/// Whether a lowercased file stem hints at a sample (`mv-smpl`), too
+/// loosely to skip it for the name alone.
+pub(super) fn hints_sample(stem_lower: &str) -> bool {
+    SAMPLE_HINTS.iter().any(|hint| stem_lower.contains(hint))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::scanner::{scan_directory, ScanOptions};
+
+    #[test]
+    fn test_plex_artifacts_are_not_scanned() {
+        let tmp = tempfile::tempdir().unwrap();
+        let movie = tmp.path().join("Movies/Heat (1995)");
+        let optimized = movie.join("Plex Versions/Optimized for TV");
+        fs::create_dir_all(&optimized).unwrap();
+        fs::write(movie.join("Heat (1995).mkv"), b"movie").unwrap();
+        fs::write(optimized.join("Heat (1995).mp4"), b"optimized").unwrap();
+        fs::write(movie.join("Thumbs.db"), b"x").unwrap();
+
+        let mut options = ScanOptions {
+            min_video_size: 0,
+            ..Default::default()
+        };
+        let names = |options: &ScanOptions| -> Vec<String> {
+            let files = scan_directory(tmp.path(), options).unwrap();
+            files.iter().map(|f| f.full_name()).collect()
+        };
+        assert_eq!(names(&options), ["Heat (1995).mkv"]);
+
+        options.exclude_names.clear();
+        assert_eq!(names(&options).len(), 2);
+    }
+}
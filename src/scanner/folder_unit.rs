@@ -0,0 +1,152 @@
+//! One-movie-per-folder mode.
+//!
+//! Curated libraries often hold exactly one movie per top-level folder,
+//! next to extras, featurettes, and samples under all sorts of names. In this
+//! mode each such folder yields only its main video — the largest — tagged
+//! with the folder name so the parser reads the title from the folder first.
+//! The other parts of a multi-part main video (`CD1`, `CD2`) come along.
+//! The folder's other videos ride on the main one as its extras, planned
+//! into Plex's extras folders with `organize.extras = "organize"`;
+//! subtitles still travel with the main video as companions. Anything
+//! else in the folder stays where it is.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use tracing::debug;
+
+use super::VIDEO_EXTENSIONS;
+use crate::models::MediaFile;
+use crate::part;
+
+/// Reduce each top-level folder under `root` that holds videos to its main
+/// video, carrying the folder's other videos in its `unit_extras`. Files
+/// directly under `root` and video-less folders (music albums) pass through
+/// unchanged.
+pub(super) fn collapse_to_units(files: Vec<MediaFile>, root: &Path) -> Vec<MediaFile> {
+    let is_video = |f: &MediaFile| VIDEO_EXTENSIONS.contains(&f.extension.as_str());
+    let mut loose = Vec::new();
+    let mut units: BTreeMap<PathBuf, Vec<MediaFile>> = BTreeMap::new();
+    for file in files {
+        match top_level_dir(&file.source_path, root) {
+            Some(dir) => units.entry(dir).or_default().push(file),
+            None => loose.push(file),
+        }
+    }
+
+    for (dir, files) in units {
+        let main = files
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| is_video(f))
+            .max_by_key(|(_, f)| f.size_bytes)
+            .map(|(i, _)| i);
+        let Some(main) = main else {
+            loose.extend(files);
+            continue;
+        };
+        let unit_name = dir.file_name().map(|n| n.to_string_lossy().into_owned());
+        // Name of the main video without its part tag, if it has one.
+        let parts_of = |f: &MediaFile| match part::extract(&f.full_name()) {
+            (stripped, Some(_)) => Some(stripped),
+            (_, None) => None,
+        };
+        let main_parts = parts_of(&files[main]);
+        let mut members = Vec::new();
+        let mut extras = Vec::new();
+        for (i, file) in files.into_iter().enumerate() {
+            let other_part =
+                main_parts.is_some() && is_video(&file) && parts_of(&file) == main_parts;
+            if i == main || other_part {
+                members.push(file);
+            } else if is_video(&file) {
+                extras.push(file.source_path);
+            } else {
+                debug!(
+                    "leaving {} with the main video of {}",
+                    file.source_path.display(),
+                    dir.display()
+                );
+            }
+        }
+        for (i, mut file) in members.into_iter().enumerate() {
+            file.unit_name = unit_name.clone();
+            // Extras go with the first part only, so they are planned once.
+            if i == 0 {
+                file.unit_extras = std::mem::take(&mut extras);
+            }
+            loose.push(file);
+        }
+    }
+    loose
+}
+
+/// The folder directly under `root` that contains `path`, if any.
+fn top_level_dir(path: &Path, root: &Path) -> Option<PathBuf> {
+    let relative = path.strip_prefix(root).ok()?;
+    let mut components = relative.components();
+    let first = components.next()?;
+    // A file directly under the root has nothing after its own name.
+    components.next()?;
+    Some(root.join(first))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{scan_directory, ScanOptions};
+    use std::fs;
+
+    #[test]
+    fn test_one_movie_per_folder_keeps_main_video() {
+        let tmp = tempfile::tempdir().unwrap();
+        let folder = tmp.path().join("Heat (1995)");
+        fs::create_dir_all(folder.join("Extras")).unwrap();
+        fs::write(folder.join("heat-bluray.mkv"), vec![0u8; 1000]).unwrap();
+        fs::write(folder.join("Extras/Making Heat.mkv"), vec![0u8; 400]).unwrap();
+        fs::write(folder.join("menu.mkv"), vec![0u8; 200]).unwrap();
+        fs::write(tmp.path().join("Loose.2020.mkv"), vec![0u8; 10]).unwrap();
+        let samurai = tmp.path().join("Seven Samurai (1954)");
+        fs::create_dir_all(&samurai).unwrap();
+        fs::write(samurai.join("Seven.Samurai.1954.CD1.mkv"), vec![0u8; 900]).unwrap();
+        fs::write(samurai.join("Seven.Samurai.1954.CD2.mkv"), vec![0u8; 800]).unwrap();
+        fs::write(samurai.join("Trailer.mkv"), vec![0u8; 100]).unwrap();
+        let album = tmp.path().join("Artist - Album (2020)");
+        fs::create_dir_all(&album).unwrap();
+        fs::write(album.join("01 - One.flac"), b"a").unwrap();
+        fs::write(album.join("02 - Two.flac"), b"a").unwrap();
+
+        let opts = ScanOptions {
+            min_video_size: 0,
+            sample_size_ratio: 0.0,
+            one_movie_per_folder: true,
+            ..Default::default()
+        };
+        let files = scan_directory(tmp.path(), &opts).unwrap();
+        let mut names: Vec<_> = files.iter().map(|f| f.full_name()).collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "01 - One.flac",
+                "02 - Two.flac",
+                "Loose.2020.mkv",
+                "Seven.Samurai.1954.CD1.mkv",
+                "Seven.Samurai.1954.CD2.mkv",
+                "heat-bluray.mkv"
+            ]
+        );
+        let main = files.iter().find(|f| f.filename == "heat-bluray").unwrap();
+        assert_eq!(main.unit_name.as_deref(), Some("Heat (1995)"));
+        let mut extras = main.unit_extras.clone();
+        extras.sort();
+        assert_eq!(
+            extras,
+            [
+                folder.join("Extras/Making Heat.mkv"),
+                folder.join("menu.mkv")
+            ]
+        );
+        let trailers: Vec<_> = files.iter().flat_map(|f| &f.unit_extras).collect();
+        assert!(trailers.contains(&&samurai.join("Trailer.mkv")));
+    }
+}
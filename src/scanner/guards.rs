@@ -0,0 +1,82 @@
+//! Limits on how far and how wide a scan goes.
+//!
+//! A source can hold folders no media library has: a runaway download
+//! client's cache with a million files in one folder, or a symlink-free but
+//! deeply nested backup tree. Walking them costs minutes and turns up
+//! nothing worth organizing. Folders past `scan.max_depth` or holding more
+//! than `scan.max_files_per_dir` entries are skipped as a unit and reported,
+//! so the coverage of a scan is never silently cut short.
+
+use std::fs;
+use std::path::Path;
+
+use super::{ScanOptions, SkipReason};
+
+/// Default `scan.max_files_per_dir`.
+pub const DEFAULT_MAX_FILES_PER_DIR: usize = 100_000;
+
+/// Why the folder at `path`, `depth` levels below the scan root, is not
+/// scanned, if it hits a limit.
+pub(super) fn check(path: &Path, depth: usize, options: &ScanOptions) -> Option<SkipReason> {
+    if options.max_depth > 0 && depth > options.max_depth {
+        return Some(SkipReason::TooDeep(options.max_depth));
+    }
+    let limit = options.max_files_per_dir;
+    if limit > 0 {
+        // Stop counting one past the limit; the exact size does not matter.
+        let entries = fs::read_dir(path).ok()?.take(limit + 1).count();
+        if entries > limit {
+            return Some(SkipReason::TooManyFiles(limit));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::scanner::{scan_directory_report, ScanOptions, SkipReason};
+
+    #[test]
+    fn test_depth_and_width_guards() {
+        let tmp = tempfile::tempdir().unwrap();
+        let deep = tmp.path().join("a/b/c");
+        let wide = tmp.path().join("wide");
+        fs::create_dir_all(&deep).unwrap();
+        fs::create_dir_all(&wide).unwrap();
+        fs::write(tmp.path().join("a/Heat.1995.mkv"), b"x").unwrap();
+        fs::write(deep.join("Alien.1979.mkv"), b"x").unwrap();
+        for i in 0..4 {
+            fs::write(wide.join(format!("Clip.{i}.mkv")), b"x").unwrap();
+        }
+
+        let options = ScanOptions {
+            min_video_size: 0,
+            max_depth: 2,
+            max_files_per_dir: 3,
+            ..Default::default()
+        };
+        let report = scan_directory_report(tmp.path(), &options).unwrap();
+        let names: Vec<_> = report.files.iter().map(|f| f.full_name()).collect();
+        assert_eq!(names, ["Heat.1995.mkv"]);
+        let mut reasons: Vec<_> = report
+            .skipped
+            .iter()
+            .map(|s| {
+                (
+                    s.path.strip_prefix(tmp.path()).unwrap().to_owned(),
+                    s.reason.clone(),
+                )
+            })
+            .collect();
+        reasons.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            reasons,
+            [
+                ("a/b/c".into(), SkipReason::TooDeep(2)),
+                ("wide".into(), SkipReason::TooManyFiles(3)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,592 @@
+//! Directory scanner — discovers media files recursively.
+//!
+//! Walks a directory tree, filters by extension, skips samples/extras/hidden
+//! files, and yields `MediaFile` records, each with the `.plexorg.toml`
+//! overrides of its folder (see [`DirOverrides`]). Long walks can be
+//! checkpointed and resumed (see [`ScanCheckpoint`]).
+
+mod checkpoint;
+mod exclude;
+mod folder_unit;
+mod guards;
+mod overrides;
+mod report;
+mod season_pack;
+
+pub use checkpoint::ScanCheckpoint;
+pub use exclude::{default_exclude_names, DEFAULT_EXCLUDE_NAMES};
+use exclude::{hints_sample, is_extras_file, is_sample_file};
+pub use guards::DEFAULT_MAX_FILES_PER_DIR;
+use overrides::OverrideCache;
+pub use overrides::{DirOverrides, OVERRIDES_FILE};
+pub use report::{ScanReport, SkipReason, SkippedDir};
+pub use season_pack::{detect_season_pack, SeasonPackEvidence};
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+use tracing::debug;
+use walkdir::WalkDir;
+
+use crate::config::{AppConfig, Extras};
+use crate::error::Error;
+use crate::extras;
+use crate::models::{MediaFile, MediaType};
+
+// ── Extension sets ──────────────────────────────────────────────────────────
+
+pub const VIDEO_EXTENSIONS: &[&str] = &[
+    ".mkv", ".mp4", ".avi", ".mov", ".wmv", ".flv", ".webm", ".m4v", ".mpg", ".mpeg", ".ts",
+    ".vob", ".divx", ".ogv",
+];
+
+pub const AUDIO_EXTENSIONS: &[&str] = &[
+    ".flac", ".mp3", ".aac", ".ogg", ".opus", ".wma", ".wav", ".m4a", ".alac", ".ape", ".dsf",
+    ".dff", ".wv",
+];
+
+pub const SUBTITLE_EXTENSIONS: &[&str] = &[".srt", ".sub", ".idx", ".ssa", ".ass", ".vtt"];
+
+/// Marker file tagging a directory as organizer output (see `organizer::mark_output_root`).
+pub const OUTPUT_MARKER: &str = ".plex-org-output";
+
+/// Default minimum video file size (50 MB) — filters menus/promos.
+pub const DEFAULT_MIN_VIDEO_SIZE: u64 = 50 * 1024 * 1024;
+
+/// Default relative size below which a video named like a sample counts as
+/// one (10%).
+pub const DEFAULT_SAMPLE_SIZE_RATIO: f64 = 0.10;
+
+// ── Scanner options ────────────────────────────────────────────────────────
+
+/// Options controlling the scanner.
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    pub include_subtitles: bool,
+    /// Minimum file size in bytes for video files. Set to 0 to disable.
+    pub min_video_size: u64,
+    /// Skip videos smaller than this fraction of the largest video in the
+    /// same folder whose names hint at a sample (e.g., a 300 MB `mv-smpl`
+    /// next to a 60 GB remux); small videos named otherwise stay. Folders
+    /// directly under the scan root are exempt, since loose downloads there
+    /// are unrelated to each other. Set to 0 to disable.
+    pub sample_size_ratio: f64,
+    /// Treat the source as a movie inbox: skip TV season-pack folders
+    /// (`Show.S01.COMPLETE/`) as a unit instead of scanning their episodes.
+    pub skip_season_packs: bool,
+    /// Directories never to descend into (e.g., a destination inside the source).
+    pub exclude_dirs: Vec<PathBuf>,
+    /// Each top-level folder is one movie: keep only its main video and
+    /// parse the folder name first.
+    pub one_movie_per_folder: bool,
+    /// File and folder names never scanned, ignoring case (Plex artifacts).
+    pub exclude_names: Vec<String>,
+    /// Leave videos in a movie's extras folders to the movie
+    /// (`organize.extras = "organize"`) instead of scanning them.
+    pub claim_extras: bool,
+    /// Deepest folder level scanned below the root; 0 is unlimited.
+    pub max_depth: usize,
+    /// Folders with more entries are skipped and reported; 0 is unlimited.
+    pub max_files_per_dir: usize,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            include_subtitles: false,
+            min_video_size: DEFAULT_MIN_VIDEO_SIZE,
+            sample_size_ratio: DEFAULT_SAMPLE_SIZE_RATIO,
+            skip_season_packs: false,
+            exclude_dirs: Vec::new(),
+            one_movie_per_folder: false,
+            exclude_names: default_exclude_names(),
+            claim_extras: false,
+            max_depth: 0,
+            max_files_per_dir: DEFAULT_MAX_FILES_PER_DIR,
+        }
+    }
+}
+
+impl ScanOptions {
+    /// Build scanner options from the `[scan]` config section.
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            min_video_size: config.scan.min_video_size_mb * 1024 * 1024,
+            sample_size_ratio: config.scan.sample_size_ratio,
+            skip_season_packs: config.scan.movies_only,
+            one_movie_per_folder: config.scan.one_movie_per_folder,
+            exclude_names: config.scan.exclude_names.clone(),
+            claim_extras: config.organize.extras == Extras::Organize,
+            max_depth: config.scan.max_depth,
+            max_files_per_dir: config.scan.max_files_per_dir,
+            ..Default::default()
+        }
+    }
+}
+
+// ── Filter helpers ──────────────────────────────────────────────────────────
+
+/// Drop videos much smaller than the largest video in their folder whose
+/// names hint at a sample.
+fn drop_relative_samples(files: Vec<MediaFile>, root: &Path, ratio: f64) -> Vec<MediaFile> {
+    if ratio <= 0.0 {
+        return files;
+    }
+
+    let is_video = |f: &MediaFile| VIDEO_EXTENSIONS.contains(&f.extension.as_str());
+    let mut largest: HashMap<PathBuf, u64> = HashMap::new();
+    for file in files.iter().filter(|f| is_video(f)) {
+        if let Some(parent) = file.source_path.parent() {
+            let max = largest.entry(parent.to_path_buf()).or_default();
+            *max = (*max).max(file.size_bytes);
+        }
+    }
+
+    files
+        .into_iter()
+        .filter(|file| {
+            let parent = match file.source_path.parent() {
+                Some(p) if p != root && is_video(file) => p,
+                _ => return true,
+            };
+            let max = largest.get(parent).copied().unwrap_or(0);
+            let small = (file.size_bytes as f64) < (max as f64) * ratio;
+            let keep = !small || !hints_sample(&file.filename.to_lowercase());
+            if !keep {
+                debug!(
+                    "skipping relative-size sample: {} ({} of {} bytes)",
+                    file.source_path.display(),
+                    file.size_bytes,
+                    max
+                );
+            }
+            keep
+        })
+        .collect()
+}
+
+/// Re-express `dir` as `root/<relative>` so it matches walkdir entries,
+/// resolving symlinks on both sides first.
+fn relative_to_root(root: &Path, dir: &Path) -> Option<PathBuf> {
+    let canonical_root = std::fs::canonicalize(root).ok()?;
+    let canonical_dir = std::fs::canonicalize(dir).ok()?;
+    let relative = canonical_dir.strip_prefix(&canonical_root).ok()?;
+    Some(root.join(relative))
+}
+
+fn detect_type_by_extension(ext: &str) -> MediaType {
+    if AUDIO_EXTENSIONS.contains(&ext) {
+        return MediaType::Music;
+    }
+    // Video could be movie or TV — hunch will disambiguate
+    MediaType::Unknown
+}
+
+// ── Public API ──────────────────────────────────────────────────────────────
+
+/// Lowercased extension with its leading dot (`.mkv`), or "" if none.
+pub fn dotted_extension(path: &Path) -> String {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| format!(".{}", e.to_lowercase()))
+        .unwrap_or_default()
+}
+
+/// Whether a path is a video or audio file (not a subtitle or sidecar).
+pub fn is_media_path(path: &Path) -> bool {
+    let ext = dotted_extension(path);
+    VIDEO_EXTENSIONS.contains(&ext.as_str()) || AUDIO_EXTENSIONS.contains(&ext.as_str())
+}
+
+/// Whether a path has a subtitle extension.
+pub fn is_subtitle_path(path: &Path) -> bool {
+    SUBTITLE_EXTENSIONS.contains(&dotted_extension(path).as_str())
+}
+
+/// Scan a directory tree and discover media files.
+pub fn scan_directory(path: &Path, options: &ScanOptions) -> crate::Result<Vec<MediaFile>> {
+    Ok(scan_directory_report(path, options)?.files)
+}
+
+/// Scan a directory tree, also reporting directories skipped as a unit.
+pub fn scan_directory_report(path: &Path, options: &ScanOptions) -> crate::Result<ScanReport> {
+    scan_directory_resumable(path, options, None, Duration::MAX, &mut |_| Ok(()))
+}
+
+/// Scan a directory tree, handing `save` a checkpoint of the walk at most
+/// every `every`, and continuing from `from` when given.
+///
+/// Folders are walked in name order, so a checkpoint's last path divides
+/// what was walked from what was not; on resume, everything up to it is
+/// passed over without being read. A complete checkpoint is the result.
+pub fn scan_directory_resumable(
+    path: &Path,
+    options: &ScanOptions,
+    from: Option<ScanCheckpoint>,
+    every: Duration,
+    save: &mut dyn FnMut(&ScanCheckpoint) -> Result<()>,
+) -> crate::Result<ScanReport> {
+    walk(path, options, from, every, save).map_err(Error::Io)
+}
+
+fn walk(
+    path: &Path,
+    options: &ScanOptions,
+    from: Option<ScanCheckpoint>,
+    every: Duration,
+    save: &mut dyn FnMut(&ScanCheckpoint) -> Result<()>,
+) -> Result<ScanReport> {
+    if let Some(done) = from.as_ref().filter(|c| c.complete) {
+        return Ok(ScanReport {
+            files: done.files.clone(),
+            skipped: done.skipped.clone(),
+        });
+    }
+    if !path.exists() {
+        bail!("Path does not exist: {}", path.display());
+    }
+    if !path.is_dir() {
+        bail!("Path is not a directory: {}", path.display());
+    }
+
+    let mut allowed: HashSet<&str> = HashSet::new();
+    for ext in VIDEO_EXTENSIONS {
+        allowed.insert(ext);
+    }
+    for ext in AUDIO_EXTENSIONS {
+        allowed.insert(ext);
+    }
+    if options.include_subtitles {
+        for ext in SUBTITLE_EXTENSIONS {
+            allowed.insert(ext);
+        }
+    }
+
+    let video_set: HashSet<&str> = VIDEO_EXTENSIONS.iter().copied().collect();
+    let from = from.unwrap_or_else(|| ScanCheckpoint::new(path));
+    let resume_after = from.last_path.clone();
+    let mut last_path = resume_after.clone();
+    let mut found = from.files;
+    let skipped = RefCell::new(from.skipped);
+    let mut last_save = Instant::now();
+    let mut overrides = OverrideCache::new(path);
+    // Compare excluded dirs in the same form walkdir yields paths: under `path`.
+    let excluded: Vec<PathBuf> = options
+        .exclude_dirs
+        .iter()
+        .filter_map(|x| relative_to_root(path, x))
+        .collect();
+
+    let walker = WalkDir::new(path)
+        .follow_links(false)
+        .sort_by_file_name()
+        .into_iter();
+
+    for entry in walker.filter_entry(|e| {
+        // Skip hidden / blacklisted directories (but never the root)
+        if e.file_type().is_dir() && e.depth() > 0 {
+            // Walked before the checkpoint: only the way to its last path.
+            if let Some(last) = resume_after.as_deref().filter(|last| e.path() <= *last) {
+                return last.starts_with(e.path());
+            }
+            let name = e.file_name().to_str().unwrap_or("");
+            if exclude::should_skip_dir(name, &options.exclude_names) {
+                return false;
+            }
+            let reason = if excluded.iter().any(|x| x == e.path()) {
+                Some(SkipReason::Excluded)
+            } else if e.path().join(OUTPUT_MARKER).is_file() {
+                Some(SkipReason::PreviousOutput)
+            } else {
+                guards::check(e.path(), e.depth(), options)
+            };
+            if let Some(reason) = reason {
+                debug!("skipping {}: {}", e.path().display(), reason);
+                checkpoint::record_skip(&skipped, e.path(), reason);
+                return false;
+            }
+            if options.skip_season_packs {
+                if let Some(evidence) = detect_season_pack(e.path()) {
+                    debug!(
+                        "skipping season pack: {} ({})",
+                        e.path().display(),
+                        evidence
+                    );
+                    let reason = SkipReason::SeasonPack(evidence);
+                    checkpoint::record_skip(&skipped, e.path(), reason);
+                    return false;
+                }
+            }
+        }
+        true
+    }) {
+        let entry = match entry {
+            Ok(e) => e,
+            Err(err) => {
+                debug!("walkdir error: {}", err);
+                continue;
+            }
+        };
+
+        if resume_after
+            .as_deref()
+            .is_some_and(|last| entry.path() <= last)
+        {
+            continue;
+        }
+        if last_save.elapsed() >= every {
+            save(&ScanCheckpoint {
+                last_path: last_path.clone(),
+                files: found.clone(),
+                skipped: skipped.borrow().clone(),
+                ..ScanCheckpoint::new(path)
+            })?;
+            last_save = Instant::now();
+        }
+        last_path = Some(entry.path().to_path_buf());
+
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let file_name = entry.file_name().to_str().unwrap_or("");
+
+        // Skip hidden files and excluded names
+        if file_name.starts_with('.') || exclude::is_excluded(file_name, &options.exclude_names) {
+            continue;
+        }
+
+        // Extract extension
+        let dot_idx = match file_name.rfind('.') {
+            Some(i) if i > 0 => i,
+            _ => continue,
+        };
+        let ext = file_name[dot_idx..].to_lowercase();
+        if !allowed.contains(ext.as_str()) {
+            continue;
+        }
+
+        let stem = &file_name[..dot_idx];
+        let stem_lower = stem.to_lowercase();
+
+        if is_sample_file(&stem_lower) || is_extras_file(&stem_lower) {
+            continue;
+        }
+        // Extras travel with their movie (see `extras`), never on their own.
+        if options.claim_extras
+            && video_set.contains(ext.as_str())
+            && extras::in_extras_folder(entry.path())
+        {
+            continue;
+        }
+        // `theme.mp3` is a movie's theme music (see `artwork`), not a track.
+        if stem_lower == "theme" && !video_set.contains(ext.as_str()) {
+            continue;
+        }
+
+        // Size check for video files
+        let metadata = match entry.metadata() {
+            Ok(m) => m,
+            Err(_) => continue,
+        };
+        let size = metadata.len();
+
+        if options.min_video_size > 0
+            && video_set.contains(ext.as_str())
+            && size < options.min_video_size
+        {
+            continue;
+        }
+
+        let parent_dir = entry
+            .path()
+            .parent()
+            .and_then(|p| p.file_name())
+            .and_then(|n| n.to_str())
+            .unwrap_or("")
+            .to_string();
+
+        found.push(MediaFile {
+            source_path: entry.path().to_path_buf(),
+            filename: stem.to_string(),
+            detected_type: detect_type_by_extension(&ext),
+            extension: ext,
+            size_bytes: size,
+            parent_dir,
+            modified: metadata.modified().ok(),
+            unit_name: None,
+            unit_extras: Vec::new(),
+            overrides: entry.path().parent().and_then(|dir| overrides.for_dir(dir)),
+        });
+    }
+
+    let mut results = drop_relative_samples(found, path, options.sample_size_ratio);
+    if options.one_movie_per_folder {
+        results = folder_unit::collapse_to_units(results, path);
+    }
+    results.sort_by(|a, b| a.source_path.cmp(&b.source_path));
+    debug!(
+        "scanned {} → {} media files found",
+        path.display(),
+        results.len()
+    );
+    Ok(ScanReport {
+        files: results,
+        skipped: skipped.into_inner(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn create_test_dir(tmp: &Path) {
+        fs::create_dir_all(tmp.join("movies")).unwrap();
+        // Create a "large enough" video file
+        let video = tmp.join("movies/Movie.2024.1080p.mkv");
+        fs::write(&video, vec![0u8; 100]).unwrap();
+        // Create audio file (no size threshold)
+        let audio = tmp.join("music.flac");
+        fs::write(&audio, b"audio").unwrap();
+        // Create sample file (should be skipped)
+        let sample = tmp.join("movies/movie-sample.mkv");
+        fs::write(&sample, vec![0u8; 100]).unwrap();
+        // Hidden file (should be skipped)
+        fs::write(tmp.join(".hidden.mkv"), b"x").unwrap();
+    }
+
+    #[test]
+    fn test_scan_finds_media_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        create_test_dir(tmp.path());
+
+        let opts = ScanOptions {
+            min_video_size: 0, // Disable size check for tests
+            ..Default::default()
+        };
+        let files = scan_directory(tmp.path(), &opts).unwrap();
+
+        let names: Vec<String> = files.iter().map(|f| f.full_name()).collect();
+        assert!(names.contains(&"Movie.2024.1080p.mkv".to_string()));
+        assert!(names.contains(&"music.flac".to_string()));
+        assert!(!names.iter().any(|n| n.contains("sample")));
+        assert!(!names.iter().any(|n| n.starts_with('.')));
+    }
+
+    #[test]
+    fn test_scan_music_detected_as_music() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(tmp.path().join("song.flac"), b"audio").unwrap();
+
+        let files = scan_directory(tmp.path(), &Default::default()).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].detected_type, MediaType::Music);
+    }
+
+    #[test]
+    fn test_scan_nonexistent_dir_errors() {
+        let result = scan_directory(Path::new("/nonexistent_xyz"), &Default::default());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_relative_size_sample_is_skipped() {
+        let tmp = tempfile::tempdir().unwrap();
+        let release = tmp.path().join("Movie.2024.2160p.REMUX");
+        fs::create_dir_all(&release).unwrap();
+        fs::write(release.join("Movie.2024.2160p.REMUX.mkv"), vec![0u8; 1000]).unwrap();
+        fs::write(release.join("mv-smpl.mkv"), vec![0u8; 50]).unwrap();
+        // Small, but not named like a sample.
+        fs::write(release.join("Short.Film.mkv"), vec![0u8; 50]).unwrap();
+        // Loose files at the scan root are never compared with each other.
+        fs::write(tmp.path().join("Other.2020.mkv"), vec![0u8; 10]).unwrap();
+
+        let opts = ScanOptions {
+            min_video_size: 0,
+            ..Default::default()
+        };
+        let files = scan_directory(tmp.path(), &opts).unwrap();
+        let names: Vec<String> = files.iter().map(|f| f.full_name()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "Movie.2024.2160p.REMUX.mkv",
+                "Short.Film.mkv",
+                "Other.2020.mkv"
+            ]
+        );
+
+        let opts = ScanOptions {
+            min_video_size: 0,
+            sample_size_ratio: 0.0,
+            ..Default::default()
+        };
+        assert_eq!(scan_directory(tmp.path(), &opts).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_season_pack_skipped_as_unit_in_movie_inbox() {
+        let tmp = tempfile::tempdir().unwrap();
+        let pack = tmp.path().join("Show.Name.S01.1080p.COMPLETE");
+        fs::create_dir_all(&pack).unwrap();
+        fs::write(pack.join("Show.Name.S01E01.mkv"), b"x").unwrap();
+        fs::write(pack.join("Show.Name.S01E02.mkv"), b"x").unwrap();
+        fs::write(tmp.path().join("Movie.2024.mkv"), b"x").unwrap();
+
+        let opts = ScanOptions {
+            min_video_size: 0,
+            skip_season_packs: true,
+            ..Default::default()
+        };
+        let report = scan_directory_report(tmp.path(), &opts).unwrap();
+        assert_eq!(report.files.len(), 1);
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].path, pack);
+
+        let opts = ScanOptions {
+            skip_season_packs: false,
+            ..opts
+        };
+        assert_eq!(scan_directory(tmp.path(), &opts).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_previous_output_and_excluded_dirs_are_skipped() {
+        let tmp = tempfile::tempdir().unwrap();
+        for dir in ["old-output/Movies/X (2020)", "new-output", "Movie.2024"] {
+            fs::create_dir_all(tmp.path().join(dir)).unwrap();
+        }
+        fs::write(tmp.path().join("old-output").join(OUTPUT_MARKER), b"").unwrap();
+        fs::write(
+            tmp.path().join("old-output/Movies/X (2020)/X (2020).mkv"),
+            b"x",
+        )
+        .unwrap();
+        fs::write(tmp.path().join("new-output/Y.2021.mkv"), b"x").unwrap();
+        fs::write(tmp.path().join("Movie.2024/Movie.2024.mkv"), b"x").unwrap();
+
+        let opts = ScanOptions {
+            min_video_size: 0,
+            exclude_dirs: vec![tmp.path().join("new-output")],
+            ..Default::default()
+        };
+        let report = scan_directory_report(tmp.path(), &opts).unwrap();
+        assert_eq!(report.files.len(), 1);
+        let reasons: Vec<&SkipReason> = report.skipped.iter().map(|s| &s.reason).collect();
+        assert!(reasons.contains(&&SkipReason::PreviousOutput));
+        assert!(reasons.contains(&&SkipReason::Excluded));
+    }
+
+    #[test]
+    fn test_extras_prefix_filtering() {
+        assert!(is_extras_file("bdmenu"));
+        assert!(is_extras_file("pv1"));
+        assert!(is_extras_file("pv"));
+        assert!(!is_extras_file("pvris")); // band name, not extras
+        assert!(is_extras_file("interview_cast"));
+    }
+}
@@ -0,0 +1,168 @@
+//! Per-directory overrides (`.plexorg.toml`).
+//!
+//! Some folders cannot be named from their files: a film released under a
+//! festival title, a show ripped as `Disc 1/title00.mkv`. A `.plexorg.toml`
+//! in a source folder says what its files are:
+//!
+//! ```toml
+//! title = "Let the Bullets Fly"
+//! year = 2010
+//! tmdb_id = 51533
+//! title_template = "{english} [{original}]"
+//! edition = "Director's Cut"
+//! ```
+//!
+//! It covers the files of its folder and of the folders below it; a nearer
+//! file overrides a farther one key by key. The parser takes its title,
+//! year, and ids over whatever the filename says, with full confidence.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// Name of the override file.
+pub const OVERRIDES_FILE: &str = ".plexorg.toml";
+
+/// What a `.plexorg.toml` says about the files under its folder.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DirOverrides {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tmdb_id: Option<u64>,
+    /// With its `tt` prefix.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub imdb_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tvdb_id: Option<u64>,
+    /// Naming template used instead of `organize.title_template`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title_template: Option<String>,
+    /// Edition named on the files (`{edition-…}`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub edition: Option<String>,
+}
+
+impl DirOverrides {
+    /// Read the override file of `dir`, if it has one.
+    pub fn load(dir: &Path) -> Result<Option<Self>> {
+        let path = dir.join(OVERRIDES_FILE);
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        let overrides =
+            toml::from_str(&text).with_context(|| format!("Invalid {}", path.display()))?;
+        Ok(Some(overrides))
+    }
+
+    /// Whether it names the files' identity (not just how they are named).
+    pub fn identifies(&self) -> bool {
+        self.title.is_some()
+            || self.year.is_some()
+            || self.tmdb_id.is_some()
+            || self.imdb_id.is_some()
+            || self.tvdb_id.is_some()
+    }
+
+    /// These overrides, with `outer`'s filling in what they leave unset.
+    fn fill_from(self, outer: &DirOverrides) -> Self {
+        Self {
+            title: self.title.or_else(|| outer.title.clone()),
+            year: self.year.or(outer.year),
+            tmdb_id: self.tmdb_id.or(outer.tmdb_id),
+            imdb_id: self.imdb_id.or_else(|| outer.imdb_id.clone()),
+            tvdb_id: self.tvdb_id.or(outer.tvdb_id),
+            title_template: self.title_template.or_else(|| outer.title_template.clone()),
+            edition: self.edition.or_else(|| outer.edition.clone()),
+        }
+    }
+}
+
+/// The overrides of each folder of one scan, read once per folder.
+#[derive(Debug)]
+pub(super) struct OverrideCache {
+    root: PathBuf,
+    /// Merged overrides in effect for each folder seen.
+    dirs: HashMap<PathBuf, Option<DirOverrides>>,
+}
+
+impl OverrideCache {
+    pub(super) fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            dirs: HashMap::new(),
+        }
+    }
+
+    /// The overrides in effect for files in `dir`: its own file's, then
+    /// those of each folder above it up to the scan root. A file that
+    /// cannot be read is reported and ignored.
+    pub(super) fn for_dir(&mut self, dir: &Path) -> Option<DirOverrides> {
+        if let Some(found) = self.dirs.get(dir) {
+            return found.clone();
+        }
+        let outer = match dir.parent() {
+            Some(parent) if dir != self.root && parent.starts_with(&self.root) => {
+                self.for_dir(parent)
+            }
+            _ => None,
+        };
+        let own = DirOverrides::load(dir).unwrap_or_else(|e| {
+            warn!("{e:#}; ignoring it");
+            None
+        });
+        let merged = match (own, outer) {
+            (Some(own), Some(outer)) => Some(own.fill_from(&outer)),
+            (own, outer) => own.or(outer),
+        };
+        self.dirs.insert(dir.to_path_buf(), merged.clone());
+        merged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nearer_files_override_farther_ones() {
+        let tmp = tempfile::tempdir().unwrap();
+        let asian = tmp.path().join("Asian Cinema");
+        let film = asian.join("让子弹飞");
+        fs::create_dir_all(&film).unwrap();
+        fs::write(
+            asian.join(OVERRIDES_FILE),
+            "title_template = \"{english} [{original}]\"\nedition = \"Remastered\"\n",
+        )
+        .unwrap();
+        fs::write(
+            film.join(OVERRIDES_FILE),
+            "title = \"Let the Bullets Fly\"\nyear = 2010\nedition = \"Director's Cut\"\n",
+        )
+        .unwrap();
+
+        let mut cache = OverrideCache::new(tmp.path());
+        let found = cache.for_dir(&film).unwrap();
+        assert_eq!(found.title.as_deref(), Some("Let the Bullets Fly"));
+        assert_eq!(found.edition.as_deref(), Some("Director's Cut"));
+        assert_eq!(
+            found.title_template.as_deref(),
+            Some("{english} [{original}]")
+        );
+        assert!(found.identifies());
+        assert!(!cache.for_dir(&asian).unwrap().identifies());
+        assert_eq!(cache.for_dir(tmp.path()), None);
+
+        fs::write(film.join(OVERRIDES_FILE), "titel = \"typo\"\n").unwrap();
+        assert!(DirOverrides::load(&film).is_err());
+    }
+}
@@ -0,0 +1,56 @@
+//! What a scan found, and the directories it left out as a unit.
+
+use std::fmt;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use super::SeasonPackEvidence;
+use crate::models::MediaFile;
+
+/// Why a whole directory was left out of a scan.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    SeasonPack(SeasonPackEvidence),
+    /// Holds the organizer's output marker from an earlier run.
+    PreviousOutput,
+    /// Listed in [`ScanOptions::exclude_dirs`](super::ScanOptions::exclude_dirs).
+    Excluded,
+    /// Nested deeper than `max_depth` folders below the scan root.
+    TooDeep(usize),
+    /// Holds more than `max_files_per_dir` entries.
+    TooManyFiles(usize),
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SeasonPack(evidence) => write!(f, "{evidence}"),
+            Self::PreviousOutput => write!(f, "output of a previous organize run"),
+            Self::Excluded => write!(f, "excluded (organize destination)"),
+            Self::TooDeep(limit) => write!(
+                f,
+                "more than {limit} folders deep (raise scan.max_depth to include it)"
+            ),
+            Self::TooManyFiles(limit) => write!(
+                f,
+                "over {limit} entries in one folder (set scan.max_files_per_dir = 0 to scan it)"
+            ),
+        }
+    }
+}
+
+/// A directory skipped as a unit, with the reason.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedDir {
+    pub path: PathBuf,
+    pub reason: SkipReason,
+}
+
+/// Scan results plus the directories that were skipped wholesale.
+#[derive(Debug, Default)]
+pub struct ScanReport {
+    pub files: Vec<MediaFile>,
+    pub skipped: Vec<SkippedDir>,
+}
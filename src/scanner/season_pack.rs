@@ -0,0 +1,126 @@
+//! Folder-level detection of TV season packs.
+//!
+//! A folder like `Show.Name.S01.1080p.COMPLETE/` dropped into a movie inbox
+//! would otherwise be parsed file by file, producing one failed "movie" per
+//! episode. Detecting it at the folder level lets the scanner skip it as a
+//! unit with a single, clear reason.
+
+use std::fmt;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::VIDEO_EXTENSIONS;
+use crate::models::MediaType;
+use crate::parser::{self, ParseOptions};
+
+static EPISODE_TAG: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\bs(\d{1,2})[ ._-]?e(\d{1,3})\b").unwrap());
+
+/// Minimum distinct episode files that make a folder a season pack.
+const MIN_EPISODE_FILES: usize = 2;
+
+/// Why a folder was classified as a TV season pack.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SeasonPackEvidence {
+    /// The folder name carries a season but no episode (`S01`, `Season 2`).
+    FolderName { seasons: String },
+    /// The folder directly holds several `SxxExx` video files.
+    EpisodeFiles { count: usize },
+}
+
+impl fmt::Display for SeasonPackEvidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FolderName { seasons } => {
+                write!(f, "TV season pack (folder name says season {seasons})")
+            }
+            Self::EpisodeFiles { count } => {
+                write!(f, "TV season pack ({count} episode files)")
+            }
+        }
+    }
+}
+
+/// Classify a directory as a season pack, if it looks like one.
+pub fn detect_season_pack(dir: &Path) -> Option<SeasonPackEvidence> {
+    let name = dir.file_name()?.to_str()?;
+
+    // Only the type, season, and episode are read; no `[parsing]`
+    // setting changes those.
+    let parsed = parser::parse_video(name, &ParseOptions::default());
+    if parsed.media_type == MediaType::Tv && parsed.episode.is_none() {
+        if let Some(season) = parsed.season {
+            return Some(SeasonPackEvidence::FolderName {
+                seasons: season.to_string(),
+            });
+        }
+    }
+
+    let mut episodes: Vec<(u32, u32)> = std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.file_name().into_string().ok())
+        .filter(|n| {
+            let lower = n.to_lowercase();
+            VIDEO_EXTENSIONS.iter().any(|ext| lower.ends_with(ext))
+        })
+        .filter_map(|n| {
+            let caps = EPISODE_TAG.captures(&n)?;
+            Some((caps[1].parse().ok()?, caps[2].parse().ok()?))
+        })
+        .collect();
+    episodes.sort_unstable();
+    episodes.dedup();
+
+    (episodes.len() >= MIN_EPISODE_FILES).then_some(SeasonPackEvidence::EpisodeFiles {
+        count: episodes.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_detects_season_pack_folder_names() {
+        let tmp = tempfile::tempdir().unwrap();
+        for name in [
+            "Show.Name.S01.1080p.COMPLETE",
+            "Show Name Season 2",
+            "The.Movie.2019.1080p",
+        ] {
+            fs::create_dir(tmp.path().join(name)).unwrap();
+        }
+
+        assert!(matches!(
+            detect_season_pack(&tmp.path().join("Show.Name.S01.1080p.COMPLETE")),
+            Some(SeasonPackEvidence::FolderName { .. })
+        ));
+        assert!(detect_season_pack(&tmp.path().join("Show Name Season 2")).is_some());
+        assert_eq!(
+            detect_season_pack(&tmp.path().join("The.Movie.2019.1080p")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_detects_folder_of_episode_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("some.download");
+        fs::create_dir(&dir).unwrap();
+        fs::write(dir.join("show.s01e01.mkv"), b"x").unwrap();
+        assert_eq!(detect_season_pack(&dir), None);
+
+        fs::write(dir.join("show.s01e02.mkv"), b"x").unwrap();
+        fs::write(dir.join("show.s01e02.en.srt"), b"x").unwrap();
+        assert_eq!(
+            detect_season_pack(&dir),
+            Some(SeasonPackEvidence::EpisodeFiles { count: 2 })
+        );
+    }
+}
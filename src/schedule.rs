@@ -0,0 +1,213 @@
+//! Cron-style schedules for the watch daemon (`watch.schedule`).
+//!
+//! A schedule is the five fields of a crontab line — minute, hour, day of
+//! month, month, day of week — each `*`, a number, a range `1-5`, a list
+//! `1,15`, or a step `*/15` or `0-30/10`. Sunday is 0 or 7. As in cron, when
+//! both day fields are restricted a day matching either one fires. Times
+//! are local; a time skipped by a daylight-saving change does not fire.
+
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use chrono::{
+    DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike,
+};
+
+/// How far ahead [`Schedule::next_after`] looks; a schedule that never
+/// fires (`0 0 31 2 *`) gives up after it.
+const HORIZON_DAYS: i64 = 5 * 366;
+
+/// One field: the values it allows, as a bit set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Field {
+    allowed: u64,
+    /// Written from `*` (`*` or `*/2`), which cron takes as placing no
+    /// restriction on the day.
+    any: bool,
+}
+
+impl Field {
+    fn parse(text: &str, min: u32, max: u32) -> Result<Self> {
+        let mut allowed = 0u64;
+        for part in text.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => {
+                    let step: u32 = step
+                        .parse()
+                        .ok()
+                        .filter(|s| *s > 0)
+                        .with_context(|| format!("invalid step {step:?}"))?;
+                    (range, step)
+                }
+                None => (part, 1),
+            };
+            let (low, high) = match range {
+                "*" => (min, max),
+                _ => match range.split_once('-') {
+                    Some((low, high)) => (number(low)?, number(high)?),
+                    // `5/15` runs from 5 to the end, as in cron.
+                    None if step > 1 => (number(range)?, max),
+                    None => (number(range)?, number(range)?),
+                },
+            };
+            if low < min || high > max || low > high {
+                bail!("{part:?} is outside {min}-{max}");
+            }
+            for value in (low..=high).step_by(step as usize) {
+                allowed |= 1 << value;
+            }
+        }
+        Ok(Self {
+            allowed,
+            any: text.starts_with('*'),
+        })
+    }
+
+    fn allows(self, value: u32) -> bool {
+        self.allowed & (1 << value) != 0
+    }
+}
+
+fn number(text: &str) -> Result<u32> {
+    text.parse()
+        .with_context(|| format!("{text:?} is not a number"))
+}
+
+/// A parsed five-field cron expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    text: String,
+    minute: Field,
+    hour: Field,
+    day: Field,
+    month: Field,
+    weekday: Field,
+}
+
+impl FromStr for Schedule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            bail!("Invalid schedule {s:?}: expected 5 fields (minute hour day month weekday)");
+        };
+        let field = |text: &str, name: &str, min, max| {
+            Field::parse(text, min, max)
+                .with_context(|| format!("Invalid {name} in schedule {s:?}"))
+        };
+        let mut weekday = field(weekday, "day of week", 0, 7)?;
+        // Sunday is both 0 and 7.
+        if weekday.allows(7) {
+            weekday.allowed |= 1;
+        }
+        Ok(Self {
+            text: fields.join(" "),
+            minute: field(minute, "minute", 0, 59)?,
+            hour: field(hour, "hour", 0, 23)?,
+            day: field(day, "day of month", 1, 31)?,
+            month: field(month, "month", 1, 12)?,
+            weekday,
+        })
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl Schedule {
+    fn day_matches(&self, date: NaiveDate) -> bool {
+        let day = self.day.allows(date.day());
+        let weekday = self.weekday.allows(date.weekday().num_days_from_sunday());
+        match (self.day.any, self.weekday.any) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+
+    /// The first local time after `after`, to the minute, that the
+    /// schedule fires at; `None` if it never fires.
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let start = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let end = start + Duration::days(HORIZON_DAYS);
+        let mut at = start;
+        while at < end {
+            let date = at.date();
+            if !self.month.allows(date.month()) || !self.day_matches(date) {
+                at = date.succ_opt()?.and_time(NaiveTime::MIN);
+            } else if !self.hour.allows(at.hour()) {
+                at = date.and_hms_opt(at.hour(), 0, 0)? + Duration::hours(1);
+            } else if !self.minute.allows(at.minute()) {
+                at += Duration::minutes(1);
+            } else {
+                return Some(at);
+            }
+        }
+        None
+    }
+
+    /// The next firing after `now` that exists on the local clock.
+    pub fn next_local(&self, now: DateTime<Local>) -> Option<DateTime<Local>> {
+        let mut after = now.naive_local();
+        loop {
+            let next = self.next_after(after)?;
+            if let Some(at) = Local.from_local_datetime(&next).earliest() {
+                return Some(at);
+            }
+            after = next;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_next_after() {
+        let nightly: Schedule = "0 3 * * *".parse().unwrap();
+        assert_eq!(
+            nightly.next_after(at("2024-05-01 02:59")),
+            Some(at("2024-05-01 03:00"))
+        );
+        assert_eq!(
+            nightly.next_after(at("2024-05-01 03:00")),
+            Some(at("2024-05-02 03:00"))
+        );
+
+        // Every 15 minutes during working hours, on weekdays.
+        let busy: Schedule = "*/15 9-17 * * 1-5".parse().unwrap();
+        // 2024-05-03 is a Friday.
+        assert_eq!(
+            busy.next_after(at("2024-05-03 17:50")),
+            Some(at("2024-05-06 09:00"))
+        );
+
+        // Either day field fires when both are restricted; 7 is Sunday.
+        let either: Schedule = "30 4 1 * 7".parse().unwrap();
+        assert_eq!(
+            either.next_after(at("2024-05-01 05:00")),
+            Some(at("2024-05-05 04:30"))
+        );
+
+        assert_eq!(
+            "0 0 31 2 *"
+                .parse::<Schedule>()
+                .unwrap()
+                .next_after(at("2024-01-01 00:00")),
+            None
+        );
+        assert!("0 3 * *".parse::<Schedule>().is_err());
+        assert!("60 3 * * *".parse::<Schedule>().is_err());
+        assert!("*/0 * * * *".parse::<Schedule>().is_err());
+        assert_eq!(nightly.to_string(), "0 3 * * *");
+    }
+}
@@ -0,0 +1,310 @@
+//! JSON Schemas for the files the organizer writes for other tools.
+//!
+//! Saved plans, run reports (undo manifests), parse traces, and TMDb
+//! recordings are read back by later runs, by later versions of this
+//! crate, and by scripts around it. Each format has a versioned schema
+//! shipped in the binary (`plex-org schema print <artifact>`), and files
+//! are checked against it when read, so a hand-edited plan or a truncated
+//! report fails with the field at fault rather than halfway through an
+//! apply.
+//!
+//! The checker covers the keywords the schemas use: `type`, `const`,
+//! `enum`, `minimum`, `maximum`, `required`, `properties`, `items`, and
+//! local `$ref`s. A schema's `version` runs from 1 to the current version,
+//! since every earlier version still reads.
+
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::models::{SavedPlan, UndoManifest};
+use crate::parse_trace;
+use crate::providers::cassette;
+
+/// Violations listed in an error before the rest are counted.
+const MAX_REPORTED: usize = 5;
+
+/// A file format with a published schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Artifact {
+    /// `plan --plan-out`, read by `organize --apply-plan`.
+    Plan,
+    /// The undo manifest of an `organize --execute` run.
+    RunReport,
+    /// `debug-parse --json`.
+    Explain,
+    /// `--tmdb-record`, read by `--tmdb-replay`.
+    Repro,
+}
+
+impl Artifact {
+    pub const ALL: [Artifact; 4] = [Self::Plan, Self::RunReport, Self::Explain, Self::Repro];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Plan => "plan",
+            Self::RunReport => "run-report",
+            Self::Explain => "explain",
+            Self::Repro => "repro",
+        }
+    }
+
+    /// Current format version, written into every file as `version`.
+    pub fn version(self) -> u32 {
+        match self {
+            Self::Plan => SavedPlan::VERSION,
+            Self::RunReport => UndoManifest::VERSION,
+            Self::Explain => parse_trace::VERSION,
+            Self::Repro => cassette::VERSION,
+        }
+    }
+
+    /// The JSON Schema (draft 2020-12) of the current version.
+    pub fn schema(self) -> &'static str {
+        match self {
+            Self::Plan => include_str!("plan.json"),
+            Self::RunReport => include_str!("run-report.json"),
+            Self::Explain => include_str!("explain.json"),
+            Self::Repro => include_str!("repro.json"),
+        }
+    }
+}
+
+impl fmt::Display for Artifact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Artifact {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|a| a.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<_> = Self::ALL.iter().map(|a| a.name()).collect();
+                anyhow!("Unknown artifact {s:?} (expected {})", names.join(", "))
+            })
+    }
+}
+
+/// Parse `content` as `artifact`: a version newer than this build reads is
+/// refused, then the document is checked against the schema.
+pub fn from_str<T: DeserializeOwned>(artifact: Artifact, content: &str) -> Result<T> {
+    let value: Value = serde_json::from_str(content).context("Not valid JSON")?;
+    if let Some(version) = value.get("version").and_then(Value::as_u64) {
+        if version > u64::from(artifact.version()) {
+            bail!(
+                "{artifact} version {version} is newer than supported version {}",
+                artifact.version()
+            );
+        }
+    }
+    validate(artifact, &value)?;
+    serde_json::from_value(value).with_context(|| format!("Unexpected {artifact} contents"))
+}
+
+/// Check `value` against the schema of `artifact`.
+pub fn validate(artifact: Artifact, value: &Value) -> Result<()> {
+    let schema: Value = serde_json::from_str(artifact.schema()).expect("shipped schemas are JSON");
+    let mut errors = Vec::new();
+    check(&schema, &schema, value, "", &mut errors);
+    if errors.is_empty() {
+        return Ok(());
+    }
+    let more = errors.len().saturating_sub(MAX_REPORTED);
+    errors.truncate(MAX_REPORTED);
+    if more > 0 {
+        errors.push(format!("and {more} more"));
+    }
+    bail!(
+        "Does not match the {artifact} schema: {}",
+        errors.join("; ")
+    )
+}
+
+fn check(schema: &Value, root: &Value, value: &Value, at: &str, errors: &mut Vec<String>) {
+    let here = if at.is_empty() { "/" } else { at };
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        match reference.strip_prefix('#').and_then(|p| root.pointer(p)) {
+            Some(target) => check(target, root, value, at, errors),
+            None => errors.push(format!("{here}: unresolved {reference}")),
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if value != expected {
+            errors.push(format!("{here}: expected {expected}, found {value}"));
+        }
+    }
+    if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+        if !options.contains(value) {
+            errors.push(format!(
+                "{here}: {value} is not one of {}",
+                Value::from(options.clone())
+            ));
+        }
+    }
+    if let Some(types) = schema.get("type") {
+        let names: Vec<&str> = match types {
+            Value::String(name) => vec![name],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !names.iter().any(|name| is_type(value, name)) {
+            errors.push(format!(
+                "{here}: expected {}, found {}",
+                names.join(" or "),
+                type_of(value)
+            ));
+            return;
+        }
+    }
+    if let (Some(minimum), Some(n)) = (
+        schema.get("minimum").and_then(Value::as_f64),
+        value.as_f64(),
+    ) {
+        if n < minimum {
+            errors.push(format!("{here}: {n} is below {minimum}"));
+        }
+    }
+    if let (Some(maximum), Some(n)) = (
+        schema.get("maximum").and_then(Value::as_f64),
+        value.as_f64(),
+    ) {
+        if n > maximum {
+            errors.push(format!("{here}: {n} is above {maximum}"));
+        }
+    }
+    if let Some(object) = value.as_object() {
+        for key in schema
+            .get("required")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            if let Some(key) = key.as_str().filter(|k| !object.contains_key(*k)) {
+                errors.push(format!("{here}: missing {key:?}"));
+            }
+        }
+        if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+            for (key, field) in object {
+                if let Some(property) = properties.get(key) {
+                    check(property, root, field, &format!("{at}/{key}"), errors);
+                }
+            }
+        }
+    }
+    if let (Some(items), Some(array)) = (schema.get("items"), value.as_array()) {
+        for (i, item) in array.iter().enumerate() {
+            check(items, root, item, &format!("{at}/{i}"), errors);
+        }
+    }
+}
+
+fn is_type(value: &Value, name: &str) -> bool {
+    match name {
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        other => type_of(value) == other,
+    }
+}
+
+fn type_of(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{OrganizeAction, UndoEntry};
+
+    #[test]
+    fn test_written_artifacts_match_their_schemas() {
+        for artifact in Artifact::ALL {
+            let schema: Value = serde_json::from_str(artifact.schema()).unwrap();
+            let version = &schema["properties"]["version"];
+            assert_eq!(version["minimum"], 1);
+            assert_eq!(version["maximum"], artifact.version());
+            assert_eq!(artifact.name().parse::<Artifact>().unwrap(), artifact);
+        }
+
+        let plan = SavedPlan::new(
+            "/media".into(),
+            vec![OrganizeAction {
+                source: "/in/Heat.1995.mkv".into(),
+                destination: "/media/Movies/Heat (1995)/Heat (1995).mkv".into(),
+                title: "Heat".to_string(),
+                year: Some(1995),
+                tmdb_id: Some(949),
+                ..Default::default()
+            }],
+        );
+        let json = serde_json::to_string(&plan).unwrap();
+        let read: SavedPlan = from_str(Artifact::Plan, &json).unwrap();
+        assert_eq!(read.actions[0].title, "Heat");
+
+        let manifest = UndoManifest {
+            entries: vec![UndoEntry {
+                source: "/in/a.mkv".to_string(),
+                destination: "/media/a.mkv".to_string(),
+                strategy: "move".to_string(),
+                title: "A".to_string(),
+                media_type: "movie".to_string(),
+                verified: Some(true),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&manifest).unwrap();
+        validate(Artifact::RunReport, &json).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_and_newer_files_are_refused() {
+        let plan = r#"{"version": 1, "created_at": "", "dest_root": "/media",
+            "actions": [{"source": "a", "destination": "b", "strategy": "move",
+                         "media_type": "film", "title": 7, "confidence": 1}]}"#;
+        let error = from_str::<SavedPlan>(Artifact::Plan, plan)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("/actions/0/media_type"), "{error}");
+        assert!(
+            error.contains("/actions/0/title: expected string"),
+            "{error}"
+        );
+
+        let newer = r#"{"version": 2, "created_at": "", "dest_root": "", "actions": []}"#;
+        let error = from_str::<SavedPlan>(Artifact::Plan, newer)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("newer than supported"), "{error}");
+        let mut value: Value = serde_json::from_str(newer).unwrap();
+        let error = validate(Artifact::Plan, &value).unwrap_err().to_string();
+        assert!(error.contains("/version: 2 is above 1"), "{error}");
+        value["version"] = 0.into();
+        let error = validate(Artifact::Plan, &value).unwrap_err().to_string();
+        assert!(error.contains("/version: 0 is below 1"), "{error}");
+
+        let missing = r#"{"version": 1, "interactions": [{}]}"#;
+        let error = validate(Artifact::Repro, &serde_json::from_str(missing).unwrap())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("/: missing \"created_at\""), "{error}");
+        assert!(
+            error.contains("/interactions/0: missing \"request\""),
+            "{error}"
+        );
+    }
+}
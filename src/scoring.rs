@@ -0,0 +1,201 @@
+//! Confidence scoring of parses and matches.
+//!
+//! A parse earns points for each field it fills (title, year, type, and the
+//! season and episode of a show or the year of a movie), up to a cap that
+//! leaves room above it for a database match. A TMDb match replaces the
+//! parse confidence when it scores higher. The points, the cap, a penalty
+//! for release tags left in a title, the weight of a TMDb match, and an
+//! offset per title strategy are all [`ScoringWeights`], set under
+//! `[parsing.scoring]`, so skip and review rates can be tuned without code
+//! changes.
+//!
+//! A [`ConfidenceScorer`] for the configured weights travels with the
+//! parse options (`parser::ParseOptions`) and the enricher, so each config
+//! scores with its own.
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::MediaType;
+use crate::title_strategy::{self, TitleStrategy};
+
+/// Points and adjustments of confidence scoring.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScoringWeights {
+    /// Points for a non-empty title.
+    pub title: f64,
+    /// Points for a year.
+    pub year: f64,
+    /// Points for knowing movie from show.
+    pub media_type: f64,
+    /// Points for the season of a show.
+    pub season: f64,
+    /// Points for the episode of a show.
+    pub episode: f64,
+    /// Further points for the year of a movie.
+    pub movie_year: f64,
+    /// Highest confidence a parse alone reaches.
+    pub parse_cap: f64,
+    /// Points lost per title word that is a release tag (`1080p`, `x264`).
+    pub title_token_penalty: f64,
+    /// Factor applied to a TMDb match score before it replaces the parse
+    /// confidence.
+    pub tmdb_match: f64,
+    /// Points added to the parse confidence by the title's strategy.
+    pub calibration: StrategyCalibration,
+}
+
+/// Confidence offset for each title strategy (see `title_strategy`).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StrategyCalibration {
+    pub hunch: f64,
+    pub before_year: f64,
+}
+
+impl Default for ScoringWeights {
+    fn default() -> Self {
+        Self {
+            title: 30.0,
+            year: 20.0,
+            media_type: 15.0,
+            season: 15.0,
+            episode: 15.0,
+            movie_year: 15.0,
+            parse_cap: 85.0,
+            title_token_penalty: 0.0,
+            tmdb_match: 1.0,
+            calibration: StrategyCalibration::default(),
+        }
+    }
+}
+
+/// What a parse found, as far as confidence goes.
+#[derive(Debug, Clone, Copy)]
+pub struct ParseSignals<'a> {
+    pub title: &'a str,
+    pub year: Option<i32>,
+    pub media_type: MediaType,
+    pub season: Option<i32>,
+    pub episode: Option<i32>,
+    pub strategy: TitleStrategy,
+}
+
+/// Scores confidence with a set of weights.
+#[derive(Debug, Clone, Default)]
+pub struct ConfidenceScorer {
+    weights: ScoringWeights,
+}
+
+impl ConfidenceScorer {
+    pub fn new(weights: ScoringWeights) -> Self {
+        Self { weights }
+    }
+
+    pub fn weights(&self) -> &ScoringWeights {
+        &self.weights
+    }
+
+    /// Confidence (0 to `parse_cap`) in a parse from the fields it filled.
+    pub fn parse_confidence(&self, signals: &ParseSignals) -> f64 {
+        let w = &self.weights;
+        let is_tv = signals.media_type == MediaType::Tv;
+        let mut score = 0.0;
+        if !signals.title.is_empty() {
+            score += w.title;
+        }
+        if signals.year.is_some() {
+            score += w.year;
+        }
+        if signals.media_type != MediaType::Unknown {
+            score += w.media_type;
+        }
+        if is_tv && signals.season.is_some() {
+            score += w.season;
+        }
+        if is_tv && signals.episode.is_some() {
+            score += w.episode;
+        }
+        if signals.media_type == MediaType::Movie && signals.year.is_some() {
+            score += w.movie_year;
+        }
+        let tags = signals
+            .title
+            .split_whitespace()
+            .filter(|word| title_strategy::is_technical(word))
+            .count();
+        score -= w.title_token_penalty * tags as f64;
+        score += match signals.strategy {
+            TitleStrategy::Hunch => w.calibration.hunch,
+            TitleStrategy::BeforeYear => w.calibration.before_year,
+        };
+        score.clamp(0.0, w.parse_cap.max(0.0))
+    }
+
+    /// Confidence after a TMDb match scoring `score`, given the parse
+    /// confidence `parsed`: the weighted match score when that is higher.
+    pub fn match_confidence(&self, parsed: f64, score: f64) -> f64 {
+        (score * self.weights.tmdb_match)
+            .clamp(0.0, 100.0)
+            .max(parsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn movie(title: &str, strategy: TitleStrategy) -> ParseSignals<'_> {
+        ParseSignals {
+            title,
+            year: Some(1995),
+            media_type: MediaType::Movie,
+            season: None,
+            episode: None,
+            strategy,
+        }
+    }
+
+    #[test]
+    fn test_default_weights_cap_parse_confidence() {
+        let scorer = ConfidenceScorer::default();
+        assert_eq!(
+            scorer.parse_confidence(&movie("Heat", TitleStrategy::Hunch)),
+            85.0
+        );
+        let yearless = ParseSignals {
+            year: None,
+            ..movie("Heat", TitleStrategy::Hunch)
+        };
+        assert_eq!(scorer.parse_confidence(&yearless), 45.0);
+        assert_eq!(scorer.match_confidence(85.0, 92.0), 92.0);
+        assert_eq!(scorer.match_confidence(85.0, 60.0), 85.0);
+    }
+
+    #[test]
+    fn test_tuned_weights() {
+        let scorer = ConfidenceScorer::new(ScoringWeights {
+            movie_year: 0.0,
+            title_token_penalty: 10.0,
+            tmdb_match: 0.5,
+            calibration: StrategyCalibration {
+                hunch: 0.0,
+                before_year: -15.0,
+            },
+            ..Default::default()
+        });
+        assert_eq!(
+            scorer.parse_confidence(&movie("Heat", TitleStrategy::Hunch)),
+            65.0
+        );
+        assert_eq!(
+            scorer.parse_confidence(&movie("Heat", TitleStrategy::BeforeYear)),
+            50.0
+        );
+        assert_eq!(
+            scorer.parse_confidence(&movie("Heat 1080p x264", TitleStrategy::Hunch)),
+            45.0
+        );
+        assert_eq!(scorer.match_confidence(40.0, 100.0), 50.0);
+    }
+}
@@ -0,0 +1,294 @@
+//! Long-running operation: the watch loop, clean shutdown, and service units.
+//!
+//! `plex-org watch` re-runs the organize pipeline on an interval. A stop
+//! request (SIGTERM from systemd, or Ctrl-C) only sets a flag: the pass in
+//! flight finishes its moves and writes its undo manifest before the loop
+//! exits, so stopping the service never leaves a half-recorded run.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use chrono::Local;
+use tracing::{info, warn};
+
+use crate::schedule::Schedule;
+
+/// Set by the signal handler; read by every installed [`Shutdown`].
+static SIGNALLED: AtomicBool = AtomicBool::new(false);
+
+/// How often a sleeping loop checks for a stop request.
+const POLL: Duration = Duration::from_millis(250);
+
+/// A cooperative stop request shared between the loop and whoever stops it.
+#[derive(Debug, Clone, Default)]
+pub struct Shutdown {
+    flag: Arc<AtomicBool>,
+    signals: bool,
+}
+
+impl Shutdown {
+    /// A handle that is only stopped through [`Shutdown::request`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A handle that is also stopped by SIGTERM and SIGINT.
+    ///
+    /// On platforms without POSIX signals the process keeps the default
+    /// behavior (Ctrl-C terminates it), so only [`Shutdown::request`] drains.
+    pub fn install() -> Self {
+        #[cfg(unix)]
+        unsafe {
+            let handler = on_signal as extern "C" fn(libc::c_int);
+            libc::signal(libc::SIGTERM, handler as libc::sighandler_t);
+            libc::signal(libc::SIGINT, handler as libc::sighandler_t);
+        }
+        Self {
+            flag: Arc::default(),
+            signals: cfg!(unix),
+        }
+    }
+
+    /// Ask the loop to stop after the current pass.
+    pub fn request(&self) {
+        self.flag.store(true, Ordering::SeqCst);
+    }
+
+    /// Has a stop been requested?
+    pub fn is_requested(&self) -> bool {
+        self.flag.load(Ordering::SeqCst) || (self.signals && SIGNALLED.load(Ordering::SeqCst))
+    }
+
+    /// Sleep for `duration`, waking early on a stop request.
+    ///
+    /// Returns `false` if the sleep was cut short.
+    pub fn sleep(&self, duration: Duration) -> bool {
+        let mut remaining = duration;
+        while !remaining.is_zero() {
+            if self.is_requested() {
+                return false;
+            }
+            let step = remaining.min(POLL);
+            thread::sleep(step);
+            remaining -= step;
+        }
+        !self.is_requested()
+    }
+}
+
+#[cfg(unix)]
+extern "C" fn on_signal(_: libc::c_int) {
+    SIGNALLED.store(true, Ordering::SeqCst);
+}
+
+/// Run `pass` every `interval` until `shutdown` is requested.
+///
+/// A failing pass is logged and retried on the next tick; a stop request
+/// is only honored between passes. Returns the number of passes run.
+pub fn run_loop<F>(interval: Duration, shutdown: &Shutdown, mut pass: F) -> usize
+where
+    F: FnMut() -> Result<()>,
+{
+    let mut passes = 0;
+    while !shutdown.is_requested() {
+        if let Err(e) = pass() {
+            warn!("watch pass failed: {e:#}");
+        }
+        passes += 1;
+        if !shutdown.sleep(interval) {
+            break;
+        }
+    }
+    info!("stopping after {passes} passes");
+    passes
+}
+
+/// Run `job` each time `schedule` fires until `shutdown` is requested.
+///
+/// Runs never overlap: the next fire time is worked out when a run ends,
+/// so times that pass during a long run are skipped (and logged), not
+/// queued. A failing run is logged like a failing pass. Returns the number
+/// of runs.
+pub fn run_schedule<F>(schedule: &Schedule, shutdown: &Shutdown, mut job: F) -> usize
+where
+    F: FnMut() -> Result<()>,
+{
+    let mut runs = 0;
+    let mut due = schedule.next_local(Local::now());
+    while let Some(at) = due {
+        info!("next scheduled run at {at}");
+        let wait = (at - Local::now()).to_std().unwrap_or(Duration::ZERO);
+        if !shutdown.sleep(wait) {
+            break;
+        }
+        if let Err(e) = job() {
+            warn!("scheduled run failed: {e:#}");
+        }
+        runs += 1;
+        due = schedule.next_local(Local::now());
+        if let Some(missed) = schedule.next_local(at).filter(|m| Some(*m) != due) {
+            warn!("scheduled run from {at} was still going at {missed}, which was skipped");
+        }
+    }
+    runs
+}
+
+/// Has the file gone unmodified for at least `settle`?
+///
+/// Downloads still being written are left for a later pass. A file whose
+/// modification time cannot be read counts as settled.
+pub fn is_settled(path: &Path, settle: Duration, now: SystemTime) -> bool {
+    match std::fs::metadata(path).and_then(|m| m.modified()) {
+        Ok(modified) => now.duration_since(modified).unwrap_or(Duration::ZERO) >= settle,
+        Err(_) => true,
+    }
+}
+
+/// A systemd unit that runs `plex-org watch`.
+#[derive(Debug, Clone)]
+pub struct SystemdUnit {
+    /// Absolute path of the `plex-org` binary.
+    pub exe: PathBuf,
+    /// Config file passed with `--config`, if any.
+    pub config: Option<PathBuf>,
+    /// Account the service runs as; `None` for a per-user unit.
+    pub user: Option<String>,
+    /// Arguments after `watch`.
+    pub watch_args: Vec<String>,
+}
+
+impl SystemdUnit {
+    /// Render the unit file.
+    ///
+    /// SIGTERM is the stop signal and the stop timeout is generous, so a
+    /// pass copying large files can finish before systemd escalates.
+    pub fn render(&self) -> String {
+        let mut command = vec![quote(&self.exe.to_string_lossy())];
+        if let Some(config) = &self.config {
+            command.push("--config".to_string());
+            command.push(quote(&config.to_string_lossy()));
+        }
+        command.push("watch".to_string());
+        command.extend(self.watch_args.iter().map(|a| quote(a)));
+
+        let mut unit = String::from(
+            "[Unit]\n\
+             Description=Plex Media Organizer (watch)\n\
+             After=network-online.target local-fs.target\n\
+             Wants=network-online.target\n\n\
+             [Service]\n\
+             Type=simple\n",
+        );
+        if let Some(user) = &self.user {
+            unit.push_str(&format!("User={user}\n"));
+        }
+        unit.push_str(&format!("ExecStart={}\n", command.join(" ")));
+        unit.push_str(
+            "KillSignal=SIGTERM\n\
+             TimeoutStopSec=600\n\
+             Restart=on-failure\n\
+             RestartSec=30\n\n\
+             [Install]\n",
+        );
+        let target = if self.user.is_some() {
+            "multi-user.target"
+        } else {
+            "default.target"
+        };
+        unit.push_str(&format!("WantedBy={target}\n"));
+        unit
+    }
+}
+
+/// Quote an `ExecStart` word when it contains whitespace or quotes.
+fn quote(word: &str) -> String {
+    if word.is_empty() || word.contains(|c: char| c.is_whitespace() || c == '"' || c == '\\') {
+        format!("\"{}\"", word.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        word.to_string()
+    }
+}
+
+/// `schtasks` arguments that start `plex-org watch` at boot on Windows.
+///
+/// plex-org has no Service Control Manager handler, so on Windows it is
+/// registered as a Task Scheduler task that starts with the machine, not as
+/// a service: `sc` and the Services console do not list it, and ending the
+/// task (`schtasks /End`) terminates the process without letting the
+/// current pass finish.
+pub fn windows_task_args(
+    name: &str,
+    exe: &Path,
+    config: Option<&Path>,
+    user: Option<&str>,
+    watch_args: &[String],
+) -> Vec<String> {
+    let mut command = format!("\"{}\"", exe.display());
+    if let Some(config) = config {
+        command.push_str(&format!(" --config \"{}\"", config.display()));
+    }
+    command.push_str(" watch");
+    for arg in watch_args {
+        if arg.contains(' ') {
+            command.push_str(&format!(" \"{arg}\""));
+        } else {
+            command.push_str(&format!(" {arg}"));
+        }
+    }
+    vec![
+        "/Create".to_string(),
+        "/F".to_string(),
+        "/TN".to_string(),
+        name.to_string(),
+        "/SC".to_string(),
+        "ONSTART".to_string(),
+        "/RU".to_string(),
+        user.unwrap_or("SYSTEM").to_string(),
+        "/TR".to_string(),
+        command,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_loop_stops_between_passes() {
+        let shutdown = Shutdown::new();
+        let mut seen = 0;
+        let passes = run_loop(Duration::ZERO, &shutdown, || {
+            seen += 1;
+            if seen == 2 {
+                anyhow::bail!("transient");
+            }
+            if seen == 3 {
+                shutdown.request();
+            }
+            Ok(())
+        });
+        // The failing pass did not end the loop; the stop waited for pass 3.
+        assert_eq!(passes, 3);
+    }
+
+    #[test]
+    fn test_systemd_unit() {
+        let unit = SystemdUnit {
+            exe: PathBuf::from("/usr/local/bin/plex-org"),
+            config: Some(PathBuf::from("/etc/plex-org/My Config.toml")),
+            user: Some("plex".to_string()),
+            watch_args: vec!["--execute".to_string()],
+        };
+        let text = unit.render();
+        assert!(text.contains("User=plex\n"));
+        assert!(text.contains(
+            "ExecStart=/usr/local/bin/plex-org --config \"/etc/plex-org/My Config.toml\" watch --execute\n"
+        ));
+        assert!(text.contains("KillSignal=SIGTERM\n"));
+        assert!(text.ends_with("WantedBy=multi-user.target\n"));
+    }
+}
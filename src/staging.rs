@@ -0,0 +1,247 @@
+//! Two-stage organization: dated staging buckets and later promotion.
+//!
+//! With staging enabled, organize writes into
+//! `<staging_dir>/YYYY-MM/<Movies|TV Shows|Music>/...` using the normal Plex
+//! layout. Once an item has sat in staging long enough, `promote` moves it
+//! into the final library root, keeping its relative path unchanged.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, Datelike, TimeZone};
+use tracing::debug;
+use walkdir::WalkDir;
+
+use crate::config::AppConfig;
+use crate::models::{MediaType, OrganizeAction};
+use crate::scanner::OUTPUT_MARKER;
+
+/// Staging bucket for the given moment: `<staging_dir>/YYYY-MM`.
+pub fn bucket_dir<Tz: TimeZone>(staging_dir: &Path, now: &DateTime<Tz>) -> PathBuf {
+    staging_dir.join(format!("{:04}-{:02}", now.year(), now.month()))
+}
+
+/// A top-level staged item, e.g. `2026-03/Movies/Heat (1995)/`.
+#[derive(Debug, Clone)]
+pub struct StagedItem {
+    /// Staged item folder.
+    pub folder: PathBuf,
+    /// Path of the folder relative to its bucket (`Movies/Heat (1995)`).
+    pub relative: PathBuf,
+    pub media_type: MediaType,
+    /// When the item entered staging (folder modification time).
+    pub staged_at: SystemTime,
+}
+
+fn is_bucket_name(name: &str) -> bool {
+    let bytes = name.as_bytes();
+    bytes.len() == 7
+        && bytes[4] == b'-'
+        && bytes[..4].iter().all(u8::is_ascii_digit)
+        && bytes[5..].iter().all(u8::is_ascii_digit)
+}
+
+fn sorted_subdirs(dir: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+/// List every staged item across all buckets.
+pub fn find_staged(staging_dir: &Path, config: &AppConfig) -> Result<Vec<StagedItem>> {
+    if !staging_dir.is_dir() {
+        bail!(
+            "Staging directory does not exist: {}",
+            staging_dir.display()
+        );
+    }
+
+    let sections = [
+        (&config.organize.movies_dir, MediaType::Movie),
+        (&config.organize.tv_dir, MediaType::Tv),
+        (&config.organize.music_dir, MediaType::Music),
+    ];
+
+    let mut items = Vec::new();
+    for bucket in sorted_subdirs(staging_dir) {
+        let bucket_name = bucket.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        if !is_bucket_name(bucket_name) {
+            debug!("ignoring non-bucket dir in staging: {}", bucket.display());
+            continue;
+        }
+        for (section, media_type) in &sections {
+            for folder in sorted_subdirs(&bucket.join(section)) {
+                let staged_at = fs::metadata(&folder)?.modified()?;
+                let relative = folder.strip_prefix(&bucket)?.to_path_buf();
+                items.push(StagedItem {
+                    folder,
+                    relative,
+                    media_type: *media_type,
+                    staged_at,
+                });
+            }
+        }
+    }
+    Ok(items)
+}
+
+/// Plan moves for staged items older than `min_age` into `library_root`.
+///
+/// Every file under a ripe item keeps its path relative to the bucket, so
+/// `2026-03/Movies/Heat (1995)/Heat (1995).mkv` lands at
+/// `<library_root>/Movies/Heat (1995)/Heat (1995).mkv`.
+pub fn plan_promotion(
+    staging_dir: &Path,
+    library_root: &Path,
+    min_age: Duration,
+    now: SystemTime,
+    config: &AppConfig,
+) -> Result<Vec<OrganizeAction>> {
+    let mut actions = Vec::new();
+    for item in find_staged(staging_dir, config)? {
+        let age = now.duration_since(item.staged_at).unwrap_or_default();
+        if age < min_age {
+            debug!("not yet ripe: {}", item.folder.display());
+            continue;
+        }
+        let title = item
+            .relative
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("")
+            .to_string();
+
+        for entry in WalkDir::new(&item.folder).sort_by_file_name() {
+            let entry = entry?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let inner = entry.path().strip_prefix(&item.folder)?;
+            actions.push(OrganizeAction {
+                source: entry.path().to_path_buf(),
+                destination: library_root.join(&item.relative).join(inner),
+                strategy: "move".to_string(),
+                media_type: item.media_type,
+                title: title.clone(),
+                confidence: 100.0,
+                ..Default::default()
+            });
+        }
+    }
+    Ok(actions)
+}
+
+/// Remove empty directories left behind in staging after a promotion.
+pub fn prune_empty_dirs(staging_dir: &Path) {
+    let mut dirs: Vec<PathBuf> = WalkDir::new(staging_dir)
+        .min_depth(1)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_dir())
+        .map(|e| e.into_path())
+        .collect();
+    dirs.sort_by_key(|d| std::cmp::Reverse(d.components().count()));
+    for dir in dirs {
+        // A bucket holding nothing but its output marker counts as empty.
+        let marker = dir.join(OUTPUT_MARKER);
+        if marker.is_file() && fs::read_dir(&dir).map_or(0, |d| d.count()) == 1 {
+            let _ = fs::remove_file(&marker);
+        }
+        // Fails (harmlessly) on non-empty directories.
+        let _ = fs::remove_dir(&dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stage_movie(staging: &Path, bucket: &str, folder: &str) -> PathBuf {
+        let dir = staging.join(bucket).join("Movies").join(folder);
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join(format!("{folder}.mkv"));
+        fs::write(&file, b"video").unwrap();
+        fs::write(dir.join(format!("{folder}.en.srt")), b"sub").unwrap();
+        file
+    }
+
+    #[test]
+    fn test_bucket_dir_uses_year_month() {
+        let now = chrono::Utc.with_ymd_and_hms(2026, 3, 9, 12, 0, 0).unwrap();
+        assert_eq!(
+            bucket_dir(Path::new("/staging"), &now),
+            PathBuf::from("/staging/2026-03")
+        );
+        assert!(is_bucket_name("2026-03"));
+        assert!(!is_bucket_name("Movies"));
+    }
+
+    #[test]
+    fn test_plan_promotion_keeps_relative_layout() {
+        let tmp = tempfile::tempdir().unwrap();
+        let staging = tmp.path().join("staging");
+        stage_movie(&staging, "2026-03", "Heat (1995)");
+        fs::create_dir_all(staging.join("not-a-bucket/Movies/X")).unwrap();
+
+        let later = SystemTime::now() + Duration::from_secs(8 * 86_400);
+        let actions = plan_promotion(
+            &staging,
+            Path::new("/library"),
+            Duration::from_secs(7 * 86_400),
+            later,
+            &AppConfig::default(),
+        )
+        .unwrap();
+
+        let dests: Vec<PathBuf> = actions.iter().map(|a| a.destination.clone()).collect();
+        assert_eq!(
+            dests,
+            vec![
+                PathBuf::from("/library/Movies/Heat (1995)/Heat (1995).en.srt"),
+                PathBuf::from("/library/Movies/Heat (1995)/Heat (1995).mkv"),
+            ]
+        );
+        assert!(actions.iter().all(|a| a.media_type == MediaType::Movie));
+    }
+
+    #[test]
+    fn test_plan_promotion_skips_fresh_items() {
+        let tmp = tempfile::tempdir().unwrap();
+        let staging = tmp.path().join("staging");
+        stage_movie(&staging, "2026-03", "Heat (1995)");
+
+        let actions = plan_promotion(
+            &staging,
+            Path::new("/library"),
+            Duration::from_secs(7 * 86_400),
+            SystemTime::now(),
+            &AppConfig::default(),
+        )
+        .unwrap();
+        assert!(actions.is_empty());
+    }
+
+    #[test]
+    fn test_prune_empty_dirs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let staging = tmp.path().join("staging");
+        let file = stage_movie(&staging, "2026-03", "Heat (1995)");
+        fs::remove_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(staging.join("2026-03").join(OUTPUT_MARKER), b"").unwrap();
+        fs::create_dir_all(staging.join("2026-04/Movies/Keep")).unwrap();
+        fs::write(staging.join("2026-04/Movies/Keep/keep.mkv"), b"x").unwrap();
+
+        prune_empty_dirs(&staging);
+        assert!(!staging.join("2026-03").exists());
+        assert!(staging.join("2026-04/Movies/Keep/keep.mkv").exists());
+        assert!(staging.exists());
+    }
+}
@@ -0,0 +1,80 @@
+//! Subtitle language suffix normalization.
+//!
+//! Release subtitles spell languages many ways (`en`, `eng`, `English`,
+//! `chs`). Plex wants an ISO 639-1 code followed by optional flags, e.g.
+//! `Movie (2020).en.forced.srt`.
+
+use crate::language::normalize_language;
+
+/// Normalize a subtitle flag (`forced`, `sdh`) to its Plex spelling.
+fn normalize_flag(token: &str) -> Option<&'static str> {
+    match token.to_lowercase().as_str() {
+        "forced" | "foreign" => Some("forced"),
+        "sdh" | "cc" | "hi" => Some("sdh"),
+        _ => None,
+    }
+}
+
+/// Derive a Plex language suffix from a subtitle file stem.
+///
+/// Only trailing tokens are considered, and at least one leading token must
+/// remain, so titles like `It` are never mistaken for a language.
+///
+/// ```text
+/// Heat.1995.1080p.eng.srt        → ".en"
+/// Heat 1995 Forced English.srt   → ".en.forced"
+/// Heat.1995.srt                  → ""
+/// ```
+pub fn language_suffix(stem: &str) -> String {
+    let tokens: Vec<&str> = stem
+        .split(['.', ' ', '_'])
+        .filter(|t| !t.is_empty())
+        .collect();
+
+    let mut language = None;
+    let mut flags: Vec<&str> = Vec::new();
+    for token in tokens.iter().skip(1).rev() {
+        if let Some(flag) = normalize_flag(token) {
+            if !flags.contains(&flag) {
+                flags.push(flag);
+            }
+        } else if let (None, Some(code)) = (language, normalize_language(token)) {
+            language = Some(code);
+        } else {
+            break;
+        }
+    }
+
+    let mut suffix = String::new();
+    if let Some(code) = language {
+        suffix.push('.');
+        suffix.push_str(code);
+    }
+    // Plex expects `forced` before `sdh`.
+    flags.sort();
+    for flag in flags {
+        suffix.push('.');
+        suffix.push_str(flag);
+    }
+    suffix
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_language_suffix_normalizes_codes_and_flags() {
+        assert_eq!(language_suffix("Heat.1995.1080p.eng"), ".en");
+        assert_eq!(language_suffix("Heat 1995 Forced English"), ".en.forced");
+        assert_eq!(language_suffix("Heat.1995.chs"), ".zh");
+        assert_eq!(language_suffix("Heat.1995.en.hi"), ".en.sdh");
+        assert_eq!(language_suffix("Heat.1995"), "");
+    }
+
+    #[test]
+    fn test_language_suffix_keeps_title_token() {
+        assert_eq!(language_suffix("It"), "");
+        assert_eq!(language_suffix("It.ita"), ".it");
+    }
+}
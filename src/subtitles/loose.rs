@@ -0,0 +1,300 @@
+//! Placing loose subtitle files next to already-organized media.
+//!
+//! Subtitle packs often arrive separately from the video. Each subtitle is
+//! parsed like a release name, matched against the library database by
+//! fuzzy title plus year (movies) or season/episode (TV), and planned into
+//! the matched file's folder with a normalized language suffix.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use tracing::debug;
+use walkdir::WalkDir;
+
+use super::language::language_suffix;
+use crate::database::FileRecord;
+use crate::models::OrganizeAction;
+use crate::parser::{self, ParseOptions};
+use crate::scanner;
+use crate::utils::title_similarity;
+
+/// Minimum title similarity for a subtitle to match a library file.
+const MATCH_THRESHOLD: f64 = 0.85;
+
+/// Why a loose subtitle was not placed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnplacedReason {
+    /// No library file matched the subtitle's title/year or episode.
+    NoMatch,
+    /// The matched folder already has a subtitle with that name.
+    DestinationExists,
+}
+
+impl fmt::Display for UnplacedReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoMatch => write!(f, "no matching library file"),
+            Self::DestinationExists => write!(f, "subtitle already exists"),
+        }
+    }
+}
+
+/// Planned placements plus the subtitles that could not be placed.
+#[derive(Debug, Default)]
+pub struct LooseSubtitlePlan {
+    pub actions: Vec<OrganizeAction>,
+    pub unplaced: Vec<(PathBuf, UnplacedReason)>,
+}
+
+/// Find subtitle files under `dir` that are not companions of a video.
+///
+/// Companions of a video (see [`find_companions`]) are left for
+/// `organize`, which already moves them with their video.
+///
+/// [`find_companions`]: super::find_companions
+pub fn find_loose_subtitles(dir: &Path) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+    for entry in WalkDir::new(dir).sort_by_file_name() {
+        let Ok(entry) = entry else { continue };
+        let path = entry.path();
+        if entry.file_type().is_file() && scanner::is_subtitle_path(path) && !is_companion(path) {
+            found.push(path.to_path_buf());
+        }
+    }
+    found
+}
+
+/// Whether a video in the subtitle's folder, or the two above it (for
+/// `Subs/` and `Subs/<video>/`), takes it as a companion.
+fn is_companion(subtitle: &Path) -> bool {
+    subtitle
+        .ancestors()
+        .skip(1)
+        .take(3)
+        .flat_map(|dir| std::fs::read_dir(dir).into_iter().flatten())
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| scanner::is_media_path(p))
+        .any(|video| {
+            super::find_companions(&video)
+                .iter()
+                .any(|c| c.path == subtitle)
+        })
+}
+
+/// Find the library file a subtitle belongs to.
+///
+/// Only video/audio records are candidates. Episodes must agree on
+/// season and episode; movies must agree on year when both sides have one.
+pub fn match_subtitle<'a>(
+    subtitle: &Path,
+    records: &'a [FileRecord],
+    options: &ParseOptions,
+) -> Option<&'a FileRecord> {
+    let name = subtitle.file_name()?.to_str()?;
+    let parsed = parser::parse_video(name, options);
+    if parsed.title.is_empty() {
+        return None;
+    }
+
+    let episode_tag = match (parsed.season, parsed.episode) {
+        (Some(s), Some(e)) => Some(format!("S{s:02}E{e:02}")),
+        _ => None,
+    };
+
+    let mut best: Option<(&FileRecord, f64)> = None;
+    for record in records {
+        if !scanner::is_media_path(Path::new(&record.destination)) {
+            continue;
+        }
+        let eligible = match &episode_tag {
+            Some(tag) => record.media_type == "tv" && record.destination.contains(tag.as_str()),
+            None => {
+                record.media_type == "movie"
+                    && !matches!((parsed.year, record.year), (Some(a), Some(b)) if a != b)
+            }
+        };
+        if !eligible {
+            continue;
+        }
+        let score = title_similarity(&parsed.title, &record.title);
+        // Later records win ties: they reflect the file's latest location.
+        if score >= MATCH_THRESHOLD && best.is_none_or(|(_, s)| score >= s) {
+            best = Some((record, score));
+        }
+    }
+
+    if let Some((record, score)) = best {
+        debug!(
+            "subtitle {name:?} → {} (score {score:.2})",
+            record.destination
+        );
+    }
+    best.map(|(record, _)| record)
+}
+
+/// Plan placement of loose subtitles next to their matched library files.
+///
+/// Records whose destination no longer exists are ignored.
+pub fn plan_loose_subtitles(
+    subtitles: &[PathBuf],
+    records: &[FileRecord],
+    strategy: &str,
+    options: &ParseOptions,
+) -> LooseSubtitlePlan {
+    let present: Vec<FileRecord> = records
+        .iter()
+        .filter(|r| Path::new(&r.destination).exists())
+        .cloned()
+        .collect();
+
+    let mut plan = LooseSubtitlePlan::default();
+    let mut used: HashSet<PathBuf> = HashSet::new();
+    for subtitle in subtitles {
+        let Some(record) = match_subtitle(subtitle, &present, options) else {
+            plan.unplaced
+                .push((subtitle.clone(), UnplacedReason::NoMatch));
+            continue;
+        };
+
+        let video = Path::new(&record.destination);
+        let video_stem = video.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+        let sub_stem = subtitle.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+        let ext = scanner::dotted_extension(subtitle);
+        let destination = video
+            .parent()
+            .unwrap_or(Path::new("."))
+            .join(format!("{video_stem}{}{ext}", language_suffix(sub_stem)));
+
+        if destination.exists() || !used.insert(destination.clone()) {
+            plan.unplaced
+                .push((subtitle.clone(), UnplacedReason::DestinationExists));
+            continue;
+        }
+
+        plan.actions.push(OrganizeAction {
+            source: subtitle.clone(),
+            destination,
+            strategy: strategy.to_string(),
+            media_type: record.media_type.parse().unwrap_or_default(),
+            title: record.title.clone(),
+            titles: record.titles.clone(),
+            year: record.year,
+            quality: record.quality.clone(),
+            confidence: 100.0,
+            tmdb_id: record.tmdb_id,
+            second_feature: record.second_feature.clone(),
+            companion_of: None,
+            fingerprint: None,
+            replaces: Vec::new(),
+        });
+    }
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::MediaType;
+    use std::fs;
+
+    fn record(title: &str, year: Option<i32>, media_type: &str, destination: &Path) -> FileRecord {
+        FileRecord {
+            destination: destination.to_string_lossy().to_string(),
+            title: title.to_string(),
+            year,
+            media_type: media_type.to_string(),
+            strategy: "move".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn library_file(root: &Path, relative: &str) -> PathBuf {
+        let path = root.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, b"video").unwrap();
+        path
+    }
+
+    #[test]
+    fn test_match_respects_year_and_episode() {
+        let records = vec![
+            record(
+                "Heat",
+                Some(1986),
+                "movie",
+                Path::new("/plex/Heat (1986).mkv"),
+            ),
+            record(
+                "Heat",
+                Some(1995),
+                "movie",
+                Path::new("/plex/Heat (1995).mkv"),
+            ),
+            record(
+                "Breaking Bad",
+                None,
+                "tv",
+                Path::new("/plex/Breaking Bad - S01E02.mkv"),
+            ),
+        ];
+
+        let options = ParseOptions::default();
+        let heat = match_subtitle(
+            Path::new("Heat.1995.1080p.BluRay.en.srt"),
+            &records,
+            &options,
+        );
+        assert_eq!(heat.unwrap().destination, "/plex/Heat (1995).mkv");
+
+        let episode = match_subtitle(
+            Path::new("Breaking.Bad.S01E02.720p.fr.srt"),
+            &records,
+            &options,
+        );
+        assert_eq!(episode.unwrap().media_type, "tv");
+        assert!(match_subtitle(Path::new("Breaking.Bad.S01E03.srt"), &records, &options).is_none());
+        assert!(match_subtitle(Path::new("Alien.1979.srt"), &records, &options).is_none());
+    }
+
+    #[test]
+    fn test_plan_places_subtitle_with_language_suffix() {
+        let tmp = tempfile::tempdir().unwrap();
+        let video = library_file(tmp.path(), "Movies/Heat (1995)/Heat (1995).mkv");
+        library_file(tmp.path(), "Movies/Heat (1995)/Heat (1995).fr.srt");
+        let records = vec![record("Heat", Some(1995), "movie", &video)];
+
+        let subs = vec![
+            PathBuf::from("/subs/Heat.1995.1080p.English.srt"),
+            PathBuf::from("/subs/Heat.1995.fre.srt"),
+            PathBuf::from("/subs/Ronin.1998.srt"),
+        ];
+        let plan = plan_loose_subtitles(&subs, &records, "move", &ParseOptions::default());
+
+        assert_eq!(plan.actions.len(), 1);
+        assert_eq!(
+            plan.actions[0].destination,
+            tmp.path().join("Movies/Heat (1995)/Heat (1995).en.srt")
+        );
+        assert_eq!(plan.actions[0].media_type, MediaType::Movie);
+        assert_eq!(
+            plan.unplaced,
+            vec![
+                (subs[1].clone(), UnplacedReason::DestinationExists),
+                (subs[2].clone(), UnplacedReason::NoMatch),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_loose_subtitles_skips_video_companions() {
+        let tmp = tempfile::tempdir().unwrap();
+        library_file(tmp.path(), "dl/Heat.1995.mkv");
+        library_file(tmp.path(), "dl/Heat.1995.en.srt");
+        library_file(tmp.path(), "subs/Ronin.1998.en.srt");
+
+        let found = find_loose_subtitles(tmp.path());
+        assert_eq!(found, vec![tmp.path().join("subs/Ronin.1998.en.srt")]);
+    }
+}
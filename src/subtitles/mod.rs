@@ -0,0 +1,225 @@
+//! Subtitle companion discovery and loose subtitle placement.
+//!
+//! Finds subtitle files (.srt, .ass, .sub, .vtt, etc.) adjacent to video
+//! files and extracts language/forced suffixes for Plex compatibility.
+//! Subtitles without a video are matched against the library instead
+//! (see [`plan_loose_subtitles`]).
+
+mod language;
+mod loose;
+
+pub use language::language_suffix;
+pub use loose::{
+    find_loose_subtitles, match_subtitle, plan_loose_subtitles, LooseSubtitlePlan, UnplacedReason,
+};
+
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+use tracing::debug;
+
+use crate::scanner::{dotted_extension, SUBTITLE_EXTENSIONS, VIDEO_EXTENSIONS};
+
+/// A subtitle companion found next to a video file.
+#[derive(Debug, Clone)]
+pub struct SubtitleCompanion {
+    pub path: PathBuf,
+    /// Language/forced suffix, e.g., ".en", ".zh.forced", or "" for default.
+    pub suffix: String,
+}
+
+/// Find subtitle files adjacent to a video file.
+///
+/// Matches subtitles sharing the video's stem (in any case), and normalizes
+/// what follows the stem to a Plex suffix. Also checks common subdirectories
+/// (Subs/, Subtitles/); there, files named for their language alone
+/// (`Subs/2_English.srt`) and files in a folder named after the video
+/// (`Subs/<video stem>/`) belong to the video as well, when it is the only
+/// video in its folder or the folder is its own.
+///
+/// # Examples
+///
+/// ```text
+/// video: Movie.Name.2020.mkv
+/// finds: Movie.Name.2020.srt            → suffix=""
+///        movie.name.2020.English.srt    → suffix=".en"
+///        Movie.Name.2020.zh.forced.srt  → suffix=".zh.forced"
+///        Subs/2_English.srt             → suffix=".en"
+/// ```
+pub fn find_companions(video_path: &Path) -> Vec<SubtitleCompanion> {
+    let video_stem = match video_path.file_stem().and_then(OsStr::to_str) {
+        Some(s) => s,
+        None => return Vec::new(),
+    };
+    let parent = match video_path.parent() {
+        Some(p) => p,
+        None => return Vec::new(),
+    };
+
+    let sub_ext_set: HashSet<&str> = SUBTITLE_EXTENSIONS.iter().copied().collect();
+
+    // Search in same directory + common subtitle subdirectories, and the
+    // video's own folder inside those. Deduplicate by canonical path to
+    // avoid double-counting on case-insensitive filesystems (macOS
+    // HFS+/APFS). Whether a directory takes any subtitle is kept with it.
+    let sole_video = only_video_in(parent);
+    let mut search_dirs = vec![(parent.to_path_buf(), false)];
+    let mut seen_canonical: HashSet<PathBuf> = HashSet::new();
+    if let Ok(canon) = std::fs::canonicalize(parent) {
+        seen_canonical.insert(canon);
+    }
+    for name in ["Subs", "subs", "Subtitles", "subtitles"] {
+        let sub_dir = parent.join(name);
+        for (dir, any) in [(sub_dir.join(video_stem), true), (sub_dir, sole_video)] {
+            if !dir.is_dir() {
+                continue;
+            }
+            let dominated = std::fs::canonicalize(&dir)
+                .map(|c| !seen_canonical.insert(c))
+                .unwrap_or(false);
+            if !dominated {
+                search_dirs.push((dir, any));
+            }
+        }
+    }
+
+    let mut companions = Vec::new();
+
+    for (dir, any) in &search_dirs {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(e) => e,
+            Err(_) => continue,
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if !path.is_file() {
+                continue;
+            }
+
+            let name = match path.file_name().and_then(OsStr::to_str) {
+                Some(n) => n.to_string(),
+                None => continue,
+            };
+
+            // Check extension
+            let ext = match path.extension().and_then(OsStr::to_str) {
+                Some(e) => format!(".{}", e.to_lowercase()),
+                None => continue,
+            };
+            if !sub_ext_set.contains(ext.as_str()) {
+                continue;
+            }
+            let Some(stem) = path.file_stem().and_then(OsStr::to_str) else {
+                continue;
+            };
+
+            // Starting with the video stem, the rest is the suffix; named
+            // for a language alone, the whole name is.
+            let prefixed = stem
+                .get(..video_stem.len())
+                .is_some_and(|p| p.eq_ignore_ascii_case(video_stem));
+            let suffix = if prefixed {
+                language_suffix(&format!("{video_stem}{}", &stem[video_stem.len()..]))
+            } else if *any {
+                language_suffix(&format!("subtitle.{stem}"))
+            } else {
+                continue;
+            };
+
+            debug!("subtitle companion: {} → suffix={:?}", name, suffix);
+
+            companions.push(SubtitleCompanion { path, suffix });
+        }
+    }
+
+    companions.sort_by(|a, b| a.path.cmp(&b.path));
+    companions
+}
+
+/// Whether `dir` holds exactly one video file.
+pub(crate) fn only_video_in(dir: &Path) -> bool {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return false;
+    };
+    let videos = entries
+        .flatten()
+        .filter(|e| {
+            let ext = dotted_extension(&e.path());
+            VIDEO_EXTENSIONS.contains(&ext.as_str())
+        })
+        .take(2)
+        .count();
+    videos == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_find_subtitle_companions() {
+        let tmp = tempfile::tempdir().unwrap();
+        let video = tmp.path().join("Movie.2024.mkv");
+        fs::write(&video, b"video").unwrap();
+
+        // Create subtitles
+        fs::write(tmp.path().join("Movie.2024.srt"), b"sub").unwrap();
+        fs::write(tmp.path().join("Movie.2024.en.srt"), b"sub").unwrap();
+        fs::write(tmp.path().join("Movie.2024.zh.forced.srt"), b"sub").unwrap();
+        fs::write(tmp.path().join("Other.Movie.srt"), b"sub").unwrap();
+
+        let companions = find_companions(&video);
+        assert_eq!(companions.len(), 3);
+
+        let suffixes: Vec<&str> = companions.iter().map(|c| c.suffix.as_str()).collect();
+        assert!(suffixes.contains(&""));
+        assert!(suffixes.contains(&".en"));
+        assert!(suffixes.contains(&".zh.forced"));
+    }
+
+    #[test]
+    fn test_companion_suffixes_follow_plex_naming() {
+        let tmp = tempfile::tempdir().unwrap();
+        let video = tmp.path().join("Heat.1995.1080p.mkv");
+        fs::write(&video, b"video").unwrap();
+        fs::write(tmp.path().join("heat.1995.1080p.English.srt"), b"sub").unwrap();
+        fs::write(tmp.path().join("Heat.1995.1080p.Forced.eng.ass"), b"sub").unwrap();
+        let subs_dir = tmp.path().join("Subs");
+        fs::create_dir(&subs_dir).unwrap();
+        fs::write(subs_dir.join("3_French.srt"), b"sub").unwrap();
+
+        let suffixes: Vec<_> = find_companions(&video)
+            .into_iter()
+            .map(|c| c.suffix)
+            .collect();
+        assert_eq!(suffixes, [".en.forced", ".fr", ".en"]);
+
+        // With a second video, language-only names are nobody's.
+        fs::write(tmp.path().join("Heat.1995.Extras.mkv"), b"video").unwrap();
+        assert_eq!(find_companions(&video).len(), 2);
+
+        // Except in a folder named after the video.
+        let own = subs_dir.join("Heat.1995.1080p");
+        fs::create_dir(&own).unwrap();
+        fs::write(own.join("2_English.srt"), b"sub").unwrap();
+        assert_eq!(find_companions(&video).len(), 3);
+    }
+
+    #[test]
+    fn test_find_companions_in_subs_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let video = tmp.path().join("Movie.mkv");
+        fs::write(&video, b"video").unwrap();
+
+        let subs_dir = tmp.path().join("Subs");
+        fs::create_dir(&subs_dir).unwrap();
+        fs::write(subs_dir.join("Movie.en.srt"), b"sub").unwrap();
+
+        let companions = find_companions(&video);
+        assert_eq!(companions.len(), 1);
+        assert_eq!(companions[0].suffix, ".en");
+    }
+}
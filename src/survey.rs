@@ -0,0 +1,148 @@
+//! Pre-organize survey of a source directory.
+//!
+//! A quick walk that sizes the job before `scan` or `organize`: file counts
+//! and bytes by extension, a size distribution, how deep files are nested,
+//! and which title languages the video filenames suggest. Nothing is
+//! filtered or looked up, so it stays fast on large trees.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use serde::Serialize;
+use walkdir::WalkDir;
+
+use crate::parser::{self, ParseOptions};
+use crate::scanner::{self, VIDEO_EXTENSIONS};
+
+/// Upper bounds of the size buckets, in bytes; the last bucket is open.
+pub const SIZE_BUCKETS: &[(u64, &str)] = &[
+    (10 << 20, "< 10 MB"),
+    (100 << 20, "10–100 MB"),
+    (1 << 30, "100 MB–1 GB"),
+    (4 << 30, "1–4 GB"),
+    (10 << 30, "4–10 GB"),
+    (u64::MAX, "≥ 10 GB"),
+];
+
+/// Count and total size of a group of files.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Tally {
+    pub files: u64,
+    pub bytes: u64,
+}
+
+impl Tally {
+    fn add(&mut self, bytes: u64) {
+        self.files += 1;
+        self.bytes += bytes;
+    }
+}
+
+/// What a directory holds.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Survey {
+    pub total: Tally,
+    /// Video and audio files (`organize` candidates before size filters).
+    pub media: Tally,
+    /// Keyed by lowercased extension with its dot; `""` for none.
+    pub by_extension: BTreeMap<String, Tally>,
+    /// One entry per [`SIZE_BUCKETS`] label, in order.
+    pub size_buckets: Vec<(String, u64)>,
+    /// Files by folder depth below the root (0 = directly in it).
+    pub depths: BTreeMap<usize, u64>,
+    /// Video files by detected title language (ISO 639-1), where the
+    /// filename gives a signal.
+    pub languages: BTreeMap<String, u64>,
+    /// Entries that could not be read.
+    pub unreadable: u64,
+}
+
+/// Walk `root` and tally what it holds, parsing video names with `options`.
+pub fn survey(root: &Path, options: &ParseOptions) -> Result<Survey> {
+    if !root.is_dir() {
+        bail!("Not a directory: {}", root.display());
+    }
+    let mut survey = Survey {
+        size_buckets: SIZE_BUCKETS
+            .iter()
+            .map(|(_, label)| (label.to_string(), 0))
+            .collect(),
+        ..Default::default()
+    };
+
+    for entry in WalkDir::new(root).follow_links(false) {
+        let Ok(entry) = entry else {
+            survey.unreadable += 1;
+            continue;
+        };
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else {
+            survey.unreadable += 1;
+            continue;
+        };
+        let path = entry.path();
+        let size = metadata.len();
+        let ext = scanner::dotted_extension(path);
+
+        survey.total.add(size);
+        survey
+            .by_extension
+            .entry(ext.clone())
+            .or_default()
+            .add(size);
+        let bucket = SIZE_BUCKETS.iter().position(|(max, _)| size < *max);
+        survey.size_buckets[bucket.unwrap_or(SIZE_BUCKETS.len() - 1)].1 += 1;
+        *survey
+            .depths
+            .entry(entry.depth().saturating_sub(1))
+            .or_default() += 1;
+
+        if scanner::is_media_path(path) {
+            survey.media.add(size);
+        }
+        if VIDEO_EXTENSIONS.contains(&ext.as_str()) {
+            let name = entry.file_name().to_string_lossy();
+            if let Some(lang) = parser::parse_video(&name, options).title_language {
+                *survey.languages.entry(lang.code).or_default() += 1;
+            }
+        }
+    }
+    Ok(survey)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_survey_tallies() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        fs::create_dir_all(root.join("Parasite (2019)/Subs")).unwrap();
+        fs::write(root.join("Heat.1995.1080p.mkv"), vec![0; 2048]).unwrap();
+        fs::write(root.join("Parasite (2019)/기생충.2019.1080p.mkv"), b"x").unwrap();
+        fs::write(root.join("Parasite (2019)/Subs/en.srt"), b"x").unwrap();
+        fs::write(root.join("notes"), b"x").unwrap();
+
+        let survey = survey(root, &ParseOptions::default()).unwrap();
+        assert_eq!(survey.total.files, 4);
+        assert_eq!(
+            survey.media,
+            Tally {
+                files: 2,
+                bytes: 2049
+            }
+        );
+        assert_eq!(survey.by_extension[".mkv"].files, 2);
+        assert_eq!(survey.by_extension[""].files, 1);
+        assert_eq!(survey.size_buckets[0].1, 4);
+        assert_eq!(survey.depths[&0], 2);
+        assert_eq!(survey.depths[&1], 1);
+        assert_eq!(survey.depths[&2], 1);
+        assert_eq!(survey.languages.get("ko"), Some(&1));
+    }
+}
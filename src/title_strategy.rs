@@ -0,0 +1,369 @@
+//! A second opinion on the title: the words before the year.
+//!
+//! hunch classifies every token, and when it misjudges one the title
+//! suffers: `Dune.Part.Two.2024` comes out as `Dune`, a site prefix
+//! (`www.Torrenting.com - Heat.1995`) leaves no title at all, and
+//! `Blade.Runner.2049.2017` loses its `2049`. Release names almost always
+//! put the title first, so the words in front of the release year (or the
+//! first technical token) are a strong alternative. Both titles are scored
+//! and the better one kept; [`TitleStrategy`] records which won, so the
+//! choice can be evaluated across a corpus of names.
+//!
+//! The name is cut positionally: it is split into bracket groups and
+//! words, and the title is the words before the first group, year, or
+//! technical word. Tags split over two words (`Blu.ray`, `H.264`, `WEB.DL`)
+//! count as technical, so `Constantine.Blu.ray.VC-1` is `Constantine`.
+//! `parsing.title_extraction = "tokens"` keeps the earlier cut, which
+//! judged each word alone; the parser passes the configured one in (see
+//! `parser::ParseOptions`).
+
+use std::fmt;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::config::TitleExtraction;
+use crate::scanner::{dotted_extension, AUDIO_EXTENSIONS, SUBTITLE_EXTENSIONS, VIDEO_EXTENSIONS};
+
+/// Which extraction produced a parsed title.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TitleStrategy {
+    /// hunch's own title.
+    #[default]
+    Hunch,
+    /// The words before the year or first technical token.
+    BeforeYear,
+}
+
+impl TitleStrategy {
+    pub fn is_hunch(&self) -> bool {
+        *self == Self::Hunch
+    }
+}
+
+impl fmt::Display for TitleStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Hunch => "hunch",
+            Self::BeforeYear => "before-year",
+        })
+    }
+}
+
+/// Resolution, source, codec, audio, and release-flag tokens, and episode
+/// markers: words that end a title.
+static TECHNICAL_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?ix)^(?:
+            \d{3,4}[pi] | 4k | uhd | hdr\d*\+? | dv | sdr | 10bit | 8bit
+          | [xh]\.?26[45] | hevc | avc | vc-?1 | xvid | divx | av1
+          | blu-?ray | bd(?:rip|remux)? | br(?:rip)? | remux | web(?:-?dl|-?rip)? | hdtv
+          | dvd(?:rip|scr)? | hdrip | hdcam | cam | ts | telesync | amzn | nf | dsnp | hmax
+          | dts(?:-?hd)? | ddp?\d? | aac\d? | ac3 | eac3 | truehd | atmos | flac
+          | imax | repack | proper | limited | internal | extended | unrated | remastered
+          | multi | dual | subbed | dubbed
+          | s\d{1,2}(?:e\d{1,3})* | \d{1,2}x\d{2}
+        )$",
+    )
+    .unwrap()
+});
+
+/// Leading `[Group]` / `(Site)` tags and `www.site.com - ` prefixes.
+static PREFIX_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?ix)^(?:
+            \s*[\[(][^\])]*[\])]
+          | \s*(?:www\.)?[a-z0-9-]+\.(?:com|org|net|to|mx|se|io|cc|me|info)\s*-
+        )+",
+    )
+    .unwrap()
+});
+
+pub(crate) fn is_technical(token: &str) -> bool {
+    TECHNICAL_RE.is_match(token)
+}
+
+/// A piece of a release name.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Segment<'a> {
+    /// The inside of `[…]`, `(…)`, or `{…}`.
+    Group(&'a str),
+    Word(&'a str),
+}
+
+fn push_words<'a>(text: &'a str, out: &mut Vec<Segment<'a>>) {
+    let words = text
+        .split(['.', ' ', '_'])
+        .filter(|w| !w.is_empty() && *w != "-");
+    out.extend(words.map(Segment::Word));
+}
+
+/// Split a name into bracket groups and the words between them. An
+/// unclosed bracket is read as words.
+fn segments(stem: &str) -> Vec<Segment<'_>> {
+    let mut out = Vec::new();
+    let mut rest = stem;
+    while let Some(open) = rest.find(['[', '(', '{']) {
+        push_words(&rest[..open], &mut out);
+        let close = match &rest[open..open + 1] {
+            "[" => ']',
+            "(" => ')',
+            _ => '}',
+        };
+        let inner = &rest[open + 1..];
+        match inner.find(close) {
+            Some(end) => {
+                out.push(Segment::Group(&inner[..end]));
+                rest = &inner[end + 1..];
+            }
+            None => rest = inner,
+        }
+    }
+    push_words(rest, &mut out);
+    out
+}
+
+/// Whether each word is technical alone or with a neighbour
+/// (`Blu ray` → `blu-ray`, `H 264` → `h.264`); with `Tokens`, alone only.
+fn technical_words(words: &[&str], extraction: TitleExtraction) -> Vec<bool> {
+    let mut technical: Vec<bool> = words.iter().map(|w| is_technical(w)).collect();
+    if extraction == TitleExtraction::Tokens {
+        return technical;
+    }
+    for (i, pair) in words.windows(2).enumerate() {
+        let (a, b) = (pair[0], pair[1]);
+        if is_technical(&format!("{a}-{b}")) || is_technical(&format!("{a}.{b}")) {
+            technical[i] = true;
+            technical[i + 1] = true;
+        }
+    }
+    technical
+}
+
+/// The words of a name before its `year` (never the first word, which may
+/// be a title like `1917`), its first technical word, or, positionally, a
+/// bracket group after the title. `None` if no words are left.
+pub fn before_year_title(
+    name: &str,
+    year: Option<i32>,
+    extraction: TitleExtraction,
+) -> Option<String> {
+    let ext = dotted_extension(std::path::Path::new(name));
+    let known = [VIDEO_EXTENSIONS, AUDIO_EXTENSIONS, SUBTITLE_EXTENSIONS]
+        .iter()
+        .any(|set| set.contains(&ext.as_str()));
+    let stem = if known {
+        &name[..name.len() - ext.len()]
+    } else {
+        name
+    };
+    let stem = PREFIX_RE.replace(stem, "");
+    let year = year.map(|y| y.to_string());
+    let words = match extraction {
+        TitleExtraction::Positional => {
+            let mut words = Vec::new();
+            for segment in segments(&stem) {
+                match segment {
+                    Segment::Word(word) => words.push(word),
+                    Segment::Group(_) if words.is_empty() => {}
+                    Segment::Group(_) => break,
+                }
+            }
+            words
+        }
+        TitleExtraction::Tokens => stem
+            .split(['.', ' ', '_'])
+            .map(|t| t.trim_matches(['(', ')', '[', ']']))
+            .filter(|t| !t.is_empty() && *t != "-")
+            .collect(),
+    };
+    let technical = technical_words(&words, extraction);
+    let end = (0..words.len())
+        .find(|&i| technical[i] || (i > 0 && year.as_deref() == Some(words[i])))
+        .unwrap_or(words.len());
+    (end > 0).then(|| words[..end].join(" "))
+}
+
+/// How title-like a candidate is: one point per plain word, minus two per
+/// technical word; an empty title scores lowest.
+pub fn title_score(title: &str, extraction: TitleExtraction) -> i32 {
+    let words: Vec<&str> = title.split_whitespace().collect();
+    if words.is_empty() {
+        return i32::MIN;
+    }
+    technical_words(&words, extraction)
+        .iter()
+        .map(|&technical| if technical { -2 } else { 1 })
+        .sum()
+}
+
+/// Pick between hunch's title and the before-year title of `name`.
+/// hunch keeps ties.
+pub fn choose(
+    name: &str,
+    hunch_title: String,
+    year: Option<i32>,
+    extraction: TitleExtraction,
+) -> (String, TitleStrategy) {
+    match before_year_title(name, year, extraction) {
+        Some(alternative)
+            if title_score(&alternative, extraction) > title_score(&hunch_title, extraction) =>
+        {
+            (alternative, TitleStrategy::BeforeYear)
+        }
+        _ => (hunch_title, TitleStrategy::Hunch),
+    }
+}
+
+/// The raw filename tokens in front of the release year.
+///
+/// This is the span where the title lives, before hunch has classified any
+/// of it: `The.Limited.2008.720p.mkv` → `["The", "Limited"]`. The title
+/// itself may be a year (`2012.2009.mkv`), so the first token is never
+/// taken as the year. `None` without a year to anchor on.
+pub fn title_region(raw_filename: &str, year: Option<i32>) -> Option<Vec<&str>> {
+    let year = year?.to_string();
+    let tokens: Vec<&str> = raw_filename
+        .split(['.', ' ', '_'])
+        .filter(|t| !t.is_empty())
+        .collect();
+    let end = tokens.iter().skip(1).position(|t| *t == year)? + 1;
+    Some(tokens[..end].to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse_video, ParseOptions};
+
+    const POSITIONAL: TitleExtraction = TitleExtraction::Positional;
+
+    /// Release names with the title each should parse to, and which
+    /// strategy should produce it.
+    const CORPUS: &[(&str, &str, TitleStrategy)] = &[
+        (
+            "Dune.Part.Two.2024.1080p.AMZN.WEB-DL.DDP5.1.H.264-FLUX.mkv",
+            "Dune Part Two",
+            TitleStrategy::BeforeYear,
+        ),
+        (
+            "www.Torrenting.com - Heat.1995.720p.mkv",
+            "Heat",
+            TitleStrategy::BeforeYear,
+        ),
+        (
+            "Blade.Runner.2049.2017.1080p.mkv",
+            "Blade Runner 2049",
+            TitleStrategy::BeforeYear,
+        ),
+        (
+            "The.Matrix.1999.1080p.BluRay.x264-GROUP.mkv",
+            "The Matrix",
+            TitleStrategy::Hunch,
+        ),
+        (
+            "[YTS.MX] Inception (2010) [1080p].mkv",
+            "Inception",
+            TitleStrategy::Hunch,
+        ),
+        ("1917.2019.1080p.mkv", "1917", TitleStrategy::Hunch),
+        (
+            "The.Walking.Dead.S05E03.720p.BluRay.x264-DEMAND.mkv",
+            "The Walking Dead",
+            TitleStrategy::Hunch,
+        ),
+    ];
+
+    #[test]
+    fn test_corpus() {
+        let mut wins = 0;
+        for (name, title, strategy) in CORPUS {
+            let parsed = parse_video(name, &ParseOptions::default());
+            assert_eq!(
+                (parsed.title.as_str(), parsed.title_strategy),
+                (*title, *strategy),
+                "{name}"
+            );
+            wins += usize::from(!parsed.title_strategy.is_hunch());
+        }
+        assert_eq!(wins, 3);
+    }
+
+    #[test]
+    fn test_before_year_title() {
+        assert_eq!(
+            before_year_title("Heat (1995)", Some(1995), POSITIONAL).as_deref(),
+            Some("Heat")
+        );
+        assert_eq!(
+            before_year_title("Show.Name.S01E02.mkv", None, POSITIONAL).as_deref(),
+            Some("Show Name")
+        );
+        assert_eq!(before_year_title("1080p.mkv", None, POSITIONAL), None);
+        assert!(title_score("Movie 1080p", POSITIONAL) < title_score("Movie", POSITIONAL));
+    }
+
+    #[test]
+    fn test_positional_segments() {
+        // Cut after "Blu ray VC" when each word was judged alone.
+        assert_eq!(
+            before_year_title(
+                "Constantine.Blu.ray.VC-1.DTS-HD.MA.5.1.mkv",
+                None,
+                POSITIONAL
+            )
+            .as_deref(),
+            Some("Constantine")
+        );
+        assert!(
+            title_score("Constantine Blu ray", POSITIONAL) < title_score("Constantine", POSITIONAL)
+        );
+        assert_eq!(
+            before_year_title(
+                "Constantine.Blu.ray.VC-1.DTS-HD.MA.5.1.mkv",
+                None,
+                TitleExtraction::Tokens
+            )
+            .as_deref(),
+            Some("Constantine Blu ray")
+        );
+        assert_eq!(
+            before_year_title("Heat.WEB.DL.H.264.mkv", None, POSITIONAL).as_deref(),
+            Some("Heat")
+        );
+        // A group ends the title; leading ones are tags.
+        assert_eq!(
+            before_year_title(
+                "Amelie [Le Fabuleux Destin] 2001.mkv",
+                Some(2001),
+                POSITIONAL
+            )
+            .as_deref(),
+            Some("Amelie")
+        );
+        assert_eq!(
+            before_year_title("[Group] Heat (1995) [1080p].mkv", Some(1995), POSITIONAL).as_deref(),
+            Some("Heat")
+        );
+        assert_eq!(
+            segments("A [b.c] d (e"),
+            vec![
+                Segment::Word("A"),
+                Segment::Group("b.c"),
+                Segment::Word("d"),
+                Segment::Word("e"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_title_region() {
+        let region = title_region("The.Limited.2008.720p.mkv", Some(2008));
+        assert_eq!(region, Some(vec!["The", "Limited"]));
+        let region = title_region("2012.2009.1080p.mkv", Some(2009));
+        assert_eq!(region, Some(vec!["2012"]));
+        assert_eq!(title_region("Heat.1080p.mkv", None), None);
+    }
+}
@@ -0,0 +1,307 @@
+//! Title variants: original, romanized, and English.
+//!
+//! A film goes by several names. Chinese, Japanese, and Korean releases
+//! often carry two (`寄生虫.Parasite.2019`), TMDb knows an English and an
+//! original title, and fans name some films by a romanization
+//! (`Gisaengchung.2019`). The parser splits a two-script title with
+//! [`split_title`] and romanizes a kana or Hangul one (see [`variants`]),
+//! [`resolve`] merges in what a TMDb match knows, and
+//! [`render`] fills the `organize.title_template` naming template, so a
+//! library can be named `Parasite [기생충]` or after the original title.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::language::script_of;
+use crate::models::{EnrichedMedia, ExternalIds, ParsedMedia, Script, TitleVariants};
+use crate::quality::Quality;
+use crate::transliterate;
+use crate::utils::title_similarity;
+
+/// A filename title at least this similar to the English one is that
+/// title, not a romanization.
+const SAME_TITLE: f64 = 0.8;
+
+/// Separators left at the ends once a title is split or a template part
+/// comes out empty.
+const SEPARATORS: &[char] = &['-', '–', '|', '/', ':', ',', ' '];
+
+/// Brackets a template placeholder left empty, with the tag it was the
+/// value of: `Heat []`, `Heat {tmdb-}`.
+static EMPTY_GROUP_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\(\s*\)|\[(?:[a-z]+-)?\s*\]|\{(?:[a-z]+-)?\s*\}").unwrap());
+
+/// Whether `word` is in a non-Latin script; `None` for words with no
+/// letters (years, numbers, separators).
+fn is_native(word: &str) -> Option<bool> {
+    let mut scripts = word.chars().filter_map(script_of).peekable();
+    scripts.peek()?;
+    Some(scripts.any(|s| s != Script::Latin))
+}
+
+/// Whether `title` is written in Latin letters only.
+fn is_latin(title: &str) -> bool {
+    is_native(title) == Some(false)
+}
+
+/// Split a title naming the film in a native script and in Latin letters
+/// (`寄生虫 Parasite`) into its original and English parts. Words without
+/// letters go with the part before them. Other titles give no variants.
+pub fn split_title(title: &str) -> TitleVariants {
+    // [Latin, native]
+    let mut parts: [Vec<&str>; 2] = Default::default();
+    let mut leading = Vec::new();
+    let mut last = None;
+    for word in title.split_whitespace() {
+        match (is_native(word), last) {
+            (Some(native), _) => {
+                let part = &mut parts[usize::from(native)];
+                part.append(&mut leading);
+                part.push(word);
+                last = Some(native);
+            }
+            (None, Some(native)) => parts[usize::from(native)].push(word),
+            (None, None) => leading.push(word),
+        }
+    }
+    let [latin, native] = parts.map(|words| words.join(" ").trim_matches(SEPARATORS).to_string());
+    if latin.is_empty() || native.is_empty() {
+        return TitleVariants::default();
+    }
+    TitleVariants {
+        original: Some(native),
+        romanized: None,
+        english: Some(latin),
+    }
+}
+
+/// The variants a filename title gives: its two parts when it has two
+/// scripts (see [`split_title`]), or its romanization when it is written
+/// in kana or Hangul alone (see [`transliterate`]).
+pub fn variants(title: &str) -> TitleVariants {
+    let titles = split_title(title);
+    if !titles.is_empty() {
+        return titles;
+    }
+    TitleVariants {
+        romanized: transliterate::romanize(title),
+        ..Default::default()
+    }
+}
+
+/// The title variants of `enriched`: those of the filename, with a TMDb
+/// match supplying the original title and, as TMDb answers in English,
+/// the English one. A Latin filename title unlike the English one of a
+/// film whose original title is in another script is its romanization.
+pub fn resolve(enriched: &EnrichedMedia) -> TitleVariants {
+    let parsed = &enriched.parsed;
+    let mut titles = parsed.titles.clone();
+    let Some(movie) = enriched.movie.as_ref().filter(|m| m.tmdb_id.is_some()) else {
+        return titles;
+    };
+    if let Some(original) = &movie.original_title {
+        titles.original = Some(original.clone());
+    }
+    if is_latin(&movie.title) {
+        titles.english = Some(movie.title.clone());
+    }
+    let named = match &parsed.titles.english {
+        Some(english) => Some(english.as_str()),
+        None => Some(parsed.title.as_str()).filter(|t| is_latin(t)),
+    };
+    let foreign = titles.original.as_deref().is_some_and(|o| !is_latin(o));
+    if let Some(named) = named.filter(|_| foreign) {
+        let english = titles.english.as_deref().unwrap_or_default();
+        if title_similarity(named, english) < SAME_TITLE {
+            titles.romanized = Some(named.to_string());
+        }
+    }
+    titles
+}
+
+/// Fill a naming template: `{title}` is the primary title, `{original}`,
+/// `{romanized}`, and `{english}` its variants, and `{tmdb_id}`,
+/// `{imdb_id}`, and `{tvdb_id}` the item's ids. A missing value leaves
+/// nothing, brackets around it and a tag it was the value of
+/// (`{tmdb-{tmdb_id}}`) included; a template that leaves nothing at all
+/// gives the primary title. Other braces are kept, so Plex tags such as
+/// `{edition-Director's Cut}` pass through.
+pub fn render(template: &str, primary: &str, titles: &TitleVariants, ids: &ExternalIds) -> String {
+    let (tmdb, tvdb) = (
+        ids.tmdb.map(|id| id.to_string()),
+        ids.tvdb.map(|id| id.to_string()),
+    );
+    let name = fill(
+        template,
+        &[
+            ("{title}", Some(primary)),
+            ("{original}", titles.original.as_deref()),
+            ("{romanized}", titles.romanized.as_deref()),
+            ("{english}", titles.english.as_deref()),
+            ("{tmdb_id}", tmdb.as_deref()),
+            ("{imdb_id}", ids.imdb.as_deref()),
+            ("{tvdb_id}", tvdb.as_deref()),
+        ],
+    );
+    if name.is_empty() {
+        primary.to_string()
+    } else {
+        name
+    }
+}
+
+/// Fill `organize.file_info_template`, the release details put after a
+/// movie or episode name: `{resolution}`, `{source}`, `{codec}`, and
+/// `{quality}` from the ranked quality, `{hdr}` (`DV HDR10+`), `{3d}`
+/// (`3D HSBS`), and `{atmos}`. Missing values drop out as in [`render`];
+/// "" when nothing is left.
+pub fn render_info(template: &str, parsed: &ParsedMedia) -> String {
+    let quality = Quality::parse(&parsed.quality);
+    let attributes = &parsed.attributes;
+    let values = [
+        quality.resolution.map(|r| r.to_string()),
+        quality.source.map(|s| s.to_string()),
+        quality.codec.map(|c| c.to_string()),
+        Some(quality.to_string()),
+        Some(attributes.hdr_label()),
+        attributes.stereo_3d.map(|d| d.to_string()),
+        attributes.atmos.then(|| "Atmos".to_string()),
+    ];
+    let placeholders = [
+        "{resolution}",
+        "{source}",
+        "{codec}",
+        "{quality}",
+        "{hdr}",
+        "{3d}",
+        "{atmos}",
+    ];
+    let pairs: Vec<(&str, Option<&str>)> = placeholders
+        .into_iter()
+        .zip(
+            values
+                .iter()
+                .map(|v| v.as_deref().filter(|v| !v.is_empty())),
+        )
+        .collect();
+    fill(template, &pairs)
+}
+
+/// Replace each placeholder with its value, dropping missing ones with
+/// their brackets, and trim what is left.
+fn fill(template: &str, values: &[(&str, Option<&str>)]) -> String {
+    let mut name = template.to_string();
+    for (placeholder, value) in values {
+        name = name.replace(placeholder, value.unwrap_or_default());
+    }
+    let name = EMPTY_GROUP_RE.replace_all(&name, "");
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    let name = name
+        .replace("[ ", "[")
+        .replace(" ]", "]")
+        .replace("( ", "(")
+        .replace(" )", ")");
+    name.trim_matches(SEPARATORS).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Movie;
+    use crate::quality::ReleaseAttributes;
+
+    #[test]
+    fn test_split_title_by_script() {
+        let titles = split_title("寄生虫 Parasite");
+        assert_eq!(titles.original.as_deref(), Some("寄生虫"));
+        assert_eq!(titles.english.as_deref(), Some("Parasite"));
+        let titles = split_title("Spirited Away - 千と千尋の神隠し");
+        assert_eq!(titles.original.as_deref(), Some("千と千尋の神隠し"));
+        assert_eq!(titles.english.as_deref(), Some("Spirited Away"));
+        assert!(split_title("Heat").is_empty());
+        assert!(split_title("기생충").is_empty());
+        assert_eq!(
+            variants("기생충").romanized.as_deref(),
+            Some("Gisaengchung")
+        );
+        assert!(variants("让子弹飞").is_empty());
+    }
+
+    #[test]
+    fn test_resolve_and_render_variants() {
+        let parsed = ParsedMedia {
+            title: "Gisaengchung".to_string(),
+            ..Default::default()
+        };
+        let mut enriched = EnrichedMedia::from_parsed(parsed);
+        enriched.movie = Some(Movie {
+            title: "Parasite".to_string(),
+            year: Some(2019),
+            tmdb_id: Some(496243),
+            original_title: Some("기생충".to_string()),
+            confidence: 90.0,
+        });
+        let titles = resolve(&enriched);
+        assert_eq!(titles.original.as_deref(), Some("기생충"));
+        assert_eq!(titles.romanized.as_deref(), Some("Gisaengchung"));
+        assert_eq!(titles.english.as_deref(), Some("Parasite"));
+
+        assert_eq!(
+            render(
+                "{english} [{original}]",
+                "Parasite",
+                &titles,
+                &ExternalIds::default()
+            ),
+            "Parasite [기생충]"
+        );
+        let english_only = TitleVariants {
+            english: Some("Heat".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            render(
+                "{english} [{original}]",
+                "Heat",
+                &english_only,
+                &ExternalIds::default()
+            ),
+            "Heat"
+        );
+        assert_eq!(
+            render("{original}", "Heat", &english_only, &ExternalIds::default()),
+            "Heat"
+        );
+
+        let ids = ExternalIds {
+            tmdb: Some(949),
+            ..Default::default()
+        };
+        let template = "{title} {tmdb-{tmdb_id}} [imdbid-{imdb_id}]";
+        assert_eq!(
+            render(template, "Heat", &english_only, &ids),
+            "Heat {tmdb-949}"
+        );
+    }
+
+    #[test]
+    fn test_render_info() {
+        let parsed = ParsedMedia {
+            quality: "2160p WEB-DL DV H.265".to_string(),
+            attributes: ReleaseAttributes::parse("Dune.2021.2160p.DV.Atmos.mkv"),
+            ..Default::default()
+        };
+        assert_eq!(render_info("[{resolution} {hdr}]", &parsed), "[2160p DV]");
+        assert_eq!(
+            render_info("[{3d}] [{resolution} {hdr} {atmos}]", &parsed),
+            "[2160p DV Atmos]"
+        );
+        let plain = ParsedMedia {
+            quality: "1080p BluRay".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(render_info("[{resolution} {hdr}]", &plain), "[1080p]");
+        assert_eq!(render_info("[{hdr}]", &plain), "");
+    }
+}
@@ -0,0 +1,190 @@
+//! Romanization of Japanese kana and Korean Hangul titles.
+//!
+//! A file named only in its native script (`기생충.2019.mkv`) has no Latin
+//! title to search providers that know films by their romanized original
+//! title — IMDb's dataset and OMDb (`Gisaengchung`). Kana is written in
+//! Hepburn and Hangul in the Revised Romanization, both worked out from
+//! the characters alone; Chinese characters (and Japanese kanji) need a
+//! reading dictionary, so titles containing them are left to TMDb's
+//! localized search (see `enricher`).
+
+/// Hepburn for the hiragana block from `ぁ` (U+3041) to `ゖ` (U+3096);
+/// katakana is the same block shifted by 0x60. Small kana are marked
+/// `*`, the sokuon `っ` as `-`.
+const KANA: [&str; 86] = [
+    "*a", "a", "*i", "i", "*u", "u", "*e", "e", "*o", "o", // ぁ–お
+    "ka", "ga", "ki", "gi", "ku", "gu", "ke", "ge", "ko", "go", // か–ご
+    "sa", "za", "shi", "ji", "su", "zu", "se", "ze", "so", "zo", // さ–ぞ
+    "ta", "da", "chi", "ji", "-", "tsu", "zu", "te", "de", "to", "do", // た–ど
+    "na", "ni", "nu", "ne", "no", // な–の
+    "ha", "ba", "pa", "hi", "bi", "pi", "fu", "bu", "pu", "he", "be", "pe", "ho", "bo",
+    "po", // は–ぽ
+    "ma", "mi", "mu", "me", "mo", // ま–も
+    "*ya", "ya", "*yu", "yu", "*yo", "yo", // ゃ–よ
+    "ra", "ri", "ru", "re", "ro", // ら–ろ
+    "*wa", "wa", "i", "e", "o", "n", "vu", "*ka", "*ke", // ゎ–ゖ
+];
+
+/// Revised Romanization of Hangul initial consonants, medial vowels, and
+/// final consonants, in Unicode's syllable order.
+const INITIALS: [&str; 19] = [
+    "g", "kk", "n", "d", "tt", "r", "m", "b", "pp", "s", "ss", "", "j", "jj", "ch", "k", "t", "p",
+    "h",
+];
+const MEDIALS: [&str; 21] = [
+    "a", "ae", "ya", "yae", "eo", "e", "yeo", "ye", "o", "wa", "wae", "oe", "yo", "u", "wo", "we",
+    "wi", "yu", "eu", "ui", "i",
+];
+const FINALS: [&str; 28] = [
+    "", "k", "k", "k", "n", "n", "n", "t", "l", "k", "m", "l", "l", "l", "p", "l", "m", "p", "p",
+    "t", "t", "ng", "t", "t", "k", "t", "p", "t",
+];
+/// A simple final consonant carried over to a following vowel
+/// (`한국어` → `hangugeo`), as its initial form; `None` keeps the final.
+const LINKED: [Option<&str>; 28] = [
+    None,
+    Some("g"),
+    Some("kk"),
+    None,
+    Some("n"),
+    None,
+    None,
+    Some("d"),
+    Some("r"),
+    None,
+    None,
+    None,
+    None,
+    None,
+    None,
+    None,
+    Some("m"),
+    Some("b"),
+    None,
+    Some("s"),
+    Some("ss"),
+    None,
+    Some("j"),
+    Some("ch"),
+    Some("k"),
+    Some("t"),
+    Some("p"),
+    Some(""),
+];
+
+/// Index of the silent initial `ㅇ`.
+const SILENT_INITIAL: usize = 11;
+
+/// A character's part of a romanization.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Piece {
+    Kana(&'static str),
+    /// Hangul syllable: initial, medial, final indices.
+    Hangul(usize, usize, usize),
+    /// The katakana long vowel mark `ー`.
+    Long,
+    Other(char),
+}
+
+fn piece(c: char) -> Option<Piece> {
+    let code = c as u32;
+    Some(match code {
+        0x3041..=0x3096 => Piece::Kana(KANA[(code - 0x3041) as usize]),
+        0x30A1..=0x30F6 => Piece::Kana(KANA[(code - 0x30A1) as usize]),
+        0x30FC => Piece::Long,
+        0xAC00..=0xD7A3 => {
+            let index = (code - 0xAC00) as usize;
+            Piece::Hangul(index / (21 * 28), index / 28 % 21, index % 28)
+        }
+        // Kanji and hanzi need a dictionary.
+        0x3400..=0x9FFF | 0xF900..=0xFAFF => return None,
+        _ => Piece::Other(c),
+    })
+}
+
+/// The title in Latin letters, with each word capitalized, when it is
+/// written in kana or Hangul (Latin words and digits may be mixed in).
+/// `None` for titles with Chinese characters or with nothing to romanize.
+pub fn romanize(title: &str) -> Option<String> {
+    let pieces = title.chars().map(piece).collect::<Option<Vec<_>>>()?;
+    if !pieces
+        .iter()
+        .any(|p| matches!(p, Piece::Kana(_) | Piece::Hangul(..)))
+    {
+        return None;
+    }
+    let mut out = String::new();
+    let mut geminate = false;
+    for (i, piece) in pieces.iter().enumerate() {
+        match *piece {
+            Piece::Kana("-") => geminate = true,
+            Piece::Kana(roman) => {
+                let roman = match roman.strip_prefix('*') {
+                    // ゃ/ゅ/ょ after an i-kana: き+ゃ → kya, し+ゃ → sha.
+                    Some(small) if small.starts_with('y') && out.ends_with('i') => {
+                        out.pop();
+                        if out.ends_with("sh") || out.ends_with("ch") || out.ends_with('j') {
+                            small.trim_start_matches('y')
+                        } else {
+                            small
+                        }
+                    }
+                    Some(small) => small,
+                    None => roman,
+                };
+                if std::mem::take(&mut geminate) {
+                    match roman.strip_prefix("ch") {
+                        Some(_) => out.push('t'),
+                        None => out.extend(roman.chars().next().filter(|c| !"aeiou".contains(*c))),
+                    }
+                }
+                out.push_str(roman);
+            }
+            Piece::Hangul(initial, medial, fin) => {
+                out.push_str(INITIALS[initial]);
+                out.push_str(MEDIALS[medial]);
+                let next_silent =
+                    matches!(pieces.get(i + 1), Some(Piece::Hangul(SILENT_INITIAL, _, _)));
+                match LINKED[fin].filter(|_| next_silent) {
+                    Some(linked) => out.push_str(linked),
+                    None => out.push_str(FINALS[fin]),
+                }
+            }
+            // Long vowels are written plainly, as in most release names.
+            Piece::Long => {}
+            Piece::Other(c) => out.push(if c == '・' || c == '　' { ' ' } else { c }),
+        }
+    }
+    let words: Vec<String> = out.split_whitespace().map(capitalize).collect();
+    Some(words.join(" "))
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_romanize() {
+        assert_eq!(romanize("기생충").as_deref(), Some("Gisaengchung"));
+        assert_eq!(romanize("한국어").as_deref(), Some("Hangugeo"));
+        assert_eq!(romanize("올드보이 2003").as_deref(), Some("Oldeuboi 2003"));
+        assert_eq!(
+            romanize("となりのトトロ").as_deref(),
+            Some("Tonarinototoro")
+        );
+        assert_eq!(romanize("きっと").as_deref(), Some("Kitto"));
+        assert_eq!(romanize("しゃちょう").as_deref(), Some("Shachou"));
+        assert_eq!(romanize("マッチ").as_deref(), Some("Matchi"));
+        assert_eq!(romanize("让子弹飞"), None);
+        assert_eq!(romanize("千と千尋の神隠し"), None);
+        assert_eq!(romanize("Heat"), None);
+    }
+}
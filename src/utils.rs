@@ -1,7 +1,9 @@
 //! Filesystem and string sanitization utilities.
 
 use std::path::Path;
+use std::time::Duration;
 
+use anyhow::{bail, Context, Result};
 use regex::Regex;
 use std::sync::LazyLock;
 
@@ -17,11 +19,7 @@ const MAX_PATH_COMPONENT: usize = 200;
 pub fn sanitize_name(name: &str) -> String {
     let cleaned = UNSAFE_CHARS.replace_all(name, "");
     let cleaned = MULTI_SPACE.replace_all(&cleaned, " ");
-    let mut cleaned = cleaned.trim().trim_end_matches('.').to_string();
-    if cleaned.len() > MAX_PATH_COMPONENT {
-        cleaned.truncate(MAX_PATH_COMPONENT);
-        cleaned = cleaned.trim_end().to_string();
-    }
+    let cleaned = fit_title(cleaned.trim().trim_end_matches('.'), MAX_PATH_COMPONENT);
     if cleaned.is_empty() {
         "Unknown".to_string()
     } else {
@@ -29,6 +27,30 @@ pub fn sanitize_name(name: &str) -> String {
     }
 }
 
+/// Shorten `title` to at most `max_bytes`, cutting at a word boundary when
+/// one falls in the back half and trimming dangling separators. Never
+/// splits a character; returns at least one character of a non-empty title.
+pub fn fit_title(title: &str, max_bytes: usize) -> String {
+    if title.len() <= max_bytes {
+        return title.to_string();
+    }
+    let mut cut = max_bytes;
+    while !title.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    let head = &title[..cut];
+    let at_word_end = title[cut..].starts_with([' ', '.', '-', '_']);
+    let head = match head.rfind(' ') {
+        Some(space) if !at_word_end && space >= cut / 2 => &head[..space],
+        _ => head,
+    };
+    let head = head.trim_end_matches([' ', '-', ',', '.', ':', ';', '_', '&']);
+    match head {
+        "" => title.chars().next().map(String::from).unwrap_or_default(),
+        head => head.to_string(),
+    }
+}
+
 /// Format a file size in bytes to a human-readable string.
 pub fn format_size(bytes: u64) -> String {
     const KB: u64 = 1024;
@@ -46,6 +68,78 @@ pub fn format_size(bytes: u64) -> String {
     }
 }
 
+/// Parse a short duration like `30s`, `15m`, `2h`, `7d`, or `2w`.
+///
+/// A bare number is taken as seconds. Fails on an unknown unit or a
+/// duration too long to count in seconds.
+pub fn parse_duration(input: &str) -> Result<Duration> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (num, unit) = input.split_at(split);
+    let value: u64 = num
+        .parse()
+        .with_context(|| format!("Expected a number, got {num:?}"))?;
+    let unit_secs = match unit.trim() {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        "w" => 7 * 86_400,
+        unit => bail!("Unknown unit {unit:?} (expected s, m, h, d, or w)"),
+    };
+    match value.checked_mul(unit_secs) {
+        Some(secs) => Ok(Duration::from_secs(secs)),
+        None => bail!("{input:?} is too long to count in seconds"),
+    }
+}
+
+/// Format a duration coarsely: `45s`, `3m 20s`, `2h 05m`.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..60 => format!("{secs}s"),
+        60..3600 => format!("{}m {:02}s", secs / 60, secs % 60),
+        _ => format!("{}h {:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+/// Lowercase a title and reduce it to space-separated alphanumeric words.
+pub fn normalize_title(title: &str) -> String {
+    title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Similarity of two titles in `0.0..=1.0`, ignoring case and punctuation.
+///
+/// One minus the Levenshtein distance over the normalized titles, divided by
+/// the longer length — tolerant of small typos and separator differences.
+pub fn title_similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = normalize_title(a).chars().collect();
+    let b: Vec<char> = normalize_title(b).chars().collect();
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 0.0;
+    }
+
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        curr[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != cb);
+            curr[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    1.0 - prev[b.len()] as f64 / longest as f64
+}
+
 /// Check if a path component is safe (no traversal).
 pub fn is_safe_component(component: &str) -> bool {
     !component.is_empty() && component != ".." && component != "." && !component.contains('/')
@@ -59,10 +153,80 @@ pub fn safe_path_join(base: &Path, component: &str) -> Option<std::path::PathBuf
     Some(base.join(component))
 }
 
+/// One CSV line: fields with commas, quotes, or line breaks are quoted,
+/// and quotes inside them doubled.
+pub fn csv_row<S: AsRef<str>>(fields: &[S]) -> String {
+    fields
+        .iter()
+        .map(|field| {
+            let field = field.as_ref();
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Whether `name` matches a shell-style `pattern` (`*` any run of
+/// characters, `?` one character), ignoring case.
+pub fn matches_glob(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    // Where the last `*` was and which name position it now covers up to.
+    let (mut p, mut n, mut star) = (0, 0, None);
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((sp, sn)) => {
+                    star = Some((sp, sn + 1));
+                    p = sp + 1;
+                    n = sn + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_matches_glob() {
+        assert!(matches_glob("*.nfo", "Heat.1995.NFO"));
+        assert!(matches_glob("*sample*", "heat-sample.mkv"));
+        assert!(matches_glob("Thumbs.db", "thumbs.db"));
+        assert!(matches_glob("cd?.txt", "CD1.txt"));
+        assert!(!matches_glob("*.nfo", "Heat.1995.mkv"));
+        assert!(!matches_glob("*.txt", "notes.txt.mkv"));
+    }
+
+    #[test]
+    fn test_csv_row_quotes_when_needed() {
+        assert_eq!(
+            csv_row(&[
+                "Heat",
+                "1995",
+                "Crouching Tiger, Hidden Dragon",
+                "The \"Thing\""
+            ]),
+            "Heat,1995,\"Crouching Tiger, Hidden Dragon\",\"The \"\"Thing\"\"\""
+        );
+    }
+
     #[test]
     fn test_sanitize_removes_unsafe_chars() {
         assert_eq!(sanitize_name("Movie: The Sequel?"), "Movie The Sequel");
@@ -80,6 +244,19 @@ mod tests {
         assert_eq!(sanitize_name("..."), "Unknown");
     }
 
+    #[test]
+    fn test_fit_title() {
+        assert_eq!(fit_title("Heat", 10), "Heat");
+        assert_eq!(fit_title("The Lord of the Rings", 14), "The Lord of");
+        assert_eq!(fit_title("The Lord of the Rings", 15), "The Lord of the");
+        assert_eq!(fit_title("Dr. Strangelove - or How", 16), "Dr. Strangelove");
+        assert_eq!(fit_title("Supercalifragilistic", 5), "Super");
+        // 3-byte characters are never split.
+        assert_eq!(fit_title("기생충 기생충", 8), "기생");
+        assert_eq!(fit_title("기생충", 2), "기");
+        assert!(sanitize_name(&"가".repeat(100)).len() <= MAX_PATH_COMPONENT);
+    }
+
     #[test]
     fn test_format_size() {
         assert_eq!(format_size(500), "500 B");
@@ -88,6 +265,35 @@ mod tests {
         assert_eq!(format_size(1_500_000_000), "1.4 GB");
     }
 
+    #[test]
+    fn test_parse_duration() {
+        let secs = |s: &str| parse_duration(s).ok().map(|d| d.as_secs());
+        assert_eq!(secs("90"), Some(90));
+        assert_eq!(secs("15m"), Some(900));
+        assert_eq!(secs("2h"), Some(7200));
+        assert_eq!(secs("7d"), Some(604_800));
+        assert_eq!(secs("1w"), Some(604_800));
+        assert_eq!(secs("d"), None);
+        assert_eq!(secs("3y"), None);
+        assert_eq!(secs("18446744073709551615w"), None);
+        assert_eq!(secs("99999999999999999999"), None);
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_secs(45)), "45s");
+        assert_eq!(format_duration(Duration::from_secs(200)), "3m 20s");
+        assert_eq!(format_duration(Duration::from_secs(7500)), "2h 05m");
+    }
+
+    #[test]
+    fn test_title_similarity() {
+        assert_eq!(title_similarity("The Matrix", "the.matrix"), 1.0);
+        assert!(title_similarity("Amelie", "Amélie") > 0.8);
+        assert!(title_similarity("Heat", "Alien") < 0.5);
+        assert_eq!(title_similarity("", "Heat"), 0.0);
+    }
+
     #[test]
     fn test_safe_path_join_rejects_traversal() {
         let base = Path::new("/tmp");
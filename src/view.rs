@@ -0,0 +1,421 @@
+//! Link-only "view" library.
+//!
+//! Instead of moving downloads, `plex-org view` builds a parallel,
+//! Plex-named library made entirely of hardlinks (or symlinks) to the
+//! originals, so a torrent client can keep seeding from the download
+//! folder while Plex reads the view. The view root holds a manifest of the
+//! enriched items it was built from; the expected layout is recomputed from
+//! it with the current config, which is how drift is found and how a
+//! naming change is applied without re-parsing or re-querying anything.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::config::AppConfig;
+use crate::models::EnrichedMedia;
+use crate::organizer;
+
+/// Manifest file kept in the view root.
+pub const MANIFEST_FILE: &str = ".plex-org-view.json";
+
+/// How view entries point at the originals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkKind {
+    /// Same file, second name; needs the view on the originals' filesystem.
+    #[default]
+    Hardlink,
+    /// Path reference; works across filesystems (Unix only).
+    Symlink,
+}
+
+impl FromStr for LinkKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "hardlink" => Ok(Self::Hardlink),
+            "symlink" => Ok(Self::Symlink),
+            _ => bail!("Unknown link kind {s:?} (expected hardlink or symlink)"),
+        }
+    }
+}
+
+impl fmt::Display for LinkKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Hardlink => write!(f, "hardlink"),
+            Self::Symlink => write!(f, "symlink"),
+        }
+    }
+}
+
+/// An original file and what it was identified as.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ViewItem {
+    /// Absolute path of the original.
+    pub source: PathBuf,
+    pub media: EnrichedMedia,
+}
+
+/// A link in the view: `link` (relative to the view root) → `source`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ViewLink {
+    pub source: PathBuf,
+    pub link: PathBuf,
+}
+
+/// Everything the view was built from.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ViewManifest {
+    pub link_kind: LinkKind,
+    /// RFC 3339 timestamp of the last sync.
+    pub updated_at: String,
+    pub items: Vec<ViewItem>,
+    /// Links currently in the view (videos and subtitle companions).
+    pub links: Vec<ViewLink>,
+}
+
+impl ViewManifest {
+    /// Load the manifest from a view root, if the view exists.
+    pub fn load(root: &Path) -> Result<Option<Self>> {
+        let path = root.join(MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let manifest = serde_json::from_str(&text)
+            .with_context(|| format!("Invalid view manifest: {}", path.display()))?;
+        Ok(Some(manifest))
+    }
+
+    /// Write the manifest into the view root.
+    pub fn save(&self, root: &Path) -> Result<()> {
+        fs::create_dir_all(root)?;
+        let path = root.join(MANIFEST_FILE);
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&tmp, &path).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Add or replace items by source path. Returns how many were new.
+    pub fn add_items(&mut self, items: Vec<(PathBuf, EnrichedMedia)>) -> usize {
+        let mut added = 0;
+        for (source, media) in items {
+            let source = std::path::absolute(&source).unwrap_or(source);
+            match self.items.iter_mut().find(|i| i.source == source) {
+                Some(item) => item.media = media,
+                None => {
+                    self.items.push(ViewItem { source, media });
+                    added += 1;
+                }
+            }
+        }
+        added
+    }
+
+    /// The links the view should have under the current config.
+    pub fn expected_links(&self, root: &Path, config: &AppConfig) -> Vec<ViewLink> {
+        let items: Vec<_> = self
+            .items
+            .iter()
+            .map(|i| (i.source.clone(), i.media.clone()))
+            .collect();
+        organizer::plan_layout(&items, root, config, &self.link_kind.to_string())
+            .into_iter()
+            .map(|a| ViewLink {
+                link: a
+                    .destination
+                    .strip_prefix(root)
+                    .map(Path::to_path_buf)
+                    .unwrap_or(a.destination),
+                source: a.source,
+            })
+            .collect()
+    }
+}
+
+/// Why part of the view no longer matches its originals or the config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewDriftReason {
+    /// The original is gone; the link would dangle or keep a stale copy.
+    SourceMissing,
+    /// The link was deleted from the view.
+    LinkMissing,
+    /// The link exists but no longer points at the original.
+    LinkStale,
+    /// The naming config changed; the link is not where it belongs.
+    Outdated,
+    /// The view lacks a link it should have (new config path or companion).
+    NotLinked,
+}
+
+impl fmt::Display for ViewDriftReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SourceMissing => write!(f, "original missing"),
+            Self::LinkMissing => write!(f, "link missing"),
+            Self::LinkStale => write!(f, "link no longer points at the original"),
+            Self::Outdated => write!(f, "naming changed; link is outdated"),
+            Self::NotLinked => write!(f, "not linked yet"),
+        }
+    }
+}
+
+/// One drifted link.
+#[derive(Debug, Clone)]
+pub struct ViewDrift {
+    pub link: ViewLink,
+    pub reason: ViewDriftReason,
+}
+
+/// Compare the view on disk with its manifest and the current config.
+pub fn verify(root: &Path, manifest: &ViewManifest, config: &AppConfig) -> Vec<ViewDrift> {
+    let expected = manifest.expected_links(root, config);
+    let mut drift = Vec::new();
+    for link in &manifest.links {
+        let reason = if !link.source.is_file() {
+            Some(ViewDriftReason::SourceMissing)
+        } else if fs::symlink_metadata(root.join(&link.link)).is_err() {
+            Some(ViewDriftReason::LinkMissing)
+        } else if !points_at(&root.join(&link.link), &link.source, manifest.link_kind) {
+            Some(ViewDriftReason::LinkStale)
+        } else if !expected.contains(link) {
+            Some(ViewDriftReason::Outdated)
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            drift.push(ViewDrift {
+                link: link.clone(),
+                reason,
+            });
+        }
+    }
+    for link in expected {
+        if link.source.is_file() && !manifest.links.contains(&link) {
+            drift.push(ViewDrift {
+                link,
+                reason: ViewDriftReason::NotLinked,
+            });
+        }
+    }
+    drift
+}
+
+/// Link changes that bring the view in line with its manifest and config.
+#[derive(Debug, Default)]
+pub struct SyncPlan {
+    pub create: Vec<ViewLink>,
+    pub remove: Vec<ViewLink>,
+    pub keep: Vec<ViewLink>,
+}
+
+impl SyncPlan {
+    pub fn is_empty(&self) -> bool {
+        self.create.is_empty() && self.remove.is_empty()
+    }
+}
+
+/// Plan a sync: drop links to missing originals, move outdated ones, and
+/// recreate missing or stale ones.
+pub fn plan_sync(root: &Path, manifest: &ViewManifest, config: &AppConfig) -> SyncPlan {
+    let expected: Vec<ViewLink> = manifest
+        .expected_links(root, config)
+        .into_iter()
+        .filter(|l| l.source.is_file())
+        .collect();
+    let healthy = |l: &ViewLink| points_at(&root.join(&l.link), &l.source, manifest.link_kind);
+
+    let mut plan = SyncPlan::default();
+    for link in &manifest.links {
+        if !expected.contains(link) || !healthy(link) {
+            plan.remove.push(link.clone());
+        }
+    }
+    for link in expected {
+        if manifest.links.contains(&link) && healthy(&link) {
+            plan.keep.push(link);
+        } else {
+            plan.create.push(link);
+        }
+    }
+    plan
+}
+
+/// Outcome of [`apply_sync`].
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub created: usize,
+    pub removed: usize,
+    pub failed: Vec<(ViewLink, String)>,
+}
+
+/// Apply a sync plan and update the manifest (the caller saves it).
+///
+/// Items whose original is gone are dropped from the manifest.
+pub fn apply_sync(root: &Path, manifest: &mut ViewManifest, plan: SyncPlan) -> SyncReport {
+    let mut report = SyncReport::default();
+    for link in &plan.remove {
+        let path = root.join(&link.link);
+        match fs::symlink_metadata(&path) {
+            Ok(m) if !m.is_dir() => match fs::remove_file(&path) {
+                Ok(()) => report.removed += 1,
+                Err(e) => report.failed.push((link.clone(), e.to_string())),
+            },
+            _ => {}
+        }
+    }
+
+    let mut links = plan.keep;
+    for link in plan.create {
+        match create_link(root, &link, manifest.link_kind) {
+            Ok(()) => {
+                report.created += 1;
+                links.push(link);
+            }
+            Err(e) => report.failed.push((link, format!("{e:#}"))),
+        }
+    }
+
+    crate::staging::prune_empty_dirs(root);
+    manifest.items.retain(|i| i.source.is_file());
+    manifest.links = links;
+    manifest.updated_at = Utc::now().to_rfc3339();
+    report
+}
+
+fn create_link(root: &Path, link: &ViewLink, kind: LinkKind) -> Result<()> {
+    let path = root.join(&link.link);
+    if fs::symlink_metadata(&path).is_ok() {
+        bail!(
+            "{} is occupied by a file the view does not own",
+            path.display()
+        );
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    match kind {
+        LinkKind::Hardlink => fs::hard_link(&link.source, &path).with_context(|| {
+            format!(
+                "Failed to hardlink {} (is the view on the same filesystem?)",
+                link.source.display()
+            )
+        }),
+        #[cfg(unix)]
+        LinkKind::Symlink => std::os::unix::fs::symlink(fs::canonicalize(&link.source)?, &path)
+            .with_context(|| format!("Failed to symlink {}", link.source.display())),
+        #[cfg(not(unix))]
+        LinkKind::Symlink => bail!("Symlink views are only supported on Unix"),
+    }
+}
+
+/// Does the file at `path` still refer to `source`?
+fn points_at(path: &Path, source: &Path, kind: LinkKind) -> bool {
+    match kind {
+        LinkKind::Symlink => match (fs::read_link(path), fs::canonicalize(source)) {
+            (Ok(target), Ok(source)) => target == source,
+            _ => false,
+        },
+        LinkKind::Hardlink => match (fs::symlink_metadata(path), fs::metadata(source)) {
+            (Ok(a), Ok(b)) => same_file(&a, &b),
+            _ => false,
+        },
+    }
+}
+
+#[cfg(unix)]
+fn same_file(a: &fs::Metadata, b: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+
+/// Without inode numbers, a hardlink that still matches in size and
+/// modification time is taken to be the same file.
+#[cfg(not(unix))]
+fn same_file(a: &fs::Metadata, b: &fs::Metadata) -> bool {
+    a.is_file() && a.len() == b.len() && a.modified().ok() == b.modified().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enricher::Enricher;
+    use crate::models::{MediaType, ParsedMedia};
+
+    fn movie(title: &str, year: i32) -> EnrichedMedia {
+        Enricher::new(AppConfig::default()).enrich(ParsedMedia {
+            title: title.to_string(),
+            year: Some(year),
+            media_type: MediaType::Movie,
+            confidence: 80.0,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_sync_builds_and_regenerates_view() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = tmp.path().join("dl/Heat.1995.1080p.mkv");
+        fs::create_dir_all(source.parent().unwrap()).unwrap();
+        fs::write(&source, b"video").unwrap();
+        let root = tmp.path().join("view");
+        let mut config = AppConfig::default();
+
+        let mut manifest = ViewManifest::default();
+        manifest.add_items(vec![(source.clone(), movie("Heat", 1995))]);
+        let plan = plan_sync(&root, &manifest, &config);
+        let report = apply_sync(&root, &mut manifest, plan);
+        assert_eq!((report.created, report.failed.len()), (1, 0));
+        let old = root.join("Movies/Heat (1995)/Heat (1995).mkv");
+        assert_eq!(fs::read(&old).unwrap(), b"video");
+        assert!(verify(&root, &manifest, &config).is_empty());
+
+        // A naming change shows up as drift and is applied by a re-sync.
+        config.organize.movies_dir = "Films".to_string();
+        let drift = verify(&root, &manifest, &config);
+        let reasons: Vec<_> = drift.iter().map(|d| d.reason).collect();
+        assert_eq!(
+            reasons,
+            [ViewDriftReason::Outdated, ViewDriftReason::NotLinked]
+        );
+        let plan = plan_sync(&root, &manifest, &config);
+        let report = apply_sync(&root, &mut manifest, plan);
+        assert_eq!((report.created, report.removed), (1, 1));
+        assert!(!old.exists());
+        assert!(root.join("Films/Heat (1995)/Heat (1995).mkv").is_file());
+        assert!(source.is_file());
+    }
+
+    #[test]
+    fn test_verify_reports_missing_original() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = tmp.path().join("Heat.1995.mkv");
+        fs::write(&source, b"video").unwrap();
+        let root = tmp.path().join("view");
+        let config = AppConfig::default();
+
+        let mut manifest = ViewManifest::default();
+        manifest.add_items(vec![(source.clone(), movie("Heat", 1995))]);
+        let plan = plan_sync(&root, &manifest, &config);
+        apply_sync(&root, &mut manifest, plan);
+        fs::remove_file(&source).unwrap();
+
+        let drift = verify(&root, &manifest, &config);
+        assert_eq!(drift.len(), 1);
+        assert_eq!(drift[0].reason, ViewDriftReason::SourceMissing);
+
+        // Syncing drops the item and its link.
+        let plan = plan_sync(&root, &manifest, &config);
+        apply_sync(&root, &mut manifest, plan);
+        assert!(manifest.items.is_empty() && manifest.links.is_empty());
+    }
+}
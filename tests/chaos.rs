@@ -0,0 +1,49 @@
+//! Runs under chaos mode. Installing chaos lasts for the rest of the
+//! process, so this file is a test binary of its own.
+
+use std::fs;
+
+use plex_media_organizer::chaos::{self, ChaosSpec};
+use plex_media_organizer::models::OrganizeAction;
+use plex_media_organizer::organizer::{execute_actions, list_operations, undo_last};
+
+#[test]
+fn test_manifest_survives_injected_rename_failures() {
+    let tmp = tempfile::tempdir().unwrap();
+    let undo_dir = tmp.path().join("undo");
+    let action = |name: &str, dest: &str| {
+        let source = tmp.path().join(name);
+        fs::write(&source, name).unwrap();
+        OrganizeAction {
+            source,
+            destination: tmp.path().join(dest),
+            strategy: "move".to_string(),
+            ..Default::default()
+        }
+    };
+    let heat = action("Heat.1995.mkv", "lib/Heat (1995)/Heat (1995).mkv");
+    execute_actions(std::slice::from_ref(&heat), &undo_dir, &Default::default()).unwrap();
+
+    chaos::install("rename=1".parse::<ChaosSpec>().unwrap());
+
+    // A run whose every move fails places nothing and leaves the earlier
+    // manifest alone.
+    let alien = action("Alien.1979.mkv", "lib/Alien (1979)/Alien (1979).mkv");
+    let error =
+        execute_actions(std::slice::from_ref(&alien), &undo_dir, &Default::default()).unwrap_err();
+    assert!(format!("{error:#}").contains("chaos"), "{error:#}");
+    assert!(alien.source.exists());
+    assert!(!alien.destination.exists());
+
+    // An undo whose every move fails keeps the manifest for a retry.
+    let report = undo_last(&undo_dir, &mut |_| Ok(())).unwrap();
+    assert!(report.reversed.is_empty());
+    assert_eq!(report.failed.len(), 1);
+    assert!(heat.destination.exists());
+    assert!(!heat.source.exists());
+
+    let operations = list_operations(&undo_dir).unwrap();
+    assert_eq!(operations.len(), 1);
+    assert_eq!(operations[0].files, 1);
+    assert_eq!(operations[0].manifest_path, report.manifest_path);
+}